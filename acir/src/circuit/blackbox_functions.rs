@@ -23,8 +23,8 @@ pub enum BlackBoxFunc {
     FixedBaseScalarMul,
 }
 
-impl std::fmt::Display for BlackBoxFunc {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for BlackBoxFunc {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.name())
     }
 }
@@ -1,16 +1,22 @@
-use std::io::{Read, Write};
+use alloc::{collections::BTreeSet, string::String, vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::Read;
 
-use crate::{
-    native_types::{Expression, Witness},
-    serialisation::{read_n, read_u16, read_u32, write_bytes, write_u16, write_u32},
+use crate::native_types::{Expression, Witness};
+use crate::serialisation::{
+    io::{self, Write},
+    write_bytes, write_u16, write_u32,
 };
+#[cfg(feature = "std")]
+use crate::serialisation::{read_n, read_u16, read_u32};
+use acir_field::{AcirField, FieldElement};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 /// Directives do not apply any constraints.
 /// You can think of them as opcodes that allow one to use non-determinism
 /// In the future, this can be replaced with asm non-determinism blocks
-pub enum Directive {
+pub enum Directive<F = FieldElement> {
     //Inverts the value of x and stores it in the result variable
     Invert {
         x: Witness,
@@ -19,16 +25,17 @@ pub enum Directive {
 
     //Performs euclidian division of a / b (as integers) and stores the quotient in q and the rest in r
     Quotient {
-        a: Expression,
-        b: Expression,
+        a: Expression<F>,
+        b: Expression<F>,
         q: Witness,
         r: Witness,
-        predicate: Option<Expression>,
+        predicate: Option<Expression<F>>,
     },
 
     //Reduces the value of a modulo 2^bit_size and stores the result in b: a= c*2^bit_size + b
+    //If bit_size is not less than the number of bits of the field, b is a and c is zero
     Truncate {
-        a: Expression,
+        a: Expression<F>,
         b: Witness,
         c: Witness,
         bit_size: u32,
@@ -43,14 +50,24 @@ pub enum Directive {
     },
 
     //decomposition of a: a=\sum b[i]*radix^i where b is an array of witnesses < radix
+    //Only the limbs which fit in the field can be non-zero, e.g. 8 bytes in a 64-bit field, and
+    //any further limbs are zero
     ToRadix {
-        a: Expression,
+        a: Expression<F>,
         b: Vec<Witness>,
         radix: u32,
     },
+
+    //Asks the solver for the values of `outputs`, from outside of the circuit. The oracle is
+    //identified by `name`, and is given the values of `inputs`
+    Oracle {
+        name: String,
+        inputs: Vec<Expression<F>>,
+        outputs: Vec<Witness>,
+    },
 }
 
-impl Directive {
+impl<F: AcirField> Directive<F> {
     pub fn name(&self) -> &str {
         match self {
             Directive::Invert { .. } => "invert",
@@ -58,8 +75,44 @@ impl Directive {
             Directive::Truncate { .. } => "truncate",
             Directive::OddRange { .. } => "odd_range",
             Directive::ToRadix { .. } => "to_radix",
+            Directive::Oracle { .. } => "oracle",
+        }
+    }
+
+    /// Returns the witnesses which are assigned by the directive
+    pub fn outputs(&self) -> Vec<Witness> {
+        match self {
+            Directive::Invert { result, .. } => vec![*result],
+            Directive::Quotient { q, r, .. } => vec![*q, *r],
+            Directive::Truncate { b, c, .. } => vec![*b, *c],
+            Directive::OddRange { b, r, .. } => vec![*b, *r],
+            Directive::ToRadix { b, .. } => b.clone(),
+            Directive::Oracle { outputs, .. } => outputs.clone(),
+        }
+    }
+
+    /// Returns the witnesses which the directive reads from
+    pub fn inputs(&self) -> BTreeSet<Witness> {
+        match self {
+            Directive::Invert { x, .. } => BTreeSet::from([*x]),
+            Directive::Quotient {
+                a, b, predicate, ..
+            } => {
+                let mut inputs = a.witnesses();
+                inputs.extend(b.witnesses());
+                if let Some(predicate) = predicate {
+                    inputs.extend(predicate.witnesses());
+                }
+                inputs
+            }
+            Directive::Truncate { a, .. } | Directive::ToRadix { a, .. } => a.witnesses(),
+            Directive::OddRange { a, .. } => BTreeSet::from([*a]),
+            Directive::Oracle { inputs, .. } => {
+                inputs.iter().flat_map(|input| input.witnesses()).collect()
+            }
         }
     }
+
     fn to_u16(&self) -> u16 {
         match self {
             Directive::Invert { .. } => 0,
@@ -67,10 +120,11 @@ impl Directive {
             Directive::Truncate { .. } => 2,
             Directive::OddRange { .. } => 3,
             Directive::ToRadix { .. } => 4,
+            Directive::Oracle { .. } => 5,
         }
     }
 
-    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
         write_u16(&mut writer, self.to_u16())?;
         match self {
             Directive::Invert { x, result } => {
@@ -116,11 +170,28 @@ impl Directive {
                 }
                 write_u32(&mut writer, *radix)?;
             }
+            Directive::Oracle {
+                name,
+                inputs,
+                outputs,
+            } => {
+                write_u32(&mut writer, name.len() as u32)?;
+                write_bytes(&mut writer, name.as_bytes())?;
+                write_u32(&mut writer, inputs.len() as u32)?;
+                for input in inputs {
+                    input.write(&mut writer)?;
+                }
+                write_u32(&mut writer, outputs.len() as u32)?;
+                for output in outputs {
+                    write_u32(&mut writer, output.witness_index())?;
+                }
+            }
         };
 
         Ok(())
     }
 
+    #[cfg(feature = "std")]
     pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
         let directive_index = read_u16(&mut reader)?;
 
@@ -168,7 +239,7 @@ impl Directive {
             4 => {
                 let a = Expression::read(&mut reader)?;
                 let b_len = read_u32(&mut reader)?;
-                let mut b = Vec::with_capacity(b_len as usize);
+                let mut b = Vec::new();
                 for _ in 0..b_len {
                     let witness = Witness(read_u32(&mut reader)?);
                     b.push(witness)
@@ -178,6 +249,33 @@ impl Directive {
 
                 Ok(Directive::ToRadix { a, b, radix })
             }
+            5 => {
+                let name_len = read_u32(&mut reader)?;
+                let mut name = Vec::new();
+                (&mut reader).take(name_len.into()).read_to_end(&mut name)?;
+                if name.len() != name_len as usize {
+                    return Err(std::io::ErrorKind::UnexpectedEof.into());
+                }
+                let name = String::from_utf8(name).map_err(|_| std::io::ErrorKind::InvalidData)?;
+
+                let inputs_len = read_u32(&mut reader)?;
+                let mut inputs = Vec::new();
+                for _ in 0..inputs_len {
+                    inputs.push(Expression::read(&mut reader)?);
+                }
+
+                let outputs_len = read_u32(&mut reader)?;
+                let mut outputs = Vec::new();
+                for _ in 0..outputs_len {
+                    outputs.push(Witness(read_u32(&mut reader)?));
+                }
+
+                Ok(Directive::Oracle {
+                    name,
+                    inputs,
+                    outputs,
+                })
+            }
 
             _ => Err(std::io::ErrorKind::InvalidData.into()),
         }
@@ -234,6 +332,12 @@ fn serialisation_roundtrip() {
         radix: 4,
     };
 
+    let oracle = Directive::Oracle {
+        name: "get_price".to_owned(),
+        inputs: vec![Expression::default(), Expression::from(&Witness(1u32))],
+        outputs: vec![Witness(2u32), Witness(3u32)],
+    };
+
     let directives = vec![
         invert,
         quotient_none,
@@ -241,6 +345,7 @@ fn serialisation_roundtrip() {
         truncate,
         odd_range,
         to_radix,
+        oracle,
     ];
 
     for directive in directives {
@@ -1,53 +1,97 @@
 pub mod blackbox_functions;
 pub mod directives;
 pub mod opcodes;
+mod program;
+mod text;
 pub use opcodes::Opcode;
+pub use program::Program;
+pub use text::ParseError;
 
-use crate::native_types::Witness;
-use crate::serialisation::{read_u32, write_u32};
-use rmp_serde;
+use crate::native_types::{MissingWitness, Witness, WitnessMap};
+#[cfg(feature = "std")]
+use crate::serialisation::read_u32;
+use crate::serialisation::{
+    io::{self, Write},
+    write_u32,
+};
+use acir_field::{AcirField, FieldElement};
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "std")]
 use flate2::bufread::{DeflateDecoder, DeflateEncoder};
+#[cfg(feature = "std")]
 use flate2::Compression;
-use std::io::prelude::*;
+#[cfg(feature = "std")]
+use std::io::Read;
 
-const VERSION_NUMBER: u32 = 0;
+/// The version of the format written by `Circuit::write`
+pub const VERSION_NUMBER: u32 = 1;
 
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
-pub struct Circuit {
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Circuit<F = FieldElement> {
     pub current_witness_index: u32,
-    pub opcodes: Vec<Opcode>,
+    pub opcodes: Vec<Opcode<F>>,
     pub public_inputs: PublicInputs,
+    /// The witnesses which hold the outputs of the program, in order. Unlike public
+    /// inputs, they are not given to the verifier. The compiler keeps them, so they can be
+    /// read from the solved witness with `return_values_from_witness`.
+    #[serde(default)]
+    pub return_values: PublicInputs,
 }
 
-impl Circuit {
+impl<F> Default for Circuit<F> {
+    fn default() -> Self {
+        Circuit {
+            current_witness_index: 0,
+            opcodes: Vec::new(),
+            public_inputs: PublicInputs::default(),
+            return_values: PublicInputs::default(),
+        }
+    }
+}
+
+impl<F: AcirField> Circuit<F> {
     pub fn num_vars(&self) -> u32 {
         self.current_witness_index + 1
     }
 
-    #[deprecated(
-        note = "we want to use a serialisation strategy that is easy to implement in many languages (without ffi). use `read` instead"
-    )]
-    pub fn from_bytes(bytes: &[u8]) -> Circuit {
-        let mut deflater = DeflateDecoder::new(bytes);
-        let mut buf_d = Vec::new();
-        deflater.read_to_end(&mut buf_d).unwrap();
-        rmp_serde::from_slice(buf_d.as_slice()).unwrap()
+    /// Returns the values of the public inputs in a solved witness map, in the order they
+    /// are declared, which is the order backends expect them in when verifying
+    pub fn public_inputs_from_witness(
+        &self,
+        witness_map: &WitnessMap<F>,
+    ) -> Result<Vec<F>, MissingWitness> {
+        self.public_inputs
+            .0
+            .iter()
+            .map(|witness| {
+                witness_map
+                    .get(witness)
+                    .copied()
+                    .ok_or(MissingWitness(*witness))
+            })
+            .collect()
     }
 
-    #[deprecated(
-        note = "we want to use a serialisation strategy that is easy to implement in many languages (without ffi).use `write` instead"
-    )]
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let buf = rmp_serde::to_vec(&self).unwrap();
-        let mut deflater = DeflateEncoder::new(buf.as_slice(), Compression::best());
-        let mut buf_c = Vec::new();
-        deflater.read_to_end(&mut buf_c).unwrap();
-        buf_c
+    /// Returns the values of the return values in a solved witness map, in order
+    pub fn return_values_from_witness(
+        &self,
+        witness_map: &WitnessMap<F>,
+    ) -> Result<Vec<F>, MissingWitness> {
+        self.return_values
+            .0
+            .iter()
+            .map(|witness| {
+                witness_map
+                    .get(witness)
+                    .copied()
+                    .ok_or(MissingWitness(*witness))
+            })
+            .collect()
     }
 
-    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
         write_u32(&mut writer, VERSION_NUMBER)?;
 
         write_u32(&mut writer, self.current_witness_index)?;
@@ -58,12 +102,19 @@ impl Circuit {
             write_u32(&mut writer, public_input_index)?;
         }
 
+        let return_value_indices = self.return_values.indices();
+        write_u32(&mut writer, return_value_indices.len() as u32)?;
+        for return_value_index in return_value_indices {
+            write_u32(&mut writer, return_value_index)?;
+        }
+
         write_u32(&mut writer, self.opcodes.len() as u32)?;
         for opcode in &self.opcodes {
             opcode.write(&mut writer)?;
         }
         Ok(())
     }
+    #[cfg(feature = "std")]
     pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
         let version_number = read_u32(&mut reader)?;
         // TODO (Note): we could use semver versioning from the Cargo.toml
@@ -77,15 +128,23 @@ impl Circuit {
 
         let current_witness_index = read_u32(&mut reader)?;
 
+        // The lengths of lists are read from the input, which may be corrupt or malicious, so
+        // lists grow as their elements are read rather than being preallocated
         let num_public_inputs = read_u32(&mut reader)?;
-        let mut public_inputs = PublicInputs(Vec::with_capacity(num_public_inputs as usize));
+        let mut public_inputs = PublicInputs(Vec::new());
         for _ in 0..num_public_inputs {
             let public_input_index = Witness(read_u32(&mut reader)?);
             public_inputs.0.push(public_input_index)
         }
 
+        let num_return_values = read_u32(&mut reader)?;
+        let mut return_values = PublicInputs(Vec::new());
+        for _ in 0..num_return_values {
+            return_values.0.push(Witness(read_u32(&mut reader)?));
+        }
+
         let num_opcodes = read_u32(&mut reader)?;
-        let mut opcodes = Vec::with_capacity(num_opcodes as usize);
+        let mut opcodes = Vec::new();
         for _ in 0..num_opcodes {
             let opcode = Opcode::read(&mut reader)?;
             opcodes.push(opcode)
@@ -95,12 +154,38 @@ impl Circuit {
             current_witness_index,
             opcodes,
             public_inputs,
+            return_values,
         })
     }
 }
 
-impl std::fmt::Display for Circuit {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Circuit {
+    #[cfg(feature = "std")]
+    #[deprecated(
+        note = "we want to use a serialisation strategy that is easy to implement in many languages (without ffi). use `read` instead"
+    )]
+    pub fn from_bytes(bytes: &[u8]) -> Circuit {
+        let mut deflater = DeflateDecoder::new(bytes);
+        let mut buf_d = Vec::new();
+        deflater.read_to_end(&mut buf_d).unwrap();
+        rmp_serde::from_slice(buf_d.as_slice()).unwrap()
+    }
+
+    #[cfg(feature = "std")]
+    #[deprecated(
+        note = "we want to use a serialisation strategy that is easy to implement in many languages (without ffi).use `write` instead"
+    )]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let buf = rmp_serde::to_vec(&self).unwrap();
+        let mut deflater = DeflateEncoder::new(buf.as_slice(), Compression::best());
+        let mut buf_c = Vec::new();
+        deflater.read_to_end(&mut buf_c).unwrap();
+        buf_c
+    }
+}
+
+impl<F: AcirField> core::fmt::Display for Circuit<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         writeln!(f, "current witness index : {}", self.current_witness_index)?;
         write!(f, "public input indices : [")?;
         let indices = self.public_inputs.indices();
@@ -111,6 +196,13 @@ impl std::fmt::Display for Circuit {
             }
         }
         writeln!(f, "]")?;
+        if !self.return_values.0.is_empty() {
+            writeln!(
+                f,
+                "return value indices : {:?}",
+                self.return_values.indices()
+            )?;
+        }
         for opcode in &self.opcodes {
             writeln!(f, "{opcode}")?
         }
@@ -118,9 +210,9 @@ impl std::fmt::Display for Circuit {
     }
 }
 
-impl std::fmt::Debug for Circuit {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Display::fmt(self, f)
+impl<F: AcirField> core::fmt::Debug for Circuit<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(self, f)
     }
 }
 
@@ -147,7 +239,7 @@ mod test {
         opcodes::{BlackBoxFuncCall, FunctionInput},
         Circuit, Opcode, PublicInputs,
     };
-    use crate::native_types::Witness;
+    use crate::native_types::{MissingWitness, Witness, WitnessMap};
     use acir_field::FieldElement;
 
     fn and_opcode() -> Opcode {
@@ -177,12 +269,21 @@ mod test {
         })
     }
 
+    fn call_opcode() -> Opcode {
+        Opcode::Call {
+            id: 1,
+            inputs: vec![Witness(1), Witness(2)],
+            outputs: vec![Witness(3)],
+        }
+    }
+
     #[test]
     fn serialisation_roundtrip() {
         let circuit = Circuit {
             current_witness_index: 5,
-            opcodes: vec![and_opcode(), range_opcode()],
+            opcodes: vec![and_opcode(), range_opcode(), call_opcode()],
             public_inputs: PublicInputs(vec![Witness(2), Witness(12)]),
+            return_values: PublicInputs(vec![Witness(5), Witness(2)]),
         };
 
         fn read_write(circuit: Circuit) -> (Circuit, Circuit) {
@@ -196,6 +297,44 @@ mod test {
         assert_eq!(circ, got_circ)
     }
 
+    #[test]
+    fn public_inputs_and_return_values_are_read_in_order() {
+        let circuit = Circuit {
+            current_witness_index: 3,
+            opcodes: Vec::new(),
+            public_inputs: PublicInputs(vec![Witness(3), Witness(1)]),
+            return_values: PublicInputs::default(),
+        };
+        let witness_map = WitnessMap::from([
+            (Witness(1), FieldElement::from(1_i128)),
+            (Witness(2), FieldElement::from(2_i128)),
+            (Witness(3), FieldElement::from(3_i128)),
+        ]);
+        assert_eq!(
+            circuit.public_inputs_from_witness(&witness_map),
+            Ok(vec![FieldElement::from(3_i128), FieldElement::from(1_i128)])
+        );
+
+        let partial = WitnessMap::from([(Witness(1), FieldElement::from(1_i128))]);
+        assert_eq!(
+            circuit.public_inputs_from_witness(&partial),
+            Err(MissingWitness(Witness(3)))
+        );
+
+        let circuit = Circuit {
+            return_values: PublicInputs(vec![Witness(2)]),
+            ..circuit
+        };
+        assert_eq!(
+            circuit.return_values_from_witness(&witness_map),
+            Ok(vec![FieldElement::from(2_i128)])
+        );
+        assert_eq!(
+            circuit.return_values_from_witness(&partial),
+            Err(MissingWitness(Witness(2)))
+        );
+    }
+
     #[test]
     fn test_serialize() {
         let circuit = Circuit {
@@ -210,6 +349,7 @@ mod test {
                 and_opcode(),
             ],
             public_inputs: PublicInputs(vec![Witness(2)]),
+            return_values: PublicInputs::default(),
         };
 
         let json = serde_json::to_string_pretty(&circuit).unwrap();
@@ -219,6 +359,7 @@ mod test {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_to_byte() {
         let circuit = Circuit {
             current_witness_index: 0,
@@ -232,6 +373,7 @@ mod test {
                 and_opcode(),
             ],
             public_inputs: PublicInputs(vec![Witness(2)]),
+            return_values: PublicInputs::default(),
         };
 
         let bytes = circuit.to_bytes();
@@ -1,19 +1,36 @@
-use std::io::{Read, Write};
+use alloc::{format, string::String, string::ToString, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::Read;
 
 use super::directives::Directive;
 use crate::native_types::{Expression, Witness};
-use crate::serialisation::{read_n, read_u16, read_u32, write_bytes, write_u16, write_u32};
+use crate::serialisation::{
+    io::{self, Write},
+    write_bytes, write_u16, write_u32,
+};
+#[cfg(feature = "std")]
+use crate::serialisation::{read_n, read_u16, read_u32};
 use crate::BlackBoxFunc;
+use acir_field::{AcirField, FieldElement};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub enum Opcode {
-    Arithmetic(Expression),
+pub enum Opcode<F = FieldElement> {
+    Arithmetic(Expression<F>),
     BlackBoxFuncCall(BlackBoxFuncCall),
-    Directive(Directive),
+    Directive(Directive<F>),
+    /// Calls the function with index `id` in the enclosing `Program`.
+    ///
+    /// The callee's public inputs are its parameters, which are bound to `inputs`, and
+    /// its return values are bound to `outputs`.
+    Call {
+        id: u32,
+        inputs: Vec<Witness>,
+        outputs: Vec<Witness>,
+    },
 }
 
-impl Opcode {
+impl<F: AcirField> Opcode<F> {
     // TODO We can add a domain separator by doing something like:
     // TODO concat!("directive:", directive.name)
     pub fn name(&self) -> &str {
@@ -21,10 +38,11 @@ impl Opcode {
             Opcode::Arithmetic(_) => "arithmetic",
             Opcode::Directive(directive) => directive.name(),
             Opcode::BlackBoxFuncCall(g) => g.name.name(),
+            Opcode::Call { .. } => "call",
         }
     }
-    // We have three types of opcodes allowed in the IR
-    // Expression, BlackBoxFuncCall and Directives
+    // We have four types of opcodes allowed in the IR
+    // Expression, BlackBoxFuncCall, Directives and Calls
     // When we serialise these opcodes, we use the index
     // to uniquely identify which category of opcode we are dealing with.
     pub(crate) fn to_index(&self) -> u8 {
@@ -32,20 +50,21 @@ impl Opcode {
             Opcode::Arithmetic(_) => 0,
             Opcode::BlackBoxFuncCall(_) => 1,
             Opcode::Directive(_) => 2,
+            Opcode::Call { .. } => 3,
         }
     }
 
     pub fn is_arithmetic(&self) -> bool {
         matches!(self, Opcode::Arithmetic(_))
     }
-    pub fn arithmetic(self) -> Option<Expression> {
+    pub fn arithmetic(self) -> Option<Expression<F>> {
         match self {
             Opcode::Arithmetic(expr) => Some(expr),
             _ => None,
         }
     }
 
-    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
         let opcode_index = self.to_index();
         write_bytes(&mut writer, &[opcode_index])?;
 
@@ -53,8 +72,18 @@ impl Opcode {
             Opcode::Arithmetic(expr) => expr.write(writer),
             Opcode::BlackBoxFuncCall(func_call) => func_call.write(writer),
             Opcode::Directive(directive) => directive.write(writer),
+            Opcode::Call {
+                id,
+                inputs,
+                outputs,
+            } => {
+                write_u32(&mut writer, *id)?;
+                write_witnesses(&mut writer, inputs)?;
+                write_witnesses(&mut writer, outputs)
+            }
         }
     }
+    #[cfg(feature = "std")]
     pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
         // First byte indicates the opcode category
         let opcode_index = read_n::<1, _>(&mut reader)?[0];
@@ -74,13 +103,23 @@ impl Opcode {
                 let directive = Directive::read(reader)?;
                 Ok(Opcode::Directive(directive))
             }
+            3 => {
+                let id = read_u32(&mut reader)?;
+                let inputs = read_witnesses(&mut reader)?;
+                let outputs = read_witnesses(&mut reader)?;
+                Ok(Opcode::Call {
+                    id,
+                    inputs,
+                    outputs,
+                })
+            }
             _ => Err(std::io::ErrorKind::InvalidData.into()),
         }
     }
 }
 
-impl std::fmt::Display for Opcode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl<F: AcirField> core::fmt::Display for Opcode<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Opcode::Arithmetic(expr) => {
                 write!(f, "EXPR [ ")?;
@@ -164,13 +203,69 @@ impl std::fmt::Display for Opcode {
                     b.last().unwrap().witness_index(),
                 )
             }
+            Opcode::Directive(Directive::Oracle {
+                name,
+                inputs,
+                outputs,
+            }) => {
+                write!(f, "DIR::ORACLE {name} ")?;
+                let inputs: Vec<String> = inputs.iter().map(ToString::to_string).collect();
+                let outputs: Vec<String> = outputs
+                    .iter()
+                    .map(|witness| format!("_{}", witness.witness_index()))
+                    .collect();
+                write!(
+                    f,
+                    "([{}], out: [{}])",
+                    inputs.join(", "),
+                    outputs.join(", ")
+                )
+            }
+            Opcode::Call {
+                id,
+                inputs,
+                outputs,
+            } => {
+                write!(f, "CALL {id} ")?;
+                let format_witnesses = |witnesses: &[Witness]| {
+                    witnesses
+                        .iter()
+                        .map(|witness| format!("_{}", witness.witness_index()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                write!(
+                    f,
+                    "([{}], out: [{}])",
+                    format_witnesses(inputs),
+                    format_witnesses(outputs)
+                )
+            }
         }
     }
 }
 
-impl std::fmt::Debug for Opcode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Display::fmt(self, f)
+fn write_witnesses<W: Write>(mut writer: W, witnesses: &[Witness]) -> io::Result<()> {
+    write_u32(&mut writer, witnesses.len() as u32)?;
+    for witness in witnesses {
+        write_u32(&mut writer, witness.witness_index())?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+fn read_witnesses<R: Read>(mut reader: R) -> std::io::Result<Vec<Witness>> {
+    let num_witnesses = read_u32(&mut reader)?;
+    let mut witnesses = Vec::new();
+    for _ in 0..num_witnesses {
+        witnesses.push(Witness(read_u32(&mut reader)?));
+    }
+    Ok(witnesses)
+}
+
+impl<F: AcirField> core::fmt::Debug for Opcode<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(self, f)
     }
 }
 
@@ -190,7 +285,7 @@ pub struct BlackBoxFuncCall {
 }
 
 impl BlackBoxFuncCall {
-    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
         write_u16(&mut writer, self.name.to_u16())?;
 
         let num_inputs = self.inputs.len() as u32;
@@ -210,12 +305,13 @@ impl BlackBoxFuncCall {
 
         Ok(())
     }
+    #[cfg(feature = "std")]
     pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
         let func_index = read_u16(&mut reader)?;
         let name = BlackBoxFunc::from_u16(func_index).ok_or(std::io::ErrorKind::InvalidData)?;
 
         let num_inputs = read_u32(&mut reader)?;
-        let mut inputs = Vec::with_capacity(num_inputs as usize);
+        let mut inputs = Vec::new();
         for _ in 0..num_inputs {
             let witness = Witness(read_u32(&mut reader)?);
             let num_bits = read_u32(&mut reader)?;
@@ -224,7 +320,7 @@ impl BlackBoxFuncCall {
         }
 
         let num_outputs = read_u32(&mut reader)?;
-        let mut outputs = Vec::with_capacity(num_outputs as usize);
+        let mut outputs = Vec::new();
         for _ in 0..num_outputs {
             let witness = Witness(read_u32(&mut reader)?);
             outputs.push(witness)
@@ -238,8 +334,8 @@ impl BlackBoxFuncCall {
     }
 }
 
-impl std::fmt::Display for BlackBoxFuncCall {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for BlackBoxFuncCall {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let uppercase_name: String = self.name.name().into();
         let uppercase_name = uppercase_name.to_uppercase();
         write!(f, "BLACKBOX::{uppercase_name} ")?;
@@ -319,9 +415,9 @@ impl std::fmt::Display for BlackBoxFuncCall {
     }
 }
 
-impl std::fmt::Debug for BlackBoxFuncCall {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Display::fmt(self, f)
+impl core::fmt::Debug for BlackBoxFuncCall {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(self, f)
     }
 }
 
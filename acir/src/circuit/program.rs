@@ -0,0 +1,107 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::Read;
+
+use super::Circuit;
+#[cfg(feature = "std")]
+use crate::serialisation::read_u32;
+use crate::serialisation::{
+    io::{self, Write},
+    write_u32,
+};
+use acir_field::{AcirField, FieldElement};
+use serde::{Deserialize, Serialize};
+
+/// A collection of circuits which can call each other using `Opcode::Call`.
+///
+/// The first function is the entry point of the program. Calls refer to
+/// functions by their index in `functions`. Programs must not be recursive.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Program<F = FieldElement> {
+    pub functions: Vec<Circuit<F>>,
+}
+
+impl<F> Default for Program<F> {
+    fn default() -> Self {
+        Program {
+            functions: Vec::new(),
+        }
+    }
+}
+
+impl<F: AcirField> core::fmt::Debug for Program<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Program")
+            .field("functions", &self.functions)
+            .finish()
+    }
+}
+
+impl<F: AcirField> Program<F> {
+    /// Returns the entry point of the program.
+    ///
+    /// Panics if the program has no functions, which `Program::read` rejects.
+    pub fn main(&self) -> &Circuit<F> {
+        &self.functions[0]
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_u32(&mut writer, self.functions.len() as u32)?;
+        for function in &self.functions {
+            function.write(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let num_functions = read_u32(&mut reader)?;
+        // A program must have an entry point
+        if num_functions == 0 {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+        let mut functions = Vec::new();
+        for _ in 0..num_functions {
+            functions.push(Circuit::read(&mut reader)?);
+        }
+        Ok(Program { functions })
+    }
+}
+
+#[test]
+fn serialisation_roundtrip() {
+    use super::{Opcode, PublicInputs};
+    use crate::native_types::{Expression, Witness};
+
+    let callee: Circuit = Circuit {
+        current_witness_index: 2,
+        opcodes: vec![Opcode::Arithmetic(
+            &Expression::from(&Witness(1)) - &Witness(2),
+        )],
+        public_inputs: PublicInputs(vec![Witness(1), Witness(2)]),
+        return_values: PublicInputs::default(),
+    };
+    let main = Circuit {
+        current_witness_index: 2,
+        opcodes: vec![Opcode::Call {
+            id: 1,
+            inputs: vec![Witness(1)],
+            outputs: vec![Witness(2)],
+        }],
+        public_inputs: PublicInputs(vec![Witness(1)]),
+        return_values: PublicInputs::default(),
+    };
+    let program = Program {
+        functions: vec![main, callee],
+    };
+
+    let mut bytes = Vec::new();
+    program.write(&mut bytes).unwrap();
+    assert_eq!(Program::read(&*bytes).unwrap(), program);
+
+    let mut bytes = Vec::new();
+    Program::<FieldElement>::default()
+        .write(&mut bytes)
+        .unwrap();
+    assert!(Program::<FieldElement>::read(&*bytes).is_err());
+}
@@ -0,0 +1,907 @@
+// A textual format for circuits, which can be written by hand and diffed
+//
+// A circuit is written as a header followed by one opcode per line:
+//
+//     current_witness_index 5
+//     public_inputs [_1, _2]
+//     return_values [_3]
+//     arithmetic 2*_1*_2 - _3 + 4
+//     range [_3:8] -> []
+//     and [_1:4, _2:4] -> [_4]
+//     invert _1 -> _5
+//     quotient (_1) / (_2 + 1) -> _3, _4 if (_5)
+//     truncate (_1) bits 8 -> _2, _3
+//     odd_range _1 bits 8 -> _2, _3
+//     to_radix (_1) radix 2 -> [_2, _3]
+//     call 1 [_1, _2] -> [_3]
+//     oracle "get_price" [(_1), (2)] -> [_4]
+//
+// The `return_values` line is only written for circuits which have return values, and may be
+// left out. Each opcode starts with its name, and witnesses are written as `_` followed by their index.
+// Expressions list their multiplication terms, then their linear terms and then their
+// constant, so that printing and parsing a circuit returns the same circuit. Coefficients are
+// decimal, or hexadecimal with a `0x` prefix. Oracle names are quoted, with `"` and `\`
+// escaped by a `\`, and line breaks, tabs and other control characters written as `\n`,
+// `\r`, `\t` or `\u{..}`. Blank lines and `//` comments are ignored.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{fmt::Write, iter::Peekable, str::CharIndices};
+
+use acir_field::FieldElement;
+
+use super::{
+    directives::Directive,
+    opcodes::{BlackBoxFuncCall, FunctionInput},
+    Circuit, Opcode, PublicInputs,
+};
+use crate::{native_types::Expression, native_types::Witness, BlackBoxFunc};
+
+/// An error in the textual format of a circuit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The line of the error, counting from 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl core::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+impl Circuit {
+    /// Returns the circuit in the textual format, which is read by `Circuit::from_text`
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        writeln!(text, "current_witness_index {}", self.current_witness_index).unwrap();
+        writeln!(
+            text,
+            "public_inputs {}",
+            witness_list(&self.public_inputs.0)
+        )
+        .unwrap();
+        if !self.return_values.0.is_empty() {
+            writeln!(
+                text,
+                "return_values {}",
+                witness_list(&self.return_values.0)
+            )
+            .unwrap();
+        }
+        for opcode in &self.opcodes {
+            writeln!(text, "{}", opcode_text(opcode)).unwrap();
+        }
+        text
+    }
+
+    /// Reads a circuit written in the textual format
+    pub fn from_text(text: &str) -> Result<Circuit, ParseError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, strip_comment(line)))
+            .filter(|(_, line)| !line.trim().is_empty())
+            .peekable();
+
+        let mut header = |keyword: &str| {
+            let (line, line_text) = lines.next().ok_or_else(|| ParseError {
+                line: text.lines().count(),
+                message: format!("expected `{keyword}`"),
+            })?;
+            let mut parser = Parser::new(line, line_text);
+            parser.expect_word(keyword)?;
+            Ok::<_, ParseError>(parser)
+        };
+
+        let mut parser = header("current_witness_index")?;
+        let current_witness_index = parser.u32()?;
+        parser.end()?;
+
+        let mut parser = header("public_inputs")?;
+        let public_inputs = PublicInputs(parser.witness_list()?);
+        parser.end()?;
+
+        let mut return_values = PublicInputs::default();
+        if let Some((line, line_text)) =
+            lines.next_if(|(_, line_text)| line_text.trim_start().starts_with("return_values"))
+        {
+            let mut parser = Parser::new(line, line_text);
+            parser.expect_word("return_values")?;
+            return_values = PublicInputs(parser.witness_list()?);
+            parser.end()?;
+        }
+
+        let mut opcodes = Vec::new();
+        for (line, text) in lines {
+            let mut parser = Parser::new(line, text);
+            opcodes.push(parser.opcode()?);
+            parser.end()?;
+        }
+
+        Ok(Circuit {
+            current_witness_index,
+            opcodes,
+            public_inputs,
+            return_values,
+        })
+    }
+}
+
+// Removes the `//` comment at the end of a line, if there is one outside of a quoted string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut chars = line.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '/' if !in_string && chars.next_if(|(_, c)| *c == '/').is_some() => {
+                return &line[..index];
+            }
+            _ => {}
+        }
+    }
+    line
+}
+
+fn witness_text(witness: &Witness) -> String {
+    format!("_{}", witness.witness_index())
+}
+
+fn witness_list(witnesses: &[Witness]) -> String {
+    let witnesses: Vec<String> = witnesses.iter().map(witness_text).collect();
+    format!("[{}]", witnesses.join(", "))
+}
+
+// Returns the coefficient without its sign, and whether it is negative
+fn coefficient_text(coefficient: &FieldElement) -> (String, bool) {
+    let (magnitude, is_negative) = if (-*coefficient).num_bits() < coefficient.num_bits() {
+        (-*coefficient, true)
+    } else {
+        (*coefficient, false)
+    };
+    let text = match magnitude.try_into_u128() {
+        Some(value) => value.to_string(),
+        None => format!("0x{}", magnitude.to_hex()),
+    };
+    (text, is_negative)
+}
+
+fn expression_text(expr: &Expression) -> String {
+    let mut terms: Vec<(FieldElement, Vec<&Witness>)> = Vec::new();
+    for (coefficient, lhs, rhs) in &expr.mul_terms {
+        terms.push((*coefficient, vec![lhs, rhs]));
+    }
+    for (coefficient, witness) in &expr.linear_combinations {
+        terms.push((*coefficient, vec![witness]));
+    }
+    if !expr.q_c.is_zero() || terms.is_empty() {
+        terms.push((expr.q_c, Vec::new()));
+    }
+
+    let mut text = String::new();
+    for (index, (coefficient, witnesses)) in terms.into_iter().enumerate() {
+        let (coefficient, is_negative) = coefficient_text(&coefficient);
+        match (index, is_negative) {
+            (0, false) => {}
+            (0, true) => text.push('-'),
+            (_, false) => text.push_str(" + "),
+            (_, true) => text.push_str(" - "),
+        }
+
+        let mut factors: Vec<String> = witnesses.into_iter().map(witness_text).collect();
+        if coefficient != "1" || factors.is_empty() {
+            factors.insert(0, coefficient);
+        }
+        text.push_str(&factors.join("*"));
+    }
+    text
+}
+
+fn string_text(string: &str) -> String {
+    let mut text = String::from('"');
+    for c in string.chars() {
+        match c {
+            '"' | '\\' => {
+                text.push('\\');
+                text.push(c);
+            }
+            '\n' => text.push_str("\\n"),
+            '\r' => text.push_str("\\r"),
+            '\t' => text.push_str("\\t"),
+            c if c.is_control() => write!(text, "\\u{{{:x}}}", c as u32).unwrap(),
+            c => text.push(c),
+        }
+    }
+    text.push('"');
+    text
+}
+
+fn opcode_text(opcode: &Opcode) -> String {
+    let name = opcode.name();
+    match opcode {
+        Opcode::Arithmetic(expr) => format!("{name} {}", expression_text(expr)),
+        Opcode::BlackBoxFuncCall(func_call) => {
+            let inputs: Vec<String> = func_call
+                .inputs
+                .iter()
+                .map(|input| format!("{}:{}", witness_text(&input.witness), input.num_bits))
+                .collect();
+            format!(
+                "{name} [{}] -> {}",
+                inputs.join(", "),
+                witness_list(&func_call.outputs)
+            )
+        }
+        Opcode::Directive(directive) => match directive {
+            Directive::Invert { x, result } => {
+                format!("{name} {} -> {}", witness_text(x), witness_text(result))
+            }
+            Directive::Quotient {
+                a,
+                b,
+                q,
+                r,
+                predicate,
+            } => {
+                let mut text = format!(
+                    "{name} ({}) / ({}) -> {}, {}",
+                    expression_text(a),
+                    expression_text(b),
+                    witness_text(q),
+                    witness_text(r)
+                );
+                if let Some(predicate) = predicate {
+                    write!(text, " if ({})", expression_text(predicate)).unwrap();
+                }
+                text
+            }
+            Directive::Truncate { a, b, c, bit_size } => format!(
+                "{name} ({}) bits {bit_size} -> {}, {}",
+                expression_text(a),
+                witness_text(b),
+                witness_text(c)
+            ),
+            Directive::OddRange { a, b, r, bit_size } => format!(
+                "{name} {} bits {bit_size} -> {}, {}",
+                witness_text(a),
+                witness_text(b),
+                witness_text(r)
+            ),
+            Directive::ToRadix { a, b, radix } => format!(
+                "{name} ({}) radix {radix} -> {}",
+                expression_text(a),
+                witness_list(b)
+            ),
+            Directive::Oracle {
+                name: oracle,
+                inputs,
+                outputs,
+            } => {
+                let inputs: Vec<String> = inputs
+                    .iter()
+                    .map(|input| format!("({})", expression_text(input)))
+                    .collect();
+                format!(
+                    "{name} {} [{}] -> {}",
+                    string_text(oracle),
+                    inputs.join(", "),
+                    witness_list(outputs)
+                )
+            }
+        },
+        Opcode::Call {
+            id,
+            inputs,
+            outputs,
+        } => format!(
+            "{name} {id} {} -> {}",
+            witness_list(inputs),
+            witness_list(outputs)
+        ),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Word(&'a str),
+    Number(&'a str),
+    Witness(u32),
+    String(String),
+    Symbol(&'static str),
+}
+
+impl core::fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Token::Word(word) | Token::Number(word) => write!(f, "`{word}`"),
+            Token::Witness(index) => write!(f, "`_{index}`"),
+            Token::String(string) => write!(f, "`{}`", string_text(string)),
+            Token::Symbol(symbol) => write!(f, "`{symbol}`"),
+        }
+    }
+}
+
+// Parses a single line of the textual format
+struct Parser<'a> {
+    line: usize,
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(line: usize, text: &'a str) -> Self {
+        Parser {
+            line,
+            text,
+            chars: text.char_indices().peekable(),
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    // Returns the end of the run of characters which match `pred`, starting at `start`
+    fn take_while(&mut self, start: usize, pred: impl Fn(char) -> bool) -> usize {
+        let mut end = start;
+        while let Some((index, c)) = self.chars.next_if(|(_, c)| pred(*c)) {
+            end = index + c.len_utf8();
+        }
+        end
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token<'a>>, ParseError> {
+        self.skip_whitespace();
+        let Some((start, c)) = self.chars.next() else {
+            return Ok(None);
+        };
+        let token = match c {
+            '_' => {
+                let end = self.take_while(start + 1, |c| c.is_ascii_digit());
+                let index = self.text[start + 1..end]
+                    .parse()
+                    .map_err(|_| self.error("expected a witness index after `_`"))?;
+                Token::Witness(index)
+            }
+            c if c.is_ascii_digit() => {
+                let end = self.take_while(start + 1, |c| c.is_ascii_alphanumeric());
+                Token::Number(&self.text[start..end])
+            }
+            c if c.is_ascii_alphabetic() => {
+                let end = self.take_while(start + 1, |c| c.is_ascii_alphanumeric() || c == '_');
+                Token::Word(&self.text[start..end])
+            }
+            '"' => Token::String(self.string()?),
+            '-' if self.chars.next_if(|(_, c)| *c == '>').is_some() => Token::Symbol("->"),
+            '-' => Token::Symbol("-"),
+            '+' => Token::Symbol("+"),
+            '*' => Token::Symbol("*"),
+            '/' => Token::Symbol("/"),
+            ':' => Token::Symbol(":"),
+            ',' => Token::Symbol(","),
+            '(' => Token::Symbol("("),
+            ')' => Token::Symbol(")"),
+            '[' => Token::Symbol("["),
+            ']' => Token::Symbol("]"),
+            c => return Err(self.error(format!("unexpected character `{c}`"))),
+        };
+        Ok(Some(token))
+    }
+
+    // Reads the rest of a quoted string, after its opening quote
+    fn string(&mut self) -> Result<String, ParseError> {
+        let mut string = String::new();
+        loop {
+            let Some((_, c)) = self.chars.next() else {
+                return Err(self.error("expected `\"` at the end of the string"));
+            };
+            match c {
+                '"' => return Ok(string),
+                '\\' => string.push(self.escape()?),
+                c => string.push(c),
+            }
+        }
+    }
+
+    // Reads the rest of an escape sequence in a quoted string, after its backslash
+    fn escape(&mut self) -> Result<char, ParseError> {
+        let c = match self.chars.next() {
+            Some((_, c @ ('"' | '\\'))) => c,
+            Some((_, 'n')) => '\n',
+            Some((_, 'r')) => '\r',
+            Some((_, 't')) => '\t',
+            Some((start, 'u')) if self.chars.next_if(|(_, c)| *c == '{').is_some() => {
+                let end = self.take_while(start + 2, |c| c.is_ascii_hexdigit());
+                let code = u32::from_str_radix(&self.text[start + 2..end], 16).ok();
+                match (code.and_then(char::from_u32), self.chars.next()) {
+                    (Some(c), Some((_, '}'))) => c,
+                    _ => return Err(self.error("expected a unicode escape `\\u{..}`")),
+                }
+            }
+            Some((_, c)) => return Err(self.error(format!("unknown escape `\\{c}`"))),
+            None => return Err(self.error("expected `\"` at the end of the string")),
+        };
+        Ok(c)
+    }
+
+    fn peek_token(&self) -> Result<Option<Token<'a>>, ParseError> {
+        Parser {
+            line: self.line,
+            text: self.text,
+            chars: self.chars.clone(),
+        }
+        .next_token()
+    }
+
+    fn expect_token(&mut self, expected: &str) -> Result<Token<'a>, ParseError> {
+        self.next_token()?
+            .ok_or_else(|| self.error(format!("expected {expected}, found the end of the line")))
+    }
+
+    fn unexpected(&self, token: Token, expected: &str) -> ParseError {
+        self.error(format!("expected {expected}, found {token}"))
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), ParseError> {
+        let expected = format!("`{symbol}`");
+        match self.expect_token(&expected)? {
+            Token::Symbol(found) if found == symbol => Ok(()),
+            token => Err(self.unexpected(token, &expected)),
+        }
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> Result<bool, ParseError> {
+        if matches!(self.peek_token()?, Some(Token::Symbol(found)) if found == symbol) {
+            self.next_token()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn word(&mut self) -> Result<&'a str, ParseError> {
+        match self.expect_token("an opcode name")? {
+            Token::Word(word) => Ok(word),
+            token => Err(self.unexpected(token, "an opcode name")),
+        }
+    }
+
+    fn expect_word(&mut self, keyword: &str) -> Result<(), ParseError> {
+        let expected = format!("`{keyword}`");
+        match self.expect_token(&expected)? {
+            Token::Word(word) if word == keyword => Ok(()),
+            token => Err(self.unexpected(token, &expected)),
+        }
+    }
+
+    fn u32(&mut self) -> Result<u32, ParseError> {
+        match self.expect_token("an integer")? {
+            Token::Number(number) => number
+                .parse()
+                .map_err(|_| self.error(format!("`{number}` is not a valid u32"))),
+            token => Err(self.unexpected(token, "an integer")),
+        }
+    }
+
+    fn witness(&mut self) -> Result<Witness, ParseError> {
+        match self.expect_token("a witness")? {
+            Token::Witness(index) => Ok(Witness(index)),
+            token => Err(self.unexpected(token, "a witness")),
+        }
+    }
+
+    // Parses a comma separated list in square brackets
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        self.expect_symbol("[")?;
+        let mut items = Vec::new();
+        if self.eat_symbol("]")? {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.eat_symbol("]")? {
+                return Ok(items);
+            }
+            self.expect_symbol(",")?;
+        }
+    }
+
+    fn witness_list(&mut self) -> Result<Vec<Witness>, ParseError> {
+        self.list(Self::witness)
+    }
+
+    fn end(&mut self) -> Result<(), ParseError> {
+        match self.next_token()? {
+            None => Ok(()),
+            Some(token) => Err(self.unexpected(token, "the end of the line")),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expression, ParseError> {
+        let mut expr = Expression::default();
+        let mut is_negative = self.eat_symbol("-")?;
+        loop {
+            self.term(&mut expr, is_negative)?;
+            if self.eat_symbol("+")? {
+                is_negative = false;
+            } else if self.eat_symbol("-")? {
+                is_negative = true;
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    // Parses a product of at most one coefficient and at most two witnesses, and adds it
+    // to the expression
+    fn term(&mut self, expr: &mut Expression, is_negative: bool) -> Result<(), ParseError> {
+        let mut coefficient = None;
+        let mut witnesses = Vec::new();
+        loop {
+            match self.expect_token("a coefficient or a witness")? {
+                Token::Number(number) if coefficient.is_none() => {
+                    let value = FieldElement::try_from_str(number).ok_or_else(|| {
+                        self.error(format!("`{number}` is not a valid field element"))
+                    })?;
+                    coefficient = Some(value);
+                }
+                Token::Witness(index) if witnesses.len() < 2 => witnesses.push(Witness(index)),
+                token => {
+                    return Err(self.unexpected(token, "a coefficient or at most two witnesses"))
+                }
+            }
+            if !self.eat_symbol("*")? {
+                break;
+            }
+        }
+
+        let coefficient = coefficient.unwrap_or_else(FieldElement::one);
+        let coefficient = if is_negative {
+            -coefficient
+        } else {
+            coefficient
+        };
+        match witnesses[..] {
+            [] => expr.q_c += coefficient,
+            [witness] => expr.term_addition(coefficient, witness),
+            [lhs, rhs] => expr.term_multiplication(coefficient, lhs, rhs),
+            _ => unreachable!("terms have at most two witnesses"),
+        }
+        Ok(())
+    }
+
+    fn bracketed_expression(&mut self) -> Result<Expression, ParseError> {
+        self.expect_symbol("(")?;
+        let expr = self.expression()?;
+        self.expect_symbol(")")?;
+        Ok(expr)
+    }
+
+    fn opcode(&mut self) -> Result<Opcode, ParseError> {
+        let name = self.word()?;
+        let opcode = match name {
+            "arithmetic" => Opcode::Arithmetic(self.expression()?),
+            "call" => {
+                let id = self.u32()?;
+                let inputs = self.witness_list()?;
+                self.expect_symbol("->")?;
+                let outputs = self.witness_list()?;
+                Opcode::Call {
+                    id,
+                    inputs,
+                    outputs,
+                }
+            }
+            "invert" => {
+                let x = self.witness()?;
+                self.expect_symbol("->")?;
+                let result = self.witness()?;
+                Opcode::Directive(Directive::Invert { x, result })
+            }
+            "quotient" => {
+                let a = self.bracketed_expression()?;
+                self.expect_symbol("/")?;
+                let b = self.bracketed_expression()?;
+                self.expect_symbol("->")?;
+                let q = self.witness()?;
+                self.expect_symbol(",")?;
+                let r = self.witness()?;
+                let predicate = if self.peek_token()?.is_some() {
+                    self.expect_word("if")?;
+                    Some(self.bracketed_expression()?)
+                } else {
+                    None
+                };
+                Opcode::Directive(Directive::Quotient {
+                    a,
+                    b,
+                    q,
+                    r,
+                    predicate,
+                })
+            }
+            "truncate" => {
+                let a = self.bracketed_expression()?;
+                self.expect_word("bits")?;
+                let bit_size = self.u32()?;
+                self.expect_symbol("->")?;
+                let b = self.witness()?;
+                self.expect_symbol(",")?;
+                let c = self.witness()?;
+                Opcode::Directive(Directive::Truncate { a, b, c, bit_size })
+            }
+            "odd_range" => {
+                let a = self.witness()?;
+                self.expect_word("bits")?;
+                let bit_size = self.u32()?;
+                self.expect_symbol("->")?;
+                let b = self.witness()?;
+                self.expect_symbol(",")?;
+                let r = self.witness()?;
+                Opcode::Directive(Directive::OddRange { a, b, r, bit_size })
+            }
+            "to_radix" => {
+                let a = self.bracketed_expression()?;
+                self.expect_word("radix")?;
+                let radix = self.u32()?;
+                self.expect_symbol("->")?;
+                let b = self.witness_list()?;
+                Opcode::Directive(Directive::ToRadix { a, b, radix })
+            }
+            "oracle" => {
+                let name = match self.expect_token("a quoted oracle name")? {
+                    Token::String(name) => name,
+                    token => return Err(self.unexpected(token, "a quoted oracle name")),
+                };
+                let inputs = self.list(Self::bracketed_expression)?;
+                self.expect_symbol("->")?;
+                let outputs = self.witness_list()?;
+                Opcode::Directive(Directive::Oracle {
+                    name,
+                    inputs,
+                    outputs,
+                })
+            }
+            name => {
+                let name = BlackBoxFunc::lookup(name)
+                    .ok_or_else(|| self.error(format!("unknown opcode `{name}`")))?;
+                let inputs = self.list(|parser| {
+                    let witness = parser.witness()?;
+                    parser.expect_symbol(":")?;
+                    let num_bits = parser.u32()?;
+                    Ok(FunctionInput { witness, num_bits })
+                })?;
+                self.expect_symbol("->")?;
+                let outputs = self.witness_list()?;
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name,
+                    inputs,
+                    outputs,
+                })
+            }
+        };
+        Ok(opcode)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn circuit() -> Circuit {
+        let mut expr = Expression::default();
+        expr.term_multiplication(FieldElement::from(2_i128), Witness(1), Witness(2));
+        expr.term_addition(-FieldElement::one(), Witness(3));
+        expr.term_addition(FieldElement::zero(), Witness(4));
+        expr.q_c = FieldElement::from(2_i128).pow(&FieldElement::from(200_i128));
+
+        Circuit {
+            current_witness_index: 9,
+            opcodes: vec![
+                Opcode::Arithmetic(expr),
+                Opcode::Arithmetic(Expression::default()),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::AND,
+                    inputs: vec![
+                        FunctionInput {
+                            witness: Witness(1),
+                            num_bits: 4,
+                        },
+                        FunctionInput {
+                            witness: Witness(2),
+                            num_bits: 4,
+                        },
+                    ],
+                    outputs: vec![Witness(5)],
+                }),
+                Opcode::Directive(Directive::Invert {
+                    x: Witness(1),
+                    result: Witness(6),
+                }),
+                Opcode::Directive(Directive::Quotient {
+                    a: Expression::from(&Witness(1)),
+                    b: Expression::from(&Witness(2)) + FieldElement::one(),
+                    q: Witness(7),
+                    r: Witness(8),
+                    predicate: Some(-&Expression::from(&Witness(3))),
+                }),
+                Opcode::Directive(Directive::Truncate {
+                    a: Expression::from(&Witness(1)),
+                    b: Witness(7),
+                    c: Witness(8),
+                    bit_size: 8,
+                }),
+                Opcode::Directive(Directive::OddRange {
+                    a: Witness(1),
+                    b: Witness(7),
+                    r: Witness(8),
+                    bit_size: 8,
+                }),
+                Opcode::Directive(Directive::ToRadix {
+                    a: Expression::from(&Witness(1)),
+                    b: vec![Witness(7), Witness(8)],
+                    radix: 2,
+                }),
+                Opcode::Call {
+                    id: 1,
+                    inputs: vec![Witness(1), Witness(2)],
+                    outputs: vec![Witness(9)],
+                },
+                Opcode::Directive(Directive::Oracle {
+                    name: "get_price".to_owned(),
+                    inputs: vec![Expression::from(&Witness(1)), Expression::default()],
+                    outputs: vec![Witness(7), Witness(8)],
+                }),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1), Witness(2)]),
+            return_values: PublicInputs(vec![Witness(9)]),
+        }
+    }
+
+    #[test]
+    fn text_roundtrip() {
+        let circuit = circuit();
+        let text = circuit.to_text();
+        assert_eq!(Circuit::from_text(&text), Ok(circuit.clone()));
+
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[1], "public_inputs [_1, _2]");
+        assert_eq!(lines[2], "return_values [_9]");
+        assert!(lines[3].starts_with("arithmetic 2*_1*_2 - _3 + 0*_4 + "));
+        // Constants are only written in hexadecimal if they do not fit in a u128
+        if FieldElement::max_num_bits() > 128 {
+            assert!(lines[3].starts_with("arithmetic 2*_1*_2 - _3 + 0*_4 + 0x"));
+        }
+        assert_eq!(lines[4], "arithmetic 0");
+        assert_eq!(lines[5], "and [_1:4, _2:4] -> [_5]");
+        assert_eq!(lines[7], "quotient (_1) / (_2 + 1) -> _7, _8 if (-_3)");
+        assert_eq!(lines[12], r#"oracle "get_price" [(_1), (0)] -> [_7, _8]"#);
+
+        // Circuits without return values do not have the line
+        let circuit = Circuit {
+            return_values: PublicInputs::default(),
+            ..circuit
+        };
+        let text = circuit.to_text();
+        assert!(!text.contains("return_values"));
+        assert_eq!(Circuit::from_text(&text), Ok(circuit));
+    }
+
+    #[test]
+    fn oracle_names_are_quoted() {
+        for name in [
+            "std::print",
+            "get-price",
+            "1st",
+            "",
+            "a // b",
+            "say \"hi\"\\",
+            "line\nbreak\ttab\u{7}",
+            "émoji 🙂",
+        ] {
+            let circuit = Circuit {
+                current_witness_index: 1,
+                opcodes: vec![Opcode::Directive(Directive::Oracle {
+                    name: name.to_owned(),
+                    inputs: vec![Expression::from(&Witness(1))],
+                    outputs: vec![Witness(1)],
+                })],
+                public_inputs: PublicInputs::default(),
+                return_values: PublicInputs::default(),
+            };
+            let text = circuit.to_text();
+            assert_eq!(text.lines().count(), 3, "{text}");
+            assert_eq!(Circuit::from_text(&text), Ok(circuit), "{text}");
+        }
+    }
+
+    #[test]
+    fn hand_written_circuits_are_parsed() {
+        let text = "
+            // x * y = z
+            current_witness_index 3
+            public_inputs []
+
+            arithmetic _1*_2 - _3  // the only constraint
+            range [_3:8] -> []
+        ";
+        let mut expr = Expression::default();
+        expr.term_multiplication(FieldElement::one(), Witness(1), Witness(2));
+        expr.term_addition(-FieldElement::one(), Witness(3));
+        assert_eq!(
+            Circuit::from_text(text),
+            Ok(Circuit {
+                current_witness_index: 3,
+                opcodes: vec![
+                    Opcode::Arithmetic(expr),
+                    Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                        name: BlackBoxFunc::RANGE,
+                        inputs: vec![FunctionInput {
+                            witness: Witness(3),
+                            num_bits: 8,
+                        }],
+                        outputs: Vec::new(),
+                    }),
+                ],
+                public_inputs: PublicInputs::default(),
+                return_values: PublicInputs::default(),
+            })
+        );
+    }
+
+    #[test]
+    fn errors_report_their_line() {
+        let header = "current_witness_index 3\npublic_inputs []\n";
+        let error = |body: &str| Circuit::from_text(&format!("{header}{body}")).unwrap_err();
+
+        assert_eq!(
+            error("arithmetic _1\nfoo _1"),
+            ParseError {
+                line: 4,
+                message: "unknown opcode `foo`".to_owned()
+            }
+        );
+        assert_eq!(
+            error("arithmetic _1*_2*_3").message,
+            "expected a coefficient or at most two witnesses, found `_3`"
+        );
+        assert_eq!(error("invert _1 _2").message, "expected `->`, found `_2`");
+        assert_eq!(
+            error("oracle get_price [] -> []").message,
+            "expected a quoted oracle name, found `get_price`"
+        );
+        assert_eq!(
+            error(r#"oracle "get_price [] -> []"#).message,
+            "expected `\"` at the end of the string"
+        );
+        assert_eq!(
+            error(r#"oracle "\q" [] -> []"#).message,
+            "unknown escape `\\q`"
+        );
+        assert_eq!(
+            Circuit::from_text("public_inputs []").unwrap_err().message,
+            "expected `current_witness_index`, found `public_inputs`"
+        );
+    }
+}
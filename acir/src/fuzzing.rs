@@ -0,0 +1,283 @@
+// Implementations of `arbitrary::Arbitrary`, which generate inputs for structured fuzzing
+//
+// Generated values are kept small, so that each input exercises the solver rather than
+// spending its time on huge circuits. Witnesses are in the range `1..=max_witness_index`,
+// where the bound is `MAX_WITNESS_INDEX` unless a circuit with a smaller
+// `current_witness_index` is being generated.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{
+    circuit::{
+        directives::Directive,
+        opcodes::{BlackBoxFuncCall, FunctionInput},
+        Circuit, Opcode, PublicInputs,
+    },
+    native_types::{Expression, Witness, WitnessMap},
+    BlackBoxFunc, FieldElement,
+};
+
+/// The largest witness index of generated values
+pub const MAX_WITNESS_INDEX: u32 = 32;
+/// The largest number of opcodes in a generated circuit
+pub const MAX_OPCODES: usize = 32;
+/// The largest number of linear terms in a generated expression. Expressions have at most
+/// one multiplication term, as arithmetic opcodes are solved in that form.
+pub const MAX_TERMS: usize = 4;
+/// The largest number of inputs or outputs of a generated directive or black box call
+pub const MAX_ARGUMENTS: usize = 8;
+
+/// Returns a field element which is often small, one less than a power of two or at the
+/// top of the field, as these are the values where solvers have edge cases
+pub fn arbitrary_field_element(u: &mut Unstructured<'_>) -> Result<FieldElement> {
+    let value = match u.int_in_range(0..=3)? {
+        0 => FieldElement::from(u.int_in_range(-4_i128..=4)?),
+        1 => FieldElement::from(u.arbitrary::<u64>()? as i128),
+        2 => {
+            let exponent = u.int_in_range(0..=FieldElement::max_num_bits())?;
+            let power = FieldElement::from(2_i128).pow(&FieldElement::from(exponent as i128));
+            power - FieldElement::from(u.int_in_range(0_i128..=1)?)
+        }
+        _ => FieldElement::from_be_bytes_reduce(&u.arbitrary::<[u8; 32]>()?),
+    };
+    Ok(value)
+}
+
+/// Returns a witness in the range `1..=max_witness_index`
+pub fn arbitrary_witness(u: &mut Unstructured<'_>, max_witness_index: u32) -> Result<Witness> {
+    Ok(Witness(u.int_in_range(1..=max_witness_index.max(1))?))
+}
+
+/// Returns an expression of the witnesses up to `max_witness_index`
+pub fn arbitrary_expression(
+    u: &mut Unstructured<'_>,
+    max_witness_index: u32,
+) -> Result<Expression> {
+    let num_mul_terms = u.int_in_range(0..=1)?;
+    let mul_terms = (0..num_mul_terms)
+        .map(|_| {
+            Ok((
+                arbitrary_field_element(u)?,
+                arbitrary_witness(u, max_witness_index)?,
+                arbitrary_witness(u, max_witness_index)?,
+            ))
+        })
+        .collect::<Result<_>>()?;
+    let num_linear_terms = u.int_in_range(0..=MAX_TERMS)?;
+    let linear_combinations = (0..num_linear_terms)
+        .map(|_| {
+            Ok((
+                arbitrary_field_element(u)?,
+                arbitrary_witness(u, max_witness_index)?,
+            ))
+        })
+        .collect::<Result<_>>()?;
+    Ok(Expression {
+        mul_terms,
+        linear_combinations,
+        q_c: arbitrary_field_element(u)?,
+    })
+}
+
+/// Returns a directive of the witnesses up to `max_witness_index`.
+///
+/// Bit sizes and radixes are not limited to the values which the directives support, so
+/// that solvers are checked to reject them without panicking.
+pub fn arbitrary_directive(u: &mut Unstructured<'_>, max_witness_index: u32) -> Result<Directive> {
+    let witness = |u: &mut Unstructured<'_>| arbitrary_witness(u, max_witness_index);
+    let expression = |u: &mut Unstructured<'_>| arbitrary_expression(u, max_witness_index);
+    let bit_size = |u: &mut Unstructured<'_>| u.int_in_range(0..=FieldElement::max_num_bits() + 1);
+
+    let directive = match u.int_in_range(0..=5)? {
+        0 => Directive::Invert {
+            x: witness(u)?,
+            result: witness(u)?,
+        },
+        1 => Directive::Quotient {
+            a: expression(u)?,
+            b: expression(u)?,
+            q: witness(u)?,
+            r: witness(u)?,
+            predicate: match bool::arbitrary(u)? {
+                true => Some(expression(u)?),
+                false => None,
+            },
+        },
+        2 => Directive::Truncate {
+            a: expression(u)?,
+            b: witness(u)?,
+            c: witness(u)?,
+            bit_size: bit_size(u)?,
+        },
+        3 => Directive::OddRange {
+            a: witness(u)?,
+            b: witness(u)?,
+            r: witness(u)?,
+            bit_size: bit_size(u)?,
+        },
+        4 => Directive::ToRadix {
+            a: expression(u)?,
+            b: (0..u.int_in_range(0..=MAX_ARGUMENTS)?)
+                .map(|_| witness(u))
+                .collect::<Result<_>>()?,
+            radix: u.int_in_range(0..=257)?,
+        },
+        _ => Directive::Oracle {
+            name: ["print", "random"][u.int_in_range(0..=1)?].to_owned(),
+            inputs: (0..u.int_in_range(0..=MAX_ARGUMENTS)?)
+                .map(|_| expression(u))
+                .collect::<Result<_>>()?,
+            outputs: (0..u.int_in_range(0..=MAX_ARGUMENTS)?)
+                .map(|_| witness(u))
+                .collect::<Result<_>>()?,
+        },
+    };
+    Ok(directive)
+}
+
+/// Returns a call to a black box function other than AES, which does not have a definition.
+///
+/// The call has as many inputs and outputs as the definition of the function, or up to
+/// `MAX_ARGUMENTS` inputs if it takes a variable number of them.
+pub fn arbitrary_black_box_call(
+    u: &mut Unstructured<'_>,
+    max_witness_index: u32,
+) -> Result<BlackBoxFuncCall> {
+    let num_functions = (0..).map_while(BlackBoxFunc::from_u16).count() as u16;
+    let name = BlackBoxFunc::from_u16(u.int_in_range(1..=num_functions - 1)?)
+        .expect("infallible: index is below the number of functions");
+    let definition = name.definition();
+
+    let num_inputs = match definition.input_size.fixed_size() {
+        Some(num_inputs) => num_inputs as usize,
+        None => u.int_in_range(0..=MAX_ARGUMENTS)?,
+    };
+    let inputs = (0..num_inputs)
+        .map(|_| {
+            Ok(FunctionInput {
+                witness: arbitrary_witness(u, max_witness_index)?,
+                num_bits: u.int_in_range(0..=FieldElement::max_num_bits() + 1)?,
+            })
+        })
+        .collect::<Result<_>>()?;
+    let outputs = (0..definition.output_size.0)
+        .map(|_| arbitrary_witness(u, max_witness_index))
+        .collect::<Result<_>>()?;
+    Ok(BlackBoxFuncCall {
+        name,
+        inputs,
+        outputs,
+    })
+}
+
+/// Returns an opcode of the witnesses up to `max_witness_index`, which is not a call as a
+/// circuit on its own has no functions to call
+pub fn arbitrary_opcode(u: &mut Unstructured<'_>, max_witness_index: u32) -> Result<Opcode> {
+    let opcode = match u.int_in_range(0..=2)? {
+        0 => Opcode::Arithmetic(arbitrary_expression(u, max_witness_index)?),
+        1 => Opcode::Directive(arbitrary_directive(u, max_witness_index)?),
+        _ => Opcode::BlackBoxFuncCall(arbitrary_black_box_call(u, max_witness_index)?),
+    };
+    Ok(opcode)
+}
+
+/// Returns assignments of some of the witnesses up to `max_witness_index`
+pub fn arbitrary_witness_map(
+    u: &mut Unstructured<'_>,
+    max_witness_index: u32,
+) -> Result<WitnessMap> {
+    let mut witness_map = WitnessMap::new();
+    for index in 1..=max_witness_index {
+        if bool::arbitrary(u)? {
+            witness_map.insert(Witness(index), arbitrary_field_element(u)?);
+        }
+    }
+    Ok(witness_map)
+}
+
+impl<'a> Arbitrary<'a> for Expression {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        arbitrary_expression(u, MAX_WITNESS_INDEX)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Opcode {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        arbitrary_opcode(u, MAX_WITNESS_INDEX)
+    }
+}
+
+impl<'a> Arbitrary<'a> for WitnessMap {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        arbitrary_witness_map(u, MAX_WITNESS_INDEX)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Circuit {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let current_witness_index = u.int_in_range(1..=MAX_WITNESS_INDEX)?;
+        let opcodes = (0..u.int_in_range(0..=MAX_OPCODES)?)
+            .map(|_| arbitrary_opcode(u, current_witness_index))
+            .collect::<Result<_>>()?;
+        let public_inputs = (1..=current_witness_index)
+            .filter_map(|index| match bool::arbitrary(u) {
+                Ok(true) => Some(Ok(Witness(index))),
+                Ok(false) => None,
+                Err(err) => Some(Err(err)),
+            })
+            .collect::<Result<_>>()?;
+        let return_values = (0..u.int_in_range(0..=MAX_ARGUMENTS)?)
+            .map(|_| arbitrary_witness(u, current_witness_index))
+            .collect::<Result<_>>()?;
+        Ok(Circuit {
+            current_witness_index,
+            opcodes,
+            public_inputs: PublicInputs(public_inputs),
+            return_values: PublicInputs(return_values),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generated_circuits_are_in_bounds() {
+        let data: Vec<u8> = (0..4096_u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+        let mut u = Unstructured::new(&data);
+        while !u.is_empty() {
+            let circuit = Circuit::arbitrary(&mut u).unwrap();
+            assert!(circuit.opcodes.len() <= MAX_OPCODES);
+            for witness in circuit
+                .public_inputs
+                .0
+                .iter()
+                .chain(&circuit.return_values.0)
+            {
+                assert!(witness.0 <= circuit.current_witness_index);
+            }
+            for opcode in &circuit.opcodes {
+                let witnesses: Vec<Witness> = match opcode {
+                    Opcode::Arithmetic(expr) => expr.witnesses().into_iter().collect(),
+                    Opcode::Directive(directive) => directive
+                        .inputs()
+                        .into_iter()
+                        .chain(directive.outputs())
+                        .collect(),
+                    Opcode::BlackBoxFuncCall(call) => {
+                        let definition = call.name.definition();
+                        assert_eq!(call.outputs.len() as u128, definition.output_size.0);
+                        call.inputs.iter().map(|input| input.witness).collect()
+                    }
+                    Opcode::Call { .. } => panic!("circuits do not call functions"),
+                };
+                for witness in witnesses {
+                    assert!((1..=circuit.current_witness_index).contains(&witness.0));
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,14 @@
 // Arbitrary Circuit Intermediate Representation
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod circuit;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
 pub mod native_types;
-mod serialisation;
+pub mod serialisation;
 
-pub use acir_field::FieldElement;
+pub use acir_field::{AcirField, FieldElement};
 pub use circuit::blackbox_functions::BlackBoxFunc;
@@ -1,10 +1,18 @@
 use crate::native_types::{Linear, Witness};
-use crate::serialisation::{read_field_element, read_u32, write_bytes, write_u32};
-use acir_field::FieldElement;
+use crate::serialisation::{
+    io::{self, Write},
+    write_bytes, write_u32,
+};
+#[cfg(feature = "std")]
+use crate::serialisation::{read_field, read_u32};
+use acir_field::{AcirField, FieldElement};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{vec, vec::Vec};
+use core::cmp::Ordering;
+use core::ops::{Add, Mul, Neg, Sub};
 use serde::{Deserialize, Serialize};
-use std::cmp::Ordering;
-use std::io::{Read, Write};
-use std::ops::{Add, Mul, Neg, Sub};
+#[cfg(feature = "std")]
+use std::io::Read;
 
 use super::witness::UnknownWitness;
 
@@ -16,31 +24,31 @@ use super::witness::UnknownWitness;
 // In the multiplication polynomial
 // XXX: If we allow the degree of the quotient polynomial to be arbitrary, then we will need a vector of wire values
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Expression {
+pub struct Expression<F = FieldElement> {
     // To avoid having to create intermediate variables pre-optimisation
     // We collect all of the multiplication terms in the arithmetic gate
     // A multiplication term if of the form q_M * wL * wR
     // Hence this vector represents the following sum: q_M1 * wL1 * wR1 + q_M2 * wL2 * wR2 + .. +
-    pub mul_terms: Vec<(FieldElement, Witness, Witness)>,
+    pub mul_terms: Vec<(F, Witness, Witness)>,
 
-    pub linear_combinations: Vec<(FieldElement, Witness)>,
+    pub linear_combinations: Vec<(F, Witness)>,
     // TODO: rename q_c to `constant` moreover q_X is not clear to those who
     // TODO are not familiar with PLONK
-    pub q_c: FieldElement,
+    pub q_c: F,
 }
 
-impl Default for Expression {
-    fn default() -> Expression {
+impl<F: AcirField> Default for Expression<F> {
+    fn default() -> Expression<F> {
         Expression {
             mul_terms: Vec::new(),
             linear_combinations: Vec::new(),
-            q_c: FieldElement::zero(),
+            q_c: F::zero(),
         }
     }
 }
 
-impl std::fmt::Display for Expression {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl<F: AcirField> core::fmt::Display for Expression<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         if self.mul_terms.is_empty() && self.linear_combinations.len() == 1 && self.q_c.is_zero() {
             write!(f, "x{}", self.linear_combinations[0].1.witness_index())
         } else {
@@ -54,7 +62,7 @@ impl std::fmt::Display for Expression {
 }
 
 // TODO: possibly remove, and move to noir repo.
-impl Ord for Expression {
+impl<F: AcirField> Ord for Expression<F> {
     fn cmp(&self, other: &Self) -> Ordering {
         let mut i1 = self.get_max_idx();
         let mut i2 = other.get_max_idx();
@@ -65,13 +73,13 @@ impl Ord for Expression {
             if m1.is_none() && m2.is_none() {
                 return Ordering::Equal;
             }
-            result = Expression::cmp_max(m1, m2);
+            result = Self::cmp_max(m1, m2);
         }
         result
     }
 }
 // TODO: possibly remove, and move to noir repo.
-impl PartialOrd for Expression {
+impl<F: AcirField> PartialOrd for Expression<F> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
@@ -83,7 +91,7 @@ struct WitnessIdx {
     second_term: bool,
 }
 
-impl Expression {
+impl<F: AcirField> Expression<F> {
     // TODO: possibly remove, and move to noir repo.
     pub const fn can_defer_constraint(&self) -> bool {
         false
@@ -92,22 +100,34 @@ impl Expression {
         self.mul_terms.len()
     }
 
-    pub fn from_field(q_c: FieldElement) -> Expression {
+    pub fn from_field(q_c: F) -> Expression<F> {
         Self {
             q_c,
             ..Default::default()
         }
     }
 
-    pub fn one() -> Expression {
-        Self::from_field(FieldElement::one())
+    pub fn one() -> Expression<F> {
+        Self::from_field(F::one())
     }
 
-    pub fn zero() -> Expression {
+    /// Returns the expression `coefficient * lhs * rhs`
+    pub fn mul_witnesses(coefficient: F, lhs: Witness, rhs: Witness) -> Expression<F> {
+        let mut expr = Expression::default();
+        expr.term_multiplication(coefficient, lhs, rhs);
+        expr
+    }
+
+    /// Returns the expression multiplied by a constant
+    pub fn scale(&self, k: F) -> Expression<F> {
+        self * &k
+    }
+
+    pub fn zero() -> Expression<F> {
         Self::default()
     }
 
-    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
         let num_mul_terms = self.mul_terms.len() as u32;
         write_u32(&mut writer, num_mul_terms)?;
 
@@ -129,28 +149,27 @@ impl Expression {
 
         Ok(())
     }
+    #[cfg(feature = "std")]
     pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
         let mut expr = Expression::default();
 
-        const FIELD_ELEMENT_NUM_BYTES: usize = FieldElement::max_num_bytes() as usize;
-
         let num_mul_terms = read_u32(&mut reader)?;
         let num_lin_comb_terms = read_u32(&mut reader)?;
 
         for _ in 0..num_mul_terms {
-            let mul_term_coeff = read_field_element::<FIELD_ELEMENT_NUM_BYTES, _>(&mut reader)?;
+            let mul_term_coeff = read_field(&mut reader)?;
             let mul_term_lhs = read_u32(&mut reader)?;
             let mul_term_rhs = read_u32(&mut reader)?;
             expr.term_multiplication(mul_term_coeff, Witness(mul_term_lhs), Witness(mul_term_rhs))
         }
 
         for _ in 0..num_lin_comb_terms {
-            let lin_term_coeff = read_field_element::<FIELD_ELEMENT_NUM_BYTES, _>(&mut reader)?;
+            let lin_term_coeff = read_field(&mut reader)?;
             let lin_term_variable = read_u32(&mut reader)?;
             expr.term_addition(lin_term_coeff, Witness(lin_term_variable))
         }
 
-        let q_c = read_field_element::<FIELD_ELEMENT_NUM_BYTES, _>(&mut reader)?;
+        let q_c = read_field(&mut reader)?;
         expr.q_c = q_c;
 
         Ok(expr)
@@ -161,15 +180,10 @@ impl Expression {
         self.mul_terms.is_empty()
     }
 
-    pub fn term_addition(&mut self, coefficient: acir_field::FieldElement, variable: Witness) {
+    pub fn term_addition(&mut self, coefficient: F, variable: Witness) {
         self.linear_combinations.push((coefficient, variable))
     }
-    pub fn term_multiplication(
-        &mut self,
-        coefficient: acir_field::FieldElement,
-        lhs: Witness,
-        rhs: Witness,
-    ) {
+    pub fn term_multiplication(&mut self, coefficient: F, lhs: Witness, rhs: Witness) {
         self.mul_terms.push((coefficient, lhs, rhs))
     }
 
@@ -177,6 +191,19 @@ impl Expression {
         self.mul_terms.is_empty() && self.linear_combinations.is_empty()
     }
 
+    /// Returns the distinct witnesses which are used in the expression
+    pub fn witnesses(&self) -> BTreeSet<Witness> {
+        let mut witnesses = BTreeSet::new();
+        for (_, w_l, w_r) in &self.mul_terms {
+            witnesses.insert(*w_l);
+            witnesses.insert(*w_r);
+        }
+        for (_, w) in &self.linear_combinations {
+            witnesses.insert(*w);
+        }
+        witnesses
+    }
+
     fn get_max_idx(&self) -> WitnessIdx {
         WitnessIdx {
             linear: self.linear_combinations.len(),
@@ -243,13 +270,77 @@ impl Expression {
     pub fn sort(&mut self) {
         self.mul_terms
             .sort_by(|a, b| a.1.cmp(&b.1).then(a.2.cmp(&b.2)));
-        self.linear_combinations.sort_by(|a, b| a.1.cmp(&b.1));
+        self.linear_combinations.sort_by_key(|a| a.1);
+    }
+
+    /// Returns an equivalent expression in normal form:
+    /// - the witnesses in each multiplication term are ordered, so that `x*y` and `y*x` are the same term
+    /// - terms with the same witnesses are combined into one
+    /// - terms with a zero coefficient are removed
+    /// - the remaining terms are sorted by witness index
+    ///
+    /// Two expressions which are equal as polynomials have the same normal form.
+    pub fn normalize(&self) -> Expression<F> {
+        let mut mul_terms: BTreeMap<(Witness, Witness), F> = BTreeMap::new();
+        for (scale, w_l, w_r) in &self.mul_terms {
+            let key = if w_l <= w_r {
+                (*w_l, *w_r)
+            } else {
+                (*w_r, *w_l)
+            };
+            *mul_terms.entry(key).or_insert_with(F::zero) += *scale;
+        }
+
+        let mut linear_combinations: BTreeMap<Witness, F> = BTreeMap::new();
+        for (scale, witness) in &self.linear_combinations {
+            *linear_combinations.entry(*witness).or_insert_with(F::zero) += *scale;
+        }
+
+        Expression {
+            mul_terms: mul_terms
+                .into_iter()
+                .filter(|(_, scale)| !scale.is_zero())
+                .map(|((w_l, w_r), scale)| (scale, w_l, w_r))
+                .collect(),
+            linear_combinations: linear_combinations
+                .into_iter()
+                .filter(|(_, scale)| !scale.is_zero())
+                .map(|(witness, scale)| (scale, witness))
+                .collect(),
+            q_c: self.q_c,
+        }
+    }
+
+    /// Returns the canonical form of the constraint `self = 0`.
+    ///
+    /// This is the normal form of the expression, multiplied by -1 if needed so that
+    /// its leading coefficient is the smaller of `c` and `-c`.
+    /// Since `e = 0` and `-e = 0` are the same constraint, two arithmetic opcodes which
+    /// apply the same constraint have the same canonical form.
+    ///
+    /// Note that this does not preserve the value of the expression, so it should not be
+    /// used on expressions which are not constrained to be zero, such as directive inputs.
+    pub fn canonicalize(&self) -> Expression<F> {
+        let expr = self.normalize();
+
+        let leading_coefficient = expr
+            .mul_terms
+            .first()
+            .map(|(scale, _, _)| *scale)
+            .or_else(|| expr.linear_combinations.first().map(|(scale, _)| *scale))
+            .unwrap_or(expr.q_c);
+
+        if leading_coefficient.to_be_bytes() > (-leading_coefficient).to_be_bytes() {
+            -&expr
+        } else {
+            expr
+        }
     }
 }
 
-impl Mul<&FieldElement> for &Expression {
-    type Output = Expression;
-    fn mul(self, rhs: &FieldElement) -> Self::Output {
+impl<F: AcirField> Mul<&F> for &Expression<F> {
+    type Output = Expression<F>;
+    fn mul(self, rhs: &F) -> Self::Output {
         // Scale the mul terms
         let mul_terms: Vec<_> = self
             .mul_terms
@@ -274,9 +365,9 @@ impl Mul<&FieldElement> for &Expression {
         }
     }
 }
-impl Add<&FieldElement> for Expression {
-    type Output = Expression;
-    fn add(self, rhs: &FieldElement) -> Self::Output {
+impl<F: AcirField> Add<&F> for Expression<F> {
+    type Output = Expression<F>;
+    fn add(self, rhs: &F) -> Self::Output {
         // Increase the constant
         let q_c = self.q_c + *rhs;
 
@@ -287,9 +378,9 @@ impl Add<&FieldElement> for Expression {
         }
     }
 }
-impl Sub<&FieldElement> for Expression {
-    type Output = Expression;
-    fn sub(self, rhs: &FieldElement) -> Self::Output {
+impl<F: AcirField> Sub<&F> for Expression<F> {
+    type Output = Expression<F>;
+    fn sub(self, rhs: &F) -> Self::Output {
         // Increase the constant
         let q_c = self.q_c - *rhs;
 
@@ -301,9 +392,9 @@ impl Sub<&FieldElement> for Expression {
     }
 }
 
-impl Add<&Expression> for &Expression {
-    type Output = Expression;
-    fn add(self, rhs: &Expression) -> Expression {
+impl<F: AcirField> Add<&Expression<F>> for &Expression<F> {
+    type Output = Expression<F>;
+    fn add(self, rhs: &Expression<F>) -> Expression<F> {
         // XXX(med) : Implement an efficient way to do this
 
         let mul_terms: Vec<_> = self
@@ -329,8 +420,8 @@ impl Add<&Expression> for &Expression {
     }
 }
 
-impl Neg for &Expression {
-    type Output = Expression;
+impl<F: AcirField> Neg for &Expression<F> {
+    type Output = Expression<F>;
     fn neg(self) -> Self::Output {
         // XXX(med) : Implement an efficient way to do this
 
@@ -355,15 +446,15 @@ impl Neg for &Expression {
     }
 }
 
-impl Sub<&Expression> for &Expression {
-    type Output = Expression;
-    fn sub(self, rhs: &Expression) -> Expression {
+impl<F: AcirField> Sub<&Expression<F>> for &Expression<F> {
+    type Output = Expression<F>;
+    fn sub(self, rhs: &Expression<F>) -> Expression<F> {
         self + &-rhs
     }
 }
 
-impl From<&FieldElement> for Expression {
-    fn from(constant: &FieldElement) -> Expression {
+impl<F: AcirField> From<&F> for Expression<F> {
+    fn from(constant: &F) -> Expression<F> {
         Expression {
             q_c: *constant,
             linear_combinations: Vec::new(),
@@ -371,8 +462,8 @@ impl From<&FieldElement> for Expression {
         }
     }
 }
-impl From<&Linear> for Expression {
-    fn from(lin: &Linear) -> Expression {
+impl<F: AcirField> From<&Linear<F>> for Expression<F> {
+    fn from(lin: &Linear<F>) -> Expression<F> {
         Expression {
             q_c: lin.add_scale,
             linear_combinations: vec![(lin.mul_scale, lin.witness)],
@@ -380,47 +471,179 @@ impl From<&Linear> for Expression {
         }
     }
 }
-impl From<Linear> for Expression {
-    fn from(lin: Linear) -> Expression {
+impl<F: AcirField> From<Linear<F>> for Expression<F> {
+    fn from(lin: Linear<F>) -> Expression<F> {
         Expression::from(&lin)
     }
 }
-impl From<&Witness> for Expression {
-    fn from(wit: &Witness) -> Expression {
+impl<F: AcirField> From<&Witness> for Expression<F> {
+    fn from(wit: &Witness) -> Expression<F> {
         Linear::from_witness(*wit).into()
     }
 }
 
-impl Add<&Expression> for &Linear {
-    type Output = Expression;
-    fn add(self, rhs: &Expression) -> Expression {
+impl<F: AcirField> Add<&Expression<F>> for &Linear<F> {
+    type Output = Expression<F>;
+    fn add(self, rhs: &Expression<F>) -> Expression<F> {
         &Expression::from(self) + rhs
     }
 }
-impl Add<&Linear> for &Expression {
-    type Output = Expression;
-    fn add(self, rhs: &Linear) -> Expression {
+impl<F: AcirField> Add<&Linear<F>> for &Expression<F> {
+    type Output = Expression<F>;
+    fn add(self, rhs: &Linear<F>) -> Expression<F> {
         &Expression::from(rhs) + self
     }
 }
-impl Sub<&Witness> for &Expression {
-    type Output = Expression;
-    fn sub(self, rhs: &Witness) -> Expression {
+impl<F: AcirField> Sub<&Witness> for &Expression<F> {
+    type Output = Expression<F>;
+    fn sub(self, rhs: &Witness) -> Expression<F> {
         self - &Expression::from(rhs)
     }
 }
-impl Sub<&UnknownWitness> for &Expression {
-    type Output = Expression;
-    fn sub(self, rhs: &UnknownWitness) -> Expression {
+impl<F: AcirField> Sub<&UnknownWitness> for &Expression<F> {
+    type Output = Expression<F>;
+    fn sub(self, rhs: &UnknownWitness) -> Expression<F> {
         let mut cloned = self.clone();
         cloned
             .linear_combinations
-            .insert(0, (-FieldElement::one(), rhs.as_witness()));
+            .insert(0, (-F::one(), rhs.as_witness()));
         cloned
     }
 }
 
-impl Expression {
+// Convenience Trait implementations, so that expressions can be built without references
+impl<F: AcirField> From<Witness> for Expression<F> {
+    fn from(witness: Witness) -> Expression<F> {
+        Expression::from(&witness)
+    }
+}
+impl<F: AcirField> From<F> for Expression<F> {
+    fn from(constant: F) -> Expression<F> {
+        Expression::from(&constant)
+    }
+}
+impl<F: AcirField> Add<Expression<F>> for Expression<F> {
+    type Output = Expression<F>;
+    fn add(self, rhs: Expression<F>) -> Expression<F> {
+        &self + &rhs
+    }
+}
+impl<F: AcirField> Add<&Expression<F>> for Expression<F> {
+    type Output = Expression<F>;
+    fn add(self, rhs: &Expression<F>) -> Expression<F> {
+        &self + rhs
+    }
+}
+impl<F: AcirField> Add<Witness> for Expression<F> {
+    type Output = Expression<F>;
+    fn add(mut self, rhs: Witness) -> Expression<F> {
+        self.term_addition(F::one(), rhs);
+        self
+    }
+}
+impl<F: AcirField> Add<F> for Expression<F> {
+    type Output = Expression<F>;
+    fn add(mut self, rhs: F) -> Expression<F> {
+        self.q_c += rhs;
+        self
+    }
+}
+impl<F: AcirField> Sub<Expression<F>> for Expression<F> {
+    type Output = Expression<F>;
+    fn sub(self, rhs: Expression<F>) -> Expression<F> {
+        &self - &rhs
+    }
+}
+impl<F: AcirField> Sub<&Expression<F>> for Expression<F> {
+    type Output = Expression<F>;
+    fn sub(self, rhs: &Expression<F>) -> Expression<F> {
+        &self - rhs
+    }
+}
+impl<F: AcirField> Sub<Witness> for Expression<F> {
+    type Output = Expression<F>;
+    fn sub(mut self, rhs: Witness) -> Expression<F> {
+        self.term_addition(-F::one(), rhs);
+        self
+    }
+}
+impl<F: AcirField> Sub<F> for Expression<F> {
+    type Output = Expression<F>;
+    fn sub(mut self, rhs: F) -> Expression<F> {
+        self.q_c -= rhs;
+        self
+    }
+}
+impl<F: AcirField> Mul<F> for Expression<F> {
+    type Output = Expression<F>;
+    fn mul(self, rhs: F) -> Expression<F> {
+        &self * &rhs
+    }
+}
+impl<F: AcirField> Neg for Expression<F> {
+    type Output = Expression<F>;
+    fn neg(self) -> Expression<F> {
+        -&self
+    }
+}
+impl Add<Witness> for Witness {
+    type Output = Expression;
+    fn add(self, rhs: Witness) -> Expression {
+        Expression::from(self) + rhs
+    }
+}
+impl<F: AcirField> Add<Expression<F>> for Witness {
+    type Output = Expression<F>;
+    fn add(self, rhs: Expression<F>) -> Expression<F> {
+        rhs + self
+    }
+}
+impl Add<FieldElement> for Witness {
+    type Output = Expression;
+    fn add(self, rhs: FieldElement) -> Expression {
+        Expression::from(self) + rhs
+    }
+}
+impl Sub<Witness> for Witness {
+    type Output = Expression;
+    fn sub(self, rhs: Witness) -> Expression {
+        Expression::from(self) - rhs
+    }
+}
+impl<F: AcirField> Sub<Expression<F>> for Witness {
+    type Output = Expression<F>;
+    fn sub(self, rhs: Expression<F>) -> Expression<F> {
+        -rhs + self
+    }
+}
+impl Sub<FieldElement> for Witness {
+    type Output = Expression;
+    fn sub(self, rhs: FieldElement) -> Expression {
+        Expression::from(self) - rhs
+    }
+}
+impl Mul<Witness> for Witness {
+    type Output = Expression;
+    fn mul(self, rhs: Witness) -> Expression {
+        Expression::mul_witnesses(FieldElement::one(), self, rhs)
+    }
+}
+impl Mul<FieldElement> for Witness {
+    type Output = Expression;
+    fn mul(self, rhs: FieldElement) -> Expression {
+        let mut expr = Expression::default();
+        expr.term_addition(rhs, self);
+        expr
+    }
+}
+impl Mul<Witness> for FieldElement {
+    type Output = Expression;
+    fn mul(self, rhs: Witness) -> Expression {
+        rhs * self
+    }
+}
+
+impl<F: AcirField> Expression<F> {
     // Checks if this polynomial can fit into one arithmetic identity
     pub fn fits_in_one_identity(&self, width: usize) -> bool {
         // A Polynomial with more than one mul term cannot fit into one gate
@@ -453,7 +676,7 @@ impl Expression {
         let mul_term = &self.mul_terms[0];
 
         // The coefficient should be non-zero, as this method is ran after the compiler removes all zero coefficient terms
-        assert_ne!(mul_term.0, FieldElement::zero());
+        assert_ne!(mul_term.0, F::zero());
 
         let mut found_x = false;
         let mut found_y = false;
@@ -502,3 +725,72 @@ fn serialisation_roundtrip() {
     let (expr, got_expr) = read_write(expr);
     assert_eq!(expr, got_expr);
 }
+
+#[test]
+fn canonicalization() {
+    let (x, y, z) = (Witness(1), Witness(2), Witness(3));
+    let two = FieldElement::from(2_i128);
+
+    // 3xy - yx + 2z + 0x - 2z + x + 5
+    let mut expr = Expression::default();
+    expr.term_multiplication(FieldElement::from(3_i128), x, y);
+    expr.term_multiplication(-FieldElement::one(), y, x);
+    expr.term_addition(two, z);
+    expr.term_addition(FieldElement::zero(), x);
+    expr.term_addition(-two, z);
+    expr.term_addition(FieldElement::one(), x);
+    expr.q_c = FieldElement::from(5_i128);
+
+    // 2xy + x + 5
+    let expected = Expression {
+        mul_terms: vec![(two, x, y)],
+        linear_combinations: vec![(FieldElement::one(), x)],
+        q_c: FieldElement::from(5_i128),
+    };
+    assert_eq!(expr.normalize(), expected);
+    assert_eq!(expr.canonicalize(), expected);
+
+    // -2xy - x - 5 = 0 is the same constraint as 2xy + x + 5 = 0
+    let negated = -&expr;
+    assert_eq!(negated.normalize(), -&expected);
+    assert_eq!(negated.canonicalize(), expected);
+}
+
+#[test]
+fn operators_build_expressions() {
+    let (x, y, z) = (Witness(1), Witness(2), Witness(3));
+    let two = FieldElement::from(2_i128);
+    let three = FieldElement::from(3_i128);
+
+    // 3xy + 2x - z + 3
+    let expr = x * y * three + x * two - z + three;
+    let expected = Expression {
+        mul_terms: vec![(three, x, y)],
+        linear_combinations: vec![(two, x), (-FieldElement::one(), z)],
+        q_c: three,
+    };
+    assert_eq!(expr.normalize(), expected);
+
+    let mut by_hand = Expression::default();
+    by_hand.term_multiplication(FieldElement::one(), x, y);
+    assert_eq!(
+        Expression::mul_witnesses(FieldElement::one(), x, y),
+        by_hand
+    );
+    assert_eq!(x * y, by_hand);
+
+    assert_eq!(
+        expected.scale(two).normalize(),
+        (expected.clone() + expected.clone()).normalize()
+    );
+    assert_eq!(
+        (expected.clone() - expected).normalize(),
+        Expression::zero()
+    );
+    assert_eq!(
+        (x + y - Expression::from(x)).normalize(),
+        Expression::from(y)
+    );
+    assert_eq!((two * z).normalize(), (z + z).normalize());
+    assert_eq!(x - three, -(Expression::from(three) - x));
+}
@@ -3,26 +3,27 @@
 #![allow(clippy::op_ref)]
 
 use crate::native_types::{Expression, Witness};
-use acir_field::FieldElement;
+use acir_field::{AcirField, FieldElement};
+use alloc::{vec, vec::Vec};
 
-use std::ops::{Add, Mul, Neg, Sub};
+use core::ops::{Add, Mul, Neg, Sub};
 
 #[derive(Clone, Copy, Debug)]
-pub struct Linear {
-    pub mul_scale: FieldElement,
+pub struct Linear<F = FieldElement> {
+    pub mul_scale: F,
     pub witness: Witness,
-    pub add_scale: FieldElement,
+    pub add_scale: F,
 }
 
-impl Linear {
+impl<F: AcirField> Linear<F> {
     pub fn is_unit(&self) -> bool {
         self.mul_scale.is_one() && self.add_scale.is_zero()
     }
-    pub fn from_witness(witness: Witness) -> Linear {
+    pub fn from_witness(witness: Witness) -> Linear<F> {
         Linear {
-            mul_scale: FieldElement::one(),
+            mul_scale: F::one(),
             witness,
-            add_scale: FieldElement::zero(),
+            add_scale: F::zero(),
         }
     }
     // XXX: This is true for the NPC languages that we use, are there any where this is not true?
@@ -31,24 +32,24 @@ impl Linear {
     }
 }
 
-impl From<Witness> for Linear {
-    fn from(w: Witness) -> Linear {
+impl<F: AcirField> From<Witness> for Linear<F> {
+    fn from(w: Witness) -> Linear<F> {
         Linear::from_witness(w)
     }
 }
-impl From<FieldElement> for Linear {
-    fn from(element: FieldElement) -> Linear {
+impl<F: AcirField> From<F> for Linear<F> {
+    fn from(element: F) -> Linear<F> {
         Linear {
             add_scale: element,
             witness: Witness::default(),
-            mul_scale: FieldElement::zero(),
+            mul_scale: F::zero(),
         }
     }
 }
 
-impl Add<&Linear> for &Linear {
-    type Output = Expression;
-    fn add(self, rhs: &Linear) -> Self::Output {
+impl<F: AcirField> Add<&Linear<F>> for &Linear<F> {
+    type Output = Expression<F>;
+    fn add(self, rhs: &Linear<F>) -> Self::Output {
         // (Ax+B) + ( Cx + D) = (Ax + Cx) + ( B+D)
         // (Ax + B) + (Cy + D) = Ax + Cy + (B+D)
         Expression {
@@ -59,8 +60,8 @@ impl Add<&Linear> for &Linear {
     }
 }
 
-impl Neg for &Linear {
-    type Output = Linear;
+impl<F: AcirField> Neg for &Linear<F> {
+    type Output = Linear<F>;
     fn neg(self) -> Self::Output {
         // -(Ax + B) = -Ax - B
         Linear {
@@ -71,10 +72,10 @@ impl Neg for &Linear {
     }
 }
 
-impl Mul<&Linear> for &Linear {
-    type Output = Expression;
+impl<F: AcirField> Mul<&Linear<F>> for &Linear<F> {
+    type Output = Expression<F>;
     #[allow(clippy::many_single_char_names)]
-    fn mul(self, rhs: &Linear) -> Self::Output {
+    fn mul(self, rhs: &Linear<F>) -> Self::Output {
         // (Ax+B)(Cy+D) = ACxy + ADx + BCy + BD
         let a = self.mul_scale;
         let b = self.add_scale;
@@ -91,7 +92,7 @@ impl Mul<&Linear> for &Linear {
 
         let mul_terms = {
             let mut mt = Vec::with_capacity(1);
-            if ac != FieldElement::zero() {
+            if ac != F::zero() {
                 mt.push((ac, x, y))
             }
             mt
@@ -100,10 +101,10 @@ impl Mul<&Linear> for &Linear {
         let linear_combinations = {
             let mut lc = Vec::with_capacity(2);
 
-            if ad != FieldElement::zero() {
+            if ad != F::zero() {
                 lc.push((ad, x));
             }
-            if bc != FieldElement::zero() {
+            if bc != F::zero() {
                 lc.push((bc, y));
             }
             lc
@@ -116,9 +117,9 @@ impl Mul<&Linear> for &Linear {
         }
     }
 }
-impl Mul<&FieldElement> for &Linear {
-    type Output = Linear;
-    fn mul(self, rhs: &FieldElement) -> Self::Output {
+impl<F: AcirField> Mul<&F> for &Linear<F> {
+    type Output = Linear<F>;
+    fn mul(self, rhs: &F) -> Self::Output {
         Linear {
             mul_scale: self.mul_scale * *rhs,
             witness: self.witness,
@@ -126,9 +127,9 @@ impl Mul<&FieldElement> for &Linear {
         }
     }
 }
-impl Add<&FieldElement> for &Linear {
-    type Output = Linear;
-    fn add(self, rhs: &FieldElement) -> Self::Output {
+impl<F: AcirField> Add<&F> for &Linear<F> {
+    type Output = Linear<F>;
+    fn add(self, rhs: &F) -> Self::Output {
         Linear {
             mul_scale: self.mul_scale,
             witness: self.witness,
@@ -138,39 +139,39 @@ impl Add<&FieldElement> for &Linear {
 }
 
 // Convenience Trait implementations
-impl Add<Linear> for Linear {
-    type Output = Expression;
-    fn add(self, rhs: Linear) -> Self::Output {
+impl<F: AcirField> Add<Linear<F>> for Linear<F> {
+    type Output = Expression<F>;
+    fn add(self, rhs: Linear<F>) -> Self::Output {
         &self + &rhs
     }
 }
-impl Mul<Linear> for Linear {
-    type Output = Expression;
-    fn mul(self, rhs: Linear) -> Self::Output {
+impl<F: AcirField> Mul<Linear<F>> for Linear<F> {
+    type Output = Expression<F>;
+    fn mul(self, rhs: Linear<F>) -> Self::Output {
         &self * &rhs
     }
 }
-impl Add<&Linear> for Linear {
-    type Output = Expression;
-    fn add(self, rhs: &Linear) -> Self::Output {
+impl<F: AcirField> Add<&Linear<F>> for Linear<F> {
+    type Output = Expression<F>;
+    fn add(self, rhs: &Linear<F>) -> Self::Output {
         &self + rhs
     }
 }
-impl Mul<&Linear> for Linear {
-    type Output = Expression;
-    fn mul(self, rhs: &Linear) -> Self::Output {
+impl<F: AcirField> Mul<&Linear<F>> for Linear<F> {
+    type Output = Expression<F>;
+    fn mul(self, rhs: &Linear<F>) -> Self::Output {
         &self * rhs
     }
 }
-impl Sub<&Linear> for &Linear {
-    type Output = Expression;
-    fn sub(self, rhs: &Linear) -> Self::Output {
+impl<F: AcirField> Sub<&Linear<F>> for &Linear<F> {
+    type Output = Expression<F>;
+    fn sub(self, rhs: &Linear<F>) -> Self::Output {
         self + &-rhs
     }
 }
-impl Sub<&FieldElement> for &Linear {
-    type Output = Linear;
-    fn sub(self, rhs: &FieldElement) -> Self::Output {
+impl<F: AcirField> Sub<&F> for &Linear<F> {
+    type Output = Linear<F>;
+    fn sub(self, rhs: &F) -> Self::Output {
         self + &-*rhs
     }
 }
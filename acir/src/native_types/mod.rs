@@ -1,7 +1,16 @@
 mod arithmetic;
 mod linear;
 mod witness;
+mod witness_diff;
+mod witness_map;
+mod witness_stack;
 
 pub use arithmetic::Expression;
 pub use linear::Linear;
 pub use witness::Witness;
+pub use witness_diff::WitnessDiff;
+pub use witness_map::{
+    MissingWitness, WitnessMap, WitnessMapIntoIter, WitnessMapIter, WitnessOutOfBounds,
+    MAX_DENSE_WITNESSES,
+};
+pub use witness_stack::{StackItem, WitnessStack};
@@ -1,5 +1,9 @@
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
 use std::io::Read;
 
+#[cfg(feature = "std")]
 use flate2::{
     bufread::{DeflateDecoder, DeflateEncoder},
     Compression,
@@ -31,8 +35,10 @@ impl Witness {
         UnknownWitness(self.0)
     }
 
+    #[cfg(feature = "std")]
+    #[deprecated(note = "use `WitnessMap::to_bytes`, whose format is documented and stable")]
     pub fn to_bytes(
-        witnesses: &std::collections::BTreeMap<Witness, acir_field::FieldElement>,
+        witnesses: &alloc::collections::BTreeMap<Witness, acir_field::FieldElement>,
     ) -> Vec<u8> {
         let buf = rmp_serde::to_vec(witnesses).unwrap();
         let mut deflater = DeflateEncoder::new(buf.as_slice(), Compression::best());
@@ -41,9 +47,11 @@ impl Witness {
         buf_c
     }
 
+    #[cfg(feature = "std")]
+    #[deprecated(note = "use `WitnessMap::from_bytes`, which returns an error for malformed bytes")]
     pub fn from_bytes(
         bytes: &[u8],
-    ) -> std::collections::BTreeMap<Witness, acir_field::FieldElement> {
+    ) -> alloc::collections::BTreeMap<Witness, acir_field::FieldElement> {
         let mut deflater = DeflateDecoder::new(bytes);
         let mut buf_d = Vec::new();
         deflater.read_to_end(&mut buf_d).unwrap();
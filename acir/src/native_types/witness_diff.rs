@@ -0,0 +1,215 @@
+use alloc::{
+    borrow::ToOwned,
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{cmp::Ordering, iter::Peekable};
+
+use acir_field::{AcirField, FieldElement};
+use serde::{Deserialize, Serialize};
+
+use crate::circuit::{Circuit, Opcode};
+
+use super::{Witness, WitnessMap, WitnessMapIter};
+
+/// A witness which is assigned differently in two witness maps
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WitnessDiff<F = FieldElement> {
+    pub witness: Witness,
+    /// The value in the first map, if the witness is assigned in it
+    pub left: Option<F>,
+    /// The value in the second map, if the witness is assigned in it
+    pub right: Option<F>,
+    /// The indices of the opcodes which can assign the witness. This is only filled in by
+    /// `WitnessMap::diff_in_circuit`.
+    pub opcodes: Vec<usize>,
+}
+
+impl<F: AcirField> core::fmt::Display for WitnessDiff<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let value = |value: &Option<F>| match value {
+            Some(value) => value.to_string(),
+            None => "unassigned".to_owned(),
+        };
+        write!(
+            f,
+            "_{}: {} != {}",
+            self.witness.witness_index(),
+            value(&self.left),
+            value(&self.right)
+        )?;
+        if !self.opcodes.is_empty() {
+            let opcodes: Vec<String> = self.opcodes.iter().map(ToString::to_string).collect();
+            write!(f, " (assigned by opcodes {})", opcodes.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: AcirField> WitnessMap<F> {
+    /// Returns the witnesses which are assigned in only one of the maps, or which have
+    /// different values, in order of witness index.
+    pub fn diff(&self, other: &WitnessMap<F>) -> Vec<WitnessDiff<F>> {
+        let mut diffs = Vec::new();
+        let mut left = self.iter().peekable();
+        let mut right = other.iter().peekable();
+        loop {
+            let diff = match (left.peek(), right.peek()) {
+                (None, None) => break,
+                (Some(_), None) => only_left(&mut left),
+                (None, Some(_)) => only_right(&mut right),
+                (Some((l, _)), Some((r, _))) => match l.cmp(r) {
+                    Ordering::Less => only_left(&mut left),
+                    Ordering::Greater => only_right(&mut right),
+                    Ordering::Equal => {
+                        let (witness, l) = left.next().expect("peeked");
+                        let (_, r) = right.next().expect("peeked");
+                        (l != r).then(|| WitnessDiff {
+                            witness: *witness,
+                            left: Some(*l),
+                            right: Some(*r),
+                            opcodes: Vec::new(),
+                        })
+                    }
+                },
+            };
+            diffs.extend(diff);
+        }
+        diffs
+    }
+
+    /// Returns the differences as `diff` does, annotated with the opcodes of `circuit`
+    /// which can assign each witness: the black box functions, directives and calls which
+    /// output it, and the arithmetic opcodes which contain it.
+    pub fn diff_in_circuit(
+        &self,
+        other: &WitnessMap<F>,
+        circuit: &Circuit<F>,
+    ) -> Vec<WitnessDiff<F>> {
+        let mut diffs = self.diff(other);
+        let mut opcodes_by_witness: BTreeMap<Witness, Vec<usize>> = diffs
+            .iter()
+            .map(|diff| (diff.witness, Vec::new()))
+            .collect();
+        for (index, opcode) in circuit.opcodes.iter().enumerate() {
+            let assigned: Vec<Witness> = match opcode {
+                Opcode::Arithmetic(expr) => expr.witnesses().into_iter().collect(),
+                Opcode::BlackBoxFuncCall(func_call) => func_call.outputs.clone(),
+                Opcode::Directive(directive) => directive.outputs(),
+                Opcode::Call { outputs, .. } => outputs.clone(),
+            };
+            for witness in assigned {
+                if let Some(opcodes) = opcodes_by_witness.get_mut(&witness) {
+                    opcodes.push(index);
+                }
+            }
+        }
+        for diff in &mut diffs {
+            diff.opcodes = opcodes_by_witness.remove(&diff.witness).unwrap_or_default();
+        }
+        diffs
+    }
+}
+
+fn only_left<F: AcirField>(left: &mut Peekable<WitnessMapIter<'_, F>>) -> Option<WitnessDiff<F>> {
+    let (witness, value) = left.next()?;
+    Some(WitnessDiff {
+        witness: *witness,
+        left: Some(*value),
+        right: None,
+        opcodes: Vec::new(),
+    })
+}
+
+fn only_right<F: AcirField>(right: &mut Peekable<WitnessMapIter<'_, F>>) -> Option<WitnessDiff<F>> {
+    let (witness, value) = right.next()?;
+    Some(WitnessDiff {
+        witness: *witness,
+        left: None,
+        right: Some(*value),
+        opcodes: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        circuit::{directives::Directive, PublicInputs},
+        native_types::Expression,
+    };
+
+    #[test]
+    fn diff_reports_missing_and_mismatched_witnesses() {
+        let left = WitnessMap::from([
+            (Witness(1), FieldElement::from(1_i128)),
+            (Witness(2), FieldElement::from(2_i128)),
+            (Witness(3), FieldElement::from(3_i128)),
+        ]);
+        let right = WitnessMap::from([
+            (Witness(2), FieldElement::from(2_i128)),
+            (Witness(3), FieldElement::from(4_i128)),
+            (Witness(5), FieldElement::from(5_i128)),
+        ])
+        .into_dense(6);
+
+        let diffs = left.diff(&right);
+        let summary: Vec<_> = diffs
+            .iter()
+            .map(|diff| (diff.witness, diff.left, diff.right))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Witness(1), Some(FieldElement::from(1_i128)), None),
+                (
+                    Witness(3),
+                    Some(FieldElement::from(3_i128)),
+                    Some(FieldElement::from(4_i128))
+                ),
+                (Witness(5), None, Some(FieldElement::from(5_i128))),
+            ]
+        );
+        assert!(left.diff(&left).is_empty());
+        assert_eq!(diffs[2].to_string(), "_5: unassigned != 5");
+    }
+
+    #[test]
+    fn diff_in_circuit_names_the_assigning_opcodes() {
+        // _2 = 1 / _1, _3 = _2 + _1
+        let circuit = Circuit {
+            current_witness_index: 3,
+            opcodes: vec![
+                Opcode::Directive(Directive::Invert {
+                    x: Witness(1),
+                    result: Witness(2),
+                }),
+                Opcode::Arithmetic(
+                    &(&Expression::from(&Witness(2)) + &Expression::from(&Witness(1)))
+                        - &Witness(3),
+                ),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        };
+        let left = WitnessMap::from([
+            (Witness(1), FieldElement::from(2_i128)),
+            (Witness(2), FieldElement::from(3_i128)),
+        ]);
+        let right = WitnessMap::from([
+            (Witness(1), FieldElement::from(2_i128)),
+            (Witness(2), FieldElement::from(5_i128)),
+            (Witness(3), FieldElement::from(7_i128)),
+        ]);
+
+        let diffs = left.diff_in_circuit(&right, &circuit);
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].opcodes, vec![0, 1]);
+        assert_eq!(diffs[1].opcodes, vec![1]);
+        assert_eq!(
+            diffs[1].to_string(),
+            "_3: unassigned != 7 (assigned by opcodes 1)"
+        );
+    }
+}
@@ -0,0 +1,582 @@
+use alloc::{
+    collections::{btree_map, BTreeMap},
+    vec,
+    vec::Vec,
+};
+use core::{iter::Flatten, ops::Index, slice};
+#[cfg(feature = "std")]
+use std::io::Read;
+
+use acir_field::{AcirField, FieldElement};
+#[cfg(feature = "std")]
+use flate2::{
+    bufread::{DeflateDecoder, DeflateEncoder},
+    Compression,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::circuit::Circuit;
+#[cfg(feature = "std")]
+use crate::serialisation::read_u32;
+use crate::serialisation::{
+    io::{self, Write},
+    write_bytes, write_u32,
+};
+
+use super::Witness;
+
+const VERSION_NUMBER: u32 = 0;
+
+/// The number of witnesses above which a witness map is stored sparsely even if it was
+/// made dense, as the number of witnesses of a circuit may come from untrusted input and
+/// a vector with a slot for each of them could exhaust memory
+pub const MAX_DENSE_WITNESSES: u32 = 1 << 22;
+
+/// The values assigned to the witnesses of a circuit
+///
+/// A witness map is sparse by default, and can be made dense when the number of witnesses
+/// in the circuit is known, which makes lookups and assignments much faster for large
+/// circuits. Both representations behave identically, and witness maps with the same
+/// assignments are equal whichever representation they use.
+#[derive(Clone, Debug)]
+pub struct WitnessMap<F = FieldElement>(Storage<F>);
+
+#[derive(Clone, Debug)]
+enum Storage<F> {
+    Sparse(BTreeMap<Witness, F>),
+    // Indexed by witness index, along with the number of assignments. The witness is
+    // stored alongside its value so that entries can be borrowed as in a `BTreeMap`.
+    Dense(Vec<Option<(Witness, F)>>, usize),
+}
+
+impl<F> WitnessMap<F> {
+    pub fn new() -> Self {
+        WitnessMap(Storage::Sparse(BTreeMap::new()))
+    }
+
+    pub fn is_dense(&self) -> bool {
+        matches!(self.0, Storage::Dense(..))
+    }
+
+    pub fn get(&self, witness: &Witness) -> Option<&F> {
+        match &self.0 {
+            Storage::Sparse(map) => map.get(witness),
+            Storage::Dense(slots, _) => match slots.get(witness.as_usize()) {
+                Some(Some((_, value))) => Some(value),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn contains_key(&self, witness: &Witness) -> bool {
+        self.get(witness).is_some()
+    }
+
+    /// Returns the number of witnesses which are assigned
+    pub fn len(&self) -> usize {
+        match &self.0 {
+            Storage::Sparse(map) => map.len(),
+            Storage::Dense(_, len) => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over the assignments in ascending witness order
+    pub fn iter(&self) -> WitnessMapIter<'_, F> {
+        match &self.0 {
+            Storage::Sparse(map) => WitnessMapIter(IterInner::Sparse(map.iter())),
+            Storage::Dense(slots, _) => WitnessMapIter(IterInner::Dense(slots.iter())),
+        }
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &Witness> {
+        self.iter().map(|(witness, _)| witness)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &F> {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+impl<F: AcirField> WitnessMap<F> {
+    /// Returns an empty witness map which is backed by a vector with a slot for each
+    /// witness index below `num_witnesses`. Witnesses outside of this range can still be
+    /// assigned, and grow the vector.
+    ///
+    /// Witness maps of more than `MAX_DENSE_WITNESSES` witnesses are sparse, and a dense
+    /// witness map becomes sparse when a witness at or above that index is assigned.
+    pub fn dense(num_witnesses: u32) -> Self {
+        if num_witnesses > MAX_DENSE_WITNESSES {
+            return WitnessMap::new();
+        }
+        WitnessMap(Storage::Dense(vec![None; num_witnesses as usize], 0))
+    }
+
+    /// Moves the assignments into a dense witness map, see `WitnessMap::dense`.
+    /// Witness maps which are already dense are returned unchanged.
+    pub fn into_dense(self, num_witnesses: u32) -> Self {
+        if self.is_dense() {
+            return self;
+        }
+        let mut dense = WitnessMap::dense(num_witnesses);
+        dense.extend(self);
+        dense
+    }
+
+    /// Assigns a value to a witness, returning its previous value
+    pub fn insert(&mut self, witness: Witness, value: F) -> Option<F> {
+        match &mut self.0 {
+            Storage::Sparse(map) => map.insert(witness, value),
+            Storage::Dense(slots, _) if witness.0 >= MAX_DENSE_WITNESSES => {
+                let map = core::mem::take(slots).into_iter().flatten().collect();
+                self.0 = Storage::Sparse(map);
+                self.insert(witness, value)
+            }
+            Storage::Dense(slots, len) => {
+                let index = witness.as_usize();
+                if index >= slots.len() {
+                    slots.resize(index + 1, None);
+                }
+                let previous = slots[index].replace((witness, value));
+                if previous.is_none() {
+                    *len += 1;
+                }
+                previous.map(|(_, value)| value)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, witness: &Witness) -> Option<F> {
+        match &mut self.0 {
+            Storage::Sparse(map) => map.remove(witness),
+            Storage::Dense(slots, len) => {
+                let previous = slots.get_mut(witness.as_usize())?.take();
+                if previous.is_some() {
+                    *len -= 1;
+                }
+                previous.map(|(_, value)| value)
+            }
+        }
+    }
+
+    /// Writes the witness map in its binary format, which is:
+    ///
+    /// - the version number, as a `u32`
+    /// - the number of assignments, as a `u32`
+    /// - each assignment in ascending witness order, as the witness index as a `u32`
+    ///   followed by the value as `F::max_num_bytes` big-endian bytes
+    ///
+    /// Integers are little-endian, as in the rest of ACIR's serialisation.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_u32(&mut writer, VERSION_NUMBER)?;
+        write_u32(&mut writer, self.len() as u32)?;
+        for (witness, value) in self {
+            write_u32(&mut writer, witness.0)?;
+            write_bytes(&mut writer, &value.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        if read_u32(&mut reader)? != VERSION_NUMBER {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+
+        let num_assignments = read_u32(&mut reader)?;
+        let mut witness_map = WitnessMap::new();
+        for _ in 0..num_assignments {
+            let witness = Witness(read_u32(&mut reader)?);
+            // Values must be less than the modulus, so that garbage is not silently reduced
+            let mut bytes = vec![0; F::max_num_bytes() as usize];
+            reader.read_exact(&mut bytes)?;
+            let value = F::try_from_be_bytes(&bytes).ok_or(std::io::ErrorKind::InvalidData)?;
+            // Witnesses must be strictly ascending, so that each map has one encoding
+            if witness_map.keys().next_back() >= Some(&witness) {
+                return Err(std::io::ErrorKind::InvalidData.into());
+            }
+            witness_map.insert(witness, value);
+        }
+        Ok(witness_map)
+    }
+
+    /// Returns the binary format written by `write`, compressed with DEFLATE
+    #[cfg(feature = "std")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write(&mut buf)
+            .expect("infallible: writing to a vector cannot fail");
+        compress(&buf)
+    }
+
+    /// Reads a witness map written by `to_bytes`
+    #[cfg(feature = "std")]
+    pub fn from_bytes(bytes: &[u8]) -> std::io::Result<Self> {
+        read_compressed(bytes, |reader| WitnessMap::read(reader))
+    }
+
+    /// Checks that every assigned witness belongs to the circuit, returning the first
+    /// witness which is above its `current_witness_index`
+    pub fn validate(&self, circuit: &Circuit<F>) -> Result<(), WitnessOutOfBounds> {
+        let current_witness_index = circuit.current_witness_index;
+        // Witnesses are iterated in ascending order, so the out of bounds witnesses are last
+        let first_out_of_bounds = self
+            .keys()
+            .rev()
+            .take_while(|witness| witness.0 > current_witness_index)
+            .last();
+        match first_out_of_bounds {
+            Some(witness) => Err(WitnessOutOfBounds {
+                witness: *witness,
+                current_witness_index,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A witness which is assigned in a witness map, but is not part of the circuit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WitnessOutOfBounds {
+    pub witness: Witness,
+    pub current_witness_index: u32,
+}
+
+impl core::fmt::Display for WitnessOutOfBounds {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "witness {} is assigned, but the circuit's current witness index is {}",
+            self.witness.0, self.current_witness_index
+        )
+    }
+}
+
+impl core::error::Error for WitnessOutOfBounds {}
+
+/// A witness which has no assignment in a witness map, but whose value is needed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MissingWitness(pub Witness);
+
+impl core::fmt::Display for MissingWitness {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "witness {} is not assigned", self.0 .0)
+    }
+}
+
+impl core::error::Error for MissingWitness {}
+
+#[cfg(feature = "std")]
+pub(super) fn compress(buf: &[u8]) -> Vec<u8> {
+    let mut deflater = DeflateEncoder::new(buf, Compression::best());
+    let mut compressed = Vec::new();
+    deflater
+        .read_to_end(&mut compressed)
+        .expect("infallible: compressing a slice cannot fail");
+    compressed
+}
+
+// Decompresses `bytes` and reads them with `read`, which must consume all of them
+#[cfg(feature = "std")]
+pub(super) fn read_compressed<T>(
+    bytes: &[u8],
+    read: impl FnOnce(&mut &[u8]) -> std::io::Result<T>,
+) -> std::io::Result<T> {
+    let mut buf = Vec::new();
+    DeflateDecoder::new(bytes).read_to_end(&mut buf)?;
+
+    let mut reader = buf.as_slice();
+    let value = read(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(std::io::ErrorKind::InvalidData.into());
+    }
+    Ok(value)
+}
+
+impl<F> Default for WitnessMap<F> {
+    fn default() -> Self {
+        WitnessMap::new()
+    }
+}
+
+impl<F: PartialEq> PartialEq for WitnessMap<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<F: Eq> Eq for WitnessMap<F> {}
+
+impl<F> Index<&Witness> for WitnessMap<F> {
+    type Output = F;
+
+    fn index(&self, witness: &Witness) -> &Self::Output {
+        self.get(witness).expect("witness is not assigned")
+    }
+}
+
+impl<F: Serialize> Serialize for WitnessMap<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de, F: Deserialize<'de>> Deserialize<'de> for WitnessMap<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        BTreeMap::deserialize(deserializer).map(WitnessMap::from)
+    }
+}
+
+impl<F> From<BTreeMap<Witness, F>> for WitnessMap<F> {
+    fn from(map: BTreeMap<Witness, F>) -> Self {
+        WitnessMap(Storage::Sparse(map))
+    }
+}
+
+impl<F> From<WitnessMap<F>> for BTreeMap<Witness, F> {
+    fn from(map: WitnessMap<F>) -> Self {
+        match map.0 {
+            Storage::Sparse(map) => map,
+            Storage::Dense(..) => map.into_iter().collect(),
+        }
+    }
+}
+
+impl<F, const N: usize> From<[(Witness, F); N]> for WitnessMap<F> {
+    fn from(assignments: [(Witness, F); N]) -> Self {
+        WitnessMap::from(BTreeMap::from(assignments))
+    }
+}
+
+impl<F: AcirField> FromIterator<(Witness, F)> for WitnessMap<F> {
+    fn from_iter<I: IntoIterator<Item = (Witness, F)>>(iter: I) -> Self {
+        WitnessMap::from(iter.into_iter().collect::<BTreeMap<_, _>>())
+    }
+}
+
+impl<F: AcirField> Extend<(Witness, F)> for WitnessMap<F> {
+    fn extend<I: IntoIterator<Item = (Witness, F)>>(&mut self, iter: I) {
+        for (witness, value) in iter {
+            self.insert(witness, value);
+        }
+    }
+}
+
+/// An iterator over the assignments of a `WitnessMap`, in ascending witness order
+pub struct WitnessMapIter<'a, F = FieldElement>(IterInner<'a, F>);
+
+enum IterInner<'a, F> {
+    Sparse(btree_map::Iter<'a, Witness, F>),
+    Dense(slice::Iter<'a, Option<(Witness, F)>>),
+}
+
+impl<'a, F> Iterator for WitnessMapIter<'a, F> {
+    type Item = (&'a Witness, &'a F);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IterInner::Sparse(iter) => iter.next(),
+            IterInner::Dense(iter) => iter.find_map(|slot| slot.as_ref().map(|(w, v)| (w, v))),
+        }
+    }
+}
+
+impl<'a, F> DoubleEndedIterator for WitnessMapIter<'a, F> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IterInner::Sparse(iter) => iter.next_back(),
+            IterInner::Dense(iter) => iter
+                .rev()
+                .find_map(|slot| slot.as_ref().map(|(w, v)| (w, v))),
+        }
+    }
+}
+
+/// An owning iterator over the assignments of a `WitnessMap`, in ascending witness order
+pub struct WitnessMapIntoIter<F = FieldElement>(IntoIterInner<F>);
+
+enum IntoIterInner<F> {
+    Sparse(btree_map::IntoIter<Witness, F>),
+    Dense(Flatten<vec::IntoIter<Option<(Witness, F)>>>),
+}
+
+impl<F> Iterator for WitnessMapIntoIter<F> {
+    type Item = (Witness, F);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IntoIterInner::Sparse(iter) => iter.next(),
+            IntoIterInner::Dense(iter) => iter.next(),
+        }
+    }
+}
+
+impl<F> IntoIterator for WitnessMap<F> {
+    type Item = (Witness, F);
+    type IntoIter = WitnessMapIntoIter<F>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self.0 {
+            Storage::Sparse(map) => WitnessMapIntoIter(IntoIterInner::Sparse(map.into_iter())),
+            Storage::Dense(slots, _) => {
+                WitnessMapIntoIter(IntoIterInner::Dense(slots.into_iter().flatten()))
+            }
+        }
+    }
+}
+
+impl<'a, F> IntoIterator for &'a WitnessMap<F> {
+    type Item = (&'a Witness, &'a F);
+    type IntoIter = WitnessMapIter<'a, F>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn witness_map() -> WitnessMap {
+        WitnessMap::from([
+            (Witness(1), FieldElement::from(7_i128)),
+            (Witness(4), -FieldElement::one()),
+            (Witness(9), FieldElement::zero()),
+        ])
+    }
+
+    #[test]
+    fn binary_roundtrip() {
+        let witness_map = witness_map();
+        let bytes = witness_map.to_bytes();
+        assert_eq!(WitnessMap::from_bytes(&bytes).unwrap(), witness_map);
+
+        let mut uncompressed = Vec::new();
+        witness_map.write(&mut uncompressed).unwrap();
+        assert_eq!(
+            uncompressed.len(),
+            4 + 4 + 3 * (4 + FieldElement::max_num_bytes() as usize)
+        );
+        assert_eq!(WitnessMap::read(&*uncompressed).unwrap(), witness_map);
+    }
+
+    #[test]
+    fn malformed_bytes_are_rejected() {
+        assert!(WitnessMap::<FieldElement>::from_bytes(&[0xff, 0x00, 0x12]).is_err());
+
+        // Assignments which are out of order
+        let mut bytes = Vec::new();
+        write_u32(&mut bytes, VERSION_NUMBER).unwrap();
+        write_u32(&mut bytes, 2).unwrap();
+        for index in [2, 1] {
+            write_u32(&mut bytes, index).unwrap();
+            write_bytes(&mut bytes, &FieldElement::one().to_be_bytes()).unwrap();
+        }
+        assert!(WitnessMap::<FieldElement>::read(&*bytes).is_err());
+
+        // Values which are not reduced modulo the field's modulus
+        let modulus = FieldElement::modulus().to_bytes_be();
+        let mut value = vec![0; FieldElement::max_num_bytes() as usize - modulus.len()];
+        value.extend(modulus);
+        let mut bytes = Vec::new();
+        write_u32(&mut bytes, VERSION_NUMBER).unwrap();
+        write_u32(&mut bytes, 1).unwrap();
+        write_u32(&mut bytes, 1).unwrap();
+        write_bytes(&mut bytes, &value).unwrap();
+        assert!(WitnessMap::<FieldElement>::read(&*bytes).is_err());
+    }
+
+    #[test]
+    fn assignments_must_belong_to_the_circuit() {
+        let circuit = |current_witness_index| Circuit {
+            current_witness_index,
+            opcodes: Vec::new(),
+            public_inputs: crate::circuit::PublicInputs::default(),
+            return_values: crate::circuit::PublicInputs::default(),
+        };
+        assert_eq!(witness_map().validate(&circuit(9)), Ok(()));
+        assert_eq!(WitnessMap::new().validate(&circuit(0)), Ok(()));
+        assert_eq!(
+            witness_map().validate(&circuit(3)),
+            Err(WitnessOutOfBounds {
+                witness: Witness(4),
+                current_witness_index: 3
+            })
+        );
+    }
+
+    #[test]
+    fn dense_storage_behaves_like_sparse_storage() {
+        let sparse = witness_map();
+        let mut dense = sparse.clone().into_dense(5);
+        assert!(dense.is_dense() && !sparse.is_dense());
+        assert_eq!(dense, sparse);
+        assert_eq!(dense.len(), 3);
+        assert_eq!(dense.get(&Witness(4)), Some(&-FieldElement::one()));
+        assert_eq!(dense.get(&Witness(2)), None);
+        assert_eq!(dense.get(&Witness(100)), None);
+        assert_eq!(
+            dense.iter().collect::<Vec<_>>(),
+            sparse.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(dense.keys().next_back(), Some(&Witness(9)));
+        assert_eq!(dense.to_bytes(), sparse.to_bytes());
+
+        // Assigning beyond the initial number of witnesses grows the storage
+        assert_eq!(dense.insert(Witness(12), FieldElement::one()), None);
+        assert_eq!(
+            dense.insert(Witness(1), FieldElement::one()),
+            Some(FieldElement::from(7_i128))
+        );
+        assert_eq!(dense.remove(&Witness(9)), Some(FieldElement::zero()));
+        assert_eq!(dense.remove(&Witness(9)), None);
+        assert_eq!(dense.len(), 3);
+        assert_eq!(
+            BTreeMap::from(dense),
+            BTreeMap::from([
+                (Witness(1), FieldElement::one()),
+                (Witness(4), -FieldElement::one()),
+                (Witness(12), FieldElement::one()),
+            ])
+        );
+    }
+
+    #[test]
+    fn large_witness_maps_are_sparse() {
+        assert!(!WitnessMap::<FieldElement>::dense(u32::MAX).is_dense());
+        assert!(!witness_map().into_dense(u32::MAX).is_dense());
+
+        // Assigning a witness beyond the bound keeps the existing assignments
+        let mut witness_map = witness_map().into_dense(5);
+        assert!(witness_map.is_dense());
+        assert_eq!(
+            witness_map.insert(Witness(u32::MAX), FieldElement::one()),
+            None
+        );
+        assert!(!witness_map.is_dense());
+        assert_eq!(witness_map.len(), 4);
+        assert_eq!(witness_map.get(&Witness(4)), Some(&-FieldElement::one()));
+        assert_eq!(
+            witness_map.get(&Witness(u32::MAX)),
+            Some(&FieldElement::one())
+        );
+    }
+
+    #[test]
+    fn serde_matches_the_underlying_map() {
+        let witness_map = witness_map();
+        let json = serde_json::to_string(&witness_map).unwrap();
+        assert_eq!(
+            json,
+            serde_json::to_string(&BTreeMap::from(witness_map.clone())).unwrap()
+        );
+        assert_eq!(
+            serde_json::from_str::<WitnessMap>(&json).unwrap(),
+            witness_map
+        );
+    }
+}
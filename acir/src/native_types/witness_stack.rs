@@ -0,0 +1,169 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::Read;
+
+use acir_field::{AcirField, FieldElement};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "std")]
+use crate::serialisation::read_u32;
+use crate::serialisation::{
+    io::{self, Write},
+    write_u32,
+};
+
+#[cfg(feature = "std")]
+use super::witness_map::{compress, read_compressed};
+use super::WitnessMap;
+
+const VERSION_NUMBER: u32 = 0;
+
+/// The witness of one call to a function of a `Program`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StackItem<F = FieldElement> {
+    /// The index of the function in the program
+    pub index: u32,
+    /// The values assigned to the witnesses of the function during the call
+    pub witness: WitnessMap<F>,
+}
+
+/// The witnesses of every function call made while solving a program.
+///
+/// Items are pushed as calls complete, so a callee comes before its caller and
+/// the entry point of the program is the last item.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WitnessStack<F = FieldElement> {
+    stack: Vec<StackItem<F>>,
+}
+
+impl<F: AcirField> WitnessStack<F> {
+    pub fn new() -> Self {
+        WitnessStack { stack: Vec::new() }
+    }
+
+    pub fn push(&mut self, index: u32, witness: WitnessMap<F>) {
+        self.stack.push(StackItem { index, witness });
+    }
+
+    pub fn pop(&mut self) -> Option<StackItem<F>> {
+        self.stack.pop()
+    }
+
+    /// Returns the most recently completed call, which is the entry point once
+    /// a program has been solved
+    pub fn peek(&self) -> Option<&StackItem<F>> {
+        self.stack.last()
+    }
+
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Removes the items of every call after the first `len`
+    pub fn truncate(&mut self, len: usize) {
+        self.stack.truncate(len);
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, StackItem<F>> {
+        self.stack.iter()
+    }
+
+    /// Writes the witness stack in its binary format, which is:
+    ///
+    /// - the version number, as a `u32`
+    /// - the number of items, as a `u32`
+    /// - each item from the bottom of the stack, as the function index as a `u32`
+    ///   followed by its witness in the format of `WitnessMap::write`
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_u32(&mut writer, VERSION_NUMBER)?;
+        write_u32(&mut writer, self.stack.len() as u32)?;
+        for item in &self.stack {
+            write_u32(&mut writer, item.index)?;
+            item.witness.write(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        if read_u32(&mut reader)? != VERSION_NUMBER {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+
+        let num_items = read_u32(&mut reader)?;
+        let mut stack = WitnessStack::new();
+        for _ in 0..num_items {
+            let index = read_u32(&mut reader)?;
+            let witness = WitnessMap::read(&mut reader)?;
+            stack.push(index, witness);
+        }
+        Ok(stack)
+    }
+
+    /// Returns the binary format written by `write`, compressed with DEFLATE
+    #[cfg(feature = "std")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write(&mut buf)
+            .expect("infallible: writing to a vector cannot fail");
+        compress(&buf)
+    }
+
+    /// Reads a witness stack written by `to_bytes`
+    #[cfg(feature = "std")]
+    pub fn from_bytes(bytes: &[u8]) -> std::io::Result<Self> {
+        read_compressed(bytes, |reader| WitnessStack::read(reader))
+    }
+}
+
+impl<F: AcirField> Default for WitnessStack<F> {
+    fn default() -> Self {
+        WitnessStack::new()
+    }
+}
+
+/// The witness stack of a program with a single function
+impl<F: AcirField> From<WitnessMap<F>> for WitnessStack<F> {
+    fn from(witness: WitnessMap<F>) -> Self {
+        let mut stack = WitnessStack::new();
+        stack.push(0, witness);
+        stack
+    }
+}
+
+impl<'a, F> IntoIterator for &'a WitnessStack<F> {
+    type Item = &'a StackItem<F>;
+    type IntoIter = core::slice::Iter<'a, StackItem<F>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.stack.iter()
+    }
+}
+
+#[test]
+fn binary_roundtrip() {
+    use super::Witness;
+
+    let mut stack = WitnessStack::new();
+    stack.push(
+        1,
+        WitnessMap::from([(Witness(1), FieldElement::from(2_i128))]),
+    );
+    stack.push(
+        0,
+        WitnessMap::from([
+            (Witness(1), FieldElement::from(3_i128)),
+            (Witness(2), FieldElement::from(2_i128)),
+        ]),
+    );
+
+    let bytes = stack.to_bytes();
+    assert_eq!(WitnessStack::from_bytes(&bytes).unwrap(), stack);
+    assert_eq!(stack.peek().map(|item| item.index), Some(0));
+
+    assert!(WitnessStack::<FieldElement>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+}
@@ -1,7 +1,53 @@
-use std::io::{Read, Write};
+#[cfg(feature = "std")]
+use std::io::Read;
 
-use acir_field::FieldElement;
+#[cfg(feature = "std")]
+use acir_field::{AcirField, FieldElement};
 
+use self::io::Write;
+
+/// The parts of `std::io` which the binary format uses.
+///
+/// Without the `std` feature, values can still be written, to a `Vec<u8>` or any other
+/// `Write`, so that they can be hashed, but they cannot be read.
+pub mod io {
+    #[cfg(feature = "std")]
+    pub use std::io::{Result, Write};
+
+    #[cfg(not(feature = "std"))]
+    pub use self::core_io::{Error, Result, Write};
+
+    #[cfg(not(feature = "std"))]
+    mod core_io {
+        use alloc::vec::Vec;
+
+        /// An error from a writer. Writing to a `Vec<u8>` never fails.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct Error;
+
+        pub type Result<T> = core::result::Result<T, Error>;
+
+        /// A sink for bytes, as `std::io::Write` is
+        pub trait Write {
+            fn write_all(&mut self, buf: &[u8]) -> Result<()>;
+        }
+
+        impl Write for Vec<u8> {
+            fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+                self.extend_from_slice(buf);
+                Ok(())
+            }
+        }
+
+        impl<W: Write + ?Sized> Write for &mut W {
+            fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+                (**self).write_all(buf)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 pub fn read_n<const NUM_BYTES: usize, R: Read>(mut r: R) -> std::io::Result<[u8; NUM_BYTES]> {
     let mut bytes = [0u8; NUM_BYTES];
     r.read_exact(&mut bytes[..])?;
@@ -10,42 +56,49 @@ pub fn read_n<const NUM_BYTES: usize, R: Read>(mut r: R) -> std::io::Result<[u8;
 pub fn write_n<const NUM_BYTES: usize, W: Write>(
     w: W,
     bytes: [u8; NUM_BYTES],
-) -> std::io::Result<usize> {
+) -> io::Result<usize> {
     write_bytes(w, &bytes)
 }
-pub fn write_bytes<W: Write>(mut w: W, bytes: &[u8]) -> std::io::Result<usize> {
-    w.write(bytes)
+pub fn write_bytes<W: Write>(mut w: W, bytes: &[u8]) -> io::Result<usize> {
+    w.write_all(bytes)?;
+    Ok(bytes.len())
 }
 
-pub fn write_u16<W: Write>(w: W, num: u16) -> std::io::Result<usize> {
+pub fn write_u16<W: Write>(w: W, num: u16) -> io::Result<usize> {
     let bytes = num.to_le_bytes();
     write_n::<2, _>(w, bytes)
 }
-pub fn write_u32<W: Write>(w: W, num: u32) -> std::io::Result<usize> {
+pub fn write_u32<W: Write>(w: W, num: u32) -> io::Result<usize> {
     let bytes = num.to_le_bytes();
     write_n::<4, _>(w, bytes)
 }
 
+#[cfg(feature = "std")]
 pub fn read_u16<R: Read>(r: R) -> std::io::Result<u16> {
     const NUM_BYTES: usize = 2;
     let bytes = read_n::<NUM_BYTES, _>(r)?;
     Ok(u16::from_le_bytes(bytes))
 }
+#[cfg(feature = "std")]
 pub fn read_u32<R: Read>(r: R) -> std::io::Result<u32> {
     const NUM_BYTES: usize = 4;
     let bytes = read_n::<NUM_BYTES, _>(r)?;
     Ok(u32::from_le_bytes(bytes))
 }
-pub fn read_field_element<const NUM_BYTES: usize, R: Read>(
-    mut r: R,
-) -> std::io::Result<FieldElement> {
-    const FIELD_ELEMENT_NUM_BYTES: usize = FieldElement::max_num_bytes() as usize;
+#[cfg(feature = "std")]
+pub fn read_field_element<const NUM_BYTES: usize, R: Read>(r: R) -> std::io::Result<FieldElement> {
+    read_field(r)
+}
 
-    let bytes = read_n::<FIELD_ELEMENT_NUM_BYTES, _>(&mut r)?;
+/// Reads a field element of any field, as `F::max_num_bytes` big-endian bytes
+#[cfg(feature = "std")]
+pub fn read_field<F: AcirField, R: Read>(mut r: R) -> std::io::Result<F> {
+    let mut bytes = vec![0; F::max_num_bytes() as usize];
+    r.read_exact(&mut bytes)?;
 
     // TODO: We should not reduce here, we want the serialisation to be
     // TODO canonical
-    let field_element = FieldElement::from_be_bytes_reduce(&bytes);
+    let field_element = F::from_be_bytes_reduce(&bytes);
 
     Ok(field_element)
 }
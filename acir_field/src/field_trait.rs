@@ -0,0 +1,82 @@
+use alloc::{string::String, vec::Vec};
+use core::{
+    fmt::{Debug, Display},
+    hash::Hash,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
+};
+
+use num_bigint::BigUint;
+
+/// The operations which ACVM needs from the field that circuits are defined over.
+///
+/// `FieldElement` implements this for the field chosen by the crate features. Code which
+/// only needs these operations can be written against this trait, so that it does not
+/// depend on which curve's scalar field is chosen.
+///
+/// Circuits, witness maps and `acvm`'s solver and compiler are generic over this trait, and
+/// default to `FieldElement`. The `bn254`, `bls12_381` or `goldilocks` feature only chooses
+/// which field `FieldElement` is.
+pub trait AcirField:
+    Copy
+    + Eq
+    + Ord
+    + Hash
+    + Debug
+    + Display
+    + From<i128>
+    + From<u128>
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + Send
+    + Sync
+    + 'static
+{
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn is_zero(&self) -> bool;
+    fn is_one(&self) -> bool;
+
+    /// The order of the field
+    fn modulus() -> BigUint;
+    /// The number of bits needed to represent any element of the field
+    fn max_num_bits() -> u32;
+    /// The number of bytes needed to represent any element of the field
+    fn max_num_bytes() -> u32;
+    /// The number of bits needed to represent this element
+    fn num_bits(&self) -> u32;
+
+    fn pow(&self, exponent: &Self) -> Self;
+    /// Returns the inverse of the element, or zero if it is zero
+    fn inverse(&self) -> Self;
+    /// Returns the inverse of the element, or None if it is zero
+    fn try_inverse(self) -> Option<Self>;
+
+    fn fits_in_u128(&self) -> bool;
+    /// Returns the lowest 128 bits of the element
+    fn to_u128(self) -> u128;
+    fn try_into_u128(self) -> Option<u128>;
+    fn try_to_u64(&self) -> Option<u64>;
+    fn to_hex(self) -> String;
+    fn from_hex(hex_str: &str) -> Option<Self>;
+    fn to_be_bytes(self) -> Vec<u8>;
+    /// Converts big endian bytes into a field element, reducing them modulo the order
+    fn from_be_bytes_reduce(bytes: &[u8]) -> Self;
+    /// Converts big endian bytes into a field element, or returns None if they are not
+    /// less than the order
+    fn try_from_be_bytes(bytes: &[u8]) -> Option<Self>;
+
+    /// The bits of the element, most significant first
+    fn bits(&self) -> Vec<bool>;
+    /// The lowest `num_bits` bits of the element, as little endian bytes
+    fn fetch_nearest_bytes(&self, num_bits: usize) -> Vec<u8>;
+
+    /// The AND of the lowest `num_bits` bits of the two elements
+    fn and(&self, rhs: &Self, num_bits: u32) -> Self;
+    /// The XOR of the lowest `num_bits` bits of the two elements
+    fn xor(&self, rhs: &Self, num_bits: u32) -> Self;
+}
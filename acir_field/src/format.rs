@@ -0,0 +1,98 @@
+// The textual representation of field elements, which is shared by every field
+
+use alloc::{
+    borrow::ToOwned,
+    string::{String, ToString},
+};
+
+use num_bigint::BigUint;
+use num_traits::Zero;
+
+// Writes the field element whose value is `number` and whose negation is `minus_number`,
+// as whichever is shorter and as a multiple of a power of two where possible
+pub(crate) fn fmt_field(
+    f: &mut core::fmt::Formatter,
+    number: BigUint,
+    minus_number: BigUint,
+) -> core::fmt::Result {
+    // First check if the number is zero
+    //
+    if number == BigUint::zero() {
+        return write!(f, "0");
+    }
+    // Check if the negative version is smaller to represent
+    //
+    let (smaller_repr, is_negative) = if minus_number.to_string().len() < number.to_string().len() {
+        (minus_number, true)
+    } else {
+        (number, false)
+    };
+    if is_negative {
+        write!(f, "-")?;
+    }
+
+    // Number of bits needed to represent the smaller representation
+    let num_bits = smaller_repr.bits();
+
+    // Check if the number represents a power of 2
+    if smaller_repr.count_ones() == 1 {
+        let mut bit_index = 0;
+        for i in 0..num_bits {
+            if smaller_repr.bit(i) {
+                bit_index = i;
+                break;
+            }
+        }
+        return write!(f, "2{}", superscript(bit_index));
+    }
+
+    // Check if number is a multiple of a power of 2.
+    // This is used because when computing the quotient
+    // we usually have numbers in the form 2^t * q + r
+    // We focus on 2^64, 2^32, 2^16, 2^8, 2^4 because
+    // they are common. We could extend this to a more
+    // general factorisation strategy, but we pay in terms of CPU time
+    let mul_sign = "×";
+    for power in [64, 32, 16, 8, 4] {
+        let power_of_two = BigUint::from(2_u128).pow(power);
+        if &smaller_repr % &power_of_two == BigUint::zero() {
+            return write!(
+                f,
+                "2{}{}{}",
+                superscript(power as u64),
+                mul_sign,
+                smaller_repr / &power_of_two,
+            );
+        }
+    }
+    write!(f, "{smaller_repr}")
+}
+
+// For pretty printing powers
+fn superscript(n: u64) -> String {
+    if n == 0 {
+        "⁰".to_owned()
+    } else if n == 1 {
+        "¹".to_owned()
+    } else if n == 2 {
+        "²".to_owned()
+    } else if n == 3 {
+        "³".to_owned()
+    } else if n == 4 {
+        "⁴".to_owned()
+    } else if n == 5 {
+        "⁵".to_owned()
+    } else if n == 6 {
+        "⁶".to_owned()
+    } else if n == 7 {
+        "⁷".to_owned()
+    } else if n == 8 {
+        "⁸".to_owned()
+    } else if n == 9 {
+        "⁹".to_owned()
+    } else if n >= 10 {
+        superscript(n / 10) + &superscript(n % 10)
+    } else {
+        panic!("{}", n.to_string() + " can't be converted to superscript.");
+    }
+}
@@ -1,82 +1,38 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
 use ark_ff::to_bytes;
 use ark_ff::FpParameters;
 use ark_ff::PrimeField;
-use ark_ff::Zero;
 use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
 
+use crate::{format, AcirField};
+
 // XXX: Switch out for a trait and proper implementations
 // This implementation is in-efficient, can definitely remove hex usage and Iterator instances for trivial functionality
 #[derive(Clone, Copy, Eq, PartialOrd, Ord)]
 pub struct FieldElement<F: PrimeField>(F);
 
-impl<F: PrimeField> std::fmt::Display for FieldElement<F> {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        // First check if the number is zero
-        //
+impl<F: PrimeField> core::fmt::Display for FieldElement<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         let number = BigUint::from_bytes_be(&self.to_be_bytes());
-        if number == BigUint::zero() {
-            return write!(f, "0");
-        }
-        // Check if the negative version is smaller to represent
-        //
         let minus_number = BigUint::from_bytes_be(&(self.neg()).to_be_bytes());
-        let (smaller_repr, is_negative) =
-            if minus_number.to_string().len() < number.to_string().len() {
-                (minus_number, true)
-            } else {
-                (number, false)
-            };
-        if is_negative {
-            write!(f, "-")?;
-        }
-
-        // Number of bits needed to represent the smaller representation
-        let num_bits = smaller_repr.bits();
-
-        // Check if the number represents a power of 2
-        if smaller_repr.count_ones() == 1 {
-            let mut bit_index = 0;
-            for i in 0..num_bits {
-                if smaller_repr.bit(i) {
-                    bit_index = i;
-                    break;
-                }
-            }
-            return write!(f, "2{}", superscript(bit_index));
-        }
-
-        // Check if number is a multiple of a power of 2.
-        // This is used because when computing the quotient
-        // we usually have numbers in the form 2^t * q + r
-        // We focus on 2^64, 2^32, 2^16, 2^8, 2^4 because
-        // they are common. We could extend this to a more
-        // general factorisation strategy, but we pay in terms of CPU time
-        let mul_sign = "×";
-        for power in [64, 32, 16, 8, 4] {
-            let power_of_two = BigUint::from(2_u128).pow(power);
-            if &smaller_repr % &power_of_two == BigUint::zero() {
-                return write!(
-                    f,
-                    "2{}{}{}",
-                    superscript(power as u64),
-                    mul_sign,
-                    smaller_repr / &power_of_two,
-                );
-            }
-        }
-        write!(f, "{smaller_repr}")
+        format::fmt_field(f, number, minus_number)
     }
 }
 
-impl<F: PrimeField> std::fmt::Debug for FieldElement<F> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Display::fmt(self, f)
+impl<F: PrimeField> core::fmt::Debug for FieldElement<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(self, f)
     }
 }
 
-impl<F: PrimeField> std::hash::Hash for FieldElement<F> {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+impl<F: PrimeField> core::hash::Hash for FieldElement<F> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
         state.write(&self.to_be_bytes())
     }
 }
@@ -133,11 +89,7 @@ impl<'de, T: ark_ff::PrimeField> Deserialize<'de> for FieldElement<T> {
 
 impl<F: PrimeField> From<u128> for FieldElement<F> {
     fn from(a: u128) -> FieldElement<F> {
-        let result = match F::from_str(&a.to_string()) {
-            Ok(result) => result,
-            Err(_) => panic!("Cannot convert u128 as a string to a field element"),
-        };
-        FieldElement(result)
+        FieldElement(F::from(a))
     }
 }
 
@@ -209,16 +161,26 @@ impl<F: PrimeField> FieldElement<F> {
     }
 
     pub fn fits_in_u128(&self) -> bool {
-        self.num_bits() <= 128
+        self.try_into_u128().is_some()
     }
 
+    /// Returns the lowest 128 bits of the value
     pub fn to_u128(self) -> u128 {
-        let bytes = self.to_be_bytes();
-        u128::from_be_bytes(bytes[16..32].try_into().unwrap())
+        let repr = self.0.into_repr();
+        // The limbs are in little-endian order
+        let limbs: &[u64] = repr.as_ref();
+        (u128::from(limbs[1]) << 64) | u128::from(limbs[0])
     }
 
+    /// Returns the value as a `u128` if it fits, reading the limbs of the field
+    /// element directly so that nothing is allocated
     pub fn try_into_u128(self) -> Option<u128> {
-        self.fits_in_u128().then(|| self.to_u128())
+        let repr = self.0.into_repr();
+        let limbs: &[u64] = repr.as_ref();
+        limbs[2..]
+            .iter()
+            .all(|limb| *limb == 0)
+            .then(|| self.to_u128())
     }
 
     pub fn try_to_u64(&self) -> Option<u64> {
@@ -268,6 +230,16 @@ impl<F: PrimeField> FieldElement<F> {
         FieldElement(F::from_be_bytes_mod_order(bytes))
     }
 
+    /// Converts bytes into a FieldElement, returning None if
+    /// they encode an integer which is not less than the modulus.
+    pub fn try_from_be_bytes(bytes: &[u8]) -> Option<FieldElement<F>> {
+        if BigUint::from_bytes_be(bytes) < Self::modulus() {
+            Some(FieldElement::from_be_bytes_reduce(bytes))
+        } else {
+            None
+        }
+    }
+
     pub fn bits(&self) -> Vec<bool> {
         let bytes = self.to_be_bytes();
         let mut bits = Vec::with_capacity(bytes.len() * 8);
@@ -290,7 +262,7 @@ impl<F: PrimeField> FieldElement<F> {
     /// This method truncates
     pub fn fetch_nearest_bytes(&self, num_bits: usize) -> Vec<u8> {
         fn nearest_bytes(num_bits: usize) -> usize {
-            ((num_bits + 7) / 8) * 8
+            num_bits.div_ceil(8) * 8
         }
 
         let num_bytes = nearest_bytes(num_bits);
@@ -327,7 +299,7 @@ impl<F: PrimeField> FieldElement<F> {
 
         let and_byte_arr: Vec<_> = lhs_bytes
             .into_iter()
-            .zip(rhs_bytes.into_iter())
+            .zip(rhs_bytes)
             .map(|(lhs, rhs)| if is_xor { lhs ^ rhs } else { lhs & rhs })
             .collect();
 
@@ -341,7 +313,7 @@ impl<F: PrimeField> FieldElement<F> {
     }
 }
 
-use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+use core::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
 
 impl<F: PrimeField> Neg for FieldElement<F> {
     type Output = FieldElement<F>;
@@ -391,22 +363,6 @@ impl<F: PrimeField> SubAssign for FieldElement<F> {
     }
 }
 
-#[cfg(test)]
-mod test {
-    #[test]
-    fn and() {
-        let max = 10_000u32;
-
-        let num_bits = (std::mem::size_of::<u32>() * 8) as u32 - max.leading_zeros();
-
-        for x in 0..max {
-            let x = crate::generic_ark::FieldElement::<ark_bn254::Fr>::from(x as i128);
-            let res = x.and(&x, num_bits);
-            assert_eq!(res.to_be_bytes(), x.to_be_bytes());
-        }
-    }
-}
-
 fn mask_vector_le(bytes: &mut [u8], num_bits: usize) {
     // reverse to big endian format
     bytes.reverse();
@@ -416,16 +372,16 @@ fn mask_vector_le(bytes: &mut [u8], num_bits: usize) {
 
     for (index, byte) in bytes.iter_mut().enumerate() {
         match index.cmp(&array_mask_index) {
-            std::cmp::Ordering::Less => {
+            core::cmp::Ordering::Less => {
                 // do nothing if the current index is less than
                 // the array index.
             }
-            std::cmp::Ordering::Equal => {
+            core::cmp::Ordering::Equal => {
                 let mask = 2u8.pow(mask_power as u32) - 1;
                 // mask the byte
                 *byte &= mask;
             }
-            std::cmp::Ordering::Greater => {
+            core::cmp::Ordering::Greater => {
                 // Anything greater than the array index
                 // will be set to zero
                 *byte = 0;
@@ -436,31 +392,132 @@ fn mask_vector_le(bytes: &mut [u8], num_bits: usize) {
     bytes.reverse();
 }
 
-// For pretty printing powers
-fn superscript(n: u64) -> String {
-    if n == 0 {
-        "⁰".to_owned()
-    } else if n == 1 {
-        "¹".to_owned()
-    } else if n == 2 {
-        "²".to_owned()
-    } else if n == 3 {
-        "³".to_owned()
-    } else if n == 4 {
-        "⁴".to_owned()
-    } else if n == 5 {
-        "⁵".to_owned()
-    } else if n == 6 {
-        "⁶".to_owned()
-    } else if n == 7 {
-        "⁷".to_owned()
-    } else if n == 8 {
-        "⁸".to_owned()
-    } else if n == 9 {
-        "⁹".to_owned()
-    } else if n >= 10 {
-        superscript(n / 10) + &superscript(n % 10)
-    } else {
-        panic!("{}", n.to_string() + " can't be converted to superscript.");
+// Each method forwards to the inherent method of the same name
+impl<F: PrimeField> AcirField for FieldElement<F> {
+    fn zero() -> Self {
+        FieldElement::zero()
+    }
+    fn one() -> Self {
+        FieldElement::one()
+    }
+    fn is_zero(&self) -> bool {
+        FieldElement::is_zero(self)
+    }
+    fn is_one(&self) -> bool {
+        FieldElement::is_one(self)
+    }
+    fn modulus() -> BigUint {
+        FieldElement::<F>::modulus()
+    }
+    fn max_num_bits() -> u32 {
+        FieldElement::<F>::max_num_bits()
+    }
+    fn max_num_bytes() -> u32 {
+        FieldElement::<F>::max_num_bytes()
+    }
+    fn num_bits(&self) -> u32 {
+        FieldElement::num_bits(self)
+    }
+    fn pow(&self, exponent: &Self) -> Self {
+        FieldElement::pow(self, exponent)
+    }
+    fn inverse(&self) -> Self {
+        FieldElement::inverse(self)
+    }
+    fn try_inverse(self) -> Option<Self> {
+        FieldElement::try_inverse(self)
+    }
+    fn fits_in_u128(&self) -> bool {
+        FieldElement::fits_in_u128(self)
+    }
+    fn to_u128(self) -> u128 {
+        FieldElement::to_u128(self)
+    }
+    fn try_into_u128(self) -> Option<u128> {
+        FieldElement::try_into_u128(self)
+    }
+    fn try_to_u64(&self) -> Option<u64> {
+        FieldElement::try_to_u64(self)
+    }
+    fn to_hex(self) -> String {
+        FieldElement::to_hex(self)
+    }
+    fn from_hex(hex_str: &str) -> Option<Self> {
+        FieldElement::from_hex(hex_str)
+    }
+    fn to_be_bytes(self) -> Vec<u8> {
+        FieldElement::to_be_bytes(self)
+    }
+    fn from_be_bytes_reduce(bytes: &[u8]) -> Self {
+        FieldElement::from_be_bytes_reduce(bytes)
+    }
+    fn try_from_be_bytes(bytes: &[u8]) -> Option<Self> {
+        FieldElement::try_from_be_bytes(bytes)
+    }
+    fn bits(&self) -> Vec<bool> {
+        FieldElement::bits(self)
+    }
+    fn fetch_nearest_bytes(&self, num_bits: usize) -> Vec<u8> {
+        FieldElement::fetch_nearest_bytes(self, num_bits)
+    }
+    fn and(&self, rhs: &Self, num_bits: u32) -> Self {
+        FieldElement::and(self, rhs, num_bits)
+    }
+    fn xor(&self, rhs: &Self, num_bits: u32) -> Self {
+        FieldElement::xor(self, rhs, num_bits)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn and() {
+        let max = 10_000u32;
+
+        let num_bits = (core::mem::size_of::<u32>() * 8) as u32 - max.leading_zeros();
+
+        for x in 0..max {
+            let x = crate::generic_ark::FieldElement::<ark_bn254::Fr>::from(x as i128);
+            let res = x.and(&x, num_bits);
+            assert_eq!(res.to_be_bytes(), x.to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn u128_conversion() {
+        type FieldElement = crate::generic_ark::FieldElement<ark_bn254::Fr>;
+
+        for value in [0, 1, u64::MAX as u128, u64::MAX as u128 + 1, u128::MAX] {
+            let x = FieldElement::from(value);
+            assert_eq!(x.try_into_u128(), Some(value));
+            assert_eq!(x.to_u128(), value);
+        }
+        let too_large = FieldElement::from(u128::MAX) + FieldElement::one();
+        assert_eq!(too_large.try_into_u128(), None);
+        assert!(!too_large.fits_in_u128());
+        assert_eq!((-FieldElement::one()).try_into_u128(), None);
+    }
+
+    #[test]
+    fn field_trait_matches_inherent_methods() {
+        use crate::AcirField;
+        type FieldElement = crate::generic_ark::FieldElement<ark_bn254::Fr>;
+
+        // Solves `a * x + b = 0` using only the trait
+        fn solve_linear<F: AcirField>(a: F, b: F) -> Option<F> {
+            a.try_inverse().map(|inverse| -b * inverse)
+        }
+
+        let (a, b) = (FieldElement::from(3_i128), FieldElement::from(12_i128));
+        assert_eq!(solve_linear(a, b), Some(FieldElement::from(-4_i128)));
+        assert_eq!(solve_linear(FieldElement::zero(), b), None);
+        assert_eq!(
+            <FieldElement as AcirField>::modulus(),
+            FieldElement::modulus()
+        );
+        assert_eq!(
+            AcirField::xor(&a, &b, 8),
+            FieldElement::from(0b0011_i128 ^ 0b1100)
+        );
     }
 }
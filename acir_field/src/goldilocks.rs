@@ -0,0 +1,418 @@
+// The Goldilocks field, of integers modulo 2^64 - 2^32 + 1, which STARK backends use
+//
+// Arkworks 0.3 only supports moduli which leave the top bit of their limbs unused, which
+// this modulus does not, so elements are stored as their canonical u64 value and reduced
+// with 128-bit arithmetic instead.
+
+use alloc::{format, string::String, vec::Vec};
+use core::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+use crate::{format, AcirField};
+
+const MODULUS: u64 = 0xFFFF_FFFF_0000_0001;
+
+// Invariant: the value is less than `MODULUS`
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FieldElement(u64);
+
+impl core::fmt::Display for FieldElement {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        format::fmt_field(f, BigUint::from(self.0), BigUint::from((-*self).0))
+    }
+}
+
+impl core::fmt::Debug for FieldElement {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(self, f)
+    }
+}
+
+impl From<i128> for FieldElement {
+    fn from(a: i128) -> FieldElement {
+        let value = FieldElement::from(a.unsigned_abs());
+        if a < 0 {
+            -value
+        } else {
+            value
+        }
+    }
+}
+
+impl From<u128> for FieldElement {
+    fn from(a: u128) -> FieldElement {
+        FieldElement((a % MODULUS as u128) as u64)
+    }
+}
+
+impl Serialize for FieldElement {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.to_hex().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldElement {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = <&str>::deserialize(deserializer)?;
+        match Self::from_hex(s) {
+            Some(value) => Ok(value),
+            None => Err(serde::de::Error::custom(format!(
+                "Invalid hex for FieldElement: {s}",
+            ))),
+        }
+    }
+}
+
+impl FieldElement {
+    pub fn one() -> FieldElement {
+        FieldElement(1)
+    }
+    pub fn zero() -> FieldElement {
+        FieldElement(0)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+    pub fn is_one(&self) -> bool {
+        self.0 == 1
+    }
+
+    pub fn pow(&self, exponent: &Self) -> Self {
+        let mut result = FieldElement::one();
+        let mut base = *self;
+        let mut exponent = exponent.0;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent >>= 1;
+        }
+        result
+    }
+
+    /// Maximum number of bits needed to represent a field element
+    pub const fn max_num_bits() -> u32 {
+        64
+    }
+
+    /// Maximum numbers of bytes needed to represent a field element
+    pub const fn max_num_bytes() -> u32 {
+        8
+    }
+
+    pub fn modulus() -> BigUint {
+        BigUint::from(MODULUS)
+    }
+    /// Returns None, if the string is not a canonical
+    /// representation of a field element; less than the order
+    /// or if the hex string is invalid.
+    /// This method can be used for both hex and decimal representations.
+    pub fn try_from_str(input: &str) -> Option<FieldElement> {
+        if input.contains('x') {
+            return FieldElement::from_hex(input);
+        }
+
+        let value: u64 = input.parse().ok()?;
+        (value < MODULUS).then_some(FieldElement(value))
+    }
+
+    /// This is the number of bits required to represent this specific field element
+    pub fn num_bits(&self) -> u32 {
+        u64::BITS - self.0.leading_zeros()
+    }
+
+    pub fn fits_in_u128(&self) -> bool {
+        true
+    }
+
+    /// Returns the lowest 128 bits of the value
+    pub fn to_u128(self) -> u128 {
+        self.0.into()
+    }
+
+    /// Returns the value as a `u128`, which every element of this field fits in
+    pub fn try_into_u128(self) -> Option<u128> {
+        Some(self.0.into())
+    }
+
+    pub fn try_to_u64(&self) -> Option<u64> {
+        Some(self.0)
+    }
+
+    /// Computes the inverse or returns zero if the inverse does not exist
+    /// Before using this FieldElement, please ensure that this behaviour is necessary
+    pub fn inverse(&self) -> FieldElement {
+        self.try_inverse().unwrap_or_else(FieldElement::zero)
+    }
+
+    // By Fermat's little theorem, x^(p - 2) is the inverse of x
+    pub fn try_inverse(self) -> Option<Self> {
+        (!self.is_zero()).then(|| self.pow(&FieldElement(MODULUS - 2)))
+    }
+
+    pub fn to_hex(self) -> String {
+        hex::encode(self.to_be_bytes())
+    }
+    pub fn from_hex(hex_str: &str) -> Option<FieldElement> {
+        let value = hex_str.strip_prefix("0x").unwrap_or(hex_str);
+        let hex_as_bytes = hex::decode(value).ok()?;
+        Some(FieldElement::from_be_bytes_reduce(&hex_as_bytes))
+    }
+
+    pub fn to_be_bytes(self) -> Vec<u8> {
+        self.0.to_be_bytes().to_vec()
+    }
+
+    /// Converts bytes into a FieldElement and applies a
+    /// reduction if needed.
+    pub fn from_be_bytes_reduce(bytes: &[u8]) -> FieldElement {
+        bytes.iter().fold(FieldElement::zero(), |acc, byte| {
+            acc * FieldElement(256) + FieldElement(u64::from(*byte))
+        })
+    }
+
+    /// Converts bytes into a FieldElement, returning None if
+    /// they encode an integer which is not less than the modulus.
+    pub fn try_from_be_bytes(bytes: &[u8]) -> Option<FieldElement> {
+        if BigUint::from_bytes_be(bytes) < Self::modulus() {
+            Some(FieldElement::from_be_bytes_reduce(bytes))
+        } else {
+            None
+        }
+    }
+
+    pub fn bits(&self) -> Vec<bool> {
+        (0..u64::BITS)
+            .rev()
+            .map(|index| (self.0 >> index) & 1 == 1)
+            .collect()
+    }
+
+    /// Returns the closest number of bytes to the bits specified
+    /// This method truncates
+    pub fn fetch_nearest_bytes(&self, num_bits: usize) -> Vec<u8> {
+        let num_bytes = num_bits.div_ceil(8).min(Self::max_num_bytes() as usize);
+        self.0.to_le_bytes()[0..num_bytes].to_vec()
+    }
+
+    // Returns the value with all but its lowest `num_bits` bits cleared
+    fn mask(&self, num_bits: u32) -> u64 {
+        if num_bits >= u64::BITS {
+            self.0
+        } else {
+            self.0 & ((1 << num_bits) - 1)
+        }
+    }
+
+    pub fn and(&self, rhs: &FieldElement, num_bits: u32) -> FieldElement {
+        FieldElement(self.mask(num_bits) & rhs.mask(num_bits))
+    }
+    // Both values are below the modulus, whose top 32 bits are all set, so their XOR may
+    // not be and is reduced
+    pub fn xor(&self, rhs: &FieldElement, num_bits: u32) -> FieldElement {
+        FieldElement::from(u128::from(self.mask(num_bits) ^ rhs.mask(num_bits)))
+    }
+}
+
+impl Neg for FieldElement {
+    type Output = FieldElement;
+
+    fn neg(self) -> Self::Output {
+        if self.is_zero() {
+            self
+        } else {
+            FieldElement(MODULUS - self.0)
+        }
+    }
+}
+
+impl Mul for FieldElement {
+    type Output = FieldElement;
+    fn mul(self, rhs: FieldElement) -> Self::Output {
+        FieldElement::from(u128::from(self.0) * u128::from(rhs.0))
+    }
+}
+impl Div for FieldElement {
+    type Output = FieldElement;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: FieldElement) -> Self::Output {
+        self * rhs.inverse()
+    }
+}
+impl Add for FieldElement {
+    type Output = FieldElement;
+    fn add(self, rhs: FieldElement) -> Self::Output {
+        FieldElement::from(u128::from(self.0) + u128::from(rhs.0))
+    }
+}
+impl AddAssign for FieldElement {
+    fn add_assign(&mut self, rhs: FieldElement) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for FieldElement {
+    type Output = FieldElement;
+    fn sub(self, rhs: FieldElement) -> Self::Output {
+        self + -rhs
+    }
+}
+impl SubAssign for FieldElement {
+    fn sub_assign(&mut self, rhs: FieldElement) {
+        *self = *self - rhs;
+    }
+}
+
+// Each method forwards to the inherent method of the same name
+impl AcirField for FieldElement {
+    fn zero() -> Self {
+        FieldElement::zero()
+    }
+    fn one() -> Self {
+        FieldElement::one()
+    }
+    fn is_zero(&self) -> bool {
+        FieldElement::is_zero(self)
+    }
+    fn is_one(&self) -> bool {
+        FieldElement::is_one(self)
+    }
+    fn modulus() -> BigUint {
+        FieldElement::modulus()
+    }
+    fn max_num_bits() -> u32 {
+        FieldElement::max_num_bits()
+    }
+    fn max_num_bytes() -> u32 {
+        FieldElement::max_num_bytes()
+    }
+    fn num_bits(&self) -> u32 {
+        FieldElement::num_bits(self)
+    }
+    fn pow(&self, exponent: &Self) -> Self {
+        FieldElement::pow(self, exponent)
+    }
+    fn inverse(&self) -> Self {
+        FieldElement::inverse(self)
+    }
+    fn try_inverse(self) -> Option<Self> {
+        FieldElement::try_inverse(self)
+    }
+    fn fits_in_u128(&self) -> bool {
+        FieldElement::fits_in_u128(self)
+    }
+    fn to_u128(self) -> u128 {
+        FieldElement::to_u128(self)
+    }
+    fn try_into_u128(self) -> Option<u128> {
+        FieldElement::try_into_u128(self)
+    }
+    fn try_to_u64(&self) -> Option<u64> {
+        FieldElement::try_to_u64(self)
+    }
+    fn to_hex(self) -> String {
+        FieldElement::to_hex(self)
+    }
+    fn from_hex(hex_str: &str) -> Option<Self> {
+        FieldElement::from_hex(hex_str)
+    }
+    fn to_be_bytes(self) -> Vec<u8> {
+        FieldElement::to_be_bytes(self)
+    }
+    fn from_be_bytes_reduce(bytes: &[u8]) -> Self {
+        FieldElement::from_be_bytes_reduce(bytes)
+    }
+    fn try_from_be_bytes(bytes: &[u8]) -> Option<Self> {
+        FieldElement::try_from_be_bytes(bytes)
+    }
+    fn bits(&self) -> Vec<bool> {
+        FieldElement::bits(self)
+    }
+    fn fetch_nearest_bytes(&self, num_bits: usize) -> Vec<u8> {
+        FieldElement::fetch_nearest_bytes(self, num_bits)
+    }
+    fn and(&self, rhs: &Self, num_bits: u32) -> Self {
+        FieldElement::and(self, rhs, num_bits)
+    }
+    fn xor(&self, rhs: &Self, num_bits: u32) -> Self {
+        FieldElement::xor(self, rhs, num_bits)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn arithmetic_is_modulo_the_order() {
+        let max = -FieldElement::one();
+        assert_eq!(max.to_u128(), u128::from(MODULUS - 1));
+        assert_eq!(max + FieldElement::one(), FieldElement::zero());
+        assert_eq!(max * max, FieldElement::one());
+        assert_eq!(
+            FieldElement::from(u128::from(MODULUS)),
+            FieldElement::zero()
+        );
+        assert_eq!(
+            FieldElement::from(-3_i128) + FieldElement::from(5_i128),
+            FieldElement::from(2_i128)
+        );
+
+        // 7 generates the multiplicative group, so has order p - 1
+        let generator = FieldElement::from(7_i128);
+        assert_eq!(generator.pow(&max), FieldElement::one());
+        assert_ne!(
+            generator.pow(&FieldElement((MODULUS - 1) / 2)),
+            FieldElement::one()
+        );
+
+        for value in [1_i128, 2, 7, -1, u64::MAX.into()] {
+            let x = FieldElement::from(value);
+            assert_eq!(x * x.inverse(), FieldElement::one());
+        }
+        assert_eq!(FieldElement::zero().try_inverse(), None);
+    }
+
+    #[test]
+    fn byte_conversions() {
+        let x = FieldElement::from(0x0102_0304_0506_0708_i128);
+        assert_eq!(x.to_be_bytes(), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(FieldElement::from_be_bytes_reduce(&x.to_be_bytes()), x);
+        assert_eq!(FieldElement::from_hex(&x.to_hex()), Some(x));
+        assert_eq!(FieldElement::try_from_str("72623859790382856"), Some(x));
+        assert_eq!(x.fetch_nearest_bytes(12), vec![8, 7]);
+        assert_eq!(x.num_bits(), 57);
+
+        assert_eq!(
+            FieldElement::try_from_be_bytes(&MODULUS.to_be_bytes()),
+            None
+        );
+        assert_eq!(
+            FieldElement::from_be_bytes_reduce(&[1; 16]),
+            FieldElement::from(u128::from_be_bytes([1; 16]))
+        );
+        assert_eq!(FieldElement::try_from_str(&MODULUS.to_string()), None);
+        assert_eq!((-FieldElement::from(5_i128)).to_string(), "-5");
+    }
+
+    #[test]
+    fn and_xor() {
+        let (a, b) = (
+            FieldElement::from(0b1100_i128),
+            FieldElement::from(0b1010_i128),
+        );
+        assert_eq!(a.and(&b, 4), FieldElement::from(0b1000_i128));
+        assert_eq!(a.xor(&b, 3), FieldElement::from(0b110_i128));
+        assert_eq!(a.xor(&b, 64), FieldElement::from(0b0110_i128));
+    }
+}
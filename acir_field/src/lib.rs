@@ -1,3 +1,14 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod field_trait;
+mod format;
+mod goldilocks;
+pub use field_trait::AcirField;
+/// The Goldilocks field, which is available whichever field `FieldElement` is
+pub use goldilocks::FieldElement as Goldilocks;
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "bn254")] {
         mod generic_ark;
@@ -8,6 +19,9 @@ cfg_if::cfg_if! {
         mod generic_ark;
         pub type FieldElement = generic_ark::FieldElement<ark_bls12_381::Fr>;
         pub const CHOSEN_FIELD : FieldOptions = FieldOptions::BLS12_381;
+    } else if #[cfg(feature = "goldilocks")] {
+        pub type FieldElement = goldilocks::FieldElement;
+        pub const CHOSEN_FIELD : FieldOptions = FieldOptions::Goldilocks;
     } else {
         compile_error!("please specify a field to compile with");
     }
@@ -17,6 +31,7 @@ cfg_if::cfg_if! {
 pub enum FieldOptions {
     BN254,
     BLS12_381,
+    Goldilocks,
 }
 
 // This is needed because features are additive through the dependency graph; if a dependency turns on the bn254, then it
@@ -34,4 +49,4 @@ macro_rules! assert_unique_feature {
 }
 // https://internals.rust-lang.org/t/mutually-exclusive-feature-flags/8601/7
 // If another field/feature is added, we add it here too
-assert_unique_feature!("bn254", "bls12_381");
+assert_unique_feature!("bn254", "bls12_381", "goldilocks");
@@ -0,0 +1,726 @@
+// Conversion between the typed inputs of a Noir program and the witness map of its circuit
+//
+// The ABI of a program describes the parameters of its entry point. Each parameter is
+// flattened into a sequence of field elements, and the parameters are assigned to
+// consecutive witnesses starting at `Witness(1)`, in the order they are declared:
+//
+// - fields, integers and booleans are one field element each
+// - arrays are flattened element by element
+// - strings are one field element per byte
+// - structs are flattened field by field, in the order the fields are declared
+//
+// Signed integers are stored in two's complement using the width of their type, so that
+// `-1: i8` is the field element 255.
+//
+// In JSON and TOML inputs, fields and integers are written as decimal or `0x` prefixed
+// hexadecimal strings, or as numbers, and may be negative. Booleans are written as
+// booleans. When inputs are written back out, fields are hexadecimal and integers decimal.
+
+use std::collections::BTreeMap;
+
+use acir::{native_types::Witness, FieldElement};
+use num_bigint::{BigInt, BigUint, Sign as BigSign};
+use num_traits::{One, Zero};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::WitnessMap;
+
+/// The typed inputs of a program, by parameter name
+pub type InputMap = BTreeMap<String, InputValue>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sign {
+    Unsigned,
+    Signed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum AbiType {
+    Field,
+    Integer {
+        sign: Sign,
+        width: u32,
+    },
+    Boolean,
+    Array {
+        length: u32,
+        #[serde(rename = "type")]
+        typ: Box<AbiType>,
+    },
+    String {
+        length: u32,
+    },
+    Struct {
+        fields: Vec<(String, AbiType)>,
+    },
+}
+
+impl AbiType {
+    /// The number of field elements which a value of this type is flattened into.
+    ///
+    /// Returns `AbiError::TooManyFields` if it does not fit into a `u32`.
+    pub fn field_count(&self) -> Result<u32, AbiError> {
+        match self {
+            AbiType::Field | AbiType::Integer { .. } | AbiType::Boolean => Ok(1),
+            AbiType::Array { length, typ } => length
+                .checked_mul(typ.field_count()?)
+                .ok_or(AbiError::TooManyFields),
+            AbiType::String { length } => Ok(*length),
+            AbiType::Struct { fields } => fields.iter().try_fold(0_u32, |count, (_, typ)| {
+                count
+                    .checked_add(typ.field_count()?)
+                    .ok_or(AbiError::TooManyFields)
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for AbiType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AbiType::Field => write!(f, "Field"),
+            AbiType::Integer {
+                sign: Sign::Unsigned,
+                width,
+            } => write!(f, "u{width}"),
+            AbiType::Integer {
+                sign: Sign::Signed,
+                width,
+            } => write!(f, "i{width}"),
+            AbiType::Boolean => write!(f, "bool"),
+            AbiType::Array { length, typ } => write!(f, "[{typ}; {length}]"),
+            AbiType::String { length } => write!(f, "str<{length}>"),
+            AbiType::Struct { .. } => write!(f, "struct"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AbiVisibility {
+    Public,
+    Private,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub typ: AbiType,
+    pub visibility: AbiVisibility,
+}
+
+/// The parameters of a program's entry point
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Abi {
+    pub parameters: Vec<AbiParameter>,
+}
+
+/// A typed input. Fields, integers and booleans are held as the field element which is
+/// assigned to their witness.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputValue {
+    Field(FieldElement),
+    Vec(Vec<InputValue>),
+    String(String),
+    Struct(BTreeMap<String, InputValue>),
+}
+
+#[derive(PartialEq, Eq, Debug, Error)]
+pub enum AbiError {
+    #[error("missing input for parameter {0}")]
+    MissingParameter(String),
+    #[error("input {0} is not a parameter of the program")]
+    UnexpectedParameter(String),
+    #[error("input {name} is not a valid {typ}")]
+    TypeMismatch { name: String, typ: AbiType },
+    #[error("witness {} for input {name} has not been assigned", .witness.witness_index())]
+    MissingWitness { name: String, witness: Witness },
+    #[error("could not parse inputs: {0}")]
+    Parse(String),
+    #[error("could not serialize inputs: {0}")]
+    Serialize(String),
+    #[error(
+        "the parameters are flattened into more than {} field elements",
+        u32::MAX
+    )]
+    TooManyFields,
+}
+
+impl Abi {
+    /// The number of witnesses which the parameters are assigned to.
+    ///
+    /// Returns `AbiError::TooManyFields` if it does not fit into a `u32`.
+    pub fn field_count(&self) -> Result<u32, AbiError> {
+        self.parameters.iter().try_fold(0_u32, |count, param| {
+            count
+                .checked_add(param.typ.field_count()?)
+                .ok_or(AbiError::TooManyFields)
+        })
+    }
+
+    /// Flattens `inputs` into the witness map which the circuit is solved from
+    pub fn encode(&self, inputs: &InputMap) -> Result<WitnessMap, AbiError> {
+        self.check_names(inputs)?;
+
+        let mut values = Vec::new();
+        for param in &self.parameters {
+            flatten(&param.name, &inputs[&param.name], &param.typ, &mut values)?;
+        }
+        Ok(values
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| (Witness(index as u32 + 1), value))
+            .collect())
+    }
+
+    /// Reads the values of the parameters from a witness map
+    pub fn decode(&self, witness_map: &WitnessMap) -> Result<InputMap, AbiError> {
+        let mut witnesses = (1..=self.field_count()?).map(Witness);
+        let mut inputs = InputMap::new();
+        for param in &self.parameters {
+            // The lengths come from the ABI, so the values are not preallocated
+            let mut values = Vec::new();
+            for witness in witnesses.by_ref().take(param.typ.field_count()? as usize) {
+                let value = witness_map
+                    .get(&witness)
+                    .ok_or_else(|| AbiError::MissingWitness {
+                        name: param.name.clone(),
+                        witness,
+                    })?;
+                values.push(*value);
+            }
+            let value = unflatten(&param.name, &param.typ, &mut values.into_iter())?;
+            inputs.insert(param.name.clone(), value);
+        }
+        Ok(inputs)
+    }
+
+    // Checks that there is an input for each parameter, and no other inputs
+    fn check_names<T>(&self, inputs: &BTreeMap<String, T>) -> Result<(), AbiError> {
+        if let Some(param) = self
+            .parameters
+            .iter()
+            .find(|param| !inputs.contains_key(&param.name))
+        {
+            return Err(AbiError::MissingParameter(param.name.clone()));
+        }
+        if let Some(name) = inputs
+            .keys()
+            .find(|name| !self.parameters.iter().any(|param| &param.name == *name))
+        {
+            return Err(AbiError::UnexpectedParameter(name.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// The formats which inputs can be read from and written to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl Format {
+    /// Parses the inputs of every parameter of `abi`
+    pub fn parse(&self, input: &str, abi: &Abi) -> Result<InputMap, AbiError> {
+        let parse_error = |err: &dyn std::fmt::Display| AbiError::Parse(err.to_string());
+        let value: Value = match self {
+            Format::Json => serde_json::from_str(input).map_err(|err| parse_error(&err))?,
+            Format::Toml => {
+                let value: toml::Value = toml::from_str(input).map_err(|err| parse_error(&err))?;
+                serde_json::to_value(value).map_err(|err| parse_error(&err))?
+            }
+        };
+        let Value::Object(values) = value else {
+            return Err(AbiError::Parse(
+                "inputs must be a table of parameter names to values".to_owned(),
+            ));
+        };
+        let values: BTreeMap<String, Value> = values.into_iter().collect();
+        abi.check_names(&values)?;
+
+        abi.parameters
+            .iter()
+            .map(|param| {
+                let value = parse_value(&param.name, &values[&param.name], &param.typ)?;
+                Ok((param.name.clone(), value))
+            })
+            .collect()
+    }
+
+    /// Writes the inputs of every parameter of `abi`
+    pub fn serialize(&self, inputs: &InputMap, abi: &Abi) -> Result<String, AbiError> {
+        abi.check_names(inputs)?;
+
+        let mut values = serde_json::Map::new();
+        for param in &abi.parameters {
+            let value = to_json(&param.name, &inputs[&param.name], &param.typ)?;
+            values.insert(param.name.clone(), value);
+        }
+        let values = Value::Object(values);
+
+        let serialize_error = |err: &dyn std::fmt::Display| AbiError::Serialize(err.to_string());
+        match self {
+            Format::Json => {
+                serde_json::to_string_pretty(&values).map_err(|err| serialize_error(&err))
+            }
+            Format::Toml => {
+                // Converting to a TOML value first orders plain values before tables,
+                // which TOML requires
+                let values = toml::Value::try_from(values).map_err(|err| serialize_error(&err))?;
+                toml::to_string(&values).map_err(|err| serialize_error(&err))
+            }
+        }
+    }
+}
+
+fn type_mismatch(name: &str, typ: &AbiType) -> AbiError {
+    AbiError::TypeMismatch {
+        name: name.to_owned(),
+        typ: typ.clone(),
+    }
+}
+
+fn field_to_biguint(value: FieldElement) -> BigUint {
+    BigUint::from_bytes_be(&value.to_be_bytes())
+}
+
+fn biguint_to_field(value: &BigUint) -> FieldElement {
+    FieldElement::from_be_bytes_reduce(&value.to_bytes_be())
+}
+
+// Returns whether a field element is a valid value of a field, integer or boolean
+fn is_valid_scalar(value: FieldElement, typ: &AbiType) -> bool {
+    match typ {
+        AbiType::Field => true,
+        AbiType::Integer { width, .. } => value.num_bits() <= *width,
+        AbiType::Boolean => value.is_zero() || value.is_one(),
+        _ => false,
+    }
+}
+
+fn flatten(
+    name: &str,
+    value: &InputValue,
+    typ: &AbiType,
+    values: &mut Vec<FieldElement>,
+) -> Result<(), AbiError> {
+    match (value, typ) {
+        (InputValue::Field(value), _) if is_valid_scalar(*value, typ) => values.push(*value),
+        (InputValue::Vec(elements), AbiType::Array { length, typ })
+            if elements.len() == *length as usize =>
+        {
+            for (index, element) in elements.iter().enumerate() {
+                flatten(&format!("{name}[{index}]"), element, typ, values)?;
+            }
+        }
+        (InputValue::String(string), AbiType::String { length })
+            if string.len() == *length as usize =>
+        {
+            values.extend(string.bytes().map(|byte| FieldElement::from(byte as i128)));
+        }
+        (InputValue::Struct(members), AbiType::Struct { fields })
+            if members.len() == fields.len() =>
+        {
+            for (field_name, typ) in fields {
+                let member = members
+                    .get(field_name)
+                    .ok_or_else(|| AbiError::MissingParameter(format!("{name}.{field_name}")))?;
+                flatten(&format!("{name}.{field_name}"), member, typ, values)?;
+            }
+        }
+        _ => return Err(type_mismatch(name, typ)),
+    }
+    Ok(())
+}
+
+fn unflatten(
+    name: &str,
+    typ: &AbiType,
+    values: &mut impl Iterator<Item = FieldElement>,
+) -> Result<InputValue, AbiError> {
+    let value = match typ {
+        AbiType::Field | AbiType::Integer { .. } | AbiType::Boolean => {
+            let value = values.next().expect("infallible: field count was checked");
+            if !is_valid_scalar(value, typ) {
+                return Err(type_mismatch(name, typ));
+            }
+            InputValue::Field(value)
+        }
+        AbiType::Array { length, typ } => InputValue::Vec(
+            (0..*length)
+                .map(|index| unflatten(&format!("{name}[{index}]"), typ, values))
+                .collect::<Result<_, _>>()?,
+        ),
+        AbiType::String { length } => {
+            let bytes = values
+                .take(*length as usize)
+                .map(|value| value.try_to_u64().and_then(|byte| u8::try_from(byte).ok()))
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(|| type_mismatch(name, typ))?;
+            InputValue::String(String::from_utf8(bytes).map_err(|_| type_mismatch(name, typ))?)
+        }
+        AbiType::Struct { fields } => InputValue::Struct(
+            fields
+                .iter()
+                .map(|(field_name, typ)| {
+                    let value = unflatten(&format!("{name}.{field_name}"), typ, values)?;
+                    Ok((field_name.clone(), value))
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+    };
+    Ok(value)
+}
+
+// Parses a decimal or hexadecimal integer, which may be negative
+fn parse_integer(value: &Value) -> Option<BigInt> {
+    match value {
+        Value::Number(number) => number
+            .as_i64()
+            .map(BigInt::from)
+            .or_else(|| number.as_u64().map(BigInt::from)),
+        Value::String(string) => {
+            let (sign, magnitude) = match string.strip_prefix('-') {
+                Some(magnitude) => (BigSign::Minus, magnitude),
+                None => (BigSign::Plus, string.as_str()),
+            };
+            let magnitude = match magnitude.strip_prefix("0x") {
+                Some(hex) => BigUint::parse_bytes(hex.as_bytes(), 16)?,
+                None => BigUint::parse_bytes(magnitude.as_bytes(), 10)?,
+            };
+            Some(BigInt::from_biguint(sign, magnitude))
+        }
+        _ => None,
+    }
+}
+
+// Encodes an integer as the field element of a field or integer type, if it is in range
+fn encode_integer(value: &BigInt, typ: &AbiType) -> Option<FieldElement> {
+    let encoded = match typ {
+        AbiType::Field => {
+            let modulus = BigInt::from(FieldElement::modulus());
+            if value.magnitude() >= modulus.magnitude() {
+                return None;
+            }
+            if value.sign() == BigSign::Minus {
+                value + modulus
+            } else {
+                value.clone()
+            }
+        }
+        AbiType::Integer {
+            sign: Sign::Unsigned,
+            width,
+        } => {
+            let bound = BigInt::one() << *width;
+            if value.sign() == BigSign::Minus || value >= &bound {
+                return None;
+            }
+            value.clone()
+        }
+        AbiType::Integer {
+            sign: Sign::Signed,
+            width,
+        } => {
+            let bound = BigInt::one() << (width - 1);
+            if value < &-&bound || value >= &bound {
+                return None;
+            }
+            if value.sign() == BigSign::Minus {
+                value + (bound << 1)
+            } else {
+                value.clone()
+            }
+        }
+        _ => return None,
+    };
+    Some(biguint_to_field(encoded.magnitude()))
+}
+
+fn parse_value(name: &str, value: &Value, typ: &AbiType) -> Result<InputValue, AbiError> {
+    let parsed = match (value, typ) {
+        (Value::Bool(value), AbiType::Boolean) => {
+            InputValue::Field(FieldElement::from(*value as i128))
+        }
+        (_, AbiType::Boolean) => match parse_integer(value) {
+            Some(value) if value.is_zero() || value.is_one() => {
+                InputValue::Field(biguint_to_field(value.magnitude()))
+            }
+            _ => return Err(type_mismatch(name, typ)),
+        },
+        (_, AbiType::Field | AbiType::Integer { .. }) => parse_integer(value)
+            .and_then(|value| encode_integer(&value, typ))
+            .map(InputValue::Field)
+            .ok_or_else(|| type_mismatch(name, typ))?,
+        (Value::Array(elements), AbiType::Array { length, typ })
+            if elements.len() == *length as usize =>
+        {
+            InputValue::Vec(
+                elements
+                    .iter()
+                    .enumerate()
+                    .map(|(index, element)| parse_value(&format!("{name}[{index}]"), element, typ))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        (Value::String(string), AbiType::String { length }) if string.len() == *length as usize => {
+            InputValue::String(string.clone())
+        }
+        (Value::Object(members), AbiType::Struct { fields }) => {
+            if let Some(member) = members
+                .keys()
+                .find(|member| !fields.iter().any(|(field_name, _)| field_name == *member))
+            {
+                return Err(AbiError::UnexpectedParameter(format!("{name}.{member}")));
+            }
+            InputValue::Struct(
+                fields
+                    .iter()
+                    .map(|(field_name, typ)| {
+                        let member_name = format!("{name}.{field_name}");
+                        let member = members
+                            .get(field_name)
+                            .ok_or_else(|| AbiError::MissingParameter(member_name.clone()))?;
+                        Ok((field_name.clone(), parse_value(&member_name, member, typ)?))
+                    })
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        _ => return Err(type_mismatch(name, typ)),
+    };
+    Ok(parsed)
+}
+
+fn to_json(name: &str, value: &InputValue, typ: &AbiType) -> Result<Value, AbiError> {
+    let json = match (value, typ) {
+        (InputValue::Field(value), _) if !is_valid_scalar(*value, typ) => {
+            return Err(type_mismatch(name, typ))
+        }
+        (InputValue::Field(value), AbiType::Field) => {
+            Value::String(format!("0x{}", value.to_hex()))
+        }
+        (InputValue::Field(value), AbiType::Integer { sign, width }) => {
+            let mut integer = BigInt::from(field_to_biguint(*value));
+            if *sign == Sign::Signed && integer >= BigInt::one() << (width - 1) {
+                integer -= BigInt::one() << *width;
+            }
+            Value::String(integer.to_string())
+        }
+        (InputValue::Field(value), AbiType::Boolean) => Value::Bool(value.is_one()),
+        (InputValue::Vec(elements), AbiType::Array { length, typ })
+            if elements.len() == *length as usize =>
+        {
+            Value::Array(
+                elements
+                    .iter()
+                    .enumerate()
+                    .map(|(index, element)| to_json(&format!("{name}[{index}]"), element, typ))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        (InputValue::String(string), AbiType::String { length })
+            if string.len() == *length as usize =>
+        {
+            Value::String(string.clone())
+        }
+        (InputValue::Struct(members), AbiType::Struct { fields })
+            if members.len() == fields.len() =>
+        {
+            let mut object = serde_json::Map::new();
+            for (field_name, typ) in fields {
+                let member_name = format!("{name}.{field_name}");
+                let member = members
+                    .get(field_name)
+                    .ok_or_else(|| AbiError::MissingParameter(member_name.clone()))?;
+                object.insert(field_name.clone(), to_json(&member_name, member, typ)?);
+            }
+            Value::Object(object)
+        }
+        _ => return Err(type_mismatch(name, typ)),
+    };
+    Ok(json)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn param(name: &str, typ: AbiType) -> AbiParameter {
+        AbiParameter {
+            name: name.to_owned(),
+            typ,
+            visibility: AbiVisibility::Private,
+        }
+    }
+
+    fn abi() -> Abi {
+        Abi {
+            parameters: vec![
+                param("x", AbiType::Field),
+                param(
+                    "ys",
+                    AbiType::Array {
+                        length: 2,
+                        typ: Box::new(AbiType::Integer {
+                            sign: Sign::Signed,
+                            width: 8,
+                        }),
+                    },
+                ),
+                param(
+                    "point",
+                    AbiType::Struct {
+                        fields: vec![
+                            (
+                                "z".to_owned(),
+                                AbiType::Integer {
+                                    sign: Sign::Unsigned,
+                                    width: 32,
+                                },
+                            ),
+                            ("valid".to_owned(), AbiType::Boolean),
+                        ],
+                    },
+                ),
+                param("name", AbiType::String { length: 2 }),
+            ],
+        }
+    }
+
+    const TOML_INPUTS: &str = r#"
+        x = "0x10"
+        ys = [-1, "5"]
+        name = "hi"
+
+        [point]
+        z = 7
+        valid = true
+    "#;
+
+    #[test]
+    fn inputs_are_flattened_in_declaration_order() {
+        let abi = abi();
+        let inputs = Format::Toml.parse(TOML_INPUTS, &abi).unwrap();
+        let witness_map = abi.encode(&inputs).unwrap();
+
+        assert_eq!(abi.field_count(), Ok(7));
+        let values: Vec<_> = [16, 255, 5, 7, 1, b'h' as i128, b'i' as i128]
+            .into_iter()
+            .map(FieldElement::from)
+            .collect();
+        assert_eq!(
+            witness_map.keys().copied().collect::<Vec<_>>(),
+            (1..=7).map(Witness).collect::<Vec<_>>()
+        );
+        assert_eq!(witness_map.values().copied().collect::<Vec<_>>(), values);
+
+        assert_eq!(abi.decode(&witness_map).unwrap(), inputs);
+    }
+
+    #[test]
+    fn formats_roundtrip() {
+        let abi = abi();
+        let inputs = Format::Toml.parse(TOML_INPUTS, &abi).unwrap();
+        for format in [Format::Json, Format::Toml] {
+            let serialized = format.serialize(&inputs, &abi).unwrap();
+            assert_eq!(format.parse(&serialized, &abi).unwrap(), inputs);
+        }
+
+        let json = Format::Json.serialize(&inputs, &abi).unwrap();
+        let json: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["ys"], serde_json::json!(["-1", "5"]));
+        assert_eq!(json["point"]["valid"], Value::Bool(true));
+    }
+
+    #[test]
+    fn out_of_range_inputs_are_rejected() {
+        let abi = abi();
+        let with = |ys: &str| TOML_INPUTS.replace(r#"[-1, "5"]"#, ys);
+
+        for ys in ["[-129, 0]", "[128, 0]", "[0]", r#"["0x1g", 0]"#] {
+            assert_eq!(
+                Format::Toml.parse(&with(ys), &abi),
+                Err(AbiError::TypeMismatch {
+                    name: if ys == "[0]" { "ys" } else { "ys[0]" }.to_owned(),
+                    typ: if ys == "[0]" {
+                        abi.parameters[1].typ.clone()
+                    } else {
+                        AbiType::Integer {
+                            sign: Sign::Signed,
+                            width: 8,
+                        }
+                    },
+                })
+            );
+        }
+        assert_eq!(
+            Format::Toml.parse(&with("[-128, 127]"), &abi).map(|_| ()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn parameter_names_must_match() {
+        let abi = abi();
+        let missing = TOML_INPUTS.replace(r#"x = "0x10""#, "");
+        assert_eq!(
+            Format::Toml.parse(&missing, &abi),
+            Err(AbiError::MissingParameter("x".to_owned()))
+        );
+
+        let extra = format!("w = 1\n{TOML_INPUTS}");
+        assert_eq!(
+            Format::Toml.parse(&extra, &abi),
+            Err(AbiError::UnexpectedParameter("w".to_owned()))
+        );
+
+        let mut witness_map = abi
+            .encode(&Format::Toml.parse(TOML_INPUTS, &abi).unwrap())
+            .unwrap();
+        witness_map.remove(&Witness(4));
+        assert_eq!(
+            abi.decode(&witness_map),
+            Err(AbiError::MissingWitness {
+                name: "point".to_owned(),
+                witness: Witness(4)
+            })
+        );
+    }
+
+    #[test]
+    fn large_lengths_are_not_trusted() {
+        let array = |length, typ| AbiType::Array {
+            length,
+            typ: Box::new(typ),
+        };
+        let overflowing = array(u32::MAX, array(2, AbiType::Field));
+        assert_eq!(overflowing.field_count(), Err(AbiError::TooManyFields));
+        let abi = Abi {
+            parameters: vec![
+                param("xs", array(u32::MAX, AbiType::Field)),
+                param("y", AbiType::Field),
+            ],
+        };
+        assert_eq!(abi.field_count(), Err(AbiError::TooManyFields));
+        assert_eq!(abi.decode(&WitnessMap::new()), Err(AbiError::TooManyFields));
+
+        // Decoding fails at the first missing witness, rather than allocating every value
+        let abi = Abi {
+            parameters: vec![param("xs", array(u32::MAX, AbiType::Field))],
+        };
+        assert_eq!(
+            abi.decode(&WitnessMap::new()),
+            Err(AbiError::MissingWitness {
+                name: "xs".to_owned(),
+                witness: Witness(1)
+            })
+        );
+    }
+}
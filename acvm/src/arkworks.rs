@@ -0,0 +1,728 @@
+// A reference backend which proves circuits with Groth16 over BN254, using arkworks
+//
+// Circuits are compiled to R1CS, and every black box call is replaced by its fallback, so
+// `compiler::to_r1cs` gives the constraint system which is synthesized for arkworks. The
+// Groth16 public inputs are the public inputs of the circuit, in order.
+//
+// Proofs are encoded as the EVM precompiles expect them, so the same bytes are verified
+// natively and by the Solidity verifier:
+//
+// - `A` as the words `x, y`
+// - `B` as the words `x.c1, x.c0, y.c1, y.c0`, with the imaginary part first
+// - `C` as the words `x, y`
+//
+// where each word is a big-endian 32 byte integer. The point at infinity is `(0, 0)`.
+//
+// Groth16 needs a trusted setup for every circuit. The setup is derived from the circuit
+// digest and the setup seed of the backend, so that `prove_with_meta` and `verify_from_cs`
+// agree on the keys without storing them. Anyone who knows the seed can forge proofs, so
+// keys which are deployed must come from a backend with a secret, random seed.
+
+use std::collections::BTreeMap;
+
+use acir::{
+    circuit::{opcodes::BlackBoxFuncCall, Circuit},
+    native_types::Witness,
+    BlackBoxFunc, FieldElement,
+};
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ff::{BigInteger, BigInteger256, PrimeField, Zero};
+use ark_groth16::{
+    create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystemRef, LinearCombination, SynthesisError, Variable,
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::{rngs::StdRng, SeedableRng};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    circuit_digest,
+    compiler::{to_r1cs, R1cs, SparseRow},
+    pwg, Backend, BackendError, Contract, ContractGenError, ContractOptions, ContractPlatform,
+    FieldModulus, Language, OpcodeResolutionError, PartialWitnessGenerator, ProofMismatch,
+    ProofSystemCompiler, ProvingKey, SmartContract, VerificationKey, WitnessMap,
+};
+
+/// The length in bytes of an encoded proof
+pub const PROOF_LENGTH: usize = 8 * 32;
+
+/// A Groth16 backend over BN254, for testing and small deployments.
+///
+/// See the module documentation regarding the trusted setup.
+#[derive(Clone, Debug, Default)]
+pub struct ArkworksBackend {
+    setup_seed: [u8; 32],
+}
+
+impl ArkworksBackend {
+    /// Creates a backend whose setup seed is zero, which is only suitable for testing
+    pub fn new() -> Self {
+        ArkworksBackend::default()
+    }
+
+    /// Sets the seed which the trusted setup of each circuit is derived from
+    pub fn with_setup_seed(mut self, setup_seed: [u8; 32]) -> Self {
+        self.setup_seed = setup_seed;
+        self
+    }
+
+    fn setup(&self, circuit: &Circuit) -> Result<ark_groth16::ProvingKey<Bn254>, BackendError> {
+        let r1cs = r1cs(circuit)?;
+        let mut hasher = Sha256::new();
+        hasher.update(self.setup_seed);
+        hasher.update(circuit_digest(circuit));
+        let mut seed = [0; 32];
+        seed.copy_from_slice(&hasher.finalize());
+
+        let synthesizer = Synthesizer {
+            r1cs: &r1cs,
+            public_inputs: &circuit.public_inputs.0,
+            assignment: None,
+        };
+        generate_random_parameters(synthesizer, &mut StdRng::from_seed(seed)).map_err(synthesis)
+    }
+
+    fn prove(
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+        proving_key: &ark_groth16::ProvingKey<Bn254>,
+    ) -> Result<Vec<u8>, BackendError> {
+        let r1cs = r1cs(circuit)?;
+        // Witnesses which are not solved are not used by any opcode, unless the witness
+        // is incomplete, in which case the assignment does not satisfy the constraints
+        let mut witness_values = witness_values.clone();
+        for witness in &r1cs.witnesses {
+            if !witness_values.contains_key(witness) {
+                witness_values.insert(*witness, FieldElement::zero());
+            }
+        }
+        let assignment = r1cs.assignment(&witness_values).map_err(|err| {
+            BackendError::Other(format!("witness does not satisfy the circuit: {err}"))
+        })?;
+        if !r1cs.is_satisfied(&assignment) {
+            return Err(BackendError::Other(
+                "witness does not satisfy the circuit".to_owned(),
+            ));
+        }
+
+        let synthesizer = Synthesizer {
+            r1cs: &r1cs,
+            public_inputs: &circuit.public_inputs.0,
+            assignment: Some(assignment),
+        };
+        let proof = create_random_proof(synthesizer, proving_key, &mut rand::thread_rng())
+            .map_err(synthesis)?;
+
+        let mut words = g1_words(&proof.a).to_vec();
+        words.extend(g2_words(&proof.b));
+        words.extend(g1_words(&proof.c));
+        Ok(words.concat())
+    }
+
+    fn verify(
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+        verification_key: &ark_groth16::VerifyingKey<Bn254>,
+    ) -> Result<bool, BackendError> {
+        let expected = circuit.public_inputs.0.len();
+        if public_inputs.len() != expected {
+            return Err(ProofMismatch::NumPublicInputs {
+                expected: expected as u32,
+                found: public_inputs.len() as u32,
+            }
+            .into());
+        }
+        let proof = decode_proof(proof).ok_or_else(|| {
+            BackendError::SerializationError("proof is not a valid Groth16 proof".to_owned())
+        })?;
+
+        let public_inputs: Vec<Fr> = public_inputs.into_iter().map(to_ark_field).collect();
+        verify_proof(
+            &prepare_verifying_key(verification_key),
+            &proof,
+            &public_inputs,
+        )
+        .map_err(synthesis)
+    }
+}
+
+impl Backend for ArkworksBackend {}
+
+impl PartialWitnessGenerator for ArkworksBackend {
+    fn solve_blackbox_function_call(
+        &self,
+        initial_witness: &mut WitnessMap,
+        func_call: &BlackBoxFuncCall,
+    ) -> Result<(), OpcodeResolutionError> {
+        pwg::solve_native_black_box(initial_witness, func_call)
+    }
+}
+
+impl SmartContract for ArkworksBackend {
+    fn contract_from_cs(
+        &self,
+        circuit: &Circuit,
+        platform: ContractPlatform,
+        options: &ContractOptions,
+    ) -> Result<Contract, ContractGenError> {
+        let (_, verification_key) = self.preprocess(circuit)?;
+        self.verifier_contract(&verification_key, platform, options)
+    }
+
+    fn verifier_contract(
+        &self,
+        verification_key: &VerificationKey,
+        platform: ContractPlatform,
+        options: &ContractOptions,
+    ) -> Result<Contract, ContractGenError> {
+        if platform != ContractPlatform::EvmSolidity {
+            return Err(ContractGenError::UnsupportedPlatform(platform));
+        }
+        options.validate()?;
+        let (digest, verification_key) = split_key(&verification_key.0)?;
+        let verification_key = deserialize::<ark_groth16::VerifyingKey<Bn254>>(verification_key)?;
+        Ok(Contract {
+            platform,
+            source: solidity_verifier(digest, &verification_key, options),
+        })
+    }
+
+    fn eth_contract_from_cs(
+        &self,
+        circuit: &Circuit,
+        options: &ContractOptions,
+    ) -> Result<String, ContractGenError> {
+        self.contract_from_cs(circuit, ContractPlatform::EvmSolidity, options)
+            .map(|contract| contract.source)
+    }
+}
+
+impl ProofSystemCompiler for ArkworksBackend {
+    fn np_language(&self) -> Language {
+        Language::R1CS
+    }
+
+    fn name(&self) -> &str {
+        "arkworks_groth16_bn254"
+    }
+
+    fn np_field(&self) -> FieldModulus {
+        FieldModulus::bn254()
+    }
+
+    fn blackbox_function_supported(&self, _opcode: &BlackBoxFunc) -> bool {
+        false
+    }
+
+    fn prove_with_meta(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+    ) -> Result<Vec<u8>, BackendError> {
+        ArkworksBackend::prove(circuit, witness_values, &self.setup(circuit)?)
+    }
+
+    fn verify_from_cs(
+        &self,
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+    ) -> Result<bool, BackendError> {
+        let proving_key = self.setup(circuit)?;
+        ArkworksBackend::verify(proof, public_inputs, circuit, &proving_key.vk)
+    }
+
+    fn get_exact_circuit_size(&self, circuit: &Circuit) -> Result<u32, BackendError> {
+        Ok(to_r1cs(circuit)?.num_constraints() as u32)
+    }
+
+    fn preprocess(&self, circuit: &Circuit) -> Result<(ProvingKey, VerificationKey), BackendError> {
+        let proving_key = self.setup(circuit)?;
+        let digest = circuit_digest(circuit);
+        Ok((
+            ProvingKey(serialize(digest, &proving_key)?),
+            VerificationKey(serialize(digest, &proving_key.vk)?),
+        ))
+    }
+
+    fn prove_with_pk(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+        proving_key: &ProvingKey,
+    ) -> Result<Vec<u8>, BackendError> {
+        let proving_key = circuit_key(circuit, &proving_key.0)?;
+        ArkworksBackend::prove(circuit, witness_values, &proving_key)
+    }
+
+    fn verify_with_vk(
+        &self,
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+        verification_key: &VerificationKey,
+    ) -> Result<bool, BackendError> {
+        let verification_key = circuit_key(circuit, &verification_key.0)?;
+        ArkworksBackend::verify(proof, public_inputs, circuit, &verification_key)
+    }
+}
+
+// Synthesizes the R1CS of a circuit, with a Groth16 public input for each public input
+// of the circuit. Repeated public inputs are constrained to be equal to the first.
+struct Synthesizer<'a> {
+    r1cs: &'a R1cs,
+    public_inputs: &'a [Witness],
+    // The assignment vector `z` of `r1cs`, which is only needed for proving
+    assignment: Option<Vec<FieldElement>>,
+}
+
+impl ConstraintSynthesizer<Fr> for Synthesizer<'_> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let value = |var: usize| {
+            self.assignment
+                .as_ref()
+                .map(|assignment| to_ark_field(assignment[var]))
+                .ok_or(SynthesisError::AssignmentMissing)
+        };
+
+        let mut inputs: BTreeMap<Witness, Variable> = BTreeMap::new();
+        for witness in self.public_inputs {
+            let var = 1 + self.r1cs.witnesses[..self.r1cs.num_public]
+                .iter()
+                .position(|public| public == witness)
+                .expect("infallible: public inputs come first in the R1CS");
+            let input = cs.new_input_variable(|| value(var))?;
+            match inputs.get(witness) {
+                Some(first) => cs.enforce_constraint(
+                    LinearCombination::from(*first),
+                    LinearCombination::from(Variable::One),
+                    LinearCombination::from(input),
+                )?,
+                None => {
+                    inputs.insert(*witness, input);
+                }
+            }
+        }
+
+        let mut variables = vec![Variable::One];
+        variables.extend(
+            self.r1cs.witnesses[..self.r1cs.num_public]
+                .iter()
+                .map(|witness| inputs[witness]),
+        );
+        for var in variables.len()..self.r1cs.num_vars {
+            variables.push(cs.new_witness_variable(|| value(var))?);
+        }
+
+        let linear_combination = |row: &SparseRow| {
+            row.iter()
+                .fold(LinearCombination::zero(), |lc, (var, coeff)| {
+                    lc + (to_ark_field(*coeff), variables[*var])
+                })
+        };
+        for ((a, b), c) in self.r1cs.a.iter().zip(&self.r1cs.b).zip(&self.r1cs.c) {
+            cs.enforce_constraint(
+                linear_combination(a),
+                linear_combination(b),
+                linear_combination(c),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn r1cs(circuit: &Circuit) -> Result<R1cs, BackendError> {
+    to_r1cs(circuit).map_err(|err| BackendError::Other(err.to_string()))
+}
+
+fn synthesis(err: SynthesisError) -> BackendError {
+    BackendError::Other(format!("Groth16 failed: {err}"))
+}
+
+/// Converts a field element into the scalar field of BN254
+pub fn to_ark_field(value: FieldElement) -> Fr {
+    Fr::from_be_bytes_mod_order(&value.to_be_bytes())
+}
+
+// Keys are the digest of their circuit followed by the serialized arkworks key
+fn serialize<T: CanonicalSerialize>(digest: [u8; 32], key: &T) -> Result<Vec<u8>, BackendError> {
+    let mut bytes = digest.to_vec();
+    key.serialize(&mut bytes)
+        .map_err(|err| BackendError::SerializationError(err.to_string()))?;
+    Ok(bytes)
+}
+
+fn deserialize<T: CanonicalDeserialize>(bytes: &[u8]) -> Result<T, BackendError> {
+    T::deserialize(bytes).map_err(|err| BackendError::SerializationError(err.to_string()))
+}
+
+fn split_key(key: &[u8]) -> Result<(&[u8], &[u8]), BackendError> {
+    if key.len() < 32 {
+        return Err(BackendError::SerializationError(
+            "key is too short".to_owned(),
+        ));
+    }
+    Ok(key.split_at(32))
+}
+
+fn circuit_key<T: CanonicalDeserialize>(circuit: &Circuit, key: &[u8]) -> Result<T, BackendError> {
+    let (digest, key) = split_key(key)?;
+    if digest != circuit_digest(circuit) {
+        return Err(BackendError::Other(
+            "key was derived for a different circuit".to_owned(),
+        ));
+    }
+    deserialize(key)
+}
+
+fn fq_word(value: &Fq) -> [u8; 32] {
+    let mut word = [0; 32];
+    word.copy_from_slice(&value.into_repr().to_bytes_be());
+    word
+}
+
+fn g1_words(point: &G1Affine) -> [[u8; 32]; 2] {
+    if point.is_zero() {
+        return [[0; 32]; 2];
+    }
+    [fq_word(&point.x), fq_word(&point.y)]
+}
+
+fn g2_words(point: &G2Affine) -> [[u8; 32]; 4] {
+    if point.is_zero() {
+        return [[0; 32]; 4];
+    }
+    [
+        fq_word(&point.x.c1),
+        fq_word(&point.x.c0),
+        fq_word(&point.y.c1),
+        fq_word(&point.y.c0),
+    ]
+}
+
+// Returns None if the word is not the canonical encoding of an element of the base field
+fn fq_from_word(word: &[u8]) -> Option<Fq> {
+    let mut limbs = [0; 4];
+    for (limb, bytes) in limbs.iter_mut().rev().zip(word.chunks_exact(8)) {
+        *limb = u64::from_be_bytes(bytes.try_into().ok()?);
+    }
+    Fq::from_repr(BigInteger256(limbs))
+}
+
+// Returns None if the words are not the encoding of a point in the prime order subgroup
+fn g1_from_words(words: &[&[u8]]) -> Option<G1Affine> {
+    let [x, y] = words else { return None };
+    let (x, y) = (fq_from_word(x)?, fq_from_word(y)?);
+    if x.is_zero() && y.is_zero() {
+        return Some(G1Affine::zero());
+    }
+    let point = G1Affine::new(x, y, false);
+    (point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve()).then_some(point)
+}
+
+fn g2_from_words(words: &[&[u8]]) -> Option<G2Affine> {
+    let [x_c1, x_c0, y_c1, y_c0] = words else {
+        return None;
+    };
+    let x = Fq2::new(fq_from_word(x_c0)?, fq_from_word(x_c1)?);
+    let y = Fq2::new(fq_from_word(y_c0)?, fq_from_word(y_c1)?);
+    if x.is_zero() && y.is_zero() {
+        return Some(G2Affine::zero());
+    }
+    let point = G2Affine::new(x, y, false);
+    (point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve()).then_some(point)
+}
+
+fn decode_proof(proof: &[u8]) -> Option<ark_groth16::Proof<Bn254>> {
+    if proof.len() != PROOF_LENGTH {
+        return None;
+    }
+    let words: Vec<&[u8]> = proof.chunks_exact(32).collect();
+    Some(ark_groth16::Proof {
+        a: g1_from_words(&words[0..2])?,
+        b: g2_from_words(&words[2..6])?,
+        c: g1_from_words(&words[6..8])?,
+    })
+}
+
+// The verification key as the words of `alpha`, `beta`, `gamma`, `delta` and then the
+// point of the constant term and of each public input
+fn verification_key_words(verification_key: &ark_groth16::VerifyingKey<Bn254>) -> Vec<[u8; 32]> {
+    let mut words = g1_words(&verification_key.alpha_g1).to_vec();
+    for point in [
+        &verification_key.beta_g2,
+        &verification_key.gamma_g2,
+        &verification_key.delta_g2,
+    ] {
+        words.extend(g2_words(point));
+    }
+    for point in &verification_key.gamma_abc_g1 {
+        words.extend(g1_words(point));
+    }
+    words
+}
+
+fn solidity_verifier(
+    digest: &[u8],
+    verification_key: &ark_groth16::VerifyingKey<Bn254>,
+    options: &ContractOptions,
+) -> String {
+    let name = &options.contract_name;
+    let words = verification_key_words(verification_key);
+    let vk_length = words.len();
+    let num_public_inputs = verification_key.gamma_abc_g1.len() - 1;
+    let (vk_source, vk_value) = if options.inline_verification_key {
+        // The first element gives the type of the array literal
+        let words: Vec<String> = words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| match i {
+                0 => format!("uint256(0x{})", hex::encode(word)),
+                _ => format!("0x{}", hex::encode(word)),
+            })
+            .collect();
+        (
+            format!(
+                "    function verificationKey() internal pure returns (uint256[{vk_length}] memory) {{\n        \
+                return [\n            {}\n        ];\n    }}\n",
+                words.join(",\n            ")
+            ),
+            "verificationKey()",
+        )
+    } else {
+        (
+            format!(
+                "    uint256[{vk_length}] private verificationKey;\n\n    \
+                constructor(uint256[{vk_length}] memory key) {{\n        \
+                verificationKey = key;\n    }}\n"
+            ),
+            "verificationKey",
+        )
+    };
+
+    format!(
+        r#"{header}
+// Groth16 verifier over BN254 for the circuit with digest {digest}
+contract {name} {{
+    // The base field and scalar field of BN254
+    uint256 constant Q = 21888242871839275222246405745257275088696311157297823662689037894645226208583;
+    uint256 constant R = 21888242871839275222246405745257275088548364400416034343698204186575808495617;
+    uint256 constant NUM_PUBLIC_INPUTS = {num_public_inputs};
+
+    // alpha in G1, beta, gamma and delta in G2, and then a point in G1 for the constant
+    // term and for each public input
+{vk_source}
+    function verify(bytes calldata proof, bytes32[] calldata publicInputs) external view returns (bool) {{
+        require(proof.length == {PROOF_LENGTH}, "invalid proof length");
+        require(publicInputs.length == NUM_PUBLIC_INPUTS, "invalid number of public inputs");
+        uint256[{vk_length}] memory vk = {vk_value};
+        // A in G1, B in G2 and C in G1
+        uint256[8] memory p = abi.decode(proof, (uint256[8]));
+
+        uint256[2] memory acc = [vk[14], vk[15]];
+        for (uint256 i = 0; i < NUM_PUBLIC_INPUTS; i++) {{
+            uint256 input = uint256(publicInputs[i]);
+            require(input < R, "public input is not in the field");
+            acc = ecAdd(acc, ecMul([vk[16 + 2 * i], vk[17 + 2 * i]], input));
+        }}
+
+        // e(-A, B) * e(alpha, beta) * e(acc, gamma) * e(C, delta) == 1
+        uint256[24] memory pairs = [
+            p[0], (Q - p[1]) % Q, p[2], p[3], p[4], p[5],
+            vk[0], vk[1], vk[2], vk[3], vk[4], vk[5],
+            acc[0], acc[1], vk[6], vk[7], vk[8], vk[9],
+            p[6], p[7], vk[10], vk[11], vk[12], vk[13]
+        ];
+        uint256[1] memory result;
+        bool success;
+        assembly {{
+            success := staticcall(gas(), 0x08, pairs, 768, result, 32)
+        }}
+        return success && result[0] == 1;
+    }}
+
+    function ecAdd(uint256[2] memory a, uint256[2] memory b) internal view returns (uint256[2] memory r) {{
+        uint256[4] memory input = [a[0], a[1], b[0], b[1]];
+        bool success;
+        assembly {{
+            success := staticcall(gas(), 0x06, input, 128, r, 64)
+        }}
+        require(success, "elliptic curve addition failed");
+    }}
+
+    function ecMul(uint256[2] memory a, uint256 s) internal view returns (uint256[2] memory r) {{
+        uint256[3] memory input = [a[0], a[1], s];
+        bool success;
+        assembly {{
+            success := staticcall(gas(), 0x07, input, 96, r, 64)
+        }}
+        require(success, "elliptic curve multiplication failed");
+    }}
+}}
+"#,
+        header = options.solidity_header(),
+        digest = hex::encode(digest),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{backend_tests, compiler::compile_for_backend, public_input_values};
+    use acir::{
+        circuit::{
+            opcodes::{FunctionInput, Opcode},
+            PublicInputs,
+        },
+        native_types::Expression,
+    };
+
+    // x * y + 3 * z - o = 0 with o public, repeated, and z range constrained to 8 bits
+    fn circuit() -> Circuit {
+        let (x, y, z, o) = (Witness(1), Witness(2), Witness(3), Witness(4));
+        let circuit = Circuit {
+            current_witness_index: 4,
+            opcodes: vec![
+                Opcode::Arithmetic(Expression {
+                    mul_terms: vec![(FieldElement::one(), x, y)],
+                    linear_combinations: vec![
+                        (FieldElement::from(3_i128), z),
+                        (-FieldElement::one(), o),
+                    ],
+                    q_c: FieldElement::zero(),
+                }),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::RANGE,
+                    inputs: vec![FunctionInput {
+                        witness: z,
+                        num_bits: 8,
+                    }],
+                    outputs: Vec::new(),
+                }),
+            ],
+            public_inputs: PublicInputs(vec![o, x, o]),
+            return_values: PublicInputs::default(),
+        };
+        compile_for_backend(circuit, &ArkworksBackend::new()).unwrap()
+    }
+
+    fn solved_witness(circuit: &Circuit) -> WitnessMap {
+        let mut witness = WitnessMap::from([
+            (Witness(1), FieldElement::from(6_i128)),
+            (Witness(2), FieldElement::from(7_i128)),
+            (Witness(3), FieldElement::from(200_i128)),
+        ]);
+        ArkworksBackend::new()
+            .solve(&mut witness, circuit.opcodes.clone())
+            .unwrap();
+        witness
+    }
+
+    #[test]
+    fn conformance() {
+        assert_eq!(backend_tests::run_all(&ArkworksBackend::new()), Ok(()));
+    }
+
+    #[test]
+    fn proofs_verify_against_their_public_inputs() {
+        let backend = ArkworksBackend::new();
+        let circuit = circuit();
+        let witness = solved_witness(&circuit);
+        let public_inputs = public_input_values(&circuit, &witness).unwrap();
+        let (six, six_hundred_forty_two) =
+            (FieldElement::from(6_i128), FieldElement::from(642_i128));
+        assert_eq!(
+            public_inputs,
+            vec![six_hundred_forty_two, six, six_hundred_forty_two]
+        );
+
+        let (pk, vk) = backend.preprocess(&circuit).unwrap();
+        let proof = backend.prove_with_pk(&circuit, &witness, &pk).unwrap();
+        assert_eq!(proof.len(), PROOF_LENGTH);
+        assert_eq!(
+            backend.verify_with_vk(&proof, public_inputs, &circuit, &vk),
+            Ok(true)
+        );
+
+        // Repeated public inputs must have the same value
+        let inconsistent = vec![
+            six_hundred_forty_two,
+            six,
+            six_hundred_forty_two + FieldElement::one(),
+        ];
+        assert_eq!(
+            backend.verify_with_vk(&proof, inconsistent, &circuit, &vk),
+            Ok(false)
+        );
+        assert!(matches!(
+            backend.verify_with_vk(&proof, vec![six], &circuit, &vk),
+            Err(BackendError::ProofMismatch(_))
+        ));
+        assert!(matches!(
+            backend.verify_with_vk(&proof[1..], vec![six; 3], &circuit, &vk),
+            Err(BackendError::SerializationError(_))
+        ));
+
+        // Keys are derived from the setup seed
+        let other = ArkworksBackend::new().with_setup_seed([1; 32]);
+        let (_, other_vk) = other.preprocess(&circuit).unwrap();
+        assert_ne!(vk, other_vk);
+        assert_eq!(
+            other.verify_with_vk(
+                &proof,
+                public_input_values(&circuit, &witness).unwrap(),
+                &circuit,
+                &other_vk
+            ),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn contracts_embed_the_verification_key() {
+        let backend = ArkworksBackend::new();
+        let circuit = circuit();
+        let (_, vk) = backend.preprocess(&circuit).unwrap();
+        let (_, ark_vk) = split_key(&vk.0).unwrap();
+        let ark_vk: ark_groth16::VerifyingKey<Bn254> = deserialize(ark_vk).unwrap();
+
+        // The words which the contract reads decode to the same points
+        let words = verification_key_words(&ark_vk);
+        assert_eq!(words.len(), 14 + 2 * 4);
+        let words: Vec<&[u8]> = words.iter().map(|word| word.as_slice()).collect();
+        assert_eq!(g1_from_words(&words[0..2]), Some(ark_vk.alpha_g1));
+        assert_eq!(g2_from_words(&words[2..6]), Some(ark_vk.beta_g2));
+        assert_eq!(g2_from_words(&words[10..14]), Some(ark_vk.delta_g2));
+        assert_eq!(g1_from_words(&words[20..22]), Some(ark_vk.gamma_abc_g1[3]));
+
+        let options = ContractOptions::default();
+        let contract = backend
+            .verifier_contract(&vk, ContractPlatform::EvmSolidity, &options)
+            .unwrap();
+        assert_eq!(
+            Ok(contract.source.clone()),
+            backend.eth_contract_from_cs(&circuit, &options)
+        );
+        assert!(contract.source.starts_with(&options.solidity_header()));
+        assert!(contract.source.contains("NUM_PUBLIC_INPUTS = 3;"));
+        assert!(contract.source.contains(&hex::encode(words[21])));
+
+        let supplied = ContractOptions {
+            inline_verification_key: false,
+            ..ContractOptions::default()
+        };
+        let source = backend.eth_contract_from_cs(&circuit, &supplied).unwrap();
+        assert!(source.contains("constructor(uint256[22] memory key)"));
+        assert!(!source.contains(&hex::encode(words[21])));
+
+        assert_eq!(
+            backend.contract_from_cs(&circuit, ContractPlatform::EvmYul, &options),
+            Err(ContractGenError::UnsupportedPlatform(
+                ContractPlatform::EvmYul
+            ))
+        );
+    }
+}
@@ -0,0 +1,251 @@
+// Async variants of the proving and verification methods
+//
+// Proving can take seconds to minutes, so hosts which run on an async
+// executor should not call the `ProofSystemCompiler` methods directly.
+
+use std::sync::Arc;
+
+use acir::circuit::Circuit;
+use async_trait::async_trait;
+
+use crate::{
+    BackendError, FieldElement, ProofSystemCompiler, ProvingKey, VerificationKey, WitnessMap,
+};
+
+/// The proving and verification methods of `ProofSystemCompiler`, which can be awaited.
+///
+/// Backends which have a native async implementation can implement this trait directly.
+/// Otherwise `BlockingAdapter` implements it for any `ProofSystemCompiler`.
+#[async_trait]
+pub trait AsyncProofSystemCompiler {
+    async fn preprocess(
+        &self,
+        circuit: &Circuit,
+    ) -> Result<(ProvingKey, VerificationKey), BackendError>;
+
+    async fn prove_with_meta(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+    ) -> Result<Vec<u8>, BackendError>;
+
+    async fn verify_from_cs(
+        &self,
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+    ) -> Result<bool, BackendError>;
+
+    async fn prove_with_pk(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+        proving_key: &ProvingKey,
+    ) -> Result<Vec<u8>, BackendError>;
+
+    async fn verify_with_vk(
+        &self,
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+        verification_key: &VerificationKey,
+    ) -> Result<bool, BackendError>;
+}
+
+/// Runs the methods of a synchronous backend on tokio's blocking thread pool.
+///
+/// Arguments which are passed by reference are cloned, as the blocking
+/// task may outlive the borrow. This must be used from within a tokio runtime.
+pub struct BlockingAdapter<B> {
+    backend: Arc<B>,
+}
+
+impl<B> BlockingAdapter<B> {
+    pub fn new(backend: B) -> Self {
+        BlockingAdapter {
+            backend: Arc::new(backend),
+        }
+    }
+
+    pub fn from_arc(backend: Arc<B>) -> Self {
+        BlockingAdapter { backend }
+    }
+}
+
+impl<B: ProofSystemCompiler + Send + Sync + 'static> BlockingAdapter<B> {
+    async fn spawn<T, F>(&self, f: F) -> Result<T, BackendError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&B) -> Result<T, BackendError> + Send + 'static,
+    {
+        let backend = self.backend.clone();
+        tokio::task::spawn_blocking(move || f(&backend))
+            .await
+            .map_err(|err| BackendError::Other(err.to_string()))?
+    }
+}
+
+#[async_trait]
+impl<B: ProofSystemCompiler + Send + Sync + 'static> AsyncProofSystemCompiler
+    for BlockingAdapter<B>
+{
+    async fn preprocess(
+        &self,
+        circuit: &Circuit,
+    ) -> Result<(ProvingKey, VerificationKey), BackendError> {
+        let circuit = circuit.clone();
+        self.spawn(move |backend| backend.preprocess(&circuit))
+            .await
+    }
+
+    async fn prove_with_meta(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+    ) -> Result<Vec<u8>, BackendError> {
+        let circuit = circuit.clone();
+        let witness_values = witness_values.clone();
+        self.spawn(move |backend| backend.prove_with_meta(&circuit, &witness_values))
+            .await
+    }
+
+    async fn verify_from_cs(
+        &self,
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+    ) -> Result<bool, BackendError> {
+        let proof = proof.to_vec();
+        let circuit = circuit.clone();
+        self.spawn(move |backend| backend.verify_from_cs(&proof, public_inputs, &circuit))
+            .await
+    }
+
+    async fn prove_with_pk(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+        proving_key: &ProvingKey,
+    ) -> Result<Vec<u8>, BackendError> {
+        let circuit = circuit.clone();
+        let witness_values = witness_values.clone();
+        let proving_key = proving_key.clone();
+        self.spawn(move |backend| backend.prove_with_pk(&circuit, &witness_values, &proving_key))
+            .await
+    }
+
+    async fn verify_with_vk(
+        &self,
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+        verification_key: &VerificationKey,
+    ) -> Result<bool, BackendError> {
+        let proof = proof.to_vec();
+        let circuit = circuit.clone();
+        let verification_key = verification_key.clone();
+        self.spawn(move |backend| {
+            backend.verify_with_vk(&proof, public_inputs, &circuit, &verification_key)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testing::MockBackend, PartialWitnessGenerator};
+    use acir::{
+        circuit::{Opcode, PublicInputs},
+        native_types::{Expression, Witness},
+    };
+
+    // The tests drive the adapter on a runtime of their own, as tokio is built without
+    // its macros
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    // x2 = x1 + x1, where x2 is public
+    fn circuit() -> Circuit {
+        Circuit {
+            current_witness_index: 2,
+            opcodes: vec![Opcode::Arithmetic(
+                &(&Expression::from(&Witness(1)) + &Expression::from(&Witness(1))) - &Witness(2),
+            )],
+            public_inputs: PublicInputs(vec![Witness(2)]),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    #[test]
+    fn blocking_backends_prove_and_verify() {
+        let adapter = BlockingAdapter::new(MockBackend::new());
+        let circuit = circuit();
+        let mut witness = WitnessMap::from([(Witness(1), FieldElement::from(3_i128))]);
+        MockBackend::new()
+            .solve(&mut witness, circuit.opcodes.clone())
+            .unwrap();
+
+        block_on(async {
+            let (pk, vk) = adapter.preprocess(&circuit).await.unwrap();
+            let proof = adapter
+                .prove_with_pk(&circuit, &witness, &pk)
+                .await
+                .unwrap();
+            let public_inputs = vec![FieldElement::from(6_i128)];
+            assert_eq!(
+                adapter
+                    .verify_with_vk(&proof, public_inputs.clone(), &circuit, &vk)
+                    .await,
+                Ok(true)
+            );
+
+            let proof = adapter.prove_with_meta(&circuit, &witness).await.unwrap();
+            assert_eq!(
+                adapter
+                    .verify_from_cs(&proof, public_inputs, &circuit)
+                    .await,
+                Ok(true)
+            );
+            assert_eq!(
+                adapter
+                    .verify_from_cs(&proof, vec![FieldElement::from(7_i128)], &circuit)
+                    .await,
+                Ok(false)
+            );
+        });
+        assert_eq!(adapter.backend.num_preprocessed(), 1);
+    }
+
+    #[test]
+    fn backend_errors_are_returned() {
+        let adapter = BlockingAdapter::new(MockBackend::new());
+        let circuit = circuit();
+        let unsatisfying = WitnessMap::from([
+            (Witness(1), FieldElement::from(3_i128)),
+            (Witness(2), FieldElement::from(7_i128)),
+        ]);
+
+        block_on(async {
+            assert!(matches!(
+                adapter.prove_with_meta(&circuit, &unsatisfying).await,
+                Err(BackendError::Other(_))
+            ));
+
+            // Keys of another circuit are rejected
+            let (pk, vk) = adapter.preprocess(&Circuit::default()).await.unwrap();
+            assert!(matches!(
+                adapter.prove_with_pk(&circuit, &unsatisfying, &pk).await,
+                Err(BackendError::Other(_))
+            ));
+            assert!(matches!(
+                adapter.verify_with_vk(&[], Vec::new(), &circuit, &vk).await,
+                Err(BackendError::Other(_))
+            ));
+        });
+    }
+}
@@ -0,0 +1,320 @@
+// A conformance test suite which backends can run against their implementation of `Backend`
+
+use acir::{
+    circuit::{opcodes::FunctionInput, Circuit},
+    native_types::{Expression, Witness},
+    BlackBoxFunc, FieldElement,
+};
+use alloc::{borrow::ToOwned, format, string::String, string::ToString, vec, vec::Vec};
+use thiserror::Error;
+
+use crate::{
+    builder::CircuitBuilder, compiler::compile_for_backend, public_input_values, Backend,
+    ContractOptions, WitnessMap,
+};
+
+/// A circuit which every backend should be able to solve, prove and verify
+#[derive(Clone, Debug)]
+pub struct ConformanceCase {
+    pub name: &'static str,
+    pub circuit: Circuit,
+    /// An assignment of the inputs of the circuit which satisfies it
+    pub inputs: WitnessMap,
+    /// A witness which no longer satisfies the circuit when one is added to its value
+    pub corrupted: Option<Witness>,
+}
+
+/// A check which a backend failed in one of the conformance cases
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("{case}: {reason}")]
+pub struct ConformanceFailure {
+    pub case: &'static str,
+    pub reason: String,
+}
+
+/// Runs every case of `cases` against the backend, and returns each check which failed.
+///
+/// Each circuit is compiled for the backend and solved with it, and then:
+/// - proving and verifying with `prove_with_meta` and `verify_from_cs` must succeed
+/// - verifying with a changed public input must not succeed
+/// - proving and verifying with the keys from `preprocess` must succeed
+/// - a proof of a witness which does not satisfy the circuit must not verify
+/// - a verifier contract must be generated for each of the `supported_platforms`
+///
+/// The backend must also use the field which ACVM was compiled with.
+pub fn run_all<B: Backend>(backend: &B) -> Result<(), Vec<ConformanceFailure>> {
+    let mut failures = Vec::new();
+    if let Err(err) = backend.check_field_compatibility() {
+        failures.push(ConformanceFailure {
+            case: "field",
+            reason: err.to_string(),
+        });
+    }
+    for case in cases(backend) {
+        if let Err(reason) = run_case(backend, &case) {
+            failures.push(ConformanceFailure {
+                case: case.name,
+                reason,
+            });
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures)
+    }
+}
+
+/// Returns the conformance cases which apply to the backend.
+///
+/// SHA256 and Blake2s are only included if the backend supports them, as they do not
+/// have an arithmetic fallback. The other black box functions are lowered by the
+/// compiler if they are not supported.
+pub fn cases<B: Backend>(backend: &B) -> Vec<ConformanceCase> {
+    let mut cases = vec![
+        empty(),
+        arithmetic(),
+        field_edge_values(),
+        wide_expression(),
+        range(),
+        logic(BlackBoxFunc::AND),
+        logic(BlackBoxFunc::XOR),
+    ];
+    for func in [BlackBoxFunc::SHA256, BlackBoxFunc::Blake2s] {
+        if backend.blackbox_function_supported(&func) {
+            cases.push(hash(func));
+        }
+    }
+    cases
+}
+
+/// Runs a single conformance case, and returns the reason it failed
+pub fn run_case<B: Backend>(backend: &B, case: &ConformanceCase) -> Result<(), String> {
+    let circuit = compile_for_backend(case.circuit.clone(), backend)
+        .map_err(|err| format!("compilation failed: {err}"))?;
+
+    let mut witness = case.inputs.clone();
+    backend
+        .solve(&mut witness, circuit.opcodes.clone())
+        .map_err(|err| format!("solving failed: {err}"))?;
+    let public_inputs = public_input_values(&circuit, &witness)
+        .map_err(|err| format!("public inputs are not assigned: {err}"))?;
+
+    let proof = backend
+        .prove_with_meta(&circuit, &witness)
+        .map_err(|err| format!("prove_with_meta failed: {err}"))?;
+    match backend.verify_from_cs(&proof, public_inputs.clone(), &circuit) {
+        Ok(true) => {}
+        Ok(false) => return Err("verify_from_cs rejected a valid proof".to_owned()),
+        Err(err) => return Err(format!("verify_from_cs failed: {err}")),
+    }
+
+    if let Some((first, rest)) = public_inputs.split_first() {
+        let mut changed = vec![*first + FieldElement::one()];
+        changed.extend_from_slice(rest);
+        if let Ok(true) = backend.verify_from_cs(&proof, changed, &circuit) {
+            return Err("verify_from_cs accepted a changed public input".to_owned());
+        }
+    }
+
+    let (proving_key, verification_key) = backend
+        .preprocess(&circuit)
+        .map_err(|err| format!("preprocess failed: {err}"))?;
+    let proof = backend
+        .prove_with_pk(&circuit, &witness, &proving_key)
+        .map_err(|err| format!("prove_with_pk failed: {err}"))?;
+    match backend.verify_with_vk(&proof, public_inputs, &circuit, &verification_key) {
+        Ok(true) => {}
+        Ok(false) => return Err("verify_with_vk rejected a valid proof".to_owned()),
+        Err(err) => return Err(format!("verify_with_vk failed: {err}")),
+    }
+
+    if let Some(corrupted) = case.corrupted {
+        let mut witness = witness.clone();
+        let value = witness[&corrupted] + FieldElement::one();
+        witness.insert(corrupted, value);
+        // Backends may refuse to prove, or create a proof which does not verify
+        if let Ok(proof) = backend.prove_with_meta(&circuit, &witness) {
+            let public_inputs = public_input_values(&circuit, &witness)
+                .map_err(|err| format!("public inputs are not assigned: {err}"))?;
+            if let Ok(true) = backend.verify_from_cs(&proof, public_inputs, &circuit) {
+                return Err(format!(
+                    "a proof verified after witness {} was changed",
+                    corrupted.witness_index()
+                ));
+            }
+        }
+    }
+
+    for platform in backend.supported_platforms() {
+        backend
+            .contract_from_cs(&circuit, platform, &ContractOptions::default())
+            .map_err(|err| format!("contract generation for {platform} failed: {err}"))?;
+    }
+
+    Ok(())
+}
+
+fn finish(
+    name: &'static str,
+    builder: CircuitBuilder,
+    inputs: impl IntoIterator<Item = (Witness, FieldElement)>,
+    corrupted: Option<Witness>,
+) -> ConformanceCase {
+    ConformanceCase {
+        name,
+        circuit: builder
+            .finish()
+            .expect("conformance circuits are well-formed"),
+        inputs: inputs.into_iter().collect(),
+        corrupted,
+    }
+}
+
+fn empty() -> ConformanceCase {
+    finish("empty", CircuitBuilder::new(), [], None)
+}
+
+// out = x * y + z
+fn arithmetic() -> ConformanceCase {
+    let mut builder = CircuitBuilder::new();
+    let x = builder.add_public_input();
+    let y = builder.add_witness();
+    let z = builder.add_witness();
+    let out = builder.evaluate(x * y + z);
+    builder.make_public(out);
+    let inputs = [
+        (x, FieldElement::from(3_i128)),
+        (y, FieldElement::from(5_i128)),
+        (z, FieldElement::from(7_i128)),
+    ];
+    finish("arithmetic", builder, inputs, Some(out))
+}
+
+// Values at the edges of the field, which wrap around
+fn field_edge_values() -> ConformanceCase {
+    let mut builder = CircuitBuilder::new();
+    let minus_one = builder.add_public_input();
+    let large = builder.add_witness();
+    let zero = builder.evaluate(minus_one + FieldElement::one());
+    let one = builder.evaluate(minus_one * minus_one);
+    let square = builder.evaluate(large * large + zero);
+    builder.assert_equal(Expression::from(one) - FieldElement::one(), zero);
+    let two = FieldElement::from(2_i128);
+    let inputs = [
+        (minus_one, -FieldElement::one()),
+        (
+            large,
+            two.pow(&FieldElement::from(
+                FieldElement::max_num_bits() as i128 - 2,
+            )),
+        ),
+    ];
+    finish("field_edge_values", builder, inputs, Some(square))
+}
+
+// A linear combination which is wider than any gate
+fn wide_expression() -> ConformanceCase {
+    const WIDTH: usize = 32;
+    let mut builder = CircuitBuilder::new();
+    let terms = builder.add_witnesses(WIDTH);
+    builder.make_public(terms[0]);
+    let sum = terms
+        .iter()
+        .enumerate()
+        .fold(Expression::default(), |sum, (i, term)| {
+            sum + *term * FieldElement::from(i as i128 + 1)
+        });
+    let sum = builder.evaluate(sum);
+    let inputs = terms
+        .iter()
+        .enumerate()
+        .map(|(i, term)| (*term, FieldElement::from(i as i128 * 1000 + 1)))
+        .collect::<Vec<_>>();
+    finish("wide_expression", builder, inputs, Some(sum))
+}
+
+// The largest value which fits in 8 bits
+fn range() -> ConformanceCase {
+    let mut builder = CircuitBuilder::new();
+    let x = builder.add_public_input();
+    builder.range(x, 8);
+    finish(
+        "range",
+        builder,
+        [(x, FieldElement::from(255_i128))],
+        Some(x),
+    )
+}
+
+fn logic(func: BlackBoxFunc) -> ConformanceCase {
+    let mut builder = CircuitBuilder::new();
+    let lhs = builder.add_public_input();
+    let rhs = builder.add_witness();
+    let outputs = builder.black_box(
+        func,
+        vec![
+            FunctionInput {
+                witness: lhs,
+                num_bits: 8,
+            },
+            FunctionInput {
+                witness: rhs,
+                num_bits: 8,
+            },
+        ],
+    );
+    let inputs = [
+        (lhs, FieldElement::from(0b1100_1010_i128)),
+        (rhs, FieldElement::from(0b0101_0110_i128)),
+    ];
+    let name = match func {
+        BlackBoxFunc::AND => "and",
+        _ => "xor",
+    };
+    finish(name, builder, inputs, Some(outputs[0]))
+}
+
+// A hash of 32 bytes, which are public
+fn hash(func: BlackBoxFunc) -> ConformanceCase {
+    let mut builder = CircuitBuilder::new();
+    let bytes: Vec<Witness> = (0..32).map(|_| builder.add_public_input()).collect();
+    let inputs = bytes
+        .iter()
+        .map(|&witness| FunctionInput {
+            witness,
+            num_bits: 8,
+        })
+        .collect();
+    let outputs = builder.black_box(func, inputs);
+    let inputs = bytes
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| (*byte, FieldElement::from(i as i128 * 7 + 3)))
+        .collect::<Vec<_>>();
+    let name = match func {
+        BlackBoxFunc::SHA256 => "sha256",
+        _ => "blake2s",
+    };
+    finish(name, builder, inputs, outputs.last().copied())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testing::MockBackend, Language};
+
+    #[test]
+    fn mock_backend_conforms() {
+        let backend = MockBackend::new();
+        assert_eq!(cases(&backend).len(), 9);
+        assert_eq!(run_all(&backend), Ok(()));
+
+        let backend = MockBackend::new()
+            .with_language(Language::PLONKCSat { width: 4 })
+            .with_supported_opcodes([]);
+        assert_eq!(cases(&backend).len(), 7);
+        assert_eq!(run_all(&backend), Ok(()));
+    }
+}
@@ -0,0 +1,76 @@
+// A process wide registry of backends
+//
+// This allows tools to select a backend using a configuration string at runtime,
+// rather than being compiled against a concrete backend type.
+
+use std::{collections::BTreeMap, sync::Mutex};
+
+use crate::Backend;
+
+/// Creates a new instance of a backend
+pub type BackendFactory = fn() -> Box<dyn Backend>;
+
+static REGISTRY: Mutex<BTreeMap<String, BackendFactory>> = Mutex::new(BTreeMap::new());
+
+/// Registers a backend under the given name.
+///
+/// Returns the factory which was previously registered under that name, if any.
+pub fn register_backend(name: &str, factory: BackendFactory) -> Option<BackendFactory> {
+    REGISTRY
+        .lock()
+        .expect("backend registry lock is poisoned")
+        .insert(name.to_owned(), factory)
+}
+
+/// Creates an instance of the backend registered under the given name
+pub fn get_backend(name: &str) -> Option<Box<dyn Backend>> {
+    let factory = *REGISTRY
+        .lock()
+        .expect("backend registry lock is poisoned")
+        .get(name)?;
+    Some(factory())
+}
+
+/// Returns the names of all registered backends, in alphabetical order
+pub fn registered_backends() -> Vec<String> {
+    REGISTRY
+        .lock()
+        .expect("backend registry lock is poisoned")
+        .keys()
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testing::MockBackend, Language};
+
+    fn mock_with_width(width: usize) -> Box<dyn Backend> {
+        Box::new(MockBackend::new().with_language(Language::PLONKCSat { width }))
+    }
+
+    #[test]
+    fn backends_are_selected_by_name() {
+        register_backend("test_width_3", || mock_with_width(3));
+        register_backend("test_width_4", || mock_with_width(4));
+
+        let backend = get_backend("test_width_4").unwrap();
+        assert!(matches!(
+            backend.np_language(),
+            Language::PLONKCSat { width: 4 }
+        ));
+        let capabilities = backend.capabilities();
+        assert_eq!(capabilities.arithmetic_width(), Some(4));
+        assert_eq!(
+            capabilities.supported_blackbox_functions,
+            backend.supported_opcodes()
+        );
+        assert!(!capabilities.supports_recursion);
+        assert!(get_backend("test_unknown").is_none());
+
+        let names = registered_backends();
+        assert!(names.contains(&"test_width_3".to_owned()));
+        assert!(names.contains(&"test_width_4".to_owned()));
+    }
+}
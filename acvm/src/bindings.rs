@@ -0,0 +1,200 @@
+// JavaScript bindings, so that witnesses can be generated in the browser
+//
+// Only the parts of ACVM which do not depend on a backend are exported. Black box functions
+// are solved with the implementations in `pwg`, and circuits are compiled for a language
+// given by its width, with the black box functions which the backend supports given by name.
+//
+// Circuits are passed in the format of `Circuit::write`, and witness maps in the format of
+// `WitnessMap::to_bytes`. `witness_to_json` and `witness_from_json` convert witness maps to
+// and from JSON objects which map witness indices to hexadecimal field elements.
+//
+// Each export is a thin wrapper around a function which returns its error as a string, so
+// the bindings can be tested without a JavaScript host.
+
+use acir::{circuit::Circuit, BlackBoxFunc};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    compiler, prepare_witness, pwg::NativeSolver, Language, PartialWitnessGenerator, WitnessMap,
+};
+
+fn read_circuit(circuit: &[u8]) -> Result<Circuit, String> {
+    Circuit::read(circuit).map_err(|err| format!("malformed circuit: {err}"))
+}
+
+fn read_witness(witness: &[u8]) -> Result<WitnessMap, String> {
+    WitnessMap::from_bytes(witness).map_err(|err| format!("malformed witness map: {err}"))
+}
+
+fn solve_impl(circuit: &[u8], initial_witness: &[u8]) -> Result<Vec<u8>, String> {
+    let circuit = read_circuit(circuit)?;
+    let mut witness = read_witness(initial_witness)?;
+    prepare_witness(&mut witness, &circuit).map_err(|err| err.to_string())?;
+    NativeSolver
+        .solve(&mut witness, circuit.opcodes)
+        .map_err(|err| err.to_string())?;
+    Ok(witness.to_bytes())
+}
+
+fn compile_impl(
+    circuit: &[u8],
+    width: Option<u32>,
+    supported_black_box_functions: &[String],
+) -> Result<Vec<u8>, String> {
+    let circuit = read_circuit(circuit)?;
+    let language = match width {
+        Some(width) => Language::PLONKCSat {
+            width: width as usize,
+        },
+        None => Language::R1CS,
+    };
+    let supported = supported_black_box_functions
+        .iter()
+        .map(|name| {
+            BlackBoxFunc::lookup(name).ok_or_else(|| format!("unknown black box function {name}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let compiled = compiler::compile(circuit, language, |func| supported.contains(func))
+        .map_err(|err| err.to_string())?;
+    let mut bytes = Vec::new();
+    compiled
+        .write(&mut bytes)
+        .expect("infallible: writing to a Vec cannot fail");
+    Ok(bytes)
+}
+
+fn circuit_digest_impl(circuit: &[u8]) -> Result<Vec<u8>, String> {
+    Ok(crate::circuit_digest(&read_circuit(circuit)?).to_vec())
+}
+
+fn witness_to_json_impl(witness: &[u8]) -> Result<String, String> {
+    serde_json::to_string(&read_witness(witness)?).map_err(|err| err.to_string())
+}
+
+fn witness_from_json_impl(json: &str) -> Result<Vec<u8>, String> {
+    let witness: WitnessMap =
+        serde_json::from_str(json).map_err(|err| format!("malformed witness map: {err}"))?;
+    Ok(witness.to_bytes())
+}
+
+/// Solves a circuit from its initial witness, and returns the solved witness
+#[wasm_bindgen]
+pub fn solve(circuit: &[u8], initial_witness: &[u8]) -> Result<Vec<u8>, JsError> {
+    solve_impl(circuit, initial_witness).map_err(|err| JsError::new(&err))
+}
+
+/// Compiles a circuit for a language of the given width, or for R1CS if no width is given
+#[wasm_bindgen]
+pub fn compile(
+    circuit: &[u8],
+    width: Option<u32>,
+    supported_black_box_functions: Vec<String>,
+) -> Result<Vec<u8>, JsError> {
+    compile_impl(circuit, width, &supported_black_box_functions).map_err(|err| JsError::new(&err))
+}
+
+/// Returns the `circuit_digest` of a circuit
+#[wasm_bindgen(js_name = circuitDigest)]
+pub fn circuit_digest(circuit: &[u8]) -> Result<Vec<u8>, JsError> {
+    circuit_digest_impl(circuit).map_err(|err| JsError::new(&err))
+}
+
+/// Converts a serialised witness map to JSON
+#[wasm_bindgen(js_name = witnessToJson)]
+pub fn witness_to_json(witness: &[u8]) -> Result<String, JsError> {
+    witness_to_json_impl(witness).map_err(|err| JsError::new(&err))
+}
+
+/// Converts a witness map in JSON to its serialised form
+#[wasm_bindgen(js_name = witnessFromJson)]
+pub fn witness_from_json(json: &str) -> Result<Vec<u8>, JsError> {
+    witness_from_json_impl(json).map_err(|err| JsError::new(&err))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{
+        circuit::{
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            Opcode, PublicInputs,
+        },
+        native_types::{Expression, Witness},
+        FieldElement,
+    };
+
+    // _3 = _1 AND _2, _4 = _3 + _1
+    fn circuit() -> Vec<u8> {
+        let circuit: Circuit = Circuit {
+            current_witness_index: 4,
+            opcodes: vec![
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::AND,
+                    inputs: vec![
+                        FunctionInput {
+                            witness: Witness(1),
+                            num_bits: 8,
+                        },
+                        FunctionInput {
+                            witness: Witness(2),
+                            num_bits: 8,
+                        },
+                    ],
+                    outputs: vec![Witness(3)],
+                }),
+                Opcode::Arithmetic(
+                    &(&Expression::from(&Witness(3)) - &Witness(4))
+                        + &Expression::from(&Witness(1)),
+                ),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        };
+        let mut bytes = Vec::new();
+        circuit.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn solves_with_native_black_box_functions() {
+        let initial_witness = witness_from_json_impl(r#"{"1": "0x0c", "2": "0x0a"}"#).unwrap();
+        let solved = solve_impl(&circuit(), &initial_witness).unwrap();
+
+        let solved: WitnessMap = WitnessMap::from_bytes(&solved).unwrap();
+        assert_eq!(solved[&Witness(3)], FieldElement::from(8_i128));
+        assert_eq!(solved[&Witness(4)], FieldElement::from(20_i128));
+
+        let json = witness_to_json_impl(&solved.to_bytes()).unwrap();
+        assert_eq!(
+            WitnessMap::from_bytes(&witness_from_json_impl(&json).unwrap()).unwrap(),
+            solved
+        );
+
+        // Witnesses outside of the circuit are rejected
+        let out_of_bounds = witness_from_json_impl(r#"{"1": "0x0c", "9": "0x0a"}"#).unwrap();
+        assert_eq!(
+            solve_impl(&circuit(), &out_of_bounds),
+            Err("witness 9 is assigned, but the circuit's current witness index is 4".to_owned())
+        );
+    }
+
+    #[test]
+    fn compiles_unsupported_black_box_functions_to_arithmetic() {
+        let compiled = compile_impl(&circuit(), Some(3), &[]).unwrap();
+        let compiled: Circuit = Circuit::read(&*compiled).unwrap();
+        assert!(compiled
+            .opcodes
+            .iter()
+            .all(|opcode| !matches!(opcode, Opcode::BlackBoxFuncCall(_))));
+
+        let compiled = compile_impl(&circuit(), None, &["and".to_owned()]).unwrap();
+        assert_eq!(circuit_digest_impl(&compiled).unwrap().len(), 32);
+
+        assert_eq!(
+            compile_impl(&circuit(), None, &["foo".to_owned()]),
+            Err("unknown black box function foo".to_owned())
+        );
+        assert!(solve_impl(&[0x01], &[]).is_err());
+    }
+}
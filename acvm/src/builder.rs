@@ -0,0 +1,221 @@
+use acir::{
+    circuit::{
+        directives::Directive,
+        opcodes::{BlackBoxFuncCall, FunctionInput},
+        Circuit, Opcode, PublicInputs,
+    },
+    native_types::{Expression, Witness},
+    BlackBoxFunc,
+};
+use alloc::{vec, vec::Vec};
+
+use crate::{compiler::validate, compiler::ValidationError, pwg::opcode_witnesses};
+
+/// Builds a `Circuit` one opcode at a time, without going through a frontend.
+///
+/// Witnesses are allocated in order starting from 1, and the current witness index of
+/// the circuit is the largest witness which was allocated or used by an opcode.
+#[derive(Clone, Debug, Default)]
+pub struct CircuitBuilder {
+    current_witness_index: u32,
+    opcodes: Vec<Opcode>,
+    public_inputs: Vec<Witness>,
+    return_values: Vec<Witness>,
+}
+
+impl CircuitBuilder {
+    pub fn new() -> Self {
+        CircuitBuilder::default()
+    }
+
+    /// Allocates a new witness
+    pub fn add_witness(&mut self) -> Witness {
+        self.current_witness_index += 1;
+        Witness(self.current_witness_index)
+    }
+
+    /// Allocates `n` new witnesses, in increasing order
+    pub fn add_witnesses(&mut self, n: usize) -> Vec<Witness> {
+        (0..n).map(|_| self.add_witness()).collect()
+    }
+
+    /// Allocates a new witness and makes it a public input
+    pub fn add_public_input(&mut self) -> Witness {
+        let witness = self.add_witness();
+        self.make_public(witness);
+        witness
+    }
+
+    /// Makes `witness` a public input, if it is not one already
+    pub fn make_public(&mut self, witness: Witness) {
+        if !self.public_inputs.contains(&witness) {
+            self.bump_witness_index(witness);
+            self.public_inputs.push(witness);
+        }
+    }
+
+    /// Appends `witness` to the return values of the circuit
+    pub fn add_return_value(&mut self, witness: Witness) {
+        self.bump_witness_index(witness);
+        self.return_values.push(witness);
+    }
+
+    pub fn current_witness_index(&self) -> u32 {
+        self.current_witness_index
+    }
+
+    pub fn opcodes(&self) -> &[Opcode] {
+        &self.opcodes
+    }
+
+    /// Appends an opcode, and returns its index in the circuit
+    pub fn push_opcode(&mut self, opcode: Opcode) -> usize {
+        for witness in opcode_witnesses(&opcode) {
+            self.bump_witness_index(witness);
+        }
+        self.opcodes.push(opcode);
+        self.opcodes.len() - 1
+    }
+
+    /// Constrains `expr` to be zero
+    pub fn assert_zero(&mut self, expr: impl Into<Expression>) -> usize {
+        self.push_opcode(Opcode::Arithmetic(expr.into()))
+    }
+
+    /// Constrains `lhs` and `rhs` to be equal
+    pub fn assert_equal(
+        &mut self,
+        lhs: impl Into<Expression>,
+        rhs: impl Into<Expression>,
+    ) -> usize {
+        self.assert_zero(lhs.into() - rhs.into())
+    }
+
+    /// Allocates a witness which is constrained to the value of `expr`
+    pub fn evaluate(&mut self, expr: impl Into<Expression>) -> Witness {
+        let witness = self.add_witness();
+        self.assert_zero(expr.into() - witness);
+        witness
+    }
+
+    pub fn directive(&mut self, directive: Directive) -> usize {
+        self.push_opcode(Opcode::Directive(directive))
+    }
+
+    /// Appends a call to a black box function, and returns the outputs which are allocated
+    /// for it according to the function's definition.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function does not have a definition, which is the case for AES.
+    pub fn black_box(&mut self, name: BlackBoxFunc, inputs: Vec<FunctionInput>) -> Vec<Witness> {
+        let num_outputs = name.definition().output_size.0 as usize;
+        let outputs = self.add_witnesses(num_outputs);
+        self.push_opcode(Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+            name,
+            inputs,
+            outputs: outputs.clone(),
+        }));
+        outputs
+    }
+
+    /// Constrains `witness` to fit in `num_bits` bits
+    pub fn range(&mut self, witness: Witness, num_bits: u32) -> usize {
+        self.black_box(
+            BlackBoxFunc::RANGE,
+            vec![FunctionInput { witness, num_bits }],
+        );
+        self.opcodes.len() - 1
+    }
+
+    /// Appends a call to the function `id` of a program, and returns the `num_outputs`
+    /// witnesses which are allocated for its outputs
+    pub fn call(&mut self, id: u32, inputs: Vec<Witness>, num_outputs: usize) -> Vec<Witness> {
+        let outputs = self.add_witnesses(num_outputs);
+        self.push_opcode(Opcode::Call {
+            id,
+            inputs,
+            outputs: outputs.clone(),
+        });
+        outputs
+    }
+
+    /// Returns the circuit which was built, after checking it with `compiler::validate`
+    pub fn finish(self) -> Result<Circuit, Vec<ValidationError>> {
+        let circuit = Circuit {
+            current_witness_index: self.current_witness_index,
+            opcodes: self.opcodes,
+            public_inputs: PublicInputs(self.public_inputs),
+            return_values: PublicInputs(self.return_values),
+        };
+        validate(&circuit)?;
+        Ok(circuit)
+    }
+
+    fn bump_witness_index(&mut self, witness: Witness) {
+        self.current_witness_index = self.current_witness_index.max(witness.witness_index());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{pwg::NativeSolver, PartialWitnessGenerator, WitnessMap};
+    use acir::FieldElement;
+
+    #[test]
+    fn built_circuits_can_be_solved() {
+        let mut builder = CircuitBuilder::new();
+        let x = builder.add_public_input();
+        let y = builder.add_public_input();
+        let x_times_y = builder.evaluate(x * y);
+        let sum = builder.evaluate(Expression::from(x_times_y) + y + FieldElement::one());
+        builder.range(sum, 8);
+        let and = builder.black_box(
+            BlackBoxFunc::AND,
+            vec![
+                FunctionInput {
+                    witness: x,
+                    num_bits: 8,
+                },
+                FunctionInput {
+                    witness: sum,
+                    num_bits: 8,
+                },
+            ],
+        );
+        assert_eq!(and.len(), 1);
+        builder.make_public(x);
+
+        let circuit = builder.finish().unwrap();
+        assert_eq!(circuit.current_witness_index, 5);
+        assert_eq!(circuit.opcodes.len(), 4);
+        assert_eq!(circuit.public_inputs, PublicInputs(vec![x, y]));
+
+        let mut witness = WitnessMap::from([
+            (x, FieldElement::from(6_i128)),
+            (y, FieldElement::from(7_i128)),
+        ]);
+        NativeSolver.solve(&mut witness, circuit.opcodes).unwrap();
+        assert_eq!(witness[&sum], FieldElement::from(50_i128));
+        assert_eq!(witness[&and[0]], FieldElement::from(2_i128));
+    }
+
+    #[test]
+    fn witnesses_used_by_pushed_opcodes_are_allocated() {
+        let mut builder = CircuitBuilder::new();
+        builder.push_opcode(Opcode::Arithmetic(Expression::from(Witness(4))));
+        assert_eq!(builder.current_witness_index(), 4);
+        assert_eq!(builder.add_witness(), Witness(5));
+    }
+
+    #[test]
+    fn unused_public_inputs_are_rejected() {
+        let mut builder = CircuitBuilder::new();
+        let x = builder.add_public_input();
+        assert_eq!(
+            builder.finish(),
+            Err(vec![ValidationError::UnusedPublicInput { witness: x }])
+        );
+    }
+}
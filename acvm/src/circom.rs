@@ -0,0 +1,399 @@
+// Import of circuits compiled by Circom
+//
+// Circom writes its constraints to a `.r1cs` file and the witness computed for some inputs
+// to a `.wtns` file. Both are little-endian binary files which start with a four byte magic
+// string and a version, followed by sections which each have a `u32` type and a `u64` size.
+//
+// Wire 0 of a Circom circuit is the constant one, and is not imported. Every other wire is
+// imported as the witness with the same index, so the witness map read from a `.wtns` file
+// can be used with the circuit read from the matching `.r1cs` file.
+
+use std::{collections::BTreeMap, io::Read};
+
+use acir::{
+    circuit::{Circuit, Opcode, PublicInputs},
+    native_types::{Expression, Witness},
+    serialisation::{read_n, read_u32},
+    FieldElement,
+};
+use num_bigint::BigUint;
+use thiserror::Error;
+
+use crate::WitnessMap;
+
+const R1CS_MAGIC: &[u8; 4] = b"r1cs";
+const R1CS_VERSION: u32 = 1;
+const R1CS_HEADER_SECTION: u32 = 1;
+const R1CS_CONSTRAINTS_SECTION: u32 = 2;
+
+const WTNS_MAGIC: &[u8; 4] = b"wtns";
+const WTNS_VERSION: u32 = 2;
+const WTNS_HEADER_SECTION: u32 = 1;
+const WTNS_VALUES_SECTION: u32 = 2;
+
+#[derive(PartialEq, Eq, Debug, Error)]
+pub enum CircomError {
+    #[error("expected a Circom {0} file")]
+    InvalidMagic(&'static str),
+    #[error("version {0} of the Circom file format is not supported")]
+    UnsupportedVersion(u32),
+    #[error("the Circom file has no section of type {0}")]
+    MissingSection(u32),
+    #[error("the Circom file has more than one section of type {0}")]
+    DuplicateSection(u32),
+    #[error("the Circom file uses a different field from the one ACVM was compiled with")]
+    FieldMismatch,
+    #[error("malformed Circom file: {0}")]
+    Malformed(String),
+}
+
+fn malformed(err: impl std::fmt::Display) -> CircomError {
+    CircomError::Malformed(err.to_string())
+}
+
+/// Reads a Circom `.r1cs` file as a circuit with one arithmetic opcode per constraint.
+///
+/// The public inputs of the circuit are the public outputs of the Circom circuit,
+/// followed by its public inputs. The public outputs are also its return values.
+pub fn read_r1cs<R: Read>(reader: R) -> Result<Circuit, CircomError> {
+    let sections = read_sections(reader, R1CS_MAGIC, R1CS_VERSION, "r1cs")?;
+
+    let mut header = section(&sections, R1CS_HEADER_SECTION)?;
+    let field_size = read_field_size(&mut header)?;
+    let num_wires = read_u32(&mut header).map_err(malformed)?;
+    let num_public_outputs = read_u32(&mut header).map_err(malformed)?;
+    let num_public_inputs = read_u32(&mut header).map_err(malformed)?;
+    let _num_private_inputs = read_u32(&mut header).map_err(malformed)?;
+    let _num_labels = read_u64(&mut header)?;
+    let num_constraints = read_u32(&mut header).map_err(malformed)?;
+
+    let mut constraints = section(&sections, R1CS_CONSTRAINTS_SECTION)?;
+    // The number of constraints is not trusted to size an allocation, as it is read
+    // before the constraints which would show it to be wrong
+    let mut opcodes = Vec::new();
+    for _ in 0..num_constraints {
+        let a = read_linear_combination(&mut constraints, field_size, num_wires)?;
+        let b = read_linear_combination(&mut constraints, field_size, num_wires)?;
+        let c = read_linear_combination(&mut constraints, field_size, num_wires)?;
+        opcodes.push(Opcode::Arithmetic(constraint_to_expression(&a, &b, &c)));
+    }
+    if !constraints.is_empty() {
+        return Err(malformed("trailing bytes after the constraints"));
+    }
+
+    let num_public = num_public_outputs
+        .checked_add(num_public_inputs)
+        .ok_or_else(|| malformed("more public wires than wires"))?;
+    if num_public >= num_wires.max(1) {
+        return Err(malformed("more public wires than wires"));
+    }
+
+    Ok(Circuit {
+        current_witness_index: num_wires.saturating_sub(1),
+        opcodes,
+        public_inputs: PublicInputs((1..=num_public).map(Witness).collect()),
+        return_values: PublicInputs((1..=num_public_outputs).map(Witness).collect()),
+    })
+}
+
+/// Reads a Circom `.wtns` file as a witness map
+pub fn read_wtns<R: Read>(reader: R) -> Result<WitnessMap, CircomError> {
+    let sections = read_sections(reader, WTNS_MAGIC, WTNS_VERSION, "wtns")?;
+
+    let mut header = section(&sections, WTNS_HEADER_SECTION)?;
+    let field_size = read_field_size(&mut header)?;
+    let num_values = read_u32(&mut header).map_err(malformed)?;
+
+    let mut values = section(&sections, WTNS_VALUES_SECTION)?;
+    let mut witness_map = WitnessMap::new();
+    for index in 0..num_values {
+        let value = read_field(&mut values, field_size)?;
+        // Wire 0 is the constant one
+        if index != 0 {
+            witness_map.insert(Witness(index), value);
+        }
+    }
+    if !values.is_empty() {
+        return Err(malformed("trailing bytes after the witness values"));
+    }
+    Ok(witness_map)
+}
+
+// Reads the whole file and returns the contents of each section by type
+fn read_sections<R: Read>(
+    mut reader: R,
+    magic: &[u8; 4],
+    version: u32,
+    kind: &'static str,
+) -> Result<BTreeMap<u32, Vec<u8>>, CircomError> {
+    if &read_n::<4, _>(&mut reader).map_err(malformed)? != magic {
+        return Err(CircomError::InvalidMagic(kind));
+    }
+    let found = read_u32(&mut reader).map_err(malformed)?;
+    if found != version {
+        return Err(CircomError::UnsupportedVersion(found));
+    }
+
+    let num_sections = read_u32(&mut reader).map_err(malformed)?;
+    let mut sections = BTreeMap::new();
+    for _ in 0..num_sections {
+        let section_type = read_u32(&mut reader).map_err(malformed)?;
+        let size = read_u64(&mut reader)?;
+        let mut contents = Vec::new();
+        (&mut reader)
+            .take(size)
+            .read_to_end(&mut contents)
+            .map_err(malformed)?;
+        if contents.len() as u64 != size {
+            return Err(malformed("unexpected end of file"));
+        }
+        if sections.insert(section_type, contents).is_some() {
+            return Err(CircomError::DuplicateSection(section_type));
+        }
+    }
+    Ok(sections)
+}
+
+fn section(sections: &BTreeMap<u32, Vec<u8>>, section_type: u32) -> Result<&[u8], CircomError> {
+    sections
+        .get(&section_type)
+        .map(Vec::as_slice)
+        .ok_or(CircomError::MissingSection(section_type))
+}
+
+fn read_u64<R: Read>(reader: R) -> Result<u64, CircomError> {
+    read_n::<8, _>(reader)
+        .map(u64::from_le_bytes)
+        .map_err(malformed)
+}
+
+// Reads the size of a field element in bytes followed by the prime, which must be the
+// modulus of `FieldElement`
+fn read_field_size(reader: &mut &[u8]) -> Result<usize, CircomError> {
+    let field_size = read_u32(&mut *reader).map_err(malformed)? as usize;
+    if field_size > reader.len() {
+        return Err(malformed("unexpected end of file"));
+    }
+    let (prime, rest) = reader.split_at(field_size);
+    *reader = rest;
+    if BigUint::from_bytes_le(prime) != FieldElement::modulus() {
+        return Err(CircomError::FieldMismatch);
+    }
+    Ok(field_size)
+}
+
+// Reads a little-endian field element, which must be less than the modulus
+fn read_field(reader: &mut &[u8], field_size: usize) -> Result<FieldElement, CircomError> {
+    if field_size > reader.len() {
+        return Err(malformed("unexpected end of file"));
+    }
+    let (bytes, rest) = reader.split_at(field_size);
+    *reader = rest;
+    let value = BigUint::from_bytes_le(bytes);
+    if value >= FieldElement::modulus() {
+        return Err(malformed("field element is not reduced"));
+    }
+    Ok(FieldElement::from_be_bytes_reduce(&value.to_bytes_be()))
+}
+
+// Reads a linear combination of wires, as pairs of a wire index and its coefficient
+fn read_linear_combination(
+    reader: &mut &[u8],
+    field_size: usize,
+    num_wires: u32,
+) -> Result<Vec<(u32, FieldElement)>, CircomError> {
+    let num_terms = read_u32(&mut *reader).map_err(malformed)?;
+    let mut terms = Vec::new();
+    for _ in 0..num_terms {
+        let wire = read_u32(&mut *reader).map_err(malformed)?;
+        if wire >= num_wires {
+            return Err(malformed(format!("wire {wire} is out of range")));
+        }
+        terms.push((wire, read_field(reader, field_size)?));
+    }
+    Ok(terms)
+}
+
+// Returns the expression A*B - C, where wire 0 is the constant one
+fn constraint_to_expression(
+    a: &[(u32, FieldElement)],
+    b: &[(u32, FieldElement)],
+    c: &[(u32, FieldElement)],
+) -> Expression {
+    let mut expr = Expression::default();
+    for (a_wire, a_coeff) in a {
+        for (b_wire, b_coeff) in b {
+            let coeff = *a_coeff * *b_coeff;
+            match (*a_wire, *b_wire) {
+                (0, 0) => expr.q_c += coeff,
+                (0, wire) | (wire, 0) => expr.term_addition(coeff, Witness(wire)),
+                (a_wire, b_wire) => {
+                    expr.term_multiplication(coeff, Witness(a_wire), Witness(b_wire))
+                }
+            }
+        }
+    }
+    for (wire, coeff) in c {
+        match wire {
+            0 => expr.q_c -= *coeff,
+            wire => expr.term_addition(-*coeff, Witness(*wire)),
+        }
+    }
+    expr.normalize()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::serialisation::{write_bytes, write_u32};
+
+    fn write_field(buf: &mut Vec<u8>, value: &BigUint) {
+        let mut bytes = value.to_bytes_le();
+        bytes.resize(FieldElement::max_num_bytes() as usize, 0);
+        write_bytes(buf, &bytes).unwrap();
+    }
+
+    fn write_file(magic: &[u8; 4], version: u32, sections: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let mut buf = magic.to_vec();
+        write_u32(&mut buf, version).unwrap();
+        write_u32(&mut buf, sections.len() as u32).unwrap();
+        for (section_type, contents) in sections {
+            write_u32(&mut buf, *section_type).unwrap();
+            write_bytes(&mut buf, &(contents.len() as u64).to_le_bytes()).unwrap();
+            write_bytes(&mut buf, contents).unwrap();
+        }
+        buf
+    }
+
+    fn field_header(prime: &BigUint) -> Vec<u8> {
+        let mut header = Vec::new();
+        write_u32(&mut header, FieldElement::max_num_bytes()).unwrap();
+        write_field(&mut header, prime);
+        header
+    }
+
+    // The header of an r1cs file with the given numbers of wires, public outputs, public
+    // inputs and private inputs, and of constraints
+    fn r1cs_header(prime: &BigUint, counts: [u32; 4], num_constraints: u32) -> Vec<u8> {
+        let mut header = field_header(prime);
+        for count in counts {
+            write_u32(&mut header, count).unwrap();
+        }
+        write_bytes(&mut header, &4_u64.to_le_bytes()).unwrap();
+        write_u32(&mut header, num_constraints).unwrap();
+        header
+    }
+
+    // out <== a * b + 3, with wires [1, out, a, b]
+    fn r1cs(prime: &BigUint) -> Vec<u8> {
+        let header = r1cs_header(prime, [4, 1, 0, 2], 1);
+
+        // a * b = out - 3
+        let minus_three = FieldElement::modulus() - BigUint::from(3_u32);
+        let mut constraints = Vec::new();
+        for terms in [
+            vec![(2, BigUint::from(1_u32))],
+            vec![(3, BigUint::from(1_u32))],
+            vec![(1, BigUint::from(1_u32)), (0, minus_three)],
+        ] {
+            write_u32(&mut constraints, terms.len() as u32).unwrap();
+            for (wire, coeff) in terms {
+                write_u32(&mut constraints, wire).unwrap();
+                write_field(&mut constraints, &coeff);
+            }
+        }
+
+        // The constraints come before the header, as the order of sections is not fixed
+        write_file(
+            R1CS_MAGIC,
+            R1CS_VERSION,
+            &[
+                (R1CS_CONSTRAINTS_SECTION, constraints),
+                (R1CS_HEADER_SECTION, header),
+            ],
+        )
+    }
+
+    #[test]
+    fn r1cs_is_read_as_a_circuit() {
+        let circuit = read_r1cs(&*r1cs(&FieldElement::modulus())).unwrap();
+
+        let mut expected = Expression::default();
+        expected.term_multiplication(FieldElement::one(), Witness(2), Witness(3));
+        expected.term_addition(-FieldElement::one(), Witness(1));
+        expected.q_c = FieldElement::from(3_i128);
+        assert_eq!(
+            circuit,
+            Circuit {
+                current_witness_index: 3,
+                opcodes: vec![Opcode::Arithmetic(expected.normalize())],
+                public_inputs: PublicInputs(vec![Witness(1)]),
+                return_values: PublicInputs(vec![Witness(1)]),
+            }
+        );
+    }
+
+    #[test]
+    fn r1cs_over_another_field_is_rejected() {
+        let bytes = r1cs(&(FieldElement::modulus() + BigUint::from(2_u32)));
+        assert_eq!(read_r1cs(&*bytes), Err(CircomError::FieldMismatch));
+
+        let mut bytes = r1cs(&FieldElement::modulus());
+        bytes[0] = b'w';
+        assert_eq!(read_r1cs(&*bytes), Err(CircomError::InvalidMagic("r1cs")));
+    }
+
+    #[test]
+    fn malformed_r1cs_headers_are_rejected() {
+        let read = |counts, num_constraints| {
+            let header = r1cs_header(&FieldElement::modulus(), counts, num_constraints);
+            read_r1cs(&*write_file(
+                R1CS_MAGIC,
+                R1CS_VERSION,
+                &[
+                    (R1CS_HEADER_SECTION, header),
+                    (R1CS_CONSTRAINTS_SECTION, Vec::new()),
+                ],
+            ))
+        };
+
+        // A huge number of constraints runs out of bytes rather than memory
+        assert!(matches!(
+            read([4, 1, 0, 2], u32::MAX),
+            Err(CircomError::Malformed(_))
+        ));
+        // The numbers of public outputs and inputs overflow when added
+        assert!(matches!(
+            read([4, u32::MAX, 1, 2], 0),
+            Err(CircomError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn wtns_is_read_as_a_witness_map() {
+        let mut header = field_header(&FieldElement::modulus());
+        write_u32(&mut header, 4).unwrap();
+        let mut values = Vec::new();
+        for value in [1_u32, 9, 2, 3] {
+            write_field(&mut values, &BigUint::from(value));
+        }
+        let bytes = write_file(
+            WTNS_MAGIC,
+            WTNS_VERSION,
+            &[(WTNS_HEADER_SECTION, header), (WTNS_VALUES_SECTION, values)],
+        );
+
+        assert_eq!(
+            read_wtns(&*bytes).unwrap(),
+            WitnessMap::from([
+                (Witness(1), FieldElement::from(9_i128)),
+                (Witness(2), FieldElement::from(2_i128)),
+                (Witness(3), FieldElement::from(3_i128)),
+            ])
+        );
+        assert!(matches!(
+            read_wtns(&bytes[..bytes.len() - 1]),
+            Err(CircomError::Malformed(_))
+        ));
+    }
+}
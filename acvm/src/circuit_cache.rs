@@ -0,0 +1,284 @@
+// Caches the circuits which the compiler transforms for backends
+//
+// Long-running services prove the same circuits many times, and compiling a circuit for
+// the backend each time repeats the same work. Compiled circuits are keyed by the backend's
+// name, the `circuit_digest` of the original circuit and a digest of the options which
+// change the output of the compiler: the version of ACVM, the backend's language, black box
+// functions, lookup tables and gate weights, and the pinned witnesses.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use acir::{
+    circuit::Circuit,
+    native_types::Witness,
+    serialisation::{read_u32, write_u32},
+};
+use sha2::{digest::FixedOutput, Digest, Sha256};
+
+use crate::{
+    circuit_digest,
+    compiler::compile_for_backend_with_pins,
+    disk_cache::{directory_name, write_file, CacheEntry, DiskCache},
+    BackendError, Language, ProofSystemCompiler,
+};
+
+type CacheKey = (String, [u8; 32], [u8; 32]);
+
+/// A circuit compiled for a backend, with the index each pinned witness has in it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompiledCircuit {
+    pub circuit: Circuit,
+    pub witness_indices: BTreeMap<Witness, Witness>,
+}
+
+/// An in-memory cache of compiled circuits, optionally backed by a directory
+#[derive(Debug)]
+pub struct CircuitCache {
+    circuits: DiskCache<CacheKey, CompiledCircuit>,
+}
+
+impl Default for CircuitCache {
+    fn default() -> Self {
+        CircuitCache {
+            circuits: DiskCache::new("circuits", None),
+        }
+    }
+}
+
+impl CircuitCache {
+    pub fn new() -> Self {
+        CircuitCache::default()
+    }
+
+    /// Creates a cache which also stores circuits in `directory`, so that they outlive the
+    /// process
+    pub fn with_directory(directory: impl Into<PathBuf>) -> Self {
+        CircuitCache {
+            circuits: DiskCache::new("circuits", Some(directory.into())),
+        }
+    }
+
+    /// Returns `circuit` compiled for `backend` with `compiler::compile_for_backend_with_pins`,
+    /// compiling it if it is not cached
+    pub fn get_or_compile<B: ProofSystemCompiler + ?Sized>(
+        &self,
+        backend: &B,
+        circuit: &Circuit,
+        pinned: &BTreeSet<Witness>,
+    ) -> Result<CompiledCircuit, BackendError> {
+        let key = (
+            backend.name().to_owned(),
+            circuit_digest(circuit),
+            options_digest(backend, pinned),
+        );
+        self.get_or_transform(key, || {
+            let (circuit, witness_indices) =
+                compile_for_backend_with_pins(circuit.clone(), backend, pinned)?;
+            Ok(CompiledCircuit {
+                circuit,
+                witness_indices,
+            })
+        })
+    }
+
+    // Returns the circuit cached under `key`, transforming it with `transform` if it is
+    // not cached. Circuits are stored at
+    // `<directory>/<backend>/<circuit digest>-<options digest>.acir`
+    fn get_or_transform(
+        &self,
+        key: CacheKey,
+        transform: impl FnOnce() -> Result<CompiledCircuit, BackendError>,
+    ) -> Result<CompiledCircuit, BackendError> {
+        let (backend, circuit, options) = &key;
+        let file_name = format!("{}-{}.acir", hex::encode(circuit), hex::encode(options));
+        let path = PathBuf::from(directory_name(backend)).join(file_name);
+        self.circuits.get_or_derive(key, || path, transform)
+    }
+
+    /// Removes all circuits from memory. Circuits stored on disk are kept.
+    pub fn clear(&self) {
+        self.circuits.clear();
+    }
+}
+
+// The circuit is written by `Circuit::write`, followed by the number of pinned witnesses
+// and the pairs of their original and compiled indices
+impl CacheEntry for CompiledCircuit {
+    fn read(path: &Path) -> Option<Self> {
+        let bytes = std::fs::read(path).ok()?;
+        let mut reader = bytes.as_slice();
+        let circuit = Circuit::read(&mut reader).ok()?;
+        let num_witnesses = read_u32(&mut reader).ok()?;
+        let witness_indices = (0..num_witnesses)
+            .map(|_| {
+                let original = read_u32(&mut reader).ok()?;
+                let compiled = read_u32(&mut reader).ok()?;
+                Some((Witness(original), Witness(compiled)))
+            })
+            .collect::<Option<_>>()?;
+        Some(CompiledCircuit {
+            circuit,
+            witness_indices,
+        })
+    }
+
+    fn write(&self, path: &Path) -> std::io::Result<()> {
+        let mut bytes = Vec::new();
+        self.circuit.write(&mut bytes)?;
+        write_u32(&mut bytes, self.witness_indices.len() as u32)?;
+        for (original, compiled) in &self.witness_indices {
+            write_u32(&mut bytes, original.witness_index())?;
+            write_u32(&mut bytes, compiled.witness_index())?;
+        }
+        write_file(path, &bytes)
+    }
+}
+
+// Returns a digest of everything other than the circuit which changes how it is compiled.
+//
+// The options are hashed in a fixed binary encoding, with sets sorted, and the version of
+// ACVM is included so that circuits cached by an older compiler are not reused.
+fn options_digest<B: ProofSystemCompiler + ?Sized>(
+    backend: &B,
+    pinned: &BTreeSet<Witness>,
+) -> [u8; 32] {
+    let capabilities = backend.capabilities();
+    let version = env!("CARGO_PKG_VERSION");
+    let mut bytes = (version.len() as u64).to_le_bytes().to_vec();
+    bytes.extend(version.as_bytes());
+    let mut write_u64 = |value: u64| bytes.extend(value.to_le_bytes());
+
+    let language: [u64; 3] = match capabilities.np_language {
+        Language::R1CS => [0, 0, 0],
+        Language::PLONKCSat { width } => [1, width as u64, 0],
+        Language::UltraPlonk { width, lookups } => [2, width as u64, lookups as u64],
+        Language::AIR { width, degree } => [3, width as u64, degree as u64],
+        Language::CCS { degree } => [4, degree as u64, 0],
+    };
+    language.into_iter().for_each(&mut write_u64);
+
+    let mut black_box_functions: Vec<u16> = capabilities
+        .supported_blackbox_functions
+        .iter()
+        .map(|func| func.to_u16())
+        .collect();
+    black_box_functions.sort();
+    write_u64(black_box_functions.len() as u64);
+    black_box_functions
+        .into_iter()
+        .for_each(|func| write_u64(func.into()));
+
+    match capabilities.lookup_table_bits {
+        Some(bits) => [1, bits.into()].into_iter().for_each(&mut write_u64),
+        None => write_u64(0),
+    }
+
+    let weights = &capabilities.gate_weights;
+    write_u64(weights.arithmetic.len() as u64);
+    weights.arithmetic.iter().copied().for_each(&mut write_u64);
+    let mut black_box_weights: Vec<(u16, u64)> = weights
+        .black_box
+        .iter()
+        .map(|(func, cost)| (func.to_u16(), *cost))
+        .collect();
+    black_box_weights.sort();
+    write_u64(black_box_weights.len() as u64);
+    for (func, cost) in black_box_weights {
+        write_u64(func.into());
+        write_u64(cost);
+    }
+    write_u64(weights.default_black_box);
+
+    write_u64(pinned.len() as u64);
+    pinned
+        .iter()
+        .for_each(|witness| write_u64(witness.witness_index().into()));
+
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    hasher.finalize_fixed().into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        compiler::{compile_for_backend, GateWeights},
+        testing::{fixtures, MockBackend},
+        Language,
+    };
+
+    fn key(backend: &MockBackend, circuit: &Circuit) -> CacheKey {
+        (
+            backend.name().to_owned(),
+            circuit_digest(circuit),
+            options_digest(backend, &BTreeSet::new()),
+        )
+    }
+
+    #[test]
+    fn circuits_are_compiled_once_per_backend_and_options() {
+        let circuit = fixtures().swap_remove(2).circuit;
+        let backend = MockBackend::new().with_supported_opcodes([]);
+        let cache = CircuitCache::new();
+
+        let compiled = cache
+            .get_or_compile(&backend, &circuit, &BTreeSet::new())
+            .unwrap();
+        assert_eq!(
+            compiled.circuit,
+            compile_for_backend(circuit.clone(), &backend).unwrap()
+        );
+        let cached =
+            cache.get_or_transform(key(&backend, &circuit), || panic!("the circuit is cached"));
+        assert_eq!(cached, Ok(compiled.clone()));
+
+        // A different language changes the options, so the circuit is compiled again
+        let wider = backend
+            .clone()
+            .with_language(Language::PLONKCSat { width: 4 });
+        assert_ne!(key(&wider, &circuit), key(&backend, &circuit));
+        let compiled_wider = cache
+            .get_or_compile(&wider, &circuit, &BTreeSet::new())
+            .unwrap();
+        assert_ne!(compiled_wider, compiled);
+
+        // As do the gate weights, which change how gates are merged
+        let weighted = wider
+            .clone()
+            .with_gate_weights(GateWeights::default().with_arithmetic(vec![1, 1, 1, 10]));
+        assert_ne!(key(&weighted, &circuit), key(&wider, &circuit));
+
+        let pinned = BTreeSet::from([Witness(1)]);
+        let compiled = cache.get_or_compile(&wider, &circuit, &pinned).unwrap();
+        assert_eq!(
+            compiled.witness_indices,
+            BTreeMap::from([(Witness(1), Witness(1))])
+        );
+    }
+
+    #[test]
+    fn circuits_are_reloaded_from_disk() {
+        let circuit = fixtures().swap_remove(2).circuit;
+        let backend = MockBackend::new().with_supported_opcodes([]);
+        let pinned = BTreeSet::from([Witness(1), Witness(2)]);
+        let dir = tempfile::tempdir().unwrap();
+
+        let compiled = CircuitCache::with_directory(dir.path())
+            .get_or_compile(&backend, &circuit, &pinned)
+            .unwrap();
+
+        // A new cache has nothing in memory, so must find the circuit on disk
+        let cache = CircuitCache::with_directory(dir.path());
+        let key = (
+            backend.name().to_owned(),
+            circuit_digest(&circuit),
+            options_digest(&backend, &pinned),
+        );
+        let cached = cache.get_or_transform(key, || panic!("the circuit is on disk"));
+        assert_eq!(cached, Ok(compiled));
+    }
+}
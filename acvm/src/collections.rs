@@ -0,0 +1,15 @@
+// The hash maps and sets which ACVM uses
+//
+// With the `std` feature these are the standard library's, so that they can be passed to
+// and from ACVM as any other map. Without it they are hashbrown's, with its default hasher,
+// as the standard library's maps are not available with only `alloc`. Code which builds
+// with and without the feature should name these rather than either library's maps.
+
+#[cfg(feature = "std")]
+pub use std::collections::{hash_map::RandomState as DefaultHashBuilder, HashMap, HashSet};
+
+#[cfg(not(feature = "std"))]
+pub use hashbrown::{DefaultHashBuilder, HashMap, HashSet};
+
+/// A map which iterates in insertion order, hashed as `HashMap` is
+pub type IndexMap<K, V> = indexmap::IndexMap<K, V, DefaultHashBuilder>;
@@ -1,84 +1,424 @@
 // The various passes that we can use over ACIR
+use alloc::{string::String, vec, vec::Vec};
+mod air;
+mod attribution;
+mod cost;
+mod directive_lowering;
 pub mod fallback;
+mod inline;
+mod lookup;
+mod lowering;
+mod memory;
 pub mod optimiser;
+pub(crate) mod parallel;
+mod r1cs;
+mod redundancy;
+mod soundness;
+mod stats;
+mod unconstrained;
+mod validate;
 
-use crate::Language;
+use alloc::collections::{BTreeMap, BTreeSet};
+
+use crate::collections::IndexMap;
+use crate::{
+    metrics,
+    profile::{self, ProfileReport},
+    pwg, trace, BackendCapabilities, BackendError, Language, ProofSystemCompiler,
+};
 use acir::{
     circuit::{Circuit, Opcode},
     native_types::{Expression, Witness},
-    BlackBoxFunc,
+    AcirField, BlackBoxFunc,
 };
-use indexmap::IndexMap;
-use optimiser::{CSatOptimiser, GeneralOptimiser};
+use optimiser::{CSatOptimiser, GeneralOptimiser, WidthPacker};
 use thiserror::Error;
 
-use self::{fallback::IsBlackBoxSupported, optimiser::R1CSOptimiser};
+use self::optimiser::R1CSOptimiser;
+pub use air::{to_air, Air, AirRow};
+pub use attribution::{attribute_gates, CallStack, CallStackCost, ConstraintProfile};
+pub use cost::{
+    estimate_cost, estimate_cost_with_weights, estimate_proving_time, CostEstimate, GateWeights,
+    ProvingTimeModel,
+};
+pub use directive_lowering::{lower_directives, max_quotient_bit_size, DirectiveLowering};
+pub use inline::inline_calls;
+pub use lookup::{lower_to_lookups, MAX_LOOKUP_TABLE_BITS};
+pub use lowering::choose_lowerings;
+pub use memory::{estimate_memory, estimate_program_memory, MemoryEstimate};
+#[cfg(any(test, feature = "testing"))]
+pub(crate) use parallel::with_shuffled_order;
+pub use r1cs::{to_r1cs, R1cs, SparseRow};
+pub use redundancy::{find_redundant_constraints, Redundancy, RedundantConstraint};
+pub use soundness::check_soundness;
+pub use stats::{stats, CircuitStats};
+pub use unconstrained::{find_unconstrained_witnesses, UnconstrainedWitness, WitnessUse};
+pub use validate::{validate, ValidationError};
 
 #[derive(PartialEq, Eq, Debug, Error)]
 pub enum CompileError {
     #[error("The blackbox function {0} is not supported by the backend and acvm does not have a fallback implementation")]
     UnsupportedBlackBox(BlackBoxFunc),
+    #[error("call to function {0} which is not in the program")]
+    UnknownFunction(u32),
+    #[error("function {0} calls itself, recursive programs cannot be inlined")]
+    RecursiveCall(u32),
+    #[error(
+        "function {0} has {1} parameters and return values, but it is called with {2} inputs and outputs"
+    )]
+    IncorrectNumCallArguments(u32, usize, usize),
+    #[error(
+        "opcode {0} cannot be converted to R1CS or AIR, it must be lowered to arithmetic opcodes first"
+    )]
+    NonArithmeticOpcode(String),
+    #[error("opcode {0} does not fit into an AIR row of {1} columns, the circuit must be compiled for the same width")]
+    OpcodeTooWide(usize, usize),
+    #[error("opcode {opcode_index} constrains witness {} to {num_bits} bits, but the compiled circuit does not", witness.witness_index())]
+    WeakenedRange {
+        opcode_index: usize,
+        witness: Witness,
+        num_bits: u32,
+    },
+    #[error(
+        "the backend's lookup tables have {0} bits, but lookup tables must have between 1 and {max} bits",
+        max = MAX_LOOKUP_TABLE_BITS
+    )]
+    InvalidLookupTableBits(u32),
 }
 
-pub fn compile(
-    acir: Circuit,
+pub fn compile<F: AcirField>(
+    acir: Circuit<F>,
+    np_language: Language,
+    is_blackbox_supported: impl Fn(&BlackBoxFunc) -> bool,
+) -> Result<Circuit<F>, CompileError> {
+    compile_impl(
+        acir,
+        np_language,
+        is_blackbox_supported,
+        None,
+        &BTreeSet::new(),
+        &GateWeights::default(),
+    )
+    .map(|(circuit, _)| circuit)
+}
+
+/// Compiles a circuit as `compile` does, adding the time taken to lower each unsupported
+/// black box function and to reduce each arithmetic expression to `profile`.
+#[cfg(feature = "std")]
+pub fn compile_profiled<F: AcirField>(
+    acir: Circuit<F>,
+    np_language: Language,
+    is_blackbox_supported: impl Fn(&BlackBoxFunc) -> bool,
+    profile: &mut ProfileReport,
+) -> Result<Circuit<F>, CompileError> {
+    compile_impl(
+        acir,
+        np_language,
+        is_blackbox_supported,
+        Some(profile),
+        &BTreeSet::new(),
+        &GateWeights::default(),
+    )
+    .map(|(circuit, _)| circuit)
+}
+
+/// Compiles a circuit as `compile` does, weighing the gates by `weights` rather than
+/// counting them when deciding whether to merge gates.
+pub fn compile_with_weights<F: AcirField>(
+    acir: Circuit<F>,
+    np_language: Language,
+    is_blackbox_supported: impl Fn(&BlackBoxFunc) -> bool,
+    weights: &GateWeights,
+) -> Result<Circuit<F>, CompileError> {
+    compile_impl(
+        acir,
+        np_language,
+        is_blackbox_supported,
+        None,
+        &BTreeSet::new(),
+        weights,
+    )
+    .map(|(circuit, _)| circuit)
+}
+
+/// Compiles a circuit as `compile` does, also returning the index of the opcode of `acir`
+/// which each compiled opcode comes from. This is used to attribute the cost of the
+/// compiled circuit to the opcodes of the original one.
+pub fn compile_with_origins<F: AcirField>(
+    acir: Circuit<F>,
+    np_language: Language,
+    is_blackbox_supported: impl Fn(&BlackBoxFunc) -> bool,
+) -> Result<(Circuit<F>, Vec<usize>), CompileError> {
+    compile_impl(
+        acir,
+        np_language,
+        is_blackbox_supported,
+        None,
+        &BTreeSet::new(),
+        &GateWeights::default(),
+    )
+}
+
+/// Compiles a circuit as `compile` does, keeping the witnesses in `pinned`, and returns the
+/// index which each of them has in the compiled circuit.
+///
+/// The optimisers remove terms with a zero coefficient, and merging gates removes terms
+/// which cancel out, either of which can remove every use of a witness. A pinned witness
+/// keeps a term with a zero coefficient instead, so each pinned witness which `acir` uses
+/// is also used by the compiled circuit.
+/// Pinned witnesses which the compiled circuit does not use are not in the returned map.
+pub fn compile_with_pins<F: AcirField>(
+    acir: Circuit<F>,
+    np_language: Language,
+    is_blackbox_supported: impl Fn(&BlackBoxFunc) -> bool,
+    pinned: &BTreeSet<Witness>,
+) -> Result<(Circuit<F>, BTreeMap<Witness, Witness>), CompileError> {
+    compile_pinned(
+        acir,
+        np_language,
+        is_blackbox_supported,
+        pinned,
+        &GateWeights::default(),
+    )
+}
+
+fn compile_pinned<F: AcirField>(
+    acir: Circuit<F>,
+    np_language: Language,
+    is_blackbox_supported: impl Fn(&BlackBoxFunc) -> bool,
+    pinned: &BTreeSet<Witness>,
+    weights: &GateWeights,
+) -> Result<(Circuit<F>, BTreeMap<Witness, Witness>), CompileError> {
+    let (circuit, _) = compile_impl(
+        acir,
+        np_language,
+        is_blackbox_supported,
+        None,
+        pinned,
+        weights,
+    )?;
+
+    let mut used: BTreeSet<Witness> = circuit
+        .opcodes
+        .iter()
+        .flat_map(pwg::opcode_witnesses)
+        .collect();
+    used.extend(&circuit.public_inputs.0);
+    used.extend(&circuit.return_values.0);
+    // The compiler only creates and removes intermediate witnesses, so the witnesses of
+    // `acir` keep their indices
+    let indices = pinned
+        .intersection(&used)
+        .map(|&witness| (witness, witness))
+        .collect();
+    Ok((circuit, indices))
+}
+
+fn compile_impl<F: AcirField>(
+    acir: Circuit<F>,
     np_language: Language,
-    is_blackbox_supported: IsBlackBoxSupported,
-) -> Result<Circuit, CompileError> {
+    is_blackbox_supported: impl Fn(&BlackBoxFunc) -> bool,
+    mut profile: Option<&mut ProfileReport>,
+    pinned: &BTreeSet<Witness>,
+    weights: &GateWeights,
+) -> Result<(Circuit<F>, Vec<usize>), CompileError> {
+    let _span = trace::span!(
+        INFO,
+        "compile",
+        language = ?np_language,
+        num_opcodes = acir.opcodes.len()
+    );
+    let _pass = metrics::pass("compile");
+
     // Instantiate the optimiser.
     // Currently the optimiser and reducer are one in the same
     // for CSAT
 
+    // The range and boolean constraints which the compiled circuit must still apply
+    let required_ranges = soundness::required_ranges(&acir);
+
     // Fallback pass
-    let fallback = fallback::fallback(acir, is_blackbox_supported)?;
+    let (fallback, fallback_origins) =
+        fallback::fallback_impl(acir, is_blackbox_supported, profile.as_deref_mut())?;
 
-    let optimiser = match &np_language {
-        crate::Language::R1CS => {
-            let optimiser = R1CSOptimiser::new(fallback);
-            return Ok(optimiser.optimise());
+    // Languages without a fixed width accept expressions of degree 2 as they are
+    let (optimiser, packer) = match np_language.width() {
+        None => {
+            let optimiser = R1CSOptimiser::new(fallback).with_pinned(pinned.clone());
+            let circuit = optimiser.optimise_with_profile(profile);
+            soundness::check_ranges(&required_ranges, &circuit)?;
+            trace::event!(
+                INFO,
+                num_opcodes = circuit.opcodes.len(),
+                num_witnesses = circuit.num_vars(),
+                "compiled circuit"
+            );
+            // Opcodes are optimised in place, so they keep their origins
+            return Ok((circuit, fallback_origins));
         }
-        crate::Language::PLONKCSat { width } => CSatOptimiser::new(*width),
+        Some(width) => (
+            CSatOptimiser::new(width).with_pinned(pinned.clone()),
+            WidthPacker::new(width)
+                .with_pinned(pinned.clone())
+                .with_weights(weights.clone()),
+        ),
     };
 
     // TODO: the code below is only for CSAT optimiser
     // TODO it may be possible to refactor it in a way that we do not need to return early from the r1cs
     // TODO or at the very least, we could put all of it inside of CSATOptimiser pass
 
-    // Optimise the arithmetic gates by reducing them into the correct width and
-    // creating intermediate variables when necessary
-    let mut optimised_gates = Vec::new();
-
-    let mut next_witness_index = fallback.current_witness_index + 1;
-    for opcode in fallback.opcodes {
-        match opcode {
+    let (circuit, origins) = {
+        let _span = trace::span!(DEBUG, "optimise");
+        let _pass = metrics::pass("optimise");
+        // Optimise the arithmetic gates by reducing them into the correct width and
+        // creating intermediate variables when necessary
+        // Each gate is reduced independently, and its intermediate variables are then
+        // renumbered to follow those of the gates before it
+        let first_intermediate = fallback.current_witness_index + 1;
+        let profiling = profile.is_some();
+        let reduced = parallel::map(fallback.opcodes, |opcode| match opcode {
             Opcode::Arithmetic(arith_expr) => {
-                let mut intermediate_variables: IndexMap<Witness, Expression> = IndexMap::new();
-
-                let arith_expr =
-                    optimiser.optimise(arith_expr, &mut intermediate_variables, next_witness_index);
-
-                // Update next_witness counter
-                next_witness_index += intermediate_variables.len() as u32;
-                let mut new_gates = Vec::new();
-                for (_, mut g) in intermediate_variables {
-                    g.sort();
-                    new_gates.push(g);
-                }
-                new_gates.push(arith_expr);
-                new_gates.sort();
-                for gate in new_gates {
-                    optimised_gates.push(Opcode::Arithmetic(gate));
-                }
+                let start = profile::start_timer(profiling);
+                let (new_gates, num_intermediates) =
+                    csat_reduce(&optimiser, &packer, arith_expr, first_intermediate);
+                (
+                    new_gates.into_iter().map(Opcode::Arithmetic).collect(),
+                    num_intermediates,
+                    start.map(|start| start.elapsed()),
+                )
             }
-            other_gate => optimised_gates.push(other_gate),
+            other_gate => (vec![other_gate], 0, None),
+        });
+
+        let mut optimised_gates = Vec::new();
+        let mut origins = Vec::new();
+        let mut next_witness_index = first_intermediate;
+        for ((new_gates, num_intermediates, elapsed), origin) in
+            reduced.into_iter().zip(fallback_origins)
+        {
+            origins.extend(core::iter::repeat_n(origin, new_gates.len()));
+            if let (Some(profile), Some(elapsed)) = (profile.as_deref_mut(), elapsed) {
+                profile.record("arithmetic", elapsed);
+            }
+            let offset = next_witness_index - first_intermediate;
+            optimised_gates.extend(
+                new_gates
+                    .into_iter()
+                    .map(|gate| parallel::shift_intermediates(gate, first_intermediate, offset)),
+            );
+            next_witness_index += num_intermediates;
         }
+
+        let current_witness_index = next_witness_index - 1;
+
+        let circuit = Circuit {
+            current_witness_index,
+            opcodes: optimised_gates,
+            public_inputs: fallback.public_inputs, // The optimiser does not add public inputs
+            // Nor does it renumber the witnesses of the original circuit
+            return_values: fallback.return_values,
+        };
+        (circuit, origins)
+    };
+    soundness::check_ranges(&required_ranges, &circuit)?;
+    trace::event!(
+        INFO,
+        num_opcodes = circuit.opcodes.len(),
+        num_witnesses = circuit.num_vars(),
+        "compiled circuit"
+    );
+    Ok((circuit, origins))
+}
+
+/// Compiles a circuit for the given backend.
+///
+/// This uses the backend's language, supported opcodes and gate weights. Each AND, XOR and
+/// RANGE call is kept, split into the backend's lookup tables if it supports lookups, or
+/// replaced with its fallback, whichever weighs least. Backends whose language has lookups
+/// but which do not specify a table size get tables of `MAX_LOOKUP_TABLE_BITS`.
+///
+/// Returns `BackendError::FieldMismatch` if the backend's field is not ACVM's, and
+/// `CompileError::InvalidLookupTableBits` if its tables are larger than `MAX_LOOKUP_TABLE_BITS`
+/// bits or have no bits.
+pub fn compile_for_backend<B: ProofSystemCompiler + ?Sized>(
+    acir: Circuit,
+    backend: &B,
+) -> Result<Circuit, BackendError> {
+    compile_for_backend_with_pins(acir, backend, &BTreeSet::new()).map(|(circuit, _)| circuit)
+}
+
+/// Compiles a circuit for the given backend as `compile_for_backend` does, keeping the
+/// witnesses in `pinned` and returning their indices as `compile_with_pins` does
+pub fn compile_for_backend_with_pins<B: ProofSystemCompiler + ?Sized>(
+    acir: Circuit,
+    backend: &B,
+    pinned: &BTreeSet<Witness>,
+) -> Result<(Circuit, BTreeMap<Witness, Witness>), BackendError> {
+    backend.check_field_compatibility()?;
+    let capabilities = backend.capabilities();
+    let lookup_table_bits = lookup_table_bits(&capabilities)?;
+    let is_blackbox_supported =
+        |func: &BlackBoxFunc| capabilities.supported_blackbox_functions.contains(func);
+    let acir = choose_lowerings(
+        acir,
+        &capabilities.np_language,
+        is_blackbox_supported,
+        lookup_table_bits,
+        &capabilities.gate_weights,
+    )?;
+
+    let compiled = compile_pinned(
+        acir,
+        capabilities.np_language.clone(),
+        is_blackbox_supported,
+        pinned,
+        &capabilities.gate_weights,
+    )?;
+    Ok(compiled)
+}
+
+// The size of the lookup tables which AND, XOR and RANGE calls are split into, if the
+// backend has lookups. Tables the backend gives must fit into a `ToRadix` limb
+fn lookup_table_bits(capabilities: &BackendCapabilities) -> Result<Option<u32>, CompileError> {
+    match capabilities.lookup_table_bits {
+        Some(table_bits) if !(1..=MAX_LOOKUP_TABLE_BITS).contains(&table_bits) => {
+            Err(CompileError::InvalidLookupTableBits(table_bits))
+        }
+        Some(table_bits) => Ok(Some(table_bits)),
+        None => Ok(capabilities
+            .np_language
+            .supports_lookup()
+            .then_some(MAX_LOOKUP_TABLE_BITS)),
+    }
+}
+
+// Reduces an arithmetic expression into gates which fit into the width of the CSAT optimiser
+// Returns the gates along with the number of intermediate variables which were created
+fn csat_reduce<F: AcirField>(
+    optimiser: &CSatOptimiser,
+    packer: &WidthPacker,
+    arith_expr: Expression<F>,
+    next_witness_index: u32,
+) -> (Vec<Expression<F>>, u32) {
+    let mut intermediate_variables: IndexMap<Witness, Expression<F>> = IndexMap::default();
+
+    let arith_expr =
+        optimiser.optimise(arith_expr, &mut intermediate_variables, next_witness_index);
+
+    let num_intermediates = intermediate_variables.len() as u32;
+    let mut new_gates = Vec::new();
+    for (_, mut g) in intermediate_variables {
+        g.sort();
+        new_gates.push(g);
     }
+    new_gates.push(arith_expr);
+    new_gates.sort();
 
-    let current_witness_index = next_witness_index - 1;
+    // Fill gates which do not use the whole width by merging them.
+    // Intermediate variables which are merged away are left as gaps in the witness indices
+    let mut new_gates = packer.pack(new_gates, Witness(next_witness_index));
+    new_gates.sort();
 
-    Ok(Circuit {
-        current_witness_index,
-        opcodes: optimised_gates,
-        public_inputs: fallback.public_inputs, // The optimiser does not add public inputs
-    })
+    (new_gates, num_intermediates)
 }
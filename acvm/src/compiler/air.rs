@@ -0,0 +1,297 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{borrow::ToOwned, vec, vec::Vec};
+
+use acir::{
+    circuit::{Circuit, Opcode},
+    native_types::{Expression, Witness},
+    AcirField, FieldElement,
+};
+
+use super::CompileError;
+use crate::{pwg::witness_to_value, OpcodeResolutionError, WitnessMap};
+
+/// A row of an AIR trace, which holds the witnesses of one arithmetic opcode along with
+/// the selectors which are fixed by the circuit.
+///
+/// Every row satisfies the transition constraint
+/// `q_m·w_0·w_1 + q_s·w_0·w_0 + Σ q_l[i]·w_i + q_c = 0`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AirRow<F = FieldElement> {
+    /// The witness in each witness column, or None if the cell is unused and holds zero
+    pub witnesses: Vec<Option<Witness>>,
+    /// Scales the product of the first two witness columns
+    pub q_m: F,
+    /// Scales the square of the first witness column
+    pub q_s: F,
+    /// Scales each witness column
+    pub q_l: Vec<F>,
+    pub q_c: F,
+}
+
+impl<F: AcirField> AirRow<F> {
+    fn empty(width: usize) -> Self {
+        AirRow {
+            witnesses: vec![None; width],
+            q_m: F::zero(),
+            q_s: F::zero(),
+            q_l: vec![F::zero(); width],
+            q_c: F::zero(),
+        }
+    }
+}
+
+/// An algebraic intermediate representation of a circuit, for STARK provers.
+///
+/// The trace has `width` witness columns and the selector columns of `AirRow`. The number
+/// of rows is a power of two, as provers interpolate columns over a subgroup, and rows
+/// which pad the trace have zero selectors. Cells which hold the same witness must be
+/// constrained to be equal by the prover, for example with a permutation argument.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Air<F = FieldElement> {
+    pub width: usize,
+    pub rows: Vec<AirRow<F>>,
+    /// The cells, as pairs of a row and a column, of each witness which is in more than
+    /// one cell
+    pub copies: Vec<Vec<(usize, usize)>>,
+    /// The cell which holds each public input, in order, for boundary constraints
+    pub public_inputs: Vec<(usize, usize)>,
+}
+
+impl<F: AcirField> Air<F> {
+    pub fn num_rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the witness columns of the trace for a solved witness map, as the values
+    /// of each row
+    pub fn trace(
+        &self,
+        witness_values: &WitnessMap<F>,
+    ) -> Result<Vec<Vec<F>>, OpcodeResolutionError<F>> {
+        self.rows
+            .iter()
+            .map(|row| {
+                row.witnesses
+                    .iter()
+                    .map(|witness| match witness {
+                        Some(witness) => witness_to_value(witness_values, *witness).copied(),
+                        None => Ok(F::zero()),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns whether every row of the trace satisfies the transition constraint, and
+    /// every copy of a witness has the same value
+    pub fn is_satisfied(&self, trace: &[Vec<F>]) -> bool {
+        let rows_are_satisfied = self.rows.iter().zip(trace).all(|(row, values)| {
+            let product = match values.as_slice() {
+                [] => F::zero(),
+                [w_0] => row.q_s * *w_0 * *w_0,
+                [w_0, w_1, ..] => row.q_m * *w_0 * *w_1 + row.q_s * *w_0 * *w_0,
+            };
+            let linear = row
+                .q_l
+                .iter()
+                .zip(values)
+                .fold(row.q_c, |acc, (q, w)| acc + *q * *w);
+            (product + linear).is_zero()
+        });
+        let copies_are_equal = self.copies.iter().all(|cells| {
+            cells
+                .windows(2)
+                .all(|pair| trace[pair[0].0][pair[0].1] == trace[pair[1].0][pair[1].1])
+        });
+        trace.len() == self.rows.len() && rows_are_satisfied && copies_are_equal
+    }
+}
+
+/// Lays a circuit out as an AIR trace with `width` witness columns.
+///
+/// The circuit must have been compiled for `Language::AIR` of the same width, so that each
+/// arithmetic opcode fits into one row, and lowered to arithmetic opcodes. Directives are
+/// skipped, as they do not add constraints. Public inputs which are not used by any
+/// opcode get a row of their own.
+pub fn to_air<F: AcirField>(circuit: &Circuit<F>, width: usize) -> Result<Air<F>, CompileError> {
+    assert!(width > 0, "an AIR trace needs at least one witness column");
+
+    let mut rows = Vec::new();
+    for (index, opcode) in circuit.opcodes.iter().enumerate() {
+        match opcode {
+            Opcode::Arithmetic(expr) => {
+                rows.push(layout_row(expr, width).ok_or(CompileError::OpcodeTooWide(index, width))?)
+            }
+            Opcode::Directive(_) => {}
+            Opcode::BlackBoxFuncCall(_) | Opcode::Call { .. } => {
+                return Err(CompileError::NonArithmeticOpcode(opcode.name().to_owned()))
+            }
+        }
+    }
+
+    let mut used: BTreeSet<Witness> = rows
+        .iter()
+        .flat_map(|row| row.witnesses.iter().flatten().copied())
+        .collect();
+    for witness in &circuit.public_inputs.0 {
+        if used.insert(*witness) {
+            let mut row = AirRow::empty(width);
+            row.witnesses[0] = Some(*witness);
+            rows.push(row);
+        }
+    }
+
+    let mut cells: BTreeMap<Witness, Vec<(usize, usize)>> = BTreeMap::new();
+    for (row_index, row) in rows.iter().enumerate() {
+        for (column, witness) in row.witnesses.iter().enumerate() {
+            if let Some(witness) = witness {
+                cells.entry(*witness).or_default().push((row_index, column));
+            }
+        }
+    }
+    let public_inputs = circuit
+        .public_inputs
+        .0
+        .iter()
+        .map(|witness| cells[witness][0])
+        .collect();
+    let copies = cells
+        .into_values()
+        .filter(|cells| cells.len() > 1)
+        .collect();
+
+    let num_rows = rows.len().next_power_of_two();
+    rows.resize_with(num_rows, || AirRow::empty(width));
+
+    Ok(Air {
+        width,
+        rows,
+        copies,
+        public_inputs,
+    })
+}
+
+// Places the witnesses of the product first, so that they are in the columns which the
+// selectors multiply, or returns None if the expression does not fit into one row
+fn layout_row<F: AcirField>(expr: &Expression<F>, width: usize) -> Option<AirRow<F>> {
+    let mut row = AirRow::empty(width);
+    let mut columns: Vec<Witness> = Vec::with_capacity(width);
+    let mut column_of = |witness: Witness| match columns.iter().position(|w| *w == witness) {
+        Some(column) => Some(column),
+        None if columns.len() < width => {
+            columns.push(witness);
+            Some(columns.len() - 1)
+        }
+        None => None,
+    };
+
+    match expr.mul_terms.as_slice() {
+        [] => {}
+        [(q, l, r)] if l == r => {
+            column_of(*l)?;
+            row.q_s = *q;
+        }
+        [(q, l, r)] => {
+            column_of(*l)?;
+            column_of(*r)?;
+            row.q_m = *q;
+        }
+        _ => return None,
+    }
+    for (q, witness) in &expr.linear_combinations {
+        let column = column_of(*witness)?;
+        row.q_l[column] += *q;
+    }
+    row.q_c = expr.q_c;
+
+    for (cell, witness) in row.witnesses.iter_mut().zip(columns) {
+        *cell = Some(witness);
+    }
+    Some(row)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        compiler::compile, pwg::NativeSolver, testing::generate_satisfied_circuit, Language,
+        PartialWitnessGenerator,
+    };
+    use acir::circuit::PublicInputs;
+
+    #[test]
+    fn compiled_circuits_fit_into_rows() {
+        for seed in 0..5 {
+            let generated = generate_satisfied_circuit(40, seed);
+            for width in [3, 4, 5] {
+                let language = Language::AIR { width, degree: 3 };
+                let compiled = compile(generated.circuit.clone(), language, |_| false).unwrap();
+                let air = to_air(&compiled, width).unwrap();
+                assert!(air.num_rows().is_power_of_two());
+                assert_eq!(
+                    air.public_inputs.len(),
+                    compiled.public_inputs.0.len(),
+                    "every public input has a cell"
+                );
+
+                let mut witness = generated.inputs.clone();
+                NativeSolver.solve(&mut witness, compiled.opcodes).unwrap();
+                let mut trace = air.trace(&witness).unwrap();
+                assert!(air.is_satisfied(&trace));
+
+                // Changing one copy of a witness breaks the constraints
+                let (row, column) = air.copies[0][1];
+                trace[row][column] += FieldElement::one();
+                assert!(!air.is_satisfied(&trace));
+            }
+        }
+    }
+
+    #[test]
+    fn rows_match_the_opcodes() {
+        let (x, y, z, p) = (Witness(1), Witness(2), Witness(3), Witness(4));
+        // 2·x·x + 3·x - y + 1 = 0 and x·z - y = 0, with p public but unused
+        let circuit = Circuit {
+            current_witness_index: 4,
+            opcodes: vec![
+                Opcode::Arithmetic(Expression {
+                    mul_terms: vec![(FieldElement::from(2_i128), x, x)],
+                    linear_combinations: vec![
+                        (FieldElement::from(3_i128), x),
+                        (-FieldElement::one(), y),
+                    ],
+                    q_c: FieldElement::one(),
+                }),
+                Opcode::Arithmetic(Expression {
+                    mul_terms: vec![(FieldElement::one(), x, z)],
+                    linear_combinations: vec![(-FieldElement::one(), y)],
+                    q_c: FieldElement::zero(),
+                }),
+            ],
+            public_inputs: PublicInputs(vec![y, p]),
+            return_values: PublicInputs::default(),
+        };
+        let air = to_air(&circuit, 3).unwrap();
+
+        assert_eq!(air.num_rows(), 4);
+        assert_eq!(air.rows[0].witnesses, vec![Some(x), Some(y), None]);
+        assert_eq!(air.rows[0].q_s, FieldElement::from(2_i128));
+        assert_eq!(air.rows[1].witnesses, vec![Some(x), Some(z), Some(y)]);
+        assert_eq!(air.rows[1].q_m, FieldElement::one());
+        assert_eq!(air.rows[2].witnesses, vec![Some(p), None, None]);
+        assert_eq!(air.rows[3], AirRow::empty(3));
+        assert_eq!(air.copies, vec![vec![(0, 0), (1, 0)], vec![(0, 1), (1, 2)]]);
+        assert_eq!(air.public_inputs, vec![(0, 1), (2, 0)]);
+
+        // x = 2 gives y = 8 + 6 + 1 = 15, so z = 15 / 2
+        let witness = WitnessMap::from([
+            (x, FieldElement::from(2_i128)),
+            (y, FieldElement::from(15_i128)),
+            (z, FieldElement::from(15_i128) / FieldElement::from(2_i128)),
+            (p, FieldElement::from(7_i128)),
+        ]);
+        assert!(air.is_satisfied(&air.trace(&witness).unwrap()));
+
+        assert_eq!(to_air(&circuit, 2), Err(CompileError::OpcodeTooWide(1, 2)));
+    }
+}
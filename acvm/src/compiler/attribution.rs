@@ -0,0 +1,174 @@
+use alloc::collections::BTreeMap;
+use alloc::{format, string::String, vec::Vec};
+
+use acir::circuit::{Circuit, Opcode};
+use acir::AcirField;
+use serde::{Deserialize, Serialize};
+
+/// The frontend call stack which an opcode was generated by, outermost call first,
+/// such as `["main.nr:12:5", "sha256.nr:40:9"]`
+pub type CallStack = Vec<String>;
+
+/// The gates which are attributed to one call stack
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallStackCost {
+    pub call_stack: CallStack,
+    /// The number of gates in the compiled circuit
+    pub num_gates: usize,
+    /// The number of opcodes in the compiled circuit, including directives
+    pub num_opcodes: usize,
+}
+
+/// The gates of a compiled circuit, attributed to the frontend call stacks which
+/// generated them
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstraintProfile {
+    /// The total number of gates in the compiled circuit
+    pub total_gates: usize,
+    /// The cost of each call stack, most gates first
+    pub call_stacks: Vec<CallStackCost>,
+    /// The number of gates which come from opcodes without a call stack
+    pub unattributed_gates: usize,
+}
+
+/// Attributes the gates of a compiled circuit to the call stacks of the opcodes of the
+/// original circuit, where `origins` is returned by `compile_with_origins` and
+/// `call_stacks` is the debug information of the frontend, keyed by opcode index.
+///
+/// As in `estimate_cost`, arithmetic opcodes and black box functions are each counted as
+/// one gate, and directives and calls are free.
+pub fn attribute_gates<F: AcirField>(
+    compiled: &Circuit<F>,
+    origins: &[usize],
+    call_stacks: &BTreeMap<usize, CallStack>,
+) -> ConstraintProfile {
+    assert_eq!(
+        compiled.opcodes.len(),
+        origins.len(),
+        "there must be an origin for each compiled opcode"
+    );
+
+    let mut profile = ConstraintProfile::default();
+    let mut costs: BTreeMap<&CallStack, (usize, usize)> = BTreeMap::new();
+    for (opcode, origin) in compiled.opcodes.iter().zip(origins) {
+        let num_gates = match opcode {
+            Opcode::Arithmetic(_) | Opcode::BlackBoxFuncCall(_) => 1,
+            Opcode::Directive(_) | Opcode::Call { .. } => 0,
+        };
+        profile.total_gates += num_gates;
+        match call_stacks.get(origin) {
+            Some(call_stack) => {
+                let (gates, opcodes) = costs.entry(call_stack).or_default();
+                *gates += num_gates;
+                *opcodes += 1;
+            }
+            None => profile.unattributed_gates += num_gates,
+        }
+    }
+
+    profile.call_stacks = costs
+        .into_iter()
+        .map(|(call_stack, (num_gates, num_opcodes))| CallStackCost {
+            call_stack: call_stack.clone(),
+            num_gates,
+            num_opcodes,
+        })
+        .collect();
+    profile
+        .call_stacks
+        .sort_by_key(|cost| core::cmp::Reverse(cost.num_gates));
+    profile
+}
+
+impl ConstraintProfile {
+    /// Returns the profile in the folded stack format read by flamegraph tools such as
+    /// `inferno-flamegraph`, with one line of `frame;frame gates` per call stack.
+    /// Unattributed gates are reported under an `unknown` frame.
+    pub fn to_folded(&self) -> String {
+        let mut folded = String::new();
+        for cost in self.call_stacks.iter().filter(|cost| cost.num_gates > 0) {
+            folded.push_str(&cost.call_stack.join(";"));
+            folded.push_str(&format!(" {}\n", cost.num_gates));
+        }
+        if self.unattributed_gates > 0 {
+            folded.push_str(&format!("unknown {}\n", self.unattributed_gates));
+        }
+        folded
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{compiler::compile_with_origins, Language};
+    use acir::{
+        circuit::{
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            PublicInputs,
+        },
+        native_types::{Expression, Witness},
+        BlackBoxFunc, FieldElement,
+    };
+
+    #[test]
+    fn gates_are_attributed_to_call_stacks() {
+        // A range check, which is lowered into many gates, and a wide expression which
+        // is split into two at width 3
+        let mut wide = Expression::default();
+        for witness in 1..=4 {
+            wide.term_addition(FieldElement::one(), Witness(witness));
+        }
+        let circuit = Circuit {
+            current_witness_index: 4,
+            opcodes: vec![
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::RANGE,
+                    inputs: vec![FunctionInput {
+                        witness: Witness(1),
+                        num_bits: 8,
+                    }],
+                    outputs: Vec::new(),
+                }),
+                Opcode::Arithmetic(wide),
+                Opcode::Arithmetic(&Expression::from(&Witness(1)) - &Witness(2)),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        };
+        let (compiled, origins) =
+            compile_with_origins(circuit.clone(), Language::PLONKCSat { width: 3 }, |_| false)
+                .unwrap();
+        assert_eq!(
+            compiled,
+            crate::compiler::compile(circuit, Language::PLONKCSat { width: 3 }, |_| false).unwrap()
+        );
+        assert_eq!(origins.len(), compiled.opcodes.len());
+        assert!(origins.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        let main = |line: usize| vec![format!("main.nr:{line}:5")];
+        let call_stacks = BTreeMap::from([
+            (0, vec!["main.nr:2:5".to_owned(), "range.nr:7:9".to_owned()]),
+            (1, main(3)),
+        ]);
+        let profile = attribute_gates(&compiled, &origins, &call_stacks);
+
+        let range_opcodes = origins.iter().filter(|origin| **origin == 0).count();
+        let range_gates = compiled
+            .opcodes
+            .iter()
+            .zip(&origins)
+            .filter(|(opcode, origin)| **origin == 0 && !matches!(opcode, Opcode::Directive(_)))
+            .count();
+        assert_eq!(profile.total_gates, range_gates + 2 + 1);
+        assert_eq!(profile.unattributed_gates, 1);
+        assert_eq!(profile.call_stacks[0].num_gates, range_gates);
+        assert_eq!(profile.call_stacks[0].num_opcodes, range_opcodes);
+        assert_eq!(profile.call_stacks[1].call_stack, main(3));
+        assert_eq!(profile.call_stacks[1].num_gates, 2);
+
+        assert_eq!(
+            profile.to_folded(),
+            format!("main.nr:2:5;range.nr:7:9 {range_gates}\nmain.nr:3:5 2\nunknown 1\n")
+        );
+    }
+}
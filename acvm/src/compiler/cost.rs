@@ -0,0 +1,480 @@
+use alloc::collections::BTreeMap;
+use alloc::{borrow::ToOwned, string::String, vec, vec::Vec};
+use core::time::Duration;
+
+use crate::collections::HashMap;
+
+use acir::{
+    circuit::{Circuit, Opcode},
+    native_types::Expression,
+    AcirField, BlackBoxFunc,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    choose_lowerings, csat_reduce,
+    fallback::opcode_fallback,
+    lookup_table_bits,
+    optimiser::{CSatOptimiser, WidthPacker},
+    CompileError,
+};
+use crate::{BackendError, Language, ProofSystemCompiler};
+
+/// The predicted number of gates a circuit will have after it has been
+/// compiled for a particular NP language.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostEstimate {
+    /// Total number of gates
+    pub total: usize,
+    /// Number of gates that each opcode in the circuit is lowered into.
+    /// This is indexed in the same order as the circuit's opcodes
+    pub per_opcode: Vec<usize>,
+    /// Number of gates for each kind of opcode, keyed by opcode name
+    pub by_name: BTreeMap<String, usize>,
+}
+
+/// The relative cost of the gates of a backend.
+///
+/// Where the compiler has a choice between lowerings, or between merging gates and
+/// keeping them apart, it picks the one with the lowest total weight. The default
+/// weighs every gate as one, so that the number of gates is minimised.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GateWeights {
+    /// The cost of an arithmetic gate, indexed by the number of witnesses it uses.
+    /// Gates which use more witnesses than this covers cost as much as the last entry,
+    /// and every gate costs one if it is empty
+    pub arithmetic: Vec<u64>,
+    /// The cost of a call to each of the black box functions which the backend supports
+    pub black_box: HashMap<BlackBoxFunc, u64>,
+    /// The cost of a call to a supported black box function which is not in `black_box`
+    pub default_black_box: u64,
+}
+
+impl Default for GateWeights {
+    fn default() -> Self {
+        GateWeights {
+            arithmetic: vec![1],
+            black_box: HashMap::new(),
+            default_black_box: 1,
+        }
+    }
+}
+
+impl GateWeights {
+    /// Sets the cost of arithmetic gates, indexed by the number of witnesses they use
+    pub fn with_arithmetic(mut self, by_num_witnesses: Vec<u64>) -> Self {
+        self.arithmetic = by_num_witnesses;
+        self
+    }
+
+    /// Sets the cost of a call to `func`
+    pub fn with_black_box(mut self, func: BlackBoxFunc, cost: u64) -> Self {
+        self.black_box.insert(func, cost);
+        self
+    }
+
+    /// Returns the cost of an arithmetic gate
+    pub fn arithmetic_cost<F: AcirField>(&self, gate: &Expression<F>) -> u64 {
+        let num_witnesses = gate.witnesses().len();
+        self.arithmetic
+            .get(num_witnesses)
+            .or(self.arithmetic.last())
+            .copied()
+            .unwrap_or(1)
+    }
+
+    /// Returns the cost of a call to a supported black box function
+    pub fn black_box_cost(&self, func: &BlackBoxFunc) -> u64 {
+        self.black_box
+            .get(func)
+            .copied()
+            .unwrap_or(self.default_black_box)
+    }
+}
+
+/// Coefficients of the time a backend takes to prove a circuit, as a function of the
+/// number of gates of the compiled circuit, on the machine which proves
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvingTimeModel {
+    /// The time spent on every proof, regardless of the circuit
+    pub fixed: Duration,
+    /// The time spent on each gate
+    pub per_gate: Duration,
+    /// The time spent on each gate for each doubling of the number of gates, for provers
+    /// whose FFTs and multi-scalar multiplications take `n log n` time
+    pub per_gate_log: Duration,
+    /// The time spent on each call to a supported black box function, on top of its gate
+    pub black_box: HashMap<BlackBoxFunc, Duration>,
+}
+
+impl ProvingTimeModel {
+    /// Returns the time to prove a circuit of `num_gates` gates, excluding the additional
+    /// time spent on black box function calls
+    pub fn time_for_gates(&self, num_gates: usize) -> Duration {
+        let num_gates = num_gates as f64;
+        self.fixed
+            + self.per_gate.mul_f64(num_gates)
+            + self
+                .per_gate_log
+                .mul_f64(num_gates * num_gates.max(1.0).log2())
+    }
+}
+
+/// Predicts how long `backend` takes to prove `circuit`, from the estimated number of gates
+/// of the circuit once it is compiled for the backend and the backend's proving time model.
+///
+/// Each AND, XOR and RANGE call is lowered as `compile_for_backend` lowers it, and the gates
+/// are then counted with the backend's gate weights as `estimate_cost_with_weights` counts
+/// them. The rest of the compilation pipeline is not run, so this is cheap enough to quote
+/// durations and set timeouts before proving. Returns an error if the backend does not
+/// report a proving time model, or if the circuit cannot be compiled for it.
+pub fn estimate_proving_time<F: AcirField, B: ProofSystemCompiler + ?Sized>(
+    circuit: &Circuit<F>,
+    backend: &B,
+) -> Result<Duration, BackendError> {
+    let model = backend
+        .proving_time_model()
+        .ok_or(BackendError::UnsupportedFeature("proving time estimates"))?;
+    let capabilities = backend.capabilities();
+    let is_blackbox_supported =
+        |func: &BlackBoxFunc| capabilities.supported_blackbox_functions.contains(func);
+
+    // AND, XOR and RANGE calls are lowered as `compile_for_backend` lowers them
+    let lowered = choose_lowerings(
+        circuit.clone(),
+        &capabilities.np_language,
+        is_blackbox_supported,
+        lookup_table_bits(&capabilities)?,
+        &capabilities.gate_weights,
+    )?;
+    let estimate = estimate_cost_with_weights(
+        &lowered,
+        &capabilities.np_language,
+        is_blackbox_supported,
+        &capabilities.gate_weights,
+    )?;
+
+    let black_box_time: Duration = lowered
+        .opcodes
+        .iter()
+        .filter_map(|opcode| match opcode {
+            Opcode::BlackBoxFuncCall(func_call) if is_blackbox_supported(&func_call.name) => {
+                model.black_box.get(&func_call.name).copied()
+            }
+            _ => None,
+        })
+        .sum();
+    Ok(model.time_for_gates(estimate.total) + black_box_time)
+}
+
+/// Predicts the number of gates that `compile` would produce for this circuit,
+/// without running the whole compilation pipeline or invoking a backend.
+///
+/// Black box functions which the backend supports are counted as a single gate,
+/// since their actual cost is only known by the backend.
+/// Directives do not apply any constraints and so they are free.
+pub fn estimate_cost<F: AcirField>(
+    circuit: &Circuit<F>,
+    np_language: &Language,
+    is_blackbox_supported: impl Fn(&BlackBoxFunc) -> bool,
+) -> Result<CostEstimate, CompileError> {
+    estimate_cost_with_weights(
+        circuit,
+        np_language,
+        is_blackbox_supported,
+        &GateWeights::default(),
+    )
+}
+
+/// Predicts the number of gates that `compile_with_weights` would produce for this circuit,
+/// as `estimate_cost` does.
+///
+/// The weights decide which gates are merged, as they do when compiling, but the estimate
+/// still counts gates rather than adding up their weights.
+pub fn estimate_cost_with_weights<F: AcirField>(
+    circuit: &Circuit<F>,
+    np_language: &Language,
+    is_blackbox_supported: impl Fn(&BlackBoxFunc) -> bool,
+    weights: &GateWeights,
+) -> Result<CostEstimate, CompileError> {
+    let mut estimate = CostEstimate::default();
+    let next_witness_index = circuit.current_witness_index + 1;
+
+    for opcode in &circuit.opcodes {
+        let cost = opcode_cost(
+            opcode,
+            np_language,
+            &is_blackbox_supported,
+            weights,
+            Measure::Count,
+            next_witness_index,
+        )? as usize;
+
+        estimate.total += cost;
+        estimate.per_opcode.push(cost);
+        *estimate
+            .by_name
+            .entry(opcode.name().to_owned())
+            .or_default() += cost;
+    }
+
+    Ok(estimate)
+}
+
+// How the gates which an opcode is compiled into are added up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Measure {
+    // The total weight of the gates
+    Weight,
+    // The number of gates
+    Count,
+}
+
+// Returns the total cost of the gates which `opcode` is compiled into, where gates are
+// merged as `weights` allows and each gate costs as `measure` says
+pub(super) fn opcode_cost<F: AcirField>(
+    opcode: &Opcode<F>,
+    np_language: &Language,
+    is_blackbox_supported: &dyn Fn(&BlackBoxFunc) -> bool,
+    weights: &GateWeights,
+    measure: Measure,
+    next_witness_index: u32,
+) -> Result<u64, CompileError> {
+    let cost = match opcode {
+        Opcode::Arithmetic(expr) => {
+            arithmetic_cost(expr, np_language, weights, measure, next_witness_index)
+        }
+        Opcode::Directive(_) => 0,
+        // The callee is costed separately, unless the program is inlined first
+        Opcode::Call { .. } => 0,
+        Opcode::BlackBoxFuncCall(func_call) => {
+            if is_blackbox_supported(&func_call.name) {
+                return Ok(match measure {
+                    Measure::Weight => weights.black_box_cost(&func_call.name),
+                    Measure::Count => 1,
+                });
+            }
+
+            // Intermediate variables are allocated after the witnesses created by the fallback
+            let (next_witness_index, fallback_opcodes) =
+                opcode_fallback::<F>(func_call, next_witness_index)?;
+            let mut cost = 0;
+            for fallback_opcode in &fallback_opcodes {
+                cost += opcode_cost(
+                    fallback_opcode,
+                    np_language,
+                    is_blackbox_supported,
+                    weights,
+                    measure,
+                    next_witness_index,
+                )?;
+            }
+            cost
+        }
+    };
+    Ok(cost)
+}
+
+fn arithmetic_cost<F: AcirField>(
+    expr: &Expression<F>,
+    np_language: &Language,
+    weights: &GateWeights,
+    measure: Measure,
+    next_witness_index: u32,
+) -> u64 {
+    let gate_cost = |gate: &Expression<F>| match measure {
+        Measure::Weight => weights.arithmetic_cost(gate),
+        Measure::Count => 1,
+    };
+    match np_language.width() {
+        // The R1CS optimiser does not split expressions
+        None => gate_cost(expr),
+        Some(width) => {
+            let (gates, _) = csat_reduce(
+                &CSatOptimiser::new(width),
+                &WidthPacker::new(width).with_weights(weights.clone()),
+                expr.clone(),
+                next_witness_index,
+            );
+            gates.iter().map(gate_cost).sum()
+        }
+    }
+}
+
+#[test]
+fn estimate_matches_compilation() {
+    use acir::{
+        circuit::{
+            directives::Directive,
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            PublicInputs,
+        },
+        native_types::Witness,
+        BlackBoxFunc, FieldElement,
+    };
+
+    let mut expr = Expression::default();
+    expr.term_multiplication(FieldElement::one(), Witness(1), Witness(2));
+    expr.term_multiplication(FieldElement::one(), Witness(3), Witness(4));
+    expr.term_addition(FieldElement::one(), Witness(5));
+    expr.term_addition(-FieldElement::one(), Witness(6));
+
+    let circuit = Circuit {
+        current_witness_index: 7,
+        opcodes: vec![
+            Opcode::Arithmetic(expr),
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                name: BlackBoxFunc::RANGE,
+                inputs: vec![FunctionInput {
+                    witness: Witness(6),
+                    num_bits: 8,
+                }],
+                outputs: vec![],
+            }),
+            Opcode::Directive(Directive::Invert {
+                x: Witness(6),
+                result: Witness(7),
+            }),
+        ],
+        public_inputs: PublicInputs::default(),
+        return_values: PublicInputs::default(),
+    };
+
+    let language = Language::PLONKCSat { width: 3 };
+    let estimate = estimate_cost(&circuit, &language, |_| false).unwrap();
+    assert_eq!(estimate.per_opcode.len(), circuit.opcodes.len());
+    assert_eq!(estimate.by_name.get("invert"), Some(&0));
+
+    let compiled = super::compile(circuit.clone(), language.clone(), |_| false).unwrap();
+    let num_gates = compiled
+        .opcodes
+        .iter()
+        .filter(|opcode| !matches!(opcode, Opcode::Directive(_)))
+        .count();
+    assert_eq!(estimate.total, num_gates);
+
+    // Gates are merged as the weights allow, and still counted one each
+    let language = Language::PLONKCSat { width: 5 };
+    let circuit = two_products_circuit();
+    let weights = GateWeights::default().with_arithmetic(vec![1, 1, 1, 1, 1, 3]);
+    let estimate = estimate_cost_with_weights(&circuit, &language, |_| false, &weights).unwrap();
+    let compiled =
+        super::compile_with_weights(circuit.clone(), language.clone(), |_| false, &weights)
+            .unwrap();
+    assert_eq!(estimate.total, compiled.opcodes.len());
+    assert_eq!(estimate.total, 3);
+    assert_eq!(
+        estimate_cost(&circuit, &language, |_| false).unwrap().total,
+        2
+    );
+}
+
+// xy + zw + a - out, which the CSAT optimiser reduces for width 5 into three gates that
+// can be merged into two
+#[cfg(test)]
+fn two_products_circuit() -> Circuit {
+    use acir::{native_types::Witness, FieldElement};
+
+    let mut expr = Expression::default();
+    expr.term_multiplication(FieldElement::one(), Witness(1), Witness(2));
+    expr.term_multiplication(FieldElement::one(), Witness(3), Witness(4));
+    expr.term_addition(FieldElement::one(), Witness(5));
+    expr.term_addition(-FieldElement::one(), Witness(6));
+    Circuit {
+        current_witness_index: 6,
+        opcodes: vec![Opcode::Arithmetic(expr)],
+        ..Circuit::default()
+    }
+}
+
+#[test]
+fn proving_time_follows_the_model() {
+    use crate::testing::MockBackend;
+    use acir::{
+        circuit::{
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            PublicInputs,
+        },
+        native_types::Witness,
+    };
+
+    let circuit: Circuit = Circuit {
+        current_witness_index: 2,
+        opcodes: vec![
+            Opcode::Arithmetic(&Expression::from(Witness(1)) - &Expression::from(Witness(2))),
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                name: BlackBoxFunc::RANGE,
+                inputs: vec![FunctionInput {
+                    witness: Witness(1),
+                    num_bits: 8,
+                }],
+                outputs: vec![],
+            }),
+        ],
+        public_inputs: PublicInputs::default(),
+        return_values: PublicInputs::default(),
+    };
+    let model = ProvingTimeModel {
+        fixed: Duration::from_secs(1),
+        per_gate: Duration::from_millis(1),
+        per_gate_log: Duration::ZERO,
+        black_box: HashMap::from([(BlackBoxFunc::RANGE, Duration::from_millis(10))]),
+    };
+
+    assert_eq!(
+        estimate_proving_time(&circuit, &MockBackend::new()),
+        Err(BackendError::UnsupportedFeature("proving time estimates"))
+    );
+
+    let backend = MockBackend::new().with_proving_time_model(model.clone());
+    assert_eq!(
+        estimate_proving_time(&circuit, &backend),
+        Ok(Duration::from_millis(1012))
+    );
+
+    // Without RANGE support, the range constraint costs a gate per bit instead
+    let backend = backend.with_supported_opcodes([]);
+    let language = Language::PLONKCSat { width: 3 };
+    let num_gates = estimate_cost(&circuit, &language, |_| false).unwrap().total;
+    assert!(num_gates > 8);
+    assert_eq!(
+        estimate_proving_time(&circuit, &backend),
+        Ok(model.time_for_gates(num_gates))
+    );
+
+    // The backend's weights decide which gates are merged
+    let backend = MockBackend::new()
+        .with_proving_time_model(model.clone())
+        .with_language(Language::PLONKCSat { width: 5 });
+    assert_eq!(
+        estimate_proving_time(&two_products_circuit(), &backend),
+        Ok(model.time_for_gates(2))
+    );
+    let backend =
+        backend.with_gate_weights(GateWeights::default().with_arithmetic(vec![1, 1, 1, 1, 1, 3]));
+    assert_eq!(
+        estimate_proving_time(&two_products_circuit(), &backend),
+        Ok(model.time_for_gates(3))
+    );
+}
+
+#[test]
+fn gates_without_weights_cost_one() {
+    use crate::{compiler::compile_for_backend, testing::MockBackend};
+    use acir::native_types::Witness;
+
+    let weights = GateWeights {
+        arithmetic: Vec::new(),
+        ..GateWeights::default()
+    };
+    let gate = &Expression::from(Witness(1)) - &Expression::from(Witness(2));
+    assert_eq!(weights.arithmetic_cost(&gate), 1);
+
+    let backend = MockBackend::new().with_gate_weights(weights);
+    let circuit = Circuit {
+        current_witness_index: 2,
+        opcodes: vec![Opcode::Arithmetic(gate)],
+        ..Circuit::default()
+    };
+    assert!(compile_for_backend(circuit, &backend).is_ok());
+}
@@ -0,0 +1,370 @@
+use acir::{
+    circuit::{
+        directives::Directive,
+        opcodes::{BlackBoxFuncCall, FunctionInput},
+        Circuit, Opcode,
+    },
+    native_types::{Expression, Witness},
+    AcirField, BlackBoxFunc,
+};
+use alloc::{vec, vec::Vec};
+
+/// Selects which kinds of directives are lowered by `lower_directives`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DirectiveLowering {
+    pub invert: bool,
+    pub quotient: bool,
+    pub truncate: bool,
+    /// The number of bits which the quotient and remainder of a lowered `Quotient` fit
+    /// into. `None` and sizes above `max_quotient_bit_size` use that maximum.
+    pub quotient_bit_size: Option<u32>,
+}
+
+impl DirectiveLowering {
+    /// Lower every kind of directive that the pass knows how to constrain
+    pub fn all() -> Self {
+        DirectiveLowering {
+            invert: true,
+            quotient: true,
+            truncate: true,
+            quotient_bit_size: None,
+        }
+    }
+}
+
+/// The largest bit size for which the quotient and remainder of a lowered `Quotient` are
+/// unique, as `q * b + r` cannot wrap around the field modulus
+pub fn max_quotient_bit_size<F: AcirField>() -> u32 {
+    (F::max_num_bits() - 3) / 2
+}
+
+/// ACIR pass which constrains the outputs of directives.
+///
+/// Directives are unconstrained hints, so some backends and auditors do not accept them.
+/// For every selected directive, this pass adds opcodes which constrain the directive's outputs
+/// to be the values that the directive computes. The directive itself is kept, so the
+/// partial witness generator can still use it to compute those values.
+///
+/// - `Invert { x, result }` adds `t = x * result`, `x * t - x = 0` and `result * t - result = 0`,
+///   so that `result` is the inverse of `x`, or zero if `x` is zero.
+/// - `Quotient { a, b, q, r }` adds `a - q * b - r = 0` along with range constraints on `q`, `r`
+///   and `b - r - 1`, so that `r < b`, to `quotient_bit_size` bits. Quotients with a predicate
+///   are left unlowered.
+/// - `Truncate { a, b, c, bit_size }` adds `a - c * 2^bit_size - b = 0` along with range
+///   constraints on `b` and `c`, so that the decomposition does not wrap around the field modulus.
+///
+/// Range constraints are emitted as black box function calls, so this pass should be run
+/// before `compile` so that they can be replaced by the fallback pass if needed.
+pub fn lower_directives<F: AcirField>(acir: Circuit<F>, options: DirectiveLowering) -> Circuit<F> {
+    let mut next_witness_index = acir.current_witness_index + 1;
+    let mut opcodes = Vec::with_capacity(acir.opcodes.len());
+
+    for opcode in acir.opcodes {
+        let constraints = match &opcode {
+            Opcode::Directive(Directive::Invert { x, result }) if options.invert => {
+                lower_invert(*x, *result, &mut next_witness_index)
+            }
+            Opcode::Directive(Directive::Quotient {
+                a,
+                b,
+                q,
+                r,
+                predicate: None,
+            }) if options.quotient => {
+                let bit_size = options
+                    .quotient_bit_size
+                    .map_or(max_quotient_bit_size::<F>(), |bits| {
+                        bits.min(max_quotient_bit_size::<F>())
+                    });
+                lower_quotient(a, b, *q, *r, bit_size, &mut next_witness_index)
+            }
+            Opcode::Directive(Directive::Truncate { a, b, c, bit_size }) if options.truncate => {
+                lower_truncate(a, *b, *c, *bit_size)
+            }
+            _ => Vec::new(),
+        };
+
+        opcodes.push(opcode);
+        opcodes.extend(constraints);
+    }
+
+    Circuit {
+        current_witness_index: next_witness_index - 1,
+        opcodes,
+        public_inputs: acir.public_inputs,
+        return_values: acir.return_values,
+    }
+}
+
+// t = x * result, x * t - x = 0 and result * t - result = 0
+//
+// If `x` is non-zero the second constraint makes `t` one, so `result` is its inverse.
+// Otherwise `t` is zero and the third constraint makes `result` zero, as the directive does.
+fn lower_invert<F: AcirField>(
+    x: Witness,
+    result: Witness,
+    next_witness_index: &mut u32,
+) -> Vec<Opcode<F>> {
+    let t = Witness(*next_witness_index);
+    *next_witness_index += 1;
+
+    let mut product = Expression::default();
+    product.term_multiplication(F::one(), x, result);
+    product.term_addition(-F::one(), t);
+
+    let is_one_or_zero = |w: Witness| {
+        let mut expr = Expression::default();
+        expr.term_multiplication(F::one(), w, t);
+        expr.term_addition(-F::one(), w);
+        Opcode::Arithmetic(expr)
+    };
+
+    vec![
+        Opcode::Arithmetic(product),
+        is_one_or_zero(x),
+        is_one_or_zero(result),
+    ]
+}
+
+// a - q * b - r = 0, with q, r and b - r - 1 less than 2^bit_size
+//
+// The bounds make `r < b` and keep `q * b + r` below the field modulus, so `q` and `r`
+// are the only quotient and remainder of `a` by `b`.
+fn lower_quotient<F: AcirField>(
+    a: &Expression<F>,
+    b: &Expression<F>,
+    q: Witness,
+    r: Witness,
+    bit_size: u32,
+    next_witness_index: &mut u32,
+) -> Vec<Opcode<F>> {
+    let mut opcodes = Vec::new();
+
+    // `q * b` is only of degree 2 if `b` is linear, otherwise we
+    // introduce an intermediate variable `t = b`
+    let b = if b.is_linear() {
+        b.clone()
+    } else {
+        let t = Witness(*next_witness_index);
+        *next_witness_index += 1;
+        opcodes.push(Opcode::Arithmetic(b - &t));
+        Expression::from(&t)
+    };
+
+    let mut expr = a.clone();
+    for (scale, witness) in &b.linear_combinations {
+        expr.term_multiplication(-*scale, q, *witness);
+    }
+    expr.term_addition(-b.q_c, q);
+    expr.term_addition(-F::one(), r);
+    opcodes.push(Opcode::Arithmetic(expr));
+
+    // d = b - r - 1
+    let d = Witness(*next_witness_index);
+    *next_witness_index += 1;
+    let mut gap = b;
+    gap.term_addition(-F::one(), r);
+    gap.term_addition(-F::one(), d);
+    gap.q_c -= F::one();
+    opcodes.push(Opcode::Arithmetic(gap));
+
+    opcodes.extend([
+        range_constraint(q, bit_size),
+        range_constraint(r, bit_size),
+        range_constraint(d, bit_size),
+    ]);
+    opcodes
+}
+
+// a - c * 2^bit_size - b = 0, with b < 2^bit_size and c < 2^(max_bits - 1 - bit_size)
+//
+// Range constraints must be below the field size, and `a` is assumed to be less than
+// 2^(max_bits - 1), so when the bit size is not below that `b` is `a` and `c` is zero.
+// This is common in small fields, such as truncating to 64 bits in a 64-bit field.
+fn lower_truncate<F: AcirField>(
+    a: &Expression<F>,
+    b: Witness,
+    c: Witness,
+    bit_size: u32,
+) -> Vec<Opcode<F>> {
+    let bit_size = bit_size.min(F::max_num_bits() - 1);
+    let two_pow = F::from(2_i128).pow(&F::from(bit_size as i128));
+
+    let mut expr = a.clone();
+    expr.term_addition(-two_pow, c);
+    expr.term_addition(-F::one(), b);
+
+    let c_bits = F::max_num_bits() - 1 - bit_size;
+
+    vec![
+        Opcode::Arithmetic(expr),
+        range_constraint(b, bit_size),
+        range_constraint(c, c_bits),
+    ]
+}
+
+fn range_constraint<F: AcirField>(witness: Witness, num_bits: u32) -> Opcode<F> {
+    Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+        name: BlackBoxFunc::RANGE,
+        inputs: vec![FunctionInput { witness, num_bits }],
+        outputs: vec![],
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::{OpcodeResolutionError, PartialWitnessGenerator, WitnessMap};
+    use acir::circuit::PublicInputs;
+    use acir::FieldElement;
+
+    struct RangeOnly;
+
+    impl PartialWitnessGenerator for RangeOnly {
+        fn solve_blackbox_function_call(
+            &self,
+            initial_witness: &mut WitnessMap,
+            func_call: &BlackBoxFuncCall,
+        ) -> Result<(), OpcodeResolutionError> {
+            crate::pwg::range::solve_range_opcode(initial_witness, func_call)
+        }
+    }
+
+    fn circuit() -> Circuit {
+        let (x, x_inv, q, r, b, c) = (
+            Witness(1),
+            Witness(2),
+            Witness(3),
+            Witness(4),
+            Witness(5),
+            Witness(6),
+        );
+        Circuit {
+            current_witness_index: 6,
+            opcodes: vec![
+                Opcode::Directive(Directive::Invert { x, result: x_inv }),
+                Opcode::Directive(Directive::Quotient {
+                    a: Expression::from(&x),
+                    b: Expression::from_field(FieldElement::from(7_i128)),
+                    q,
+                    r,
+                    predicate: None,
+                }),
+                Opcode::Directive(Directive::Truncate {
+                    a: Expression::from(&x),
+                    b,
+                    c,
+                    bit_size: 4,
+                }),
+            ],
+            public_inputs: PublicInputs::default(),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    #[test]
+    fn only_selected_directives_are_lowered() {
+        let options = DirectiveLowering {
+            invert: true,
+            ..Default::default()
+        };
+        let lowered = lower_directives(circuit(), options);
+        assert_eq!(lowered.opcodes.len(), 6);
+        assert!(lowered.opcodes[1].is_arithmetic());
+    }
+
+    #[test]
+    fn lowered_circuit_is_satisfied() {
+        let lowered = lower_directives(circuit(), DirectiveLowering::all());
+        // 3 for invert, 5 for quotient and 3 for truncate
+        assert_eq!(lowered.opcodes.len(), 3 + 11);
+
+        let mut witness = WitnessMap::from([(Witness(1), FieldElement::from(100_i128))]);
+        RangeOnly
+            .solve(&mut witness, lowered.opcodes.clone())
+            .unwrap();
+
+        assert_eq!(witness[&Witness(3)], FieldElement::from(14_i128));
+        assert_eq!(witness[&Witness(4)], FieldElement::from(2_i128));
+        assert_eq!(witness[&Witness(5)], FieldElement::from(4_i128));
+        assert_eq!(witness[&Witness(6)], FieldElement::from(6_i128));
+
+        // Zero inverts to zero, as is-zero checks rely on
+        let mut witness = WitnessMap::from([(Witness(1), FieldElement::zero())]);
+        RangeOnly.solve(&mut witness, lowered.opcodes).unwrap();
+        assert_eq!(witness[&Witness(2)], FieldElement::zero());
+    }
+
+    #[test]
+    fn lowered_outputs_are_unique() {
+        let lowered = lower_directives(circuit(), DirectiveLowering::all());
+        let constraints: Vec<Opcode> = lowered
+            .opcodes
+            .into_iter()
+            .filter(|opcode| !matches!(opcode, Opcode::Directive(_)))
+            .collect();
+        // _1 = 100 with the inverse, quotient and remainder replaced
+        let check = |x_inv: FieldElement, q: i128, r: i128| {
+            let value = |value: i128| FieldElement::from(value);
+            let mut witness = WitnessMap::from([
+                (Witness(1), value(100)),
+                (Witness(2), x_inv),
+                (Witness(3), value(q)),
+                (Witness(4), value(r)),
+                (Witness(5), value(4)),
+                (Witness(6), value(6)),
+            ]);
+            RangeOnly.solve(&mut witness, constraints.clone())
+        };
+        let inverse = FieldElement::from(100_i128).inverse();
+        assert_eq!(check(inverse, 14, 2), Ok(()));
+        assert!(check(FieldElement::zero(), 14, 2).is_err());
+        // 100 = 13 * 7 + 9, but the remainder is not less than 7
+        assert!(check(inverse, 13, 9).is_err());
+
+        // A zero with a non-zero inverse
+        let constraints = lower_directives(
+            Circuit {
+                current_witness_index: 2,
+                opcodes: vec![Opcode::Directive(Directive::Invert {
+                    x: Witness(1),
+                    result: Witness(2),
+                })],
+                ..Circuit::default()
+            },
+            DirectiveLowering::all(),
+        )
+        .opcodes
+        .split_off(1);
+        let mut witness = WitnessMap::from([
+            (Witness(1), FieldElement::zero()),
+            (Witness(2), FieldElement::one()),
+        ]);
+        assert!(RangeOnly.solve(&mut witness, constraints).is_err());
+    }
+
+    #[test]
+    fn truncating_to_the_field_size_is_lowered() {
+        let max_num_bits = FieldElement::max_num_bits();
+        for bit_size in [max_num_bits - 1, max_num_bits, max_num_bits + 1] {
+            let circuit = Circuit {
+                current_witness_index: 3,
+                opcodes: vec![Opcode::Directive(Directive::Truncate {
+                    a: Expression::from(&Witness(1)),
+                    b: Witness(2),
+                    c: Witness(3),
+                    bit_size,
+                })],
+                ..Circuit::default()
+            };
+            let lowered = lower_directives(circuit, DirectiveLowering::all());
+
+            let a = FieldElement::from(100_i128);
+            let mut witness = WitnessMap::from([(Witness(1), a)]);
+            RangeOnly.solve(&mut witness, lowered.opcodes).unwrap();
+            assert_eq!(witness[&Witness(2)], a);
+            assert_eq!(witness[&Witness(3)], FieldElement::zero());
+        }
+    }
+}
@@ -1,59 +1,110 @@
-use super::CompileError;
+use alloc::{vec, vec::Vec};
+use core::time::Duration;
+
+use super::{parallel, CompileError};
+use crate::{
+    metrics,
+    profile::{self, ProfileReport},
+    trace,
+};
 use acir::{
     circuit::{opcodes::BlackBoxFuncCall, Circuit, Opcode},
     native_types::Expression,
-    BlackBoxFunc,
+    AcirField, BlackBoxFunc,
 };
 
 // A predicate that returns true if the black box function is supported
 pub type IsBlackBoxSupported = fn(&BlackBoxFunc) -> bool;
 
 //ACIR pass which replace unsupported opcodes using arithmetic fallback
-pub fn fallback(acir: Circuit, is_supported: IsBlackBoxSupported) -> Result<Circuit, CompileError> {
-    let mut acir_supported_opcodes = Vec::with_capacity(acir.opcodes.len());
-
-    let mut witness_idx = acir.current_witness_index + 1;
+pub fn fallback<F: AcirField>(
+    acir: Circuit<F>,
+    is_supported: impl Fn(&BlackBoxFunc) -> bool,
+) -> Result<Circuit<F>, CompileError> {
+    fallback_impl(acir, is_supported, None).map(|(circuit, _)| circuit)
+}
 
-    for opcode in acir.opcodes {
-        let bb_func_call = match &opcode {
-            Opcode::Arithmetic(_) | Opcode::Directive(_) => {
-                // If it is not a black box function, then it is a directive or
-                // an arithmetic expression which are always supported
-                acir_supported_opcodes.push(opcode);
-                continue;
-            }
-            Opcode::BlackBoxFuncCall(bb_func_call) => {
-                // We know it is an black box function. Now check if it is
-                // supported by the backend. If it is supported, then we can simply
-                // collect the opcode
-                if is_supported(&bb_func_call.name) {
-                    acir_supported_opcodes.push(opcode);
-                    continue;
-                }
-                bb_func_call
-            }
-        };
+// Runs the fallback pass, recording the time taken to lower each unsupported black box
+// function if a report is given. Also returns the index of the opcode of `acir` which
+// each lowered opcode comes from.
+pub(super) fn fallback_impl<F: AcirField>(
+    acir: Circuit<F>,
+    is_supported: impl Fn(&BlackBoxFunc) -> bool,
+    mut profile: Option<&mut ProfileReport>,
+) -> Result<(Circuit<F>, Vec<usize>), CompileError> {
+    let _span = trace::span!(DEBUG, "fallback", num_opcodes = acir.opcodes.len());
+    let _pass = metrics::pass("fallback");
+    // The predicate is checked on this thread, as it does not need to be `Sync`
+    let opcodes: Vec<(Opcode<F>, bool)> = acir
+        .opcodes
+        .into_iter()
+        .map(|opcode| {
+            // Arithmetic expressions, directives and calls are always supported
+            let needs_fallback = matches!(
+                &opcode,
+                Opcode::BlackBoxFuncCall(bb_func_call) if !is_supported(&bb_func_call.name)
+            );
+            (opcode, needs_fallback)
+        })
+        .collect();
 
-        // If we get here then we know that this black box function is not supported
-        // so we need to replace it with a version of the opcode which only uses arithmetic
-        // expressions
-        let (updated_witness_index, opcodes_fallback) = opcode_fallback(bb_func_call, witness_idx)?;
-        witness_idx = updated_witness_index;
+    // Each unsupported black box function is replaced with a version which only uses
+    // arithmetic expressions, as if it were the first to create witnesses. The new
+    // witnesses are then renumbered to follow those of the opcodes before it.
+    let first_intermediate = acir.current_witness_index + 1;
+    let profiling = profile.is_some();
+    let lowered = parallel::map(opcodes, |(opcode, needs_fallback)| match &opcode {
+        Opcode::BlackBoxFuncCall(bb_func_call) if needs_fallback => {
+            let start = profile::start_timer(profiling);
+            let (updated_witness_index, opcodes_fallback) =
+                opcode_fallback(bb_func_call, first_intermediate)?;
+            let timing: Option<(&'static str, Duration)> =
+                start.map(|start| (bb_func_call.name.name(), start.elapsed()));
+            Ok((
+                opcodes_fallback,
+                updated_witness_index - first_intermediate,
+                timing,
+            ))
+        }
+        _ => Ok((vec![opcode], 0, None)),
+    });
 
-        acir_supported_opcodes.extend(opcodes_fallback);
+    let mut acir_supported_opcodes = Vec::with_capacity(lowered.len());
+    let mut origins = Vec::with_capacity(lowered.len());
+    let mut witness_idx = first_intermediate;
+    for (origin, result) in lowered.into_iter().enumerate() {
+        let (opcodes, num_witnesses, timing) = result?;
+        origins.extend(core::iter::repeat_n(origin, opcodes.len()));
+        if let (Some(profile), Some((category, elapsed))) = (profile.as_deref_mut(), timing) {
+            profile.record(category, elapsed);
+        }
+        let offset = witness_idx - first_intermediate;
+        acir_supported_opcodes.extend(
+            opcodes
+                .into_iter()
+                .map(|opcode| parallel::shift_intermediates(opcode, first_intermediate, offset)),
+        );
+        witness_idx += num_witnesses;
     }
 
-    Ok(Circuit {
+    let circuit = Circuit {
         current_witness_index: witness_idx,
         opcodes: acir_supported_opcodes,
         public_inputs: acir.public_inputs,
-    })
+        return_values: acir.return_values,
+    };
+    trace::event!(
+        DEBUG,
+        num_opcodes = circuit.opcodes.len(),
+        "replaced unsupported black box functions"
+    );
+    Ok((circuit, origins))
 }
 
-fn opcode_fallback(
+pub(crate) fn opcode_fallback<F: AcirField>(
     gc: &BlackBoxFuncCall,
     current_witness_idx: u32,
-) -> Result<(u32, Vec<Opcode>), CompileError> {
+) -> Result<(u32, Vec<Opcode<F>>), CompileError> {
     let (updated_witness_index, opcodes_fallback) = match gc.name {
         BlackBoxFunc::AND => {
             let (lhs, rhs, result, num_bits) = crate::pwg::logic::extract_input_output(gc);
@@ -0,0 +1,320 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use acir::{
+    circuit::{directives::Directive, opcodes::BlackBoxFuncCall, Circuit, Opcode, Program},
+    native_types::{Expression, Witness},
+    AcirField,
+};
+
+use super::CompileError;
+use crate::{metrics, trace};
+
+/// ACIR pass which flattens a program into a single circuit by inlining every call.
+///
+/// The witnesses of each callee are renamed into the caller's witness space. The callee's
+/// parameters and return values are replaced by the witnesses passed to the call, and its
+/// other witnesses are allocated after the caller's `current_witness_index`.
+///
+/// The returned circuit has the public inputs of the program's entry point.
+pub fn inline_calls<F: AcirField>(program: &Program<F>) -> Result<Circuit<F>, CompileError> {
+    let _span = trace::span!(
+        DEBUG,
+        "inline_calls",
+        num_functions = program.functions.len()
+    );
+    let _pass = metrics::pass("inline_calls");
+    let mut inliner = Inliner {
+        functions: &program.functions,
+        call_stack: Vec::new(),
+    };
+    inliner.inline_function(0)
+}
+
+struct Inliner<'a, F> {
+    functions: &'a [Circuit<F>],
+    // Functions which are currently being inlined, used to detect recursion
+    call_stack: Vec<u32>,
+}
+
+impl<F: AcirField> Inliner<'_, F> {
+    fn inline_function(&mut self, id: u32) -> Result<Circuit<F>, CompileError> {
+        if self.call_stack.contains(&id) {
+            return Err(CompileError::RecursiveCall(id));
+        }
+        let function = self
+            .functions
+            .get(id as usize)
+            .ok_or(CompileError::UnknownFunction(id))?;
+
+        self.call_stack.push(id);
+
+        let mut next_witness_index = function.current_witness_index + 1;
+        let mut opcodes = Vec::with_capacity(function.opcodes.len());
+        for opcode in &function.opcodes {
+            match opcode {
+                Opcode::Call {
+                    id,
+                    inputs,
+                    outputs,
+                } => {
+                    let callee = self.inline_function(*id)?;
+                    opcodes.extend(inline_call(
+                        *id,
+                        callee,
+                        inputs,
+                        outputs,
+                        &mut next_witness_index,
+                    )?);
+                }
+                other => opcodes.push(other.clone()),
+            }
+        }
+
+        self.call_stack.pop();
+
+        Ok(Circuit {
+            current_witness_index: next_witness_index - 1,
+            opcodes,
+            public_inputs: function.public_inputs.clone(),
+            return_values: function.return_values.clone(),
+        })
+    }
+}
+
+// Renames the witnesses of `callee`, which has no calls, into the caller's witness space
+fn inline_call<F: AcirField>(
+    id: u32,
+    callee: Circuit<F>,
+    inputs: &[Witness],
+    outputs: &[Witness],
+    next_witness_index: &mut u32,
+) -> Result<Vec<Opcode<F>>, CompileError> {
+    let (parameters, return_values) = (&callee.public_inputs.0, &callee.return_values.0);
+    if parameters.len() != inputs.len() || return_values.len() != outputs.len() {
+        return Err(CompileError::IncorrectNumCallArguments(
+            id,
+            parameters.len() + return_values.len(),
+            inputs.len() + outputs.len(),
+        ));
+    }
+
+    let mut opcodes = Vec::new();
+    let mut renaming: BTreeMap<Witness, Witness> = BTreeMap::new();
+    let signature = parameters.iter().chain(return_values);
+    for (callee_witness, caller_witness) in signature.zip(inputs.iter().chain(outputs)) {
+        // If the callee uses the same witness twice in its signature, for example when it
+        // returns one of its parameters, then the caller's witnesses must be equal
+        match renaming.get(callee_witness) {
+            Some(renamed) => opcodes.push(Opcode::Arithmetic(
+                &Expression::from(renamed) - caller_witness,
+            )),
+            None => {
+                renaming.insert(*callee_witness, *caller_witness);
+            }
+        }
+    }
+
+    let mut rename = |witness: Witness| {
+        *renaming.entry(witness).or_insert_with(|| {
+            let renamed = Witness(*next_witness_index);
+            *next_witness_index += 1;
+            renamed
+        })
+    };
+    opcodes.extend(
+        callee
+            .opcodes
+            .iter()
+            .map(|opcode| rename_witnesses(opcode, &mut rename)),
+    );
+
+    Ok(opcodes)
+}
+
+pub(super) fn rename_witnesses<F: AcirField>(
+    opcode: &Opcode<F>,
+    rename: &mut impl FnMut(Witness) -> Witness,
+) -> Opcode<F> {
+    match opcode {
+        Opcode::Arithmetic(expr) => Opcode::Arithmetic(rename_expression(expr, rename)),
+        Opcode::BlackBoxFuncCall(func_call) => {
+            let mut inputs = func_call.inputs.clone();
+            for input in &mut inputs {
+                input.witness = rename(input.witness);
+            }
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                name: func_call.name,
+                inputs,
+                outputs: func_call.outputs.iter().map(|w| rename(*w)).collect(),
+            })
+        }
+        Opcode::Directive(directive) => Opcode::Directive(rename_directive(directive, rename)),
+        Opcode::Call {
+            id,
+            inputs,
+            outputs,
+        } => Opcode::Call {
+            id: *id,
+            inputs: inputs.iter().map(|w| rename(*w)).collect(),
+            outputs: outputs.iter().map(|w| rename(*w)).collect(),
+        },
+    }
+}
+
+fn rename_expression<F: AcirField>(
+    expr: &Expression<F>,
+    rename: &mut impl FnMut(Witness) -> Witness,
+) -> Expression<F> {
+    Expression {
+        mul_terms: expr
+            .mul_terms
+            .iter()
+            .map(|(scale, w_l, w_r)| (*scale, rename(*w_l), rename(*w_r)))
+            .collect(),
+        linear_combinations: expr
+            .linear_combinations
+            .iter()
+            .map(|(scale, w)| (*scale, rename(*w)))
+            .collect(),
+        q_c: expr.q_c,
+    }
+}
+
+fn rename_directive<F: AcirField>(
+    directive: &Directive<F>,
+    rename: &mut impl FnMut(Witness) -> Witness,
+) -> Directive<F> {
+    match directive {
+        Directive::Invert { x, result } => Directive::Invert {
+            x: rename(*x),
+            result: rename(*result),
+        },
+        Directive::Quotient {
+            a,
+            b,
+            q,
+            r,
+            predicate,
+        } => Directive::Quotient {
+            a: rename_expression(a, rename),
+            b: rename_expression(b, rename),
+            q: rename(*q),
+            r: rename(*r),
+            predicate: predicate
+                .as_ref()
+                .map(|predicate| rename_expression(predicate, rename)),
+        },
+        Directive::Truncate { a, b, c, bit_size } => Directive::Truncate {
+            a: rename_expression(a, rename),
+            b: rename(*b),
+            c: rename(*c),
+            bit_size: *bit_size,
+        },
+        Directive::OddRange { a, b, r, bit_size } => Directive::OddRange {
+            a: rename(*a),
+            b: rename(*b),
+            r: rename(*r),
+            bit_size: *bit_size,
+        },
+        Directive::ToRadix { a, b, radix } => Directive::ToRadix {
+            a: rename_expression(a, rename),
+            b: b.iter().map(|w| rename(*w)).collect(),
+            radix: *radix,
+        },
+        Directive::Oracle {
+            name,
+            inputs,
+            outputs,
+        } => Directive::Oracle {
+            name: name.clone(),
+            inputs: inputs
+                .iter()
+                .map(|input| rename_expression(input, rename))
+                .collect(),
+            outputs: outputs.iter().map(|w| rename(*w)).collect(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{pwg::NativeSolver, PartialWitnessGenerator, WitnessMap};
+    use acir::{circuit::PublicInputs, FieldElement};
+
+    fn call(id: u32, input: u32, output: u32) -> Opcode {
+        Opcode::Call {
+            id,
+            inputs: vec![Witness(input)],
+            outputs: vec![Witness(output)],
+        }
+    }
+
+    // main(x1) calls f twice, where f(x) = x^2 + 1
+    fn program() -> Program {
+        let mut square_plus_one = Expression::default();
+        square_plus_one.term_multiplication(FieldElement::one(), Witness(1), Witness(1));
+        square_plus_one.term_addition(-FieldElement::one(), Witness(3));
+        square_plus_one.q_c = FieldElement::one();
+
+        let f = Circuit {
+            current_witness_index: 3,
+            opcodes: vec![
+                Opcode::Arithmetic(square_plus_one),
+                Opcode::Arithmetic(&Expression::from(&Witness(3)) - &Witness(2)),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs(vec![Witness(2)]),
+        };
+        let main = Circuit {
+            current_witness_index: 3,
+            opcodes: vec![call(1, 1, 2), call(1, 2, 3)],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        };
+
+        Program {
+            functions: vec![main, f],
+        }
+    }
+
+    #[test]
+    fn inlined_program_agrees_with_calls() {
+        let program = program();
+        let initial_witness = WitnessMap::from([(Witness(1), FieldElement::from(3_i128))]);
+
+        let mut witness = initial_witness.clone();
+        let stack = NativeSolver
+            .solve_program_with_stack(&mut witness, &program)
+            .unwrap();
+        assert_eq!(witness[&Witness(3)], FieldElement::from(101_i128));
+
+        // Each call to `f` has its own witness, and the entry point comes last
+        let indices: Vec<_> = stack.iter().map(|item| item.index).collect();
+        assert_eq!(indices, vec![1, 1, 0]);
+        assert_eq!(stack.peek().unwrap().witness, witness);
+        assert_ne!(
+            stack.iter().next().unwrap().witness,
+            stack.iter().nth(1).unwrap().witness
+        );
+
+        let inlined = inline_calls(&program).unwrap();
+        assert_eq!(inlined.opcodes.len(), 4);
+        assert_eq!(inlined.current_witness_index, 5);
+
+        let mut inlined_witness = initial_witness;
+        NativeSolver
+            .solve(&mut inlined_witness, inlined.opcodes)
+            .unwrap();
+        assert_eq!(inlined_witness[&Witness(3)], FieldElement::from(101_i128));
+    }
+
+    #[test]
+    fn recursion_is_rejected() {
+        let mut program = program();
+        program.functions[1].opcodes.push(call(1, 1, 2));
+
+        assert_eq!(inline_calls(&program), Err(CompileError::RecursiveCall(1)));
+    }
+}
@@ -0,0 +1,296 @@
+use acir::{
+    circuit::{
+        directives::Directive,
+        opcodes::{BlackBoxFuncCall, FunctionInput},
+        Circuit, Opcode,
+    },
+    native_types::{Expression, Witness},
+    AcirField, BlackBoxFunc,
+};
+use alloc::{vec, vec::Vec};
+
+use crate::{metrics, trace};
+
+/// The largest table size supported by `lower_to_lookups`.
+///
+/// Limbs are computed using the `ToRadix` directive, which only supports radices up to 256.
+pub const MAX_LOOKUP_TABLE_BITS: u32 = 8;
+
+/// ACIR pass which splits AND, XOR and RANGE calls into calls which fit into a lookup table.
+///
+/// Backends with lookup support implement these functions with a single lookup gate,
+/// as long as the operands fit into the table. This pass decomposes wider operands into
+/// limbs of `table_bits` bits and emits one call per limb, along with the arithmetic
+/// opcodes which recompose the limbs. This avoids the bit decomposition which the
+/// fallback pass would otherwise emit.
+///
+/// The limbs of AND and XOR inputs are not range constrained separately, as the lookup
+/// gate already constrains its inputs to be in the table.
+///
+/// This pass should be run before `compile`, with these functions marked as supported.
+pub fn lower_to_lookups<F: AcirField>(acir: Circuit<F>, table_bits: u32) -> Circuit<F> {
+    assert!(
+        (1..=MAX_LOOKUP_TABLE_BITS).contains(&table_bits),
+        "lookup tables must have between 1 and {MAX_LOOKUP_TABLE_BITS} bits"
+    );
+
+    let _span = trace::span!(DEBUG, "lower_to_lookups", table_bits);
+    let _pass = metrics::pass("lower_to_lookups");
+    let mut next_witness_index = acir.current_witness_index + 1;
+    let mut opcodes = Vec::with_capacity(acir.opcodes.len());
+
+    for opcode in acir.opcodes {
+        match opcode {
+            Opcode::BlackBoxFuncCall(func_call) if needs_lowering(&func_call, table_bits) => {
+                opcodes.extend(lower_call(&func_call, table_bits, &mut next_witness_index));
+            }
+            other => opcodes.push(other),
+        }
+    }
+
+    Circuit {
+        current_witness_index: next_witness_index - 1,
+        opcodes,
+        public_inputs: acir.public_inputs,
+        return_values: acir.return_values,
+    }
+}
+
+pub(super) fn needs_lowering(func_call: &BlackBoxFuncCall, table_bits: u32) -> bool {
+    matches!(
+        func_call.name,
+        BlackBoxFunc::AND | BlackBoxFunc::XOR | BlackBoxFunc::RANGE
+    ) && func_call
+        .inputs
+        .iter()
+        .any(|input| input.num_bits > table_bits)
+}
+
+// Splits an AND, XOR or RANGE call into calls which fit into the table
+pub(super) fn lower_call<F: AcirField>(
+    func_call: &BlackBoxFuncCall,
+    table_bits: u32,
+    next_witness_index: &mut u32,
+) -> Vec<Opcode<F>> {
+    match func_call.name {
+        BlackBoxFunc::RANGE => lower_range(func_call, table_bits, next_witness_index),
+        _ => lower_logic(func_call, table_bits, next_witness_index),
+    }
+}
+
+fn lower_range<F: AcirField>(
+    func_call: &BlackBoxFuncCall,
+    table_bits: u32,
+    next_witness_index: &mut u32,
+) -> Vec<Opcode<F>> {
+    let input = &func_call.inputs[0];
+
+    let mut opcodes = Vec::new();
+    let limbs = decompose(
+        input.witness,
+        input.num_bits,
+        table_bits,
+        next_witness_index,
+        &mut opcodes,
+    );
+
+    for limb in limbs {
+        opcodes.push(Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+            name: BlackBoxFunc::RANGE,
+            inputs: vec![limb],
+            outputs: vec![],
+        }));
+    }
+
+    opcodes
+}
+
+fn lower_logic<F: AcirField>(
+    func_call: &BlackBoxFuncCall,
+    table_bits: u32,
+    next_witness_index: &mut u32,
+) -> Vec<Opcode<F>> {
+    let (lhs, rhs, result, num_bits) = crate::pwg::logic::extract_input_output(func_call);
+
+    let mut opcodes = Vec::new();
+    let lhs_limbs = decompose(lhs, num_bits, table_bits, next_witness_index, &mut opcodes);
+    let rhs_limbs = decompose(rhs, num_bits, table_bits, next_witness_index, &mut opcodes);
+
+    // result - \sum 2^(i * table_bits) * result_i = 0
+    let mut recomposition = Expression::from(&result);
+    let limb_scale = limb_scale(table_bits);
+    let mut scale = F::one();
+    for (lhs_limb, rhs_limb) in lhs_limbs.into_iter().zip(rhs_limbs) {
+        let limb_result = Witness(*next_witness_index);
+        *next_witness_index += 1;
+
+        opcodes.push(Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+            name: func_call.name,
+            inputs: vec![lhs_limb, rhs_limb],
+            outputs: vec![limb_result],
+        }));
+
+        recomposition.term_addition(-scale, limb_result);
+        scale = scale * limb_scale;
+    }
+    recomposition.sort();
+    opcodes.push(Opcode::Arithmetic(recomposition));
+
+    opcodes
+}
+
+// Splits `witness` into little endian limbs of `table_bits` bits, the last of which may be smaller.
+// Emits the directive which computes the limbs and the constraint which recomposes them.
+fn decompose<F: AcirField>(
+    witness: Witness,
+    num_bits: u32,
+    table_bits: u32,
+    next_witness_index: &mut u32,
+    opcodes: &mut Vec<Opcode<F>>,
+) -> Vec<FunctionInput> {
+    let num_limbs = num_bits.div_ceil(table_bits);
+
+    let limbs: Vec<FunctionInput> = (0..num_limbs)
+        .map(|i| {
+            let limb = Witness(*next_witness_index);
+            *next_witness_index += 1;
+            FunctionInput {
+                witness: limb,
+                num_bits: table_bits.min(num_bits - i * table_bits),
+            }
+        })
+        .collect();
+
+    opcodes.push(Opcode::Directive(Directive::ToRadix {
+        a: Expression::from(&witness),
+        b: limbs.iter().map(|limb| limb.witness).collect(),
+        radix: 1 << table_bits,
+    }));
+
+    // witness - \sum 2^(i * table_bits) * limb_i = 0
+    let mut recomposition = Expression::from(&witness);
+    let limb_scale = limb_scale(table_bits);
+    let mut scale = F::one();
+    for limb in &limbs {
+        recomposition.term_addition(-scale, limb.witness);
+        scale = scale * limb_scale;
+    }
+    recomposition.sort();
+    opcodes.push(Opcode::Arithmetic(recomposition));
+
+    limbs
+}
+
+fn limb_scale<F: AcirField>(table_bits: u32) -> F {
+    F::from(1_i128 << table_bits)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::{OpcodeResolutionError, PartialWitnessGenerator, WitnessMap};
+    use acir::circuit::PublicInputs;
+    use acir::FieldElement;
+
+    struct LookupBackend;
+
+    impl PartialWitnessGenerator for LookupBackend {
+        fn solve_blackbox_function_call(
+            &self,
+            initial_witness: &mut WitnessMap,
+            func_call: &BlackBoxFuncCall,
+        ) -> Result<(), OpcodeResolutionError> {
+            match func_call.name {
+                BlackBoxFunc::RANGE => {
+                    crate::pwg::range::solve_range_opcode(initial_witness, func_call)
+                }
+                _ => crate::pwg::logic::solve_logic_opcode(initial_witness, func_call),
+            }
+        }
+    }
+
+    fn function_input(witness: Witness, num_bits: u32) -> FunctionInput {
+        FunctionInput { witness, num_bits }
+    }
+
+    #[test]
+    fn wide_calls_fit_into_table() {
+        let (a, b, and_result, xor_result) = (Witness(1), Witness(2), Witness(3), Witness(4));
+        let circuit = Circuit {
+            current_witness_index: 4,
+            opcodes: vec![
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::AND,
+                    inputs: vec![function_input(a, 20), function_input(b, 20)],
+                    outputs: vec![and_result],
+                }),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::XOR,
+                    inputs: vec![function_input(a, 20), function_input(b, 20)],
+                    outputs: vec![xor_result],
+                }),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::RANGE,
+                    inputs: vec![function_input(a, 20)],
+                    outputs: vec![],
+                }),
+            ],
+            public_inputs: PublicInputs::default(),
+            return_values: PublicInputs::default(),
+        };
+
+        let lowered = lower_to_lookups(circuit, 8);
+        assert!(lowered.opcodes.iter().all(|opcode| match opcode {
+            Opcode::BlackBoxFuncCall(func_call) => !needs_lowering(func_call, 8),
+            _ => true,
+        }));
+
+        let (a_value, b_value) = (0xa5c3f_u128, 0x3c96a_u128);
+        let mut witness = WitnessMap::from([
+            (a, FieldElement::from(a_value)),
+            (b, FieldElement::from(b_value)),
+        ]);
+        LookupBackend.solve(&mut witness, lowered.opcodes).unwrap();
+
+        assert_eq!(witness[&and_result], FieldElement::from(a_value & b_value));
+        assert_eq!(witness[&xor_result], FieldElement::from(a_value ^ b_value));
+    }
+
+    #[test]
+    fn backend_table_sizes_are_checked() {
+        use crate::{
+            compiler::{compile_for_backend, CompileError},
+            testing::MockBackend,
+            BackendError, Language,
+        };
+
+        let circuit = Circuit {
+            current_witness_index: 1,
+            opcodes: vec![Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                name: BlackBoxFunc::RANGE,
+                inputs: vec![function_input(Witness(1), 32)],
+                outputs: vec![],
+            })],
+            public_inputs: PublicInputs::default(),
+            return_values: PublicInputs::default(),
+        };
+        let backend = MockBackend::new().with_language(Language::UltraPlonk {
+            width: 4,
+            lookups: true,
+        });
+
+        for table_bits in [0, 16, 32] {
+            assert_eq!(
+                compile_for_backend(
+                    circuit.clone(),
+                    &backend.clone().with_lookup_table_bits(table_bits)
+                ),
+                Err(BackendError::Compile(CompileError::InvalidLookupTableBits(
+                    table_bits
+                )))
+            );
+        }
+        assert!(compile_for_backend(circuit, &backend.with_lookup_table_bits(4)).is_ok());
+    }
+}
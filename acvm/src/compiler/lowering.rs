@@ -0,0 +1,188 @@
+use acir::{
+    circuit::{opcodes::BlackBoxFuncCall, Circuit, Opcode},
+    AcirField, BlackBoxFunc,
+};
+use alloc::{vec, vec::Vec};
+
+use super::{
+    cost::{opcode_cost, GateWeights, Measure},
+    fallback::opcode_fallback,
+    lookup, CompileError,
+};
+use crate::{metrics, trace, Language};
+
+/// ACIR pass which picks the cheapest lowering of each AND, XOR and RANGE call, according
+/// to the backend's gate weights.
+///
+/// Each call can be kept as it is if the backend supports it, split into calls which fit
+/// into the lookup tables if the backend has lookups and the call does not fit, or
+/// replaced with its arithmetic fallback. Calls which the backend does not support are
+/// kept if that is cheapest, as `compile` replaces them with their fallback.
+/// Lowerings which cost the same are preferred in that order.
+///
+/// This pass replaces `lower_to_lookups` and should be run before `compile`.
+pub fn choose_lowerings<F: AcirField>(
+    acir: Circuit<F>,
+    np_language: &Language,
+    is_blackbox_supported: impl Fn(&BlackBoxFunc) -> bool,
+    lookup_table_bits: Option<u32>,
+    weights: &GateWeights,
+) -> Result<Circuit<F>, CompileError> {
+    let _span = trace::span!(DEBUG, "choose_lowerings", num_opcodes = acir.opcodes.len());
+    let _pass = metrics::pass("choose_lowerings");
+    let mut next_witness_index = acir.current_witness_index + 1;
+    let mut opcodes = Vec::with_capacity(acir.opcodes.len());
+
+    for opcode in acir.opcodes {
+        match opcode {
+            Opcode::BlackBoxFuncCall(func_call)
+                if matches!(
+                    func_call.name,
+                    BlackBoxFunc::AND | BlackBoxFunc::XOR | BlackBoxFunc::RANGE
+                ) =>
+            {
+                let (lowered, updated_witness_index) = cheapest_lowering(
+                    func_call,
+                    np_language,
+                    &is_blackbox_supported,
+                    lookup_table_bits,
+                    weights,
+                    next_witness_index,
+                )?;
+                opcodes.extend(lowered);
+                next_witness_index = updated_witness_index;
+            }
+            other => opcodes.push(other),
+        }
+    }
+
+    Ok(Circuit {
+        current_witness_index: next_witness_index - 1,
+        opcodes,
+        public_inputs: acir.public_inputs,
+        return_values: acir.return_values,
+    })
+}
+
+// Returns the lowering of the call with the lowest weight, along with the index of the
+// next witness after those which it creates
+fn cheapest_lowering<F: AcirField>(
+    func_call: BlackBoxFuncCall,
+    np_language: &Language,
+    is_blackbox_supported: &dyn Fn(&BlackBoxFunc) -> bool,
+    lookup_table_bits: Option<u32>,
+    weights: &GateWeights,
+    next_witness_index: u32,
+) -> Result<(Vec<Opcode<F>>, u32), CompileError> {
+    let supported = is_blackbox_supported(&func_call.name);
+    let lookup_table_bits =
+        lookup_table_bits.filter(|table_bits| lookup::needs_lowering(&func_call, *table_bits));
+
+    // The candidates, in order of preference
+    let mut candidates = Vec::new();
+    // A lookup backend cannot apply a supported call which does not fit into its tables
+    if !supported || lookup_table_bits.is_none() {
+        candidates.push((
+            vec![Opcode::BlackBoxFuncCall(func_call.clone())],
+            next_witness_index,
+        ));
+    }
+    if let Some(table_bits) = lookup_table_bits {
+        let mut updated_witness_index = next_witness_index;
+        let lowered = lookup::lower_call(&func_call, table_bits, &mut updated_witness_index);
+        candidates.push((lowered, updated_witness_index));
+    }
+    if supported {
+        let (updated_witness_index, lowered) = opcode_fallback(&func_call, next_witness_index)?;
+        candidates.push((lowered, updated_witness_index));
+    }
+
+    let mut cheapest: Option<(u64, _)> = None;
+    for (lowered, updated_witness_index) in candidates {
+        let cost = lowered
+            .iter()
+            .map(|opcode| {
+                opcode_cost(
+                    opcode,
+                    np_language,
+                    is_blackbox_supported,
+                    weights,
+                    Measure::Weight,
+                    updated_witness_index,
+                )
+            })
+            .sum::<Result<u64, CompileError>>()?;
+        if cheapest.as_ref().is_none_or(|(lowest, _)| cost < *lowest) {
+            cheapest = Some((cost, (lowered, updated_witness_index)));
+        }
+    }
+    Ok(cheapest
+        .expect("the call or its fallback is always a candidate")
+        .1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{
+        circuit::{opcodes::FunctionInput, PublicInputs},
+        native_types::Witness,
+    };
+
+    fn range_circuit(num_bits: u32) -> Circuit {
+        Circuit {
+            current_witness_index: 1,
+            opcodes: vec![Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                name: BlackBoxFunc::RANGE,
+                inputs: vec![FunctionInput {
+                    witness: Witness(1),
+                    num_bits,
+                }],
+                outputs: Vec::new(),
+            })],
+            public_inputs: PublicInputs::default(),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    fn num_calls(circuit: &Circuit) -> usize {
+        circuit
+            .opcodes
+            .iter()
+            .filter(|opcode| matches!(opcode, Opcode::BlackBoxFuncCall(_)))
+            .count()
+    }
+
+    #[test]
+    fn cheapest_lowering_is_chosen() {
+        let language = Language::UltraPlonk {
+            width: 4,
+            lookups: true,
+        };
+        let lower = |num_bits, lookup_table_bits, weights: &GateWeights| {
+            choose_lowerings(
+                range_circuit(num_bits),
+                &language,
+                |func| *func == BlackBoxFunc::RANGE,
+                lookup_table_bits,
+                weights,
+            )
+            .unwrap()
+        };
+
+        // Counting gates, lookups are cheaper than the bit decomposition
+        let weights = GateWeights::default();
+        assert_eq!(lower(16, Some(8), &weights).opcodes.len(), 4);
+        assert_eq!(num_calls(&lower(16, Some(8), &weights)), 2);
+        assert_eq!(lower(16, None, &weights), range_circuit(16));
+
+        // The bit decomposition is used when lookups are expensive
+        let weights = weights.with_black_box(BlackBoxFunc::RANGE, 100);
+        assert_eq!(num_calls(&lower(16, Some(8), &weights)), 0);
+        assert_eq!(num_calls(&lower(16, None, &weights)), 0);
+        // Unless it is wider still
+        let weights = weights.with_black_box(BlackBoxFunc::RANGE, 20);
+        assert_eq!(num_calls(&lower(4, None, &weights)), 0);
+        assert_eq!(lower(64, None, &weights), range_circuit(64));
+    }
+}
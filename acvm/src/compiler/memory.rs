@@ -0,0 +1,287 @@
+// Estimates of the memory used to solve a circuit
+//
+// Only the allocations which grow with the circuit are counted: the witness maps, the
+// opcodes being solved along with the solver's queue, and the temporaries allocated to
+// solve a single opcode. Sizes are those of the types on the target which acvm is
+// compiled for, so estimates for wasm are made by acvm compiled to wasm.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+use acir::{
+    circuit::{directives::Directive, opcodes::FunctionInput, Circuit, Opcode, Program},
+    native_types::{Expression, Witness},
+    AcirField,
+};
+use serde::{Deserialize, Serialize};
+
+use super::CompileError;
+use crate::OpcodeNotSolvable;
+
+/// The memory needed to solve a circuit, in bytes unless stated otherwise
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryEstimate {
+    /// The number of witnesses in all of the witness maps created while solving
+    pub num_witnesses: usize,
+    /// The witness maps of the entry point and of every call, as the solver keeps the
+    /// witness of each call until solving finishes
+    pub witness_bytes: usize,
+    /// The opcodes and solver queues of the deepest chain of nested calls
+    pub opcode_bytes: usize,
+    /// The largest temporary allocated to solve a single opcode
+    pub temporary_bytes: usize,
+    /// The largest single allocation, which is usually the witness map of the largest
+    /// function. Streaming the opcodes does not reduce it.
+    pub largest_block_bytes: usize,
+}
+
+impl MemoryEstimate {
+    /// Returns the estimated peak memory use while solving
+    pub fn peak_bytes(&self) -> usize {
+        self.witness_bytes + self.opcode_bytes + self.temporary_bytes
+    }
+
+    /// Returns true if solving is expected to fit into `budget` bytes
+    pub fn fits_in(&self, budget: usize) -> bool {
+        self.peak_bytes() <= budget
+    }
+}
+
+/// Estimates the memory needed to solve `circuit` with a dense witness map, as
+/// `solve_program` uses.
+///
+/// Calls to other functions are not followed, see `estimate_program_memory`.
+pub fn estimate_memory<F: AcirField>(circuit: &Circuit<F>) -> MemoryEstimate {
+    let mut estimator = MemoryEstimator {
+        functions: None,
+        call_stack: Vec::new(),
+        estimates: BTreeMap::new(),
+    };
+    estimator
+        .estimate_function(circuit)
+        .expect("calls are not followed")
+}
+
+/// Estimates the memory needed to solve a program with `solve_program`, including the
+/// witness maps and opcodes of every call.
+pub fn estimate_program_memory<F: AcirField>(
+    program: &Program<F>,
+) -> Result<MemoryEstimate, CompileError> {
+    let mut estimator = MemoryEstimator {
+        functions: Some(&program.functions),
+        call_stack: Vec::new(),
+        estimates: BTreeMap::new(),
+    };
+    estimator.estimate_call(0)
+}
+
+struct MemoryEstimator<'a, F> {
+    // The functions which calls are resolved against, or `None` if calls are not followed
+    functions: Option<&'a [Circuit<F>]>,
+    // Functions which are currently being estimated, used to detect recursion
+    call_stack: Vec<u32>,
+    // The estimates of the functions which have been estimated, as a function can be
+    // called many times
+    estimates: BTreeMap<u32, MemoryEstimate>,
+}
+
+impl<F: AcirField> MemoryEstimator<'_, F> {
+    fn estimate_call(&mut self, id: u32) -> Result<MemoryEstimate, CompileError> {
+        if let Some(estimate) = self.estimates.get(&id) {
+            return Ok(*estimate);
+        }
+        if self.call_stack.contains(&id) {
+            return Err(CompileError::RecursiveCall(id));
+        }
+        let function = self
+            .functions
+            .and_then(|functions| functions.get(id as usize))
+            .ok_or(CompileError::UnknownFunction(id))?;
+
+        self.call_stack.push(id);
+        let estimate = self.estimate_function(function)?;
+        self.call_stack.pop();
+
+        self.estimates.insert(id, estimate);
+        Ok(estimate)
+    }
+
+    fn estimate_function(&mut self, function: &Circuit<F>) -> Result<MemoryEstimate, CompileError> {
+        let num_opcodes = function.opcodes.len();
+        let witness_bytes = function.num_vars() as usize * size_of::<Option<(Witness, F)>>();
+        let opcode_vec_bytes = num_opcodes * size_of::<Opcode<F>>();
+        let queue_bytes = num_opcodes
+            * (size_of::<usize>() + size_of::<bool>() + size_of::<Option<OpcodeNotSolvable<F>>>());
+
+        let mut estimate = MemoryEstimate {
+            num_witnesses: function.num_vars() as usize,
+            witness_bytes,
+            opcode_bytes: opcode_vec_bytes
+                + function
+                    .opcodes
+                    .iter()
+                    .map(opcode_heap_bytes)
+                    .sum::<usize>()
+                + queue_bytes,
+            temporary_bytes: 0,
+            largest_block_bytes: witness_bytes.max(opcode_vec_bytes),
+        };
+
+        // Calls are solved one after the other, so only the deepest one adds to the
+        // opcodes which are held at once
+        let mut deepest_call_bytes = 0;
+        for opcode in &function.opcodes {
+            estimate.temporary_bytes = estimate.temporary_bytes.max(temporary_bytes(opcode));
+            let Opcode::Call { id, .. } = opcode else {
+                continue;
+            };
+            if self.functions.is_none() {
+                continue;
+            }
+            let callee = self.estimate_call(*id)?;
+            estimate.num_witnesses += callee.num_witnesses;
+            estimate.witness_bytes += callee.witness_bytes;
+            deepest_call_bytes = deepest_call_bytes.max(callee.opcode_bytes);
+            estimate.temporary_bytes = estimate.temporary_bytes.max(callee.temporary_bytes);
+            estimate.largest_block_bytes =
+                estimate.largest_block_bytes.max(callee.largest_block_bytes);
+        }
+        estimate.opcode_bytes += deepest_call_bytes;
+
+        Ok(estimate)
+    }
+}
+
+// The bytes which an opcode holds on the heap
+fn opcode_heap_bytes<F: AcirField>(opcode: &Opcode<F>) -> usize {
+    let witnesses = |num_witnesses: usize| num_witnesses * size_of::<Witness>();
+    match opcode {
+        Opcode::Arithmetic(expr) => expression_bytes(expr),
+        Opcode::BlackBoxFuncCall(func_call) => {
+            func_call.inputs.len() * size_of::<FunctionInput>() + witnesses(func_call.outputs.len())
+        }
+        Opcode::Directive(directive) => match directive {
+            Directive::Invert { .. } | Directive::OddRange { .. } => 0,
+            Directive::Quotient {
+                a, b, predicate, ..
+            } => {
+                expression_bytes(a)
+                    + expression_bytes(b)
+                    + predicate.as_ref().map_or(0, expression_bytes)
+            }
+            Directive::Truncate { a, .. } => expression_bytes(a),
+            Directive::ToRadix { a, b, .. } => expression_bytes(a) + witnesses(b.len()),
+            Directive::Oracle {
+                name,
+                inputs,
+                outputs,
+            } => {
+                name.len()
+                    + inputs
+                        .iter()
+                        .map(|input| size_of::<Expression<F>>() + expression_bytes(input))
+                        .sum::<usize>()
+                    + witnesses(outputs.len())
+            }
+        },
+        Opcode::Call {
+            inputs, outputs, ..
+        } => witnesses(inputs.len() + outputs.len()),
+    }
+}
+
+fn expression_bytes<F: AcirField>(expr: &Expression<F>) -> usize {
+    expr.mul_terms.len() * size_of::<(F, Witness, Witness)>()
+        + expr.linear_combinations.len() * size_of::<(F, Witness)>()
+}
+
+// The bytes which are allocated to solve an opcode, and freed once it is solved
+fn temporary_bytes<F: AcirField>(opcode: &Opcode<F>) -> usize {
+    let values = |num_values: usize| num_values * size_of::<F>();
+    match opcode {
+        // The values of the inputs
+        Opcode::BlackBoxFuncCall(func_call) => values(func_call.inputs.len()),
+        Opcode::Directive(directive) => match directive {
+            // The operands as integers
+            Directive::Quotient { .. } | Directive::Truncate { .. } => values(2),
+            // The value as an integer, and its digits
+            Directive::ToRadix { b, .. } => values(1) + b.len(),
+            // The values passed to and returned by the oracle
+            Directive::Oracle {
+                inputs, outputs, ..
+            } => values(inputs.len() + outputs.len()),
+            Directive::Invert { .. } | Directive::OddRange { .. } => 0,
+        },
+        Opcode::Arithmetic(_) | Opcode::Call { .. } => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::circuit::PublicInputs;
+
+    fn function(current_witness_index: u32, opcodes: Vec<Opcode>, num_parameters: u32) -> Circuit {
+        Circuit {
+            current_witness_index,
+            opcodes,
+            public_inputs: PublicInputs((1..=num_parameters).map(Witness).collect()),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    fn call(id: u32) -> Opcode {
+        Opcode::Call {
+            id,
+            inputs: vec![Witness(1)],
+            outputs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn calls_add_their_witness_maps() {
+        let to_radix = Opcode::Directive(Directive::ToRadix {
+            a: Expression::from(Witness(1)),
+            b: (2..34).map(Witness).collect(),
+            radix: 2,
+        });
+        let callee = function(1000, vec![to_radix.clone()], 1);
+        let main = function(10, vec![call(1), call(1)], 1);
+
+        let callee_estimate = estimate_memory(&callee);
+        assert_eq!(callee_estimate.num_witnesses, 1001);
+        assert_eq!(callee_estimate.temporary_bytes, temporary_bytes(&to_radix));
+        assert_eq!(
+            callee_estimate.largest_block_bytes,
+            callee_estimate.witness_bytes
+        );
+
+        let program = Program {
+            functions: vec![main.clone(), callee],
+        };
+        let estimate = estimate_program_memory(&program).unwrap();
+        let main_estimate = estimate_memory(&main);
+        // Both calls keep their witness, but only one is solved at a time
+        assert_eq!(estimate.num_witnesses, 11 + 2 * 1001);
+        assert_eq!(
+            estimate.witness_bytes,
+            main_estimate.witness_bytes + 2 * callee_estimate.witness_bytes
+        );
+        assert_eq!(
+            estimate.opcode_bytes,
+            main_estimate.opcode_bytes + callee_estimate.opcode_bytes
+        );
+        assert_eq!(estimate.largest_block_bytes, callee_estimate.witness_bytes);
+        assert!(estimate.fits_in(estimate.peak_bytes()));
+        assert!(!estimate.fits_in(estimate.peak_bytes() - 1));
+
+        let recursive = Program {
+            functions: vec![main, function(1, vec![call(1)], 1)],
+        };
+        assert_eq!(
+            estimate_program_memory(&recursive),
+            Err(CompileError::RecursiveCall(1))
+        );
+    }
+}
@@ -1,12 +1,14 @@
-use std::cmp::Ordering;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
 
+use crate::collections::IndexMap;
 use acir::{
     native_types::{Expression, Witness},
-    FieldElement,
+    AcirField,
 };
-use indexmap::IndexMap;
 
-use super::general_optimiser::GeneralOpt;
+use super::{general_optimiser::GeneralOpt, pool};
 // Optimiser struct with all of the related optimisations to the arithmetic gate
 
 // Is this more of a Reducer than an optimiser?
@@ -14,6 +16,7 @@ use super::general_optimiser::GeneralOpt;
 // Have a single optimiser that you instantiate with a width, then pass many gates through
 pub struct Optimiser {
     width: usize,
+    pinned: BTreeSet<Witness>,
 }
 
 impl Optimiser {
@@ -21,19 +24,29 @@ impl Optimiser {
     pub fn new(width: usize) -> Optimiser {
         assert!(width > 2);
 
-        Optimiser { width }
+        Optimiser {
+            width,
+            pinned: BTreeSet::new(),
+        }
+    }
+
+    // Keeps a use of each of the `pinned` witnesses in the gates which use them, where the
+    // general optimiser would remove the terms of a witness with a zero coefficient
+    pub fn with_pinned(mut self, pinned: BTreeSet<Witness>) -> Optimiser {
+        self.pinned = pinned;
+        self
     }
 
     // Still missing dead witness optimisation.
     // To do this, we will need the whole set of arithmetic gates
     // I think it can also be done before the local optimisation seen here, as dead variables will come from the user
-    pub fn optimise(
+    pub fn optimise<F: AcirField>(
         &self,
-        gate: Expression,
-        intermediate_variables: &mut IndexMap<Witness, Expression>,
+        gate: Expression<F>,
+        intermediate_variables: &mut IndexMap<Witness, Expression<F>>,
         num_witness: u32,
-    ) -> Expression {
-        let gate = GeneralOpt::optimise(gate);
+    ) -> Expression<F> {
+        let gate = GeneralOpt::optimise_pinned(gate, &self.pinned);
 
         // Here we create intermediate variables and constrain them to be equal to any subset of the polynomial that can be represented as a full gate
         let gate = self.full_gate_scan_optimisation(gate, intermediate_variables, num_witness);
@@ -70,12 +83,12 @@ impl Optimiser {
     // The polynomial now looks like so t + t2
     // We can no longer extract another full gate, hence the algorithm terminates. Creating two intermediate variables t and t2.
     // This stage of preprocessing does not guarantee that all polynomials can fit into a gate. It only guarantees that all full gates have been extracted from each polynomial
-    fn full_gate_scan_optimisation(
+    fn full_gate_scan_optimisation<F: AcirField>(
         &self,
-        mut gate: Expression,
-        intermediate_variables: &mut IndexMap<Witness, Expression>,
+        mut gate: Expression<F>,
+        intermediate_variables: &mut IndexMap<Witness, Expression<F>>,
         num_witness: u32,
-    ) -> Expression {
+    ) -> Expression<F> {
         // We pass around this intermediate variable IndexMap, so that we do not create intermediate variables that we have created before
         // One instance where this might happen is t1 = wL * wR and t2 = wR * wL
 
@@ -96,7 +109,7 @@ impl Optimiser {
 
         // This will be our new gate which will be equal to `self` except we will have intermediate variables that will be constrained to any
         // subset of the terms that can be represented as full gates
-        let mut new_gate = Expression::default();
+        let mut new_gate = pool::expression();
 
         while !gate.mul_terms.is_empty() {
             let pair = gate.mul_terms[0];
@@ -133,7 +146,7 @@ impl Optimiser {
 
                     // Lets create an intermediate gate to store this full gate
                     //
-                    let mut intermediate_gate = Expression::default();
+                    let mut intermediate_gate = pool::expression();
                     intermediate_gate.mul_terms.push(pair);
 
                     // Add the left and right wires
@@ -178,14 +191,12 @@ impl Optimiser {
                     // Constrain the gate to the intermediate variable
                     intermediate_gate
                         .linear_combinations
-                        .push((-FieldElement::one(), inter_var));
+                        .push((-F::one(), inter_var));
                     // Add intermediate gate to the map
                     intermediate_variables.insert(inter_var, intermediate_gate);
 
                     // Add intermediate variable to the new gate instead of the full gate
-                    new_gate
-                        .linear_combinations
-                        .push((FieldElement::one(), inter_var));
+                    new_gate.linear_combinations.push((F::one(), inter_var));
                 }
             };
             // Remove this term as we are finished processing it
@@ -193,11 +204,12 @@ impl Optimiser {
         }
 
         // Add the rest of the elements back into the new_gate
-        new_gate.mul_terms.extend(gate.mul_terms.clone());
+        new_gate.mul_terms.append(&mut gate.mul_terms);
         new_gate
             .linear_combinations
-            .extend(gate.linear_combinations.clone());
+            .append(&mut gate.linear_combinations);
         new_gate.q_c = gate.q_c;
+        pool::recycle(gate);
         new_gate.sort();
         new_gate
     }
@@ -239,12 +251,12 @@ impl Optimiser {
     // Also remember that since we did full gate scan, there is no way we can have a non-zero mul term along with the wL and wR terms being non-zero
     //
     // Cases, a lot of mul terms, a lot of fan-in terms, 50/50
-    fn partial_gate_scan_optimisation(
+    fn partial_gate_scan_optimisation<F: AcirField>(
         &self,
-        mut gate: Expression,
-        intermediate_variables: &mut IndexMap<Witness, Expression>,
+        mut gate: Expression<F>,
+        intermediate_variables: &mut IndexMap<Witness, Expression<F>>,
         num_witness: u32,
-    ) -> Expression {
+    ) -> Expression<F> {
         // We will go for the easiest route, which is to convert all multiplications into additions using intermediate variables
         // Then use intermediate variables again to squash the fan-in, so that it can fit into the appropriate width
 
@@ -255,28 +267,26 @@ impl Optimiser {
         }
 
         // 2. Create Intermediate variables for the multiplication gates
-        for mul_term in gate.mul_terms.clone().into_iter() {
+        for mul_term in gate.mul_terms.drain(..) {
             // Create intermediate variable to squash the multiplication term
             let inter_var = Witness((intermediate_variables.len() as u32) + num_witness);
-            let mut intermediate_gate = Expression::default();
+            let mut intermediate_gate = pool::expression();
 
             // Push mul term into the gate
             intermediate_gate.mul_terms.push(mul_term);
             // Constrain it to be equal to the intermediate variable
             intermediate_gate
                 .linear_combinations
-                .push((-FieldElement::one(), inter_var));
+                .push((-F::one(), inter_var));
 
             // Add intermediate gate and variable to map
             intermediate_variables.insert(inter_var, intermediate_gate);
 
             // Add intermediate variable as a part of the fan-in for the original gate
-            gate.linear_combinations
-                .push((FieldElement::one(), inter_var));
+            gate.linear_combinations.push((F::one(), inter_var));
         }
 
-        // Remove all of the mul terms as we have intermediate variables to represent them now
-        gate.mul_terms.clear();
+        // All of the mul terms have been drained, as we have intermediate variables to represent them now
 
         // We now only have a polynomial with only fan-in/fan-out terms i.e. terms of the form Ax + By + Cd + ...
         // Lets create intermediate variables if all of them cannot fit into the width
@@ -292,7 +302,7 @@ impl Optimiser {
 
         while gate.linear_combinations.len() > self.width {
             // Collect as many terms up to the given width-1 and constrain them to an intermediate variable
-            let mut intermediate_gate = Expression::default();
+            let mut intermediate_gate = pool::expression();
 
             for _ in 0..(self.width - 1) {
                 match gate.linear_combinations.pop() {
@@ -307,11 +317,11 @@ impl Optimiser {
             // Constrain the intermediate gate to be equal to the intermediate variable
             let inter_var = Witness((intermediate_variables.len() as u32) + num_witness);
 
-            added.push((FieldElement::one(), inter_var));
+            added.push((F::one(), inter_var));
 
             intermediate_gate
                 .linear_combinations
-                .push((-FieldElement::one(), inter_var));
+                .push((-F::one(), inter_var));
 
             // Add intermediate gate and variable to map
             intermediate_variables.insert(inter_var, intermediate_gate);
@@ -327,6 +337,8 @@ impl Optimiser {
 
 #[test]
 fn simple_reduction_smoke_test() {
+    use acir::FieldElement;
+
     let a = Witness(0);
     let b = Witness(1);
     let c = Witness(2);
@@ -344,7 +356,7 @@ fn simple_reduction_smoke_test() {
         q_c: FieldElement::zero(),
     };
 
-    let mut intermediate_variables: IndexMap<Witness, Expression> = IndexMap::new();
+    let mut intermediate_variables: IndexMap<Witness, Expression> = IndexMap::default();
 
     let num_witness = 4;
 
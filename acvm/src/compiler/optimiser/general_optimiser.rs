@@ -1,20 +1,31 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+use crate::collections::IndexMap;
 use acir::{
     native_types::{Expression, Witness},
-    FieldElement,
+    AcirField,
 };
-use indexmap::IndexMap;
 
 pub struct GeneralOpt;
 impl GeneralOpt {
-    pub fn optimise(gate: Expression) -> Expression {
+    pub fn optimise<F: AcirField>(gate: Expression<F>) -> Expression<F> {
+        Self::optimise_pinned(gate, &BTreeSet::new())
+    }
+
+    // Optimises the gate as `optimise` does, without removing every use of a pinned witness
+    pub fn optimise_pinned<F: AcirField>(
+        gate: Expression<F>,
+        pinned: &BTreeSet<Witness>,
+    ) -> Expression<F> {
         // XXX: Perhaps this optimisation can be done on the fly
-        let gate = remove_zero_coefficients(gate);
+        let gate = remove_zero_coefficients_pinned(gate, pinned);
         simplify_mul_terms(gate)
     }
 }
 
 // Remove all terms with zero as a coefficient
-pub fn remove_zero_coefficients(mut gate: Expression) -> Expression {
+pub fn remove_zero_coefficients<F: AcirField>(mut gate: Expression<F>) -> Expression<F> {
     // Check the mul terms
     gate.mul_terms.retain(|(scale, _, _)| !scale.is_zero());
     // Check the linear combination terms
@@ -23,24 +34,55 @@ pub fn remove_zero_coefficients(mut gate: Expression) -> Expression {
     gate
 }
 
+// Remove all terms with zero as a coefficient, except that a pinned witness which is only
+// used by such terms keeps a linear term with a zero coefficient
+fn remove_zero_coefficients_pinned<F: AcirField>(
+    gate: Expression<F>,
+    pinned: &BTreeSet<Witness>,
+) -> Expression<F> {
+    if pinned.is_empty() {
+        return remove_zero_coefficients(gate);
+    }
+    let witnesses = gate.witnesses();
+    restore_pinned(remove_zero_coefficients(gate), &witnesses, pinned)
+}
+
+// Adds a linear term with a zero coefficient for each pinned witness of `witnesses` which
+// the gate no longer uses
+pub(crate) fn restore_pinned<F: AcirField>(
+    mut gate: Expression<F>,
+    witnesses: &BTreeSet<Witness>,
+    pinned: &BTreeSet<Witness>,
+) -> Expression<F> {
+    let used = gate.witnesses();
+    let removed: Vec<Witness> = witnesses
+        .intersection(pinned)
+        .filter(|witness| !used.contains(witness))
+        .copied()
+        .collect();
+    gate.linear_combinations
+        .extend(removed.into_iter().map(|witness| (F::zero(), witness)));
+    gate
+}
+
 // Simplifies all mul terms with the same bi-variate variables
-pub fn simplify_mul_terms(mut gate: Expression) -> Expression {
-    let mut hash_map: IndexMap<(Witness, Witness), FieldElement> = IndexMap::new();
+pub fn simplify_mul_terms<F: AcirField>(mut gate: Expression<F>) -> Expression<F> {
+    let mut hash_map: IndexMap<(Witness, Witness), F> = IndexMap::default();
 
     // Canonicalise the ordering of the multiplication, lets just order by variable name
-    for (scale, w_l, w_r) in gate.mul_terms.clone().into_iter() {
-        let mut pair = vec![w_l, w_r];
+    for (scale, w_l, w_r) in gate.mul_terms.drain(..) {
+        let mut pair = [w_l, w_r];
         // Sort using rust sort algorithm
         pair.sort();
 
-        *hash_map
-            .entry((pair[0], pair[1]))
-            .or_insert_with(FieldElement::zero) += scale;
+        *hash_map.entry((pair[0], pair[1])).or_insert_with(F::zero) += scale;
     }
 
-    gate.mul_terms = hash_map
-        .into_iter()
-        .map(|((w_l, w_r), scale)| (scale, w_l, w_r))
-        .collect();
+    // Reuse the allocation of the drained mul terms
+    gate.mul_terms.extend(
+        hash_map
+            .into_iter()
+            .map(|((w_l, w_r), scale)| (scale, w_l, w_r)),
+    );
     gate
 }
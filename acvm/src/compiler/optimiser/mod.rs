@@ -1,7 +1,10 @@
 mod csat_optimiser;
 mod general_optimiser;
+mod pool;
 mod r1cs_optimiser;
+mod width_packing;
 
 pub use csat_optimiser::Optimiser as CSatOptimiser;
 pub use general_optimiser::GeneralOpt as GeneralOptimiser;
 pub use r1cs_optimiser::R1CSOptimiser;
+pub use width_packing::WidthPacker;
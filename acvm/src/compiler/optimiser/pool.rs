@@ -0,0 +1,93 @@
+// A per-thread pool of expressions which the optimiser passes have finished with
+//
+// Reducing a large circuit builds and drops many small temporary expressions. Taking
+// them from the pool, and returning the ones which are not kept, reuses the allocations
+// of their term vectors instead of going back to the allocator for each one.
+//
+// The pool is per thread so that it needs no locking when opcodes are reduced in parallel.
+// Without the `std` feature there are no thread locals, so nothing is pooled.
+
+#[cfg(feature = "std")]
+use core::{any::Any, cell::RefCell};
+
+#[cfg(feature = "std")]
+use acir::FieldElement;
+use acir::{native_types::Expression, AcirField};
+
+// The most expressions which are kept in each thread's pool
+#[cfg(feature = "std")]
+const MAX_POOLED_EXPRESSIONS: usize = 64;
+
+// Expressions whose vectors have grown past this many terms are not kept, so that one
+// very wide expression does not pin its memory for the rest of the compilation
+#[cfg(feature = "std")]
+const MAX_POOLED_TERMS: usize = 256;
+
+// Thread locals cannot be generic, so only expressions over `FieldElement` are pooled
+#[cfg(feature = "std")]
+thread_local! {
+    static POOL: RefCell<Vec<Expression>> = const { RefCell::new(Vec::new()) };
+}
+
+// Returns an empty expression, reusing the allocations of a recycled expression if there is one
+#[cfg(feature = "std")]
+pub(super) fn expression<F: AcirField>() -> Expression<F> {
+    let mut expr = Expression::default();
+    if let Some(pooled) = (&mut expr as &mut dyn Any).downcast_mut::<Expression>() {
+        if let Some(recycled) = POOL.with(|pool| pool.borrow_mut().pop()) {
+            *pooled = recycled;
+        }
+    }
+    expr
+}
+
+#[cfg(not(feature = "std"))]
+pub(super) fn expression<F: AcirField>() -> Expression<F> {
+    Expression::default()
+}
+
+// Returns an expression which is no longer needed to the pool
+#[cfg(feature = "std")]
+pub(super) fn recycle<F: AcirField>(mut expr: Expression<F>) {
+    let Some(expr) = (&mut expr as &mut dyn Any).downcast_mut::<Expression>() else {
+        return;
+    };
+    if expr.mul_terms.capacity() > MAX_POOLED_TERMS
+        || expr.linear_combinations.capacity() > MAX_POOLED_TERMS
+    {
+        return;
+    }
+    expr.mul_terms.clear();
+    expr.linear_combinations.clear();
+    expr.q_c = FieldElement::zero();
+
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        if pool.len() < MAX_POOLED_EXPRESSIONS {
+            pool.push(core::mem::take(expr));
+        }
+    });
+}
+
+#[cfg(not(feature = "std"))]
+pub(super) fn recycle<F: AcirField>(_expr: Expression<F>) {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{native_types::Witness, FieldElement};
+
+    #[test]
+    fn recycled_expressions_are_empty() {
+        let mut expr = Expression::default();
+        expr.term_multiplication(FieldElement::one(), Witness(1), Witness(2));
+        expr.term_addition(FieldElement::one(), Witness(3));
+        expr.q_c = FieldElement::one();
+        let capacity = expr.linear_combinations.capacity();
+        recycle(expr);
+
+        let reused: Expression = expression();
+        assert_eq!(reused, Expression::default());
+        assert!(reused.linear_combinations.capacity() >= capacity);
+    }
+}
@@ -1,26 +1,67 @@
-use crate::compiler::GeneralOptimiser;
-use acir::circuit::{Circuit, Opcode};
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
 
-pub struct R1CSOptimiser {
-    acir: Circuit,
+use crate::{
+    compiler::{parallel, GeneralOptimiser},
+    metrics,
+    profile::{self, ProfileReport},
+    trace,
+};
+use acir::{
+    circuit::{Circuit, Opcode},
+    native_types::Witness,
+    AcirField, FieldElement,
+};
+
+pub struct R1CSOptimiser<F = FieldElement> {
+    acir: Circuit<F>,
+    pinned: BTreeSet<Witness>,
 }
 
-impl R1CSOptimiser {
-    pub fn new(acir: Circuit) -> Self {
-        Self { acir }
+impl<F: AcirField> R1CSOptimiser<F> {
+    pub fn new(acir: Circuit<F>) -> Self {
+        Self {
+            acir,
+            pinned: BTreeSet::new(),
+        }
+    }
+
+    // Keeps a use of each of the `pinned` witnesses in the gates which use them, as
+    // `CSatOptimiser::with_pinned` does
+    pub fn with_pinned(mut self, pinned: BTreeSet<Witness>) -> Self {
+        self.pinned = pinned;
+        self
     }
     // R1CS optimisations uses the general optimiser.
     // TODO: We could possibly make sure that all polynomials are at most degree-2
-    pub fn optimise(self) -> Circuit {
-        let optimised_arith_gates: Vec<_> = self
-            .acir
-            .opcodes
-            .into_iter()
-            .map(|gate| match gate {
-                Opcode::Arithmetic(arith) => Opcode::Arithmetic(GeneralOptimiser::optimise(arith)),
-                other_gates => other_gates,
-            })
-            .collect();
+    pub fn optimise(self) -> Circuit<F> {
+        self.optimise_with_profile(None)
+    }
+
+    // Optimises the circuit, recording the time taken to optimise each arithmetic gate if
+    // a report is given
+    pub(crate) fn optimise_with_profile(self, profile: Option<&mut ProfileReport>) -> Circuit<F> {
+        let _span = trace::span!(DEBUG, "optimise");
+        let _pass = metrics::pass("optimise");
+        let profiling = profile.is_some();
+        let optimised = parallel::map(self.acir.opcodes, |gate| match gate {
+            Opcode::Arithmetic(arith) => {
+                let start = profile::start_timer(profiling);
+                let gate =
+                    Opcode::Arithmetic(GeneralOptimiser::optimise_pinned(arith, &self.pinned));
+                (gate, start.map(|start| start.elapsed()))
+            }
+            other_gates => (other_gates, None),
+        });
+
+        let mut optimised_arith_gates = Vec::with_capacity(optimised.len());
+        let mut profile = profile;
+        for (gate, elapsed) in optimised {
+            if let (Some(profile), Some(elapsed)) = (profile.as_deref_mut(), elapsed) {
+                profile.record("arithmetic", elapsed);
+            }
+            optimised_arith_gates.push(gate);
+        }
 
         Circuit {
             // The general optimiser may remove enough gates that a witness is no longer used
@@ -30,6 +71,7 @@ impl R1CSOptimiser {
             current_witness_index: self.acir.current_witness_index,
             opcodes: optimised_arith_gates,
             public_inputs: self.acir.public_inputs,
+            return_values: self.acir.return_values,
         }
     }
 }
@@ -0,0 +1,208 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+use acir::{
+    native_types::{Expression, Witness},
+    AcirField,
+};
+
+use super::{general_optimiser::restore_pinned, pool};
+use crate::compiler::GateWeights;
+
+// The CSAT optimiser splits an expression into gates which each fit into the width
+// by introducing intermediate variables. Since this is done greedily, many of the
+// resulting gates use fewer wires than the width allows.
+//
+// This pass fills those gates by merging pairs of gates which are the only two users of
+// an intermediate variable, when the merged gate still fits into the width:
+//
+// g1 = c1 * t + rest1
+// g2 = c2 * t + rest2
+//
+// becomes g1 - (c1/c2) * g2, which no longer contains `t`.
+// Since `t` is not used anywhere else, `g2 = 0` can always be satisfied by a choice of `t`,
+// so the merged gate applies the same constraint on the other witnesses as the two gates did.
+//
+// Terms which cancel out are removed from the merged gate, unless they are of a pinned
+// witness, which keeps a term with a zero coefficient.
+//
+// Gates are only merged if the merged gate weighs no more than the two gates did, as
+// wide gates can cost more than several narrow ones.
+pub struct WidthPacker {
+    width: usize,
+    pinned: BTreeSet<Witness>,
+    weights: GateWeights,
+}
+
+impl WidthPacker {
+    pub fn new(width: usize) -> WidthPacker {
+        assert!(width > 2);
+
+        WidthPacker {
+            width,
+            pinned: BTreeSet::new(),
+            weights: GateWeights::default(),
+        }
+    }
+
+    // Keeps a use of each of the `pinned` witnesses in the merged gates
+    pub fn with_pinned(mut self, pinned: BTreeSet<Witness>) -> WidthPacker {
+        self.pinned = pinned;
+        self
+    }
+
+    // Weighs the gates by the backend's costs when deciding whether to merge them
+    pub fn with_weights(mut self, weights: GateWeights) -> WidthPacker {
+        self.weights = weights;
+        self
+    }
+
+    /// Packs the gates that an expression was reduced to.
+    /// Witnesses with an index at or above `first_intermediate` are treated as intermediate
+    /// variables that are not referenced outside of `gates`.
+    pub fn pack<F: AcirField>(
+        &self,
+        mut gates: Vec<Expression<F>>,
+        first_intermediate: Witness,
+    ) -> Vec<Expression<F>> {
+        while let Some((i, j, merged)) = self.find_merge(&gates, first_intermediate) {
+            pool::recycle(core::mem::replace(&mut gates[i], merged));
+            pool::recycle(gates.remove(j));
+        }
+        gates
+    }
+
+    // Returns the first pair of gates that can be merged, along with the merged gate
+    fn find_merge<F: AcirField>(
+        &self,
+        gates: &[Expression<F>],
+        first_intermediate: Witness,
+    ) -> Option<(usize, usize, Expression<F>)> {
+        let mut intermediates: Vec<Witness> = gates
+            .iter()
+            .flat_map(|gate| gate.linear_combinations.iter().map(|(_, witness)| *witness))
+            .filter(|witness| *witness >= first_intermediate)
+            .collect();
+        intermediates.sort();
+        intermediates.dedup();
+
+        for intermediate in intermediates {
+            let users: Vec<usize> = gates
+                .iter()
+                .enumerate()
+                .filter(|(_, gate)| uses_witness(gate, intermediate))
+                .map(|(index, _)| index)
+                .collect();
+
+            let (i, j) = match users[..] {
+                [i, j] => (i, j),
+                _ => continue,
+            };
+
+            // We can only eliminate the intermediate variable if it is used linearly
+            let (c1, c2) = match (
+                linear_coefficient(&gates[i], intermediate),
+                linear_coefficient(&gates[j], intermediate),
+            ) {
+                (Some(c1), Some(c2)) => (c1, c2),
+                _ => continue,
+            };
+
+            let scale = -(c1 / c2);
+            let mut merged = (&gates[i] + &(&gates[j] * &scale)).normalize();
+            if !self.pinned.is_empty() {
+                let witnesses = gates[i]
+                    .witnesses()
+                    .union(&gates[j].witnesses())
+                    .filter(|witness| **witness < first_intermediate)
+                    .copied()
+                    .collect();
+                merged = restore_pinned(merged, &witnesses, &self.pinned);
+            }
+
+            let separate_cost =
+                self.weights.arithmetic_cost(&gates[i]) + self.weights.arithmetic_cost(&gates[j]);
+            if merged.fits_in_one_identity(self.width)
+                && self.weights.arithmetic_cost(&merged) <= separate_cost
+            {
+                return Some((i, j, merged));
+            }
+            pool::recycle(merged);
+        }
+
+        None
+    }
+}
+
+fn uses_witness<F: AcirField>(gate: &Expression<F>, witness: Witness) -> bool {
+    gate.linear_combinations.iter().any(|(_, w)| *w == witness)
+        || gate
+            .mul_terms
+            .iter()
+            .any(|(_, w_l, w_r)| *w_l == witness || *w_r == witness)
+}
+
+// Returns the coefficient of `witness` if it only appears in the linear terms of the gate
+fn linear_coefficient<F: AcirField>(gate: &Expression<F>, witness: Witness) -> Option<F> {
+    if gate
+        .mul_terms
+        .iter()
+        .any(|(_, w_l, w_r)| *w_l == witness || *w_r == witness)
+    {
+        return None;
+    }
+
+    let coefficient = gate
+        .linear_combinations
+        .iter()
+        .filter(|(_, w)| *w == witness)
+        .fold(F::zero(), |acc, (scale, _)| acc + *scale);
+
+    (!coefficient.is_zero()).then_some(coefficient)
+}
+
+#[test]
+fn packs_under_utilised_gates() {
+    use acir::FieldElement;
+
+    let (x, y, z, w, a, out) = (
+        Witness(0),
+        Witness(1),
+        Witness(2),
+        Witness(3),
+        Witness(4),
+        Witness(5),
+    );
+    let (t1, t2) = (Witness(6), Witness(7));
+
+    // The CSAT optimiser reduces xy + zw + a - out for width 5 into
+    // xy - t1, zw - t2 and a - out + t1 + t2
+    let mut g1 = Expression::default();
+    g1.term_multiplication(FieldElement::one(), x, y);
+    g1.term_addition(-FieldElement::one(), t1);
+
+    let mut g2 = Expression::default();
+    g2.term_multiplication(FieldElement::one(), z, w);
+    g2.term_addition(-FieldElement::one(), t2);
+
+    let mut g3 = Expression::default();
+    g3.term_addition(FieldElement::one(), a);
+    g3.term_addition(-FieldElement::one(), out);
+    g3.term_addition(FieldElement::one(), t1);
+    g3.term_addition(FieldElement::one(), t2);
+
+    let gates = vec![g1, g2, g3];
+    let packed = WidthPacker::new(5).pack(gates.clone(), t1);
+
+    // The first mul term can be moved into the last gate, but not the second one
+    assert_eq!(packed.len(), 2);
+    assert!(packed.iter().all(|gate| gate.fits_in_one_identity(5)));
+    assert!(!packed.iter().any(|gate| uses_witness(gate, t1)));
+
+    // The merged gate uses all five wires, which costs more than the two gates it replaces
+    let weights = GateWeights::default().with_arithmetic(vec![1, 1, 1, 1, 1, 3]);
+    let packed = WidthPacker::new(5)
+        .with_weights(weights)
+        .pack(gates.clone(), t1);
+    assert_eq!(packed, gates);
+}
@@ -0,0 +1,279 @@
+// Runs the per-opcode work of the compiler passes, and the evaluation of arithmetic
+// opcodes by the solver, on several threads
+//
+// Passes which create intermediate witnesses reduce every opcode as if it were the first
+// to create any, and then shift the intermediate witnesses of each opcode past those of
+// the opcodes before it. This gives the same circuit as reducing the opcodes in order.
+//
+// Without the `std` feature there are no threads, so the work is always done on the
+// current one.
+
+use alloc::vec::Vec;
+#[cfg(any(test, feature = "testing"))]
+use core::cell::Cell;
+#[cfg(feature = "std")]
+use std::thread;
+
+use acir::{circuit::Opcode, native_types::Witness, AcirField};
+#[cfg(any(test, feature = "testing"))]
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use super::inline::rename_witnesses;
+
+// Below this many items, the work is done on the current thread. Tests use a small
+// threshold so that every compiler and solver test goes through the parallel path.
+#[cfg(all(feature = "std", not(test)))]
+const MIN_PARALLEL_ITEMS: usize = 1 << 12;
+#[cfg(test)]
+const MIN_PARALLEL_ITEMS: usize = 2;
+
+#[cfg(feature = "std")]
+fn num_threads(num_items: usize) -> usize {
+    if num_items < MIN_PARALLEL_ITEMS {
+        return 1;
+    }
+    let available = thread::available_parallelism().map_or(1, |threads| threads.get());
+    if cfg!(test) {
+        available.max(4)
+    } else {
+        available
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+thread_local! {
+    // The seed of the order in which `map` applies its function to the items, while
+    // `with_shuffled_order` is running on this thread
+    static SHUFFLE_SEED: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+// Runs `f` with every `map` on this thread applying its function to the items one at a
+// time, in an order shuffled by `seed`. The results are still in the order of the items,
+// so the output of `f` only changes if the work on one item depends on another.
+#[cfg(any(test, feature = "testing"))]
+pub(crate) fn with_shuffled_order<R>(seed: u64, f: impl FnOnce() -> R) -> R {
+    // Restores the previous seed even if `f` panics
+    struct Restore(Option<u64>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SHUFFLE_SEED.with(|cell| cell.set(self.0));
+        }
+    }
+
+    let _restore = Restore(SHUFFLE_SEED.with(|cell| cell.replace(Some(seed))));
+    f()
+}
+
+// Returns true if `map` applies its function to this many items on several threads
+#[cfg(feature = "std")]
+pub(crate) fn runs_in_parallel(num_items: usize) -> bool {
+    num_threads(num_items) > 1
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn runs_in_parallel(_num_items: usize) -> bool {
+    false
+}
+
+// Applies `f` to each item, returning the results in the same order as the items
+pub(crate) fn map<T: Send, U: Send>(items: Vec<T>, f: impl Fn(T) -> U + Sync) -> Vec<U> {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(seed) = SHUFFLE_SEED.with(Cell::get) {
+        return map_shuffled(items, f, seed);
+    }
+    #[cfg(feature = "std")]
+    {
+        let threads = num_threads(items.len());
+        if threads > 1 {
+            return map_on_threads(items, f, threads);
+        }
+    }
+    items.into_iter().map(f).collect()
+}
+
+#[cfg(feature = "std")]
+fn map_on_threads<T: Send, U: Send>(
+    items: Vec<T>,
+    f: impl Fn(T) -> U + Sync,
+    threads: usize,
+) -> Vec<U> {
+    // Split the items into one contiguous chunk per thread, taking them from the back
+    let chunk_size = items.len().div_ceil(threads);
+    let mut items = items;
+    let mut chunks = Vec::with_capacity(threads);
+    while items.len() > chunk_size {
+        chunks.push(items.split_off(items.len() - chunk_size));
+    }
+    chunks.push(items);
+    chunks.reverse();
+
+    let f = &f;
+    thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .into_iter()
+            .map(|chunk| scope.spawn(move || chunk.into_iter().map(f).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    })
+}
+
+#[cfg(any(test, feature = "testing"))]
+fn map_shuffled<T, U>(items: Vec<T>, f: impl Fn(T) -> U, seed: u64) -> Vec<U> {
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.shuffle(&mut StdRng::seed_from_u64(seed));
+
+    let mut items: Vec<Option<T>> = items.into_iter().map(Some).collect();
+    let mut results: Vec<Option<U>> = items.iter().map(|_| None).collect();
+    for index in order {
+        let item = items[index].take().expect("each index is visited once");
+        results[index] = Some(f(item));
+    }
+    results.into_iter().flatten().collect()
+}
+
+// Moves the witnesses of a reduced opcode which are at or above `first_intermediate`
+// up by `offset`
+pub(super) fn shift_intermediates<F: AcirField>(
+    opcode: Opcode<F>,
+    first_intermediate: u32,
+    offset: u32,
+) -> Opcode<F> {
+    if offset == 0 {
+        return opcode;
+    }
+    rename_witnesses(&opcode, &mut |witness| {
+        if witness.0 >= first_intermediate {
+            Witness(witness.0 + offset)
+        } else {
+            witness
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        compiler::{
+            compile, csat_reduce,
+            optimiser::{CSatOptimiser, WidthPacker},
+        },
+        Language,
+    };
+    use acir::{
+        circuit::{opcodes::BlackBoxFuncCall, opcodes::FunctionInput, Circuit, PublicInputs},
+        native_types::Expression,
+        BlackBoxFunc, FieldElement,
+    };
+
+    #[test]
+    fn map_preserves_order() {
+        let items: Vec<u32> = (0..1001).collect();
+        let doubled = map(items, |item| item * 2);
+        assert_eq!(doubled, (0..1001).map(|item| item * 2).collect::<Vec<_>>());
+        assert!(map(Vec::<u32>::new(), |item| item).is_empty());
+    }
+
+    #[test]
+    fn shuffled_map_preserves_order() {
+        let items: Vec<u32> = (0..101).collect();
+        let visited = std::sync::Mutex::new(Vec::new());
+        let doubled = with_shuffled_order(7, || {
+            map(items.clone(), |item| {
+                visited.lock().unwrap().push(item);
+                item * 2
+            })
+        });
+        assert_eq!(doubled, (0..101).map(|item| item * 2).collect::<Vec<_>>());
+        assert_ne!(visited.into_inner().unwrap(), items);
+        assert_eq!(SHUFFLE_SEED.with(Cell::get), None);
+    }
+
+    #[test]
+    fn only_intermediates_are_shifted() {
+        let opcode: Opcode = Opcode::Arithmetic(&Expression::from(&Witness(3)) - &Witness(10));
+        assert_eq!(
+            shift_intermediates(opcode, 10, 5),
+            Opcode::Arithmetic(&Expression::from(&Witness(3)) - &Witness(15))
+        );
+    }
+
+    #[test]
+    fn parallel_compilation_matches_sequential_reduction() {
+        // Wide expressions, which each need intermediate variables at width 3
+        let mut opcodes = Vec::new();
+        for i in 0..20_u32 {
+            let mut expr = Expression::default();
+            for j in 1..=6 {
+                expr.term_addition(FieldElement::from(i128::from(i + j)), Witness(j));
+            }
+            expr.term_multiplication(FieldElement::one(), Witness(1), Witness(i % 6 + 1));
+            opcodes.push(Opcode::Arithmetic(expr));
+        }
+        let circuit = Circuit {
+            current_witness_index: 6,
+            opcodes,
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        };
+
+        let optimiser = CSatOptimiser::new(3);
+        let packer = WidthPacker::new(3);
+        let mut expected = Vec::new();
+        // The fallback pass leaves `current_witness_index` at the first free witness
+        let mut next_witness_index = 8;
+        for opcode in circuit.opcodes.clone() {
+            let expr = opcode.arithmetic().unwrap();
+            let (gates, num_intermediates) =
+                csat_reduce(&optimiser, &packer, expr, next_witness_index);
+            expected.extend(gates.into_iter().map(Opcode::Arithmetic));
+            next_witness_index += num_intermediates;
+        }
+
+        let compiled = compile(circuit, Language::PLONKCSat { width: 3 }, |_| false).unwrap();
+        assert_eq!(compiled.opcodes, expected);
+        assert_eq!(compiled.current_witness_index, next_witness_index - 1);
+    }
+
+    #[test]
+    fn parallel_fallback_numbers_witnesses_in_order() {
+        let range = |witness| -> Opcode {
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                name: BlackBoxFunc::RANGE,
+                inputs: vec![FunctionInput {
+                    witness: Witness(witness),
+                    num_bits: 4,
+                }],
+                outputs: Vec::new(),
+            })
+        };
+        let circuit = Circuit {
+            current_witness_index: 3,
+            opcodes: vec![range(1), range(2), range(3)],
+            public_inputs: PublicInputs::default(),
+            return_values: PublicInputs::default(),
+        };
+        let lowered = crate::compiler::fallback::fallback(circuit.clone(), |_| false).unwrap();
+
+        let mut expected = Vec::new();
+        let mut witness_idx = 4;
+        for opcode in &circuit.opcodes {
+            let Opcode::BlackBoxFuncCall(func_call) = opcode else {
+                unreachable!()
+            };
+            let (updated_witness_index, opcodes) =
+                crate::compiler::fallback::opcode_fallback(func_call, witness_idx).unwrap();
+            expected.extend(opcodes);
+            witness_idx = updated_witness_index;
+        }
+        assert_eq!(lowered.opcodes, expected);
+        assert_eq!(lowered.current_witness_index, witness_idx);
+    }
+}
@@ -0,0 +1,290 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{borrow::ToOwned, vec::Vec};
+
+use acir::{
+    circuit::{Circuit, Opcode},
+    native_types::{Expression, Witness},
+    AcirField, FieldElement,
+};
+
+use super::CompileError;
+use crate::{pwg::witness_to_value, OpcodeResolutionError, WitnessMap};
+
+/// A row of a sparse matrix, as pairs of a variable index and its coefficient
+pub type SparseRow<F = FieldElement> = Vec<(usize, F)>;
+
+/// A rank-1 constraint system, where each constraint is `(A·z) * (B·z) = C·z`.
+///
+/// The assignment vector `z` is the constant one, followed by the public inputs, the
+/// other witnesses and finally the auxiliary variables which hold extra products.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct R1cs<F = FieldElement> {
+    pub a: Vec<SparseRow<F>>,
+    pub b: Vec<SparseRow<F>>,
+    pub c: Vec<SparseRow<F>>,
+    /// The number of variables, including the constant one
+    pub num_vars: usize,
+    /// The number of public inputs, which are the variables from 1 to `num_public`
+    pub num_public: usize,
+    /// The witness assigned to each variable from 1, up to the auxiliary variables
+    pub witnesses: Vec<Witness>,
+    /// The factors of each auxiliary variable, in order
+    pub products: Vec<(usize, usize)>,
+}
+
+impl<F: AcirField> R1cs<F> {
+    pub fn num_constraints(&self) -> usize {
+        self.a.len()
+    }
+
+    /// Returns the assignment vector `z` for a solved witness map
+    pub fn assignment(
+        &self,
+        witness_values: &WitnessMap<F>,
+    ) -> Result<Vec<F>, OpcodeResolutionError<F>> {
+        let mut assignment = Vec::with_capacity(self.num_vars);
+        assignment.push(F::one());
+        for witness in &self.witnesses {
+            assignment.push(*witness_to_value(witness_values, *witness)?);
+        }
+        for (left, right) in &self.products {
+            assignment.push(assignment[*left] * assignment[*right]);
+        }
+        Ok(assignment)
+    }
+
+    /// Returns whether every constraint is satisfied by the assignment vector `z`
+    pub fn is_satisfied(&self, assignment: &[F]) -> bool {
+        let dot = |row: &SparseRow<F>| {
+            row.iter().fold(F::zero(), |acc, (var, coeff)| {
+                acc + *coeff * assignment[*var]
+            })
+        };
+        (0..self.num_constraints()).all(|i| dot(&self.a[i]) * dot(&self.b[i]) == dot(&self.c[i]))
+    }
+}
+
+/// Converts a circuit into sparse R1CS matrices.
+///
+/// The circuit must have been lowered to arithmetic opcodes of degree 2, for example by
+/// `compile` and `lower_directives`. Directives are skipped, as they do not add constraints.
+/// An expression with several multiplication terms gets an auxiliary variable for each
+/// product after the first.
+pub fn to_r1cs<F: AcirField>(circuit: &Circuit<F>) -> Result<R1cs<F>, CompileError> {
+    let mut expressions = Vec::new();
+    for opcode in &circuit.opcodes {
+        match opcode {
+            Opcode::Arithmetic(expr) => expressions.push(expr),
+            Opcode::Directive(_) => {}
+            Opcode::BlackBoxFuncCall(_) | Opcode::Call { .. } => {
+                return Err(CompileError::NonArithmeticOpcode(opcode.name().to_owned()))
+            }
+        }
+    }
+
+    // Public inputs come first, in the order of the circuit
+    let mut witnesses: Vec<Witness> = Vec::new();
+    for witness in &circuit.public_inputs.0 {
+        if !witnesses.contains(witness) {
+            witnesses.push(*witness);
+        }
+    }
+    let num_public = witnesses.len();
+
+    let mut private: BTreeSet<Witness> = (1..=circuit.current_witness_index).map(Witness).collect();
+    for expr in &expressions {
+        private.extend(expr.linear_combinations.iter().map(|(_, w)| *w));
+        private.extend(expr.mul_terms.iter().flat_map(|(_, l, r)| [*l, *r]));
+    }
+    witnesses.extend(
+        private
+            .into_iter()
+            .filter(|w| !circuit.public_inputs.contains(w.as_usize())),
+    );
+
+    let variables: BTreeMap<Witness, usize> = witnesses
+        .iter()
+        .enumerate()
+        .map(|(index, witness)| (*witness, index + 1))
+        .collect();
+
+    let mut builder = Builder {
+        r1cs: R1cs {
+            a: Vec::new(),
+            b: Vec::new(),
+            c: Vec::new(),
+            num_vars: witnesses.len() + 1,
+            num_public,
+            witnesses,
+            products: Vec::new(),
+        },
+        variables,
+    };
+    for expr in expressions {
+        builder.add_expression(expr);
+    }
+    Ok(builder.r1cs)
+}
+
+struct Builder<F> {
+    r1cs: R1cs<F>,
+    variables: BTreeMap<Witness, usize>,
+}
+
+impl<F: AcirField> Builder<F> {
+    fn add_constraint(
+        &mut self,
+        a: BTreeMap<usize, F>,
+        b: BTreeMap<usize, F>,
+        c: BTreeMap<usize, F>,
+    ) {
+        let to_row = |terms: BTreeMap<usize, F>| {
+            terms
+                .into_iter()
+                .filter(|(_, coeff)| !coeff.is_zero())
+                .collect()
+        };
+        self.r1cs.a.push(to_row(a));
+        self.r1cs.b.push(to_row(b));
+        self.r1cs.c.push(to_row(c));
+    }
+
+    // Returns a new variable which is constrained to be the product of two others
+    fn add_product(&mut self, left: usize, right: usize) -> usize {
+        let product = self.r1cs.num_vars;
+        self.r1cs.num_vars += 1;
+        self.r1cs.products.push((left, right));
+        self.add_constraint(
+            BTreeMap::from([(left, F::one())]),
+            BTreeMap::from([(right, F::one())]),
+            BTreeMap::from([(product, F::one())]),
+        );
+        product
+    }
+
+    fn add_expression(&mut self, expr: &Expression<F>) {
+        // The linear terms and constant, with every product after the first replaced by
+        // an auxiliary variable
+        let mut linear: BTreeMap<usize, F> = BTreeMap::new();
+        let mut add_term = |var: usize, coeff: F| {
+            let entry = linear.entry(var).or_insert_with(F::zero);
+            *entry += coeff;
+        };
+        add_term(0, expr.q_c);
+        for (coeff, witness) in &expr.linear_combinations {
+            add_term(self.variables[witness], *coeff);
+        }
+
+        let mut mul_terms = expr.mul_terms.iter();
+        let first_product = mul_terms.next();
+        for (coeff, left, right) in mul_terms {
+            let product = self.add_product(self.variables[left], self.variables[right]);
+            add_term(product, *coeff);
+        }
+
+        match first_product {
+            // q·l·r + L = 0 is (q·l) * r = -L
+            Some((coeff, left, right)) => {
+                let negated = linear
+                    .into_iter()
+                    .map(|(var, coeff)| (var, -coeff))
+                    .collect();
+                self.add_constraint(
+                    BTreeMap::from([(self.variables[left], *coeff)]),
+                    BTreeMap::from([(self.variables[right], F::one())]),
+                    negated,
+                );
+            }
+            // L = 0 is 1 * L = 0
+            None => self.add_constraint(BTreeMap::from([(0, F::one())]), linear, BTreeMap::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::circuit::{directives::Directive, opcodes::BlackBoxFuncCall, PublicInputs};
+    use acir::BlackBoxFunc;
+
+    // x * y + 2 * z * z - o = 0, o - x - 3 = 0, with o public
+    fn circuit() -> Circuit {
+        let x = Witness(1);
+        let y = Witness(2);
+        let z = Witness(3);
+        let o = Witness(4);
+        Circuit {
+            current_witness_index: 4,
+            opcodes: vec![
+                Opcode::Arithmetic(Expression {
+                    mul_terms: vec![
+                        (FieldElement::one(), x, y),
+                        (FieldElement::from(2_i128), z, z),
+                    ],
+                    linear_combinations: vec![(-FieldElement::one(), o)],
+                    q_c: FieldElement::zero(),
+                }),
+                Opcode::Arithmetic(Expression {
+                    mul_terms: Vec::new(),
+                    linear_combinations: vec![(FieldElement::one(), o), (-FieldElement::one(), x)],
+                    q_c: -FieldElement::from(3_i128),
+                }),
+                Opcode::Directive(Directive::Invert {
+                    x: Witness(1),
+                    result: Witness(5),
+                }),
+            ],
+            public_inputs: PublicInputs(vec![o]),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    fn witness(x: i128, y: i128, z: i128, o: i128) -> WitnessMap {
+        WitnessMap::from([
+            (Witness(1), FieldElement::from(x)),
+            (Witness(2), FieldElement::from(y)),
+            (Witness(3), FieldElement::from(z)),
+            (Witness(4), FieldElement::from(o)),
+        ])
+    }
+
+    #[test]
+    fn constraints_match_the_circuit() {
+        let r1cs = to_r1cs(&circuit()).unwrap();
+
+        // One constraint per opcode, and one for the product z * z
+        assert_eq!(r1cs.num_constraints(), 3);
+        assert_eq!(r1cs.num_public, 1);
+        assert_eq!(
+            r1cs.witnesses,
+            vec![Witness(4), Witness(1), Witness(2), Witness(3)]
+        );
+        assert_eq!(r1cs.products, vec![(4, 4)]);
+        assert_eq!(r1cs.num_vars, 6);
+
+        // 5 * 2 + 2 * 3 * 3 = 28, but o = x + 3
+        let satisfying = r1cs.assignment(&witness(5, 2, 3, 8)).unwrap();
+        assert_eq!(satisfying.len(), r1cs.num_vars);
+        assert!(!r1cs.is_satisfied(&satisfying));
+
+        // 1 * 2 + 2 * 1 * 1 = 4 = 1 + 3
+        let satisfying = r1cs.assignment(&witness(1, 2, 1, 4)).unwrap();
+        assert!(r1cs.is_satisfied(&satisfying));
+    }
+
+    #[test]
+    fn black_box_functions_must_be_lowered() {
+        let mut circuit = circuit();
+        circuit
+            .opcodes
+            .push(Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                name: BlackBoxFunc::SHA256,
+                inputs: Vec::new(),
+                outputs: Vec::new(),
+            }));
+        assert_eq!(
+            to_r1cs(&circuit),
+            Err(CompileError::NonArithmeticOpcode("sha256".to_owned()))
+        );
+    }
+}
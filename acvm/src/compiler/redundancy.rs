@@ -0,0 +1,249 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use acir::{
+    circuit::{Circuit, Opcode},
+    native_types::{Expression, Witness},
+    AcirField, BlackBoxFunc,
+};
+
+/// The reason that a constraint is redundant, or can never be satisfied
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Redundancy {
+    /// The expression is zero, so the constraint always holds
+    AlwaysSatisfied,
+    /// The expression is a constant which is not zero, so the circuit cannot be satisfied
+    NeverSatisfied,
+    /// The opcode applies the same constraint as the earlier opcode at this index, up to
+    /// a constant factor for arithmetic opcodes
+    Duplicate(usize),
+    /// The range constraint is implied by the range constraint with fewer bits on the same
+    /// witness at this index
+    ImpliedRange(usize),
+}
+
+/// An opcode which does not constrain the circuit any further than the other opcodes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RedundantConstraint {
+    pub opcode_index: usize,
+    pub redundancy: Redundancy,
+}
+
+impl core::fmt::Display for RedundantConstraint {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let index = self.opcode_index;
+        match self.redundancy {
+            Redundancy::AlwaysSatisfied => write!(f, "opcode {index} is always satisfied"),
+            Redundancy::NeverSatisfied => {
+                write!(
+                    f,
+                    "opcode {index} constrains a non-zero constant to be zero"
+                )
+            }
+            Redundancy::Duplicate(other) => write!(f, "opcode {index} duplicates opcode {other}"),
+            Redundancy::ImpliedRange(other) => {
+                write!(
+                    f,
+                    "opcode {index} is implied by the range constraint of opcode {other}"
+                )
+            }
+        }
+    }
+}
+
+/// Returns the opcodes which are implied by the other opcodes of the circuit, or which
+/// constrain constants, in the order of the opcodes.
+///
+/// The following are found:
+/// - arithmetic opcodes without witnesses, which either always hold or never hold
+/// - arithmetic opcodes which are a multiple of an earlier one
+/// - black box function calls and function calls which are the same as an earlier one
+/// - RANGE calls on a witness which has a RANGE call with fewer bits, or with as many
+///   bits earlier in the circuit
+///
+/// Directives are not constraints, so they are never reported.
+pub fn find_redundant_constraints<F: AcirField>(circuit: &Circuit<F>) -> Vec<RedundantConstraint> {
+    let mut redundant = Vec::new();
+    // The first opcode with each constraint, keyed by its serialized form
+    let mut constraints: BTreeMap<Vec<u8>, usize> = BTreeMap::new();
+    // The first of the range constraints with the fewest bits, for each witness
+    let mut ranges: BTreeMap<Witness, (u32, usize)> = BTreeMap::new();
+
+    let mut check_duplicate = |opcode_index: usize, key: Vec<u8>| {
+        let first = *constraints.entry(key).or_insert(opcode_index);
+        (first != opcode_index).then_some(RedundantConstraint {
+            opcode_index,
+            redundancy: Redundancy::Duplicate(first),
+        })
+    };
+    for (opcode_index, opcode) in circuit.opcodes.iter().enumerate() {
+        match opcode {
+            Opcode::Arithmetic(expr) => {
+                let expr = monic(expr);
+                if expr.is_const() {
+                    let redundancy = if expr.q_c.is_zero() {
+                        Redundancy::AlwaysSatisfied
+                    } else {
+                        Redundancy::NeverSatisfied
+                    };
+                    redundant.push(RedundantConstraint {
+                        opcode_index,
+                        redundancy,
+                    });
+                } else {
+                    redundant.extend(check_duplicate(
+                        opcode_index,
+                        serialize(&Opcode::Arithmetic(expr)),
+                    ));
+                }
+            }
+            Opcode::BlackBoxFuncCall(call) if call.name == BlackBoxFunc::RANGE => {
+                if let [input] = call.inputs.as_slice() {
+                    let range = ranges
+                        .entry(input.witness)
+                        .or_insert((input.num_bits, opcode_index));
+                    if input.num_bits < range.0 {
+                        *range = (input.num_bits, opcode_index);
+                    }
+                }
+            }
+            Opcode::BlackBoxFuncCall(_) | Opcode::Call { .. } => {
+                redundant.extend(check_duplicate(opcode_index, serialize(opcode)));
+            }
+            Opcode::Directive(_) => {}
+        }
+    }
+
+    // Each range constraint is compared against the narrowest one on the same witness,
+    // which is only known once every opcode has been seen
+    for (opcode_index, opcode) in circuit.opcodes.iter().enumerate() {
+        let Opcode::BlackBoxFuncCall(call) = opcode else {
+            continue;
+        };
+        let [input] = call.inputs.as_slice() else {
+            continue;
+        };
+        if call.name != BlackBoxFunc::RANGE {
+            continue;
+        }
+        let (num_bits, narrowest) = ranges[&input.witness];
+        if narrowest == opcode_index {
+            continue;
+        }
+        let redundancy = if input.num_bits == num_bits {
+            Redundancy::Duplicate(narrowest)
+        } else {
+            Redundancy::ImpliedRange(narrowest)
+        };
+        redundant.push(RedundantConstraint {
+            opcode_index,
+            redundancy,
+        });
+    }
+
+    redundant.sort_by_key(|constraint| constraint.opcode_index);
+    redundant
+}
+
+// The normal form of the expression, scaled so that its leading coefficient is one, which
+// is the same for every expression which applies the same constraint
+fn monic<F: AcirField>(expr: &Expression<F>) -> Expression<F> {
+    let expr = expr.normalize();
+    let leading_coefficient = expr
+        .mul_terms
+        .first()
+        .map(|(scale, _, _)| *scale)
+        .or_else(|| expr.linear_combinations.first().map(|(scale, _)| *scale));
+    match leading_coefficient {
+        Some(scale) => expr.scale(scale.inverse()),
+        None => expr,
+    }
+}
+
+fn serialize<F: AcirField>(opcode: &Opcode<F>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    opcode
+        .write(&mut bytes)
+        .expect("infallible: writing to a Vec cannot fail");
+    bytes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{
+        circuit::{
+            directives::Directive,
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            PublicInputs,
+        },
+        FieldElement,
+    };
+
+    fn black_box(name: BlackBoxFunc, inputs: &[(Witness, u32)], outputs: Vec<Witness>) -> Opcode {
+        Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+            name,
+            inputs: inputs
+                .iter()
+                .map(|(witness, num_bits)| FunctionInput {
+                    witness: *witness,
+                    num_bits: *num_bits,
+                })
+                .collect(),
+            outputs,
+        })
+    }
+
+    #[test]
+    fn redundant_constraints_are_found() {
+        let (x, y, z, o) = (Witness(1), Witness(2), Witness(3), Witness(4));
+        let product = Expression {
+            mul_terms: vec![(FieldElement::one(), x, y)],
+            linear_combinations: vec![(-FieldElement::one(), z)],
+            q_c: FieldElement::zero(),
+        };
+        let circuit = Circuit {
+            current_witness_index: 4,
+            opcodes: vec![
+                Opcode::Arithmetic(product.clone()),
+                // The same constraint, scaled and with the witnesses in the other order
+                Opcode::Arithmetic(Expression {
+                    mul_terms: vec![(FieldElement::from(-2_i128), y, x)],
+                    linear_combinations: vec![(FieldElement::from(2_i128), z)],
+                    q_c: FieldElement::zero(),
+                }),
+                Opcode::Arithmetic(&product - &product),
+                Opcode::Arithmetic(Expression::from_field(FieldElement::from(5_i128))),
+                black_box(BlackBoxFunc::RANGE, &[(x, 16)], Vec::new()),
+                black_box(BlackBoxFunc::AND, &[(x, 8), (y, 8)], vec![o]),
+                black_box(BlackBoxFunc::RANGE, &[(x, 8)], Vec::new()),
+                black_box(BlackBoxFunc::AND, &[(x, 8), (y, 8)], vec![o]),
+                black_box(BlackBoxFunc::RANGE, &[(x, 8)], Vec::new()),
+                black_box(BlackBoxFunc::RANGE, &[(y, 8)], Vec::new()),
+                Opcode::Directive(Directive::Invert { x, result: z }),
+                Opcode::Directive(Directive::Invert { x, result: z }),
+            ],
+            public_inputs: PublicInputs(vec![x]),
+            return_values: PublicInputs::default(),
+        };
+
+        let redundant = find_redundant_constraints(&circuit);
+        let expected = [
+            (1, Redundancy::Duplicate(0)),
+            (2, Redundancy::AlwaysSatisfied),
+            (3, Redundancy::NeverSatisfied),
+            (4, Redundancy::ImpliedRange(6)),
+            (7, Redundancy::Duplicate(5)),
+            (8, Redundancy::Duplicate(6)),
+        ]
+        .map(|(opcode_index, redundancy)| RedundantConstraint {
+            opcode_index,
+            redundancy,
+        });
+        assert_eq!(redundant, expected);
+        assert_eq!(
+            redundant[3].to_string(),
+            "opcode 4 is implied by the range constraint of opcode 6"
+        );
+    }
+}
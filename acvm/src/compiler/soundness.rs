@@ -0,0 +1,390 @@
+// A check that compiling a circuit did not weaken its range and boolean constraints
+//
+// The optimisers rewrite arithmetic opcodes and the fallbacks replace RANGE calls with
+// bit decompositions, so a bug in either could silently drop a constraint that the
+// circuit's soundness depends on. Every RANGE call and boolean constraint of the original
+// circuit must therefore be implied by the compiled circuit, on the same witness, as the
+// compiler does not renumber the witnesses of the original circuit.
+//
+// The compiled circuit is checked by inferring an interval of integers for the witnesses
+// which it bounds:
+//
+// - a RANGE call bounds its input to `[0, 2^num_bits - 1]`
+// - a boolean constraint `c·(x·x - x) = 0` bounds `x` to `[0, 1]`
+// - a linear opcode in which a witness has a coefficient of one or minus one bounds that
+//   witness by the sum of the intervals of the other terms, if they are all bounded and
+//   the sum cannot wrap around the modulus
+//
+// Bounds are propagated until no interval gets narrower, so that decompositions which the
+// CSAT optimiser splits into several gates with intermediate variables are followed.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::{vec, vec::Vec};
+
+use acir::{
+    circuit::{Circuit, Opcode},
+    native_types::{Expression, Witness},
+    AcirField, BlackBoxFunc,
+};
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Signed, Zero};
+
+use super::CompileError;
+use crate::{metrics, trace};
+
+/// A constraint of the original circuit that the value of a witness fits into `num_bits`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct RequiredRange {
+    opcode_index: usize,
+    witness: Witness,
+    num_bits: u32,
+}
+
+// An interval of integers, which the value of a witness is congruent to an element of
+type Interval = (BigInt, BigInt);
+
+// An opcode `Σ coefficient·witness + constant = 0` with signed coefficients
+struct LinearGate {
+    terms: Vec<(BigInt, Witness)>,
+    constant: BigInt,
+}
+
+/// Returns an error if a RANGE call or boolean constraint of `acir` is not implied by
+/// the circuit which it was compiled to
+pub fn check_soundness<F: AcirField>(
+    acir: &Circuit<F>,
+    compiled: &Circuit<F>,
+) -> Result<(), CompileError> {
+    check_ranges(&required_ranges(acir), compiled)
+}
+
+/// Returns the range and boolean constraints of a circuit. Ranges which are not below
+/// the field size are skipped, as they do not constrain the witness.
+pub(crate) fn required_ranges<F: AcirField>(circuit: &Circuit<F>) -> Vec<RequiredRange> {
+    let mut required = Vec::new();
+    for (opcode_index, opcode) in circuit.opcodes.iter().enumerate() {
+        match opcode {
+            Opcode::BlackBoxFuncCall(call) if call.name == BlackBoxFunc::RANGE => {
+                required.extend(
+                    call.inputs
+                        .iter()
+                        .filter(|input| input.num_bits < F::max_num_bits())
+                        .map(|input| RequiredRange {
+                            opcode_index,
+                            witness: input.witness,
+                            num_bits: input.num_bits,
+                        }),
+                );
+            }
+            Opcode::Arithmetic(expr) => required.extend(boolean_witness(&expr.normalize()).map(
+                |witness| RequiredRange {
+                    opcode_index,
+                    witness,
+                    num_bits: 1,
+                },
+            )),
+            _ => {}
+        }
+    }
+    required
+}
+
+pub(crate) fn check_ranges<F: AcirField>(
+    required: &[RequiredRange],
+    compiled: &Circuit<F>,
+) -> Result<(), CompileError> {
+    let _span = trace::span!(DEBUG, "soundness", num_ranges = required.len());
+    let _pass = metrics::pass("soundness");
+    if required.is_empty() {
+        return Ok(());
+    }
+
+    let bounds = infer_bounds(compiled);
+    for range in required {
+        let max = (BigInt::one() << range.num_bits) - 1;
+        let implied = bounds
+            .get(&range.witness)
+            .is_some_and(|(lo, hi)| !lo.is_negative() && *hi <= max);
+        if !implied {
+            return Err(CompileError::WeakenedRange {
+                opcode_index: range.opcode_index,
+                witness: range.witness,
+                num_bits: range.num_bits,
+            });
+        }
+    }
+    Ok(())
+}
+
+// Returns `x` if the normalized expression is `c·(x·x - x)`
+fn boolean_witness<F: AcirField>(expr: &Expression<F>) -> Option<Witness> {
+    match (
+        expr.mul_terms.as_slice(),
+        expr.linear_combinations.as_slice(),
+    ) {
+        ([(q_m, x, y)], [(q_l, z)]) if x == y && x == z && *q_m == -*q_l && expr.q_c.is_zero() => {
+            Some(*x)
+        }
+        _ => None,
+    }
+}
+
+fn infer_bounds<F: AcirField>(circuit: &Circuit<F>) -> BTreeMap<Witness, Interval> {
+    let modulus = BigInt::from(F::modulus());
+    let mut bounds: BTreeMap<Witness, Interval> = BTreeMap::new();
+    let mut gates = Vec::new();
+    for opcode in &circuit.opcodes {
+        match opcode {
+            Opcode::BlackBoxFuncCall(call) if call.name == BlackBoxFunc::RANGE => {
+                for input in &call.inputs {
+                    let max = (BigInt::one() << input.num_bits) - 1;
+                    narrow(&mut bounds, input.witness, (BigInt::zero(), max));
+                }
+            }
+            Opcode::Arithmetic(expr) => {
+                let expr = expr.normalize();
+                if let Some(witness) = boolean_witness(&expr) {
+                    narrow(&mut bounds, witness, (BigInt::zero(), BigInt::one()));
+                } else if expr.mul_terms.is_empty() && !expr.linear_combinations.is_empty() {
+                    gates.push(LinearGate {
+                        terms: expr
+                            .linear_combinations
+                            .iter()
+                            .map(|(q, w)| (signed(*q, &modulus), *w))
+                            .collect(),
+                        constant: signed(expr.q_c, &modulus),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut users: BTreeMap<Witness, Vec<usize>> = BTreeMap::new();
+    for (index, gate) in gates.iter().enumerate() {
+        for (_, witness) in &gate.terms {
+            users.entry(*witness).or_default().push(index);
+        }
+    }
+
+    // Intervals only get narrower, but a cycle of gates could narrow them by one at a
+    // time, so the work is bounded. Stopping early only loses bounds.
+    let mut remaining_steps = 16 * gates.len();
+    let mut queue: VecDeque<usize> = (0..gates.len()).collect();
+    let mut queued = vec![true; gates.len()];
+    while let Some(index) = queue.pop_front() {
+        if remaining_steps == 0 {
+            break;
+        }
+        remaining_steps -= 1;
+        queued[index] = false;
+
+        for witness in propagate(&gates[index], &mut bounds, &modulus) {
+            for user in &users[&witness] {
+                if !queued[*user] {
+                    queued[*user] = true;
+                    queue.push_back(*user);
+                }
+            }
+        }
+    }
+    bounds
+}
+
+// Bounds the witnesses of the gate with a coefficient of one or minus one by the other
+// terms, and returns the witnesses whose interval got narrower
+fn propagate(
+    gate: &LinearGate,
+    bounds: &mut BTreeMap<Witness, Interval>,
+    modulus: &BigInt,
+) -> Vec<Witness> {
+    let term_interval = |coefficient: &BigInt, (lo, hi): &Interval| {
+        let (a, b) = (coefficient * lo, coefficient * hi);
+        if a <= b {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    };
+
+    let mut unbounded = Vec::new();
+    let (mut sum_lo, mut sum_hi) = (gate.constant.clone(), gate.constant.clone());
+    for (coefficient, witness) in &gate.terms {
+        match bounds.get(witness) {
+            Some(interval) => {
+                let (lo, hi) = term_interval(coefficient, interval);
+                sum_lo += lo;
+                sum_hi += hi;
+            }
+            None => unbounded.push(*witness),
+        }
+    }
+    let candidates: Vec<&(BigInt, Witness)> = match unbounded.as_slice() {
+        [] => gate.terms.iter().collect(),
+        [witness] => gate.terms.iter().filter(|(_, w)| w == witness).collect(),
+        _ => return Vec::new(),
+    };
+
+    let mut narrowed = Vec::new();
+    for (coefficient, witness) in candidates {
+        if !coefficient.magnitude().is_one() {
+            continue;
+        }
+        // The sum of the other terms
+        let (mut lo, mut hi) = (sum_lo.clone(), sum_hi.clone());
+        if let Some(interval) = bounds.get(witness) {
+            let (own_lo, own_hi) = term_interval(coefficient, interval);
+            lo -= own_lo;
+            hi -= own_hi;
+        }
+        if &(&hi - &lo) >= modulus {
+            continue;
+        }
+        // coefficient·witness = -sum
+        let interval = if coefficient.is_positive() {
+            (-hi, -lo)
+        } else {
+            (lo, hi)
+        };
+        if narrow(bounds, *witness, interval) {
+            narrowed.push(*witness);
+        }
+    }
+    narrowed
+}
+
+// Intersects the interval of the witness with `interval`, and returns whether it got narrower
+fn narrow(bounds: &mut BTreeMap<Witness, Interval>, witness: Witness, interval: Interval) -> bool {
+    match bounds.get_mut(&witness) {
+        None => {
+            bounds.insert(witness, interval);
+            true
+        }
+        Some((lo, hi)) => {
+            let mut narrowed = false;
+            if interval.0 > *lo {
+                *lo = interval.0;
+                narrowed = true;
+            }
+            if interval.1 < *hi {
+                *hi = interval.1;
+                narrowed = true;
+            }
+            narrowed
+        }
+    }
+}
+
+// The integer of smallest magnitude which is congruent to the field element
+fn signed<F: AcirField>(value: F, modulus: &BigInt) -> BigInt {
+    let value = BigInt::from(BigUint::from_bytes_be(&value.to_be_bytes()));
+    if &value * 2 > *modulus {
+        value - modulus
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{compiler::compile, Language};
+    use acir::circuit::{
+        opcodes::{BlackBoxFuncCall, FunctionInput},
+        PublicInputs,
+    };
+    use acir::FieldElement;
+
+    fn range(witness: Witness, num_bits: u32) -> Opcode {
+        Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+            name: BlackBoxFunc::RANGE,
+            inputs: vec![FunctionInput { witness, num_bits }],
+            outputs: Vec::new(),
+        })
+    }
+
+    fn boolean(witness: Witness) -> Opcode {
+        Opcode::Arithmetic(Expression {
+            mul_terms: vec![(-FieldElement::one(), witness, witness)],
+            linear_combinations: vec![(FieldElement::one(), witness)],
+            q_c: FieldElement::zero(),
+        })
+    }
+
+    fn circuit(opcodes: Vec<Opcode>) -> Circuit {
+        Circuit {
+            current_witness_index: 10,
+            opcodes,
+            public_inputs: PublicInputs::default(),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    #[test]
+    fn fallbacks_keep_ranges() {
+        let acir = circuit(vec![range(Witness(1), 32), boolean(Witness(2))]);
+        for language in [
+            Language::R1CS,
+            Language::PLONKCSat { width: 3 },
+            Language::PLONKCSat { width: 4 },
+        ] {
+            // RANGE is replaced by a bit decomposition, which the CSAT optimiser splits
+            let compiled = compile(acir.clone(), language.clone(), |_| false).unwrap();
+            assert_eq!(check_soundness(&acir, &compiled), Ok(()));
+
+            let compiled = compile(acir.clone(), language, |_| true).unwrap();
+            assert_eq!(check_soundness(&acir, &compiled), Ok(()));
+        }
+    }
+
+    #[test]
+    fn weakened_ranges_are_rejected() {
+        let acir = circuit(vec![range(Witness(1), 2), boolean(Witness(3))]);
+        let weakened = |witness, num_bits| CompileError::WeakenedRange {
+            opcode_index: 0,
+            witness,
+            num_bits,
+        };
+
+        // x = b_0 + 2·t and t = b_1, where b_0 and b_1 are boolean
+        let (x, b_0, b_1, t) = (Witness(1), Witness(4), Witness(5), Witness(6));
+        let decomposition = |b_1_is_boolean: bool| {
+            let mut opcodes = vec![
+                boolean(Witness(3)),
+                boolean(b_0),
+                Opcode::Arithmetic(Expression {
+                    mul_terms: Vec::new(),
+                    linear_combinations: vec![
+                        (FieldElement::one(), x),
+                        (-FieldElement::one(), b_0),
+                        (FieldElement::from(-2_i128), t),
+                    ],
+                    q_c: FieldElement::zero(),
+                }),
+                Opcode::Arithmetic(&Expression::from(t) - &Expression::from(b_1)),
+            ];
+            if b_1_is_boolean {
+                opcodes.push(boolean(b_1));
+            }
+            circuit(opcodes)
+        };
+        assert_eq!(check_soundness(&acir, &decomposition(true)), Ok(()));
+        assert_eq!(
+            check_soundness(&acir, &decomposition(false)),
+            Err(weakened(x, 2))
+        );
+
+        assert_eq!(
+            check_soundness(&acir, &circuit(vec![range(x, 3), boolean(Witness(3))])),
+            Err(weakened(x, 2))
+        );
+        assert_eq!(
+            check_soundness(&acir, &circuit(vec![range(x, 2)])),
+            Err(CompileError::WeakenedRange {
+                opcode_index: 1,
+                witness: Witness(3),
+                num_bits: 1,
+            })
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use alloc::collections::BTreeMap;
+use alloc::{borrow::ToOwned, string::String};
+
+use acir::{
+    circuit::{Circuit, Opcode},
+    AcirField,
+};
+use serde::{Deserialize, Serialize};
+
+/// A summary of the opcodes and witnesses contained in a circuit.
+///
+/// This is mostly useful for understanding what a frontend emitted
+/// or how much a compiler pass changed a circuit.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitStats {
+    /// Total number of opcodes in the circuit
+    pub num_opcodes: usize,
+    /// Number of arithmetic opcodes
+    pub num_arithmetic: usize,
+    /// Number of calls for each black box function, keyed by function name
+    pub blackbox_calls: BTreeMap<String, usize>,
+    /// Number of occurrences of each directive, keyed by directive name
+    pub directives: BTreeMap<String, usize>,
+    /// Number of calls to other functions of the program
+    pub num_calls: usize,
+    /// The largest number of distinct witnesses used by a single arithmetic opcode
+    pub max_expression_width: usize,
+    /// Number of witnesses in the circuit, ie `current_witness_index + 1`
+    pub num_witnesses: u32,
+    /// Number of public inputs
+    pub num_public_inputs: usize,
+}
+
+/// Collects statistics about the given circuit
+pub fn stats<F: AcirField>(circuit: &Circuit<F>) -> CircuitStats {
+    let mut stats = CircuitStats {
+        num_opcodes: circuit.opcodes.len(),
+        num_witnesses: circuit.num_vars(),
+        num_public_inputs: circuit.public_inputs.0.len(),
+        ..Default::default()
+    };
+
+    for opcode in &circuit.opcodes {
+        match opcode {
+            Opcode::Arithmetic(expr) => {
+                stats.num_arithmetic += 1;
+
+                stats.max_expression_width = stats.max_expression_width.max(expr.witnesses().len());
+            }
+            Opcode::BlackBoxFuncCall(func_call) => {
+                *stats
+                    .blackbox_calls
+                    .entry(func_call.name.name().to_owned())
+                    .or_default() += 1;
+            }
+            Opcode::Directive(directive) => {
+                *stats
+                    .directives
+                    .entry(directive.name().to_owned())
+                    .or_default() += 1;
+            }
+            Opcode::Call { .. } => stats.num_calls += 1,
+        }
+    }
+
+    stats
+}
+
+#[test]
+fn counts_opcodes() {
+    use acir::{
+        circuit::{
+            directives::Directive,
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            PublicInputs,
+        },
+        native_types::{Expression, Witness},
+        BlackBoxFunc, FieldElement,
+    };
+
+    let mut expr = Expression::default();
+    expr.term_multiplication(FieldElement::one(), Witness(1), Witness(2));
+    expr.term_addition(FieldElement::one(), Witness(1));
+    expr.term_addition(-FieldElement::one(), Witness(3));
+
+    let range = Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+        name: BlackBoxFunc::RANGE,
+        inputs: vec![FunctionInput {
+            witness: Witness(3),
+            num_bits: 8,
+        }],
+        outputs: vec![],
+    });
+
+    let circuit = Circuit {
+        current_witness_index: 4,
+        opcodes: vec![
+            Opcode::Arithmetic(expr),
+            range.clone(),
+            range,
+            Opcode::Directive(Directive::Invert {
+                x: Witness(3),
+                result: Witness(4),
+            }),
+        ],
+        public_inputs: PublicInputs(vec![Witness(1)]),
+        return_values: PublicInputs::default(),
+    };
+
+    let stats = stats(&circuit);
+    assert_eq!(stats.num_opcodes, 4);
+    assert_eq!(stats.num_arithmetic, 1);
+    assert_eq!(stats.blackbox_calls.get("range"), Some(&2));
+    assert_eq!(stats.directives.get("invert"), Some(&1));
+    assert_eq!(stats.max_expression_width, 3);
+    assert_eq!(stats.num_witnesses, 5);
+    assert_eq!(stats.num_public_inputs, 1);
+}
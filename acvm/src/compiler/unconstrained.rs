@@ -0,0 +1,203 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{string::String, string::ToString, vec::Vec};
+
+use acir::{
+    circuit::{directives::Directive, Circuit, Opcode},
+    native_types::Witness,
+    AcirField, FieldElement,
+};
+
+/// A use of the value of an unconstrained witness
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WitnessUse {
+    /// The directive at this opcode index reads the value. Its outputs are only
+    /// constrained if other opcodes constrain them.
+    Directive(usize),
+    /// The witness is a public input of the circuit
+    PublicInput,
+    /// The witness is a return value of the circuit
+    ReturnValue,
+}
+
+impl core::fmt::Display for WitnessUse {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            WitnessUse::Directive(opcode_index) => write!(f, "directive {opcode_index}"),
+            WitnessUse::PublicInput => f.write_str("public input"),
+            WitnessUse::ReturnValue => f.write_str("return value"),
+        }
+    }
+}
+
+/// A witness which is assigned by a directive but not constrained by any opcode, so a
+/// prover can give it any value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnconstrainedWitness<F = FieldElement> {
+    pub witness: Witness,
+    /// The index of the first directive which assigns the witness
+    pub opcode_index: usize,
+    pub directive: Directive<F>,
+    /// The uses of the value, in the order of the opcodes and then as a public input or
+    /// return value
+    pub uses: Vec<WitnessUse>,
+}
+
+impl<F: AcirField> core::fmt::Display for UnconstrainedWitness<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "witness {} is assigned by the {} directive at opcode {}, but is not constrained by any opcode",
+            self.witness.witness_index(),
+            self.directive.name(),
+            self.opcode_index
+        )?;
+        if !self.uses.is_empty() {
+            let uses: Vec<String> = self.uses.iter().map(ToString::to_string).collect();
+            write!(f, ", and is used by {}", uses.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the witnesses which are assigned by directives but which no arithmetic opcode,
+/// black box function call or function call constrains, in the order of the witnesses.
+///
+/// Directives are hints for the solver, so the value of such a witness is not checked by
+/// the proof. This is usually a soundness bug in the frontend which emitted the circuit.
+pub fn find_unconstrained_witnesses<F: AcirField>(
+    circuit: &Circuit<F>,
+) -> Vec<UnconstrainedWitness<F>> {
+    let mut constrained: BTreeSet<Witness> = BTreeSet::new();
+    let mut assigned: BTreeMap<Witness, (usize, &Directive<F>)> = BTreeMap::new();
+    for (opcode_index, opcode) in circuit.opcodes.iter().enumerate() {
+        match opcode {
+            Opcode::Arithmetic(expr) => constrained.extend(expr.witnesses()),
+            Opcode::BlackBoxFuncCall(func_call) => {
+                constrained.extend(func_call.inputs.iter().map(|input| input.witness));
+                constrained.extend(&func_call.outputs);
+            }
+            Opcode::Call {
+                inputs, outputs, ..
+            } => constrained.extend(inputs.iter().chain(outputs)),
+            Opcode::Directive(directive) => {
+                for witness in directive.outputs() {
+                    assigned.entry(witness).or_insert((opcode_index, directive));
+                }
+            }
+        }
+    }
+
+    assigned
+        .into_iter()
+        .filter(|(witness, _)| !constrained.contains(witness))
+        .map(|(witness, (opcode_index, directive))| {
+            let mut uses: Vec<WitnessUse> = circuit
+                .opcodes
+                .iter()
+                .enumerate()
+                .filter_map(|(index, opcode)| match opcode {
+                    Opcode::Directive(directive) if directive.inputs().contains(&witness) => {
+                        Some(WitnessUse::Directive(index))
+                    }
+                    _ => None,
+                })
+                .collect();
+            if circuit.public_inputs.0.contains(&witness) {
+                uses.push(WitnessUse::PublicInput);
+            }
+            if circuit.return_values.0.contains(&witness) {
+                uses.push(WitnessUse::ReturnValue);
+            }
+            UnconstrainedWitness {
+                witness,
+                opcode_index,
+                directive: directive.clone(),
+                uses,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{compiler::compile, testing::generate_satisfied_circuit, Language};
+    use acir::{circuit::PublicInputs, native_types::Expression, FieldElement};
+
+    #[test]
+    fn compiled_circuits_are_constrained() {
+        for seed in 0..5 {
+            let generated = generate_satisfied_circuit(40, seed);
+            for language in [Language::R1CS, Language::PLONKCSat { width: 3 }] {
+                let compiled = compile(generated.circuit.clone(), language, |_| false).unwrap();
+                assert_eq!(find_unconstrained_witnesses(&compiled), Vec::new());
+            }
+        }
+    }
+
+    #[test]
+    fn hints_without_constraints_are_found() {
+        let (x, bits, inverse, y) = (
+            Witness(1),
+            vec![Witness(2), Witness(3)],
+            Witness(4),
+            Witness(5),
+        );
+        let to_radix = Directive::ToRadix {
+            a: Expression::from(&x),
+            b: bits.clone(),
+            radix: 2,
+        };
+        // Only the low bit is constrained, to be boolean
+        let circuit = Circuit {
+            current_witness_index: 5,
+            opcodes: vec![
+                Opcode::Directive(to_radix.clone()),
+                Opcode::Arithmetic(Expression {
+                    mul_terms: vec![(FieldElement::one(), bits[0], bits[0])],
+                    linear_combinations: vec![(-FieldElement::one(), bits[0])],
+                    q_c: FieldElement::zero(),
+                }),
+                Opcode::Directive(Directive::Invert {
+                    x: bits[1],
+                    result: inverse,
+                }),
+                Opcode::Directive(Directive::Invert { x, result: y }),
+            ],
+            public_inputs: PublicInputs(vec![x]),
+            return_values: PublicInputs(vec![inverse]),
+        };
+
+        let unconstrained = find_unconstrained_witnesses(&circuit);
+        assert_eq!(
+            unconstrained,
+            vec![
+                UnconstrainedWitness {
+                    witness: bits[1],
+                    opcode_index: 0,
+                    directive: to_radix,
+                    uses: vec![WitnessUse::Directive(2)],
+                },
+                UnconstrainedWitness {
+                    witness: inverse,
+                    opcode_index: 2,
+                    directive: Directive::Invert {
+                        x: bits[1],
+                        result: inverse
+                    },
+                    uses: vec![WitnessUse::ReturnValue],
+                },
+                UnconstrainedWitness {
+                    witness: y,
+                    opcode_index: 3,
+                    directive: Directive::Invert { x, result: y },
+                    uses: Vec::new(),
+                },
+            ]
+        );
+        assert_eq!(
+            unconstrained[0].to_string(),
+            "witness 3 is assigned by the to_radix directive at opcode 0, but is not constrained by any opcode, and is used by directive 2"
+        );
+    }
+}
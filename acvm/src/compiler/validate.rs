@@ -0,0 +1,252 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+use acir::{
+    circuit::{opcodes::BlackBoxFuncCall, Circuit, Opcode},
+    native_types::Witness,
+    AcirField, BlackBoxFunc,
+};
+use thiserror::Error;
+
+use crate::pwg::opcode_witnesses;
+
+#[derive(PartialEq, Eq, Debug, Error)]
+pub enum ValidationError {
+    #[error("opcode {opcode_index} uses witness {} which is above the current witness index {current_witness_index}", witness.witness_index())]
+    WitnessOutOfBounds {
+        opcode_index: usize,
+        witness: Witness,
+        current_witness_index: u32,
+    },
+    #[error("public input {} is above the current witness index {current_witness_index}", witness.witness_index())]
+    PublicInputOutOfBounds {
+        witness: Witness,
+        current_witness_index: u32,
+    },
+    #[error("return value {} is above the current witness index {current_witness_index}", witness.witness_index())]
+    ReturnValueOutOfBounds {
+        witness: Witness,
+        current_witness_index: u32,
+    },
+    #[error("public input {} is not used by any opcode", witness.witness_index())]
+    UnusedPublicInput { witness: Witness },
+    #[error(
+        "opcode {opcode_index}: expected {expected} inputs for function {func}, but got {got}"
+    )]
+    IncorrectNumFunctionInputs {
+        opcode_index: usize,
+        func: BlackBoxFunc,
+        expected: usize,
+        got: usize,
+    },
+    #[error(
+        "opcode {opcode_index}: expected {expected} outputs for function {func}, but got {got}"
+    )]
+    IncorrectNumFunctionOutputs {
+        opcode_index: usize,
+        func: BlackBoxFunc,
+        expected: usize,
+        got: usize,
+    },
+    #[error("opcode {opcode_index}: directive writes to public input {}", witness.witness_index())]
+    DirectiveWritesPublicInput {
+        opcode_index: usize,
+        witness: Witness,
+    },
+}
+
+/// Checks that a circuit is well-formed before it is compiled or solved.
+///
+/// The following is checked:
+/// - every witness used by an opcode or declared as a public input or a return value is at
+///   most `current_witness_index`
+/// - every public input is used by at least one opcode
+/// - black box function calls have as many inputs and outputs as the function's definition
+/// - directives do not assign values to public inputs
+///
+/// All of the problems which are found are returned, in the order of the opcodes.
+pub fn validate<F: AcirField>(circuit: &Circuit<F>) -> Result<(), Vec<ValidationError>> {
+    let mut errors = Vec::new();
+    let current_witness_index = circuit.current_witness_index;
+    let public_inputs: BTreeSet<Witness> = circuit.public_inputs.0.iter().copied().collect();
+
+    for witness in &public_inputs {
+        if witness.witness_index() > current_witness_index {
+            errors.push(ValidationError::PublicInputOutOfBounds {
+                witness: *witness,
+                current_witness_index,
+            });
+        }
+    }
+    for witness in &circuit.return_values.0 {
+        if witness.witness_index() > current_witness_index {
+            errors.push(ValidationError::ReturnValueOutOfBounds {
+                witness: *witness,
+                current_witness_index,
+            });
+        }
+    }
+
+    let mut used_witnesses = BTreeSet::new();
+    for (opcode_index, opcode) in circuit.opcodes.iter().enumerate() {
+        let witnesses = opcode_witnesses(opcode);
+        for witness in &witnesses {
+            if witness.witness_index() > current_witness_index {
+                errors.push(ValidationError::WitnessOutOfBounds {
+                    opcode_index,
+                    witness: *witness,
+                    current_witness_index,
+                });
+            }
+        }
+        used_witnesses.extend(witnesses);
+
+        match opcode {
+            Opcode::Arithmetic(_) | Opcode::Call { .. } => {}
+            Opcode::BlackBoxFuncCall(func_call) => {
+                validate_func_call(opcode_index, func_call, &mut errors)
+            }
+            Opcode::Directive(directive) => {
+                for witness in directive.outputs() {
+                    if public_inputs.contains(&witness) {
+                        errors.push(ValidationError::DirectiveWritesPublicInput {
+                            opcode_index,
+                            witness,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    for witness in public_inputs.difference(&used_witnesses) {
+        errors.push(ValidationError::UnusedPublicInput { witness: *witness });
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn validate_func_call(
+    opcode_index: usize,
+    func_call: &BlackBoxFuncCall,
+    errors: &mut Vec<ValidationError>,
+) {
+    // AES does not have a definition yet
+    if func_call.name == BlackBoxFunc::AES {
+        return;
+    }
+    let definition = func_call.name.definition();
+
+    if let Some(expected) = definition.input_size.fixed_size() {
+        if func_call.inputs.len() as u128 != expected {
+            errors.push(ValidationError::IncorrectNumFunctionInputs {
+                opcode_index,
+                func: func_call.name,
+                expected: expected as usize,
+                got: func_call.inputs.len(),
+            });
+        }
+    }
+
+    let expected = definition.output_size.0;
+    if func_call.outputs.len() as u128 != expected {
+        errors.push(ValidationError::IncorrectNumFunctionOutputs {
+            opcode_index,
+            func: func_call.name,
+            expected: expected as usize,
+            got: func_call.outputs.len(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{
+        circuit::{directives::Directive, opcodes::FunctionInput, PublicInputs},
+        native_types::Expression,
+        FieldElement,
+    };
+
+    fn range(witness: Witness) -> Opcode {
+        Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+            name: BlackBoxFunc::RANGE,
+            inputs: vec![FunctionInput {
+                witness,
+                num_bits: 8,
+            }],
+            outputs: vec![],
+        })
+    }
+
+    #[test]
+    fn well_formed_circuit_is_valid() {
+        let mut expr = Expression::default();
+        expr.term_multiplication(FieldElement::one(), Witness(1), Witness(2));
+        expr.term_addition(-FieldElement::one(), Witness(3));
+
+        let circuit = Circuit {
+            current_witness_index: 3,
+            opcodes: vec![Opcode::Arithmetic(expr), range(Witness(3))],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        };
+        assert_eq!(validate(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let circuit: Circuit = Circuit {
+            current_witness_index: 3,
+            opcodes: vec![
+                Opcode::Directive(Directive::Invert {
+                    x: Witness(1),
+                    result: Witness(2),
+                }),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::AND,
+                    inputs: vec![FunctionInput {
+                        witness: Witness(1),
+                        num_bits: 8,
+                    }],
+                    outputs: vec![Witness(4)],
+                }),
+            ],
+            public_inputs: PublicInputs(vec![Witness(2), Witness(3)]),
+            return_values: PublicInputs(vec![Witness(2), Witness(5)]),
+        };
+
+        let errors = validate(&circuit).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                ValidationError::ReturnValueOutOfBounds {
+                    witness: Witness(5),
+                    current_witness_index: 3,
+                },
+                ValidationError::DirectiveWritesPublicInput {
+                    opcode_index: 0,
+                    witness: Witness(2),
+                },
+                ValidationError::WitnessOutOfBounds {
+                    opcode_index: 1,
+                    witness: Witness(4),
+                    current_witness_index: 3,
+                },
+                ValidationError::IncorrectNumFunctionInputs {
+                    opcode_index: 1,
+                    func: BlackBoxFunc::AND,
+                    expected: 2,
+                    got: 1,
+                },
+                ValidationError::UnusedPublicInput {
+                    witness: Witness(3)
+                },
+            ]
+        );
+    }
+}
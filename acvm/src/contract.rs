@@ -0,0 +1,227 @@
+// Types shared by the verifier contract generators of backends
+
+use acir::FieldElement;
+use alloc::{borrow::ToOwned, format, string::String, vec::Vec};
+use sha3::{Digest, Keccak256};
+use thiserror::Error;
+
+use crate::{BackendError, Proof, ProofMismatch};
+
+/// The signature of the function which generated Solidity verifiers expose
+pub const VERIFY_SIGNATURE: &str = "verify(bytes,bytes32[])";
+
+/// The chains and languages which verifier contracts can be generated for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContractPlatform {
+    /// Solidity, for Ethereum and other EVM chains
+    EvmSolidity,
+    /// Yul, for EVM chains where the verifier is hand optimised
+    EvmYul,
+    /// Cairo, for Starknet
+    StarknetCairo,
+    /// Sway, for Fuel
+    FuelSway,
+}
+
+impl core::fmt::Display for ContractPlatform {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            ContractPlatform::EvmSolidity => "evm-solidity",
+            ContractPlatform::EvmYul => "evm-yul",
+            ContractPlatform::StarknetCairo => "starknet-cairo",
+            ContractPlatform::FuelSway => "fuel-sway",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The source code of a verifier contract
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Contract {
+    pub platform: ContractPlatform,
+    pub source: String,
+}
+
+#[derive(PartialEq, Eq, Debug, Error)]
+pub enum ContractGenError {
+    #[error("backend cannot generate verifier contracts for {0}")]
+    UnsupportedPlatform(ContractPlatform),
+    #[error("invalid contract option: {0}")]
+    InvalidOption(String),
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+}
+
+/// Options which control the generated contract
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContractOptions {
+    /// The name of the verifier contract
+    pub contract_name: String,
+    /// The version requirement written in the Solidity `pragma` directive
+    pub solidity_pragma: String,
+    /// The SPDX license identifier written at the top of the contract, if any
+    pub license: Option<String>,
+    /// Whether the verification key is embedded in the contract. Otherwise it must be
+    /// supplied to the contract when it is deployed.
+    pub inline_verification_key: bool,
+}
+
+impl Default for ContractOptions {
+    fn default() -> Self {
+        ContractOptions {
+            contract_name: "UltraVerifier".to_owned(),
+            solidity_pragma: ">=0.8.4".to_owned(),
+            license: Some("Apache-2.0".to_owned()),
+            inline_verification_key: true,
+        }
+    }
+}
+
+impl ContractOptions {
+    /// Returns an error if the contract name is not a valid identifier
+    pub fn validate(&self) -> Result<(), ContractGenError> {
+        let mut chars = self.contract_name.chars();
+        let is_identifier = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Err(ContractGenError::InvalidOption(format!(
+                "{:?} is not a valid contract name",
+                self.contract_name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the license comment and pragma directive which start a Solidity contract
+    pub fn solidity_header(&self) -> String {
+        let mut header = String::new();
+        if let Some(license) = &self.license {
+            header.push_str(&format!("// SPDX-License-Identifier: {license}\n"));
+        }
+        header.push_str(&format!("pragma solidity {};\n", self.solidity_pragma));
+        header
+    }
+}
+
+/// Returns the function selector of `VERIFY_SIGNATURE`
+pub fn verify_selector() -> [u8; 4] {
+    let hash = Keccak256::digest(VERIFY_SIGNATURE.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Returns the ABI encoded calldata, including the function selector, of a call
+/// to `verify` on a generated Solidity verifier.
+///
+/// Returns an error if the number of public inputs differs from the number in the proof.
+pub fn verify_calldata(
+    proof: &Proof,
+    public_inputs: &[FieldElement],
+) -> Result<Vec<u8>, ProofMismatch> {
+    if public_inputs.len() != proof.num_public_inputs as usize {
+        return Err(ProofMismatch::NumPublicInputs {
+            expected: proof.num_public_inputs,
+            found: public_inputs.len() as u32,
+        });
+    }
+
+    // Both arguments are dynamic, so the head holds their offsets from the start of the
+    // arguments and the tail holds their contents.
+    let padded_proof_len = proof.bytes.len().div_ceil(32) * 32;
+    let proof_offset = 2 * 32;
+    let public_inputs_offset = proof_offset + 32 + padded_proof_len;
+
+    let mut calldata = verify_selector().to_vec();
+    calldata.extend(abi_word(proof_offset));
+    calldata.extend(abi_word(public_inputs_offset));
+
+    calldata.extend(abi_word(proof.bytes.len()));
+    calldata.extend(&proof.bytes);
+    calldata.resize(calldata.len() + padded_proof_len - proof.bytes.len(), 0);
+
+    calldata.extend(abi_word(public_inputs.len()));
+    for value in public_inputs {
+        let bytes = value.to_be_bytes();
+        calldata.resize(calldata.len() + 32 - bytes.len(), 0);
+        calldata.extend(bytes);
+    }
+    Ok(calldata)
+}
+
+// Encodes an integer as a big-endian 32 byte word
+fn abi_word(value: usize) -> [u8; 32] {
+    let mut word = [0; 32];
+    word[24..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
+
+#[test]
+fn solidity_header() {
+    let options = ContractOptions::default();
+    assert_eq!(
+        options.solidity_header(),
+        "// SPDX-License-Identifier: Apache-2.0\npragma solidity >=0.8.4;\n"
+    );
+
+    let unlicensed = ContractOptions {
+        license: None,
+        ..options
+    };
+    assert_eq!(unlicensed.solidity_header(), "pragma solidity >=0.8.4;\n");
+}
+
+#[test]
+fn contract_names_must_be_identifiers() {
+    assert_eq!(ContractOptions::default().validate(), Ok(()));
+
+    for name in ["", "1Verifier", "My Verifier"] {
+        let options = ContractOptions {
+            contract_name: name.to_owned(),
+            ..ContractOptions::default()
+        };
+        assert!(matches!(
+            options.validate(),
+            Err(ContractGenError::InvalidOption(_))
+        ));
+    }
+}
+
+#[test]
+fn verify_calldata_is_abi_encoded() {
+    use acir::circuit::{Circuit, PublicInputs};
+    use acir::native_types::Witness;
+
+    assert_eq!(verify_selector(), [0xea, 0x50, 0xd0, 0xe4]);
+
+    let circuit = Circuit {
+        current_witness_index: 2,
+        opcodes: Vec::new(),
+        public_inputs: PublicInputs(vec![Witness(1), Witness(2)]),
+        return_values: PublicInputs::default(),
+    };
+    let proof = Proof::new("mock", &circuit, vec![0xab; 33]);
+    let public_inputs = [FieldElement::from(1_i128), FieldElement::from(0x0102_i128)];
+    let calldata = verify_calldata(&proof, &public_inputs).unwrap();
+
+    let words: Vec<&[u8]> = calldata[4..].chunks(32).collect();
+    assert_eq!(calldata[..4], verify_selector());
+    assert_eq!(words.len(), 2 + 1 + 2 + 1 + 2);
+    assert_eq!(words[0], abi_word(0x40));
+    assert_eq!(words[1], abi_word(0x40 + 32 + 64));
+    assert_eq!(words[2], abi_word(33));
+    assert_eq!(words[3], [0xab; 32]);
+    assert_eq!(words[4][0], 0xab);
+    assert!(words[4][1..].iter().all(|&byte| byte == 0));
+    assert_eq!(words[5], abi_word(2));
+    assert_eq!(words[6], abi_word(1));
+    assert_eq!(words[7], abi_word(0x0102));
+
+    assert_eq!(
+        verify_calldata(&proof, &public_inputs[..1]),
+        Err(ProofMismatch::NumPublicInputs {
+            expected: 2,
+            found: 1
+        })
+    );
+}
@@ -0,0 +1,213 @@
+// Management of the structured reference strings which pairing based backends need
+//
+// The reference string is downloaded once, cached on disk and sliced down to the
+// number of points which each circuit requires. ACVM does not depend on an HTTP client,
+// so downloading is delegated to a function supplied by the caller.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::PathBuf,
+};
+
+use acir::{
+    circuit::Circuit,
+    serialisation::{read_u32, write_bytes, write_u32},
+};
+
+use crate::{BackendError, ProofSystemCompiler, ProvingKey, VerificationKey};
+
+/// A structured reference string, which is a sequence of G1 points followed by G2 data.
+///
+/// Points are stored in the backend's encoding, each taking `g1_point_size` bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Crs {
+    pub g1_point_size: usize,
+    pub g1_data: Vec<u8>,
+    pub g2_data: Vec<u8>,
+}
+
+impl Crs {
+    pub fn num_points(&self) -> usize {
+        self.g1_data.len() / self.g1_point_size
+    }
+
+    /// Returns a reference string with only the first `num_points` G1 points
+    pub fn slice(&self, num_points: usize) -> Result<Crs, BackendError> {
+        if num_points > self.num_points() {
+            return Err(BackendError::CrsMissing);
+        }
+        Ok(Crs {
+            g1_point_size: self.g1_point_size,
+            g1_data: self.g1_data[..num_points * self.g1_point_size].to_vec(),
+            g2_data: self.g2_data.clone(),
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write_u32(&mut writer, self.g1_point_size as u32)?;
+        write_u32(&mut writer, self.g1_data.len() as u32)?;
+        write_bytes(&mut writer, &self.g1_data)?;
+        write_u32(&mut writer, self.g2_data.len() as u32)?;
+        write_bytes(&mut writer, &self.g2_data)?;
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let g1_point_size = read_u32(&mut reader)? as usize;
+        if g1_point_size == 0 {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+        let mut g1_data = vec![0; read_u32(&mut reader)? as usize];
+        reader.read_exact(&mut g1_data)?;
+        let mut g2_data = vec![0; read_u32(&mut reader)? as usize];
+        reader.read_exact(&mut g2_data)?;
+        Ok(Crs {
+            g1_point_size,
+            g1_data,
+            g2_data,
+        })
+    }
+}
+
+/// A source of structured reference strings
+pub trait CommonReferenceString {
+    /// Returns a reference string with exactly `num_points` G1 points
+    fn get_crs(&self, num_points: usize) -> Result<Crs, BackendError>;
+}
+
+impl CommonReferenceString for Crs {
+    fn get_crs(&self, num_points: usize) -> Result<Crs, BackendError> {
+        self.slice(num_points)
+    }
+}
+
+/// A reference string which is fetched on demand and cached in a file.
+///
+/// The cache is refetched when a circuit needs more points than it holds.
+pub struct CachedCrs<F> {
+    path: PathBuf,
+    fetch: F,
+}
+
+impl<F: Fn(usize) -> Result<Crs, BackendError>> CachedCrs<F> {
+    /// Creates a source which caches reference strings at `path`, using `fetch` to
+    /// download a reference string with at least the given number of points
+    pub fn new(path: impl Into<PathBuf>, fetch: F) -> Self {
+        CachedCrs {
+            path: path.into(),
+            fetch,
+        }
+    }
+
+    fn read_cache(&self) -> Option<Crs> {
+        let file = File::open(&self.path).ok()?;
+        Crs::read(BufReader::new(file)).ok()
+    }
+
+    fn write_cache(&self, crs: &Crs) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write to a temporary file first, so that a partial write is never read back
+        let temporary = self.path.with_extension("partial");
+        crs.write(BufWriter::new(File::create(&temporary)?))?;
+        std::fs::rename(temporary, &self.path)
+    }
+}
+
+impl<F: Fn(usize) -> Result<Crs, BackendError>> CommonReferenceString for CachedCrs<F> {
+    fn get_crs(&self, num_points: usize) -> Result<Crs, BackendError> {
+        if let Some(crs) = self.read_cache() {
+            if crs.num_points() >= num_points {
+                return crs.slice(num_points);
+            }
+        }
+
+        let crs = (self.fetch)(num_points)?;
+        self.write_cache(&crs).map_err(|err| {
+            BackendError::Other(format!(
+                "could not cache reference string at {}: {err}",
+                self.path.display()
+            ))
+        })?;
+        crs.slice(num_points)
+    }
+}
+
+/// Preprocesses a circuit, fetching the reference string it needs from `source`.
+///
+/// Backends which do not need a reference string, or whose selected commitment scheme
+/// has no trusted setup, are preprocessed without one.
+pub fn preprocess_with_source<B: ProofSystemCompiler + ?Sized>(
+    backend: &B,
+    circuit: &Circuit,
+    source: &dyn CommonReferenceString,
+) -> Result<(ProvingKey, VerificationKey), BackendError> {
+    let num_points = backend
+        .commitment_scheme()
+        .requires_trusted_setup()
+        .then(|| backend.crs_num_points(circuit))
+        .flatten();
+    match num_points {
+        Some(num_points) => {
+            let crs = source.get_crs(num_points)?;
+            backend.preprocess_with_crs(circuit, &crs)
+        }
+        None => backend.preprocess(circuit),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn crs(num_points: usize) -> Crs {
+        Crs {
+            g1_point_size: 2,
+            g1_data: (0..num_points * 2).map(|i| i as u8).collect(),
+            g2_data: vec![0xff; 4],
+        }
+    }
+
+    #[test]
+    fn slicing_keeps_the_first_points() {
+        let sliced = crs(4).slice(2).unwrap();
+        assert_eq!(sliced.num_points(), 2);
+        assert_eq!(sliced.g1_data, vec![0, 1, 2, 3]);
+        assert_eq!(sliced.g2_data, vec![0xff; 4]);
+
+        assert_eq!(crs(4).slice(5), Err(BackendError::CrsMissing));
+    }
+
+    #[test]
+    fn reference_strings_are_fetched_only_when_too_small() {
+        let dir = tempfile::tempdir().unwrap();
+        let fetched = Cell::new(0);
+        let source = CachedCrs::new(dir.path().join("crs/bn254.dat"), |num_points| {
+            fetched.set(fetched.get() + 1);
+            Ok(crs(num_points))
+        });
+
+        assert_eq!(source.get_crs(4).unwrap(), crs(4));
+        assert_eq!(source.get_crs(2).unwrap(), crs(2));
+        assert_eq!(fetched.get(), 1);
+
+        assert_eq!(source.get_crs(8).unwrap(), crs(8));
+        assert_eq!(fetched.get(), 2);
+    }
+
+    #[test]
+    fn fetch_failures_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = CachedCrs::new(dir.path().join("bn254.dat"), |_| {
+            Err(BackendError::Other("offline".to_owned()))
+        });
+        assert_eq!(
+            source.get_crs(1),
+            Err(BackendError::Other("offline".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,117 @@
+// An in-memory map of derived values, optionally backed by a directory
+//
+// `KeyCache` and `CircuitCache` both look a value up in memory, then on disk, and only
+// derive it when neither has it. Values found on disk or derived are kept in memory, and
+// derived values are written to disk. The cache is only an optimisation, so values which
+// cannot be read or written are derived again rather than reported as errors.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::metrics;
+
+// A value which can be stored in a `DiskCache`
+pub(crate) trait CacheEntry: Clone + Sized {
+    // Reads the value stored at `path`, which the value may extend with its own extensions
+    fn read(path: &Path) -> Option<Self>;
+
+    fn write(&self, path: &Path) -> std::io::Result<()>;
+}
+
+#[derive(Debug)]
+pub(crate) struct DiskCache<K, V> {
+    entries: Mutex<HashMap<K, V>>,
+    directory: Option<PathBuf>,
+    // The label of the cache's metrics
+    name: &'static str,
+}
+
+impl<K: Eq + Hash, V: CacheEntry> DiskCache<K, V> {
+    pub(crate) fn new(name: &'static str, directory: Option<PathBuf>) -> Self {
+        DiskCache {
+            entries: Mutex::default(),
+            directory,
+            name,
+        }
+    }
+
+    // Returns the value cached under `key`, deriving it with `derive` if it is not cached.
+    // On disk, the value is stored at `path` relative to the cache's directory.
+    pub(crate) fn get_or_derive<E>(
+        &self,
+        key: K,
+        path: impl FnOnce() -> PathBuf,
+        derive: impl FnOnce() -> Result<V, E>,
+    ) -> Result<V, E> {
+        if let Some(value) = self.lock().get(&key) {
+            metrics::cache_lookup(self.name, true);
+            return Ok(value.clone());
+        }
+        let path = self
+            .directory
+            .as_ref()
+            .map(|directory| directory.join(path()));
+        if let Some(value) = path.as_deref().and_then(V::read) {
+            metrics::cache_lookup(self.name, true);
+            self.lock().insert(key, value.clone());
+            return Ok(value);
+        }
+
+        metrics::cache_lookup(self.name, false);
+        let value = derive()?;
+        if let Some(path) = path {
+            let _ = value.write(&path);
+        }
+        self.lock().insert(key, value.clone());
+        Ok(value)
+    }
+
+    // Removes all values from memory. Values stored on disk are kept.
+    pub(crate) fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<K, V>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|_| panic!("{} cache lock is poisoned", self.name))
+    }
+}
+
+// Writes `bytes` to `path`, creating its directory if it does not exist.
+//
+// The bytes are written to a temporary file which is then renamed, so that a crash never
+// leaves a truncated file which would later be read as a complete value.
+pub(crate) fn write_file(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(format!(".{}.partial", std::process::id()));
+    std::fs::write(&temporary, bytes)?;
+    std::fs::rename(&temporary, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temporary);
+    })
+}
+
+// Returns the name of a backend with the characters which may not be valid in a path
+// escaped as `_` followed by the hex of each of their bytes, so that different names
+// never share a directory
+pub(crate) fn directory_name(backend: &str) -> String {
+    let mut name = String::with_capacity(backend.len());
+    for c in backend.chars() {
+        if c.is_ascii_alphanumeric() || c == '-' {
+            name.push(c);
+        } else {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                name.push_str(&format!("_{byte:02x}"));
+            }
+        }
+    }
+    name
+}
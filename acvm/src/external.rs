@@ -0,0 +1,574 @@
+// A backend which runs an external prover binary
+//
+// This lets provers which are not written in Rust be used with ACVM without FFI bindings.
+//
+// # Protocol
+//
+// By default, the binary is spawned once per request. ACVM writes a single JSON object to
+// its stdin and closes it, then the binary writes a single JSON object to its stdout and exits.
+// Other transports, such as `wasm::WasmTransport`, exchange the same messages.
+//
+// Requests have the form `{"method": <string>, "params": <object>}`. Responses have the
+// form `{"result": <value>}` on success or `{"error": <string>}` on failure.
+//
+// Binary values (circuits, proofs and keys) are hex encoded. Circuits use the format
+// written by `Circuit::write`. Field elements are hex encoded big-endian integers, and
+// witness maps are objects from witness indices to field elements.
+//
+// | method           | params                                                 | result                 |
+// |------------------|--------------------------------------------------------|------------------------|
+// | `info`           | `{}`                                                   | `Info`                 |
+// | `solve_blackbox` | `{"name", "inputs": [{"witness", "num_bits", "value"}], "outputs": [witness]}` | `[field]` |
+// | `eth_contract`   | `{"circuit", "options"}`                               | `string`               |
+// | `verifier_contract` | `{"vk", "platform", "options"}`                    | `string`               |
+// | `circuit_size`   | `{"circuit"}`                                          | `number`               |
+// | `prove`          | `{"circuit", "witness"}`                               | `proof`                |
+// | `verify`         | `{"proof", "public_inputs", "circuit"}`                | `bool`                 |
+// | `preprocess`     | `{"circuit"}`                                          | `{"pk", "vk"}`         |
+// | `prove_with_pk`  | `{"circuit", "witness", "pk"}`                         | `proof`                |
+// | `verify_with_vk` | `{"proof", "public_inputs", "circuit", "vk"}`          | `bool`                 |
+//
+// `info` is requested once when the backend is created. Its result has the form
+// `{"name": <string>, "width": <number or null for R1CS>, "field": <hex modulus, or
+// "bn254", "bls12_381" or "goldilocks">, "blackbox_functions": [<name>]}`.
+//
+// Contract options have the form `{"contract_name": <string>, "solidity_pragma": <string>,
+// "license": <string or null>, "inline_verification_key": <bool>}`.
+
+use std::{
+    collections::HashSet,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use acir::{
+    circuit::{opcodes::BlackBoxFuncCall, Circuit},
+    BlackBoxFunc, FieldElement,
+};
+use num_bigint::BigUint;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{
+    pwg::witness_to_value, Backend, BackendError, Contract, ContractGenError, ContractOptions,
+    ContractPlatform, FieldModulus, Language, OpcodeResolutionError, PartialWitnessGenerator,
+    ProofSystemCompiler, ProvingKey, SmartContract, VerificationKey, WitnessMap,
+};
+
+/// Sends a serialized request to a prover and returns its serialized response
+pub trait Transport {
+    fn exchange(&self, request: &[u8]) -> Result<Vec<u8>, BackendError>;
+}
+
+/// A transport which spawns a prover binary for each request
+#[derive(Debug, Clone)]
+pub struct Process {
+    program: PathBuf,
+    args: Vec<String>,
+}
+
+impl Process {
+    pub fn new(
+        program: impl Into<PathBuf>,
+        args: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Process {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// A backend which forwards every request to an external prover
+#[derive(Debug, Clone)]
+pub struct ExternalBackend<T = Process> {
+    transport: T,
+    info: BackendInfo,
+}
+
+#[derive(Debug, Clone)]
+struct BackendInfo {
+    name: String,
+    np_language: Language,
+    np_field: FieldModulus,
+    blackbox_functions: HashSet<BlackBoxFunc>,
+}
+
+#[derive(Deserialize)]
+struct InfoResponse {
+    name: String,
+    width: Option<usize>,
+    field: String,
+    blackbox_functions: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Response<T> {
+    Result(T),
+    Error(String),
+}
+
+#[derive(Deserialize)]
+struct KeyPair {
+    pk: String,
+    vk: String,
+}
+
+impl ExternalBackend {
+    /// Creates a backend which runs `program` with `args`, and requests its `info`
+    pub fn new(
+        program: impl Into<PathBuf>,
+        args: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<Self, BackendError> {
+        Self::with_transport(Process::new(program, args))
+    }
+}
+
+impl<T: Transport> ExternalBackend<T> {
+    /// Creates a backend which sends requests over `transport`, and requests its `info`
+    pub fn with_transport(transport: T) -> Result<Self, BackendError> {
+        let info: InfoResponse = request(&transport, "info", json!({}))?;
+        let np_language = match info.width {
+            Some(width) => Language::PLONKCSat { width },
+            None => Language::R1CS,
+        };
+        let np_field = FieldModulus::from_name(&info.field)
+            .or_else(|| {
+                BigUint::parse_bytes(info.field.trim_start_matches("0x").as_bytes(), 16)
+                    .map(FieldModulus)
+            })
+            .ok_or_else(|| {
+                BackendError::SerializationError(format!("invalid field modulus {}", info.field))
+            })?;
+        let blackbox_functions = info
+            .blackbox_functions
+            .iter()
+            .map(|name| {
+                BlackBoxFunc::lookup(name)
+                    .ok_or_else(|| BackendError::UnsupportedOpcode(name.clone()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(ExternalBackend {
+            transport,
+            info: BackendInfo {
+                name: info.name,
+                np_language,
+                np_field,
+                blackbox_functions,
+            },
+        })
+    }
+
+    fn request<R: DeserializeOwned>(&self, method: &str, params: Value) -> Result<R, BackendError> {
+        request(&self.transport, method, params)
+    }
+}
+
+impl Transport for Process {
+    fn exchange(&self, request: &[u8]) -> Result<Vec<u8>, BackendError> {
+        let io_error = |err: std::io::Error| {
+            BackendError::Other(format!("could not run {}: {err}", self.program.display()))
+        };
+
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(io_error)?;
+
+        // The request is written on another thread while the output is read, as a prover
+        // which writes more than a pipe buffer before reading all of its input would
+        // otherwise block on its output while ACVM blocks on its input
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let (written, output) = std::thread::scope(|scope| {
+            let writer = scope.spawn(move || {
+                stdin.write_all(request)?;
+                stdin.write_all(b"\n")
+            });
+            let output = child.wait_with_output();
+            let written = writer.join().expect("writing to a pipe does not panic");
+            (written, output)
+        });
+
+        let output = output.map_err(io_error)?;
+        if !output.status.success() {
+            return Err(BackendError::Other(format!(
+                "{} exited with {}: {}",
+                self.program.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        written.map_err(io_error)?;
+        Ok(output.stdout)
+    }
+}
+
+fn request<R: DeserializeOwned>(
+    transport: &impl Transport,
+    method: &str,
+    params: Value,
+) -> Result<R, BackendError> {
+    let request = serde_json::to_vec(&json!({ "method": method, "params": params }))
+        .map_err(|err| BackendError::SerializationError(err.to_string()))?;
+    let response = transport.exchange(&request)?;
+
+    match serde_json::from_slice(&response)
+        .map_err(|err| BackendError::SerializationError(err.to_string()))?
+    {
+        Response::Result(result) => Ok(result),
+        Response::Error(message) => Err(BackendError::Other(message)),
+    }
+}
+
+fn encode_circuit(circuit: &Circuit) -> Result<String, BackendError> {
+    let mut bytes = Vec::new();
+    circuit
+        .write(&mut bytes)
+        .map_err(|err| BackendError::SerializationError(err.to_string()))?;
+    Ok(hex::encode(bytes))
+}
+
+fn encode_contract_options(options: &ContractOptions) -> Value {
+    json!({
+        "contract_name": options.contract_name,
+        "solidity_pragma": options.solidity_pragma,
+        "license": options.license,
+        "inline_verification_key": options.inline_verification_key,
+    })
+}
+
+fn encode_witness(witness_values: &WitnessMap) -> Value {
+    witness_values
+        .iter()
+        .map(|(witness, value)| (witness.0.to_string(), Value::String(value.to_hex())))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+fn encode_fields(values: &[FieldElement]) -> Vec<String> {
+    values.iter().map(|value| value.to_hex()).collect()
+}
+
+fn decode_bytes(hex_str: &str) -> Result<Vec<u8>, BackendError> {
+    hex::decode(hex_str.trim_start_matches("0x"))
+        .map_err(|err| BackendError::SerializationError(err.to_string()))
+}
+
+impl<T: Transport> Backend for ExternalBackend<T> {}
+
+impl<T: Transport> PartialWitnessGenerator for ExternalBackend<T> {
+    fn solve_blackbox_function_call(
+        &self,
+        initial_witness: &mut WitnessMap,
+        func_call: &BlackBoxFuncCall,
+    ) -> Result<(), OpcodeResolutionError> {
+        if !self.info.blackbox_functions.contains(&func_call.name) {
+            return Err(OpcodeResolutionError::UnsupportedBlackBoxFunc(
+                func_call.name,
+            ));
+        }
+
+        let inputs = func_call
+            .inputs
+            .iter()
+            .map(|input| {
+                let value = witness_to_value(initial_witness, input.witness)?;
+                Ok(json!({
+                    "witness": input.witness.0,
+                    "num_bits": input.num_bits,
+                    "value": value.to_hex(),
+                }))
+            })
+            .collect::<Result<Vec<_>, OpcodeResolutionError>>()?;
+        let outputs: Vec<u32> = func_call.outputs.iter().map(|w| w.0).collect();
+
+        let values: Vec<String> = self.request(
+            "solve_blackbox",
+            json!({
+                "name": func_call.name.name(),
+                "inputs": inputs,
+                "outputs": outputs,
+            }),
+        )?;
+        if values.len() != func_call.outputs.len() {
+            return Err(BackendError::SerializationError(format!(
+                "backend returned {} values for the {} outputs of {}",
+                values.len(),
+                func_call.outputs.len(),
+                func_call.name
+            ))
+            .into());
+        }
+
+        for (witness, value) in func_call.outputs.iter().zip(values) {
+            let value = FieldElement::from_hex(&value).ok_or_else(|| {
+                BackendError::SerializationError(format!("invalid field element {value}"))
+            })?;
+            initial_witness.insert(*witness, value);
+        }
+        Ok(())
+    }
+}
+
+impl<T: Transport> SmartContract for ExternalBackend<T> {
+    fn verifier_contract(
+        &self,
+        verification_key: &VerificationKey,
+        platform: ContractPlatform,
+        options: &ContractOptions,
+    ) -> Result<Contract, ContractGenError> {
+        options.validate()?;
+        let source = self.request(
+            "verifier_contract",
+            json!({
+                "vk": hex::encode(&verification_key.0),
+                "platform": platform.to_string(),
+                "options": encode_contract_options(options),
+            }),
+        )?;
+        Ok(Contract { platform, source })
+    }
+
+    fn eth_contract_from_cs(
+        &self,
+        circuit: &Circuit,
+        options: &ContractOptions,
+    ) -> Result<String, ContractGenError> {
+        options.validate()?;
+        let circuit = encode_circuit(circuit)?;
+        let source = self.request(
+            "eth_contract",
+            json!({ "circuit": circuit, "options": encode_contract_options(options) }),
+        )?;
+        Ok(source)
+    }
+}
+
+impl<T: Transport> ProofSystemCompiler for ExternalBackend<T> {
+    fn np_language(&self) -> Language {
+        self.info.np_language.clone()
+    }
+
+    fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    fn np_field(&self) -> FieldModulus {
+        self.info.np_field.clone()
+    }
+
+    fn blackbox_function_supported(&self, opcode: &BlackBoxFunc) -> bool {
+        self.info.blackbox_functions.contains(opcode)
+    }
+
+    fn supported_opcodes(&self) -> HashSet<BlackBoxFunc> {
+        self.info.blackbox_functions.clone()
+    }
+
+    fn prove_with_meta(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+    ) -> Result<Vec<u8>, BackendError> {
+        let proof: String = self.request(
+            "prove",
+            json!({
+                "circuit": encode_circuit(circuit)?,
+                "witness": encode_witness(witness_values),
+            }),
+        )?;
+        decode_bytes(&proof)
+    }
+
+    fn verify_from_cs(
+        &self,
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+    ) -> Result<bool, BackendError> {
+        self.request(
+            "verify",
+            json!({
+                "proof": hex::encode(proof),
+                "public_inputs": encode_fields(&public_inputs),
+                "circuit": encode_circuit(circuit)?,
+            }),
+        )
+    }
+
+    fn get_exact_circuit_size(&self, circuit: &Circuit) -> Result<u32, BackendError> {
+        self.request(
+            "circuit_size",
+            json!({ "circuit": encode_circuit(circuit)? }),
+        )
+    }
+
+    fn preprocess(&self, circuit: &Circuit) -> Result<(ProvingKey, VerificationKey), BackendError> {
+        let keys: KeyPair =
+            self.request("preprocess", json!({ "circuit": encode_circuit(circuit)? }))?;
+        Ok((
+            ProvingKey(decode_bytes(&keys.pk)?),
+            VerificationKey(decode_bytes(&keys.vk)?),
+        ))
+    }
+
+    fn prove_with_pk(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+        proving_key: &ProvingKey,
+    ) -> Result<Vec<u8>, BackendError> {
+        let proof: String = self.request(
+            "prove_with_pk",
+            json!({
+                "circuit": encode_circuit(circuit)?,
+                "witness": encode_witness(witness_values),
+                "pk": hex::encode(&proving_key.0),
+            }),
+        )?;
+        decode_bytes(&proof)
+    }
+
+    fn verify_with_vk(
+        &self,
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+        verification_key: &VerificationKey,
+    ) -> Result<bool, BackendError> {
+        self.request(
+            "verify_with_vk",
+            json!({
+                "proof": hex::encode(proof),
+                "public_inputs": encode_fields(&public_inputs),
+                "circuit": encode_circuit(circuit)?,
+                "vk": hex::encode(&verification_key.0),
+            }),
+        )
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use acir::{circuit::opcodes::FunctionInput, native_types::Witness};
+    use std::os::unix::fs::PermissionsExt;
+
+    // A prover which answers `info`, `circuit_size` and `prove`, and fails everything else
+    const PROVER: &str = r#"#!/bin/sh
+read -r request
+case "$request" in
+  *'"method":"info"'*)
+    echo '{"result": {"name": "shell_prover", "width": 3, "field": "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001", "blackbox_functions": ["sha256"]}}' ;;
+  *'"method":"circuit_size"'*)
+    echo '{"result": 7}' ;;
+  *'"method":"prove"'*)
+    echo '{"result": "0xdeadbeef"}' ;;
+  *)
+    echo '{"error": "unsupported method"}' ;;
+esac
+"#;
+
+    #[test]
+    fn requests_are_forwarded_to_the_prover() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prover.sh");
+        std::fs::write(&path, PROVER).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let backend = ExternalBackend::new(&path, Vec::<String>::new()).unwrap();
+        assert_eq!(backend.name(), "shell_prover");
+        assert!(matches!(
+            backend.np_language(),
+            Language::PLONKCSat { width: 3 }
+        ));
+        assert_eq!(backend.np_field(), FieldModulus::bn254());
+        assert_eq!(
+            backend.supported_opcodes(),
+            HashSet::from([BlackBoxFunc::SHA256])
+        );
+
+        let circuit = Circuit::default();
+        assert_eq!(backend.get_exact_circuit_size(&circuit), Ok(7));
+        assert_eq!(
+            backend.prove_with_meta(&circuit, &WitnessMap::new()),
+            Ok(vec![0xde, 0xad, 0xbe, 0xef])
+        );
+        assert_eq!(
+            backend.preprocess(&circuit),
+            Err(BackendError::Other("unsupported method".to_owned()))
+        );
+
+        // Failed requests while solving are reported as backend errors
+        let sha256 = BlackBoxFuncCall {
+            name: BlackBoxFunc::SHA256,
+            inputs: vec![FunctionInput {
+                witness: Witness(1),
+                num_bits: 8,
+            }],
+            outputs: (2..34).map(Witness).collect(),
+        };
+        let mut witness = WitnessMap::from([(Witness(1), FieldElement::one())]);
+        assert_eq!(
+            backend.solve_blackbox_function_call(&mut witness, &sha256),
+            Err(OpcodeResolutionError::Backend(BackendError::Other(
+                "unsupported method".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn missing_binaries_are_reported() {
+        let result = ExternalBackend::new("/nonexistent/prover", ["--flag"]);
+        assert!(matches!(result, Err(BackendError::Other(_))));
+
+        // A prover which disappears after the backend is created fails each request
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prover.sh");
+        std::fs::write(&path, PROVER).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let backend = ExternalBackend::new(&path, Vec::<String>::new()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            backend.get_exact_circuit_size(&Circuit::default()),
+            Err(BackendError::Other(_))
+        ));
+    }
+
+    // A prover which logs more than a pipe buffer before it reads its request
+    const LOGGING_PROVER: &str = r#"#!/bin/sh
+head -c 262144 /dev/zero >&2
+request=$(head -c 64)
+cat > /dev/null
+case "$request" in
+  *'"method":"info"'*)
+    echo '{"result": {"name": "logging_prover", "width": 3, "field": "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001", "blackbox_functions": []}}' ;;
+  *)
+    echo '{"result": 7}' ;;
+esac
+"#;
+
+    #[test]
+    fn large_requests_do_not_block_on_logging() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prover.sh");
+        std::fs::write(&path, LOGGING_PROVER).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let backend = ExternalBackend::new(&path, Vec::<String>::new()).unwrap();
+
+        // Several MB of hex, which is more than the pipe to the prover holds
+        let circuit = crate::testing::generate_circuit(&crate::testing::CircuitSpec {
+            num_arithmetic: 20_000,
+            ..Default::default()
+        })
+        .0;
+        assert!(encode_circuit(&circuit).unwrap().len() > 1 << 20);
+        assert_eq!(backend.get_exact_circuit_size(&circuit), Ok(7));
+    }
+}
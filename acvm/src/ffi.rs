@@ -0,0 +1,380 @@
+// A C ABI for loading circuits and solving their witnesses
+//
+// Circuits and buffers are returned as opaque handles, which must be freed with
+// `acvm_circuit_free` and `acvm_buffer_free`. Every fallible function returns an
+// `AcvmStatus`, and writes its result through an out pointer only when it returns
+// `ACVM_OK`. Panics are caught and returned as `ACVM_PANIC`.
+//
+// Circuits are passed in the format of `Circuit::write`, and witness maps in the format of
+// `WitnessMap::to_bytes`. Black box functions are solved with `pwg::NativeSolver`.
+
+use std::{
+    ffi::c_char,
+    panic::{self, AssertUnwindSafe},
+    slice,
+};
+
+use acir::circuit::Circuit;
+
+use crate::{
+    pwg::{self, NativeSolver},
+    OpcodeResolutionError, PartialWitnessGenerator, WitnessMap,
+};
+
+/// The result of an FFI call
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum AcvmStatus {
+    ACVM_OK = 0,
+    ACVM_NULL_POINTER = 1,
+    ACVM_MALFORMED_CIRCUIT = 2,
+    ACVM_MALFORMED_WITNESS = 3,
+    /// Some witnesses could not be solved, as their inputs are missing
+    ACVM_UNSOLVABLE = 4,
+    /// The witness does not satisfy the circuit
+    ACVM_UNSATISFIED = 5,
+    /// The circuit uses a black box function which can only be solved by a backend
+    ACVM_UNSUPPORTED_BLACK_BOX = 6,
+    /// ACVM panicked, which is a bug in ACVM
+    ACVM_PANIC = 7,
+}
+
+impl From<OpcodeResolutionError> for AcvmStatus {
+    fn from(err: OpcodeResolutionError) -> Self {
+        match err {
+            OpcodeResolutionError::OpcodeNotSolvable(_) => AcvmStatus::ACVM_UNSOLVABLE,
+            OpcodeResolutionError::UnsupportedBlackBoxFunc(_)
+            | OpcodeResolutionError::UnsupportedBlackBoxFuncCall { .. } => {
+                AcvmStatus::ACVM_UNSUPPORTED_BLACK_BOX
+            }
+            _ => AcvmStatus::ACVM_UNSATISFIED,
+        }
+    }
+}
+
+/// A circuit which has been loaded
+pub struct AcvmCircuit(Circuit);
+
+/// A byte buffer owned by ACVM
+pub struct AcvmBuffer(Vec<u8>);
+
+// Returns the bytes at `data`, which may be null if `len` is zero
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    if len == 0 {
+        Some(&[])
+    } else if data.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(data, len))
+    }
+}
+
+// Runs the body of an FFI call, returning `ACVM_PANIC` if it panics rather than unwinding
+// into the host, which would abort it
+fn catch_panic(body: impl FnOnce() -> AcvmStatus) -> AcvmStatus {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(AcvmStatus::ACVM_PANIC)
+}
+
+/// Returns a static, NUL terminated description of a status. The status is taken as an
+/// integer, so that values which are not an `AcvmStatus` are described rather than being
+/// undefined behaviour.
+#[no_mangle]
+pub extern "C" fn acvm_status_message(status: u32) -> *const c_char {
+    const STATUSES: [AcvmStatus; 8] = [
+        AcvmStatus::ACVM_OK,
+        AcvmStatus::ACVM_NULL_POINTER,
+        AcvmStatus::ACVM_MALFORMED_CIRCUIT,
+        AcvmStatus::ACVM_MALFORMED_WITNESS,
+        AcvmStatus::ACVM_UNSOLVABLE,
+        AcvmStatus::ACVM_UNSATISFIED,
+        AcvmStatus::ACVM_UNSUPPORTED_BLACK_BOX,
+        AcvmStatus::ACVM_PANIC,
+    ];
+    let status = STATUSES.into_iter().find(|known| *known as u32 == status);
+    let message: &'static [u8] = match status {
+        Some(AcvmStatus::ACVM_OK) => b"ok\0",
+        Some(AcvmStatus::ACVM_NULL_POINTER) => b"a required pointer was null\0",
+        Some(AcvmStatus::ACVM_MALFORMED_CIRCUIT) => b"malformed circuit\0",
+        Some(AcvmStatus::ACVM_MALFORMED_WITNESS) => b"malformed witness map\0",
+        Some(AcvmStatus::ACVM_UNSOLVABLE) => b"the witness could not be solved\0",
+        Some(AcvmStatus::ACVM_UNSATISFIED) => b"the witness does not satisfy the circuit\0",
+        Some(AcvmStatus::ACVM_UNSUPPORTED_BLACK_BOX) => {
+            b"the circuit uses a black box function which needs a backend\0"
+        }
+        Some(AcvmStatus::ACVM_PANIC) => b"ACVM panicked\0",
+        None => b"unknown status\0",
+    };
+    message.as_ptr().cast()
+}
+
+/// Loads a circuit, and writes its handle to `out`.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn acvm_circuit_load(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut AcvmCircuit,
+) -> AcvmStatus {
+    catch_panic(|| {
+        let Some(bytes) = bytes(data, len) else {
+            return AcvmStatus::ACVM_NULL_POINTER;
+        };
+        if out.is_null() {
+            return AcvmStatus::ACVM_NULL_POINTER;
+        }
+        match Circuit::read(bytes) {
+            Ok(circuit) => {
+                *out = Box::into_raw(Box::new(AcvmCircuit(circuit)));
+                AcvmStatus::ACVM_OK
+            }
+            Err(_) => AcvmStatus::ACVM_MALFORMED_CIRCUIT,
+        }
+    })
+}
+
+/// Frees a circuit returned by `acvm_circuit_load`. Null is ignored.
+///
+/// # Safety
+///
+/// `circuit` must be null or a handle which has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn acvm_circuit_free(circuit: *mut AcvmCircuit) {
+    if !circuit.is_null() {
+        drop(Box::from_raw(circuit));
+    }
+}
+
+/// Solves a circuit from an initial witness map, and writes a buffer holding the
+/// solved witness map to `out`.
+///
+/// # Safety
+///
+/// `circuit` must be a valid handle, `witness` must point to `witness_len` readable
+/// bytes, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn acvm_solve(
+    circuit: *const AcvmCircuit,
+    witness: *const u8,
+    witness_len: usize,
+    out: *mut *mut AcvmBuffer,
+) -> AcvmStatus {
+    catch_panic(|| {
+        let (Some(circuit), Some(witness)) = (circuit.as_ref(), bytes(witness, witness_len)) else {
+            return AcvmStatus::ACVM_NULL_POINTER;
+        };
+        if out.is_null() {
+            return AcvmStatus::ACVM_NULL_POINTER;
+        }
+        let Ok(witness) = WitnessMap::from_bytes(witness) else {
+            return AcvmStatus::ACVM_MALFORMED_WITNESS;
+        };
+        if witness.validate(&circuit.0).is_err() {
+            return AcvmStatus::ACVM_MALFORMED_WITNESS;
+        }
+        let mut witness = witness.into_dense(circuit.0.num_vars());
+        match NativeSolver.solve(&mut witness, circuit.0.opcodes.clone()) {
+            Ok(()) => {
+                *out = Box::into_raw(Box::new(AcvmBuffer(witness.to_bytes())));
+                AcvmStatus::ACVM_OK
+            }
+            Err(err) => err.into(),
+        }
+    })
+}
+
+/// Checks that a complete witness map satisfies the constraints of a circuit, as
+/// `pwg::check_satisfied` does, returning `ACVM_OK` if it does and `ACVM_UNSATISFIED` if it
+/// does not. Witness maps which are missing a witness that a constraint uses return
+/// `ACVM_UNSOLVABLE`.
+///
+/// # Safety
+///
+/// `circuit` must be a valid handle, and `witness` must point to `witness_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn acvm_check_satisfied(
+    circuit: *const AcvmCircuit,
+    witness: *const u8,
+    witness_len: usize,
+) -> AcvmStatus {
+    catch_panic(|| {
+        let (Some(circuit), Some(witness)) = (circuit.as_ref(), bytes(witness, witness_len)) else {
+            return AcvmStatus::ACVM_NULL_POINTER;
+        };
+        let Ok(witness) = WitnessMap::from_bytes(witness) else {
+            return AcvmStatus::ACVM_MALFORMED_WITNESS;
+        };
+        if witness.validate(&circuit.0).is_err() {
+            return AcvmStatus::ACVM_MALFORMED_WITNESS;
+        }
+
+        match pwg::check_satisfied(&NativeSolver, &circuit.0, &witness) {
+            Ok(()) => AcvmStatus::ACVM_OK,
+            Err(err) => err.into(),
+        }
+    })
+}
+
+/// Returns a pointer to the contents of a buffer, which is valid until it is freed
+///
+/// # Safety
+///
+/// `buffer` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn acvm_buffer_data(buffer: *const AcvmBuffer) -> *const u8 {
+    match buffer.as_ref() {
+        Some(buffer) => buffer.0.as_ptr(),
+        None => std::ptr::null(),
+    }
+}
+
+/// Returns the length of a buffer in bytes
+///
+/// # Safety
+///
+/// `buffer` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn acvm_buffer_len(buffer: *const AcvmBuffer) -> usize {
+    buffer.as_ref().map_or(0, |buffer| buffer.0.len())
+}
+
+/// Frees a buffer returned by ACVM. Null is ignored.
+///
+/// # Safety
+///
+/// `buffer` must be null or a handle which has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn acvm_buffer_free(buffer: *mut AcvmBuffer) {
+    if !buffer.is_null() {
+        drop(Box::from_raw(buffer));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{
+        circuit::{Opcode, PublicInputs},
+        native_types::{Expression, Witness},
+        FieldElement,
+    };
+    use std::ffi::CStr;
+
+    // _2 = _1 + 1
+    fn load_circuit() -> *mut AcvmCircuit {
+        let circuit: Circuit = Circuit {
+            current_witness_index: 2,
+            opcodes: vec![Opcode::Arithmetic(
+                &(&Expression::from(&Witness(1)) - &Witness(2)) + &Expression::one(),
+            )],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        };
+        let mut bytes = Vec::new();
+        circuit.write(&mut bytes).unwrap();
+
+        let mut handle = std::ptr::null_mut();
+        let status = unsafe { acvm_circuit_load(bytes.as_ptr(), bytes.len(), &mut handle) };
+        assert_eq!(status, AcvmStatus::ACVM_OK);
+        handle
+    }
+
+    #[test]
+    fn solve_and_check() {
+        let circuit = load_circuit();
+        let initial_witness =
+            WitnessMap::from([(Witness(1), FieldElement::from(4_i128))]).to_bytes();
+
+        let mut buffer = std::ptr::null_mut();
+        let status = unsafe {
+            acvm_solve(
+                circuit,
+                initial_witness.as_ptr(),
+                initial_witness.len(),
+                &mut buffer,
+            )
+        };
+        assert_eq!(status, AcvmStatus::ACVM_OK);
+        let solved = unsafe {
+            slice::from_raw_parts(acvm_buffer_data(buffer), acvm_buffer_len(buffer)).to_vec()
+        };
+        assert_eq!(
+            WitnessMap::<FieldElement>::from_bytes(&solved).unwrap()[&Witness(2)],
+            FieldElement::from(5_i128)
+        );
+
+        let status = unsafe { acvm_check_satisfied(circuit, solved.as_ptr(), solved.len()) };
+        assert_eq!(status, AcvmStatus::ACVM_OK);
+
+        let wrong = WitnessMap::from([
+            (Witness(1), FieldElement::from(4_i128)),
+            (Witness(2), FieldElement::from(6_i128)),
+        ])
+        .to_bytes();
+        let status = unsafe { acvm_check_satisfied(circuit, wrong.as_ptr(), wrong.len()) };
+        assert_eq!(status, AcvmStatus::ACVM_UNSATISFIED);
+
+        unsafe {
+            acvm_buffer_free(buffer);
+            acvm_circuit_free(circuit);
+        }
+    }
+
+    #[test]
+    fn errors_are_reported_with_status_codes() {
+        let mut handle = std::ptr::null_mut();
+        let status = unsafe { acvm_circuit_load([0x01].as_ptr(), 1, &mut handle) };
+        assert_eq!(status, AcvmStatus::ACVM_MALFORMED_CIRCUIT);
+        assert!(handle.is_null());
+        let status = unsafe { acvm_circuit_load(std::ptr::null(), 4, &mut handle) };
+        assert_eq!(status, AcvmStatus::ACVM_NULL_POINTER);
+
+        // Lengths in the circuit are not trusted to allocate
+        let mut bytes = Vec::new();
+        Circuit::<FieldElement>::default()
+            .write(&mut bytes)
+            .unwrap();
+        let num_opcodes = bytes.len() - 4;
+        bytes[num_opcodes..].copy_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend([0; 64]);
+        let status = unsafe { acvm_circuit_load(bytes.as_ptr(), bytes.len(), &mut handle) };
+        assert_eq!(status, AcvmStatus::ACVM_MALFORMED_CIRCUIT);
+
+        let circuit = load_circuit();
+        let mut buffer = std::ptr::null_mut();
+        let status = unsafe { acvm_solve(circuit, std::ptr::null(), 0, &mut buffer) };
+        assert_eq!(status, AcvmStatus::ACVM_MALFORMED_WITNESS);
+
+        let empty = WitnessMap::<FieldElement>::new().to_bytes();
+        let status = unsafe { acvm_solve(circuit, empty.as_ptr(), empty.len(), &mut buffer) };
+        assert_eq!(status, AcvmStatus::ACVM_UNSOLVABLE);
+        assert!(buffer.is_null());
+
+        // _3 is not part of the circuit
+        let extra = WitnessMap::from([
+            (Witness(1), FieldElement::from(4_i128)),
+            (Witness(3), FieldElement::from(5_i128)),
+        ])
+        .to_bytes();
+        let status = unsafe { acvm_solve(circuit, extra.as_ptr(), extra.len(), &mut buffer) };
+        assert_eq!(status, AcvmStatus::ACVM_MALFORMED_WITNESS);
+        unsafe { acvm_circuit_free(circuit) };
+
+        let message = |status| unsafe { CStr::from_ptr(acvm_status_message(status)) }.to_str();
+        assert_eq!(
+            message(AcvmStatus::ACVM_UNSOLVABLE as u32),
+            Ok("the witness could not be solved")
+        );
+        assert_eq!(message(8), Ok("unknown status"));
+        assert_eq!(message(u32::MAX), Ok("unknown status"));
+
+        // Panics are returned to the host rather than unwinding into it
+        assert_eq!(
+            catch_panic(|| panic!("a bug in ACVM")),
+            AcvmStatus::ACVM_PANIC
+        );
+    }
+}
@@ -0,0 +1,71 @@
+// A harness which solves arbitrary circuits for fuzz targets, and checks the invariants of
+// the solver
+
+use acir::{
+    circuit::Circuit,
+    fuzzing::arbitrary_witness_map,
+    native_types::{Witness, WitnessMap},
+};
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::{pwg, pwg::NativeSolver, PartialWitnessGenerator};
+
+/// Solves a circuit and inputs generated from `data` with `pwg::NativeSolver`, and panics
+/// if the solver panics or breaks one of these invariants:
+///
+/// - solving the same inputs twice has the same result
+/// - when solving succeeds, the inputs are kept and every witness of every opcode is assigned
+///
+/// Data which does not contain enough bytes for a circuit is ignored.
+pub fn solve_and_check(data: &[u8]) {
+    let mut u = Unstructured::new(data);
+    let (circuit, inputs) = match arbitrary_circuit_and_inputs(&mut u) {
+        Ok(generated) => generated,
+        Err(_) => return,
+    };
+
+    let mut witness = inputs.clone();
+    let result = NativeSolver.solve(&mut witness, circuit.opcodes.clone());
+    let mut witness_again = inputs.clone();
+    let result_again = NativeSolver.solve(&mut witness_again, circuit.opcodes.clone());
+    assert_eq!(result, result_again, "solving is not deterministic");
+    assert_eq!(witness, witness_again, "solving is not deterministic");
+    if result.is_err() {
+        return;
+    }
+
+    for input in inputs.keys() {
+        assert!(witness.contains_key(input), "input {input:?} was removed");
+    }
+    for (index, opcode) in circuit.opcodes.iter().enumerate() {
+        let unassigned: Vec<Witness> = pwg::missing_witnesses(opcode, &witness);
+        assert!(
+            unassigned.is_empty(),
+            "opcode {index} was solved without assigning {unassigned:?}: {opcode:?}"
+        );
+    }
+}
+
+fn arbitrary_circuit_and_inputs(
+    u: &mut Unstructured<'_>,
+) -> arbitrary::Result<(Circuit, WitnessMap)> {
+    let circuit = Circuit::arbitrary(u)?;
+    let inputs = arbitrary_witness_map(u, circuit.current_witness_index)?;
+    Ok((circuit, inputs))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    #[test]
+    fn arbitrary_circuits_are_solved_without_panicking() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut data = vec![0; 2048];
+        for _ in 0..1000 {
+            rng.fill_bytes(&mut data);
+            solve_and_check(&data);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+// An adapter which synthesizes ACIR circuits with halo2
+//
+// Circuits are compiled into gates of `WIDTH` witnesses and laid out by `compiler::to_air`,
+// whose rows map directly onto a PLONKish constraint system:
+//
+// - each witness column of the trace is an advice column, with equality enabled
+// - the selectors of each row are fixed columns, and a single gate applies the transition
+//   constraint `q_m·w_0·w_1 + q_s·w_0·w_0 + Σ q_l[i]·w_i + q_c = 0` on every row
+// - cells which hold the same witness are constrained to be equal
+// - public inputs are constrained to the rows of one instance column, in order
+//
+// halo2_proofs does not provide gadgets, so black box calls are replaced by the fallbacks of
+// the compiler, and calls without a fallback are rejected.
+//
+// halo2 circuits are defined over the field `F` of the proof system, which must be the field
+// ACVM is built with, as witnesses such as inverses and negations depend on the modulus.
+// `Halo2Circuit::new` and `synthesize` return an error for any other field. halo2_proofs
+// 0.1 only provides the Pasta fields, which none of ACVM's field features select.
+
+use std::marker::PhantomData;
+
+use acir::{circuit::Circuit as AcirCircuit, FieldElement};
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Layouter, SimpleFloorPlanner},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Instance},
+    poly::Rotation,
+};
+use num_bigint::BigUint;
+
+use crate::{
+    check_field_compatibility,
+    compiler::{self, to_air, Air, CompileError},
+    BackendError, FieldModulus, Language, OpcodeResolutionError, WitnessMap,
+};
+
+/// An ACIR circuit laid out in rows of `WIDTH` advice cells, which implements
+/// `halo2_proofs::plonk::Circuit` over the field `F`
+#[derive(Clone, Debug)]
+pub struct Halo2Circuit<F, const WIDTH: usize> {
+    air: Air,
+    // The values of the cells of each row, if the circuit has a witness
+    trace: Option<Vec<Vec<FieldElement>>>,
+    field: PhantomData<F>,
+}
+
+/// The columns of a `Halo2Circuit`
+#[derive(Clone, Debug)]
+pub struct Halo2Config<const WIDTH: usize> {
+    advice: [Column<Advice>; WIDTH],
+    q_m: Column<Fixed>,
+    q_s: Column<Fixed>,
+    q_l: [Column<Fixed>; WIDTH],
+    q_c: Column<Fixed>,
+    instance: Column<Instance>,
+}
+
+impl<F: FieldExt, const WIDTH: usize> Halo2Circuit<F, WIDTH> {
+    /// Compiles a circuit into gates which fit into a row, replacing every black box call
+    /// with its fallback. The compiled circuit is the one to solve for the witness.
+    pub fn compile(acir: AcirCircuit) -> Result<AcirCircuit, CompileError> {
+        compiler::compile(acir, Language::PLONKCSat { width: WIDTH }, |_| false)
+    }
+
+    /// Lays out a circuit which was compiled by `Halo2Circuit::compile`. Returns
+    /// `BackendError::FieldMismatch` if `F` is not the field ACVM is built with.
+    pub fn new(compiled: &AcirCircuit) -> Result<Self, BackendError> {
+        check_field_compatibility(&halo2_field_modulus::<F>())?;
+        Ok(Halo2Circuit {
+            air: to_air(compiled, WIDTH)?,
+            trace: None,
+            field: PhantomData,
+        })
+    }
+
+    /// Assigns the cells from a solved witness map of the compiled circuit
+    pub fn with_witness(mut self, witness: &WitnessMap) -> Result<Self, OpcodeResolutionError> {
+        self.trace = Some(self.air.trace(witness)?);
+        Ok(self)
+    }
+
+    /// Returns the values of the instance column, which are the public inputs in order,
+    /// if the circuit has a witness
+    pub fn instance(&self) -> Option<Vec<F>> {
+        let trace = self.trace.as_ref()?;
+        Some(
+            self.air
+                .public_inputs
+                .iter()
+                .map(|(row, column)| to_halo2_field(trace[*row][*column]))
+                .collect(),
+        )
+    }
+
+    /// Returns the smallest `k` such that the circuit fits into `2^k` rows, along with
+    /// the rows which halo2 reserves for blinding
+    pub fn min_k(&self) -> u32 {
+        let mut meta = ConstraintSystem::<F>::default();
+        <Self as Circuit<F>>::configure(&mut meta);
+        let num_rows = self.air.num_rows() + meta.minimum_rows();
+        num_rows.next_power_of_two().trailing_zeros()
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize> Circuit<F> for Halo2Circuit<F, WIDTH> {
+    type Config = Halo2Config<WIDTH>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Halo2Circuit {
+            air: self.air.clone(),
+            trace: None,
+            field: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [(); WIDTH].map(|_| meta.advice_column());
+        for column in advice {
+            meta.enable_equality(column);
+        }
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let config = Halo2Config {
+            advice,
+            q_m: meta.fixed_column(),
+            q_s: meta.fixed_column(),
+            q_l: [(); WIDTH].map(|_| meta.fixed_column()),
+            q_c: meta.fixed_column(),
+            instance,
+        };
+
+        meta.create_gate("acir", |meta| {
+            let w = config
+                .advice
+                .map(|column| meta.query_advice(column, Rotation::cur()));
+            let q_m = meta.query_fixed(config.q_m, Rotation::cur());
+            let q_s = meta.query_fixed(config.q_s, Rotation::cur());
+            let q_c = meta.query_fixed(config.q_c, Rotation::cur());
+
+            let mut constraint = q_c;
+            if let [w_0, w_1, ..] = w.as_slice() {
+                constraint = constraint + q_m * w_0.clone() * w_1.clone();
+            }
+            if let Some(w_0) = w.first() {
+                constraint = constraint + q_s * w_0.clone() * w_0.clone();
+            }
+            for (q_l, w_i) in config.q_l.iter().zip(w) {
+                constraint = constraint + meta.query_fixed(*q_l, Rotation::cur()) * w_i;
+            }
+            vec![constraint]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        // The circuit may have been built without `new`, for example by `without_witnesses`
+        // on a circuit of another field
+        if halo2_field_modulus::<F>() != FieldModulus::acvm() {
+            return Err(Error::Synthesis);
+        }
+        let cells = layouter.assign_region(
+            || "acir",
+            |mut region| {
+                let mut cells: Vec<Vec<Cell>> = Vec::with_capacity(self.air.num_rows());
+                for (offset, row) in self.air.rows.iter().enumerate() {
+                    let fixed = [
+                        (config.q_m, row.q_m),
+                        (config.q_s, row.q_s),
+                        (config.q_c, row.q_c),
+                    ]
+                    .into_iter()
+                    .chain(config.q_l.into_iter().zip(row.q_l.iter().copied()));
+                    for (column, value) in fixed {
+                        region.assign_fixed(
+                            || "selector",
+                            column,
+                            offset,
+                            || Ok(to_halo2_field::<F>(value)),
+                        )?;
+                    }
+
+                    let mut row_cells = Vec::with_capacity(WIDTH);
+                    for (column_index, column) in config.advice.into_iter().enumerate() {
+                        let value = || {
+                            let trace = self.trace.as_ref().ok_or(Error::Synthesis)?;
+                            Ok(to_halo2_field::<F>(trace[offset][column_index]))
+                        };
+                        row_cells.push(
+                            region
+                                .assign_advice(|| "witness", column, offset, value)?
+                                .cell(),
+                        );
+                    }
+                    cells.push(row_cells);
+                }
+
+                for copies in &self.air.copies {
+                    let (first_row, first_column) = copies[0];
+                    for (row, column) in &copies[1..] {
+                        region.constrain_equal(
+                            cells[first_row][first_column],
+                            cells[*row][*column],
+                        )?;
+                    }
+                }
+                Ok(cells)
+            },
+        )?;
+
+        for (index, (row, column)) in self.air.public_inputs.iter().enumerate() {
+            layouter.constrain_instance(cells[*row][*column], config.instance, index)?;
+        }
+        Ok(())
+    }
+}
+
+/// Converts a field element into `F`, which must have the same modulus to keep its value
+pub fn to_halo2_field<F: FieldExt>(value: FieldElement) -> F {
+    let mut bytes = [0; 64];
+    for (byte, value) in bytes.iter_mut().zip(value.to_be_bytes().into_iter().rev()) {
+        *byte = value;
+    }
+    F::from_bytes_wide(&bytes)
+}
+
+/// Returns the modulus of the halo2 field `F`
+pub fn halo2_field_modulus<F: FieldExt>() -> FieldModulus {
+    let hex = F::MODULUS.trim_start_matches("0x");
+    FieldModulus(
+        BigUint::parse_bytes(hex.as_bytes(), 16).expect("infallible: the modulus is valid hex"),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{
+        circuit::{
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            Opcode, PublicInputs,
+        },
+        native_types::{Expression, Witness},
+        BlackBoxFunc,
+    };
+    use halo2_proofs::{dev::MockProver, pasta::Fp};
+
+    // x * y + 3 * z - o = 0 with o public and z range constrained to 8 bits
+    fn circuit() -> AcirCircuit {
+        let (x, y, z, o) = (Witness(1), Witness(2), Witness(3), Witness(4));
+        AcirCircuit {
+            current_witness_index: 4,
+            opcodes: vec![
+                Opcode::Arithmetic(Expression {
+                    mul_terms: vec![(FieldElement::one(), x, y)],
+                    linear_combinations: vec![
+                        (FieldElement::from(3_i128), z),
+                        (-FieldElement::one(), o),
+                    ],
+                    q_c: FieldElement::zero(),
+                }),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::RANGE,
+                    inputs: vec![FunctionInput {
+                        witness: z,
+                        num_bits: 8,
+                    }],
+                    outputs: Vec::new(),
+                }),
+            ],
+            public_inputs: PublicInputs(vec![o]),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    #[test]
+    fn other_fields_are_rejected() {
+        let pasta = halo2_field_modulus::<Fp>();
+        assert_eq!(pasta.0.bits(), 255);
+        assert_ne!(pasta, FieldModulus::acvm());
+
+        let compiled = Halo2Circuit::<Fp, 3>::compile(circuit()).unwrap();
+        assert!(matches!(
+            Halo2Circuit::<Fp, 3>::new(&compiled),
+            Err(BackendError::FieldMismatch { .. })
+        ));
+
+        // A circuit which was laid out without `new` cannot be synthesized either
+        let circuit = Halo2Circuit::<Fp, 3> {
+            air: to_air(&compiled, 3).unwrap(),
+            trace: None,
+            field: PhantomData,
+        };
+        assert!(MockProver::run(circuit.min_k(), &circuit, vec![Vec::new()]).is_err());
+    }
+
+    #[test]
+    fn values_are_converted_by_value() {
+        assert_eq!(
+            to_halo2_field::<Fp>(FieldElement::from(5_i128)),
+            Fp::from(5)
+        );
+    }
+}
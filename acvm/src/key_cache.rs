@@ -0,0 +1,378 @@
+// Caches the proving and verification keys derived by backends
+//
+// Preprocessing dominates the cost of proving and verifying the same circuit repeatedly.
+// Keys are keyed by the backend's name and commitment scheme, and the `circuit_digest`
+// of the circuit.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use acir::{
+    circuit::{opcodes::BlackBoxFuncCall, Circuit},
+    BlackBoxFunc, FieldElement,
+};
+
+use crate::{
+    circuit_digest,
+    compiler::{GateWeights, ProvingTimeModel},
+    crs::Crs,
+    disk_cache::{directory_name, write_file, CacheEntry, DiskCache},
+    streaming::{CircuitHeader, OpcodeStream, WitnessStream},
+    Backend, BackendError, CommitmentScheme, Contract, ContractGenError, ContractOptions,
+    ContractPlatform, FieldModulus, Language, OpcodeResolutionError, PartialWitnessGenerator,
+    Proof, ProofSystemCompiler, ProvingKey, SmartContract, VerificationKey, WitnessMap,
+};
+
+type KeyPair = (ProvingKey, VerificationKey);
+
+/// An in-memory cache of proving and verification keys, optionally backed by a directory
+#[derive(Debug)]
+pub struct KeyCache {
+    keys: DiskCache<(String, [u8; 32]), KeyPair>,
+}
+
+impl Default for KeyCache {
+    fn default() -> Self {
+        KeyCache {
+            keys: DiskCache::new("keys", None),
+        }
+    }
+}
+
+impl KeyCache {
+    pub fn new() -> Self {
+        KeyCache::default()
+    }
+
+    /// Creates a cache which also stores keys in `directory`, so that they outlive the process
+    pub fn with_directory(directory: impl Into<PathBuf>) -> Self {
+        KeyCache {
+            keys: DiskCache::new("keys", Some(directory.into())),
+        }
+    }
+
+    /// Returns the keys for `circuit`, preprocessing it with `backend` if they are not cached
+    pub fn get_or_preprocess<B: ProofSystemCompiler + ?Sized>(
+        &self,
+        backend: &B,
+        circuit: &Circuit,
+    ) -> Result<KeyPair, BackendError> {
+        self.get_or_derive(&cache_name(backend), circuit, || {
+            backend.preprocess(circuit)
+        })
+    }
+
+    // Returns the keys for `circuit`, deriving them with `derive` if they are not cached.
+    // Keys are stored at `<directory>/<backend>/<digest>.pk` and `.vk`
+    fn get_or_derive(
+        &self,
+        backend: &str,
+        circuit: &Circuit,
+        derive: impl FnOnce() -> Result<KeyPair, BackendError>,
+    ) -> Result<KeyPair, BackendError> {
+        let digest = circuit_digest(circuit);
+        let path = || PathBuf::from(directory_name(backend)).join(hex::encode(digest));
+        self.keys
+            .get_or_derive((backend.to_owned(), digest), path, derive)
+    }
+
+    /// Removes all keys from memory. Keys stored on disk are kept.
+    pub fn clear(&self) {
+        self.keys.clear();
+    }
+}
+
+impl CacheEntry for KeyPair {
+    fn read(path: &Path) -> Option<Self> {
+        let proving_key = std::fs::read(path.with_extension("pk")).ok()?;
+        let verification_key = std::fs::read(path.with_extension("vk")).ok()?;
+        Some((ProvingKey(proving_key), VerificationKey(verification_key)))
+    }
+
+    fn write(&self, path: &Path) -> std::io::Result<()> {
+        let (pk, vk) = self;
+        write_file(&path.with_extension("pk"), &pk.0)?;
+        write_file(&path.with_extension("vk"), &vk.0)
+    }
+}
+
+// Keys derived with different commitment schemes are not interchangeable
+fn cache_name<B: ProofSystemCompiler + ?Sized>(backend: &B) -> String {
+    format!("{}-{}", backend.name(), backend.commitment_scheme())
+}
+
+/// A backend whose `preprocess` is served from a `KeyCache`.
+///
+/// Every other method is forwarded to the wrapped backend, except `prove_batch`
+/// which uses the default implementation so that it also benefits from the cache.
+#[derive(Debug)]
+pub struct CachingBackend<B> {
+    backend: B,
+    cache: KeyCache,
+}
+
+impl<B> CachingBackend<B> {
+    pub fn new(backend: B, cache: KeyCache) -> Self {
+        CachingBackend { backend, cache }
+    }
+
+    pub fn cache(&self) -> &KeyCache {
+        &self.cache
+    }
+
+    pub fn into_inner(self) -> B {
+        self.backend
+    }
+}
+
+impl<B: Backend> Backend for CachingBackend<B> {}
+
+impl<B: PartialWitnessGenerator> PartialWitnessGenerator for CachingBackend<B> {
+    fn solve_blackbox_function_call(
+        &self,
+        initial_witness: &mut WitnessMap,
+        func_call: &BlackBoxFuncCall,
+    ) -> Result<(), OpcodeResolutionError> {
+        self.backend
+            .solve_blackbox_function_call(initial_witness, func_call)
+    }
+}
+
+impl<B: SmartContract> SmartContract for CachingBackend<B> {
+    fn supported_platforms(&self) -> HashSet<ContractPlatform> {
+        self.backend.supported_platforms()
+    }
+
+    fn contract_from_cs(
+        &self,
+        circuit: &Circuit,
+        platform: ContractPlatform,
+        options: &ContractOptions,
+    ) -> Result<Contract, ContractGenError> {
+        self.backend.contract_from_cs(circuit, platform, options)
+    }
+
+    fn verifier_contract(
+        &self,
+        verification_key: &VerificationKey,
+        platform: ContractPlatform,
+        options: &ContractOptions,
+    ) -> Result<Contract, ContractGenError> {
+        self.backend
+            .verifier_contract(verification_key, platform, options)
+    }
+
+    fn eth_contract_from_cs(
+        &self,
+        circuit: &Circuit,
+        options: &ContractOptions,
+    ) -> Result<String, ContractGenError> {
+        self.backend.eth_contract_from_cs(circuit, options)
+    }
+}
+
+impl<B: ProofSystemCompiler> ProofSystemCompiler for CachingBackend<B> {
+    fn np_language(&self) -> Language {
+        self.backend.np_language()
+    }
+
+    fn name(&self) -> &str {
+        self.backend.name()
+    }
+
+    fn np_field(&self) -> FieldModulus {
+        self.backend.np_field()
+    }
+
+    fn blackbox_function_supported(&self, opcode: &BlackBoxFunc) -> bool {
+        self.backend.blackbox_function_supported(opcode)
+    }
+
+    fn supported_opcodes(&self) -> HashSet<BlackBoxFunc> {
+        self.backend.supported_opcodes()
+    }
+
+    fn commitment_scheme(&self) -> CommitmentScheme {
+        self.backend.commitment_scheme()
+    }
+
+    fn supported_commitment_schemes(&self) -> HashSet<CommitmentScheme> {
+        self.backend.supported_commitment_schemes()
+    }
+
+    fn set_commitment_scheme(&mut self, scheme: CommitmentScheme) -> Result<(), BackendError> {
+        self.backend.set_commitment_scheme(scheme)
+    }
+
+    fn lookup_table_bits(&self) -> Option<u32> {
+        self.backend.lookup_table_bits()
+    }
+
+    fn gate_weights(&self) -> GateWeights {
+        self.backend.gate_weights()
+    }
+
+    fn proving_time_model(&self) -> Option<ProvingTimeModel> {
+        self.backend.proving_time_model()
+    }
+
+    fn prove_with_meta(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+    ) -> Result<Vec<u8>, BackendError> {
+        self.backend.prove_with_meta(circuit, witness_values)
+    }
+
+    fn verify_from_cs(
+        &self,
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+    ) -> Result<bool, BackendError> {
+        self.backend.verify_from_cs(proof, public_inputs, circuit)
+    }
+
+    fn get_exact_circuit_size(&self, circuit: &Circuit) -> Result<u32, BackendError> {
+        self.backend.get_exact_circuit_size(circuit)
+    }
+
+    fn prove_streaming(
+        &self,
+        header: &CircuitHeader,
+        opcodes: &mut OpcodeStream,
+        witness_values: &mut WitnessStream,
+    ) -> Result<Vec<u8>, BackendError> {
+        self.backend
+            .prove_streaming(header, opcodes, witness_values)
+    }
+
+    // Keys are checked against the field before the cache, which may hold keys derived
+    // by a build of ACVM with another field
+    fn preprocess(&self, circuit: &Circuit) -> Result<KeyPair, BackendError> {
+        self.check_field_compatibility()?;
+        self.cache.get_or_preprocess(&self.backend, circuit)
+    }
+
+    fn crs_num_points(&self, circuit: &Circuit) -> Option<usize> {
+        self.backend.crs_num_points(circuit)
+    }
+
+    fn preprocess_with_crs(&self, circuit: &Circuit, crs: &Crs) -> Result<KeyPair, BackendError> {
+        self.check_field_compatibility()?;
+        self.cache
+            .get_or_derive(&cache_name(&self.backend), circuit, || {
+                self.backend.preprocess_with_crs(circuit, crs)
+            })
+    }
+
+    fn prove_with_pk(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+        proving_key: &ProvingKey,
+    ) -> Result<Vec<u8>, BackendError> {
+        self.backend
+            .prove_with_pk(circuit, witness_values, proving_key)
+    }
+
+    fn verify_with_vk(
+        &self,
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+        verification_key: &VerificationKey,
+    ) -> Result<bool, BackendError> {
+        self.backend
+            .verify_with_vk(proof, public_inputs, circuit, verification_key)
+    }
+
+    fn public_inputs_from_proof(
+        &self,
+        proof: &[u8],
+        circuit: &Circuit,
+    ) -> Result<Vec<FieldElement>, BackendError> {
+        self.backend.public_inputs_from_proof(proof, circuit)
+    }
+
+    fn max_circuit_size(&self) -> Option<u32> {
+        self.backend.max_circuit_size()
+    }
+
+    fn supports_aggregation(&self) -> bool {
+        self.backend.supports_aggregation()
+    }
+
+    fn aggregate(&self, proofs: &[Proof], vks: &[VerificationKey]) -> Result<Proof, BackendError> {
+        self.backend.aggregate(proofs, vks)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{disk_cache::directory_name, testing::MockBackend};
+    use acir::{circuit::PublicInputs, native_types::Witness};
+
+    fn circuits() -> (Circuit, Circuit) {
+        let circuit = Circuit {
+            current_witness_index: 1,
+            opcodes: Vec::new(),
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        };
+        (circuit, Circuit::default())
+    }
+
+    #[test]
+    fn keys_are_derived_once_per_circuit() {
+        let (circuit, other) = circuits();
+        let backend = CachingBackend::new(MockBackend::new(), KeyCache::new());
+
+        let keys = backend.preprocess(&circuit).unwrap();
+        assert_eq!(backend.preprocess(&circuit).unwrap(), keys);
+        let witness = WitnessMap::from([(Witness(1), FieldElement::one())]);
+        backend
+            .prove_batch(&circuit, &[witness.clone(), witness])
+            .unwrap();
+        assert_eq!(backend.backend.num_preprocessed(), 1);
+
+        assert_ne!(backend.preprocess(&other).unwrap(), keys);
+        assert_eq!(backend.backend.num_preprocessed(), 2);
+    }
+
+    #[test]
+    fn keys_are_reloaded_from_disk() {
+        let (circuit, _) = circuits();
+        let dir = tempfile::tempdir().unwrap();
+
+        let backend = MockBackend::new();
+        let keys = KeyCache::with_directory(dir.path())
+            .get_or_preprocess(&backend, &circuit)
+            .unwrap();
+
+        // A new cache has nothing in memory, so must find the keys on disk
+        let cache = KeyCache::with_directory(dir.path());
+        assert_eq!(cache.get_or_preprocess(&backend, &circuit).unwrap(), keys);
+        assert_eq!(backend.num_preprocessed(), 1);
+
+        // Only complete keys are written, so no partial files are left behind
+        let backend_dir = dir.path().join(directory_name(&cache_name(&backend)));
+        let mut files: Vec<String> = std::fs::read_dir(backend_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        let digest = hex::encode(circuit_digest(&circuit));
+        assert_eq!(files, vec![format!("{digest}.pk"), format!("{digest}.vk")]);
+    }
+
+    #[test]
+    fn backend_names_do_not_collide_on_disk() {
+        assert_ne!(directory_name("a/b"), directory_name("a_b"));
+        assert_ne!(directory_name("a b"), directory_name("a_b"));
+        assert_eq!(directory_name("barretenberg-2"), "barretenberg-2");
+    }
+}
@@ -1,21 +1,91 @@
 // Key is currently {NPComplete_lang}_{OptionalFanIn}_ProofSystem_OrgName
 // Org name is needed because more than one implementation of the same proof system may arise
 
+// Without the default `std` feature, the solver and the compiler build with only `core` and
+// `alloc`. Parts which need the standard library, such as files, threads, locks and
+// clocks, are only available with it.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "abi")]
+pub mod abi;
+#[cfg(feature = "arkworks")]
+pub mod arkworks;
+#[cfg(feature = "async")]
+pub mod async_backend;
+pub mod backend_tests;
+#[cfg(feature = "std")]
+pub mod backends;
+#[cfg(feature = "wasm-bindgen")]
+pub mod bindings;
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod circom;
+#[cfg(feature = "std")]
+pub mod circuit_cache;
+pub mod collections;
 pub mod compiler;
+mod contract;
+#[cfg(feature = "std")]
+pub mod crs;
+#[cfg(feature = "std")]
+mod disk_cache;
+#[cfg(feature = "external")]
+pub mod external;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+#[cfg(feature = "halo2")]
+pub mod halo2;
+#[cfg(feature = "std")]
+pub mod key_cache;
+pub mod metrics;
+pub mod profile;
+mod proof;
 pub mod pwg;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "std")]
+pub mod streaming;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod trace;
+#[cfg(feature = "std")]
+pub mod versioning;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "zkinterface")]
+pub mod zkinterface;
 
+use crate::collections::HashSet;
+use crate::compiler::{CompileError, GateWeights, ProvingTimeModel};
+#[cfg(feature = "std")]
+use crate::crs::Crs;
 use crate::pwg::arithmetic::ArithmeticSolver;
+#[cfg(feature = "std")]
+use crate::streaming::{CircuitHeader, OpcodeStream, WitnessStream};
 use acir::{
-    circuit::{directives::Directive, opcodes::BlackBoxFuncCall, Circuit, Opcode},
-    native_types::{Expression, Witness},
+    circuit::{directives::Directive, opcodes::BlackBoxFuncCall, Circuit, Opcode, Program},
+    native_types::{Expression, MissingWitness, Witness, WitnessOutOfBounds, WitnessStack},
     BlackBoxFunc,
 };
-use std::collections::BTreeMap;
+use alloc::{borrow::ToOwned, format, string::String, vec::Vec};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 // re-export acir
 pub use acir;
-pub use acir::FieldElement;
+pub use acir::native_types::WitnessMap;
+pub use acir::{AcirField, FieldElement};
+pub use contract::{
+    verify_calldata, verify_selector, Contract, ContractGenError, ContractOptions,
+    ContractPlatform, VERIFY_SIGNATURE,
+};
+pub use proof::{Proof, ProofMismatch};
 
 // This enum represents the different cases in which an
 // opcode can be unsolvable.
@@ -25,78 +95,405 @@ pub use acir::FieldElement;
 // TODO: ExpressionHasTooManyUnknowns is specific for arithmetic expressions
 // TODO: we could have a error enum for arithmetic failure cases in that module
 // TODO that can be converted into an OpcodeNotSolvable or OpcodeResolutionError enum
-#[derive(PartialEq, Eq, Debug, Error)]
-pub enum OpcodeNotSolvable {
+#[derive(Clone, PartialEq, Eq, Debug, Error)]
+pub enum OpcodeNotSolvable<F: AcirField = FieldElement> {
     #[error("missing assignment for witness index {0}")]
     MissingAssignment(u32),
     #[error("expression has too many unknowns {0}")]
-    ExpressionHasTooManyUnknowns(Expression),
+    ExpressionHasTooManyUnknowns(Expression<F>),
 }
 
 #[derive(PartialEq, Eq, Debug, Error)]
-pub enum OpcodeResolutionError {
+pub enum OpcodeResolutionError<F: AcirField = FieldElement> {
     #[error("cannot solve opcode: {0}")]
-    OpcodeNotSolvable(OpcodeNotSolvable),
+    OpcodeNotSolvable(OpcodeNotSolvable<F>),
     #[error("backend does not currently support the {0} opcode. ACVM does not currently have a fallback for this opcode.")]
     UnsupportedBlackBoxFunc(BlackBoxFunc),
+    #[error("backend does not support the {} opcode, which is called by opcode {opcode_index}: {call}", call.name)]
+    UnsupportedBlackBoxFuncCall {
+        /// The index of the opcode in the circuit or function which contains it
+        opcode_index: usize,
+        call: BlackBoxFuncCall,
+    },
     #[error("could not satisfy all constraints")]
     UnsatisfiedConstrain,
     #[error("unexpected opcode, expected {0}, but got {1}")]
     UnexpectedOpcode(&'static str, BlackBoxFunc),
     #[error("expected {0} inputs for function {1}, but got {2}")]
     IncorrectNumFunctionArguments(usize, BlackBoxFunc, usize),
+    #[error("call to function {0} which is not in the program")]
+    UnknownFunction(u32),
+    #[error(
+        "function {0} has {1} parameters and return values, but it is called with {2} inputs and outputs"
+    )]
+    IncorrectNumCallArguments(u32, usize, usize),
+    #[error("function {0} calls itself, recursive programs cannot be solved")]
+    RecursiveCall(u32),
+    #[error(
+        "cannot range check to {num_bits} bits, as every field element fits in {max_num_bits} bits"
+    )]
+    RangeBitSizeTooLarge { num_bits: u32, max_num_bits: u32 },
+    #[error("there is no handler for the oracle {0}")]
+    UnsupportedOracle(String),
+    #[error("oracle {0} has {1} outputs, but the handler returned {2} values")]
+    IncorrectNumOracleOutputs(String, usize, usize),
+    #[error("call {index} to oracle {name} does not match the transcript")]
+    OracleTranscriptMismatch { index: usize, name: String },
+    #[error("invalid initial witness: {0}")]
+    InvalidInitialWitness(#[from] WitnessOutOfBounds),
+    #[error("the inputs of function {0} must have the same bit size, but have {1} and {2} bits")]
+    MismatchedBitSizes(BlackBoxFunc, u32, u32),
+    #[error("cannot decompose a value into radix {0}, which must be between 2 and 256")]
+    InvalidRadix(u32),
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+}
+
+// The errors which a backend can return when proving or verifying
+#[derive(PartialEq, Eq, Debug, Error)]
+pub enum BackendError {
+    #[error("backend does not support the {0} opcode")]
+    UnsupportedOpcode(String),
+    #[error("backend does not support {0}")]
+    UnsupportedFeature(&'static str),
+    #[error("the common reference string is missing or too small for this circuit")]
+    CrsMissing,
+    #[error("serialization failed: {0}")]
+    SerializationError(String),
+    #[error("backend failure: {0}")]
+    Other(String),
+    #[error(transparent)]
+    ProofMismatch(#[from] ProofMismatch),
+    #[error(transparent)]
+    Compile(#[from] CompileError),
+    #[error("backend does not support the {0} commitment scheme")]
+    UnsupportedCommitmentScheme(CommitmentScheme),
+    #[error("backend uses the field with modulus {backend}, but ACVM was compiled with the field with modulus {acvm}{}", field_feature_hint(backend))]
+    FieldMismatch {
+        backend: FieldModulus,
+        acvm: FieldModulus,
+    },
+}
+
+// The ways in which public inputs given to a verifier can fail to match the circuit
+#[derive(PartialEq, Eq, Debug, Error)]
+pub enum PublicInputError {
+    #[error("the circuit has {expected} public inputs, but {found} were provided")]
+    IncorrectNumPublicInputs { expected: usize, found: usize },
+    #[error("public input {index} is witness {found}, which is not a public input of the circuit")]
+    NotPublic { index: usize, found: u32 },
+    #[error("public input {index} is witness {found}, but the circuit declares witness {expected} at that position")]
+    OutOfOrder {
+        index: usize,
+        expected: u32,
+        found: u32,
+    },
+}
+
+/// The modulus of the prime field which constraints are defined over
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FieldModulus(pub BigUint);
+
+impl FieldModulus {
+    /// The scalar field of the BN254 curve
+    pub fn bn254() -> Self {
+        FieldModulus(
+            BigUint::parse_bytes(
+                b"30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001",
+                16,
+            )
+            .expect("infallible: the modulus is valid hex"),
+        )
+    }
+
+    /// The scalar field of the BLS12-381 curve
+    pub fn bls12_381() -> Self {
+        FieldModulus(
+            BigUint::parse_bytes(
+                b"73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001",
+                16,
+            )
+            .expect("infallible: the modulus is valid hex"),
+        )
+    }
+
+    /// The Goldilocks field, of integers modulo 2^64 - 2^32 + 1, which STARK backends use
+    pub fn goldilocks() -> Self {
+        FieldModulus(BigUint::from(0xFFFF_FFFF_0000_0001_u64))
+    }
+
+    /// The field which `FieldElement` is defined over, and therefore the field of every circuit
+    pub fn acvm() -> Self {
+        FieldModulus::of::<FieldElement>()
+    }
+
+    /// The field which `F` is defined over
+    pub fn of<F: AcirField>() -> Self {
+        FieldModulus(F::modulus())
+    }
+
+    /// Returns the field called `name`, which is the name of the feature that compiles ACVM
+    /// with it: `bn254`, `bls12_381` or `goldilocks`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bn254" => Some(FieldModulus::bn254()),
+            "bls12_381" => Some(FieldModulus::bls12_381()),
+            "goldilocks" => Some(FieldModulus::goldilocks()),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the feature which compiles ACVM with this field, if there is one
+    pub fn name(&self) -> Option<&'static str> {
+        ["bn254", "bls12_381", "goldilocks"]
+            .into_iter()
+            .find(|name| FieldModulus::from_name(name).as_ref() == Some(self))
+    }
 }
 
-pub trait Backend: SmartContract + ProofSystemCompiler + PartialWitnessGenerator {}
+fn field_feature_hint(backend: &FieldModulus) -> String {
+    match backend.name() {
+        Some(name) => format!(", enable the `{name}` feature of ACVM to use this backend"),
+        None => String::new(),
+    }
+}
+
+impl core::fmt::Display for FieldModulus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "0x{}", self.0.to_str_radix(16))
+    }
+}
+
+/// Returns an error if a backend which uses `backend_field` cannot prove circuits built by ACVM
+pub fn check_field_compatibility(backend_field: &FieldModulus) -> Result<(), BackendError> {
+    let acvm_field = FieldModulus::acvm();
+    if *backend_field == acvm_field {
+        Ok(())
+    } else {
+        Err(BackendError::FieldMismatch {
+            backend: backend_field.clone(),
+            acvm: acvm_field,
+        })
+    }
+}
+
+/// A backend specific proving key, produced by `ProofSystemCompiler::preprocess`.
+///
+/// The key is opaque to ACVM, so that it can be stored and reused across calls.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvingKey(pub Vec<u8>);
+
+/// A backend specific verification key, produced by `ProofSystemCompiler::preprocess`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationKey(pub Vec<u8>);
+
+/// Everything that the compiler and tooling need to know about what a backend can do
+#[derive(Clone, Debug)]
+pub struct BackendCapabilities {
+    /// The language which circuits are compiled to, including the arithmetic width
+    pub np_language: Language,
+    /// The field which the proof system is defined over
+    pub np_field: FieldModulus,
+    /// The commitment scheme which is currently selected
+    pub commitment_scheme: CommitmentScheme,
+    /// The commitment schemes which can be selected
+    pub supported_commitment_schemes: HashSet<CommitmentScheme>,
+    /// The size of the lookup tables used for AND, XOR and RANGE, if lookups are supported
+    pub lookup_table_bits: Option<u32>,
+    /// Whether the backend can aggregate proofs
+    pub supports_recursion: bool,
+    /// The largest circuit that the backend can prove, in gates, if there is a limit
+    pub max_circuit_size: Option<u32>,
+    /// The black box functions which the backend supports natively
+    pub supported_blackbox_functions: HashSet<BlackBoxFunc>,
+    /// The relative cost of the backend's gates, which the compiler minimises
+    pub gate_weights: GateWeights,
+}
+
+impl BackendCapabilities {
+    /// Returns the arithmetic width, or `None` for languages where expressions are not split
+    pub fn arithmetic_width(&self) -> Option<usize> {
+        self.np_language.width()
+    }
+}
+
+pub trait Backend: SmartContract + ProofSystemCompiler + PartialWitnessGenerator {
+    /// Solves the opcodes, after checking that the backend's field is ACVM's and that the
+    /// backend supports every black box function.
+    ///
+    /// This catches circuits which were not compiled for this backend before any opcode is solved.
+    fn solve_supported(
+        &self,
+        initial_witness: &mut WitnessMap,
+        opcodes: Vec<Opcode>,
+    ) -> Result<(), OpcodeResolutionError> {
+        self.check_field_compatibility()?;
+        let supported_opcodes = self.supported_opcodes();
+        let unsupported =
+            opcodes
+                .iter()
+                .enumerate()
+                .find_map(|(opcode_index, opcode)| match opcode {
+                    Opcode::BlackBoxFuncCall(call) if !supported_opcodes.contains(&call.name) => {
+                        Some(OpcodeResolutionError::UnsupportedBlackBoxFuncCall {
+                            opcode_index,
+                            call: call.clone(),
+                        })
+                    }
+                    _ => None,
+                });
+        if let Some(err) = unsupported {
+            return Err(err);
+        }
+
+        self.solve(initial_witness, opcodes)
+    }
+}
+
+// Checks that the initial witness belongs to the circuit, and switches it to dense
+// storage, as the number of witnesses is known
+pub(crate) fn prepare_witness<F: AcirField>(
+    witness: &mut WitnessMap<F>,
+    circuit: &Circuit<F>,
+) -> Result<(), WitnessOutOfBounds> {
+    witness.validate(circuit)?;
+    *witness = core::mem::take(witness).into_dense(circuit.num_vars());
+    Ok(())
+}
 
 /// This component will generate the backend specific output for
 /// each OPCODE.
 /// Returns an Error if the backend does not support that OPCODE
-pub trait PartialWitnessGenerator {
+pub trait PartialWitnessGenerator<F: AcirField = FieldElement> {
     fn solve(
         &self,
-        initial_witness: &mut BTreeMap<Witness, FieldElement>,
-        opcodes: Vec<Opcode>,
-    ) -> Result<(), OpcodeResolutionError> {
-        if opcodes.is_empty() {
-            return Ok(());
-        }
-        let mut unsolved_opcodes: Vec<Opcode> = Vec::new();
-
-        for opcode in opcodes.into_iter() {
-            let resolution = match &opcode {
-                Opcode::Arithmetic(expr) => ArithmeticSolver::solve(initial_witness, expr),
-                Opcode::BlackBoxFuncCall(bb_func) => {
-                    Self::solve_blackbox_function_call(initial_witness, bb_func)
-                }
-                Opcode::Directive(directive) => Self::solve_directives(initial_witness, directive),
-            };
+        initial_witness: &mut WitnessMap<F>,
+        opcodes: Vec<Opcode<F>>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        self.solve_with_calls(initial_witness, opcodes, &[], &mut WitnessStack::new())
+    }
+
+    /// Solves the entry point of a program, resolving calls by solving the callees.
+    ///
+    /// Fails with `InvalidInitialWitness` before solving if a witness which is not part
+    /// of the entry point is assigned.
+    fn solve_program(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        program: &Program<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        prepare_witness(initial_witness, program.main())?;
+        self.solve_with_calls(
+            initial_witness,
+            program.main().opcodes.clone(),
+            &program.functions,
+            &mut WitnessStack::new(),
+        )
+    }
+
+    /// Solves a program like `solve_program`, and returns the witness of every call
+    /// which was made, with the witness of the entry point last
+    fn solve_program_with_stack(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        program: &Program<F>,
+    ) -> Result<WitnessStack<F>, OpcodeResolutionError<F>> {
+        let mut stack = WitnessStack::new();
+        prepare_witness(initial_witness, program.main())?;
+        self.solve_with_calls(
+            initial_witness,
+            program.main().opcodes.clone(),
+            &program.functions,
+            &mut stack,
+        )?;
+        stack.push(0, initial_witness.clone());
+        Ok(stack)
+    }
+
+    // Solves the opcodes of a function, where `functions` are the functions
+    // of the enclosing program which calls are resolved against, and the
+    // witnesses of completed calls are pushed onto `stack`
+    fn solve_with_calls(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        opcodes: Vec<Opcode<F>>,
+        functions: &[Circuit<F>],
+        stack: &mut WitnessStack<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        pwg::solve_opcodes(
+            self,
+            initial_witness,
+            &opcodes,
+            0..opcodes.len(),
+            functions,
+            stack,
+            None,
+        )
+        .map(|_| ())
+    }
 
-            match resolution {
-                Ok(_) => {
-                    // We do nothing in the happy case
-                }
-                Err(OpcodeResolutionError::OpcodeNotSolvable(_)) => {
-                    // For opcode not solvable errors, we push those opcodes to the back as
-                    // it could be because the opcodes are out of order, ie this assignment
-                    // relies on a later opcodes's results
-                    unsolved_opcodes.push(opcode);
-                }
-                Err(err) => return Err(err),
+    /// Attempts to solve a single opcode, where `functions` and `stack` are as in
+    /// `solve_with_calls`.
+    ///
+    /// Returns `OpcodeNotSolvable` if the opcode is missing inputs, in which case it is
+    /// retried once they are assigned.
+    fn solve_opcode(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        opcode: &Opcode<F>,
+        functions: &[Circuit<F>],
+        stack: &mut WitnessStack<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        match opcode {
+            Opcode::Arithmetic(expr) => ArithmeticSolver::solve(initial_witness, expr),
+            Opcode::BlackBoxFuncCall(bb_func) => {
+                let _span = trace::span!(TRACE, "black_box", name = bb_func.name.name());
+                self.solve_blackbox_function_call(initial_witness, bb_func)
             }
+            Opcode::Directive(directive) => {
+                let _span = trace::span!(TRACE, "directive", name = directive.name());
+                self.solve_directives(initial_witness, directive)
+            }
+            Opcode::Call {
+                id,
+                inputs,
+                outputs,
+            } => pwg::call::solve_call(
+                self,
+                initial_witness,
+                functions,
+                stack,
+                *id,
+                inputs,
+                outputs,
+            ),
         }
-        self.solve(initial_witness, unsolved_opcodes)
     }
 
     fn solve_blackbox_function_call(
-        initial_witness: &mut BTreeMap<Witness, FieldElement>,
+        &self,
+        initial_witness: &mut WitnessMap<F>,
         func_call: &BlackBoxFuncCall,
-    ) -> Result<(), OpcodeResolutionError>;
+    ) -> Result<(), OpcodeResolutionError<F>>;
+
+    /// Returns true if the solver may solve opcodes together rather than one at a time
+    /// through `solve_opcode`: arithmetic opcodes are solved with `ArithmeticSolver`, in
+    /// runs on several threads and keeping the partial evaluations of those which cannot
+    /// be solved yet, and invert directives are inverted in batches.
+    ///
+    /// Backends which change how `solve_opcode` solves arithmetic opcodes or how
+    /// `solve_directives` solves invert directives, or which observe every opcode, should
+    /// return false. Wrappers which pass these opcodes to the backend they wrap should
+    /// return its answer.
+    fn batch_opcodes(&self) -> bool {
+        true
+    }
 
     // Check if all of the inputs to the function have assignments
     // Returns true if all of the inputs have been assigned
     fn all_func_inputs_assigned(
-        initial_witness: &mut BTreeMap<Witness, FieldElement>,
+        &self,
+        initial_witness: &mut WitnessMap<F>,
         func_call: &BlackBoxFuncCall,
     ) -> bool {
         // This call to .any returns true, if any of the witnesses do not have assignments
@@ -108,29 +505,97 @@ pub trait PartialWitnessGenerator {
     }
 
     fn solve_directives(
-        initial_witness: &mut BTreeMap<Witness, FieldElement>,
-        directive: &Directive,
-    ) -> Result<(), OpcodeResolutionError> {
-        pwg::directives::solve_directives(initial_witness, directive)
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        directive: &Directive<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        match directive {
+            Directive::Oracle {
+                name,
+                inputs,
+                outputs,
+            } => pwg::oracle::solve_oracle(self, initial_witness, name, inputs, outputs),
+            _ => pwg::directives::solve_directives(initial_witness, directive),
+        }
+    }
+
+    /// Returns the values of the outputs of an oracle, given the values of its inputs.
+    ///
+    /// Oracles provide data from outside of the circuit, so by default there are none.
+    fn solve_oracle_call(
+        &self,
+        name: &str,
+        _inputs: &[F],
+    ) -> Result<Vec<F>, OpcodeResolutionError<F>> {
+        Err(OpcodeResolutionError::UnsupportedOracle(name.to_owned()))
     }
 }
 
 pub trait SmartContract {
-    // Takes a verification  key and produces a smart contract
-    // The platform indicator allows a backend to support multiple smart contract platforms
-    //
-    // fn verification_key(&self, platform: u8, vk: &[u8]) -> &[u8] {
-    //     todo!("currently the backend is not configured to use this.")
-    // }
+    /// The platforms which `contract_from_cs` can generate contracts for.
+    ///
+    /// Backends which override `verifier_contract` should also override this.
+    fn supported_platforms(&self) -> HashSet<ContractPlatform> {
+        HashSet::from([ContractPlatform::EvmSolidity])
+    }
+
+    /// Takes an ACIR circuit and produces a contract which verifies its proofs on the given platform.
+    ///
+    /// The default implementation supports only Solidity, using `eth_contract_from_cs`.
+    fn contract_from_cs(
+        &self,
+        circuit: &Circuit,
+        platform: ContractPlatform,
+        options: &ContractOptions,
+    ) -> Result<Contract, ContractGenError> {
+        match platform {
+            ContractPlatform::EvmSolidity => Ok(Contract {
+                platform,
+                source: self.eth_contract_from_cs(circuit, options)?,
+            }),
+            _ => Err(ContractGenError::UnsupportedPlatform(platform)),
+        }
+    }
+
+    /// Takes a verification key returned by `preprocess` and produces a contract which
+    /// verifies proofs for that circuit on the given platform.
+    ///
+    /// This avoids preprocessing the circuit again when the keys already exist.
+    fn verifier_contract(
+        &self,
+        _verification_key: &VerificationKey,
+        platform: ContractPlatform,
+        _options: &ContractOptions,
+    ) -> Result<Contract, ContractGenError> {
+        Err(ContractGenError::UnsupportedPlatform(platform))
+    }
+
+    /// Takes an ACIR circuit and returns an EVM verifier written in Yul, which costs less
+    /// gas to verify with than the Solidity verifier.
+    ///
+    /// Backends provide this by supporting `ContractPlatform::EvmYul` in `contract_from_cs`.
+    fn yul_contract_from_cs(
+        &self,
+        circuit: &Circuit,
+        options: &ContractOptions,
+    ) -> Result<String, ContractGenError> {
+        self.contract_from_cs(circuit, ContractPlatform::EvmYul, options)
+            .map(|contract| contract.source)
+    }
 
     /// Takes an ACIR circuit, the number of witnesses and the number of public inputs
-    /// Then returns an Ethereum smart contract
+    /// Then returns an Ethereum smart contract, configured by `options`
     ///
-    /// XXX: This will be deprecated in future releases for the above method.
+    /// XXX: This will be deprecated in future releases in favour of `verifier_contract`
+    /// and `contract_from_cs`.
     /// This deprecation may happen in two stages:
     /// The first stage will remove `num_witnesses` and `num_public_inputs` parameters.
     /// If we cannot avoid `num_witnesses`, it can be added into the Circuit struct.
-    fn eth_contract_from_cs(&self, circuit: Circuit) -> String;
+    fn eth_contract_from_cs(
+        &self,
+        circuit: &Circuit,
+        options: &ContractOptions,
+    ) -> Result<String, ContractGenError>;
 }
 
 pub trait ProofSystemCompiler {
@@ -139,9 +604,94 @@ pub trait ProofSystemCompiler {
     /// as this in most cases will be inefficient. For this reason, we want to throw a hard error
     /// if the language and proof system does not line up.
     fn np_language(&self) -> Language;
+
+    /// An identifier for the proof system and its version, which is recorded in `Proof`s.
+    ///
+    /// The default is the name of the backend's type. Proofs and cached keys are tied to
+    /// the name, so backends should override this with a name which includes their version.
+    fn name(&self) -> &str {
+        core::any::type_name::<Self>()
+    }
+
+    /// The modulus of the field which the proof system's constraints are defined over.
+    ///
+    /// The default is the field which ACVM was compiled with.
+    fn np_field(&self) -> FieldModulus {
+        FieldModulus::acvm()
+    }
+
+    /// Returns an error if the proof system's field is not the field which ACVM was compiled with.
+    ///
+    /// Circuits and witnesses are reduced modulo ACVM's field, so proofs created by a backend
+    /// with a different field are meaningless. `compiler::compile_for_backend`,
+    /// `Backend::solve_supported`, `prove_batch` and `CachingBackend` check it before compiling,
+    /// solving or proving.
+    fn check_field_compatibility(&self) -> Result<(), BackendError> {
+        check_field_compatibility(&self.np_field())
+    }
     // Returns true if the backend supports the selected blackbox function
     fn blackbox_function_supported(&self, opcode: &BlackBoxFunc) -> bool;
 
+    /// Returns the black box functions which the backend supports natively.
+    ///
+    /// Arithmetic opcodes and directives are always supported. The compiler replaces
+    /// other black box functions with their arithmetic fallback where one exists.
+    fn supported_opcodes(&self) -> HashSet<BlackBoxFunc> {
+        (0..)
+            .map_while(BlackBoxFunc::from_u16)
+            .filter(|func| self.blackbox_function_supported(func))
+            .collect()
+    }
+
+    /// The commitment scheme which is used by `preprocess` and the proving methods.
+    ///
+    /// The default is KZG. Backends which use another scheme should override this.
+    fn commitment_scheme(&self) -> CommitmentScheme {
+        CommitmentScheme::KZG
+    }
+
+    /// The commitment schemes which can be selected using `set_commitment_scheme`
+    fn supported_commitment_schemes(&self) -> HashSet<CommitmentScheme> {
+        HashSet::from([self.commitment_scheme()])
+    }
+
+    /// Selects the commitment scheme used by subsequent preprocessing and proving,
+    /// which trades proof size against the need for a trusted setup.
+    ///
+    /// Keys derived with one scheme cannot be used with another. Returns an error
+    /// if the scheme is not one of the `supported_commitment_schemes`.
+    fn set_commitment_scheme(&mut self, scheme: CommitmentScheme) -> Result<(), BackendError> {
+        if scheme == self.commitment_scheme() {
+            Ok(())
+        } else {
+            Err(BackendError::UnsupportedCommitmentScheme(scheme))
+        }
+    }
+
+    /// Returns the number of bits of the lookup tables used for AND, XOR and RANGE
+    /// if the backend supports lookups.
+    ///
+    /// If this returns `Some`, these functions should be passed through
+    /// `compiler::lower_to_lookups` before compilation.
+    fn lookup_table_bits(&self) -> Option<u32> {
+        None
+    }
+
+    /// The relative cost of the backend's gates, which `compiler::compile_for_backend` uses
+    /// to choose between lowerings.
+    ///
+    /// The default weighs every gate as one. Backends where, for example, lookups are cheap
+    /// and wide gates are expensive should override this.
+    fn gate_weights(&self) -> GateWeights {
+        GateWeights::default()
+    }
+
+    /// The coefficients of the time the backend takes to prove a circuit on this machine,
+    /// which `compiler::estimate_proving_time` uses, if the backend reports them.
+    fn proving_time_model(&self) -> Option<ProvingTimeModel> {
+        None
+    }
+
     /// Creates a Proof given the circuit description and the witness values.
     /// It is important to note that the intermediate witnesses for blackbox functions will not generated
     /// This is the responsibility of the proof system.
@@ -149,9 +699,9 @@ pub trait ProofSystemCompiler {
     /// See `SmartContract` regarding the removal of `num_witnesses` and `num_public_inputs`
     fn prove_with_meta(
         &self,
-        circuit: Circuit,
-        witness_values: BTreeMap<Witness, FieldElement>,
-    ) -> Vec<u8>;
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+    ) -> Result<Vec<u8>, BackendError>;
 
     /// Verifies a Proof, given the circuit description.
     ///
@@ -164,10 +714,188 @@ pub trait ProofSystemCompiler {
         &self,
         proof: &[u8],
         public_inputs: Vec<FieldElement>,
-        circuit: Circuit,
-    ) -> bool;
+        circuit: &Circuit,
+    ) -> Result<bool, BackendError>;
+
+    /// Returns the number of gates which the backend uses for the circuit
+    fn get_exact_circuit_size(&self, circuit: &Circuit) -> Result<u32, BackendError>;
+
+    /// Creates a Proof from a circuit and witness values which are read incrementally,
+    /// so that circuits which do not fit in memory can be proven.
+    ///
+    /// The default implementation collects the circuit and witness into memory and calls
+    /// `prove_with_meta`. Backends which can consume opcodes as they arrive should override this.
+    #[cfg(feature = "std")]
+    fn prove_streaming(
+        &self,
+        header: &CircuitHeader,
+        opcodes: &mut OpcodeStream,
+        witness_values: &mut WitnessStream,
+    ) -> Result<Vec<u8>, BackendError> {
+        let circuit = streaming::collect_circuit(header, opcodes)?;
+        let witness_values = streaming::collect_witness(witness_values)?;
+        self.prove_with_meta(&circuit, &witness_values)
+    }
+
+    /// Derives the proving and verification keys for a circuit.
+    ///
+    /// This is the expensive part of proving and verifying, so callers which
+    /// prove or verify the same circuit repeatedly should preprocess it once
+    /// and use `prove_with_pk` and `verify_with_vk`.
+    ///
+    /// The default returns `BackendError::UnsupportedFeature`, for backends which derive
+    /// their keys each time they prove or verify.
+    fn preprocess(
+        &self,
+        _circuit: &Circuit,
+    ) -> Result<(ProvingKey, VerificationKey), BackendError> {
+        Err(BackendError::UnsupportedFeature("preprocessing"))
+    }
+
+    /// Returns the number of points of the structured reference string which are needed to
+    /// preprocess the circuit, or `None` if the proof system does not use one.
+    fn crs_num_points(&self, _circuit: &Circuit) -> Option<usize> {
+        None
+    }
+
+    /// Derives the proving and verification keys for a circuit using the given reference string,
+    /// which has `crs_num_points` points.
+    ///
+    /// See `crs::preprocess_with_source`. The default implementation ignores the reference string.
+    #[cfg(feature = "std")]
+    fn preprocess_with_crs(
+        &self,
+        circuit: &Circuit,
+        _crs: &Crs,
+    ) -> Result<(ProvingKey, VerificationKey), BackendError> {
+        self.preprocess(circuit)
+    }
+
+    /// Creates a Proof given the circuit description, the witness values and
+    /// a proving key returned by `preprocess` for the same circuit.
+    ///
+    /// The default ignores the key and calls `prove_with_meta`.
+    fn prove_with_pk(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+        _proving_key: &ProvingKey,
+    ) -> Result<Vec<u8>, BackendError> {
+        self.prove_with_meta(circuit, witness_values)
+    }
 
-    fn get_exact_circuit_size(&self, circuit: Circuit) -> u32;
+    /// Verifies a Proof, given the circuit description and a verification key
+    /// returned by `preprocess` for the same circuit.
+    ///
+    /// The default ignores the key and calls `verify_from_cs`.
+    fn verify_with_vk(
+        &self,
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+        _verification_key: &VerificationKey,
+    ) -> Result<bool, BackendError> {
+        self.verify_from_cs(proof, public_inputs, circuit)
+    }
+
+    /// Creates a proof for each of the witness maps, which must all be for the same circuit.
+    ///
+    /// The default implementation preprocesses the circuit once and then proves each
+    /// witness map in turn, or proves each of them with `prove_with_meta` if the backend
+    /// does not support preprocessing. Backends which can prove in parallel should
+    /// override this.
+    fn prove_batch(
+        &self,
+        circuit: &Circuit,
+        witnesses: &[WitnessMap],
+    ) -> Result<Vec<Proof>, BackendError> {
+        self.check_field_compatibility()?;
+        let proving_key = match self.preprocess(circuit) {
+            Ok((proving_key, _)) => Some(proving_key),
+            Err(BackendError::UnsupportedFeature(_)) => None,
+            Err(err) => return Err(err),
+        };
+        witnesses
+            .iter()
+            .map(|witness_values| {
+                let bytes = match &proving_key {
+                    Some(proving_key) => {
+                        self.prove_with_pk(circuit, witness_values, proving_key)?
+                    }
+                    None => self.prove_with_meta(circuit, witness_values)?,
+                };
+                Ok(Proof::new(self.name(), circuit, bytes))
+            })
+            .collect()
+    }
+
+    /// Extracts the values of the public inputs from a proof created by this backend,
+    /// in the order of the circuit's public inputs.
+    ///
+    /// Backends whose proofs do not contain the public inputs return an error.
+    fn public_inputs_from_proof(
+        &self,
+        _proof: &[u8],
+        _circuit: &Circuit,
+    ) -> Result<Vec<FieldElement>, BackendError> {
+        Err(BackendError::UnsupportedFeature(
+            "extracting public inputs from proofs",
+        ))
+    }
+
+    /// Attaches the values of the public inputs which are contained in the proof to it
+    fn attach_public_inputs(
+        &self,
+        proof: &mut Proof,
+        circuit: &Circuit,
+    ) -> Result<(), BackendError> {
+        proof.check(self.name(), circuit, circuit.public_inputs.0.len())?;
+        let values = self.public_inputs_from_proof(&proof.bytes, circuit)?;
+        proof.attach_public_inputs(values)?;
+        Ok(())
+    }
+
+    /// The largest circuit that the backend can prove, in gates, if there is a limit.
+    ///
+    /// This is usually bounded by the size of the common reference string.
+    fn max_circuit_size(&self) -> Option<u32> {
+        None
+    }
+
+    /// Collects the backend's capabilities into a single descriptor.
+    ///
+    /// The default implementation queries the individual capability methods.
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            np_language: self.np_language(),
+            np_field: self.np_field(),
+            commitment_scheme: self.commitment_scheme(),
+            supported_commitment_schemes: self.supported_commitment_schemes(),
+            lookup_table_bits: self.lookup_table_bits(),
+            supports_recursion: self.supports_aggregation(),
+            max_circuit_size: self.max_circuit_size(),
+            supported_blackbox_functions: self.supported_opcodes(),
+            gate_weights: self.gate_weights(),
+        }
+    }
+
+    /// Returns true if the backend can aggregate proofs using `aggregate`
+    fn supports_aggregation(&self) -> bool {
+        false
+    }
+
+    /// Aggregates proofs into a single proof which is valid only if all of them are valid.
+    ///
+    /// `vks` are the verification keys of the circuits which `proofs` were created for,
+    /// in the same order. Backends which support recursion should override this along
+    /// with `supports_aggregation`.
+    fn aggregate(
+        &self,
+        _proofs: &[Proof],
+        _vks: &[VerificationKey],
+    ) -> Result<Proof, BackendError> {
+        Err(BackendError::UnsupportedFeature("proof aggregation"))
+    }
 }
 
 /// Supported NP complete languages
@@ -175,12 +903,92 @@ pub trait ProofSystemCompiler {
 #[derive(Debug, Clone)]
 pub enum Language {
     R1CS,
-    PLONKCSat { width: usize },
+    PLONKCSat {
+        width: usize,
+    },
+    /// PLONK with custom gates, and lookup tables if `lookups` is set
+    UltraPlonk {
+        width: usize,
+        lookups: bool,
+    },
+    /// Algebraic intermediate representation, where constraints are polynomials
+    /// of at most `degree` over the rows of an execution trace with `width` witness
+    /// columns. Circuits compiled for it are laid out as a trace by `compiler::to_air`
+    AIR {
+        width: usize,
+        degree: usize,
+    },
+    /// Customizable constraint systems, where constraints are sums of products
+    /// of at most `degree` linear combinations
+    CCS {
+        degree: usize,
+    },
 }
 
-pub fn hash_constraint_system(cs: &Circuit) -> [u8; 32] {
+impl Language {
+    /// Returns the number of witnesses which fit into a gate, for languages which
+    /// split expressions into gates of a fixed width
+    pub fn width(&self) -> Option<usize> {
+        match self {
+            Language::PLONKCSat { width }
+            | Language::UltraPlonk { width, .. }
+            | Language::AIR { width, .. } => Some(*width),
+            Language::R1CS | Language::CCS { .. } => None,
+        }
+    }
+
+    /// Returns true if the language has lookup tables for AND, XOR and RANGE
+    pub fn supports_lookup(&self) -> bool {
+        matches!(self, Language::UltraPlonk { lookups: true, .. })
+    }
+
+    /// Returns the largest degree of a single constraint
+    pub fn arith_degree(&self) -> usize {
+        match self {
+            Language::R1CS | Language::PLONKCSat { .. } | Language::UltraPlonk { .. } => 2,
+            Language::AIR { degree, .. } | Language::CCS { degree } => *degree,
+        }
+    }
+}
+
+/// Polynomial commitment schemes which a proof system may be built on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CommitmentScheme {
+    /// Small proofs, but needs a structured reference string from a trusted setup
+    KZG,
+    /// Inner product arguments, which need no trusted setup but have larger proofs
+    IPA,
+    /// FRI, which needs no trusted setup and is plausibly post-quantum, but has the largest proofs
+    FRI,
+}
+
+impl CommitmentScheme {
+    pub fn requires_trusted_setup(&self) -> bool {
+        matches!(self, CommitmentScheme::KZG)
+    }
+}
+
+impl core::fmt::Display for CommitmentScheme {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            CommitmentScheme::KZG => "kzg",
+            CommitmentScheme::IPA => "ipa",
+            CommitmentScheme::FRI => "fri",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Returns the SHA256 digest of the circuit's binary serialisation.
+///
+/// Unlike the `Debug` output, the binary serialisation is versioned, so this
+/// digest is stable and can be used as a key when caching artifacts derived
+/// from a circuit, such as proving keys or transformed circuits.
+pub fn circuit_digest(circuit: &Circuit) -> [u8; 32] {
     let mut bytes = Vec::new();
-    cs.write(&mut bytes).expect("could not serialise circuit");
+    circuit
+        .write(&mut bytes)
+        .expect("infallible: writing to a Vec cannot fail");
 
     use sha2::{digest::FixedOutput, Digest, Sha256};
     let mut hasher = Sha256::new();
@@ -189,6 +997,80 @@ pub fn hash_constraint_system(cs: &Circuit) -> [u8; 32] {
     hasher.finalize_fixed().into()
 }
 
+/// Returns the values of the circuit's public inputs, in the order which backends expect them.
+///
+/// This is `Circuit::public_inputs_from_witness`, with a missing witness reported as an
+/// `OpcodeResolutionError` like the other errors of solving.
+pub fn public_input_values<F: AcirField>(
+    circuit: &Circuit<F>,
+    witness_values: &WitnessMap<F>,
+) -> Result<Vec<F>, OpcodeResolutionError<F>> {
+    circuit
+        .public_inputs_from_witness(witness_values)
+        .map_err(|MissingWitness(witness)| {
+            OpcodeResolutionError::OpcodeNotSolvable(OpcodeNotSolvable::MissingAssignment(
+                witness.witness_index(),
+            ))
+        })
+}
+
+/// Returns the values of the circuit's return values after solving, in order.
+///
+/// A missing witness is reported as an `OpcodeResolutionError`, as in `public_input_values`.
+pub fn return_values<F: AcirField>(
+    circuit: &Circuit<F>,
+    witness_values: &WitnessMap<F>,
+) -> Result<Vec<F>, OpcodeResolutionError<F>> {
+    circuit
+        .return_values_from_witness(witness_values)
+        .map_err(|MissingWitness(witness)| {
+            OpcodeResolutionError::OpcodeNotSolvable(OpcodeNotSolvable::MissingAssignment(
+                witness.witness_index(),
+            ))
+        })
+}
+
+/// Checks that `public_inputs` assigns each of the circuit's public inputs in the order
+/// they are declared, and returns their values in that order for `verify_from_cs` and
+/// `verify_with_vk`.
+///
+/// Backends only see the values, so public inputs which are given in the wrong order
+/// just fail verification. This names the first one which is out of place instead.
+pub fn check_public_inputs(
+    circuit: &Circuit,
+    public_inputs: &[(Witness, FieldElement)],
+) -> Result<Vec<FieldElement>, PublicInputError> {
+    let declared = &circuit.public_inputs.0;
+    if public_inputs.len() != declared.len() {
+        return Err(PublicInputError::IncorrectNumPublicInputs {
+            expected: declared.len(),
+            found: public_inputs.len(),
+        });
+    }
+    for (index, ((witness, _), expected)) in public_inputs.iter().zip(declared).enumerate() {
+        if witness == expected {
+            continue;
+        }
+        if declared.contains(witness) {
+            return Err(PublicInputError::OutOfOrder {
+                index,
+                expected: expected.witness_index(),
+                found: witness.witness_index(),
+            });
+        }
+        return Err(PublicInputError::NotPublic {
+            index,
+            found: witness.witness_index(),
+        });
+    }
+    Ok(public_inputs.iter().map(|(_, value)| *value).collect())
+}
+
+#[deprecated(note = "use `circuit_digest` instead")]
+pub fn hash_constraint_system(cs: &Circuit) -> [u8; 32] {
+    circuit_digest(cs)
+}
+
 #[deprecated(
     note = "For backwards compatibility, this method allows you to derive _sensible_ defaults for blackbox function support based on the np language. \n Backends should simply specify what they support."
 )]
@@ -200,10 +1082,8 @@ pub fn default_is_blackbox_supported(
 ) -> compiler::fallback::IsBlackBoxSupported {
     // R1CS does not support any of the blackbox functions by default.
     // The compiler will replace those that it can -- ie range, xor, and
-    fn r1cs_is_supported(opcode: &BlackBoxFunc) -> bool {
-        match opcode {
-            _ => false,
-        }
+    fn r1cs_is_supported(_opcode: &BlackBoxFunc) -> bool {
+        false
     }
 
     // PLONK supports most of the blackbox functions by default
@@ -211,14 +1091,540 @@ pub fn default_is_blackbox_supported(
     // attempt to transform into supported gates. If these are also not available
     // then a compiler error will be emitted.
     fn plonk_is_supported(opcode: &BlackBoxFunc) -> bool {
-        match opcode {
-            BlackBoxFunc::AES => false,
-            _ => true,
-        }
+        !matches!(opcode, BlackBoxFunc::AES)
     }
 
     match language {
-        Language::R1CS => r1cs_is_supported,
-        Language::PLONKCSat { .. } => plonk_is_supported,
+        Language::R1CS | Language::AIR { .. } | Language::CCS { .. } => r1cs_is_supported,
+        Language::PLONKCSat { .. } | Language::UltraPlonk { .. } => plonk_is_supported,
+    }
+}
+
+#[test]
+fn circuit_digest_is_stable_across_serialisation() {
+    use acir::{circuit::PublicInputs, native_types::Witness};
+
+    let circuit = Circuit {
+        current_witness_index: 2,
+        opcodes: vec![Opcode::Directive(Directive::Invert {
+            x: Witness(1),
+            result: Witness(2),
+        })],
+        public_inputs: PublicInputs(vec![Witness(1)]),
+        return_values: PublicInputs::default(),
+    };
+
+    let mut bytes = Vec::new();
+    circuit.write(&mut bytes).unwrap();
+    let deserialised = Circuit::read(&*bytes).unwrap();
+    assert_eq!(circuit_digest(&circuit), circuit_digest(&deserialised));
+
+    // Caches are keyed by the digest, so it must only change with the serialisation version
+    assert_eq!(
+        hex::encode(circuit_digest(&circuit)),
+        "61730da153c48731c7a36e8a2441c038d61ed344d53aa189a5f4ad0cce2db366"
+    );
+    #[allow(deprecated)]
+    let hash = hash_constraint_system(&circuit);
+    assert_eq!(hash, circuit_digest(&circuit));
+
+    let other = Circuit {
+        public_inputs: PublicInputs::default(),
+        ..circuit.clone()
+    };
+    assert_ne!(circuit_digest(&circuit), circuit_digest(&other));
+}
+
+#[test]
+fn field_compatibility() {
+    #[cfg(feature = "bn254")]
+    assert_eq!(FieldModulus::acvm(), FieldModulus::bn254());
+    #[cfg(feature = "bls12_381")]
+    assert_eq!(FieldModulus::acvm(), FieldModulus::bls12_381());
+    #[cfg(feature = "goldilocks")]
+    assert_eq!(FieldModulus::acvm(), FieldModulus::goldilocks());
+
+    assert_eq!(check_field_compatibility(&FieldModulus::acvm()), Ok(()));
+
+    let other = FieldModulus(BigUint::from(2_u8).pow(61) - 1_u8);
+    assert!(matches!(
+        check_field_compatibility(&other),
+        Err(BackendError::FieldMismatch { .. })
+    ));
+    assert_eq!(other.name(), None);
+
+    for name in ["bn254", "bls12_381", "goldilocks"] {
+        let field = FieldModulus::from_name(name).unwrap();
+        assert_eq!(field.name(), Some(name));
+        if let Err(err) = check_field_compatibility(&field) {
+            assert!(err.to_string().ends_with(&format!(
+                "enable the `{name}` feature of ACVM to use this backend"
+            )));
+        }
+    }
+    assert_eq!(FieldModulus::from_name("bn256"), None);
+}
+
+#[test]
+fn backends_may_rely_on_the_default_methods() {
+    // A backend which only implements the required methods, and whose proofs are the
+    // number of assigned witnesses
+    struct MinimalBackend;
+
+    impl ProofSystemCompiler for MinimalBackend {
+        fn np_language(&self) -> Language {
+            Language::R1CS
+        }
+
+        fn blackbox_function_supported(&self, _opcode: &BlackBoxFunc) -> bool {
+            false
+        }
+
+        fn prove_with_meta(
+            &self,
+            _circuit: &Circuit,
+            witness_values: &WitnessMap,
+        ) -> Result<Vec<u8>, BackendError> {
+            Ok(vec![witness_values.len() as u8])
+        }
+
+        fn verify_from_cs(
+            &self,
+            proof: &[u8],
+            _public_inputs: Vec<FieldElement>,
+            _circuit: &Circuit,
+        ) -> Result<bool, BackendError> {
+            Ok(!proof.is_empty())
+        }
+
+        fn get_exact_circuit_size(&self, circuit: &Circuit) -> Result<u32, BackendError> {
+            Ok(circuit.opcodes.len() as u32)
+        }
+    }
+
+    let backend = MinimalBackend;
+    let circuit = Circuit::default();
+    assert!(backend.name().ends_with("MinimalBackend"));
+    assert_eq!(backend.np_field(), FieldModulus::acvm());
+    assert_eq!(
+        backend.preprocess(&circuit),
+        Err(BackendError::UnsupportedFeature("preprocessing"))
+    );
+
+    let witness = WitnessMap::from([(acir::native_types::Witness(1), FieldElement::one())]);
+    let proof = backend
+        .prove_with_pk(&circuit, &witness, &ProvingKey(Vec::new()))
+        .unwrap();
+    assert_eq!(proof, vec![1]);
+    assert_eq!(
+        backend.verify_with_vk(&proof, Vec::new(), &circuit, &VerificationKey(Vec::new())),
+        Ok(true)
+    );
+
+    // Batches are proven without preprocessing
+    let proofs = backend
+        .prove_batch(&circuit, &[witness, WitnessMap::new()])
+        .unwrap();
+    let bytes: Vec<_> = proofs.iter().map(|proof| proof.bytes.clone()).collect();
+    assert_eq!(bytes, vec![vec![1], vec![0]]);
+}
+
+#[test]
+fn opcodes_are_only_retried_when_their_witnesses_are_assigned() {
+    use acir::{circuit::opcodes::FunctionInput, native_types::Witness};
+    use core::cell::Cell;
+
+    // Counts the black box function calls which are attempted
+    #[derive(Default)]
+    struct CountingSolver(Cell<usize>);
+
+    impl PartialWitnessGenerator for CountingSolver {
+        fn solve_blackbox_function_call(
+            &self,
+            initial_witness: &mut WitnessMap,
+            func_call: &BlackBoxFuncCall,
+        ) -> Result<(), OpcodeResolutionError> {
+            self.0.set(self.0.get() + 1);
+            pwg::solve_native_black_box(initial_witness, func_call)
+        }
+    }
+
+    // _{i+1} = _i AND _i, in reverse order so that only the last opcode can be solved
+    // in the first pass
+    const LENGTH: u32 = 100;
+    let opcodes: Vec<Opcode> = (1..=LENGTH)
+        .rev()
+        .map(|i| {
+            let input = FunctionInput {
+                witness: Witness(i),
+                num_bits: 8,
+            };
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                name: BlackBoxFunc::AND,
+                inputs: vec![input.clone(), input],
+                outputs: vec![Witness(i + 1)],
+            })
+        })
+        .collect();
+
+    let solver = CountingSolver::default();
+    let mut witness = WitnessMap::from([(Witness(1), FieldElement::from(7_i128))]);
+    solver.solve(&mut witness, opcodes.clone()).unwrap();
+    assert_eq!(witness[&Witness(LENGTH + 1)], FieldElement::from(7_i128));
+    // Every opcode but the last is attempted once before its input is assigned
+    assert_eq!(solver.0.get(), 2 * LENGTH as usize - 1);
+
+    let solver = CountingSolver::default();
+    assert_eq!(
+        solver.solve(&mut WitnessMap::new(), opcodes),
+        Err(OpcodeResolutionError::OpcodeNotSolvable(
+            OpcodeNotSolvable::MissingAssignment(LENGTH)
+        ))
+    );
+    assert_eq!(solver.0.get(), LENGTH as usize);
+}
+
+#[test]
+fn public_inputs_are_in_circuit_order() {
+    use acir::{circuit::PublicInputs, native_types::Witness};
+
+    let circuit = Circuit {
+        current_witness_index: 3,
+        opcodes: Vec::new(),
+        public_inputs: PublicInputs(vec![Witness(3), Witness(1)]),
+        return_values: PublicInputs::default(),
+    };
+    let witness_values = WitnessMap::from([
+        (Witness(1), FieldElement::from(1_i128)),
+        (Witness(2), FieldElement::from(2_i128)),
+        (Witness(3), FieldElement::from(3_i128)),
+    ]);
+    assert_eq!(
+        public_input_values(&circuit, &witness_values),
+        Ok(vec![FieldElement::from(3_i128), FieldElement::from(1_i128)])
+    );
+
+    let missing = WitnessMap::from([(Witness(1), FieldElement::from(1_i128))]);
+    assert_eq!(
+        public_input_values(&circuit, &missing),
+        Err(OpcodeResolutionError::OpcodeNotSolvable(
+            OpcodeNotSolvable::MissingAssignment(3)
+        ))
+    );
+}
+
+#[test]
+fn return_values_are_kept_through_compilation() {
+    use acir::circuit::opcodes::FunctionInput;
+
+    let mut builder = builder::CircuitBuilder::new();
+    let x = builder.add_public_input();
+    let y = builder.add_witness();
+    let inputs = [x, y].map(|witness| FunctionInput {
+        witness,
+        num_bits: 8,
+    });
+    let and = builder.black_box(BlackBoxFunc::AND, inputs.to_vec())[0];
+    let sum = builder.evaluate(x * y + and + FieldElement::one());
+    builder.add_return_value(sum);
+    builder.add_return_value(and);
+    let circuit = builder.finish().unwrap();
+
+    let compiled =
+        compiler::compile(circuit.clone(), Language::PLONKCSat { width: 3 }, |_| false).unwrap();
+    assert_eq!(compiled.return_values, circuit.return_values);
+    assert!(compiled.current_witness_index > circuit.current_witness_index);
+
+    let mut witness = WitnessMap::from([
+        (x, FieldElement::from(0b1100_i128)),
+        (y, FieldElement::from(0b1010_i128)),
+    ]);
+    pwg::NativeSolver
+        .solve(&mut witness, compiled.opcodes)
+        .unwrap();
+    assert_eq!(
+        return_values(&circuit, &witness),
+        Ok(vec![
+            FieldElement::from(12 * 10 + 0b1000 + 1_i128),
+            FieldElement::from(0b1000_i128)
+        ])
+    );
+    assert_eq!(
+        return_values(&circuit, &WitnessMap::new()),
+        Err(OpcodeResolutionError::OpcodeNotSolvable(
+            OpcodeNotSolvable::MissingAssignment(sum.witness_index())
+        ))
+    );
+}
+
+#[test]
+fn circuits_over_other_fields_are_compiled_and_solved() {
+    use acir::{
+        circuit::{opcodes::FunctionInput, PublicInputs},
+        native_types::{Expression, Witness},
+    };
+    use acir_field::Goldilocks;
+
+    // _3 = _1 AND _2, _5 = _4 * _4 + _3
+    let (x, y, and, z, output) = (Witness(1), Witness(2), Witness(3), Witness(4), Witness(5));
+    let inputs = [x, y].map(|witness| FunctionInput {
+        witness,
+        num_bits: 8,
+    });
+    let mut square = Expression::<Goldilocks>::from(&and);
+    square.term_multiplication(Goldilocks::one(), z, z);
+    let circuit = Circuit {
+        current_witness_index: 5,
+        opcodes: vec![
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                name: BlackBoxFunc::AND,
+                inputs: inputs.to_vec(),
+                outputs: vec![and],
+            }),
+            Opcode::Arithmetic(&square - &output),
+        ],
+        public_inputs: PublicInputs(vec![x, y, z]),
+        return_values: PublicInputs(vec![output]),
+    };
+
+    let compiled =
+        compiler::compile(circuit.clone(), Language::PLONKCSat { width: 3 }, |_| false).unwrap();
+    assert!(compiled
+        .opcodes
+        .iter()
+        .all(|opcode| !matches!(opcode, Opcode::BlackBoxFuncCall(_))));
+
+    // The square wraps around the Goldilocks modulus, 2^64 - 2^32 + 1, rather than ACVM's
+    let two_pow = |exponent: i128| Goldilocks::from(2_i128).pow(&Goldilocks::from(exponent));
+    let mut witness = WitnessMap::from([
+        (x, Goldilocks::from(0b1100_i128)),
+        (y, Goldilocks::from(0b1010_i128)),
+        (z, two_pow(40)),
+    ]);
+    pwg::NativeSolver
+        .solve(&mut witness, compiled.opcodes)
+        .unwrap();
+    assert_eq!(
+        return_values(&circuit, &witness),
+        Ok(vec![
+            two_pow(48) - two_pow(16) + Goldilocks::from(0b1000_i128)
+        ])
+    );
+}
+
+#[test]
+fn pinned_witnesses_are_kept_through_compilation() {
+    use acir::{circuit::PublicInputs, native_types::Expression};
+    use alloc::collections::{BTreeMap, BTreeSet};
+
+    // `unused` only appears with a zero coefficient, so the optimisers would remove it
+    let (x, y, unused, out) = (Witness(1), Witness(2), Witness(3), Witness(4));
+    let circuit = Circuit {
+        current_witness_index: 4,
+        opcodes: vec![Opcode::Arithmetic(Expression {
+            mul_terms: vec![(FieldElement::one(), x, y)],
+            linear_combinations: vec![(FieldElement::zero(), unused), (-FieldElement::one(), out)],
+            q_c: FieldElement::zero(),
+        })],
+        public_inputs: PublicInputs::default(),
+        return_values: PublicInputs::default(),
+    };
+    let uses = |circuit: &Circuit, witness: Witness| {
+        circuit
+            .opcodes
+            .iter()
+            .any(|opcode| pwg::opcode_witnesses(opcode).contains(&witness))
+    };
+
+    for language in [Language::R1CS, Language::PLONKCSat { width: 3 }] {
+        let compiled = compiler::compile(circuit.clone(), language.clone(), |_| false).unwrap();
+        assert!(!uses(&compiled, unused));
+
+        let pinned = BTreeSet::from([unused, out, Witness(10)]);
+        let (compiled, indices) =
+            compiler::compile_with_pins(circuit.clone(), language, |_| false, &pinned).unwrap();
+        assert!(uses(&compiled, unused));
+        assert_eq!(indices, BTreeMap::from([(unused, unused), (out, out)]));
     }
 }
+
+#[test]
+fn public_inputs_are_checked_against_the_circuit() {
+    use acir::circuit::PublicInputs;
+
+    let circuit = Circuit {
+        current_witness_index: 3,
+        opcodes: Vec::new(),
+        public_inputs: PublicInputs(vec![Witness(3), Witness(1)]),
+        return_values: PublicInputs::default(),
+    };
+    let one = FieldElement::one();
+    let two = FieldElement::from(2_i128);
+    assert_eq!(
+        check_public_inputs(&circuit, &[(Witness(3), two), (Witness(1), one)]),
+        Ok(vec![two, one])
+    );
+    assert_eq!(
+        check_public_inputs(&circuit, &[(Witness(3), two)]),
+        Err(PublicInputError::IncorrectNumPublicInputs {
+            expected: 2,
+            found: 1
+        })
+    );
+    assert_eq!(
+        check_public_inputs(&circuit, &[(Witness(1), one), (Witness(3), two)]),
+        Err(PublicInputError::OutOfOrder {
+            index: 0,
+            expected: 3,
+            found: 1
+        })
+    );
+    assert_eq!(
+        check_public_inputs(&circuit, &[(Witness(3), two), (Witness(2), one)]),
+        Err(PublicInputError::NotPublic { index: 1, found: 2 })
+    );
+}
+
+#[test]
+fn language_queries() {
+    let ultra_plonk = Language::UltraPlonk {
+        width: 4,
+        lookups: true,
+    };
+    assert_eq!(ultra_plonk.width(), Some(4));
+    assert!(ultra_plonk.supports_lookup());
+    assert_eq!(ultra_plonk.arith_degree(), 2);
+
+    assert!(!Language::PLONKCSat { width: 3 }.supports_lookup());
+    assert_eq!(Language::R1CS.width(), None);
+    assert_eq!(Language::CCS { degree: 3 }.arith_degree(), 3);
+    assert_eq!(
+        Language::AIR {
+            width: 5,
+            degree: 4
+        }
+        .width(),
+        Some(5)
+    );
+}
+
+#[test]
+fn commitment_scheme_defaults_to_kzg_only() {
+    let mut backend = testing::MockBackend::new();
+    assert_eq!(backend.commitment_scheme(), CommitmentScheme::KZG);
+    assert_eq!(
+        backend.capabilities().supported_commitment_schemes,
+        HashSet::from([CommitmentScheme::KZG])
+    );
+
+    assert_eq!(backend.set_commitment_scheme(CommitmentScheme::KZG), Ok(()));
+    assert_eq!(
+        backend.set_commitment_scheme(CommitmentScheme::FRI),
+        Err(BackendError::UnsupportedCommitmentScheme(
+            CommitmentScheme::FRI
+        ))
+    );
+}
+
+#[test]
+fn errors_can_cross_error_handling_boundaries() {
+    fn assert_error<E: core::error::Error + Send + Sync + 'static>() {}
+    assert_error::<OpcodeNotSolvable>();
+    assert_error::<OpcodeResolutionError>();
+    assert_error::<BackendError>();
+    assert_error::<PublicInputError>();
+    assert_error::<ContractGenError>();
+    assert_error::<compiler::CompileError>();
+    assert_error::<compiler::ValidationError>();
+    assert_error::<circom::CircomError>();
+    assert_error::<versioning::CircuitVersionError>();
+    assert_error::<testing::EquivalenceError>();
+
+    fn solve() -> Result<(), Box<dyn core::error::Error + Send + Sync>> {
+        Err(OpcodeResolutionError::<FieldElement>::UnsatisfiedConstrain)?;
+        Ok(())
+    }
+    assert_eq!(
+        solve().unwrap_err().to_string(),
+        "could not satisfy all constraints"
+    );
+}
+
+#[test]
+fn initial_witnesses_outside_of_the_program_are_rejected() {
+    use acir::{circuit::PublicInputs, native_types::Witness};
+
+    let main = Circuit {
+        current_witness_index: 2,
+        opcodes: vec![Opcode::Arithmetic(
+            &Expression::from(&Witness(1)) - &Witness(2),
+        )],
+        public_inputs: PublicInputs(vec![Witness(1)]),
+        return_values: PublicInputs::default(),
+    };
+    let program = Program {
+        functions: vec![main],
+    };
+    let mut witness = WitnessMap::from([
+        (Witness(1), FieldElement::from(5_i128)),
+        (Witness(7), FieldElement::from(5_i128)),
+    ]);
+    let err = pwg::NativeSolver
+        .solve_program(&mut witness, &program)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        OpcodeResolutionError::InvalidInitialWitness(WitnessOutOfBounds {
+            witness: Witness(7),
+            current_witness_index: 2
+        })
+    );
+    assert_eq!(
+        err.to_string(),
+        "invalid initial witness: witness 7 is assigned, but the circuit's current witness index is 2"
+    );
+
+    witness.remove(&Witness(7));
+    pwg::NativeSolver
+        .solve_program(&mut witness, &program)
+        .unwrap();
+    assert_eq!(witness[&Witness(2)], FieldElement::from(5_i128));
+}
+
+#[test]
+fn recursive_programs_are_rejected() {
+    use acir::{circuit::PublicInputs, native_types::Witness};
+
+    // Function 1 returns its parameter by calling itself
+    let call = |id| Opcode::Call {
+        id,
+        inputs: vec![Witness(1)],
+        outputs: vec![Witness(2)],
+    };
+    let function = |opcodes| Circuit {
+        current_witness_index: 2,
+        opcodes,
+        public_inputs: PublicInputs(vec![Witness(1)]),
+        return_values: PublicInputs(vec![Witness(2)]),
+    };
+    let program = Program {
+        functions: vec![function(vec![call(1)]), function(vec![call(1)])],
+    };
+    let mut witness = WitnessMap::from([(Witness(1), FieldElement::one())]);
+    assert_eq!(
+        pwg::NativeSolver.solve_program(&mut witness, &program),
+        Err(OpcodeResolutionError::RecursiveCall(1))
+    );
+
+    // Calling the same function twice is not recursion
+    let identity = function(vec![Opcode::Arithmetic(
+        &Expression::from(&Witness(1)) - &Witness(2),
+    )]);
+    let program = Program {
+        functions: vec![function(vec![call(1), call(1)]), identity],
+    };
+    pwg::NativeSolver
+        .solve_program(&mut witness, &program)
+        .unwrap();
+    assert_eq!(witness[&Witness(2)], FieldElement::one());
+}
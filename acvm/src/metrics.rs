@@ -0,0 +1,212 @@
+// Hooks for exporting metrics from the solver, the compiler and the caches
+//
+// ACVM reports counters and histogram observations to a `MetricsSink`, which hosts
+// implement to forward them to Prometheus or another metrics system, without ACVM
+// depending on it. The sink is installed for the whole process with `set_sink`, as the
+// solver and compiler are reached from too many entry points to pass it to each of them.
+// Nothing is measured while no sink is installed, and without the `std` feature no sink
+// can be installed, as there is no lock to install it behind.
+
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use std::sync::RwLock;
+
+use crate::profile::{self, Timer};
+
+/// Counter of the opcodes which were solved, including those of called functions
+pub const OPCODES_SOLVED: &str = "acvm_opcodes_solved_total";
+/// Counter of the compiler passes which were run, labelled by `pass`
+pub const COMPILER_PASSES: &str = "acvm_compiler_passes_total";
+/// Histogram of the time taken by each compiler pass in seconds, labelled by `pass`
+pub const COMPILER_PASS_SECONDS: &str = "acvm_compiler_pass_duration_seconds";
+/// Counter of the lookups which were found in a cache, labelled by `cache`, which is
+/// `keys` or `circuits`
+pub const CACHE_HITS: &str = "acvm_cache_hits_total";
+/// Counter of the lookups which were not found in a cache, labelled by `cache`
+pub const CACHE_MISSES: &str = "acvm_cache_misses_total";
+
+/// The labels of a metric, as pairs of a label name and its value
+pub type Labels<'a> = &'a [(&'static str, &'a str)];
+
+/// Receives the metrics which ACVM reports
+pub trait MetricsSink: Send + Sync {
+    /// Adds `value` to the counter `name`
+    fn increment_counter(&self, name: &'static str, labels: Labels<'_>, value: u64);
+
+    /// Records an observation of the histogram `name`
+    fn observe_histogram(&self, name: &'static str, labels: Labels<'_>, value: f64);
+}
+
+#[cfg(feature = "std")]
+static SINK: RwLock<Option<Arc<dyn MetricsSink>>> = RwLock::new(None);
+
+/// Installs the sink which receives the metrics of the process, replacing any other
+#[cfg(feature = "std")]
+pub fn set_sink(sink: Arc<dyn MetricsSink>) {
+    *SINK.write().expect("metrics sink lock is poisoned") = Some(sink);
+}
+
+/// Removes the installed sink, after which no metrics are reported
+#[cfg(feature = "std")]
+pub fn clear_sink() {
+    *SINK.write().expect("metrics sink lock is poisoned") = None;
+}
+
+#[cfg(feature = "std")]
+fn sink() -> Option<Arc<dyn MetricsSink>> {
+    SINK.read().expect("metrics sink lock is poisoned").clone()
+}
+
+#[cfg(not(feature = "std"))]
+fn sink() -> Option<Arc<dyn MetricsSink>> {
+    None
+}
+
+pub(crate) fn increment_counter(name: &'static str, labels: Labels<'_>, value: u64) {
+    if let Some(sink) = sink() {
+        sink.increment_counter(name, labels, value);
+    }
+}
+
+// Counts a lookup in one of the caches
+#[cfg(feature = "std")]
+pub(crate) fn cache_lookup(cache: &'static str, hit: bool) {
+    let name = if hit { CACHE_HITS } else { CACHE_MISSES };
+    increment_counter(name, &[("cache", cache)], 1);
+}
+
+// Reports that a compiler pass was run, and how long it took, when it is dropped
+pub(crate) struct Pass {
+    name: &'static str,
+    start: Option<Timer>,
+}
+
+pub(crate) fn pass(name: &'static str) -> Pass {
+    Pass {
+        name,
+        start: profile::start_timer(sink().is_some()),
+    }
+}
+
+impl Drop for Pass {
+    fn drop(&mut self) {
+        let (Some(start), Some(sink)) = (&self.start, sink()) else {
+            return;
+        };
+        let labels = [("pass", self.name)];
+        sink.increment_counter(COMPILER_PASSES, &labels, 1);
+        sink.observe_histogram(
+            COMPILER_PASS_SECONDS,
+            &labels,
+            start.elapsed().as_secs_f64(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeSet, sync::Mutex};
+
+    use acir::{
+        circuit::{
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            Circuit, Opcode, PublicInputs,
+        },
+        native_types::{Expression, Witness},
+        BlackBoxFunc, FieldElement,
+    };
+
+    use super::*;
+    use crate::{
+        circuit_cache::CircuitCache, pwg::NativeSolver, testing::MockBackend,
+        PartialWitnessGenerator, WitnessMap,
+    };
+
+    // Records the metrics with their labels. Other tests run while the sink is installed,
+    // so metrics may be recorded more often than the test reports them.
+    #[derive(Default)]
+    struct Recorder {
+        counters: Mutex<Vec<(String, Vec<String>, u64)>>,
+        histograms: Mutex<Vec<(String, Vec<String>)>>,
+    }
+
+    fn labels(labels: Labels<'_>) -> Vec<String> {
+        labels
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect()
+    }
+
+    impl MetricsSink for Recorder {
+        fn increment_counter(&self, name: &'static str, labels: Labels<'_>, value: u64) {
+            self.counters.lock().unwrap().push((
+                name.to_owned(),
+                super::test::labels(labels),
+                value,
+            ));
+        }
+
+        fn observe_histogram(&self, name: &'static str, labels: Labels<'_>, _value: f64) {
+            self.histograms
+                .lock()
+                .unwrap()
+                .push((name.to_owned(), super::test::labels(labels)));
+        }
+    }
+
+    #[test]
+    fn metrics_are_reported_to_the_sink() {
+        let (x, y) = (Witness(1), Witness(2));
+        let circuit = Circuit {
+            current_witness_index: 2,
+            opcodes: vec![
+                Opcode::Arithmetic(&Expression::from(x) - &Expression::from(y)),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::RANGE,
+                    inputs: vec![FunctionInput {
+                        witness: x,
+                        num_bits: 8,
+                    }],
+                    outputs: Vec::new(),
+                }),
+            ],
+            public_inputs: PublicInputs(vec![x]),
+            return_values: PublicInputs::default(),
+        };
+
+        let recorder = Arc::new(Recorder::default());
+        set_sink(recorder.clone());
+        let mut witness = WitnessMap::from([(x, FieldElement::from(7_i128))]);
+        NativeSolver
+            .solve(&mut witness, circuit.opcodes.clone())
+            .unwrap();
+        let cache = CircuitCache::new();
+        let backend = MockBackend::new().with_supported_opcodes([]);
+        for _ in 0..2 {
+            cache
+                .get_or_compile(&backend, &circuit, &BTreeSet::new())
+                .unwrap();
+        }
+        clear_sink();
+
+        let counters = recorder.counters.lock().unwrap();
+        let counted = |name: &str, label: Option<&str>| {
+            counters.iter().any(|(counter, labels, value)| {
+                counter == name
+                    && *value > 0
+                    && label.is_none_or(|label| labels.iter().any(|l| l == label))
+            })
+        };
+        assert!(counted(OPCODES_SOLVED, None));
+        assert!(counted(COMPILER_PASSES, Some("pass=fallback")));
+        assert!(counted(COMPILER_PASSES, Some("pass=optimise")));
+        assert!(counted(CACHE_MISSES, Some("cache=circuits")));
+        assert!(counted(CACHE_HITS, Some("cache=circuits")));
+
+        let histograms = recorder.histograms.lock().unwrap();
+        assert!(histograms.contains(&(
+            COMPILER_PASS_SECONDS.to_owned(),
+            vec!["pass=compile".to_owned()]
+        )));
+    }
+}
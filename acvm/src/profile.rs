@@ -0,0 +1,383 @@
+// Opt-in profiling of the solver and compiler
+//
+// Time and counts are recorded per opcode category, which is the name of the opcode:
+// `arithmetic`, `call`, the name of each directive and the name of each black box function.
+//
+// Solving is profiled by wrapping a backend in a `Profiler`, and compiling with
+// `compiler::compile_profiled`. Nothing is timed otherwise, nor without the `std` feature,
+// as there is no clock without the standard library.
+
+use alloc::{borrow::ToOwned, collections::BTreeMap, format, string::String, vec::Vec};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+#[cfg(feature = "std")]
+use acir::{
+    circuit::{directives::Directive, opcodes::BlackBoxFuncCall, Circuit, Opcode},
+    native_types::WitnessStack,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "std")]
+use crate::{pwg, OpcodeResolutionError, PartialWitnessGenerator, WitnessMap};
+
+/// Measures the time spent on profiled work
+#[cfg(feature = "std")]
+pub(crate) type Timer = std::time::Instant;
+
+/// Starts timing profiled work, if it is being profiled
+#[cfg(feature = "std")]
+pub(crate) fn start_timer(profiling: bool) -> Option<Timer> {
+    profiling.then(Timer::now)
+}
+
+// Without a clock no timer is ever started
+#[cfg(not(feature = "std"))]
+pub(crate) enum Timer {}
+
+#[cfg(not(feature = "std"))]
+impl Timer {
+    pub(crate) fn elapsed(&self) -> Duration {
+        match *self {}
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn start_timer(_profiling: bool) -> Option<Timer> {
+    None
+}
+
+/// The time spent on one category of opcode
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpcodeProfile {
+    /// The number of times an opcode of the category was solved or reduced. When solving,
+    /// this includes attempts which failed because an input was not yet assigned.
+    pub count: usize,
+    pub total: Duration,
+}
+
+impl OpcodeProfile {
+    /// Returns the mean time spent on each opcode
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.count as u32
+        }
+    }
+}
+
+/// The time spent on each category of opcode while solving or compiling
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileReport {
+    pub opcodes: BTreeMap<String, OpcodeProfile>,
+}
+
+impl ProfileReport {
+    pub fn new() -> Self {
+        ProfileReport::default()
+    }
+
+    /// Records the time spent on one opcode of a category
+    pub fn record(&mut self, category: &str, elapsed: Duration) {
+        let profile = match self.opcodes.get_mut(category) {
+            Some(profile) => profile,
+            None => self.opcodes.entry(category.to_owned()).or_default(),
+        };
+        profile.count += 1;
+        profile.total += elapsed;
+    }
+
+    /// Adds the counts and times of another report to this one
+    pub fn merge(&mut self, other: &ProfileReport) {
+        for (category, other) in &other.opcodes {
+            let profile = self.opcodes.entry(category.clone()).or_default();
+            profile.count += other.count;
+            profile.total += other.total;
+        }
+    }
+
+    /// Returns the total time spent on every category
+    pub fn total(&self) -> Duration {
+        self.opcodes.values().map(|profile| profile.total).sum()
+    }
+
+    /// Returns the categories ordered by the time spent on them, most first
+    pub fn by_total(&self) -> Vec<(&str, &OpcodeProfile)> {
+        let mut categories: Vec<_> = self
+            .opcodes
+            .iter()
+            .map(|(category, profile)| (category.as_str(), profile))
+            .collect();
+        categories.sort_by_key(|(_, profile)| core::cmp::Reverse(profile.total));
+        categories
+    }
+}
+
+impl core::fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(
+            f,
+            "{:<20} {:>10} {:>14} {:>14}",
+            "opcode", "count", "total", "mean"
+        )?;
+        for (category, profile) in self.by_total() {
+            writeln!(
+                f,
+                "{:<20} {:>10} {:>14} {:>14}",
+                category,
+                profile.count,
+                format!("{:?}", profile.total),
+                format!("{:?}", profile.mean())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Wraps a backend, recording the time spent solving each opcode.
+///
+/// The time of a call includes the time spent solving the opcodes of the callee, which
+/// are also recorded in their own categories.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Profiler<B> {
+    backend: B,
+    report: Mutex<ProfileReport>,
+}
+
+#[cfg(feature = "std")]
+impl<B> Profiler<B> {
+    pub fn new(backend: B) -> Self {
+        Profiler {
+            backend,
+            report: Mutex::new(ProfileReport::new()),
+        }
+    }
+
+    /// Returns the times which have been recorded so far
+    pub fn report(&self) -> ProfileReport {
+        self.report
+            .lock()
+            .expect("profile report lock poisoned")
+            .clone()
+    }
+
+    /// Clears the times which have been recorded, returning them
+    pub fn take_report(&self) -> ProfileReport {
+        std::mem::take(&mut *self.report.lock().expect("profile report lock poisoned"))
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn into_inner(self) -> B {
+        self.backend
+    }
+}
+
+#[cfg(feature = "std")]
+impl<B: PartialWitnessGenerator> PartialWitnessGenerator for Profiler<B> {
+    // Each opcode is timed, so they are solved one at a time
+    fn batch_opcodes(&self) -> bool {
+        false
+    }
+
+    fn solve_opcode(
+        &self,
+        initial_witness: &mut WitnessMap,
+        opcode: &Opcode,
+        functions: &[Circuit],
+        stack: &mut WitnessStack,
+    ) -> Result<(), OpcodeResolutionError> {
+        let start = Timer::now();
+        let result = match opcode {
+            // Calls are solved through the profiler, so that the callee's opcodes are recorded
+            Opcode::Call {
+                id,
+                inputs,
+                outputs,
+            } => pwg::call::solve_call(
+                self,
+                initial_witness,
+                functions,
+                stack,
+                *id,
+                inputs,
+                outputs,
+            ),
+            _ => self
+                .backend
+                .solve_opcode(initial_witness, opcode, functions, stack),
+        };
+        let elapsed = start.elapsed();
+
+        self.report
+            .lock()
+            .expect("profile report lock poisoned")
+            .record(opcode.name(), elapsed);
+        result
+    }
+
+    fn solve_blackbox_function_call(
+        &self,
+        initial_witness: &mut WitnessMap,
+        func_call: &BlackBoxFuncCall,
+    ) -> Result<(), OpcodeResolutionError> {
+        self.backend
+            .solve_blackbox_function_call(initial_witness, func_call)
+    }
+
+    fn solve_directives(
+        &self,
+        initial_witness: &mut WitnessMap,
+        directive: &Directive,
+    ) -> Result<(), OpcodeResolutionError> {
+        self.backend.solve_directives(initial_witness, directive)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pwg::NativeSolver;
+    use acir::{
+        circuit::{opcodes::FunctionInput, Program, PublicInputs},
+        native_types::{Expression, Witness},
+        BlackBoxFunc, FieldElement,
+    };
+
+    #[test]
+    fn report_merges_and_orders_categories() {
+        let mut report = ProfileReport::new();
+        report.record("arithmetic", Duration::from_millis(1));
+        report.record("arithmetic", Duration::from_millis(3));
+        report.record("sha256", Duration::from_millis(10));
+
+        let mut other = ProfileReport::new();
+        other.record("arithmetic", Duration::from_millis(2));
+        report.merge(&other);
+
+        let arithmetic = report.opcodes["arithmetic"];
+        assert_eq!(arithmetic.count, 3);
+        assert_eq!(arithmetic.mean(), Duration::from_millis(2));
+        assert_eq!(report.total(), Duration::from_millis(16));
+        assert_eq!(report.by_total()[0].0, "sha256");
+        assert!(report
+            .to_string()
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("sha256"));
+    }
+
+    #[test]
+    fn profiler_records_each_opcode() {
+        // main calls f, where f(x) = x AND x and then returns x + 1
+        let f = Circuit {
+            current_witness_index: 3,
+            opcodes: vec![
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::AND,
+                    inputs: vec![
+                        FunctionInput {
+                            witness: Witness(1),
+                            num_bits: 8,
+                        },
+                        FunctionInput {
+                            witness: Witness(1),
+                            num_bits: 8,
+                        },
+                    ],
+                    outputs: vec![Witness(3)],
+                }),
+                Opcode::Arithmetic(
+                    &(&Expression::from(&Witness(3)) - &Witness(2)) + &Expression::one(),
+                ),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs(vec![Witness(2)]),
+        };
+        let main = Circuit {
+            current_witness_index: 2,
+            opcodes: vec![Opcode::Call {
+                id: 1,
+                inputs: vec![Witness(1)],
+                outputs: vec![Witness(2)],
+            }],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        };
+        let program = Program {
+            functions: vec![main, f],
+        };
+
+        let profiler = Profiler::new(NativeSolver);
+        let mut witness = WitnessMap::from([(Witness(1), FieldElement::from(5_i128))]);
+        profiler.solve_program(&mut witness, &program).unwrap();
+        assert_eq!(witness[&Witness(2)], FieldElement::from(6_i128));
+
+        let report = profiler.take_report();
+        let counts: BTreeMap<&str, usize> = report
+            .opcodes
+            .iter()
+            .map(|(category, profile)| (category.as_str(), profile.count))
+            .collect();
+        assert_eq!(
+            counts,
+            BTreeMap::from([("and", 1), ("arithmetic", 1), ("call", 1)])
+        );
+        assert!(report.opcodes["call"].total >= report.opcodes["and"].total);
+        assert_eq!(profiler.report(), ProfileReport::new());
+    }
+
+    #[test]
+    fn compile_profiled_records_lowered_opcodes() {
+        let range: Opcode = Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+            name: BlackBoxFunc::RANGE,
+            inputs: vec![FunctionInput {
+                witness: Witness(1),
+                num_bits: 4,
+            }],
+            outputs: Vec::new(),
+        });
+        let circuit = Circuit {
+            current_witness_index: 2,
+            opcodes: vec![
+                range,
+                Opcode::Arithmetic(&Expression::from(&Witness(1)) - &Witness(2)),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        };
+
+        let mut report = ProfileReport::new();
+        let compiled = crate::compiler::compile_profiled(
+            circuit.clone(),
+            crate::Language::PLONKCSat { width: 3 },
+            |_| false,
+            &mut report,
+        )
+        .unwrap();
+        let unprofiled = crate::compiler::compile(
+            circuit.clone(),
+            crate::Language::PLONKCSat { width: 3 },
+            |_| false,
+        )
+        .unwrap();
+        assert_eq!(compiled, unprofiled);
+
+        // The range is lowered into arithmetic gates, which are then reduced themselves
+        let lowered = crate::compiler::fallback::fallback(circuit, |_| false).unwrap();
+        let num_arithmetic = lowered
+            .opcodes
+            .iter()
+            .filter(|opcode| opcode.is_arithmetic())
+            .count();
+        assert_eq!(report.opcodes["range"].count, 1);
+        assert_eq!(report.opcodes["arithmetic"].count, num_arithmetic);
+        assert!(num_arithmetic > 1);
+    }
+}
@@ -0,0 +1,202 @@
+// A self-describing container for the proofs created by backends
+
+use alloc::borrow::ToOwned;
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
+
+#[cfg(feature = "std")]
+use acir::serialisation::{read_n, read_u32, write_bytes, write_u32};
+use acir::{circuit::Circuit, FieldElement};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::circuit_digest;
+
+// Version 0 proofs do not have public input values
+#[cfg(feature = "std")]
+const PROOF_VERSION: u32 = 1;
+
+#[derive(PartialEq, Eq, Debug, Error)]
+pub enum ProofMismatch {
+    #[error("proof was created by backend {found}, but is being verified by {expected}")]
+    Backend { expected: String, found: String },
+    #[error("proof was created for a different circuit")]
+    Circuit,
+    #[error("proof has {found} public inputs, but {expected} were provided")]
+    NumPublicInputs { expected: u32, found: u32 },
+}
+
+/// A proof along with the information needed to check that it is verified
+/// by the right backend, against the right circuit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proof {
+    /// The identifier of the backend which created the proof
+    pub backend: String,
+    /// The `circuit_digest` of the circuit which was proven
+    pub circuit_digest: [u8; 32],
+    /// The number of public inputs of the circuit
+    pub num_public_inputs: u32,
+    /// The proof as returned by the backend
+    pub bytes: Vec<u8>,
+    /// The values of the public inputs in the order of the circuit's public inputs,
+    /// or empty if they have not been attached
+    pub public_inputs: Vec<FieldElement>,
+}
+
+impl Proof {
+    pub fn new(backend: &str, circuit: &Circuit, bytes: Vec<u8>) -> Proof {
+        Proof {
+            backend: backend.to_owned(),
+            circuit_digest: circuit_digest(circuit),
+            num_public_inputs: circuit.public_inputs.0.len() as u32,
+            bytes,
+            public_inputs: Vec::new(),
+        }
+    }
+
+    /// Attaches the values of the public inputs, which can be found using `public_input_values`
+    pub fn attach_public_inputs(&mut self, values: Vec<FieldElement>) -> Result<(), ProofMismatch> {
+        if values.len() != self.num_public_inputs as usize {
+            return Err(ProofMismatch::NumPublicInputs {
+                expected: values.len() as u32,
+                found: self.num_public_inputs,
+            });
+        }
+        self.public_inputs = values;
+        Ok(())
+    }
+
+    /// Returns the values of the public inputs, if they have been attached
+    pub fn public_inputs(&self) -> Option<&[FieldElement]> {
+        (self.public_inputs.len() == self.num_public_inputs as usize)
+            .then_some(&self.public_inputs[..])
+    }
+
+    /// Checks that the proof was created by `backend` for `circuit`,
+    /// and that `num_public_inputs` public inputs have been provided for it.
+    pub fn check(
+        &self,
+        backend: &str,
+        circuit: &Circuit,
+        num_public_inputs: usize,
+    ) -> Result<(), ProofMismatch> {
+        if self.backend != backend {
+            return Err(ProofMismatch::Backend {
+                expected: backend.to_owned(),
+                found: self.backend.clone(),
+            });
+        }
+        if self.circuit_digest != circuit_digest(circuit) {
+            return Err(ProofMismatch::Circuit);
+        }
+        if self.num_public_inputs as usize != num_public_inputs {
+            return Err(ProofMismatch::NumPublicInputs {
+                expected: num_public_inputs as u32,
+                found: self.num_public_inputs,
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write_u32(&mut writer, PROOF_VERSION)?;
+
+        write_u32(&mut writer, self.backend.len() as u32)?;
+        write_bytes(&mut writer, self.backend.as_bytes())?;
+
+        write_bytes(&mut writer, &self.circuit_digest)?;
+        write_u32(&mut writer, self.num_public_inputs)?;
+
+        write_u32(&mut writer, self.bytes.len() as u32)?;
+        write_bytes(&mut writer, &self.bytes)?;
+
+        write_u32(&mut writer, self.public_inputs.len() as u32)?;
+        for value in &self.public_inputs {
+            let value = value.to_be_bytes();
+            write_u32(&mut writer, value.len() as u32)?;
+            write_bytes(&mut writer, &value)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let version = read_u32(&mut reader)?;
+        if version > PROOF_VERSION {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+
+        let backend = String::from_utf8(read_vec(&mut reader)?)
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidData))?;
+        let circuit_digest = read_n::<32, _>(&mut reader)?;
+        let num_public_inputs = read_u32(&mut reader)?;
+        let bytes = read_vec(&mut reader)?;
+
+        let mut public_inputs = Vec::new();
+        if version >= 1 {
+            let num_values = read_u32(&mut reader)?;
+            for _ in 0..num_values {
+                let value = read_vec(&mut reader)?;
+                public_inputs.push(FieldElement::from_be_bytes_reduce(&value));
+            }
+        }
+
+        Ok(Proof {
+            backend,
+            circuit_digest,
+            num_public_inputs,
+            bytes,
+            public_inputs,
+        })
+    }
+}
+
+// Reads a length prefixed byte vector
+#[cfg(feature = "std")]
+fn read_vec<R: Read>(mut reader: R) -> std::io::Result<Vec<u8>> {
+    let len = read_u32(&mut reader)?;
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len as usize {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+#[test]
+fn serialisation_roundtrip() {
+    use acir::{circuit::PublicInputs, native_types::Witness};
+
+    let circuit = Circuit {
+        current_witness_index: 1,
+        opcodes: Vec::new(),
+        public_inputs: PublicInputs(vec![Witness(1)]),
+        return_values: PublicInputs::default(),
+    };
+    let mut proof = Proof::new("test_backend", &circuit, vec![1, 2, 3]);
+    assert_eq!(proof.public_inputs(), None);
+    proof
+        .attach_public_inputs(vec![FieldElement::from(5_i128)])
+        .unwrap();
+
+    let mut bytes = Vec::new();
+    proof.write(&mut bytes).unwrap();
+    let deserialised = Proof::read(&*bytes).unwrap();
+    assert_eq!(deserialised, proof);
+    assert_eq!(
+        deserialised.public_inputs(),
+        Some(&[FieldElement::from(5_i128)][..])
+    );
+
+    assert_eq!(deserialised.check("test_backend", &circuit, 1), Ok(()));
+    assert!(matches!(
+        deserialised.check("other_backend", &circuit, 1),
+        Err(ProofMismatch::Backend { .. })
+    ));
+    assert_eq!(
+        deserialised.check("test_backend", &Circuit::default(), 1),
+        Err(ProofMismatch::Circuit)
+    );
+}
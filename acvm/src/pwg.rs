@@ -1,29 +1,65 @@
 // Re-usable methods that backends can use to implement their PWG
 
-use crate::{OpcodeNotSolvable, OpcodeResolutionError};
+use alloc::collections::{BTreeSet, VecDeque};
+use alloc::{vec, vec::Vec};
+use core::ops::ControlFlow;
+
+use crate::{
+    collections::HashMap, metrics, trace, OpcodeNotSolvable, OpcodeResolutionError,
+    PartialWitnessGenerator, WitnessMap,
+};
 use acir::{
-    native_types::{Expression, Witness},
-    FieldElement,
+    circuit::{opcodes::BlackBoxFuncCall, Circuit, Opcode},
+    native_types::{Expression, Witness, WitnessStack},
+    AcirField, BlackBoxFunc,
 };
-use std::collections::BTreeMap;
 
 // arithmetic
 pub mod arithmetic;
+// solving runs of arithmetic opcodes and invert directives together, and keeping the
+// partial evaluations of arithmetic opcodes which cannot be solved yet
+mod batch;
+// calls between the functions of a program
+pub mod call;
+// checking that a complete witness satisfies the constraints of a circuit
+mod check;
 // Directives
 pub mod directives;
+// Graphviz export of the dependencies between witnesses
+mod graph;
+// recording and replaying the responses of oracles
+pub mod oracle;
+// streaming the events of solving to debuggers
+#[cfg(feature = "std")]
+pub mod events;
+// stepping through the opcodes of a circuit with breakpoints
+pub mod debug;
+// re-solving the witnesses which depend on changed inputs
+mod incremental;
+// solving for outputs as expressions of the unknown inputs
+mod symbolic;
+// reporting the assignment of chosen witnesses while solving
+mod watch;
 // blackbox functions
 pub mod hash;
 pub mod logic;
 pub mod range;
 pub mod signature;
 
+use batch::OpcodeBatch;
+pub use check::check_satisfied;
+pub use graph::{dependency_graph, DotGraph};
+pub use incremental::IncrementalSolver;
+pub use symbolic::solve_symbolic;
+pub use watch::{solve_with_watchpoints, WatchEvent};
+
 // Returns the concrete value for a particular witness
 // If the witness has no assignment, then
 // an error is returned
-pub fn witness_to_value(
-    initial_witness: &BTreeMap<Witness, FieldElement>,
+pub fn witness_to_value<F: AcirField>(
+    initial_witness: &WitnessMap<F>,
     witness: Witness,
-) -> Result<&FieldElement, OpcodeResolutionError> {
+) -> Result<&F, OpcodeResolutionError<F>> {
     match initial_witness.get(&witness) {
         Some(value) => Ok(value),
         None => Err(OpcodeResolutionError::OpcodeNotSolvable(
@@ -31,12 +67,285 @@ pub fn witness_to_value(
         )),
     }
 }
+
+// The callback of `solve_opcodes`, which is given the index of each opcode which is
+// solved along with the witnesses it assigned and the witness map, and can stop solving
+pub(crate) type OnSolved<'a, F> =
+    &'a mut dyn FnMut(usize, Vec<Witness>, &WitnessMap<F>) -> ControlFlow<()>;
+
+// Solves the opcodes at `indices`, in that order where possible. Opcodes which cannot be
+// solved yet wait for the witnesses they are missing, and are only attempted again once
+// one of those witnesses is assigned.
+//
+// If `on_solved` is given, it is called after each opcode is solved. Returns `Break` if
+// it stopped solving.
+pub(crate) fn solve_opcodes<F: AcirField, B: PartialWitnessGenerator<F> + ?Sized>(
+    backend: &B,
+    initial_witness: &mut WitnessMap<F>,
+    opcodes: &[Opcode<F>],
+    indices: impl IntoIterator<Item = usize>,
+    functions: &[Circuit<F>],
+    stack: &mut WitnessStack<F>,
+    mut on_solved: Option<OnSolved<'_, F>>,
+) -> Result<ControlFlow<()>, OpcodeResolutionError<F>> {
+    let _span = trace::span!(DEBUG, "solve", num_opcodes = opcodes.len());
+    let mut queue = OpcodeQueue::new(opcodes.len(), indices);
+    let mut batch = backend.batch_opcodes().then(OpcodeBatch::default);
+    let mut num_solved = 0;
+    let result = loop {
+        if let Some(batch) = &mut batch {
+            batch.evaluate(&queue, opcodes, initial_witness);
+        }
+        let Some(index) = queue.pop() else {
+            break queue.finish().map(|()| ControlFlow::Continue(()));
+        };
+        let opcode = &opcodes[index];
+        let missing: Vec<Witness> = match on_solved {
+            Some(_) => missing_witnesses(opcode, initial_witness),
+            None => Vec::new(),
+        };
+        let solved = match batch
+            .as_mut()
+            .and_then(|batch| batch.solve(index, opcode, initial_witness))
+        {
+            Some(solved) => solved,
+            None => backend.solve_opcode(initial_witness, opcode, functions, stack),
+        };
+        match solved {
+            Ok(()) => {
+                num_solved += 1;
+                queue.solved(index, opcode, initial_witness);
+                if let Some(on_solved) = on_solved.as_deref_mut() {
+                    let assigned = missing
+                        .into_iter()
+                        .filter(|witness| initial_witness.contains_key(witness))
+                        .collect();
+                    if on_solved(index, assigned, initial_witness).is_break() {
+                        break Ok(ControlFlow::Break(()));
+                    }
+                }
+            }
+            Err(OpcodeResolutionError::OpcodeNotSolvable(err)) => {
+                trace::event!(TRACE, opcode_index = index, %err, "opcode is waiting for inputs");
+                queue.stalled(index, opcode, initial_witness, err)
+            }
+            Err(err) => {
+                trace::event!(DEBUG, opcode_index = index, %err, "opcode failed");
+                break Err(with_opcode_context(err, index, opcode));
+            }
+        }
+    };
+    metrics::increment_counter(metrics::OPCODES_SOLVED, &[], num_solved);
+    result
+}
+
+// Adds the index and call of the opcode to errors which only name the black box
+// function which is not supported
+pub(crate) fn with_opcode_context<F: AcirField>(
+    err: OpcodeResolutionError<F>,
+    opcode_index: usize,
+    opcode: &Opcode<F>,
+) -> OpcodeResolutionError<F> {
+    match (err, opcode) {
+        (OpcodeResolutionError::UnsupportedBlackBoxFunc(_), Opcode::BlackBoxFuncCall(call)) => {
+            OpcodeResolutionError::UnsupportedBlackBoxFuncCall {
+                opcode_index,
+                call: call.clone(),
+            }
+        }
+        (err, _) => err,
+    }
+}
+
+// The opcodes which are waiting to be solved
+//
+// Opcodes which cannot be solved wait for the witnesses they are missing, and are queued
+// again once one of them is assigned
+#[derive(Clone, Debug)]
+pub(crate) struct OpcodeQueue<F: AcirField> {
+    pending: VecDeque<usize>,
+    queued: Vec<bool>,
+    unsolved: Vec<Option<OpcodeNotSolvable<F>>>,
+    waiting: HashMap<Witness, Vec<usize>>,
+}
+
+impl<F: AcirField> OpcodeQueue<F> {
+    // Queues the opcodes at `indices` of a list of `num_opcodes` opcodes
+    pub(crate) fn new(num_opcodes: usize, indices: impl IntoIterator<Item = usize>) -> Self {
+        let pending: VecDeque<usize> = indices.into_iter().collect();
+        let mut queued = vec![false; num_opcodes];
+        for &index in &pending {
+            queued[index] = true;
+        }
+        OpcodeQueue {
+            pending,
+            queued,
+            unsolved: vec![None; num_opcodes],
+            waiting: HashMap::new(),
+        }
+    }
+
+    // Returns the indices of the opcodes which will be attempted, in order
+    pub(crate) fn pending(&self) -> impl Iterator<Item = usize> + '_ {
+        self.pending.iter().copied()
+    }
+
+    // Returns the index of the next opcode to attempt, without removing it
+    pub(crate) fn peek(&self) -> Option<usize> {
+        self.pending.front().copied()
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<usize> {
+        let index = self.pending.pop_front()?;
+        self.queued[index] = false;
+        Some(index)
+    }
+
+    // Queues the opcodes which were waiting for the witnesses the opcode assigned
+    pub(crate) fn solved(&mut self, index: usize, opcode: &Opcode<F>, witness: &WitnessMap<F>) {
+        self.unsolved[index] = None;
+        for assigned in opcode_witnesses(opcode) {
+            if !witness.contains_key(&assigned) {
+                continue;
+            }
+            for waiter in self.waiting.remove(&assigned).unwrap_or_default() {
+                if self.unsolved[waiter].is_some() && !self.queued[waiter] {
+                    self.queued[waiter] = true;
+                    self.pending.push_back(waiter);
+                }
+            }
+        }
+    }
+
+    // Makes the opcode wait for the witnesses it is missing
+    pub(crate) fn stalled(
+        &mut self,
+        index: usize,
+        opcode: &Opcode<F>,
+        witness: &WitnessMap<F>,
+        err: OpcodeNotSolvable<F>,
+    ) {
+        // Witnesses are never unassigned, so the witnesses which are missing now were
+        // already missing when the opcode was first attempted
+        if self.unsolved[index].is_none() {
+            for missing in missing_witnesses(opcode, witness) {
+                self.waiting.entry(missing).or_default().push(index);
+            }
+        }
+        self.unsolved[index] = Some(err);
+    }
+
+    // Returns the error of the first opcode which could not be solved, once the queue
+    // is empty
+    pub(crate) fn finish(&self) -> Result<(), OpcodeResolutionError<F>> {
+        match self.unsolved.iter().flatten().next() {
+            Some(err) => Err(OpcodeResolutionError::OpcodeNotSolvable(err.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+// Returns the witnesses of an opcode which are not assigned
+pub(crate) fn missing_witnesses<F: AcirField>(
+    opcode: &Opcode<F>,
+    witness: &WitnessMap<F>,
+) -> Vec<Witness> {
+    opcode_witnesses(opcode)
+        .into_iter()
+        .filter(|w| !witness.contains_key(w))
+        .collect()
+}
+
+// Returns every witness which an opcode reads or assigns
+pub(crate) fn opcode_witnesses<F: AcirField>(opcode: &Opcode<F>) -> BTreeSet<Witness> {
+    match opcode {
+        Opcode::Arithmetic(expr) => expr.witnesses(),
+        Opcode::BlackBoxFuncCall(func_call) => func_call
+            .inputs
+            .iter()
+            .map(|input| input.witness)
+            .chain(func_call.outputs.iter().copied())
+            .collect(),
+        Opcode::Directive(directive) => {
+            let mut witnesses = directive.inputs();
+            witnesses.extend(directive.outputs());
+            witnesses
+        }
+        Opcode::Call {
+            inputs, outputs, ..
+        } => inputs.iter().chain(outputs).copied().collect(),
+    }
+}
+
+/// The black box functions which `solve_native_black_box` can solve
+pub const NATIVE_BLACK_BOX_FUNCTIONS: [BlackBoxFunc; 6] = [
+    BlackBoxFunc::AND,
+    BlackBoxFunc::XOR,
+    BlackBoxFunc::RANGE,
+    BlackBoxFunc::SHA256,
+    BlackBoxFunc::Blake2s,
+    BlackBoxFunc::EcdsaSecp256k1,
+];
+
+// Solves the black box functions which are implemented in this module, so they do not
+// depend on a backend. Other functions return `UnsupportedBlackBoxFunc`.
+pub fn solve_native_black_box<F: AcirField>(
+    initial_witness: &mut WitnessMap<F>,
+    func_call: &BlackBoxFuncCall,
+) -> Result<(), OpcodeResolutionError<F>> {
+    if let Some(input) = func_call
+        .inputs
+        .iter()
+        .find(|input| !initial_witness.contains_key(&input.witness))
+    {
+        return Err(OpcodeResolutionError::OpcodeNotSolvable(
+            OpcodeNotSolvable::MissingAssignment(input.witness.witness_index()),
+        ));
+    }
+
+    match func_call.name {
+        BlackBoxFunc::AND | BlackBoxFunc::XOR => {
+            logic::solve_logic_opcode(initial_witness, func_call)
+        }
+        BlackBoxFunc::RANGE => range::solve_range_opcode(initial_witness, func_call),
+        BlackBoxFunc::SHA256 => {
+            hash::sha256(initial_witness, func_call);
+            Ok(())
+        }
+        BlackBoxFunc::Blake2s => {
+            hash::blake2s(initial_witness, func_call);
+            Ok(())
+        }
+        BlackBoxFunc::EcdsaSecp256k1 => {
+            signature::ecdsa::secp256k1_prehashed(initial_witness, func_call)
+        }
+        _ => Err(OpcodeResolutionError::UnsupportedBlackBoxFunc(
+            func_call.name,
+        )),
+    }
+}
+
+/// A partial witness generator which does not depend on a backend, and solves the
+/// black box functions in `NATIVE_BLACK_BOX_FUNCTIONS`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NativeSolver;
+
+impl<F: AcirField> PartialWitnessGenerator<F> for NativeSolver {
+    fn solve_blackbox_function_call(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        func_call: &BlackBoxFuncCall,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        solve_native_black_box(initial_witness, func_call)
+    }
+}
+
 // TODO: There is an issue open to decide on whether we need to get values from Expressions
 // TODO versus just getting values from Witness
-pub fn get_value(
-    expr: &Expression,
-    initial_witness: &BTreeMap<Witness, FieldElement>,
-) -> Result<FieldElement, OpcodeResolutionError> {
+pub fn get_value<F: AcirField>(
+    expr: &Expression<F>,
+    initial_witness: &WitnessMap<F>,
+) -> Result<F, OpcodeResolutionError<F>> {
     let mut result = expr.q_c;
 
     for term in &expr.linear_combinations {
@@ -1,34 +1,49 @@
 use acir::{
     native_types::{Expression, Witness},
-    FieldElement,
+    AcirField,
 };
-use std::collections::BTreeMap;
+use alloc::vec::Vec;
 
-use crate::{OpcodeNotSolvable, OpcodeResolutionError};
+use crate::{OpcodeNotSolvable, OpcodeResolutionError, WitnessMap};
 
 /// An Arithmetic solver will take a Circuit's arithmetic gates with witness assignments
 /// and create the other witness variables
 pub struct ArithmeticSolver;
 
 #[allow(clippy::enum_variant_names)]
-enum GateStatus {
-    GateSatisfied(FieldElement),
-    GateSolvable(FieldElement, (FieldElement, Witness)),
+enum GateStatus<F> {
+    GateSatisfied(F),
+    GateSolvable(F, (F, Witness)),
     GateUnsolvable,
 }
 
-enum MulTerm {
-    OneUnknown(FieldElement, Witness), // (qM * known_witness, unknown_witness)
+enum MulTerm<F> {
+    OneUnknown(F, Witness, F), // (qM * known_witness, unknown_witness, sum of the solved terms)
     TooManyUnknowns,
-    Solved(FieldElement),
+    Solved(F),
 }
 
 impl ArithmeticSolver {
     /// Derives the rest of the witness based on the initial low level variables
-    pub fn solve(
-        initial_witness: &mut BTreeMap<Witness, FieldElement>,
-        gate: &Expression,
-    ) -> Result<(), OpcodeResolutionError> {
+    pub fn solve<F: AcirField>(
+        initial_witness: &mut WitnessMap<F>,
+        gate: &Expression<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        if let Some((witness, value)) = ArithmeticSolver::evaluate(initial_witness, gate)? {
+            initial_witness.insert(witness, value);
+        }
+        Ok(())
+    }
+
+    /// Evaluates the gate without assigning to the witness, returning the witness which
+    /// it solves for along with its value, or `None` if every witness is assigned and
+    /// the gate is satisfied.
+    ///
+    /// As the witness is only read, many gates can be evaluated at once.
+    pub fn evaluate<F: AcirField>(
+        initial_witness: &WitnessMap<F>,
+        gate: &Expression<F>,
+    ) -> Result<Option<(Witness, F)>, OpcodeResolutionError<F>> {
         // Evaluate multiplication term
         let mul_result = ArithmeticSolver::solve_mul_term(gate, initial_witness);
         // Evaluate the fan-in terms
@@ -40,21 +55,19 @@ impl ArithmeticSolver {
                     OpcodeNotSolvable::ExpressionHasTooManyUnknowns(gate.clone()),
                 ))
             }
-            (MulTerm::OneUnknown(q, w1), GateStatus::GateSolvable(a, (b, w2))) => {
+            (MulTerm::OneUnknown(q, w1, solved), GateStatus::GateSolvable(a, (b, w2))) => {
                 if w1 == w2 {
                     // We have one unknown so we can solve the equation
-                    let total_sum = a + gate.q_c;
+                    let total_sum = solved + a + gate.q_c;
                     if (q + b).is_zero() {
                         if !total_sum.is_zero() {
                             Err(OpcodeResolutionError::UnsatisfiedConstrain)
                         } else {
-                            Ok(())
+                            Ok(None)
                         }
                     } else {
                         let assignment = -total_sum / (q + b);
-                        // Add this into the witness assignments
-                        initial_witness.insert(w1, assignment);
-                        Ok(())
+                        Ok(Some((w1, assignment)))
                     }
                 } else {
                     // TODO: can we be more specific with this error?
@@ -63,23 +76,24 @@ impl ArithmeticSolver {
                     ))
                 }
             }
-            (MulTerm::OneUnknown(partial_prod, unknown_var), GateStatus::GateSatisfied(sum)) => {
+            (
+                MulTerm::OneUnknown(partial_prod, unknown_var, solved),
+                GateStatus::GateSatisfied(sum),
+            ) => {
                 // We have one unknown in the mul term and the fan-in terms are solved.
                 // Hence the equation is solvable, since there is a single unknown
-                // The equation is: partial_prod * unknown_var + sum + qC = 0
+                // The equation is: partial_prod * unknown_var + solved + sum + qC = 0
 
-                let total_sum = sum + gate.q_c;
+                let total_sum = solved + sum + gate.q_c;
                 if partial_prod.is_zero() {
                     if !total_sum.is_zero() {
                         Err(OpcodeResolutionError::UnsatisfiedConstrain)
                     } else {
-                        Ok(())
+                        Ok(None)
                     }
                 } else {
                     let assignment = -(total_sum / partial_prod);
-                    // Add this into the witness assignments
-                    initial_witness.insert(unknown_var, assignment);
-                    Ok(())
+                    Ok(Some((unknown_var, assignment)))
                 }
             }
             (MulTerm::Solved(a), GateStatus::GateSatisfied(b)) => {
@@ -88,7 +102,7 @@ impl ArithmeticSolver {
                 if !(a + b + gate.q_c).is_zero() {
                     Err(OpcodeResolutionError::UnsatisfiedConstrain)
                 } else {
-                    Ok(())
+                    Ok(None)
                 }
             }
             (
@@ -103,64 +117,99 @@ impl ArithmeticSolver {
                     if !total_sum.is_zero() {
                         Err(OpcodeResolutionError::UnsatisfiedConstrain)
                     } else {
-                        Ok(())
+                        Ok(None)
                     }
                 } else {
                     let assignment = -(total_sum / coeff);
-                    // Add this into the witness assignments
-                    initial_witness.insert(unknown_var, assignment);
-                    Ok(())
+                    Ok(Some((unknown_var, assignment)))
                 }
             }
         }
     }
 
-    /// Returns the evaluation of the multiplication term in the arithmetic gate
-    /// If the witness values are not known, then the function returns a None
+    /// Returns the gate with the terms whose witnesses are all assigned folded into its
+    /// constant, so that evaluating it again only reads the terms which had a missing
+    /// witness. Evaluating the folded gate gives the same result as evaluating `gate`,
+    /// except that errors name the folded gate.
+    pub fn fold_known<F: AcirField>(
+        initial_witness: &WitnessMap<F>,
+        gate: &Expression<F>,
+    ) -> Expression<F> {
+        let mut q_c = gate.q_c;
+        let mut mul_terms = Vec::new();
+        for &(q_m, w_l, w_r) in &gate.mul_terms {
+            match (initial_witness.get(&w_l), initial_witness.get(&w_r)) {
+                (Some(w_l), Some(w_r)) => q_c += q_m * *w_l * *w_r,
+                _ => mul_terms.push((q_m, w_l, w_r)),
+            }
+        }
+        let mut linear_combinations = Vec::new();
+        for &(q_l, w_l) in &gate.linear_combinations {
+            match initial_witness.get(&w_l) {
+                Some(w_l) => q_c += q_l * *w_l,
+                None => linear_combinations.push((q_l, w_l)),
+            }
+        }
+        Expression {
+            mul_terms,
+            linear_combinations,
+            q_c,
+        }
+    }
+
+    /// Returns the evaluation of the multiplication terms in the arithmetic gate.
+    ///
+    /// Circuits compiled for R1CS keep several multiplication terms in an expression. These
+    /// are solvable if at most one witness is unknown, and it is not multiplied by itself.
     /// XXX: Do we need to account for the case where 5xy + 6x = 0 ? We do not know y, but it can be solved given x . But I believe x can be solved with another gate
     /// XXX: What about making a mul gate = a constant 5xy + 7 = 0 ? This is the same as the above.
-    fn solve_mul_term(
-        arith_gate: &Expression,
-        witness_assignments: &BTreeMap<Witness, FieldElement>,
-    ) -> MulTerm {
-        // First note that the mul term can only contain one/zero term
-        // We are assuming it has been optimised.
-        match arith_gate.mul_terms.len() {
-            0 => MulTerm::Solved(FieldElement::zero()),
-            1 => {
-                let q_m = &arith_gate.mul_terms[0].0;
-                let w_l = &arith_gate.mul_terms[0].1;
-                let w_r = &arith_gate.mul_terms[0].2;
-
-                // Check if these values are in the witness assignments
-                let w_l_value = witness_assignments.get(w_l);
-                let w_r_value = witness_assignments.get(w_r);
-
-                match (w_l_value, w_r_value) {
-                    (None, None) => MulTerm::TooManyUnknowns,
-                    (Some(w_l), Some(w_r)) => MulTerm::Solved(*q_m * *w_l * *w_r),
-                    (None, Some(w_r)) => MulTerm::OneUnknown(*q_m * *w_r, *w_l),
-                    (Some(w_l), None) => MulTerm::OneUnknown(*q_m * *w_l, *w_r),
-                }
+    fn solve_mul_term<F: AcirField>(
+        arith_gate: &Expression<F>,
+        witness_assignments: &WitnessMap<F>,
+    ) -> MulTerm<F> {
+        let mut solved = F::zero();
+        let mut unknown: Option<(F, Witness)> = None;
+        for (q_m, w_l, w_r) in &arith_gate.mul_terms {
+            // Check if these values are in the witness assignments
+            let (partial_prod, unknown_var) =
+                match (witness_assignments.get(w_l), witness_assignments.get(w_r)) {
+                    (None, None) => return MulTerm::TooManyUnknowns,
+                    (Some(w_l), Some(w_r)) => {
+                        solved += *q_m * *w_l * *w_r;
+                        continue;
+                    }
+                    (None, Some(w_r)) => (*q_m * *w_r, *w_l),
+                    (Some(w_l), None) => (*q_m * *w_l, *w_r),
+                };
+            match &mut unknown {
+                None => unknown = Some((partial_prod, unknown_var)),
+                Some((q, w)) if *w == unknown_var => *q += partial_prod,
+                Some(_) => return MulTerm::TooManyUnknowns,
             }
-            _ => panic!("Mul term in the arithmetic gate must contain either zero or one term"),
+        }
+
+        match unknown {
+            Some((partial_prod, unknown_var)) => {
+                MulTerm::OneUnknown(partial_prod, unknown_var, solved)
+            }
+            None => MulTerm::Solved(solved),
         }
     }
 
     /// Returns the summation of all of the variables, plus the unknown variable
     /// Returns None, if there is more than one unknown variable
     /// We cannot assign
-    fn solve_fan_in_term(
-        arith_gate: &Expression,
-        witness_assignments: &BTreeMap<Witness, FieldElement>,
-    ) -> GateStatus {
+    fn solve_fan_in_term<F: AcirField>(
+        arith_gate: &Expression<F>,
+        witness_assignments: &WitnessMap<F>,
+    ) -> GateStatus<F> {
         // This is assuming that the fan-in is more than 0
 
         // This is the variable that we want to assign the value to
-        let mut unknown_variable = (FieldElement::zero(), Witness::default());
+        let mut unknown_variable = (F::zero(), Witness::default());
         let mut num_unknowns = 0;
         // This is the sum of all of the known variables
-        let mut result = FieldElement::zero();
+        let mut result = F::zero();
 
         for term in arith_gate.linear_combinations.iter() {
             let q_l = term.0;
@@ -193,6 +242,8 @@ impl ArithmeticSolver {
 
 #[test]
 fn arithmetic_smoke_test() {
+    use acir::FieldElement;
+
     let a = Witness(0);
     let b = Witness(1);
     let c = Witness(2);
@@ -221,7 +272,7 @@ fn arithmetic_smoke_test() {
         q_c: FieldElement::zero(),
     };
 
-    let mut values: BTreeMap<Witness, FieldElement> = BTreeMap::new();
+    let mut values = WitnessMap::new();
     values.insert(b, FieldElement::from(2_i128));
     values.insert(c, FieldElement::from(1_i128));
     values.insert(d, FieldElement::from(1_i128));
@@ -231,3 +282,77 @@ fn arithmetic_smoke_test() {
 
     assert_eq!(values.get(&a).unwrap(), &FieldElement::from(4_i128));
 }
+
+#[test]
+fn several_mul_terms() {
+    use acir::FieldElement;
+
+    let (a, b, c, d) = (Witness(0), Witness(1), Witness(2), Witness(3));
+
+    // a * b + 2 * c * b + c * d - d = 0
+    let gate = Expression {
+        mul_terms: vec![
+            (FieldElement::one(), a, b),
+            (FieldElement::from(2_i128), c, b),
+            (FieldElement::one(), c, d),
+        ],
+        linear_combinations: vec![(-FieldElement::one(), d)],
+        q_c: FieldElement::zero(),
+    };
+
+    // a = 1, c = 2 and d = 15 give 5 * b + 15 = 0
+    let mut values = WitnessMap::new();
+    values.insert(a, FieldElement::one());
+    values.insert(c, FieldElement::from(2_i128));
+    values.insert(d, FieldElement::from(15_i128));
+    assert_eq!(ArithmeticSolver::solve(&mut values, &gate), Ok(()));
+    assert_eq!(values.get(&b).unwrap(), &FieldElement::from(-3_i128));
+    assert_eq!(ArithmeticSolver::solve(&mut values, &gate), Ok(()));
+
+    // b and d are both unknown
+    let mut values = WitnessMap::new();
+    values.insert(a, FieldElement::one());
+    values.insert(c, FieldElement::from(2_i128));
+    assert!(ArithmeticSolver::solve(&mut values, &gate).is_err());
+}
+
+#[test]
+fn folded_gates_evaluate_as_the_gate() {
+    use acir::FieldElement;
+
+    let (a, b, c, d) = (Witness(0), Witness(1), Witness(2), Witness(3));
+
+    // a * b + 3 * c * d + 2 * a - c + 7 = 0
+    let gate = Expression {
+        mul_terms: vec![
+            (FieldElement::one(), a, b),
+            (FieldElement::from(3_i128), c, d),
+        ],
+        linear_combinations: vec![(FieldElement::from(2_i128), a), (-FieldElement::one(), c)],
+        q_c: FieldElement::from(7_i128),
+    };
+
+    // Only the terms of c and d are left once a and b are assigned
+    let mut values = WitnessMap::new();
+    values.insert(a, FieldElement::from(2_i128));
+    values.insert(b, FieldElement::from(5_i128));
+    let folded = ArithmeticSolver::fold_known(&values, &gate);
+    assert_eq!(folded.mul_terms, vec![(FieldElement::from(3_i128), c, d)]);
+    assert_eq!(folded.linear_combinations, vec![(-FieldElement::one(), c)]);
+    assert_eq!(folded.q_c, FieldElement::from(21_i128));
+    assert!(ArithmeticSolver::evaluate(&values, &folded).is_err());
+
+    // Folding again as more witnesses are assigned gives the same values
+    for (witness, value) in [(c, 4_i128), (d, 6_i128)] {
+        values.insert(witness, FieldElement::from(value));
+        let folded = ArithmeticSolver::fold_known(&values, &folded);
+        assert_eq!(
+            ArithmeticSolver::evaluate(&values, &folded),
+            ArithmeticSolver::evaluate(&values, &gate)
+        );
+    }
+    assert_eq!(
+        ArithmeticSolver::evaluate(&values, &gate),
+        Err(OpcodeResolutionError::UnsatisfiedConstrain)
+    );
+}
@@ -0,0 +1,302 @@
+// Solves opcodes in the solver's queue together rather than one at a time
+//
+// Arithmetic opcodes only read the witness map while they are evaluated, so the run of
+// them at the front of the queue is evaluated at once on several threads, and their
+// assignments are then applied one at a time as the solver pops them. An evaluation which
+// an earlier assignment of the run could have changed is discarded and the opcode is
+// solved again.
+//
+// Arithmetic opcodes which cannot be solved yet are kept with the terms whose witnesses
+// are assigned folded into their constants, so that when they are attempted again once
+// a missing witness is assigned, only the terms which were missing a witness are read.
+//
+// Invert directives only depend on their input, which never changes once it is assigned.
+// When one reaches the front of the queue, every queued invert directive whose input is
+// assigned is inverted with a single field inversion, and each inverse is assigned when
+// its directive is popped.
+//
+// The witness and errors are the same as when the opcodes are solved one at a time.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::collections::HashMap;
+
+use acir::{
+    circuit::{directives::Directive, Opcode},
+    native_types::{Expression, Witness},
+    AcirField,
+};
+
+use super::{arithmetic::ArithmeticSolver, directives::batch_inverse, OpcodeQueue};
+use crate::{compiler::parallel, OpcodeNotSolvable, OpcodeResolutionError, WitnessMap};
+
+type Evaluation<F> = Result<Option<(Witness, F)>, OpcodeResolutionError<F>>;
+
+pub(super) struct OpcodeBatch<F: AcirField> {
+    // The evaluations of the opcodes at the front of the queue, in the order of the queue,
+    // along with the folded opcodes which cannot be solved yet
+    evaluations: VecDeque<(Evaluation<F>, Option<Expression<F>>)>,
+    // The number of witnesses which were assigned when the opcodes were evaluated
+    num_assigned: usize,
+    // The number of opcodes at the front of the queue which were found to be too few to
+    // evaluate together, so that they are not looked at again
+    num_unbatched: usize,
+    // The arithmetic opcodes which could not be solved, with the terms whose witnesses
+    // were assigned folded into their constants, by the index of the opcode
+    folded: HashMap<usize, Expression<F>>,
+    // The inverses of the invert directives in the queue, by the index of the directive
+    inverses: HashMap<usize, F>,
+    // The number of opcodes to pop before the queue is searched for invert directives
+    // again, so that the searches take time linear in the number of opcodes popped
+    num_until_search: usize,
+}
+
+impl<F: AcirField> Default for OpcodeBatch<F> {
+    fn default() -> Self {
+        OpcodeBatch {
+            evaluations: VecDeque::new(),
+            num_assigned: 0,
+            num_unbatched: 0,
+            folded: HashMap::new(),
+            inverses: HashMap::new(),
+            num_until_search: 0,
+        }
+    }
+}
+
+impl<F: AcirField> OpcodeBatch<F> {
+    // Evaluates the opcodes at the front of the queue which are solved together, before
+    // the first of them is popped
+    pub(super) fn evaluate(
+        &mut self,
+        queue: &OpcodeQueue<F>,
+        opcodes: &[Opcode<F>],
+        witness: &WitnessMap<F>,
+    ) {
+        self.evaluate_arithmetic(queue, opcodes, witness);
+        self.invert(queue, opcodes, witness);
+    }
+
+    // Evaluates the run of arithmetic opcodes at the front of the queue, if the previous
+    // run has been solved and this one is long enough to be worth splitting across threads
+    fn evaluate_arithmetic(
+        &mut self,
+        queue: &OpcodeQueue<F>,
+        opcodes: &[Opcode<F>],
+        witness: &WitnessMap<F>,
+    ) {
+        if !self.evaluations.is_empty() || self.num_unbatched > 0 {
+            return;
+        }
+        let gates: Vec<&Expression<F>> = queue
+            .pending()
+            .map_while(|index| match &opcodes[index] {
+                Opcode::Arithmetic(gate) => Some(self.folded.get(&index).unwrap_or(gate)),
+                _ => None,
+            })
+            .collect();
+        if !parallel::runs_in_parallel(gates.len()) {
+            // The opcode after the run is not arithmetic, so it is skipped as well
+            self.num_unbatched = gates.len() + 1;
+            return;
+        }
+        self.evaluations = parallel::map(gates, |gate| {
+            let evaluation = ArithmeticSolver::evaluate(witness, gate);
+            let folded = matches!(evaluation, Err(OpcodeResolutionError::OpcodeNotSolvable(_)))
+                .then(|| ArithmeticSolver::fold_known(witness, gate));
+            (evaluation, folded)
+        })
+        .into();
+        self.num_assigned = witness.len();
+    }
+
+    // Inverts the invert directives in the queue whose inputs are assigned, if the one at
+    // the front of the queue has not been inverted
+    fn invert(&mut self, queue: &OpcodeQueue<F>, opcodes: &[Opcode<F>], witness: &WitnessMap<F>) {
+        let Some(front) = queue.pending().next() else {
+            return;
+        };
+        if self.num_until_search > 0
+            || self.inverses.contains_key(&front)
+            || !matches!(opcodes[front], Opcode::Directive(Directive::Invert { .. }))
+        {
+            return;
+        }
+
+        let mut num_queued = 0;
+        let (indices, mut values): (Vec<usize>, Vec<F>) = queue
+            .pending()
+            .inspect(|_| num_queued += 1)
+            .filter_map(|index| match &opcodes[index] {
+                Opcode::Directive(Directive::Invert { x, .. }) => Some((index, *witness.get(x)?)),
+                _ => None,
+            })
+            .unzip();
+        batch_inverse(&mut values);
+        self.inverses.extend(indices.into_iter().zip(values));
+        self.num_until_search = num_queued;
+    }
+
+    // Solves the opcode at `index`, which was just popped from the queue, or returns `None`
+    // if it is solved by the backend
+    pub(super) fn solve(
+        &mut self,
+        index: usize,
+        opcode: &Opcode<F>,
+        witness: &mut WitnessMap<F>,
+    ) -> Option<Result<(), OpcodeResolutionError<F>>> {
+        self.num_until_search = self.num_until_search.saturating_sub(1);
+        let evaluation = if self.num_unbatched > 0 {
+            self.num_unbatched -= 1;
+            None
+        } else {
+            self.evaluations.pop_front()
+        };
+
+        match opcode {
+            Opcode::Directive(Directive::Invert { result, .. }) => {
+                let inverse = self.inverses.remove(&index)?;
+                witness.insert(*result, inverse);
+                Some(Ok(()))
+            }
+            Opcode::Arithmetic(gate) => Some(match evaluation {
+                Some((evaluation, folded)) => self.apply(index, gate, evaluation, folded, witness),
+                None => self.solve_folded(index, gate, witness),
+            }),
+            _ => None,
+        }
+    }
+
+    // Applies the evaluation of an arithmetic opcode in the run at the front of the queue
+    fn apply(
+        &mut self,
+        index: usize,
+        gate: &Expression<F>,
+        evaluation: Evaluation<F>,
+        folded: Option<Expression<F>>,
+        witness: &mut WitnessMap<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        if let Some(folded) = folded {
+            self.folded.insert(index, folded);
+        }
+        match evaluation {
+            Ok(Some((unknown, value))) if !witness.contains_key(&unknown) => {
+                self.folded.remove(&index);
+                witness.insert(unknown, value);
+                Ok(())
+            }
+            // Witnesses which were missing may have been assigned since
+            Ok(Some(_)) | Err(OpcodeResolutionError::OpcodeNotSolvable(_))
+                if witness.len() > self.num_assigned =>
+            {
+                self.solve_folded(index, gate, witness)
+            }
+            Err(OpcodeResolutionError::OpcodeNotSolvable(_)) => Err(too_many_unknowns(gate)),
+            evaluation => {
+                self.folded.remove(&index);
+                evaluation.map(|_| ())
+            }
+        }
+    }
+
+    // Solves an arithmetic opcode from the terms which were left when it was last
+    // attempted. If it still cannot be solved, the witnesses which have been assigned
+    // since are folded in.
+    fn solve_folded(
+        &mut self,
+        index: usize,
+        gate: &Expression<F>,
+        witness: &mut WitnessMap<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        let folded = self.folded.remove(&index);
+        let terms = folded.as_ref().unwrap_or(gate);
+        match ArithmeticSolver::evaluate(witness, terms) {
+            Ok(Some((unknown, value))) => {
+                witness.insert(unknown, value);
+                Ok(())
+            }
+            Ok(None) => Ok(()),
+            Err(OpcodeResolutionError::OpcodeNotSolvable(_)) => {
+                let folded = ArithmeticSolver::fold_known(witness, terms);
+                self.folded.insert(index, folded);
+                Err(too_many_unknowns(gate))
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+// The error of an arithmetic opcode which cannot be solved yet, which names the opcode
+// rather than its folded terms
+fn too_many_unknowns<F: AcirField>(gate: &Expression<F>) -> OpcodeResolutionError<F> {
+    OpcodeResolutionError::OpcodeNotSolvable(OpcodeNotSolvable::ExpressionHasTooManyUnknowns(
+        gate.clone(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        profile::Profiler,
+        pwg::NativeSolver,
+        testing::{generate_circuit, CircuitSpec},
+        PartialWitnessGenerator,
+    };
+
+    use super::*;
+
+    #[test]
+    fn batches_solve_as_opcodes_one_at_a_time() {
+        let (circuit, inputs) = generate_circuit(&CircuitSpec {
+            num_arithmetic: 200,
+            ..CircuitSpec::default()
+        });
+        let last = Witness(circuit.current_witness_index);
+        // Reversed, the opcodes are solved in many runs which depend on each other
+        let mut opcodes: Vec<Opcode> = circuit.opcodes.into_iter().rev().collect();
+        // Inverses of witnesses which are assigned at different points, some of which are
+        // next to each other
+        for i in 1..=20 {
+            let invert = Directive::Invert {
+                x: Witness(i * 10),
+                result: Witness(last.0 + i),
+            };
+            opcodes.insert(i as usize * 7 % 150, Opcode::Directive(invert));
+        }
+        let solve = |backend: &dyn PartialWitnessGenerator, opcodes: &[Opcode]| {
+            let mut witness = inputs.clone();
+            let result = backend.solve(&mut witness, opcodes.to_vec());
+            (result, witness)
+        };
+
+        // The profiler times each opcode, so it solves them one at a time
+        let profiler = Profiler::new(NativeSolver);
+        assert!(!profiler.batch_opcodes());
+        let (result, witness) = solve(&NativeSolver, &opcodes);
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            witness[&Witness(last.0 + 1)],
+            witness[&Witness(10)].inverse()
+        );
+        assert_eq!(solve(&profiler, &opcodes), (result, witness));
+
+        // A gate which is not satisfied fails in the same way
+        opcodes.insert(
+            0,
+            Opcode::Arithmetic(&Expression::from(last) - &Expression::one()),
+        );
+        let (result, witness) = solve(&NativeSolver, &opcodes);
+        assert_eq!(result, Err(OpcodeResolutionError::UnsatisfiedConstrain));
+        assert_eq!(solve(&profiler, &opcodes), (result, witness));
+
+        // A gate which is never solved is reported as it is in the circuit, rather than
+        // with its assigned terms folded
+        let unsolvable = &(&Expression::from(last) + &Expression::from(Witness(last.0 + 21)))
+            - &Expression::from(Witness(last.0 + 22));
+        opcodes[0] = Opcode::Arithmetic(unsolvable.clone());
+        let (result, witness) = solve(&NativeSolver, &opcodes);
+        assert_eq!(result, Err(too_many_unknowns(&unsolvable)));
+        assert_eq!(solve(&profiler, &opcodes), (result, witness));
+    }
+}
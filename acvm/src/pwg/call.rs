@@ -0,0 +1,150 @@
+#[cfg(feature = "std")]
+use core::cell::RefCell;
+#[cfg(not(feature = "std"))]
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use acir::{
+    circuit::Circuit,
+    native_types::{Witness, WitnessStack},
+    AcirField,
+};
+
+use super::witness_to_value;
+use crate::{OpcodeResolutionError, PartialWitnessGenerator, WitnessMap};
+
+#[cfg(feature = "std")]
+thread_local! {
+    // The calls which are being solved on this thread, by the functions of their program
+    // and the function's index, used to detect recursion as `compiler::inline` does.
+    // Calls are solved through the backend's `solve_with_calls`, so the stack cannot be
+    // passed down to the nested calls.
+    static ACTIVE_CALLS: RefCell<Vec<(*const (), u32)>> = const { RefCell::new(Vec::new()) };
+}
+
+// Without thread locals the calls cannot be recorded, so recursion is detected from how
+// deeply they are nested instead: calls which do not recurse are nested at most one fewer
+// times than their program has functions.
+#[cfg(not(feature = "std"))]
+static CALL_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+// Removes a call from `ACTIVE_CALLS` once it has been solved, or if solving panics
+struct ActiveCall;
+
+impl ActiveCall {
+    #[cfg(feature = "std")]
+    fn enter<F: AcirField>(
+        functions: &[Circuit<F>],
+        id: u32,
+    ) -> Result<Self, OpcodeResolutionError<F>> {
+        ACTIVE_CALLS.with(|calls| {
+            let mut calls = calls.borrow_mut();
+            let call = (functions.as_ptr().cast(), id);
+            if calls.contains(&call) {
+                return Err(OpcodeResolutionError::RecursiveCall(id));
+            }
+            calls.push(call);
+            Ok(ActiveCall)
+        })
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn enter<F: AcirField>(
+        functions: &[Circuit<F>],
+        id: u32,
+    ) -> Result<Self, OpcodeResolutionError<F>> {
+        let depth = CALL_DEPTH.fetch_add(1, Ordering::Relaxed) + 1;
+        let call = ActiveCall;
+        if depth >= functions.len() {
+            return Err(OpcodeResolutionError::RecursiveCall(id));
+        }
+        Ok(call)
+    }
+}
+
+impl Drop for ActiveCall {
+    #[cfg(feature = "std")]
+    fn drop(&mut self) {
+        ACTIVE_CALLS.with(|calls| calls.borrow_mut().pop());
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn drop(&mut self) {
+        CALL_DEPTH.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Solves a call by solving the callee with a fresh witness map which
+/// is initialised with the values of `inputs`.
+///
+/// The callee's public inputs are its parameters.
+/// Once the callee is solved, its return values are assigned to `outputs`
+/// and its witness is pushed onto `stack`. Fails with `RecursiveCall` if the callee
+/// is already being solved, as a recursive program would never finish.
+pub fn solve_call<F: AcirField, B: PartialWitnessGenerator<F> + ?Sized>(
+    backend: &B,
+    initial_witness: &mut WitnessMap<F>,
+    functions: &[Circuit<F>],
+    stack: &mut WitnessStack<F>,
+    id: u32,
+    inputs: &[Witness],
+    outputs: &[Witness],
+) -> Result<(), OpcodeResolutionError<F>> {
+    let callee = functions
+        .get(id as usize)
+        .ok_or(OpcodeResolutionError::UnknownFunction(id))?;
+
+    let (parameters, return_values) = (&callee.public_inputs.0, &callee.return_values.0);
+    if parameters.len() != inputs.len() || return_values.len() != outputs.len() {
+        return Err(OpcodeResolutionError::IncorrectNumCallArguments(
+            id,
+            parameters.len() + return_values.len(),
+            inputs.len() + outputs.len(),
+        ));
+    }
+
+    let mut callee_witness = WitnessMap::dense(callee.num_vars());
+    for (parameter, input) in parameters.iter().zip(inputs) {
+        let value = *witness_to_value(initial_witness, *input)?;
+        if let Some(existing) = callee_witness.insert(*parameter, value) {
+            // The same witness is used for two parameters
+            if existing != value {
+                return Err(OpcodeResolutionError::UnsatisfiedConstrain);
+            }
+        }
+    }
+
+    let _call = ActiveCall::enter(functions, id)?;
+    let stack_len = stack.len();
+    let result = backend
+        .solve_with_calls(
+            &mut callee_witness,
+            callee.opcodes.clone(),
+            functions,
+            stack,
+        )
+        .and_then(|()| {
+            for (return_value, output) in return_values.iter().zip(outputs) {
+                let value = *witness_to_value(&callee_witness, *return_value)?;
+                match initial_witness.insert(*output, value) {
+                    Some(existing) if existing != value => {
+                        return Err(OpcodeResolutionError::UnsatisfiedConstrain)
+                    }
+                    _ => {}
+                }
+            }
+            Ok(())
+        });
+
+    match result {
+        Ok(()) => {
+            stack.push(id, callee_witness);
+            Ok(())
+        }
+        Err(err) => {
+            // The call may be retried once more witnesses are known, so the
+            // witnesses of any calls which the callee made are discarded
+            stack.truncate(stack_len);
+            Err(err)
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use acir::{
+    circuit::{Circuit, Opcode},
+    AcirField,
+};
+
+use crate::{
+    pwg::{get_value, with_opcode_context, witness_to_value},
+    OpcodeResolutionError, PartialWitnessGenerator, WitnessMap,
+};
+
+/// Checks that a complete witness satisfies the constraints of a circuit, without solving it.
+///
+/// Arithmetic opcodes are evaluated against the witness. Black box functions are solved by
+/// `backend` from the values of their inputs, and their outputs compared with the witness.
+/// Directives only compute hints, so the values they would assign are not checked: a valid
+/// witness may hold other values, such as any inverse of zero in an is-zero gadget.
+/// Calls cannot be checked, as the callee is not part of the circuit.
+///
+/// Returns `UnsatisfiedConstrain` if a constraint does not hold, and `OpcodeNotSolvable` if
+/// a witness which a constraint uses is not assigned.
+pub fn check_satisfied<F: AcirField, B: PartialWitnessGenerator<F> + ?Sized>(
+    backend: &B,
+    circuit: &Circuit<F>,
+    witness: &WitnessMap<F>,
+) -> Result<(), OpcodeResolutionError<F>> {
+    for (opcode_index, opcode) in circuit.opcodes.iter().enumerate() {
+        check_opcode(backend, opcode, witness)
+            .map_err(|err| with_opcode_context(err, opcode_index, opcode))?;
+    }
+    Ok(())
+}
+
+fn check_opcode<F: AcirField, B: PartialWitnessGenerator<F> + ?Sized>(
+    backend: &B,
+    opcode: &Opcode<F>,
+    witness: &WitnessMap<F>,
+) -> Result<(), OpcodeResolutionError<F>> {
+    match opcode {
+        Opcode::Arithmetic(expr) => {
+            if !get_value(expr, witness)?.is_zero() {
+                return Err(OpcodeResolutionError::UnsatisfiedConstrain);
+            }
+        }
+        Opcode::BlackBoxFuncCall(func_call) => {
+            // Solved from the inputs alone, so that the outputs are computed rather than kept
+            let mut solved = WitnessMap::new();
+            for input in &func_call.inputs {
+                solved.insert(input.witness, *witness_to_value(witness, input.witness)?);
+            }
+            backend.solve_blackbox_function_call(&mut solved, func_call)?;
+            for output in &func_call.outputs {
+                if witness_to_value(witness, *output)? != witness_to_value(&solved, *output)? {
+                    return Err(OpcodeResolutionError::UnsatisfiedConstrain);
+                }
+            }
+        }
+        Opcode::Directive(_) => {}
+        Opcode::Call { id, .. } => return Err(OpcodeResolutionError::UnknownFunction(*id)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pwg::NativeSolver;
+    use acir::{
+        circuit::{
+            directives::Directive,
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            PublicInputs,
+        },
+        native_types::{Expression, Witness},
+        BlackBoxFunc, FieldElement,
+    };
+
+    // _3 = (_1 == 0), with _2 the inverse of _1 as a hint, and _4 = _5 AND _6 where _5 = _1
+    fn is_zero_circuit() -> Circuit {
+        let x = Expression::from(&Witness(1));
+        let mut is_zero = &Expression::one() - &Witness(3);
+        is_zero.term_multiplication(-FieldElement::one(), Witness(1), Witness(2));
+        let mut x_is_zero = Expression::default();
+        x_is_zero.term_multiplication(FieldElement::one(), Witness(1), Witness(3));
+        Circuit {
+            current_witness_index: 6,
+            opcodes: vec![
+                Opcode::Directive(Directive::Invert {
+                    x: Witness(1),
+                    result: Witness(2),
+                }),
+                Opcode::Arithmetic(is_zero),
+                Opcode::Arithmetic(x_is_zero),
+                Opcode::Arithmetic(&x - &Witness(5)),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::AND,
+                    inputs: vec![
+                        FunctionInput {
+                            witness: Witness(5),
+                            num_bits: 8,
+                        },
+                        FunctionInput {
+                            witness: Witness(6),
+                            num_bits: 8,
+                        },
+                    ],
+                    outputs: vec![Witness(4)],
+                }),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1), Witness(6)]),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    fn witness(values: [i128; 6]) -> WitnessMap {
+        (1..)
+            .map(Witness)
+            .zip(values.map(FieldElement::from))
+            .collect()
+    }
+
+    #[test]
+    fn constraints_are_checked_rather_than_hints() {
+        let circuit = is_zero_circuit();
+        let check = |values| check_satisfied(&NativeSolver, &circuit, &witness(values));
+
+        assert_eq!(check([0, 0, 1, 0, 0, 0xf]), Ok(()));
+        // The solver would assign the inverse of zero as zero, but any value satisfies the circuit
+        assert_eq!(check([0, 5, 1, 0, 0, 0xf]), Ok(()));
+        assert_eq!(
+            check([0, 5, 0, 0, 0, 0xf]),
+            Err(OpcodeResolutionError::UnsatisfiedConstrain)
+        );
+
+        // The outputs of black box functions are computed from their inputs
+        let inverse = FieldElement::from(0x3c_i128).inverse();
+        let mut valid = witness([0x3c, 0, 0, 0xc, 0x3c, 0xf]);
+        valid.insert(Witness(2), inverse);
+        assert_eq!(check_satisfied(&NativeSolver, &circuit, &valid), Ok(()));
+        valid.insert(Witness(4), FieldElement::from(0xd_i128));
+        assert_eq!(
+            check_satisfied(&NativeSolver, &circuit, &valid),
+            Err(OpcodeResolutionError::UnsatisfiedConstrain)
+        );
+
+        // The witness is not solved, so it must be complete
+        valid.remove(&Witness(4));
+        assert!(matches!(
+            check_satisfied(&NativeSolver, &circuit, &valid),
+            Err(OpcodeResolutionError::OpcodeNotSolvable(_))
+        ));
+    }
+}
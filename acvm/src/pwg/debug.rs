@@ -0,0 +1,298 @@
+use alloc::collections::BTreeSet;
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+
+use acir::{
+    circuit::{Circuit, Opcode},
+    native_types::{Witness, WitnessStack},
+    AcirField, FieldElement,
+};
+
+use crate::{prepare_witness, OpcodeResolutionError, PartialWitnessGenerator, WitnessMap};
+
+use super::{missing_witnesses, with_opcode_context, OpcodeQueue};
+
+/// A point at which `DebugSolver::resume` pauses
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Breakpoint {
+    /// Before the opcode at this index is attempted
+    Opcode(usize),
+    /// Before each opcode of this kind is attempted, named as in `Opcode::name`, such
+    /// as `arithmetic`, `to_radix` or `sha256`
+    Kind(String),
+    /// After this witness is assigned
+    Witness(Witness),
+}
+
+/// The result of attempting one opcode
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// The opcode was solved, assigning these witnesses
+    Solved {
+        opcode_index: usize,
+        assigned: Vec<Witness>,
+    },
+    /// The opcode is missing inputs, so it waits until one of them is assigned
+    Stalled { opcode_index: usize },
+    /// Every opcode has been solved
+    Finished,
+}
+
+/// The reason `DebugSolver::resume` returned
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Pause {
+    /// A breakpoint was reached. For opcode and kind breakpoints, `opcode_index` is the
+    /// opcode which will be attempted next. For witness breakpoints, it is the opcode
+    /// which assigned the witness.
+    Breakpoint {
+        breakpoint: Breakpoint,
+        opcode_index: usize,
+    },
+    /// Every opcode has been solved
+    Finished,
+}
+
+/// Solves a circuit one opcode at a time, pausing at breakpoints so that the witness
+/// can be inspected.
+///
+/// Opcodes are attempted in the same order as `PartialWitnessGenerator::solve` attempts
+/// them. Calls are not supported, as the circuit is solved without the rest of its program.
+pub struct DebugSolver<'a, B: ?Sized, F: AcirField = FieldElement> {
+    backend: &'a B,
+    circuit: &'a Circuit<F>,
+    witness: WitnessMap<F>,
+    queue: OpcodeQueue<F>,
+    breakpoints: BTreeSet<Breakpoint>,
+    // The opcode which execution paused before. It is attempted without checking the
+    // breakpoints again when execution resumes.
+    paused_before: Option<usize>,
+}
+
+impl<'a, F: AcirField, B: PartialWitnessGenerator<F> + ?Sized> DebugSolver<'a, B, F> {
+    /// Fails if the initial witness assigns witnesses which are not in the circuit
+    pub fn new(
+        backend: &'a B,
+        circuit: &'a Circuit<F>,
+        mut initial_witness: WitnessMap<F>,
+    ) -> Result<Self, OpcodeResolutionError<F>> {
+        prepare_witness(&mut initial_witness, circuit)?;
+        Ok(DebugSolver {
+            backend,
+            circuit,
+            witness: initial_witness,
+            queue: OpcodeQueue::new(circuit.opcodes.len(), 0..circuit.opcodes.len()),
+            breakpoints: BTreeSet::new(),
+            paused_before: None,
+        })
+    }
+
+    /// Returns the witnesses which have been assigned so far
+    pub fn witness(&self) -> &WitnessMap<F> {
+        &self.witness
+    }
+
+    pub fn into_witness(self) -> WitnessMap<F> {
+        self.witness
+    }
+
+    /// Adds a breakpoint, returning false if it was already set
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> bool {
+        self.breakpoints.insert(breakpoint)
+    }
+
+    /// Removes a breakpoint, returning false if it was not set
+    pub fn remove_breakpoint(&mut self, breakpoint: &Breakpoint) -> bool {
+        self.breakpoints.remove(breakpoint)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.breakpoints.iter()
+    }
+
+    /// Returns the index of the opcode which will be attempted next, along with the opcode
+    pub fn next_opcode(&self) -> Option<(usize, &'a Opcode<F>)> {
+        let index = self.queue.peek()?;
+        Some((index, &self.circuit.opcodes[index]))
+    }
+
+    /// Attempts the next opcode, ignoring breakpoints
+    pub fn step(&mut self) -> Result<Step, OpcodeResolutionError<F>> {
+        let Some(index) = self.queue.pop() else {
+            return self.queue.finish().map(|()| Step::Finished);
+        };
+        self.paused_before = None;
+
+        let opcode = &self.circuit.opcodes[index];
+        let missing = missing_witnesses(opcode, &self.witness);
+        let result =
+            self.backend
+                .solve_opcode(&mut self.witness, opcode, &[], &mut WitnessStack::default());
+        match result {
+            Ok(()) => {
+                self.queue.solved(index, opcode, &self.witness);
+                let assigned = missing
+                    .into_iter()
+                    .filter(|witness| self.witness.contains_key(witness))
+                    .collect();
+                Ok(Step::Solved {
+                    opcode_index: index,
+                    assigned,
+                })
+            }
+            Err(OpcodeResolutionError::OpcodeNotSolvable(err)) => {
+                self.queue.stalled(index, opcode, &self.witness, err);
+                Ok(Step::Stalled {
+                    opcode_index: index,
+                })
+            }
+            Err(err) => Err(with_opcode_context(err, index, opcode)),
+        }
+    }
+
+    /// Attempts opcodes until a breakpoint is reached or the circuit is solved
+    pub fn resume(&mut self) -> Result<Pause, OpcodeResolutionError<F>> {
+        loop {
+            if let Some((index, opcode)) = self.next_opcode() {
+                if self.paused_before != Some(index) {
+                    if let Some(breakpoint) = self.opcode_breakpoint(index, opcode) {
+                        self.paused_before = Some(index);
+                        return Ok(Pause::Breakpoint {
+                            breakpoint,
+                            opcode_index: index,
+                        });
+                    }
+                }
+            }
+
+            match self.step()? {
+                Step::Solved {
+                    opcode_index,
+                    assigned,
+                } => {
+                    let watched = assigned
+                        .into_iter()
+                        .map(Breakpoint::Witness)
+                        .find(|breakpoint| self.breakpoints.contains(breakpoint));
+                    if let Some(breakpoint) = watched {
+                        return Ok(Pause::Breakpoint {
+                            breakpoint,
+                            opcode_index,
+                        });
+                    }
+                }
+                Step::Stalled { .. } => {}
+                Step::Finished => return Ok(Pause::Finished),
+            }
+        }
+    }
+
+    // Returns the breakpoint which pauses execution before the opcode, if there is one
+    fn opcode_breakpoint(&self, index: usize, opcode: &Opcode<F>) -> Option<Breakpoint> {
+        [
+            Breakpoint::Opcode(index),
+            Breakpoint::Kind(opcode.name().to_owned()),
+        ]
+        .into_iter()
+        .find(|breakpoint| self.breakpoints.contains(breakpoint))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        pwg::NativeSolver,
+        testing::{generate_circuit, CircuitSpec},
+    };
+
+    // Four gates assigning _3 to _6, a range check on _7 and two SHA256 gadgets
+    fn circuit() -> (Circuit, WitnessMap) {
+        generate_circuit(&CircuitSpec {
+            num_arithmetic: 4,
+            num_range_checks: 1,
+            num_sha256: 2,
+            ..CircuitSpec::default()
+        })
+    }
+
+    #[test]
+    fn stepping_solves_one_opcode_at_a_time() {
+        let (circuit, inputs) = circuit();
+        let mut debugger = DebugSolver::new(&NativeSolver, &circuit, inputs.clone()).unwrap();
+
+        assert_eq!(debugger.next_opcode(), Some((0, &circuit.opcodes[0])));
+        assert_eq!(
+            debugger.step(),
+            Ok(Step::Solved {
+                opcode_index: 0,
+                assigned: vec![Witness(3)]
+            })
+        );
+        assert!(debugger.witness().contains_key(&Witness(3)));
+        assert!(!debugger.witness().contains_key(&Witness(4)));
+
+        let mut num_steps = 1;
+        while debugger.step() != Ok(Step::Finished) {
+            num_steps += 1;
+        }
+        assert_eq!(num_steps, circuit.opcodes.len());
+
+        let mut expected = inputs.clone();
+        NativeSolver
+            .solve(&mut expected, circuit.opcodes.clone())
+            .unwrap();
+        assert_eq!(debugger.into_witness(), expected);
+
+        // Witnesses outside of the circuit are rejected
+        let mut out_of_bounds = inputs;
+        out_of_bounds.insert(
+            Witness(circuit.current_witness_index + 1),
+            acir::FieldElement::one(),
+        );
+        assert!(matches!(
+            DebugSolver::new(&NativeSolver, &circuit, out_of_bounds),
+            Err(OpcodeResolutionError::InvalidInitialWitness(_))
+        ));
+    }
+
+    #[test]
+    fn execution_pauses_at_breakpoints() {
+        let (circuit, inputs) = circuit();
+        let mut debugger = DebugSolver::new(&NativeSolver, &circuit, inputs).unwrap();
+        assert!(debugger.add_breakpoint(Breakpoint::Opcode(2)));
+        assert!(debugger.add_breakpoint(Breakpoint::Kind("sha256".to_owned())));
+        assert!(debugger.add_breakpoint(Breakpoint::Witness(Witness(5))));
+        assert!(!debugger.add_breakpoint(Breakpoint::Opcode(2)));
+
+        // Before opcode 2, and then after it assigns _5
+        assert_eq!(
+            debugger.resume(),
+            Ok(Pause::Breakpoint {
+                breakpoint: Breakpoint::Opcode(2),
+                opcode_index: 2
+            })
+        );
+        assert!(!debugger.witness().contains_key(&Witness(5)));
+        assert_eq!(
+            debugger.resume(),
+            Ok(Pause::Breakpoint {
+                breakpoint: Breakpoint::Witness(Witness(5)),
+                opcode_index: 2
+            })
+        );
+        assert!(debugger.witness().contains_key(&Witness(5)));
+
+        // Before each SHA256 gadget
+        for opcode_index in [5, 6] {
+            assert_eq!(
+                debugger.resume(),
+                Ok(Pause::Breakpoint {
+                    breakpoint: Breakpoint::Kind("sha256".to_owned()),
+                    opcode_index
+                })
+            );
+        }
+        assert!(debugger.remove_breakpoint(&Breakpoint::Kind("sha256".to_owned())));
+        assert_eq!(debugger.resume(), Ok(Pause::Finished));
+        assert_eq!(debugger.resume(), Ok(Pause::Finished));
+    }
+}
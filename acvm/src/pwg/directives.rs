@@ -1,17 +1,16 @@
-use std::collections::BTreeMap;
-
-use acir::{circuit::directives::Directive, native_types::Witness, FieldElement};
+use acir::{circuit::directives::Directive, AcirField};
+use alloc::vec::Vec;
 use num_bigint::BigUint;
-use num_traits::{One, Zero};
+use num_traits::One;
 
-use crate::OpcodeResolutionError;
+use crate::{OpcodeResolutionError, WitnessMap};
 
 use super::{get_value, witness_to_value};
 
-pub fn solve_directives(
-    initial_witness: &mut BTreeMap<Witness, FieldElement>,
-    directive: &Directive,
-) -> Result<(), OpcodeResolutionError> {
+pub fn solve_directives<F: AcirField>(
+    initial_witness: &mut WitnessMap<F>,
+    directive: &Directive<F>,
+) -> Result<(), OpcodeResolutionError<F>> {
     match directive {
         Directive::Invert { x, result } => {
             let val = witness_to_value(initial_witness, *x)?;
@@ -29,42 +28,74 @@ pub fn solve_directives(
             let val_a = get_value(a, initial_witness)?;
             let val_b = get_value(b, initial_witness)?;
 
-            let int_a = BigUint::from_bytes_be(&val_a.to_be_bytes());
-            let int_b = BigUint::from_bytes_be(&val_b.to_be_bytes());
-
             // If the predicate is `None`, then we simply return the value 1
             // If the predicate is `Some` but we cannot find a value, then we return unresolved
             let pred_value = match predicate {
                 Some(pred) => get_value(pred, initial_witness)?,
-                None => FieldElement::one(),
+                None => F::one(),
             };
 
-            let (int_r, int_q) = if pred_value.is_zero() {
-                (BigUint::zero(), BigUint::zero())
+            let (val_r, val_q) = if pred_value.is_zero() {
+                (F::zero(), F::zero())
+            } else if val_b.is_zero() {
+                // There is no remainder which is less than zero
+                return Err(OpcodeResolutionError::UnsatisfiedConstrain);
+            } else if let (Some(int_a), Some(int_b)) =
+                (val_a.try_into_u128(), val_b.try_into_u128())
+            {
+                (F::from(int_a % int_b), F::from(int_a / int_b))
             } else {
-                (&int_a % &int_b, &int_a / &int_b)
+                let int_a = BigUint::from_bytes_be(&val_a.to_be_bytes());
+                let int_b = BigUint::from_bytes_be(&val_b.to_be_bytes());
+                (
+                    F::from_be_bytes_reduce(&(&int_a % &int_b).to_bytes_be()),
+                    F::from_be_bytes_reduce(&(&int_a / &int_b).to_bytes_be()),
+                )
             };
 
-            initial_witness.insert(*q, FieldElement::from_be_bytes_reduce(&int_q.to_bytes_be()));
-            initial_witness.insert(*r, FieldElement::from_be_bytes_reduce(&int_r.to_bytes_be()));
+            initial_witness.insert(*q, val_q);
+            initial_witness.insert(*r, val_r);
 
             Ok(())
         }
         Directive::Truncate { a, b, c, bit_size } => {
             let val_a = get_value(a, initial_witness)?;
 
-            let pow: BigUint = BigUint::one() << bit_size;
+            let (val_b, val_c) = if let Some(int_a) = val_a.try_into_u128() {
+                if *bit_size >= 128 {
+                    (val_a, F::zero())
+                } else {
+                    (
+                        F::from(low_bits(int_a, *bit_size)),
+                        F::from(int_a >> bit_size),
+                    )
+                }
+            } else if *bit_size <= 128 {
+                // `a - b` is a multiple of 2^bit_size which is less than the modulus,
+                // so dividing in the field gives the same result as dividing integers
+                let val_b = F::from(low_bits(val_a.to_u128(), *bit_size));
+                (val_b, (val_a - val_b) / pow_of_two(*bit_size))
+            } else {
+                let pow: BigUint = BigUint::one() << bit_size;
 
-            let int_a = BigUint::from_bytes_be(&val_a.to_be_bytes());
-            let int_b: BigUint = &int_a % &pow;
-            let int_c: BigUint = (&int_a - &int_b) / &pow;
+                let int_a = BigUint::from_bytes_be(&val_a.to_be_bytes());
+                let int_b: BigUint = &int_a % &pow;
+                let int_c: BigUint = (&int_a - &int_b) / &pow;
+                (
+                    F::from_be_bytes_reduce(&int_b.to_bytes_be()),
+                    F::from_be_bytes_reduce(&int_c.to_bytes_be()),
+                )
+            };
 
-            initial_witness.insert(*b, FieldElement::from_be_bytes_reduce(&int_b.to_bytes_be()));
-            initial_witness.insert(*c, FieldElement::from_be_bytes_reduce(&int_c.to_bytes_be()));
+            initial_witness.insert(*b, val_b);
+            initial_witness.insert(*c, val_c);
 
             Ok(())
         }
         Directive::ToRadix { a, b, radix } => {
+            if !(2..=256).contains(radix) {
+                return Err(OpcodeResolutionError::InvalidRadix(*radix));
+            }
             let val_a = get_value(a, initial_witness)?;
 
             let a_big = BigUint::from_bytes_be(&val_a.to_be_bytes());
@@ -74,16 +105,16 @@ pub fn solve_directives(
             }
             for i in 0..b.len() {
                 let v = if i < a_dec.len() {
-                    FieldElement::from_be_bytes_reduce(&[a_dec[i]])
+                    F::from_be_bytes_reduce(&[a_dec[i]])
                 } else {
-                    FieldElement::zero()
+                    F::zero()
                 };
-                match initial_witness.entry(b[i]) {
-                    std::collections::btree_map::Entry::Vacant(e) => {
-                        e.insert(v);
+                match initial_witness.get(&b[i]) {
+                    None => {
+                        initial_witness.insert(b[i], v);
                     }
-                    std::collections::btree_map::Entry::Occupied(e) => {
-                        if e.get() != &v {
+                    Some(value) => {
+                        if value != &v {
                             return Err(OpcodeResolutionError::UnsatisfiedConstrain);
                         }
                     }
@@ -94,6 +125,24 @@ pub fn solve_directives(
         }
         Directive::OddRange { a, b, r, bit_size } => {
             let val_a = witness_to_value(initial_witness, *a)?;
+            // Without any bits, there is no highest bit
+            if *bit_size == 0 {
+                return Err(OpcodeResolutionError::UnsatisfiedConstrain);
+            }
+
+            if (1..=128).contains(bit_size) {
+                // `a` must be less than 2^bit_size, so it must fit in a u128
+                let int_a = match val_a.try_into_u128() {
+                    Some(int_a) if *bit_size == 128 || int_a >> bit_size == 0 => int_a,
+                    _ => return Err(OpcodeResolutionError::UnsatisfiedConstrain),
+                };
+                let int_b = int_a >> (bit_size - 1);
+                let int_r = int_a - (int_b << (bit_size - 1));
+
+                initial_witness.insert(*b, F::from(int_b));
+                initial_witness.insert(*r, F::from(int_r));
+                return Ok(());
+            }
 
             let int_a = BigUint::from_bytes_be(&val_a.to_be_bytes());
             let pow: BigUint = BigUint::one() << (bit_size - 1);
@@ -105,10 +154,203 @@ pub fn solve_directives(
             let int_r = &int_a - &bb;
             let int_b = &bb >> (bit_size - 1);
 
-            initial_witness.insert(*b, FieldElement::from_be_bytes_reduce(&int_b.to_bytes_be()));
-            initial_witness.insert(*r, FieldElement::from_be_bytes_reduce(&int_r.to_bytes_be()));
+            initial_witness.insert(*b, F::from_be_bytes_reduce(&int_b.to_bytes_be()));
+            initial_witness.insert(*r, F::from_be_bytes_reduce(&int_r.to_bytes_be()));
 
             Ok(())
         }
+        // Oracles are answered by the backend, in `PartialWitnessGenerator::solve_directives`
+        Directive::Oracle { name, .. } => {
+            Err(OpcodeResolutionError::UnsupportedOracle(name.clone()))
+        }
+    }
+}
+
+// Returns the lowest `bit_size` bits of `value`
+fn low_bits(value: u128, bit_size: u32) -> u128 {
+    if bit_size >= 128 {
+        value
+    } else {
+        value & ((1 << bit_size) - 1)
+    }
+}
+
+// Returns 2^exponent, for exponents up to 128
+fn pow_of_two<F: AcirField>(exponent: u32) -> F {
+    if exponent < 128 {
+        F::from(1_u128 << exponent)
+    } else {
+        F::from(u128::MAX) + F::one()
+    }
+}
+
+// Replaces each value with its inverse, or zero if it is zero, as `Directive::Invert` does.
+//
+// Montgomery's trick inverts all of the values with a single inversion: the running
+// products of the values are inverted once, and each inverse is peeled off that with two
+// multiplications, for three multiplications per value.
+pub(crate) fn batch_inverse<F: AcirField>(values: &mut [F]) {
+    let mut products = Vec::with_capacity(values.len());
+    let mut product = F::one();
+    for value in values.iter().filter(|value| !value.is_zero()) {
+        products.push(product);
+        product = product * *value;
+    }
+
+    // The inverse of the product of the values before the current one
+    let mut inverse = product.inverse();
+    for value in values.iter_mut().rev().filter(|value| !value.is_zero()) {
+        let product = products
+            .pop()
+            .expect("there is a product for each non-zero value");
+        let value_inverse = inverse * product;
+        inverse = inverse * *value;
+        *value = value_inverse;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::native_types::{Expression, Witness};
+    use acir::FieldElement;
+
+    fn to_big(value: FieldElement) -> BigUint {
+        BigUint::from_bytes_be(&value.to_be_bytes())
+    }
+
+    fn values() -> Vec<FieldElement> {
+        vec![
+            FieldElement::zero(),
+            FieldElement::from(1_u128),
+            FieldElement::from(0xdead_beef_u128),
+            FieldElement::from(u64::MAX as u128 + 7),
+            FieldElement::from(u128::MAX),
+            FieldElement::from(u128::MAX) + FieldElement::one(),
+            -FieldElement::one(),
+        ]
+    }
+
+    fn solve(directive: Directive, a: FieldElement, b: FieldElement) -> WitnessMap {
+        let mut witness = WitnessMap::from([(Witness(1), a), (Witness(2), b)]);
+        solve_directives(&mut witness, &directive).unwrap();
+        witness
+    }
+
+    #[test]
+    fn batch_inverse_matches_inverse() {
+        let mut inverses = values();
+        batch_inverse(&mut inverses);
+        let expected: Vec<FieldElement> = values().iter().map(FieldElement::inverse).collect();
+        assert_eq!(inverses, expected);
+
+        let mut zeros = vec![FieldElement::zero(); 2];
+        batch_inverse(&mut zeros);
+        assert_eq!(zeros, vec![FieldElement::zero(); 2]);
+        batch_inverse::<FieldElement>(&mut []);
+    }
+
+    #[test]
+    fn quotient_matches_integer_division() {
+        let directive = Directive::Quotient {
+            a: Expression::from(&Witness(1)),
+            b: Expression::from(&Witness(2)),
+            q: Witness(3),
+            r: Witness(4),
+            predicate: None,
+        };
+        for a in values() {
+            for b in values().into_iter().filter(|b| !b.is_zero()) {
+                let witness = solve(directive.clone(), a, b);
+                assert_eq!(to_big(witness[&Witness(3)]), to_big(a) / to_big(b));
+                assert_eq!(to_big(witness[&Witness(4)]), to_big(a) % to_big(b));
+            }
+        }
+    }
+
+    #[test]
+    fn truncate_matches_integer_arithmetic() {
+        for bit_size in [1, 8, 64, 127, 128, 129, 200] {
+            let directive = Directive::Truncate {
+                a: Expression::from(&Witness(1)),
+                b: Witness(3),
+                c: Witness(4),
+                bit_size,
+            };
+            for a in values() {
+                let witness = solve(directive.clone(), a, FieldElement::zero());
+                let pow = BigUint::one() << bit_size;
+                assert_eq!(to_big(witness[&Witness(3)]), to_big(a) % &pow);
+                assert_eq!(to_big(witness[&Witness(4)]), to_big(a) / &pow);
+            }
+        }
+    }
+
+    #[test]
+    fn odd_range_matches_integer_arithmetic() {
+        for bit_size in [1, 8, 64, 128] {
+            let directive = Directive::OddRange {
+                a: Witness(1),
+                b: Witness(3),
+                r: Witness(4),
+                bit_size,
+            };
+            for a in values() {
+                let mut witness = WitnessMap::from([(Witness(1), a)]);
+                let result = solve_directives(&mut witness, &directive);
+
+                let int_a = to_big(a);
+                if int_a >= BigUint::one() << bit_size {
+                    assert_eq!(result, Err(OpcodeResolutionError::UnsatisfiedConstrain));
+                    continue;
+                }
+                let pow = BigUint::one() << (bit_size - 1);
+                assert_eq!(to_big(witness[&Witness(3)]), &int_a / &pow);
+                assert_eq!(to_big(witness[&Witness(4)]), &int_a % &pow);
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_arguments_are_rejected() {
+        let quotient = Directive::Quotient {
+            a: Expression::from(&Witness(1)),
+            b: Expression::from(&Witness(2)),
+            q: Witness(3),
+            r: Witness(4),
+            predicate: None,
+        };
+        let mut witness = WitnessMap::from([
+            (Witness(1), FieldElement::one()),
+            (Witness(2), FieldElement::zero()),
+        ]);
+        assert_eq!(
+            solve_directives(&mut witness, &quotient),
+            Err(OpcodeResolutionError::UnsatisfiedConstrain)
+        );
+
+        for radix in [0, 1, 257] {
+            let to_radix = Directive::ToRadix {
+                a: Expression::from(&Witness(1)),
+                b: vec![Witness(3)],
+                radix,
+            };
+            assert_eq!(
+                solve_directives(&mut witness, &to_radix),
+                Err(OpcodeResolutionError::InvalidRadix(radix))
+            );
+        }
+
+        let odd_range = Directive::OddRange {
+            a: Witness(1),
+            b: Witness(3),
+            r: Witness(4),
+            bit_size: 0,
+        };
+        assert_eq!(
+            solve_directives(&mut witness, &odd_range),
+            Err(OpcodeResolutionError::UnsatisfiedConstrain)
+        );
+        assert_eq!(witness.len(), 2);
     }
 }
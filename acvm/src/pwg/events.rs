@@ -0,0 +1,256 @@
+use std::sync::mpsc::Sender;
+
+use acir::{
+    circuit::{opcodes::BlackBoxFuncCall, Circuit, Opcode, Program},
+    native_types::{Witness, WitnessStack},
+    AcirField, FieldElement,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{prepare_witness, OpcodeResolutionError, PartialWitnessGenerator, WitnessMap};
+
+use super::{
+    missing_witnesses, oracle::solve_opcode_with_oracles, with_opcode_context, OpcodeQueue,
+};
+
+/// Something which happened while solving, as reported by `solve_with_events`.
+///
+/// Opcode indices are those of the entry point of the program. The opcodes of called
+/// functions are not reported individually, but oracles which they call are.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event")]
+pub enum SolverEvent<F = FieldElement> {
+    /// An arithmetic gate, black box function call or call was solved, assigning these
+    /// witnesses
+    GateSolved {
+        opcode_index: usize,
+        assigned: Vec<(Witness, F)>,
+    },
+    /// A directive was executed, assigning these witnesses
+    DirectiveExecuted {
+        opcode_index: usize,
+        name: String,
+        assigned: Vec<(Witness, F)>,
+    },
+    /// An oracle was asked for the values of its outputs. Oracle directives are also
+    /// reported as executed once they are answered.
+    OracleRequested { name: String, inputs: Vec<F> },
+    /// An opcode failed, which stops solving. Opcodes which are left unsolved as they
+    /// are missing inputs are not reported.
+    ConstraintFailed { opcode_index: usize, error: String },
+}
+
+/// Solves a program as `PartialWitnessGenerator::solve_program` does, sending an event to
+/// `events` for each opcode which is solved or fails and each oracle which is called.
+///
+/// Solving continues if the receiver is dropped, and its result is the same as that of
+/// `solve_program`.
+pub fn solve_with_events<F: AcirField, B: PartialWitnessGenerator<F> + ?Sized>(
+    backend: &B,
+    initial_witness: &mut WitnessMap<F>,
+    program: &Program<F>,
+    events: &Sender<SolverEvent<F>>,
+) -> Result<(), OpcodeResolutionError<F>> {
+    prepare_witness(initial_witness, program.main())?;
+    let emitter = Emitter { backend, events };
+    let opcodes = &program.main().opcodes;
+    let mut stack = WitnessStack::new();
+    let mut queue = OpcodeQueue::new(opcodes.len(), 0..opcodes.len());
+
+    while let Some(index) = queue.pop() {
+        let opcode = &opcodes[index];
+        let missing = missing_witnesses(opcode, initial_witness);
+        match emitter.solve_opcode(initial_witness, opcode, &program.functions, &mut stack) {
+            Ok(()) => {
+                queue.solved(index, opcode, initial_witness);
+                let assigned = missing
+                    .into_iter()
+                    .filter_map(|witness| Some((witness, *initial_witness.get(&witness)?)))
+                    .collect();
+                emitter.emit(match opcode {
+                    Opcode::Directive(directive) => SolverEvent::DirectiveExecuted {
+                        opcode_index: index,
+                        name: directive.name().to_owned(),
+                        assigned,
+                    },
+                    _ => SolverEvent::GateSolved {
+                        opcode_index: index,
+                        assigned,
+                    },
+                });
+            }
+            Err(OpcodeResolutionError::OpcodeNotSolvable(err)) => {
+                queue.stalled(index, opcode, initial_witness, err)
+            }
+            Err(err) => {
+                let err = with_opcode_context(err, index, opcode);
+                emitter.emit(SolverEvent::ConstraintFailed {
+                    opcode_index: index,
+                    error: err.to_string(),
+                });
+                return Err(err);
+            }
+        }
+    }
+    queue.finish()
+}
+
+// Wraps the backend so that oracle calls are reported, including those made by callees
+struct Emitter<'a, B: ?Sized, F> {
+    backend: &'a B,
+    events: &'a Sender<SolverEvent<F>>,
+}
+
+impl<B: ?Sized, F> Emitter<'_, B, F> {
+    // The events are only for observers, so a dropped receiver is not an error
+    fn emit(&self, event: SolverEvent<F>) {
+        let _ = self.events.send(event);
+    }
+}
+
+impl<F: AcirField, B: PartialWitnessGenerator<F> + ?Sized> PartialWitnessGenerator<F>
+    for Emitter<'_, B, F>
+{
+    fn solve_opcode(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        opcode: &Opcode<F>,
+        functions: &[Circuit<F>],
+        stack: &mut WitnessStack<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        solve_opcode_with_oracles(
+            self,
+            self.backend,
+            initial_witness,
+            opcode,
+            functions,
+            stack,
+        )
+    }
+
+    fn solve_blackbox_function_call(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        func_call: &BlackBoxFuncCall,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        self.backend
+            .solve_blackbox_function_call(initial_witness, func_call)
+    }
+
+    fn batch_opcodes(&self) -> bool {
+        self.backend.batch_opcodes()
+    }
+
+    fn solve_oracle_call(
+        &self,
+        name: &str,
+        inputs: &[F],
+    ) -> Result<Vec<F>, OpcodeResolutionError<F>> {
+        self.emit(SolverEvent::OracleRequested {
+            name: name.to_owned(),
+            inputs: inputs.to_vec(),
+        });
+        self.backend.solve_oracle_call(name, inputs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::pwg::{
+        oracle::{OracleCall, OracleReplayer, OracleTranscript},
+        NativeSolver,
+    };
+    use acir::{
+        circuit::{directives::Directive, PublicInputs},
+        native_types::Expression,
+    };
+
+    // _2 + 1 = _3, _2 = double(_1), _3 = 5
+    fn program() -> Program {
+        let (x, doubled, y) = (Witness(1), Witness(2), Witness(3));
+        let main = Circuit {
+            current_witness_index: 3,
+            opcodes: vec![
+                // Checked once the oracle has assigned _2 and the last gate _3
+                Opcode::Arithmetic(&(&Expression::from(doubled) + &Expression::one()) - &y),
+                Opcode::Directive(Directive::Oracle {
+                    name: "double".to_owned(),
+                    inputs: vec![x.into()],
+                    outputs: vec![doubled],
+                }),
+                Opcode::Arithmetic(
+                    &Expression::from(y) - &Expression::from_field(FieldElement::from(5_i128)),
+                ),
+            ],
+            public_inputs: PublicInputs(vec![x]),
+            return_values: PublicInputs::default(),
+        };
+        Program {
+            functions: vec![main],
+        }
+    }
+
+    fn solve(x: i128) -> (Result<(), OpcodeResolutionError>, Vec<SolverEvent>) {
+        let x = FieldElement::from(x);
+        let oracle = OracleReplayer::new(
+            NativeSolver,
+            OracleTranscript {
+                calls: vec![OracleCall {
+                    name: "double".to_owned(),
+                    inputs: vec![x],
+                    outputs: vec![x + x],
+                }],
+            },
+        );
+        let (sender, receiver) = mpsc::channel();
+        let mut witness = WitnessMap::from([(Witness(1), x)]);
+        let result = solve_with_events(&oracle, &mut witness, &program(), &sender);
+        (result, receiver.try_iter().collect())
+    }
+
+    #[test]
+    fn events_are_sent_while_solving() {
+        let value = |value: i128| FieldElement::from(value);
+        let (result, events) = solve(2);
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            events,
+            vec![
+                SolverEvent::OracleRequested {
+                    name: "double".to_owned(),
+                    inputs: vec![value(2)],
+                },
+                SolverEvent::DirectiveExecuted {
+                    opcode_index: 1,
+                    name: "oracle".to_owned(),
+                    assigned: vec![(Witness(2), value(4))],
+                },
+                SolverEvent::GateSolved {
+                    opcode_index: 2,
+                    assigned: vec![(Witness(3), value(5))],
+                },
+                SolverEvent::GateSolved {
+                    opcode_index: 0,
+                    assigned: Vec::new(),
+                },
+            ]
+        );
+
+        let (result, events) = solve(3);
+        assert_eq!(result, Err(OpcodeResolutionError::UnsatisfiedConstrain));
+        let failure = SolverEvent::ConstraintFailed {
+            opcode_index: 0,
+            error: OpcodeResolutionError::<FieldElement>::UnsatisfiedConstrain.to_string(),
+        };
+        assert_eq!(events.last(), Some(&failure));
+
+        let bytes = rmp_serde::to_vec_named(&failure).unwrap();
+        assert_eq!(
+            rmp_serde::from_slice::<SolverEvent>(&bytes).unwrap(),
+            failure
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{format, string::String, vec};
+
+use acir::{
+    circuit::{Circuit, Opcode},
+    native_types::Witness,
+    AcirField,
+};
+
+/// How a witness is used by an opcode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Dependency {
+    /// The opcode reads the witness
+    Input,
+    /// The opcode assigns the witness
+    Output,
+    /// The witness is in an arithmetic opcode, which can solve for any one of its witnesses
+    Constraint,
+}
+
+/// A graph of the data dependencies between the witnesses and opcodes of a circuit,
+/// which is displayed in the Graphviz DOT format.
+///
+/// Witnesses are drawn as ellipses, with public inputs filled, and opcodes as boxes.
+/// There is an edge from each witness an opcode reads to the opcode, and from the opcode
+/// to each witness it assigns. Arithmetic opcodes are joined to their witnesses by
+/// undirected edges.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DotGraph {
+    witnesses: BTreeSet<Witness>,
+    public_inputs: BTreeSet<Witness>,
+    // The label of each opcode by its index in the circuit
+    opcodes: BTreeMap<usize, String>,
+    edges: BTreeSet<(usize, Witness, Dependency)>,
+}
+
+/// Returns the dependency graph of every witness and opcode in the circuit
+pub fn dependency_graph<F: AcirField>(circuit: &Circuit<F>) -> DotGraph {
+    let mut graph = DotGraph {
+        public_inputs: circuit.public_inputs.0.iter().copied().collect(),
+        ..DotGraph::default()
+    };
+    graph.witnesses.extend(&graph.public_inputs);
+
+    for (index, opcode) in circuit.opcodes.iter().enumerate() {
+        graph
+            .opcodes
+            .insert(index, format!("{index}: {}", opcode.name()));
+
+        let mut add_edges = |witnesses: &mut dyn Iterator<Item = Witness>, dependency| {
+            for witness in witnesses {
+                graph.witnesses.insert(witness);
+                graph.edges.insert((index, witness, dependency));
+            }
+        };
+        match opcode {
+            Opcode::Arithmetic(expr) => {
+                add_edges(&mut expr.witnesses().into_iter(), Dependency::Constraint)
+            }
+            Opcode::BlackBoxFuncCall(func_call) => {
+                add_edges(
+                    &mut func_call.inputs.iter().map(|input| input.witness),
+                    Dependency::Input,
+                );
+                add_edges(&mut func_call.outputs.iter().copied(), Dependency::Output);
+            }
+            Opcode::Directive(directive) => {
+                add_edges(&mut directive.inputs().into_iter(), Dependency::Input);
+                add_edges(&mut directive.outputs().into_iter(), Dependency::Output);
+            }
+            Opcode::Call {
+                inputs, outputs, ..
+            } => {
+                add_edges(&mut inputs.iter().copied(), Dependency::Input);
+                add_edges(&mut outputs.iter().copied(), Dependency::Output);
+            }
+        }
+    }
+    graph
+}
+
+impl DotGraph {
+    /// Returns the part of the graph which `witness` depends on: the opcodes which can
+    /// assign it, the witnesses which those opcodes read, and so on.
+    ///
+    /// When a witness cannot be solved, the witnesses at the edges of its cone are the
+    /// ones which are missing.
+    pub fn cone(&self, witness: Witness) -> DotGraph {
+        let mut cone = DotGraph {
+            public_inputs: self.public_inputs.clone(),
+            ..DotGraph::default()
+        };
+
+        let mut pending = vec![witness];
+        while let Some(witness) = pending.pop() {
+            if !self.witnesses.contains(&witness) || !cone.witnesses.insert(witness) {
+                continue;
+            }
+
+            // The opcodes which can assign the witness
+            for &(index, _, _) in self
+                .edges
+                .iter()
+                .filter(|(_, w, dependency)| *w == witness && *dependency != Dependency::Input)
+            {
+                if cone.opcodes.contains_key(&index) {
+                    continue;
+                }
+                cone.opcodes.insert(index, self.opcodes[&index].clone());
+                pending.extend(
+                    self.edges
+                        .iter()
+                        .filter(|(i, _, dependency)| {
+                            *i == index && *dependency != Dependency::Output
+                        })
+                        .map(|(_, w, _)| *w),
+                );
+            }
+        }
+
+        cone.public_inputs.retain(|w| cone.witnesses.contains(w));
+        cone.edges = self
+            .edges
+            .iter()
+            .filter(|(index, witness, _)| {
+                cone.opcodes.contains_key(index) && cone.witnesses.contains(witness)
+            })
+            .copied()
+            .collect();
+        cone
+    }
+
+    pub fn num_witnesses(&self) -> usize {
+        self.witnesses.len()
+    }
+
+    pub fn num_opcodes(&self) -> usize {
+        self.opcodes.len()
+    }
+}
+
+impl core::fmt::Display for DotGraph {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "digraph circuit {{")?;
+        for witness in &self.witnesses {
+            let index = witness.witness_index();
+            if self.public_inputs.contains(witness) {
+                writeln!(f, "    w{index} [label=\"_{index}\", style=filled];")?;
+            } else {
+                writeln!(f, "    w{index} [label=\"_{index}\"];")?;
+            }
+        }
+        for (index, label) in &self.opcodes {
+            writeln!(f, "    op{index} [label=\"{label}\", shape=box];")?;
+        }
+        for (index, witness, dependency) in &self.edges {
+            let witness = witness.witness_index();
+            match dependency {
+                Dependency::Input => writeln!(f, "    w{witness} -> op{index};")?,
+                Dependency::Output => writeln!(f, "    op{index} -> w{witness};")?,
+                Dependency::Constraint => writeln!(f, "    w{witness} -> op{index} [dir=none];")?,
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{
+        circuit::{directives::Directive, PublicInputs},
+        native_types::Expression,
+    };
+
+    // _3 = 1 / _1, _2 - _3 = 0, and an unrelated _4 - _5 = 0
+    fn circuit() -> Circuit {
+        Circuit {
+            current_witness_index: 5,
+            opcodes: vec![
+                Opcode::Directive(Directive::Invert {
+                    x: Witness(1),
+                    result: Witness(3),
+                }),
+                Opcode::Arithmetic(&Expression::from(&Witness(2)) - &Witness(3)),
+                Opcode::Arithmetic(&Expression::from(&Witness(4)) - &Witness(5)),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    #[test]
+    fn dot_output() {
+        let graph = dependency_graph(&circuit());
+        assert_eq!(graph.num_witnesses(), 5);
+        assert_eq!(graph.num_opcodes(), 3);
+
+        let dot = graph.to_string();
+        assert!(dot.starts_with("digraph circuit {\n"));
+        assert!(dot.contains("    w1 [label=\"_1\", style=filled];\n"));
+        assert!(dot.contains("    op0 [label=\"0: invert\", shape=box];\n"));
+        assert!(dot.contains("    w1 -> op0;\n"));
+        assert!(dot.contains("    op0 -> w3;\n"));
+        assert!(dot.contains("    w2 -> op1 [dir=none];\n"));
+    }
+
+    #[test]
+    fn cone_only_contains_dependencies() {
+        let graph = dependency_graph(&circuit());
+
+        let cone = graph.cone(Witness(2));
+        assert_eq!(
+            cone.witnesses,
+            BTreeSet::from([Witness(1), Witness(2), Witness(3)])
+        );
+        assert_eq!(cone.opcodes.keys().copied().collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(cone.edges.len(), 4);
+
+        // Public inputs are not assigned by any opcode
+        let cone = graph.cone(Witness(1));
+        assert_eq!(cone.num_witnesses(), 1);
+        assert_eq!(cone.num_opcodes(), 0);
+
+        assert_eq!(graph.cone(Witness(9)), DotGraph::default());
+    }
+}
@@ -1,24 +1,19 @@
-use acir::{circuit::opcodes::BlackBoxFuncCall, native_types::Witness, FieldElement};
+use acir::{circuit::opcodes::BlackBoxFuncCall, AcirField};
 use blake2::{Blake2s, Digest};
 use sha2::Sha256;
-use std::collections::BTreeMap;
 
-pub fn blake2s(
-    initial_witness: &mut BTreeMap<Witness, FieldElement>,
-    gadget_call: &BlackBoxFuncCall,
-) {
-    generic_hash_256::<Blake2s>(initial_witness, gadget_call)
+use crate::WitnessMap;
+
+pub fn blake2s<F: AcirField>(initial_witness: &mut WitnessMap<F>, gadget_call: &BlackBoxFuncCall) {
+    generic_hash_256::<F, Blake2s>(initial_witness, gadget_call)
 }
 
-pub fn sha256(
-    initial_witness: &mut BTreeMap<Witness, FieldElement>,
-    gadget_call: &BlackBoxFuncCall,
-) {
-    generic_hash_256::<Sha256>(initial_witness, gadget_call)
+pub fn sha256<F: AcirField>(initial_witness: &mut WitnessMap<F>, gadget_call: &BlackBoxFuncCall) {
+    generic_hash_256::<F, Sha256>(initial_witness, gadget_call)
 }
 
-fn generic_hash_256<D: Digest>(
-    initial_witness: &mut BTreeMap<Witness, FieldElement>,
+fn generic_hash_256<F: AcirField, D: Digest>(
+    initial_witness: &mut WitnessMap<F>,
     gadget_call: &BlackBoxFuncCall,
 ) {
     let mut hasher = D::new();
@@ -41,7 +36,7 @@ fn generic_hash_256<D: Digest>(
     for i in 0..32 {
         initial_witness.insert(
             gadget_call.outputs[i],
-            FieldElement::from_be_bytes_reduce(&[result[i]]),
+            F::from_be_bytes_reduce(&[result[i]]),
         );
     }
 }
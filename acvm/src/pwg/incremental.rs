@@ -0,0 +1,256 @@
+use alloc::{vec, vec::Vec};
+use core::ops::ControlFlow;
+
+use crate::collections::{HashMap, HashSet};
+
+use acir::{
+    circuit::{Circuit, Opcode},
+    native_types::{Witness, WitnessStack},
+    AcirField, FieldElement,
+};
+
+use crate::{prepare_witness, OpcodeResolutionError, PartialWitnessGenerator, WitnessMap};
+
+use super::{opcode_witnesses, solve_opcodes};
+
+/// A solved witness which can be updated when some of its inputs change, by solving
+/// only the opcodes which depend on them.
+///
+/// While solving, the witnesses which each opcode assigns are recorded. An opcode depends
+/// on a changed input if it reads the input, or reads a witness assigned by an opcode which
+/// depends on it. Only those opcodes are solved again, and the witnesses they assign are
+/// unassigned first.
+///
+/// Calls are not supported, as the circuit is solved without the rest of its program.
+#[derive(Clone, Debug)]
+pub struct IncrementalSolver<F: AcirField = FieldElement> {
+    opcodes: Vec<Opcode<F>>,
+    witness: WitnessMap<F>,
+    // The witnesses which each opcode assigned when it was last solved
+    assignments: Vec<Vec<Witness>>,
+    // The opcodes which read or assign each witness
+    opcodes_by_witness: HashMap<Witness, Vec<usize>>,
+}
+
+impl<F: AcirField> IncrementalSolver<F> {
+    /// Solves a circuit from its initial witness
+    pub fn solve<B: PartialWitnessGenerator<F> + ?Sized>(
+        backend: &B,
+        circuit: &Circuit<F>,
+        mut initial_witness: WitnessMap<F>,
+    ) -> Result<Self, OpcodeResolutionError<F>> {
+        prepare_witness(&mut initial_witness, circuit)?;
+        let mut opcodes_by_witness: HashMap<Witness, Vec<usize>> = HashMap::new();
+        for (index, opcode) in circuit.opcodes.iter().enumerate() {
+            for witness in opcode_witnesses(opcode) {
+                let opcodes = opcodes_by_witness.entry(witness).or_default();
+                if opcodes.last() != Some(&index) {
+                    opcodes.push(index);
+                }
+            }
+        }
+
+        let mut solver = IncrementalSolver {
+            opcodes: circuit.opcodes.clone(),
+            witness: initial_witness,
+            assignments: vec![Vec::new(); circuit.opcodes.len()],
+            opcodes_by_witness,
+        };
+        solver.solve_indices(backend, 0..circuit.opcodes.len())?;
+        Ok(solver)
+    }
+
+    /// Returns the solved witness
+    pub fn witness(&self) -> &WitnessMap<F> {
+        &self.witness
+    }
+
+    pub fn into_witness(self) -> WitnessMap<F> {
+        self.witness
+    }
+
+    /// Changes the value of an input. The witnesses which depend on it are not updated
+    /// until `resolve_incremental` is called.
+    pub fn set(&mut self, witness: Witness, value: F) {
+        self.witness.insert(witness, value);
+    }
+
+    /// Solves the opcodes which depend on the `changed` witnesses again, returning the
+    /// number of opcodes which were solved.
+    ///
+    /// The changed witnesses should be inputs of the circuit, rather than witnesses which
+    /// an opcode assigns. If solving fails the witness is left partially solved, and the
+    /// circuit should be solved again from scratch.
+    pub fn resolve_incremental<B: PartialWitnessGenerator<F> + ?Sized>(
+        &mut self,
+        backend: &B,
+        changed: &[Witness],
+    ) -> Result<usize, OpcodeResolutionError<F>> {
+        let changed: HashSet<Witness> = changed.iter().copied().collect();
+
+        // Follow the opcodes which read each stale witness to the witnesses they assigned
+        let mut dirty = vec![false; self.opcodes.len()];
+        let mut stale: Vec<Witness> = changed.iter().copied().collect();
+        let mut unassigned = HashSet::new();
+        while let Some(witness) = stale.pop() {
+            for &index in self.opcodes_by_witness.get(&witness).into_iter().flatten() {
+                if dirty[index] {
+                    continue;
+                }
+                dirty[index] = true;
+                for &output in &self.assignments[index] {
+                    if !changed.contains(&output) && unassigned.insert(output) {
+                        stale.push(output);
+                    }
+                }
+            }
+        }
+
+        for witness in &unassigned {
+            self.witness.remove(witness);
+        }
+        let indices: Vec<usize> = (0..self.opcodes.len())
+            .filter(|&index| dirty[index])
+            .collect();
+        for &index in &indices {
+            self.assignments[index].clear();
+        }
+
+        let num_solved = indices.len();
+        self.solve_indices(backend, indices)?;
+        Ok(num_solved)
+    }
+
+    fn solve_indices<B: PartialWitnessGenerator<F> + ?Sized>(
+        &mut self,
+        backend: &B,
+        indices: impl IntoIterator<Item = usize>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        let assignments = &mut self.assignments;
+        solve_opcodes(
+            backend,
+            &mut self.witness,
+            &self.opcodes,
+            indices,
+            &[],
+            &mut WitnessStack::default(),
+            Some(&mut |index, assigned, _| {
+                assignments[index] = assigned;
+                ControlFlow::Continue(())
+            }),
+        )
+        .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        pwg::NativeSolver,
+        testing::{generate_circuit, CircuitSpec},
+    };
+    use acir::{
+        circuit::{
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            PublicInputs,
+        },
+        native_types::Expression,
+        BlackBoxFunc,
+    };
+
+    #[test]
+    fn only_dependent_opcodes_are_solved_again() {
+        // _3 = _1 + 1, _4 = sha256(_2), _5 = _3 * _3
+        let circuit = Circuit {
+            current_witness_index: 36,
+            opcodes: vec![
+                Opcode::Arithmetic(
+                    &(&Expression::from(&Witness(1)) - &Witness(3)) + &Expression::one(),
+                ),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::SHA256,
+                    inputs: vec![FunctionInput {
+                        witness: Witness(2),
+                        num_bits: 8,
+                    }],
+                    outputs: (4..36).map(Witness).collect(),
+                }),
+                Opcode::Arithmetic({
+                    let mut expr = Expression::default();
+                    expr.term_multiplication(FieldElement::one(), Witness(3), Witness(3));
+                    expr.term_addition(-FieldElement::one(), Witness(36));
+                    expr
+                }),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1), Witness(2)]),
+            return_values: PublicInputs::default(),
+        };
+        let initial_witness = WitnessMap::from([
+            (Witness(1), FieldElement::from(2_i128)),
+            (Witness(2), FieldElement::from(5_i128)),
+        ]);
+        let mut solver =
+            IncrementalSolver::solve(&NativeSolver, &circuit, initial_witness).unwrap();
+        assert_eq!(solver.witness()[&Witness(36)], FieldElement::from(9_i128));
+
+        solver.set(Witness(1), FieldElement::from(3_i128));
+        assert_eq!(
+            solver.resolve_incremental(&NativeSolver, &[Witness(1)]),
+            Ok(2)
+        );
+        assert_eq!(solver.witness()[&Witness(3)], FieldElement::from(4_i128));
+        assert_eq!(solver.witness()[&Witness(36)], FieldElement::from(16_i128));
+    }
+
+    #[test]
+    fn incremental_solving_matches_full_solving() {
+        let (circuit, mut inputs) = generate_circuit(&CircuitSpec {
+            num_arithmetic: 40,
+            width: 3,
+            num_range_checks: 3,
+            range_bits: 8,
+            num_sha256: 2,
+            seed: 3,
+        });
+        let mut solver = IncrementalSolver::solve(&NativeSolver, &circuit, inputs.clone()).unwrap();
+
+        // The range checks follow the two public inputs and the outputs of the gates. The
+        // first range check input only affects its own range check
+        let range_input = Witness(43);
+        let value = FieldElement::from(17_i128);
+        solver.set(range_input, value);
+        assert_eq!(
+            solver.resolve_incremental(&NativeSolver, &[range_input]),
+            Ok(1)
+        );
+
+        // Every arithmetic gate depends on the last public input
+        solver.set(Witness(2), FieldElement::from(11_i128));
+        assert_eq!(
+            solver.resolve_incremental(&NativeSolver, &[Witness(2)]),
+            Ok(40)
+        );
+
+        inputs.insert(range_input, value);
+        inputs.insert(Witness(2), FieldElement::from(11_i128));
+        let mut expected = inputs;
+        NativeSolver.solve(&mut expected, circuit.opcodes).unwrap();
+        assert_eq!(*solver.witness(), expected);
+    }
+
+    #[test]
+    fn changes_which_break_constraints_are_reported() {
+        let (circuit, inputs) = generate_circuit(&CircuitSpec {
+            num_range_checks: 1,
+            range_bits: 8,
+            ..CircuitSpec::default()
+        });
+        let mut solver = IncrementalSolver::solve(&NativeSolver, &circuit, inputs).unwrap();
+        solver.set(Witness(1), FieldElement::from(256_i128));
+        assert_eq!(
+            solver.resolve_incremental(&NativeSolver, &[Witness(1)]),
+            Err(OpcodeResolutionError::UnsatisfiedConstrain)
+        );
+    }
+}
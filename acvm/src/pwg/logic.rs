@@ -1,12 +1,20 @@
 use super::witness_to_value;
-use crate::OpcodeResolutionError;
-use acir::{circuit::opcodes::BlackBoxFuncCall, native_types::Witness, BlackBoxFunc, FieldElement};
-use std::collections::BTreeMap;
+use crate::{OpcodeResolutionError, WitnessMap};
+use acir::{circuit::opcodes::BlackBoxFuncCall, native_types::Witness, AcirField, BlackBoxFunc};
 
-pub fn solve_logic_opcode(
-    initial_witness: &mut BTreeMap<Witness, FieldElement>,
+pub fn solve_logic_opcode<F: AcirField>(
+    initial_witness: &mut WitnessMap<F>,
     func_call: &BlackBoxFuncCall,
-) -> Result<(), OpcodeResolutionError> {
+) -> Result<(), OpcodeResolutionError<F>> {
+    if let [lhs, rhs, ..] = func_call.inputs.as_slice() {
+        if lhs.num_bits != rhs.num_bits {
+            return Err(OpcodeResolutionError::MismatchedBitSizes(
+                func_call.name,
+                lhs.num_bits,
+                rhs.num_bits,
+            ));
+        }
+    }
     match func_call.name {
         BlackBoxFunc::AND => LogicSolver::solve_and_gate(initial_witness, func_call),
         BlackBoxFunc::XOR => LogicSolver::solve_xor_gate(initial_witness, func_call),
@@ -21,14 +29,14 @@ pub struct LogicSolver;
 
 impl LogicSolver {
     /// Derives the rest of the witness based on the initial low level variables
-    fn solve_logic_gate(
-        initial_witness: &mut BTreeMap<Witness, FieldElement>,
+    fn solve_logic_gate<F: AcirField>(
+        initial_witness: &mut WitnessMap<F>,
         a: &Witness,
         b: &Witness,
         result: Witness,
         num_bits: u32,
         is_xor_gate: bool,
-    ) -> Result<(), OpcodeResolutionError> {
+    ) -> Result<(), OpcodeResolutionError<F>> {
         let w_l_value = witness_to_value(initial_witness, *a)?;
         let w_r_value = witness_to_value(initial_witness, *b)?;
 
@@ -42,17 +50,17 @@ impl LogicSolver {
         Ok(())
     }
 
-    pub fn solve_and_gate(
-        initial_witness: &mut BTreeMap<Witness, FieldElement>,
+    pub fn solve_and_gate<F: AcirField>(
+        initial_witness: &mut WitnessMap<F>,
         gate: &BlackBoxFuncCall,
-    ) -> Result<(), OpcodeResolutionError> {
+    ) -> Result<(), OpcodeResolutionError<F>> {
         let (a, b, result, num_bits) = extract_input_output(gate);
         LogicSolver::solve_logic_gate(initial_witness, &a, &b, result, num_bits, false)
     }
-    pub fn solve_xor_gate(
-        initial_witness: &mut BTreeMap<Witness, FieldElement>,
+    pub fn solve_xor_gate<F: AcirField>(
+        initial_witness: &mut WitnessMap<F>,
         gate: &BlackBoxFuncCall,
-    ) -> Result<(), OpcodeResolutionError> {
+    ) -> Result<(), OpcodeResolutionError<F>> {
         let (a, b, result, num_bits) = extract_input_output(gate);
         LogicSolver::solve_logic_gate(initial_witness, &a, &b, result, num_bits, true)
     }
@@ -74,3 +82,39 @@ pub(crate) fn extract_input_output(gc: &BlackBoxFuncCall) -> (Witness, Witness,
 
     (a.witness, b.witness, *result, num_bits)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{circuit::opcodes::FunctionInput, FieldElement};
+
+    #[test]
+    fn inputs_must_have_the_same_bit_size() {
+        let mut witness = WitnessMap::from([
+            (Witness(1), FieldElement::one()),
+            (Witness(2), FieldElement::one()),
+        ]);
+        let func_call = BlackBoxFuncCall {
+            name: BlackBoxFunc::AND,
+            inputs: vec![
+                FunctionInput {
+                    witness: Witness(1),
+                    num_bits: 8,
+                },
+                FunctionInput {
+                    witness: Witness(2),
+                    num_bits: 16,
+                },
+            ],
+            outputs: vec![Witness(3)],
+        };
+        assert_eq!(
+            solve_logic_opcode(&mut witness, &func_call),
+            Err(OpcodeResolutionError::MismatchedBitSizes(
+                BlackBoxFunc::AND,
+                8,
+                16
+            ))
+        );
+    }
+}
@@ -0,0 +1,437 @@
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+#[cfg(feature = "std")]
+use acir::{
+    circuit::{directives::Directive, opcodes::BlackBoxFuncCall, Circuit, Opcode},
+    native_types::WitnessStack,
+};
+use acir::{
+    native_types::{Expression, Witness},
+    AcirField, FieldElement,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{OpcodeResolutionError, PartialWitnessGenerator, WitnessMap};
+
+use super::get_value;
+
+// Solves an oracle directive by asking `backend` for the values of its outputs
+pub(crate) fn solve_oracle<F: AcirField, B: PartialWitnessGenerator<F> + ?Sized>(
+    backend: &B,
+    initial_witness: &mut WitnessMap<F>,
+    name: &str,
+    inputs: &[Expression<F>],
+    outputs: &[Witness],
+) -> Result<(), OpcodeResolutionError<F>> {
+    let inputs = inputs
+        .iter()
+        .map(|input| get_value(input, initial_witness))
+        .collect::<Result<Vec<_>, _>>()?;
+    let values = backend.solve_oracle_call(name, &inputs)?;
+    if values.len() != outputs.len() {
+        return Err(OpcodeResolutionError::IncorrectNumOracleOutputs(
+            name.to_owned(),
+            outputs.len(),
+            values.len(),
+        ));
+    }
+    for (output, value) in outputs.iter().zip(values) {
+        initial_witness.insert(*output, value);
+    }
+    Ok(())
+}
+
+/// One call to an oracle and its response
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleCall<F = FieldElement> {
+    pub name: String,
+    pub inputs: Vec<F>,
+    pub outputs: Vec<F>,
+}
+
+/// The calls made to oracles while solving, in the order they were made
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleTranscript<F = FieldElement> {
+    pub calls: Vec<OracleCall<F>>,
+}
+
+impl<F> Default for OracleTranscript<F> {
+    fn default() -> Self {
+        OracleTranscript { calls: Vec::new() }
+    }
+}
+
+/// Wraps a backend, recording each oracle call which it answers into a transcript
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct OracleRecorder<B, F = FieldElement> {
+    backend: B,
+    transcript: Mutex<OracleTranscript<F>>,
+}
+
+#[cfg(feature = "std")]
+impl<B, F: Clone> OracleRecorder<B, F> {
+    pub fn new(backend: B) -> Self {
+        OracleRecorder {
+            backend,
+            transcript: Mutex::new(OracleTranscript::default()),
+        }
+    }
+
+    /// Returns the calls which have been recorded so far
+    pub fn transcript(&self) -> OracleTranscript<F> {
+        self.transcript
+            .lock()
+            .expect("oracle transcript lock poisoned")
+            .clone()
+    }
+
+    /// Clears the calls which have been recorded, returning them
+    pub fn take_transcript(&self) -> OracleTranscript<F> {
+        core::mem::take(
+            &mut *self
+                .transcript
+                .lock()
+                .expect("oracle transcript lock poisoned"),
+        )
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn into_inner(self) -> B {
+        self.backend
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: AcirField, B: PartialWitnessGenerator<F>> PartialWitnessGenerator<F>
+    for OracleRecorder<B, F>
+{
+    fn solve_opcode(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        opcode: &Opcode<F>,
+        functions: &[Circuit<F>],
+        stack: &mut WitnessStack<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        solve_opcode_with_oracles(
+            self,
+            &self.backend,
+            initial_witness,
+            opcode,
+            functions,
+            stack,
+        )
+    }
+
+    fn solve_blackbox_function_call(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        func_call: &BlackBoxFuncCall,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        self.backend
+            .solve_blackbox_function_call(initial_witness, func_call)
+    }
+
+    fn batch_opcodes(&self) -> bool {
+        self.backend.batch_opcodes()
+    }
+
+    fn solve_oracle_call(
+        &self,
+        name: &str,
+        inputs: &[F],
+    ) -> Result<Vec<F>, OpcodeResolutionError<F>> {
+        let outputs = self.backend.solve_oracle_call(name, inputs)?;
+        self.transcript
+            .lock()
+            .expect("oracle transcript lock poisoned")
+            .calls
+            .push(OracleCall {
+                name: name.to_owned(),
+                inputs: inputs.to_vec(),
+                outputs: outputs.clone(),
+            });
+        Ok(outputs)
+    }
+}
+
+/// Wraps a backend, answering oracle calls from a transcript instead of the backend.
+///
+/// The calls must be made in the order of the transcript, with the same names and inputs,
+/// otherwise solving fails with `OracleTranscriptMismatch`. As the solver is
+/// deterministic, this holds when the same circuit is solved with the same inputs.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct OracleReplayer<B, F = FieldElement> {
+    backend: B,
+    transcript: OracleTranscript<F>,
+    // The index of the next call in the transcript
+    next_call: Mutex<usize>,
+}
+
+#[cfg(feature = "std")]
+impl<B, F> OracleReplayer<B, F> {
+    pub fn new(backend: B, transcript: OracleTranscript<F>) -> Self {
+        OracleReplayer {
+            backend,
+            transcript,
+            next_call: Mutex::new(0),
+        }
+    }
+
+    /// Returns the number of calls in the transcript which have not been replayed
+    pub fn num_remaining(&self) -> usize {
+        let next_call = *self.next_call.lock().expect("oracle replay lock poisoned");
+        self.transcript.calls.len() - next_call
+    }
+
+    /// Starts replaying from the first call of the transcript again
+    pub fn rewind(&self) {
+        *self.next_call.lock().expect("oracle replay lock poisoned") = 0;
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn into_inner(self) -> B {
+        self.backend
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: AcirField, B: PartialWitnessGenerator<F>> PartialWitnessGenerator<F>
+    for OracleReplayer<B, F>
+{
+    fn solve_opcode(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        opcode: &Opcode<F>,
+        functions: &[Circuit<F>],
+        stack: &mut WitnessStack<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        solve_opcode_with_oracles(
+            self,
+            &self.backend,
+            initial_witness,
+            opcode,
+            functions,
+            stack,
+        )
+    }
+
+    fn solve_blackbox_function_call(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        func_call: &BlackBoxFuncCall,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        self.backend
+            .solve_blackbox_function_call(initial_witness, func_call)
+    }
+
+    fn batch_opcodes(&self) -> bool {
+        self.backend.batch_opcodes()
+    }
+
+    fn solve_oracle_call(
+        &self,
+        name: &str,
+        inputs: &[F],
+    ) -> Result<Vec<F>, OpcodeResolutionError<F>> {
+        let mut next_call = self.next_call.lock().expect("oracle replay lock poisoned");
+        let index = *next_call;
+        match self.transcript.calls.get(index) {
+            Some(call) if call.name == name && call.inputs == inputs => {
+                *next_call += 1;
+                Ok(call.outputs.clone())
+            }
+            _ => Err(OpcodeResolutionError::OracleTranscriptMismatch {
+                index,
+                name: name.to_owned(),
+            }),
+        }
+    }
+}
+
+// Solves an opcode with `backend`, except for oracles and calls, which are solved through
+// `wrapper` so that it sees every oracle call, including those made by callees
+#[cfg(feature = "std")]
+pub(super) fn solve_opcode_with_oracles<
+    F: AcirField,
+    W: PartialWitnessGenerator<F> + ?Sized,
+    B: PartialWitnessGenerator<F> + ?Sized,
+>(
+    wrapper: &W,
+    backend: &B,
+    initial_witness: &mut WitnessMap<F>,
+    opcode: &Opcode<F>,
+    functions: &[Circuit<F>],
+    stack: &mut WitnessStack<F>,
+) -> Result<(), OpcodeResolutionError<F>> {
+    match opcode {
+        Opcode::Directive(Directive::Oracle {
+            name,
+            inputs,
+            outputs,
+        }) => solve_oracle(wrapper, initial_witness, name, inputs, outputs),
+        Opcode::Call {
+            id,
+            inputs,
+            outputs,
+        } => super::call::solve_call(
+            wrapper,
+            initial_witness,
+            functions,
+            stack,
+            *id,
+            inputs,
+            outputs,
+        ),
+        _ => backend.solve_opcode(initial_witness, opcode, functions, stack),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pwg::NativeSolver;
+    use acir::circuit::PublicInputs;
+
+    // An oracle which returns the square of its input, counting how often it is called
+    #[derive(Default)]
+    struct Squares {
+        num_calls: Mutex<usize>,
+    }
+
+    impl PartialWitnessGenerator for Squares {
+        fn solve_blackbox_function_call(
+            &self,
+            initial_witness: &mut WitnessMap,
+            func_call: &BlackBoxFuncCall,
+        ) -> Result<(), OpcodeResolutionError> {
+            NativeSolver.solve_blackbox_function_call(initial_witness, func_call)
+        }
+
+        fn solve_oracle_call(
+            &self,
+            name: &str,
+            inputs: &[FieldElement],
+        ) -> Result<Vec<FieldElement>, OpcodeResolutionError> {
+            match name {
+                "square" => {
+                    *self.num_calls.lock().unwrap() += 1;
+                    Ok(vec![inputs[0] * inputs[0]])
+                }
+                _ => Err(OpcodeResolutionError::UnsupportedOracle(name.to_owned())),
+            }
+        }
+    }
+
+    // _2 = square(_1 + 1), _3 = square(_2)
+    fn circuit() -> Circuit {
+        let square = |input: Expression, output| {
+            Opcode::Directive(Directive::Oracle {
+                name: "square".to_owned(),
+                inputs: vec![input],
+                outputs: vec![output],
+            })
+        };
+        Circuit {
+            current_witness_index: 3,
+            opcodes: vec![
+                // Stalls on _2 until the first oracle is answered
+                square(Expression::from(&Witness(2)), Witness(3)),
+                square(
+                    &Expression::from(&Witness(1)) + &Expression::one(),
+                    Witness(2),
+                ),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    fn inputs() -> WitnessMap {
+        WitnessMap::from([(Witness(1), FieldElement::from(2_i128))])
+    }
+
+    #[test]
+    fn oracles_need_a_handler() {
+        let mut witness = inputs();
+        assert_eq!(
+            NativeSolver.solve(&mut witness, circuit().opcodes),
+            Err(OpcodeResolutionError::UnsupportedOracle(
+                "square".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn recorded_transcript_replays_without_the_oracle() {
+        let recorder = OracleRecorder::new(Squares::default());
+        let mut recorded = inputs();
+        recorder.solve(&mut recorded, circuit().opcodes).unwrap();
+        assert_eq!(recorded[&Witness(3)], FieldElement::from(81_i128));
+
+        let transcript = recorder.take_transcript();
+        assert_eq!(
+            transcript.calls,
+            vec![
+                OracleCall {
+                    name: "square".to_owned(),
+                    inputs: vec![FieldElement::from(3_i128)],
+                    outputs: vec![FieldElement::from(9_i128)],
+                },
+                OracleCall {
+                    name: "square".to_owned(),
+                    inputs: vec![FieldElement::from(9_i128)],
+                    outputs: vec![FieldElement::from(81_i128)],
+                },
+            ]
+        );
+        assert_eq!(*recorder.backend().num_calls.lock().unwrap(), 2);
+
+        let bytes = rmp_serde::to_vec(&transcript).unwrap();
+        let transcript: OracleTranscript = rmp_serde::from_slice(&bytes).unwrap();
+
+        let replayer = OracleReplayer::new(Squares::default(), transcript);
+        let mut replayed = inputs();
+        replayer.solve(&mut replayed, circuit().opcodes).unwrap();
+        assert_eq!(replayed, recorded);
+        assert_eq!(replayer.num_remaining(), 0);
+        assert_eq!(*replayer.backend().num_calls.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn replay_rejects_calls_which_differ_from_the_transcript() {
+        let recorder = OracleRecorder::new(Squares::default());
+        recorder.solve(&mut inputs(), circuit().opcodes).unwrap();
+        let replayer = OracleReplayer::new(Squares::default(), recorder.take_transcript());
+
+        let mut witness = WitnessMap::from([(Witness(1), FieldElement::from(3_i128))]);
+        assert_eq!(
+            replayer.solve(&mut witness, circuit().opcodes),
+            Err(OpcodeResolutionError::OracleTranscriptMismatch {
+                index: 0,
+                name: "square".to_owned()
+            })
+        );
+
+        // Replaying past the end of the transcript also fails
+        replayer.solve(&mut inputs(), circuit().opcodes).unwrap();
+        assert_eq!(
+            replayer.solve(&mut inputs(), circuit().opcodes),
+            Err(OpcodeResolutionError::OracleTranscriptMismatch {
+                index: 2,
+                name: "square".to_owned()
+            })
+        );
+        replayer.rewind();
+        assert_eq!(replayer.num_remaining(), 2);
+    }
+}
@@ -1,11 +1,35 @@
-use crate::{pwg::witness_to_value, OpcodeResolutionError};
-use acir::{circuit::opcodes::BlackBoxFuncCall, native_types::Witness, BlackBoxFunc, FieldElement};
-use std::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cell::RefCell;
 
-pub fn solve_range_opcode(
-    initial_witness: &mut BTreeMap<Witness, FieldElement>,
+use crate::{
+    pwg::{self, witness_to_value},
+    OpcodeResolutionError, PartialWitnessGenerator, WitnessMap,
+};
+use acir::{
+    circuit::{
+        directives::Directive,
+        opcodes::{BlackBoxFuncCall, FunctionInput},
+        Circuit, Opcode,
+    },
+    native_types::WitnessStack,
+    AcirField, BlackBoxFunc,
+};
+use num_bigint::BigUint;
+use num_traits::One;
+
+pub fn solve_range_opcode<F: AcirField>(
+    initial_witness: &mut WitnessMap<F>,
     func_call: &BlackBoxFuncCall,
-) -> Result<(), OpcodeResolutionError> {
+) -> Result<(), OpcodeResolutionError<F>> {
+    let input = range_input(func_call)?;
+    check_range(initial_witness, input)
+}
+
+// Returns the input of a RANGE opcode, after checking that there is one input and that
+// its bit size is below the field size
+fn range_input<F: AcirField>(
+    func_call: &BlackBoxFuncCall,
+) -> Result<&FunctionInput, OpcodeResolutionError<F>> {
     // TODO: this consistency check can be moved to a general function
     let defined_input_size = BlackBoxFunc::RANGE
         .definition()
@@ -30,11 +54,398 @@ pub fn solve_range_opcode(
         .first()
         .expect("infallible: checked that input size is 1");
 
+    // Every field element is less than 2^max_num_bits, so larger bit sizes do not
+    // constrain anything and are most likely a mistake in the frontend
+    let max_num_bits = F::max_num_bits();
+    if input.num_bits >= max_num_bits {
+        return Err(OpcodeResolutionError::RangeBitSizeTooLarge {
+            num_bits: input.num_bits,
+            max_num_bits,
+        });
+    }
+
+    Ok(input)
+}
+
+// Checks that the value of the input is less than 2^num_bits
+fn check_range<F: AcirField>(
+    initial_witness: &WitnessMap<F>,
+    input: &FunctionInput,
+) -> Result<(), OpcodeResolutionError<F>> {
     let w_value = witness_to_value(initial_witness, input.witness)?;
 
-    if w_value.num_bits() > input.num_bits {
+    if !fits_in_bits(w_value, input.num_bits) {
         return Err(OpcodeResolutionError::UnsatisfiedConstrain);
     }
 
     Ok(())
 }
+
+// Returns true if `value` is less than 2^num_bits, as an integer
+fn fits_in_bits<F: AcirField>(value: &F, num_bits: u32) -> bool {
+    match value.try_into_u128() {
+        Some(_) if num_bits >= 128 => true,
+        Some(int_value) => int_value >> num_bits == 0,
+        None => BigUint::from_bytes_be(&value.to_be_bytes()) < (BigUint::one() << num_bits),
+    }
+}
+
+/// When the values of RANGE opcodes are checked
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RangeCheckMode {
+    /// Each RANGE opcode is checked as soon as its input is assigned, and solving fails
+    /// immediately if the value does not fit
+    #[default]
+    Strict,
+    /// RANGE opcodes are checked once every opcode of the circuit has been solved, against
+    /// the final value of their input. This tolerates frontends whose directives overwrite
+    /// a value which was assigned earlier.
+    Deferred,
+}
+
+/// Wraps a backend, checking RANGE opcodes natively according to a `RangeCheckMode`.
+///
+/// Deferred checks are made at the end of `PartialWitnessGenerator::solve_with_calls`,
+/// which `solve`, `solve_program` and calls go through. The solvers in `pwg` which step
+/// through opcodes themselves, such as `DebugSolver`, do not make deferred checks.
+#[derive(Debug)]
+pub struct RangeChecker<B> {
+    backend: B,
+    mode: RangeCheckMode,
+}
+
+impl<B> RangeChecker<B> {
+    pub fn new(backend: B, mode: RangeCheckMode) -> Self {
+        RangeChecker { backend, mode }
+    }
+
+    pub fn mode(&self) -> RangeCheckMode {
+        self.mode
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn into_inner(self) -> B {
+        self.backend
+    }
+}
+
+impl<F: AcirField, B: PartialWitnessGenerator<F>> PartialWitnessGenerator<F> for RangeChecker<B> {
+    fn solve_with_calls(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        opcodes: Vec<Opcode<F>>,
+        functions: &[Circuit<F>],
+        stack: &mut WitnessStack<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        let indices = 0..opcodes.len();
+        if self.mode == RangeCheckMode::Strict {
+            return pwg::solve_opcodes(
+                self,
+                initial_witness,
+                &opcodes,
+                indices,
+                functions,
+                stack,
+                None,
+            )
+            .map(|_| ());
+        }
+
+        let frame = DeferredChecks {
+            checker: self,
+            inputs: RefCell::default(),
+        };
+        pwg::solve_opcodes(
+            &frame,
+            initial_witness,
+            &opcodes,
+            indices,
+            functions,
+            stack,
+            None,
+        )
+        .map(|_| ())?;
+        for input in frame.inputs.into_inner() {
+            check_range(initial_witness, &input)?;
+        }
+        Ok(())
+    }
+
+    fn solve_blackbox_function_call(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        func_call: &BlackBoxFuncCall,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        match (func_call.name, self.mode) {
+            (BlackBoxFunc::RANGE, RangeCheckMode::Strict) => {
+                solve_range_opcode(initial_witness, func_call)
+            }
+            // Outside of `solve_with_calls` there is nowhere to defer the check to
+            (BlackBoxFunc::RANGE, RangeCheckMode::Deferred) => range_input(func_call).map(|_| ()),
+            _ => self
+                .backend
+                .solve_blackbox_function_call(initial_witness, func_call),
+        }
+    }
+
+    fn solve_directives(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        directive: &Directive<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        self.backend.solve_directives(initial_witness, directive)
+    }
+
+    fn batch_opcodes(&self) -> bool {
+        self.backend.batch_opcodes()
+    }
+
+    fn solve_oracle_call(
+        &self,
+        name: &str,
+        inputs: &[F],
+    ) -> Result<Vec<F>, OpcodeResolutionError<F>> {
+        self.backend.solve_oracle_call(name, inputs)
+    }
+}
+
+// The solver for one function in `RangeCheckMode::Deferred`, which collects the inputs of its
+// RANGE opcodes to be checked once the function is solved. Each call of
+// `RangeChecker::solve_with_calls` has its own, so that solves sharing a checker, and callees,
+// check their own inputs against their own witness.
+struct DeferredChecks<'a, B> {
+    checker: &'a RangeChecker<B>,
+    inputs: RefCell<Vec<FunctionInput>>,
+}
+
+impl<F: AcirField, B: PartialWitnessGenerator<F>> PartialWitnessGenerator<F>
+    for DeferredChecks<'_, B>
+{
+    fn solve_with_calls(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        opcodes: Vec<Opcode<F>>,
+        functions: &[Circuit<F>],
+        stack: &mut WitnessStack<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        self.checker
+            .solve_with_calls(initial_witness, opcodes, functions, stack)
+    }
+
+    fn solve_blackbox_function_call(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        func_call: &BlackBoxFuncCall,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        if func_call.name != BlackBoxFunc::RANGE {
+            return self
+                .checker
+                .solve_blackbox_function_call(initial_witness, func_call);
+        }
+        let input = range_input(func_call)?;
+        self.inputs.borrow_mut().push(input.clone());
+        Ok(())
+    }
+
+    fn solve_directives(
+        &self,
+        initial_witness: &mut WitnessMap<F>,
+        directive: &Directive<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        self.checker.solve_directives(initial_witness, directive)
+    }
+
+    fn batch_opcodes(&self) -> bool {
+        self.checker.batch_opcodes()
+    }
+
+    fn solve_oracle_call(
+        &self,
+        name: &str,
+        inputs: &[F],
+    ) -> Result<Vec<F>, OpcodeResolutionError<F>> {
+        self.checker.solve_oracle_call(name, inputs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pwg::NativeSolver;
+    use acir::FieldElement;
+    use acir::{
+        circuit::{Program, PublicInputs},
+        native_types::Witness,
+    };
+
+    fn solve_range(value: FieldElement, num_bits: u32) -> Result<(), OpcodeResolutionError> {
+        let mut witness = WitnessMap::from([(Witness(1), value)]);
+        let func_call = BlackBoxFuncCall {
+            name: BlackBoxFunc::RANGE,
+            inputs: vec![FunctionInput {
+                witness: Witness(1),
+                num_bits,
+            }],
+            outputs: Vec::new(),
+        };
+        solve_range_opcode(&mut witness, &func_call)
+    }
+
+    #[test]
+    fn range_is_checked_against_powers_of_two() {
+        let two = FieldElement::from(2_i128);
+        for num_bits in [
+            0,
+            1,
+            8,
+            127,
+            128,
+            129,
+            200,
+            FieldElement::max_num_bits() - 1,
+        ]
+        .into_iter()
+        // Bit sizes which are not below the field size are rejected
+        .filter(|num_bits| *num_bits < FieldElement::max_num_bits())
+        {
+            let pow = two.pow(&FieldElement::from(num_bits as i128));
+            let largest = pow - FieldElement::one();
+            assert_eq!(solve_range(largest, num_bits), Ok(()), "{num_bits} bits");
+            assert_eq!(
+                solve_range(pow, num_bits),
+                Err(OpcodeResolutionError::UnsatisfiedConstrain),
+                "{num_bits} bits"
+            );
+        }
+        assert_eq!(
+            solve_range(-FieldElement::one(), FieldElement::max_num_bits() - 1),
+            Err(OpcodeResolutionError::UnsatisfiedConstrain)
+        );
+    }
+
+    #[test]
+    fn range_bit_sizes_must_be_below_the_field_size() {
+        let max_num_bits = FieldElement::max_num_bits();
+        for num_bits in [max_num_bits, max_num_bits + 1, u32::MAX] {
+            assert_eq!(
+                solve_range(FieldElement::zero(), num_bits),
+                Err(OpcodeResolutionError::RangeBitSizeTooLarge {
+                    num_bits,
+                    max_num_bits
+                })
+            );
+        }
+    }
+
+    // Checks _2 to 8 bits, and then overwrites _2 with the inverse of _1
+    fn overwriting_circuit() -> Circuit {
+        Circuit {
+            current_witness_index: 2,
+            opcodes: vec![
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::RANGE,
+                    inputs: vec![FunctionInput {
+                        witness: Witness(2),
+                        num_bits: 8,
+                    }],
+                    outputs: Vec::new(),
+                }),
+                Opcode::Directive(Directive::Invert {
+                    x: Witness(1),
+                    result: Witness(2),
+                }),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    fn inputs(x: i128) -> WitnessMap {
+        WitnessMap::from([
+            (Witness(1), FieldElement::from(x)),
+            (Witness(2), FieldElement::from(300_i128)),
+        ])
+    }
+
+    #[test]
+    fn strict_range_checks_fail_on_the_first_value() {
+        let checker = RangeChecker::new(NativeSolver, RangeCheckMode::default());
+        assert_eq!(checker.mode(), RangeCheckMode::Strict);
+        assert_eq!(
+            checker.solve(&mut inputs(1), overwriting_circuit().opcodes),
+            Err(OpcodeResolutionError::UnsatisfiedConstrain)
+        );
+        assert_eq!(
+            NativeSolver.solve(&mut inputs(1), overwriting_circuit().opcodes),
+            Err(OpcodeResolutionError::UnsatisfiedConstrain)
+        );
+    }
+
+    #[test]
+    fn deferred_range_checks_use_the_final_value() {
+        let checker = RangeChecker::new(NativeSolver, RangeCheckMode::Deferred);
+        let mut witness = inputs(1);
+        checker
+            .solve(&mut witness, overwriting_circuit().opcodes)
+            .unwrap();
+        assert_eq!(witness[&Witness(2)], FieldElement::one());
+
+        // The inverse of 2 does not fit in 8 bits
+        assert_eq!(
+            checker.solve(&mut inputs(2), overwriting_circuit().opcodes),
+            Err(OpcodeResolutionError::UnsatisfiedConstrain)
+        );
+
+        // A callee's checks are made against the callee's witness
+        let main = Circuit {
+            current_witness_index: 3,
+            opcodes: vec![Opcode::Call {
+                id: 1,
+                inputs: vec![Witness(1), Witness(2)],
+                outputs: Vec::new(),
+            }],
+            public_inputs: PublicInputs(vec![Witness(1), Witness(2)]),
+            return_values: PublicInputs::default(),
+        };
+        let callee = Circuit {
+            public_inputs: PublicInputs(vec![Witness(1), Witness(2)]),
+            ..overwriting_circuit()
+        };
+        let program = Program {
+            functions: vec![main, callee],
+        };
+        checker.solve_program(&mut inputs(1), &program).unwrap();
+        assert_eq!(
+            checker.solve_program(&mut inputs(2), &program),
+            Err(OpcodeResolutionError::UnsatisfiedConstrain)
+        );
+    }
+
+    #[test]
+    fn concurrent_solves_check_their_own_ranges() {
+        let checker = RangeChecker::new(NativeSolver, RangeCheckMode::Deferred);
+        std::thread::scope(|scope| {
+            for thread in 0..8 {
+                let checker = &checker;
+                scope.spawn(move || {
+                    for i in 0..50 {
+                        // The inverse of 1 fits in 8 bits, the inverse of 2 does not
+                        let x = 1 + (thread + i) % 2;
+                        let expected = if x == 1 {
+                            Ok(())
+                        } else {
+                            Err(OpcodeResolutionError::UnsatisfiedConstrain)
+                        };
+                        assert_eq!(
+                            checker.solve(&mut inputs(x), overwriting_circuit().opcodes),
+                            expected
+                        );
+                    }
+                });
+            }
+        });
+    }
+}
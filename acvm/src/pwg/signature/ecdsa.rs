@@ -1,12 +1,22 @@
-use acir::{circuit::opcodes::BlackBoxFuncCall, native_types::Witness, FieldElement};
-use std::collections::BTreeMap;
+use acir::{circuit::opcodes::BlackBoxFuncCall, AcirField, BlackBoxFunc};
+use alloc::vec::Vec;
 
-use crate::{pwg::witness_to_value, OpcodeResolutionError};
+use crate::{pwg::witness_to_value, OpcodeResolutionError, WitnessMap};
 
-pub fn secp256k1_prehashed(
-    initial_witness: &mut BTreeMap<Witness, FieldElement>,
+// The bytes of the x and y coordinates of the public key, the signature and the hashed message
+const NUM_INPUTS: usize = 32 + 32 + 64 + 32;
+
+pub fn secp256k1_prehashed<F: AcirField>(
+    initial_witness: &mut WitnessMap<F>,
     gadget_call: &BlackBoxFuncCall,
-) -> Result<(), OpcodeResolutionError> {
+) -> Result<(), OpcodeResolutionError<F>> {
+    if gadget_call.inputs.len() != NUM_INPUTS {
+        return Err(OpcodeResolutionError::IncorrectNumFunctionArguments(
+            NUM_INPUTS,
+            BlackBoxFunc::EcdsaSecp256k1,
+            gadget_call.inputs.len(),
+        ));
+    }
     let mut inputs_iter = gadget_call.inputs.iter();
 
     let mut pub_key_x = [0u8; 32];
@@ -51,8 +61,8 @@ pub fn secp256k1_prehashed(
             .is_ok();
 
     let result = match result {
-        true => FieldElement::one(),
-        false => FieldElement::zero(),
+        true => F::one(),
+        false => F::zero(),
     };
 
     initial_witness.insert(gadget_call.outputs[0], result);
@@ -60,7 +70,7 @@ pub fn secp256k1_prehashed(
 }
 
 mod ecdsa_secp256k1 {
-    use std::convert::TryInto;
+    use core::convert::TryInto;
 
     use k256::{ecdsa::Signature, Scalar};
     use k256::{
@@ -111,7 +121,7 @@ mod ecdsa_secp256k1 {
     ) -> Result<(), ()> {
         // Convert the inputs into k256 data structures
 
-        let signature = Signature::try_from(signature).unwrap();
+        let signature = Signature::try_from(signature).map_err(|_| ())?;
 
         let pub_key_x_arr: [u8; 32] = {
             let pub_key_x_bytes: &[u8] = public_key_x_bytes;
@@ -127,7 +137,7 @@ mod ecdsa_secp256k1 {
             &pub_key_y_arr.into(),
             true,
         );
-        let pubkey = PublicKey::try_from(point).unwrap();
+        let pubkey = PublicKey::try_from(point).map_err(|_| ())?;
 
         let z = Scalar::from_bytes_reduced(hashed_msg.into());
 
@@ -158,3 +168,43 @@ mod ecdsa_secp256k1 {
         Err(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::FieldElement;
+    use acir::{circuit::opcodes::FunctionInput, native_types::Witness};
+
+    fn call(num_inputs: u32) -> BlackBoxFuncCall {
+        BlackBoxFuncCall {
+            name: BlackBoxFunc::EcdsaSecp256k1,
+            inputs: (1..=num_inputs)
+                .map(|index| FunctionInput {
+                    witness: Witness(index),
+                    num_bits: 8,
+                })
+                .collect(),
+            outputs: vec![Witness(num_inputs + 1)],
+        }
+    }
+
+    #[test]
+    fn malformed_calls_are_rejected_without_panicking() {
+        let mut witness: WitnessMap = (1..=NUM_INPUTS as u32)
+            .map(|index| (Witness(index), FieldElement::from(index as i128)))
+            .collect();
+        assert_eq!(
+            secp256k1_prehashed(&mut witness, &call(3)),
+            Err(OpcodeResolutionError::IncorrectNumFunctionArguments(
+                NUM_INPUTS,
+                BlackBoxFunc::EcdsaSecp256k1,
+                3
+            ))
+        );
+
+        // The bytes are neither a point on the curve nor a valid signature
+        let output = Witness(NUM_INPUTS as u32 + 1);
+        secp256k1_prehashed(&mut witness, &call(NUM_INPUTS as u32)).unwrap();
+        assert_eq!(witness[&output], FieldElement::zero());
+    }
+}
@@ -0,0 +1,383 @@
+use crate::collections::HashMap;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+
+use acir::{
+    circuit::{Circuit, Opcode},
+    native_types::{Expression, Witness, WitnessStack},
+    AcirField,
+};
+
+use crate::{OpcodeResolutionError, PartialWitnessGenerator, WitnessMap};
+
+/// Solves a circuit where some witnesses are unknown, returning the value of each of
+/// the `outputs` as an expression of the witnesses which could not be solved.
+///
+/// Witnesses which the solver would stall on are represented by themselves. When no
+/// opcode can be solved, the lowest unknown witness of the first stalled opcode becomes
+/// a symbol, so the symbols are typically the inputs which were left out of
+/// `initial_witness`. Arithmetic opcodes are solved by substituting the known values,
+/// which are simplified into normal form. Black box functions and directives are solved
+/// with the backend when their inputs are constant, otherwise their outputs become
+/// symbols, as do the outputs of calls and any witness whose expression would have a
+/// degree above two.
+///
+/// This solves every opcode in repeated passes, so it is meant for inspecting circuits
+/// rather than for generating witnesses.
+pub fn solve_symbolic<F: AcirField, B: PartialWitnessGenerator<F> + ?Sized>(
+    backend: &B,
+    circuit: &Circuit<F>,
+    initial_witness: &WitnessMap<F>,
+    outputs: &[Witness],
+) -> Result<BTreeMap<Witness, Expression<F>>, OpcodeResolutionError<F>> {
+    initial_witness.validate(circuit)?;
+    let mut solver = SymbolicSolver {
+        values: initial_witness
+            .iter()
+            .map(|(witness, value)| (*witness, Expression::from_field(*value)))
+            .collect(),
+        constants: initial_witness.clone(),
+    };
+
+    let mut pending: Vec<&Opcode<F>> = circuit.opcodes.iter().collect();
+    while !pending.is_empty() {
+        let mut unsolved = Vec::with_capacity(pending.len());
+        for opcode in pending.iter().copied() {
+            if !solver.solve_opcode(backend, opcode)? {
+                unsolved.push(opcode);
+            }
+        }
+
+        if unsolved.len() == pending.len() {
+            // Instead of stalling, treat one of the missing witnesses as a symbol
+            let symbol = unsolved
+                .iter()
+                .find_map(|opcode| solver.unknown_inputs(opcode).into_iter().next())
+                .expect("an unsolved opcode has an unknown witness");
+            solver.assign(symbol, Expression::from(&symbol));
+        }
+        pending = unsolved;
+    }
+
+    Ok(outputs
+        .iter()
+        .map(|witness| {
+            let value = solver
+                .values
+                .get(witness)
+                .cloned()
+                .unwrap_or_else(|| Expression::from(witness));
+            (*witness, value)
+        })
+        .collect())
+}
+
+struct SymbolicSolver<F: AcirField> {
+    // The value of each witness which has been solved, possibly in terms of symbols
+    values: HashMap<Witness, Expression<F>>,
+    // The witnesses whose values are constant, for solving black box functions
+    constants: WitnessMap<F>,
+}
+
+impl<F: AcirField> SymbolicSolver<F> {
+    fn assign(&mut self, witness: Witness, value: Expression<F>) {
+        if value.is_const() {
+            self.constants.insert(witness, value.q_c);
+        }
+        self.values.insert(witness, value);
+    }
+
+    // Returns the witnesses which an opcode needs before it can be solved, and which
+    // have no value
+    fn unknown_inputs(&self, opcode: &Opcode<F>) -> BTreeSet<Witness> {
+        let mut inputs = match opcode {
+            Opcode::Arithmetic(expr) => expr.witnesses(),
+            Opcode::BlackBoxFuncCall(func_call) => {
+                func_call.inputs.iter().map(|input| input.witness).collect()
+            }
+            Opcode::Directive(directive) => directive.inputs(),
+            Opcode::Call { inputs, .. } => inputs.iter().copied().collect(),
+        };
+        inputs.retain(|witness| !self.values.contains_key(witness));
+        inputs
+    }
+
+    // Returns whether the opcode was solved
+    fn solve_opcode<B: PartialWitnessGenerator<F> + ?Sized>(
+        &mut self,
+        backend: &B,
+        opcode: &Opcode<F>,
+    ) -> Result<bool, OpcodeResolutionError<F>> {
+        let Opcode::Arithmetic(expr) = opcode else {
+            if !self.unknown_inputs(opcode).is_empty() {
+                return Ok(false);
+            }
+            return self.solve_with_backend(backend, opcode).map(|()| true);
+        };
+
+        let unknowns = self.unknown_inputs(opcode);
+        let Some(substituted) = self.substitute(expr) else {
+            // The expression has too high a degree to be represented, so the unknown
+            // witness becomes a symbol
+            if let [unknown] = unknowns.iter().copied().collect::<Vec<_>>()[..] {
+                self.assign(unknown, Expression::from(&unknown));
+            }
+            return Ok(unknowns.len() <= 1);
+        };
+
+        match unknowns.len() {
+            0 => {
+                if substituted.is_const() && !substituted.q_c.is_zero() {
+                    return Err(OpcodeResolutionError::UnsatisfiedConstrain);
+                }
+                Ok(true)
+            }
+            1 => {
+                let unknown = *unknowns.first().expect("there is one unknown");
+                if substituted
+                    .mul_terms
+                    .iter()
+                    .any(|(_, lhs, rhs)| *lhs == unknown || *rhs == unknown)
+                {
+                    return Ok(false);
+                }
+                let coefficient = substituted
+                    .linear_combinations
+                    .iter()
+                    .find(|(_, witness)| *witness == unknown)
+                    .map(|(coefficient, _)| *coefficient);
+                let Some(coefficient) = coefficient else {
+                    // The unknown witness cancels out
+                    return Ok(false);
+                };
+
+                // c * unknown + rest = 0, so unknown = -rest / c
+                let mut rest = substituted;
+                rest.linear_combinations
+                    .retain(|(_, witness)| *witness != unknown);
+                let value = &rest * &(-coefficient.inverse());
+                self.assign(unknown, value);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    // Solves an opcode whose inputs have all been solved. Its outputs are constant if its
+    // inputs are, and are symbols otherwise.
+    fn solve_with_backend<B: PartialWitnessGenerator<F> + ?Sized>(
+        &mut self,
+        backend: &B,
+        opcode: &Opcode<F>,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        let (inputs, outputs): (BTreeSet<Witness>, Vec<Witness>) = match opcode {
+            Opcode::Arithmetic(_) => unreachable!("arithmetic opcodes are solved symbolically"),
+            Opcode::BlackBoxFuncCall(func_call) => (
+                func_call.inputs.iter().map(|input| input.witness).collect(),
+                func_call.outputs.clone(),
+            ),
+            Opcode::Directive(directive) => (directive.inputs(), directive.outputs()),
+            Opcode::Call { outputs, .. } => (BTreeSet::new(), outputs.clone()),
+        };
+
+        let is_call = matches!(opcode, Opcode::Call { .. });
+        if is_call
+            || !inputs
+                .iter()
+                .all(|witness| self.constants.contains_key(witness))
+        {
+            for output in outputs {
+                self.assign(output, Expression::from(&output));
+            }
+            return Ok(());
+        }
+
+        backend.solve_opcode(
+            &mut self.constants,
+            opcode,
+            &[],
+            &mut WitnessStack::default(),
+        )?;
+        for output in outputs {
+            let value = match self.constants.get(&output) {
+                Some(value) => Expression::from_field(*value),
+                None => Expression::from(&output),
+            };
+            self.values.insert(output, value);
+        }
+        Ok(())
+    }
+
+    // Replaces each witness of the expression which has a value with its value. Returns
+    // `None` if the result would have a degree above two.
+    fn substitute(&self, expr: &Expression<F>) -> Option<Expression<F>> {
+        let value = |witness: &Witness| {
+            self.values
+                .get(witness)
+                .cloned()
+                .unwrap_or_else(|| Expression::from(witness))
+        };
+
+        let mut result = Expression::from_field(expr.q_c);
+        for (coefficient, witness) in &expr.linear_combinations {
+            result = &result + &(&value(witness) * coefficient);
+        }
+        for (coefficient, lhs, rhs) in &expr.mul_terms {
+            let product = multiply(&value(lhs), &value(rhs))?;
+            result = &result + &(&product * coefficient);
+        }
+        Some(result.normalize())
+    }
+}
+
+// Returns the product of two expressions, or `None` if it would have a degree above two
+fn multiply<F: AcirField>(lhs: &Expression<F>, rhs: &Expression<F>) -> Option<Expression<F>> {
+    if lhs.is_const() {
+        return Some(rhs * &lhs.q_c);
+    }
+    if rhs.is_const() {
+        return Some(lhs * &rhs.q_c);
+    }
+    if !lhs.is_linear() || !rhs.is_linear() {
+        return None;
+    }
+
+    // (a + sum(a_i * x_i)) * (b + sum(b_j * y_j))
+    let mut product = Expression::from_field(lhs.q_c * rhs.q_c);
+    for (a, x) in &lhs.linear_combinations {
+        product.term_addition(*a * rhs.q_c, *x);
+        for (b, y) in &rhs.linear_combinations {
+            product.term_multiplication(*a * *b, *x, *y);
+        }
+    }
+    for (b, y) in &rhs.linear_combinations {
+        product.term_addition(*b * lhs.q_c, *y);
+    }
+    Some(product)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pwg::NativeSolver;
+    use acir::{
+        circuit::{
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            PublicInputs,
+        },
+        BlackBoxFunc, FieldElement,
+    };
+
+    fn field(value: i128) -> FieldElement {
+        FieldElement::from(value)
+    }
+
+    // lhs * rhs - output = 0
+    fn product(lhs: u32, rhs: u32, output: u32) -> Opcode {
+        let mut expr = Expression::default();
+        expr.term_multiplication(FieldElement::one(), Witness(lhs), Witness(rhs));
+        expr.term_addition(-FieldElement::one(), Witness(output));
+        Opcode::Arithmetic(expr)
+    }
+
+    #[test]
+    fn outputs_are_expressions_of_unknown_inputs() {
+        // _3 = _1 * _2, _4 = _3 + 2 * _1 + 5
+        let mut sum = Expression::default();
+        sum.term_addition(FieldElement::one(), Witness(3));
+        sum.term_addition(field(2), Witness(1));
+        sum.term_addition(-FieldElement::one(), Witness(4));
+        sum.q_c = field(5);
+        let circuit = Circuit {
+            current_witness_index: 4,
+            opcodes: vec![product(1, 2, 3), Opcode::Arithmetic(sum)],
+            public_inputs: PublicInputs(vec![Witness(1), Witness(2)]),
+            return_values: PublicInputs::default(),
+        };
+
+        // With _2 = 3, _4 = 3 * _1 + 2 * _1 + 5 = 5 * _1 + 5
+        let initial_witness = WitnessMap::from([(Witness(2), field(3))]);
+        let outputs =
+            solve_symbolic(&NativeSolver, &circuit, &initial_witness, &[Witness(4)]).unwrap();
+        let mut expected = Expression::default();
+        expected.term_addition(field(5), Witness(1));
+        expected.q_c = field(5);
+        assert_eq!(outputs[&Witness(4)], expected);
+
+        // With no inputs, _4 = _1 * _2 + 2 * _1 + 5
+        let outputs =
+            solve_symbolic(&NativeSolver, &circuit, &WitnessMap::new(), &[Witness(4)]).unwrap();
+        let mut expected = Expression::default();
+        expected.term_multiplication(FieldElement::one(), Witness(1), Witness(2));
+        expected.term_addition(field(2), Witness(1));
+        expected.q_c = field(5);
+        assert_eq!(outputs[&Witness(4)], expected.normalize());
+
+        // With every input, the output is constant
+        let initial_witness = WitnessMap::from([(Witness(1), field(2)), (Witness(2), field(3))]);
+        let outputs =
+            solve_symbolic(&NativeSolver, &circuit, &initial_witness, &[Witness(4)]).unwrap();
+        assert_eq!(outputs[&Witness(4)], Expression::from_field(field(15)));
+    }
+
+    #[test]
+    fn high_degree_and_black_box_outputs_become_symbols() {
+        // _3 = _1 * _2, _4 = _3 * _3, _5 = _1 AND _1
+        let circuit = Circuit {
+            current_witness_index: 5,
+            opcodes: vec![
+                product(1, 2, 3),
+                product(3, 3, 4),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::AND,
+                    inputs: vec![
+                        FunctionInput {
+                            witness: Witness(1),
+                            num_bits: 8,
+                        },
+                        FunctionInput {
+                            witness: Witness(1),
+                            num_bits: 8,
+                        },
+                    ],
+                    outputs: vec![Witness(5)],
+                }),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1), Witness(2)]),
+            return_values: PublicInputs::default(),
+        };
+
+        let outputs = [Witness(4), Witness(5)];
+        let symbolic =
+            solve_symbolic(&NativeSolver, &circuit, &WitnessMap::new(), &outputs).unwrap();
+        assert_eq!(symbolic[&Witness(4)], Expression::from(&Witness(4)));
+        assert_eq!(symbolic[&Witness(5)], Expression::from(&Witness(5)));
+
+        // Once _1 is known, the black box function is solved
+        let initial_witness = WitnessMap::from([(Witness(1), field(6))]);
+        let symbolic = solve_symbolic(&NativeSolver, &circuit, &initial_witness, &outputs).unwrap();
+        assert_eq!(symbolic[&Witness(5)], Expression::from_field(field(6)));
+        let mut expected = Expression::default();
+        expected.term_multiplication(field(36), Witness(2), Witness(2));
+        assert_eq!(symbolic[&Witness(4)], expected);
+    }
+
+    #[test]
+    fn constant_constraints_are_checked() {
+        let circuit = Circuit {
+            current_witness_index: 3,
+            opcodes: vec![product(1, 2, 3)],
+            public_inputs: PublicInputs::default(),
+            return_values: PublicInputs::default(),
+        };
+        let initial_witness = WitnessMap::from([
+            (Witness(1), field(2)),
+            (Witness(2), field(3)),
+            (Witness(3), field(7)),
+        ]);
+        assert_eq!(
+            solve_symbolic(&NativeSolver, &circuit, &initial_witness, &[]),
+            Err(OpcodeResolutionError::UnsatisfiedConstrain)
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use alloc::collections::BTreeSet;
+use core::ops::ControlFlow;
+
+use crate::collections::HashSet;
+
+use acir::{
+    circuit::{Circuit, Opcode},
+    native_types::{Witness, WitnessStack},
+    AcirField, FieldElement,
+};
+
+use crate::{prepare_witness, OpcodeResolutionError, PartialWitnessGenerator, WitnessMap};
+
+use super::solve_opcodes;
+
+/// The assignment of a watched witness
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchEvent<'a, F: AcirField = FieldElement> {
+    pub witness: Witness,
+    pub value: F,
+    /// The index of the opcode which assigned the witness
+    pub opcode_index: usize,
+    pub opcode: &'a Opcode<F>,
+}
+
+/// Solves a circuit as `PartialWitnessGenerator::solve` does, calling `on_assigned` as
+/// soon as each of the `watchpoints` is assigned.
+///
+/// The callback can stop solving by returning `Break`, in which case `Break` is returned
+/// and the witness holds the assignments made so far. Watched witnesses which are in
+/// `initial_witness` are not reported, as no opcode assigns them.
+pub fn solve_with_watchpoints<F: AcirField, B: PartialWitnessGenerator<F> + ?Sized>(
+    backend: &B,
+    initial_witness: &mut WitnessMap<F>,
+    circuit: &Circuit<F>,
+    watchpoints: &[Witness],
+    mut on_assigned: impl FnMut(WatchEvent<'_, F>) -> ControlFlow<()>,
+) -> Result<ControlFlow<()>, OpcodeResolutionError<F>> {
+    prepare_witness(initial_witness, circuit)?;
+    let watchpoints: HashSet<Witness> = watchpoints.iter().copied().collect();
+    solve_opcodes(
+        backend,
+        initial_witness,
+        &circuit.opcodes,
+        0..circuit.opcodes.len(),
+        &[],
+        &mut WitnessStack::default(),
+        Some(&mut |opcode_index, assigned, witness_map| {
+            let watched: BTreeSet<Witness> = assigned
+                .into_iter()
+                .filter(|witness| watchpoints.contains(witness))
+                .collect();
+            for witness in watched {
+                on_assigned(WatchEvent {
+                    witness,
+                    value: witness_map[&witness],
+                    opcode_index,
+                    opcode: &circuit.opcodes[opcode_index],
+                })?;
+            }
+            ControlFlow::Continue(())
+        }),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        pwg::NativeSolver,
+        testing::{generate_circuit, CircuitSpec},
+    };
+
+    #[test]
+    fn watched_witnesses_are_reported_when_assigned() {
+        // Each gate assigns the witness after the two inputs, in order
+        let (circuit, inputs) = generate_circuit(&CircuitSpec {
+            num_arithmetic: 10,
+            ..CircuitSpec::default()
+        });
+
+        let mut events = Vec::new();
+        let mut witness = inputs.clone();
+        let result = solve_with_watchpoints(
+            &NativeSolver,
+            &mut witness,
+            &circuit,
+            &[Witness(1), Witness(5), Witness(9)],
+            |event| {
+                events.push((event.witness, event.value, event.opcode_index));
+                ControlFlow::Continue(())
+            },
+        );
+        assert_eq!(result, Ok(ControlFlow::Continue(())));
+        assert_eq!(
+            events,
+            vec![
+                (Witness(5), witness[&Witness(5)], 2),
+                (Witness(9), witness[&Witness(9)], 6),
+            ]
+        );
+
+        // Solving stops at the first watchpoint when the callback breaks
+        let mut witness = inputs;
+        let result =
+            solve_with_watchpoints(&NativeSolver, &mut witness, &circuit, &[Witness(5)], |_| {
+                ControlFlow::Break(())
+            });
+        assert_eq!(result, Ok(ControlFlow::Break(())));
+        assert!(witness.contains_key(&Witness(5)));
+        assert!(!witness.contains_key(&Witness(6)));
+    }
+}
@@ -0,0 +1,227 @@
+// Python bindings, built with pyo3
+//
+// The `acvm` Python module exports a `Circuit` class, which is loaded from the format of
+// `Circuit::write` or from the textual format of `Circuit::to_text`, and functions for
+// solving and checking witnesses. As with the other bindings, black box functions are
+// solved with `pwg::NativeSolver`.
+//
+// Witness maps are dictionaries from witness indices to integers. Integers are reduced
+// modulo the field, so negative values may be given, and solved values are returned in
+// the range `[0, p)`.
+//
+// Malformed circuits raise `ValueError`, and witnesses which cannot be solved raise
+// `acvm.SolveError`.
+
+use std::collections::BTreeMap;
+
+use acir::{circuit::Circuit, native_types::Witness, FieldElement};
+use num_bigint::{BigInt, BigUint};
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+
+use crate::{
+    compiler, prepare_witness,
+    pwg::{self, NativeSolver},
+    OpcodeResolutionError, PartialWitnessGenerator, WitnessMap,
+};
+
+create_exception!(acvm, SolveError, PyException);
+
+fn to_witness_map(values: BTreeMap<u32, BigInt>) -> WitnessMap {
+    let modulus = BigInt::from(FieldElement::modulus());
+    values
+        .into_iter()
+        .map(|(index, value)| {
+            let (_, bytes) = (((value % &modulus) + &modulus) % &modulus).to_bytes_be();
+            (Witness(index), FieldElement::from_be_bytes_reduce(&bytes))
+        })
+        .collect()
+}
+
+fn from_witness_map(witness: &WitnessMap) -> BTreeMap<u32, BigUint> {
+    witness
+        .iter()
+        .map(|(witness, value)| {
+            (
+                witness.witness_index(),
+                BigUint::from_bytes_be(&value.to_be_bytes()),
+            )
+        })
+        .collect()
+}
+
+fn solve_impl(
+    circuit: &Circuit,
+    mut witness: WitnessMap,
+) -> Result<WitnessMap, OpcodeResolutionError> {
+    prepare_witness(&mut witness, circuit)?;
+    NativeSolver.solve(&mut witness, circuit.opcodes.clone())?;
+    Ok(witness)
+}
+
+fn is_satisfied_impl(
+    circuit: &Circuit,
+    witness: &WitnessMap,
+) -> Result<bool, OpcodeResolutionError> {
+    witness.validate(circuit)?;
+    match pwg::check_satisfied(&NativeSolver, circuit, witness) {
+        Ok(()) => Ok(true),
+        Err(
+            err @ (OpcodeResolutionError::OpcodeNotSolvable(_)
+            | OpcodeResolutionError::UnsupportedBlackBoxFunc(_)
+            | OpcodeResolutionError::UnsupportedBlackBoxFuncCall { .. }),
+        ) => Err(err),
+        Err(_) => Ok(false),
+    }
+}
+
+fn solve_error(err: OpcodeResolutionError) -> PyErr {
+    SolveError::new_err(err.to_string())
+}
+
+/// A circuit which has been loaded
+#[pyclass(name = "Circuit", module = "acvm", frozen)]
+pub struct PyCircuit(Circuit);
+
+#[pymethods]
+impl PyCircuit {
+    /// Loads a circuit in the format of `Circuit::write`
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<Self> {
+        Circuit::read(data)
+            .map(PyCircuit)
+            .map_err(|err| PyValueError::new_err(format!("malformed circuit: {err}")))
+    }
+
+    /// Parses a circuit in the format of `Circuit::to_text`
+    #[staticmethod]
+    fn from_text(text: &str) -> PyResult<Self> {
+        Circuit::from_text(text)
+            .map(PyCircuit)
+            .map_err(|err| PyValueError::new_err(format!("malformed circuit: {err}")))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.0
+            .write(&mut bytes)
+            .expect("infallible: writing to a Vec cannot fail");
+        bytes
+    }
+
+    fn to_text(&self) -> String {
+        self.0.to_text()
+    }
+
+    /// Returns the `compiler::CircuitStats` of the circuit as a dictionary
+    fn stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let stats = compiler::stats(&self.0);
+        let dict = PyDict::new(py);
+        dict.set_item("num_opcodes", stats.num_opcodes)?;
+        dict.set_item("num_arithmetic", stats.num_arithmetic)?;
+        dict.set_item("blackbox_calls", stats.blackbox_calls)?;
+        dict.set_item("directives", stats.directives)?;
+        dict.set_item("num_calls", stats.num_calls)?;
+        dict.set_item("max_expression_width", stats.max_expression_width)?;
+        dict.set_item("num_witnesses", stats.num_witnesses)?;
+        dict.set_item("num_public_inputs", stats.num_public_inputs)?;
+        Ok(dict)
+    }
+
+    #[getter]
+    fn public_inputs(&self) -> Vec<u32> {
+        self.0.public_inputs.indices()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Circuit(num_opcodes={}, num_witnesses={})",
+            self.0.opcodes.len(),
+            self.0.num_vars()
+        )
+    }
+}
+
+/// Solves a circuit from its initial witness, and returns the solved witness
+#[pyfunction]
+fn solve(
+    circuit: &PyCircuit,
+    initial_witness: BTreeMap<u32, BigInt>,
+) -> PyResult<BTreeMap<u32, BigUint>> {
+    solve_impl(&circuit.0, to_witness_map(initial_witness))
+        .map(|witness| from_witness_map(&witness))
+        .map_err(solve_error)
+}
+
+/// Returns whether a complete witness satisfies the constraints of a circuit. Raises
+/// `SolveError` if a witness which a constraint uses is missing.
+#[pyfunction]
+fn is_satisfied(circuit: &PyCircuit, witness: BTreeMap<u32, BigInt>) -> PyResult<bool> {
+    is_satisfied_impl(&circuit.0, &to_witness_map(witness)).map_err(solve_error)
+}
+
+/// The `acvm` Python module
+#[pymodule]
+fn acvm(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyCircuit>()?;
+    m.add_function(wrap_pyfunction!(solve, m)?)?;
+    m.add_function(wrap_pyfunction!(is_satisfied, m)?)?;
+    m.add("SolveError", m.py().get_type::<SolveError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{
+        circuit::{Opcode, PublicInputs},
+        native_types::Expression,
+    };
+
+    // _2 = _1 + 1
+    fn circuit() -> Circuit {
+        Circuit {
+            current_witness_index: 2,
+            opcodes: vec![Opcode::Arithmetic(
+                &(&Expression::from(&Witness(1)) - &Witness(2)) + &Expression::one(),
+            )],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    #[test]
+    fn witness_values_are_reduced() {
+        let witness = to_witness_map(BTreeMap::from([
+            (1, BigInt::from(-1)),
+            (2, BigInt::from(FieldElement::modulus()) + 3),
+        ]));
+        assert_eq!(witness[&Witness(1)], -FieldElement::one());
+        assert_eq!(witness[&Witness(2)], FieldElement::from(3_i128));
+
+        let values = from_witness_map(&witness);
+        assert_eq!(values[&1], FieldElement::modulus() - 1_u32);
+        assert_eq!(values[&2], BigUint::from(3_u32));
+    }
+
+    #[test]
+    fn solve_and_check() {
+        let circuit = circuit();
+        let initial_witness = to_witness_map(BTreeMap::from([(1, BigInt::from(4))]));
+        let solved = solve_impl(&circuit, initial_witness).unwrap();
+        assert_eq!(solved[&Witness(2)], FieldElement::from(5_i128));
+        assert_eq!(is_satisfied_impl(&circuit, &solved), Ok(true));
+
+        let wrong = to_witness_map(BTreeMap::from([(1, BigInt::from(4)), (2, BigInt::from(6))]));
+        assert_eq!(is_satisfied_impl(&circuit, &wrong), Ok(false));
+
+        assert!(matches!(
+            is_satisfied_impl(&circuit, &WitnessMap::new()),
+            Err(OpcodeResolutionError::OpcodeNotSolvable(_))
+        ));
+    }
+}
@@ -0,0 +1,285 @@
+// Incremental serialisation of circuits and witnesses
+//
+// Large circuits do not need to be held in memory all at once. The header of a circuit is
+// read first, and its opcodes are then read one at a time. The format is the one used by
+// `Circuit::write` and `Circuit::read`, so either side can be materialised.
+
+use std::io::{Read, Write};
+
+use acir::{
+    circuit::{Circuit, Opcode, PublicInputs, VERSION_NUMBER},
+    native_types::Witness,
+    serialisation::{read_u32, write_bytes, write_u32},
+    FieldElement,
+};
+
+use crate::{BackendError, WitnessMap};
+
+/// Everything in a serialised circuit which precedes its opcodes
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct CircuitHeader {
+    pub current_witness_index: u32,
+    pub public_inputs: PublicInputs,
+    pub return_values: PublicInputs,
+    pub num_opcodes: u32,
+}
+
+impl CircuitHeader {
+    pub fn new(circuit: &Circuit) -> Self {
+        CircuitHeader {
+            current_witness_index: circuit.current_witness_index,
+            public_inputs: circuit.public_inputs.clone(),
+            return_values: circuit.return_values.clone(),
+            num_opcodes: circuit.opcodes.len() as u32,
+        }
+    }
+}
+
+/// Writes a circuit from its header and opcodes, without collecting the opcodes.
+///
+/// Returns an error if there are not exactly `header.num_opcodes` opcodes.
+pub fn write_circuit<'a, W: Write>(
+    mut writer: W,
+    header: &CircuitHeader,
+    opcodes: impl IntoIterator<Item = &'a Opcode>,
+) -> std::io::Result<()> {
+    write_u32(&mut writer, VERSION_NUMBER)?;
+    write_u32(&mut writer, header.current_witness_index)?;
+
+    let public_input_indices = header.public_inputs.indices();
+    write_u32(&mut writer, public_input_indices.len() as u32)?;
+    for public_input_index in public_input_indices {
+        write_u32(&mut writer, public_input_index)?;
+    }
+
+    let return_value_indices = header.return_values.indices();
+    write_u32(&mut writer, return_value_indices.len() as u32)?;
+    for return_value_index in return_value_indices {
+        write_u32(&mut writer, return_value_index)?;
+    }
+
+    write_u32(&mut writer, header.num_opcodes)?;
+    let mut num_written = 0;
+    for opcode in opcodes {
+        opcode.write(&mut writer)?;
+        num_written += 1;
+    }
+    if num_written != header.num_opcodes {
+        return Err(std::io::ErrorKind::InvalidInput.into());
+    }
+    Ok(())
+}
+
+/// Reads the header of a circuit, and returns it along with an iterator over its opcodes
+pub fn read_circuit<R: Read>(mut reader: R) -> std::io::Result<(CircuitHeader, OpcodeReader<R>)> {
+    if read_u32(&mut reader)? != VERSION_NUMBER {
+        return Err(std::io::ErrorKind::InvalidData.into());
+    }
+    let current_witness_index = read_u32(&mut reader)?;
+
+    let num_public_inputs = read_u32(&mut reader)?;
+    let public_inputs = (0..num_public_inputs)
+        .map(|_| read_u32(&mut reader).map(Witness))
+        .collect::<std::io::Result<_>>()?;
+
+    let num_return_values = read_u32(&mut reader)?;
+    let return_values = (0..num_return_values)
+        .map(|_| read_u32(&mut reader).map(Witness))
+        .collect::<std::io::Result<_>>()?;
+
+    let num_opcodes = read_u32(&mut reader)?;
+    let header = CircuitHeader {
+        current_witness_index,
+        public_inputs: PublicInputs(public_inputs),
+        return_values: PublicInputs(return_values),
+        num_opcodes,
+    };
+    Ok((
+        header,
+        OpcodeReader {
+            reader,
+            remaining: num_opcodes,
+        },
+    ))
+}
+
+/// Reads the opcodes of a circuit one at a time
+pub struct OpcodeReader<R> {
+    reader: R,
+    remaining: u32,
+}
+
+impl<R: Read> Iterator for OpcodeReader<R> {
+    type Item = std::io::Result<Opcode>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(Opcode::read(&mut self.reader))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
+    }
+}
+
+/// Writes witness assignments as a sequence of entries, each of which is a witness index
+/// followed by the length prefixed big-endian bytes of its value.
+/// The end of the sequence is marked by `u32::MAX`.
+pub fn write_witness<W: Write>(
+    mut writer: W,
+    assignments: impl IntoIterator<Item = (Witness, FieldElement)>,
+) -> std::io::Result<()> {
+    for (witness, value) in assignments {
+        write_u32(&mut writer, witness.0)?;
+        let value = value.to_be_bytes();
+        write_u32(&mut writer, value.len() as u32)?;
+        write_bytes(&mut writer, &value)?;
+    }
+    write_u32(&mut writer, u32::MAX)?;
+    Ok(())
+}
+
+/// Reads witness assignments written by `write_witness` one at a time
+pub struct WitnessReader<R> {
+    reader: R,
+    finished: bool,
+}
+
+impl<R: Read> WitnessReader<R> {
+    pub fn new(reader: R) -> Self {
+        WitnessReader {
+            reader,
+            finished: false,
+        }
+    }
+
+    fn read_assignment(&mut self) -> std::io::Result<Option<(Witness, FieldElement)>> {
+        let index = read_u32(&mut self.reader)?;
+        if index == u32::MAX {
+            return Ok(None);
+        }
+        let len = read_u32(&mut self.reader)?;
+        let mut value = vec![0; len as usize];
+        self.reader.read_exact(&mut value)?;
+        Ok(Some((
+            Witness(index),
+            FieldElement::from_be_bytes_reduce(&value),
+        )))
+    }
+}
+
+impl<R: Read> Iterator for WitnessReader<R> {
+    type Item = std::io::Result<(Witness, FieldElement)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let assignment = self.read_assignment().transpose();
+        self.finished = !matches!(assignment, Some(Ok(_)));
+        assignment
+    }
+}
+
+/// The opcodes of a circuit, read incrementally
+pub type OpcodeStream<'a> = dyn Iterator<Item = std::io::Result<Opcode>> + 'a;
+
+/// Witness assignments, read incrementally
+pub type WitnessStream<'a> = dyn Iterator<Item = std::io::Result<(Witness, FieldElement)>> + 'a;
+
+fn io_error(err: std::io::Error) -> BackendError {
+    BackendError::SerializationError(err.to_string())
+}
+
+/// Collects a streamed circuit into memory
+pub fn collect_circuit(
+    header: &CircuitHeader,
+    opcodes: &mut OpcodeStream,
+) -> Result<Circuit, BackendError> {
+    let opcodes = opcodes
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(io_error)?;
+    Ok(Circuit {
+        current_witness_index: header.current_witness_index,
+        opcodes,
+        public_inputs: header.public_inputs.clone(),
+        return_values: header.return_values.clone(),
+    })
+}
+
+/// Collects streamed witness assignments into memory
+pub fn collect_witness(witness: &mut WitnessStream) -> Result<WitnessMap, BackendError> {
+    witness.collect::<std::io::Result<_>>().map_err(io_error)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{circuit::directives::Directive, native_types::Expression};
+
+    fn circuit() -> Circuit {
+        Circuit {
+            current_witness_index: 3,
+            opcodes: vec![
+                Opcode::Arithmetic(&Expression::from(&Witness(1)) - &Witness(2)),
+                Opcode::Directive(Directive::Invert {
+                    x: Witness(2),
+                    result: Witness(3),
+                }),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs(vec![Witness(3)]),
+        }
+    }
+
+    #[test]
+    fn streamed_circuits_match_materialised_circuits() {
+        let circuit = circuit();
+        let header = CircuitHeader::new(&circuit);
+
+        let mut streamed = Vec::new();
+        write_circuit(&mut streamed, &header, &circuit.opcodes).unwrap();
+        let mut materialised = Vec::new();
+        circuit.write(&mut materialised).unwrap();
+        assert_eq!(streamed, materialised);
+
+        let (read_header, mut opcodes) = read_circuit(&*materialised).unwrap();
+        assert_eq!(read_header, header);
+        assert_eq!(opcodes.size_hint(), (2, Some(2)));
+        assert_eq!(
+            collect_circuit(&read_header, &mut opcodes).unwrap(),
+            circuit
+        );
+    }
+
+    #[test]
+    fn opcode_count_must_match_header() {
+        let circuit = circuit();
+        let header = CircuitHeader {
+            num_opcodes: 3,
+            ..CircuitHeader::new(&circuit)
+        };
+        assert!(write_circuit(Vec::new(), &header, &circuit.opcodes).is_err());
+    }
+
+    #[test]
+    fn witness_roundtrip() {
+        let witness = WitnessMap::from([
+            (Witness(1), FieldElement::from(7_i128)),
+            (Witness(4), -FieldElement::one()),
+        ]);
+
+        let mut bytes = Vec::new();
+        write_witness(&mut bytes, witness.clone()).unwrap();
+        let mut reader = WitnessReader::new(&*bytes);
+        assert_eq!(collect_witness(&mut reader).unwrap(), witness);
+        assert!(reader.next().is_none());
+
+        // A truncated stream is an error rather than a short witness
+        let mut truncated = WitnessReader::new(&bytes[..bytes.len() - 4]);
+        assert!(collect_witness(&mut truncated).is_err());
+    }
+}
@@ -0,0 +1,20 @@
+// Utilities for testing circuits, compiler passes and backends
+//
+// These are exposed publicly, behind the `testing` feature, so that backends can use
+// them to validate their own transformations.
+
+mod audit;
+mod dsl;
+mod equivalence;
+mod fixtures;
+mod generator;
+mod minimize;
+mod mock;
+
+pub use audit::{audit_compile, audit_solve, AuditError};
+pub use dsl::TestCircuit;
+pub use equivalence::{check_equivalence, EquivalenceError};
+pub use fixtures::{fixtures, write_fixtures, Fixture};
+pub use generator::{generate_circuit, generate_satisfied_circuit, CircuitSpec, SatisfiedCircuit};
+pub use minimize::minimize;
+pub use mock::MockBackend;
@@ -0,0 +1,282 @@
+use std::collections::BTreeSet;
+
+use acir::{
+    circuit::{Circuit, Opcode},
+    native_types::Witness,
+    BlackBoxFunc, FieldElement,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use thiserror::Error;
+
+use crate::{
+    compiler::{compile, with_shuffled_order, CompileError},
+    Language, OpcodeResolutionError, PartialWitnessGenerator, WitnessMap,
+};
+
+/// An error of `audit_solve` or `audit_compile`. The errors other than `Solve` and
+/// `Compile` mean that the result depends on the order of work which should not matter.
+#[derive(PartialEq, Eq, Debug, Error)]
+pub enum AuditError {
+    #[error(transparent)]
+    Solve(OpcodeResolutionError),
+    #[error(transparent)]
+    Compile(CompileError),
+    #[error("solving failed only {}: {error}", failed_order(*failed_in_order))]
+    SolveOutcomeMismatch {
+        failed_in_order: bool,
+        error: OpcodeResolutionError,
+    },
+    #[error("witness {} is {in_order:?} when solving in order, but {shuffled:?} when solving in a shuffled order", witness.witness_index())]
+    WitnessMismatch {
+        witness: Witness,
+        in_order: Option<FieldElement>,
+        shuffled: Option<FieldElement>,
+    },
+    #[error("compiling failed only {}: {error}", failed_order(*failed_in_order))]
+    CompileOutcomeMismatch {
+        failed_in_order: bool,
+        error: CompileError,
+    },
+    #[error("compiling in a shuffled order gave a different circuit, {difference}")]
+    CircuitMismatch { difference: String },
+}
+
+/// Solves the opcodes twice, once in their order and once in an order shuffled by `seed`,
+/// and fails if the two solves disagree on whether the opcodes are satisfied or on the
+/// value of any witness. The order of opcodes does not change what they constrain, so
+/// any difference means that solving is not deterministic.
+///
+/// On success `initial_witness` holds the solution. If both solves fail, the error of
+/// solving in order is returned as `AuditError::Solve`, as the opcode which fails first
+/// may depend on the order.
+pub fn audit_solve<B: PartialWitnessGenerator>(
+    backend: &B,
+    initial_witness: &mut WitnessMap,
+    opcodes: Vec<Opcode>,
+    seed: u64,
+) -> Result<(), AuditError> {
+    let mut shuffled_opcodes = opcodes.clone();
+    shuffled_opcodes.shuffle(&mut StdRng::seed_from_u64(seed));
+
+    let mut shuffled_witness = initial_witness.clone();
+    let in_order = backend.solve(initial_witness, opcodes);
+    let shuffled = backend.solve(&mut shuffled_witness, shuffled_opcodes);
+    match (in_order, shuffled) {
+        (Ok(()), Ok(())) => {}
+        (Err(err), Err(_)) => return Err(AuditError::Solve(err)),
+        (Err(error), Ok(())) => {
+            return Err(AuditError::SolveOutcomeMismatch {
+                failed_in_order: true,
+                error,
+            })
+        }
+        (Ok(()), Err(error)) => {
+            return Err(AuditError::SolveOutcomeMismatch {
+                failed_in_order: false,
+                error,
+            })
+        }
+    }
+
+    let witnesses: BTreeSet<Witness> = initial_witness
+        .keys()
+        .chain(shuffled_witness.keys())
+        .copied()
+        .collect();
+    for witness in witnesses {
+        let in_order = initial_witness.get(&witness).copied();
+        let shuffled = shuffled_witness.get(&witness).copied();
+        if in_order != shuffled {
+            return Err(AuditError::WitnessMismatch {
+                witness,
+                in_order,
+                shuffled,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Compiles the circuit as `compiler::compile` does twice, the second time with the work
+/// which the passes do on each opcode done in an order shuffled by `seed`, and fails if
+/// the two compiled circuits differ. Each opcode is compiled independently of the others,
+/// so any difference means that compilation is not deterministic.
+pub fn audit_compile(
+    acir: Circuit,
+    np_language: Language,
+    is_blackbox_supported: impl Fn(&BlackBoxFunc) -> bool,
+    seed: u64,
+) -> Result<Circuit, AuditError> {
+    let in_order = compile(acir.clone(), np_language.clone(), &is_blackbox_supported);
+    let shuffled = with_shuffled_order(seed, || compile(acir, np_language, &is_blackbox_supported));
+    let (in_order, shuffled) = match (in_order, shuffled) {
+        (Ok(in_order), Ok(shuffled)) => (in_order, shuffled),
+        (Err(error), Err(_)) => return Err(AuditError::Compile(error)),
+        (Err(error), Ok(_)) => {
+            return Err(AuditError::CompileOutcomeMismatch {
+                failed_in_order: true,
+                error,
+            })
+        }
+        (Ok(_), Err(error)) => {
+            return Err(AuditError::CompileOutcomeMismatch {
+                failed_in_order: false,
+                error,
+            })
+        }
+    };
+
+    if let Some(difference) = circuit_difference(&in_order, &shuffled) {
+        return Err(AuditError::CircuitMismatch { difference });
+    }
+    Ok(in_order)
+}
+
+fn failed_order(failed_in_order: bool) -> &'static str {
+    if failed_in_order {
+        "in order"
+    } else {
+        "in a shuffled order"
+    }
+}
+
+// Describes the first difference between two circuits
+fn circuit_difference(in_order: &Circuit, shuffled: &Circuit) -> Option<String> {
+    if let Some((index, (lhs, rhs))) = in_order
+        .opcodes
+        .iter()
+        .zip(&shuffled.opcodes)
+        .enumerate()
+        .find(|(_, (lhs, rhs))| lhs != rhs)
+    {
+        return Some(format!("opcode {index} is {lhs:?} rather than {rhs:?}"));
+    }
+    if in_order.opcodes.len() != shuffled.opcodes.len() {
+        return Some(format!(
+            "it has {} opcodes rather than {}",
+            shuffled.opcodes.len(),
+            in_order.opcodes.len()
+        ));
+    }
+    if in_order.current_witness_index != shuffled.current_witness_index {
+        return Some(format!(
+            "its current witness index is {} rather than {}",
+            shuffled.current_witness_index, in_order.current_witness_index
+        ));
+    }
+    if in_order.public_inputs != shuffled.public_inputs {
+        return Some(format!(
+            "its public inputs are {:?} rather than {:?}",
+            shuffled.public_inputs, in_order.public_inputs
+        ));
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        pwg::NativeSolver,
+        testing::{fixtures, generate_satisfied_circuit},
+    };
+
+    #[test]
+    fn solving_and_compiling_are_deterministic() {
+        for fixture in fixtures() {
+            let mut witness = fixture.inputs.clone();
+            audit_solve(
+                &NativeSolver,
+                &mut witness,
+                fixture.circuit.opcodes.clone(),
+                1,
+            )
+            .unwrap();
+            assert_eq!(witness, fixture.witness);
+        }
+        for seed in 0..10 {
+            let generated = generate_satisfied_circuit(40, seed);
+            let mut witness = generated.inputs.clone();
+            audit_solve(
+                &NativeSolver,
+                &mut witness,
+                generated.circuit.opcodes.clone(),
+                seed,
+            )
+            .unwrap();
+            assert_eq!(witness, generated.witness);
+
+            for language in [
+                Language::R1CS,
+                Language::PLONKCSat { width: 3 },
+                Language::PLONKCSat { width: 4 },
+            ] {
+                let compiled =
+                    audit_compile(generated.circuit.clone(), language.clone(), |_| false, seed);
+                assert_eq!(
+                    compiled,
+                    Ok(compile(generated.circuit.clone(), language, |_| false).unwrap())
+                );
+            }
+        }
+    }
+
+    // Assigns the output of each black box call to the number of calls solved before it
+    #[derive(Default)]
+    struct CountingSolver(std::cell::Cell<u32>);
+
+    impl PartialWitnessGenerator for CountingSolver {
+        fn solve_blackbox_function_call(
+            &self,
+            initial_witness: &mut WitnessMap,
+            func_call: &acir::circuit::opcodes::BlackBoxFuncCall,
+        ) -> Result<(), OpcodeResolutionError> {
+            let count = self.0.replace(self.0.get() + 1);
+            initial_witness.insert(func_call.outputs[0], FieldElement::from(count as i128));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn differences_are_reported() {
+        let call = |output| {
+            Opcode::BlackBoxFuncCall(acir::circuit::opcodes::BlackBoxFuncCall {
+                name: BlackBoxFunc::Pedersen,
+                inputs: Vec::new(),
+                outputs: vec![Witness(output)],
+            })
+        };
+        let opcodes = vec![call(1), call(2)];
+        // The first seed which swaps the calls
+        let seed = (0..)
+            .find(|&seed| {
+                let mut shuffled = opcodes.clone();
+                shuffled.shuffle(&mut StdRng::seed_from_u64(seed));
+                shuffled != opcodes
+            })
+            .unwrap();
+        let result = audit_solve(
+            &CountingSolver::default(),
+            &mut WitnessMap::new(),
+            opcodes,
+            seed,
+        );
+        assert_eq!(
+            result,
+            Err(AuditError::WitnessMismatch {
+                witness: Witness(1),
+                in_order: Some(FieldElement::zero()),
+                shuffled: Some(FieldElement::from(3_i128)),
+            })
+        );
+
+        let in_order = Circuit::default();
+        let mut shuffled = Circuit::default();
+        assert_eq!(circuit_difference(&in_order, &shuffled), None);
+        shuffled.current_witness_index = 1;
+        assert_eq!(
+            circuit_difference(&in_order, &shuffled),
+            Some("its current witness index is 1 rather than 0".to_owned())
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::{collections::BTreeMap, ops::Index};
+
+use acir::{circuit::Circuit, native_types::Witness, FieldElement};
+
+use crate::WitnessMap;
+
+/// A circuit declared with `circuit!`, along with the names of its witnesses
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestCircuit {
+    pub circuit: Circuit,
+    pub witnesses: BTreeMap<&'static str, Witness>,
+}
+
+impl TestCircuit {
+    /// Returns the witness which was declared as `name`
+    ///
+    /// # Panics
+    ///
+    /// Panics if no witness was declared as `name`.
+    pub fn witness(&self, name: &str) -> Witness {
+        match self.witnesses.get(name) {
+            Some(witness) => *witness,
+            None => panic!("the circuit has no witness named {name}"),
+        }
+    }
+
+    /// Returns a witness map which assigns each value to the witness of the same name
+    ///
+    /// # Panics
+    ///
+    /// Panics if no witness was declared as one of the names.
+    pub fn inputs(&self, values: &[(&str, i128)]) -> WitnessMap {
+        values
+            .iter()
+            .map(|&(name, value)| (self.witness(name), FieldElement::from(value)))
+            .collect()
+    }
+}
+
+impl Index<&str> for TestCircuit {
+    type Output = Witness;
+
+    fn index(&self, name: &str) -> &Witness {
+        match self.witnesses.get(name) {
+            Some(witness) => witness,
+            None => panic!("the circuit has no witness named {name}"),
+        }
+    }
+}
+
+/// Declares a small circuit with named witnesses, using `builder::CircuitBuilder`, and
+/// returns it as a `TestCircuit`.
+///
+/// Each statement ends with a semicolon and is one of:
+/// - `public x, y;` which allocates public inputs
+/// - `private z;` which allocates witnesses
+/// - `let w = x * y + 1;` which allocates a witness constrained to the value of the right
+///   hand side
+/// - `x * y == z - 2;` which constrains both sides to be equal
+/// - `range(x, 8);` which constrains `x` to fit in 8 bits
+///
+/// Arithmetic is written with the operators on witnesses, expressions and field elements,
+/// so the left operand of `+` and `-` must be a witness or an expression. Integer literals
+/// are converted to field elements.
+///
+/// # Panics
+///
+/// Panics if the circuit fails `compiler::validate`, for example because a public input
+/// is not used.
+#[macro_export]
+macro_rules! circuit {
+    (@statements $builder:ident $witnesses:ident) => {};
+    (@statements $builder:ident $witnesses:ident public $($name:ident),+ ; $($rest:tt)*) => {
+        $(
+            let $name = $builder.add_public_input();
+            $witnesses.insert(stringify!($name), $name);
+        )+
+        $crate::circuit!(@statements $builder $witnesses $($rest)*);
+    };
+    (@statements $builder:ident $witnesses:ident private $($name:ident),+ ; $($rest:tt)*) => {
+        $(
+            let $name = $builder.add_witness();
+            $witnesses.insert(stringify!($name), $name);
+        )+
+        $crate::circuit!(@statements $builder $witnesses $($rest)*);
+    };
+    (@statements $builder:ident $witnesses:ident range($witness:ident, $num_bits:expr) ; $($rest:tt)*) => {
+        $builder.range($witness, $num_bits);
+        $crate::circuit!(@statements $builder $witnesses $($rest)*);
+    };
+    (@statements $builder:ident $witnesses:ident let $name:ident = $($rest:tt)*) => {
+        $crate::circuit!(@let $builder $witnesses $name [] $($rest)*);
+    };
+    (@statements $builder:ident $witnesses:ident $($rest:tt)+) => {
+        $crate::circuit!(@lhs $builder $witnesses [] $($rest)+);
+    };
+
+    // `let` statements, up to the semicolon
+    (@let $builder:ident $witnesses:ident $name:ident [$($value:tt)+] ; $($rest:tt)*) => {
+        let $name = $builder.evaluate($crate::__circuit_expr!([] $($value)+));
+        $witnesses.insert(stringify!($name), $name);
+        $crate::circuit!(@statements $builder $witnesses $($rest)*);
+    };
+    (@let $builder:ident $witnesses:ident $name:ident [$($value:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::circuit!(@let $builder $witnesses $name [$($value)* $next] $($rest)*);
+    };
+
+    // Constraints, up to the `==` and then up to the semicolon
+    (@lhs $builder:ident $witnesses:ident [$($lhs:tt)+] == $($rest:tt)*) => {
+        $crate::circuit!(@rhs $builder $witnesses [$($lhs)+] [] $($rest)*);
+    };
+    (@lhs $builder:ident $witnesses:ident [$($lhs:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::circuit!(@lhs $builder $witnesses [$($lhs)* $next] $($rest)*);
+    };
+    (@rhs $builder:ident $witnesses:ident [$($lhs:tt)+] [$($rhs:tt)+] ; $($rest:tt)*) => {
+        $builder.assert_equal(
+            $crate::__circuit_expr!([] $($lhs)+),
+            $crate::__circuit_expr!([] $($rhs)+),
+        );
+        $crate::circuit!(@statements $builder $witnesses $($rest)*);
+    };
+    (@rhs $builder:ident $witnesses:ident [$($lhs:tt)+] [$($rhs:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::circuit!(@rhs $builder $witnesses [$($lhs)+] [$($rhs)* $next] $($rest)*);
+    };
+
+    ($($statements:tt)*) => {{
+        let mut builder = $crate::builder::CircuitBuilder::new();
+        let mut witnesses = ::std::collections::BTreeMap::new();
+        $crate::circuit!(@statements builder witnesses $($statements)*);
+        match builder.finish() {
+            Ok(circuit) => $crate::testing::TestCircuit { circuit, witnesses },
+            Err(errors) => panic!("invalid circuit: {errors:?}"),
+        }
+    }};
+}
+
+// Converts the integer literals of an arithmetic expression in `circuit!` to field
+// elements, including those in parentheses
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __circuit_expr {
+    ([$($out:tt)*]) => { $($out)* };
+    // A minus sign is kept as an operator, rather than being parsed as part of a literal
+    ([$($out:tt)*] - $($rest:tt)*) => {
+        $crate::__circuit_expr!([$($out)* -] $($rest)*)
+    };
+    ([$($out:tt)*] $literal:literal $($rest:tt)*) => {
+        $crate::__circuit_expr!([$($out)* $crate::FieldElement::from($literal as i128)] $($rest)*)
+    };
+    ([$($out:tt)*] ($($inner:tt)*) $($rest:tt)*) => {
+        $crate::__circuit_expr!([$($out)* ($crate::__circuit_expr!([] $($inner)*))] $($rest)*)
+    };
+    ([$($out:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__circuit_expr!([$($out)* $next] $($rest)*)
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use acir::{
+        circuit::{Opcode, PublicInputs},
+        native_types::{Expression, Witness},
+        FieldElement,
+    };
+
+    use crate::{pwg::NativeSolver, PartialWitnessGenerator};
+
+    #[test]
+    fn circuits_are_built_from_statements() {
+        let test = crate::circuit! {
+            public x, y;
+            private z;
+            let w = x * y + 1;
+            z == w * 2 - (x - 3);
+            range(z, 8);
+        };
+        let x = test.witness("x");
+        let y = test.witness("y");
+        let z = test.witness("z");
+        let w = test.witness("w");
+        assert_eq!(
+            (x, y, z, w),
+            (Witness(1), Witness(2), Witness(3), Witness(4))
+        );
+        assert_eq!(test["w"], w);
+        assert_eq!(test.circuit.public_inputs, PublicInputs(vec![x, y]));
+        assert_eq!(test.circuit.current_witness_index, 4);
+
+        let three = FieldElement::from(3_i128);
+        let expected_w = x * y + FieldElement::one() - w;
+        let expected_z = Expression::from(z) - (w * FieldElement::from(2_i128) - (x - three));
+        assert_eq!(test.circuit.opcodes[0], Opcode::Arithmetic(expected_w));
+        assert_eq!(test.circuit.opcodes[1], Opcode::Arithmetic(expected_z));
+        assert_eq!(test.circuit.opcodes.len(), 3);
+
+        // z = 2 * (x * y + 1) - x + 3
+        let mut witness = test.inputs(&[("x", 5), ("y", 2), ("z", 20)]);
+        NativeSolver
+            .solve(&mut witness, test.circuit.opcodes.clone())
+            .unwrap();
+        assert_eq!(witness[&w], FieldElement::from(11_i128));
+
+        let mut witness = test.inputs(&[("x", 5), ("y", 2), ("z", 21)]);
+        assert!(NativeSolver
+            .solve(&mut witness, test.circuit.opcodes)
+            .is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid circuit")]
+    fn invalid_circuits_panic() {
+        crate::circuit! {
+            public x, y;
+            x == 1;
+        };
+    }
+}
@@ -0,0 +1,153 @@
+use acir::{circuit::Circuit, native_types::Witness, FieldElement};
+use rand::Rng;
+use thiserror::Error;
+
+use crate::{PartialWitnessGenerator, WitnessMap};
+
+#[derive(PartialEq, Eq, Debug, Error)]
+pub enum EquivalenceError {
+    #[error("trial {trial}: original circuit satisfied = {original}, transformed circuit satisfied = {transformed}")]
+    SatisfiabilityMismatch {
+        trial: usize,
+        inputs: WitnessMap,
+        original: bool,
+        transformed: bool,
+    },
+    #[error("trial {trial}: witness {} is {original} in the original circuit but {transformed} in the transformed circuit", witness.witness_index())]
+    WitnessMismatch {
+        trial: usize,
+        inputs: WitnessMap,
+        witness: Witness,
+        original: FieldElement,
+        transformed: FieldElement,
+    },
+}
+
+/// Checks that a transformed circuit behaves the same as the original circuit.
+///
+/// Both circuits are solved `num_trials` times using the same random assignment for `inputs`,
+/// where each value is less than 2^`input_bits`. The circuits must agree on whether the
+/// assignment satisfies them, and every witness of the original circuit which is assigned
+/// in both solutions must have the same value.
+///
+/// Witnesses above the original circuit's `current_witness_index` are intermediate variables
+/// introduced by the transformation and are not compared.
+pub fn check_equivalence<B: PartialWitnessGenerator, R: Rng>(
+    backend: &B,
+    original: &Circuit,
+    transformed: &Circuit,
+    inputs: &[Witness],
+    input_bits: u32,
+    num_trials: usize,
+    rng: &mut R,
+) -> Result<(), EquivalenceError> {
+    for trial in 0..num_trials {
+        let input_assignment: WitnessMap = inputs
+            .iter()
+            .map(|witness| (*witness, random_field_element(rng, input_bits)))
+            .collect();
+
+        let mut original_witness = input_assignment.clone();
+        let original_satisfied = backend
+            .solve(&mut original_witness, original.opcodes.clone())
+            .is_ok();
+
+        let mut transformed_witness = input_assignment.clone();
+        let transformed_satisfied = backend
+            .solve(&mut transformed_witness, transformed.opcodes.clone())
+            .is_ok();
+
+        if original_satisfied != transformed_satisfied {
+            return Err(EquivalenceError::SatisfiabilityMismatch {
+                trial,
+                inputs: input_assignment,
+                original: original_satisfied,
+                transformed: transformed_satisfied,
+            });
+        }
+
+        for (witness, original_value) in original_witness
+            .iter()
+            .take_while(|(witness, _)| witness.0 <= original.current_witness_index)
+        {
+            if let Some(transformed_value) = transformed_witness.get(witness) {
+                if original_value != transformed_value {
+                    return Err(EquivalenceError::WitnessMismatch {
+                        trial,
+                        inputs: input_assignment,
+                        witness: *witness,
+                        original: *original_value,
+                        transformed: *transformed_value,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Samples a field element uniformly from [0, 2^num_bits)
+pub(super) fn random_field_element<R: Rng>(rng: &mut R, num_bits: u32) -> FieldElement {
+    let num_bits = num_bits.min(FieldElement::max_num_bits() - 1);
+    let mut bytes = vec![0u8; FieldElement::max_num_bytes() as usize];
+    rng.fill(&mut bytes[..]);
+
+    // Mask off everything above `num_bits`. The bytes are big endian
+    let num_bytes = bytes.len();
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        let bit_offset = ((num_bytes - 1 - index) * 8) as u32;
+        if bit_offset >= num_bits {
+            *byte = 0;
+        } else if num_bits - bit_offset < 8 {
+            *byte &= (1u8 << (num_bits - bit_offset)) - 1;
+        }
+    }
+
+    FieldElement::from_be_bytes_reduce(&bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{compiler::compile, pwg::NativeSolver, Language};
+    use acir::{
+        circuit::{Opcode, PublicInputs},
+        native_types::Expression,
+    };
+    use rand::SeedableRng;
+
+    #[test]
+    fn csat_reduction_is_equivalent() {
+        // x5 = x1 + x2 + x3 * x4
+        let mut expr = Expression::default();
+        expr.term_addition(FieldElement::one(), Witness(1));
+        expr.term_addition(FieldElement::one(), Witness(2));
+        expr.term_multiplication(FieldElement::one(), Witness(3), Witness(4));
+        expr.term_addition(-FieldElement::one(), Witness(5));
+
+        let circuit = Circuit {
+            current_witness_index: 5,
+            opcodes: vec![Opcode::Arithmetic(expr)],
+            public_inputs: PublicInputs::default(),
+            return_values: PublicInputs::default(),
+        };
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let inputs = [Witness(1), Witness(2), Witness(3), Witness(4)];
+        for width in 3..=5 {
+            let transformed =
+                compile(circuit.clone(), Language::PLONKCSat { width }, |_| false).unwrap();
+            check_equivalence(
+                &NativeSolver,
+                &circuit,
+                &transformed,
+                &inputs,
+                64,
+                10,
+                &mut rng,
+            )
+            .unwrap();
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::{fs, io, path::Path};
+
+use acir::{
+    circuit::{directives::Directive, opcodes::FunctionInput, Circuit},
+    native_types::{Expression, Witness},
+    BlackBoxFunc, FieldElement,
+};
+use k256::{
+    ecdsa::{signature::Signer, Signature, SigningKey, VerifyingKey},
+    elliptic_curve::sec1::{Coordinates, ToEncodedPoint},
+};
+use sha2::{Digest, Sha256};
+
+use crate::{builder::CircuitBuilder, pwg::NativeSolver, PartialWitnessGenerator, WitnessMap};
+
+/// A test vector of a circuit which exercises one kind of opcode, with the inputs it is
+/// solved from and the full witness which solving it produces
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fixture {
+    pub name: &'static str,
+    pub circuit: Circuit,
+    pub inputs: WitnessMap,
+    pub witness: WitnessMap,
+}
+
+impl Fixture {
+    /// Writes the fixture into `dir`, creating it if necessary, as:
+    ///
+    /// - `<name>.acir`, the circuit written by `Circuit::write`
+    /// - `<name>.acir.txt`, the circuit in the textual format of `Circuit::to_text`
+    /// - `<name>.inputs` and `<name>.witness`, the inputs and the full witness written by
+    ///   `WitnessMap::write`
+    pub fn write_to(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let name = self.name;
+
+        let mut circuit = Vec::new();
+        self.circuit.write(&mut circuit)?;
+        fs::write(dir.join(format!("{name}.acir")), circuit)?;
+        fs::write(dir.join(format!("{name}.acir.txt")), self.circuit.to_text())?;
+
+        let mut inputs = Vec::new();
+        self.inputs.write(&mut inputs)?;
+        fs::write(dir.join(format!("{name}.inputs")), inputs)?;
+        let mut witness = Vec::new();
+        self.witness.write(&mut witness)?;
+        fs::write(dir.join(format!("{name}.witness")), witness)
+    }
+
+    /// Reads the fixture called `name` which `write_to` wrote into `dir`
+    pub fn read_from(dir: &Path, name: &'static str) -> io::Result<Fixture> {
+        Ok(Fixture {
+            name,
+            circuit: Circuit::read(fs::read(dir.join(format!("{name}.acir")))?.as_slice())?,
+            inputs: WitnessMap::read(fs::read(dir.join(format!("{name}.inputs")))?.as_slice())?,
+            witness: WitnessMap::read(fs::read(dir.join(format!("{name}.witness")))?.as_slice())?,
+        })
+    }
+}
+
+/// Returns a fixture for each kind of opcode which `pwg::NativeSolver` can solve: arithmetic
+/// gates, the black box functions in `pwg::NATIVE_BLACK_BOX_FUNCTIONS` and every directive
+/// other than oracles. The expected witnesses are solved with `NativeSolver`.
+///
+/// The fixtures are the same on every call, so they can be checked into other repositories.
+pub fn fixtures() -> Vec<Fixture> {
+    vec![
+        arithmetic(),
+        range(),
+        logic("and", BlackBoxFunc::AND),
+        logic("xor", BlackBoxFunc::XOR),
+        hash("sha256", BlackBoxFunc::SHA256),
+        hash("blake2s", BlackBoxFunc::Blake2s),
+        ecdsa_secp256k1(),
+        invert(),
+        quotient(),
+        truncate(),
+        odd_range(),
+        to_radix(),
+    ]
+}
+
+/// Writes every fixture returned by `fixtures` into `dir`, as `Fixture::write_to` does
+pub fn write_fixtures(dir: &Path) -> io::Result<()> {
+    fixtures()
+        .iter()
+        .try_for_each(|fixture| fixture.write_to(dir))
+}
+
+fn solved(name: &'static str, builder: CircuitBuilder, inputs: &[(Witness, i128)]) -> Fixture {
+    let inputs: WitnessMap = inputs
+        .iter()
+        .map(|&(witness, value)| (witness, FieldElement::from(value)))
+        .collect();
+    fixture(name, builder, inputs)
+}
+
+fn fixture(name: &'static str, builder: CircuitBuilder, inputs: WitnessMap) -> Fixture {
+    let circuit = builder.finish().expect("fixture circuits are well-formed");
+    let mut witness = inputs.clone();
+    NativeSolver
+        .solve(&mut witness, circuit.opcodes.clone())
+        .expect("fixture circuits are solvable");
+    Fixture {
+        name,
+        circuit,
+        inputs,
+        witness,
+    }
+}
+
+fn bytes(witnesses: &[Witness]) -> Vec<FunctionInput> {
+    witnesses
+        .iter()
+        .map(|&witness| FunctionInput {
+            witness,
+            num_bits: 8,
+        })
+        .collect()
+}
+
+fn arithmetic() -> Fixture {
+    let mut builder = CircuitBuilder::new();
+    let x = builder.add_public_input();
+    let y = builder.add_witness();
+    let z = builder.add_witness();
+    builder.evaluate(x * y * FieldElement::from(3_i128) + z - FieldElement::one());
+    solved("arithmetic", builder, &[(x, 5), (y, -7), (z, 11)])
+}
+
+fn range() -> Fixture {
+    let mut builder = CircuitBuilder::new();
+    let x = builder.add_public_input();
+    builder.range(x, 8);
+    solved("range", builder, &[(x, 255)])
+}
+
+fn logic(name: &'static str, func: BlackBoxFunc) -> Fixture {
+    let mut builder = CircuitBuilder::new();
+    let lhs = builder.add_public_input();
+    let rhs = builder.add_witness();
+    builder.black_box(func, bytes(&[lhs, rhs]));
+    solved(name, builder, &[(lhs, 0b1100_1010), (rhs, 0b0101_0110)])
+}
+
+fn hash(name: &'static str, func: BlackBoxFunc) -> Fixture {
+    let mut builder = CircuitBuilder::new();
+    let message = builder.add_witnesses(32);
+    builder.make_public(message[0]);
+    builder.black_box(func, bytes(&message));
+    let inputs: Vec<(Witness, i128)> = message
+        .iter()
+        .enumerate()
+        .map(|(i, &byte)| (byte, i as i128 * 7 + 3))
+        .collect();
+    solved(name, builder, &inputs)
+}
+
+// A valid signature of a fixed message with a fixed key
+fn ecdsa_secp256k1() -> Fixture {
+    let signing_key = SigningKey::from_bytes(&[2u8; 32]).expect("the key is a valid scalar");
+    let message = b"ACVM test vector";
+    let signature: Signature = signing_key.sign(message);
+    let hashed_message = Sha256::digest(message);
+    let public_key = VerifyingKey::from(&signing_key).to_encoded_point(false);
+    let (x, y) = match public_key.coordinates() {
+        Coordinates::Uncompressed { x, y } => (x.to_vec(), y.to_vec()),
+        _ => unreachable!("the point is uncompressed"),
+    };
+    let values: Vec<u8> = [
+        x.as_slice(),
+        y.as_slice(),
+        signature.as_ref(),
+        &hashed_message[..],
+    ]
+    .concat();
+
+    let mut builder = CircuitBuilder::new();
+    let witnesses = builder.add_witnesses(values.len());
+    builder.make_public(witnesses[0]);
+    builder.black_box(BlackBoxFunc::EcdsaSecp256k1, bytes(&witnesses));
+    let inputs = witnesses
+        .into_iter()
+        .zip(values)
+        .map(|(witness, value)| (witness, FieldElement::from(value as i128)))
+        .collect();
+    fixture("ecdsa_secp256k1", builder, inputs)
+}
+
+fn invert() -> Fixture {
+    let mut builder = CircuitBuilder::new();
+    let x = builder.add_public_input();
+    let result = builder.add_witness();
+    builder.directive(Directive::Invert { x, result });
+    solved("invert", builder, &[(x, 3)])
+}
+
+fn quotient() -> Fixture {
+    let mut builder = CircuitBuilder::new();
+    let a = builder.add_public_input();
+    let b = builder.add_witness();
+    let q = builder.add_witness();
+    let r = builder.add_witness();
+    builder.directive(Directive::Quotient {
+        a: a.into(),
+        b: b.into(),
+        q,
+        r,
+        predicate: None,
+    });
+    solved("quotient", builder, &[(a, 1000), (b, 7)])
+}
+
+fn truncate() -> Fixture {
+    let mut builder = CircuitBuilder::new();
+    let a = builder.add_public_input();
+    let b = builder.add_witness();
+    let c = builder.add_witness();
+    builder.directive(Directive::Truncate {
+        a: a.into(),
+        b,
+        c,
+        bit_size: 8,
+    });
+    solved("truncate", builder, &[(a, 1000)])
+}
+
+fn odd_range() -> Fixture {
+    let mut builder = CircuitBuilder::new();
+    let a = builder.add_public_input();
+    let b = builder.add_witness();
+    let r = builder.add_witness();
+    builder.directive(Directive::OddRange {
+        a,
+        b,
+        r,
+        bit_size: 8,
+    });
+    solved("odd_range", builder, &[(a, 200)])
+}
+
+fn to_radix() -> Fixture {
+    let mut builder = CircuitBuilder::new();
+    let a = builder.add_public_input();
+    let b = builder.add_witnesses(4);
+    builder.directive(Directive::ToRadix {
+        a: Expression::from(a),
+        b,
+        radix: 10,
+    });
+    solved("to_radix", builder, &[(a, 1234)])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fixtures_are_written_and_read_back() {
+        let fixtures = fixtures();
+        let dir = tempfile::tempdir().unwrap();
+        write_fixtures(dir.path()).unwrap();
+        for fixture in &fixtures {
+            assert_eq!(
+                &Fixture::read_from(dir.path(), fixture.name).unwrap(),
+                fixture
+            );
+            let text = fs::read_to_string(dir.path().join(format!("{}.acir.txt", fixture.name)));
+            assert_eq!(
+                Circuit::from_text(&text.unwrap()),
+                Ok(fixture.circuit.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn fixtures_have_the_expected_witnesses() {
+        let fixtures = fixtures();
+        assert_eq!(fixtures, super::fixtures());
+        let value = |name: &str, index: u32| {
+            let fixture = fixtures
+                .iter()
+                .find(|fixture| fixture.name == name)
+                .unwrap();
+            fixture.witness[&Witness(index)]
+        };
+        let field = |value: i128| FieldElement::from(value);
+
+        assert_eq!(value("arithmetic", 4), field(5 * -7 * 3 + 11 - 1));
+        assert_eq!(value("and", 3), field(0b0100_0010));
+        assert_eq!(value("xor", 3), field(0b1001_1100));
+        assert_eq!(value("ecdsa_secp256k1", 161), FieldElement::one());
+        assert_eq!(value("invert", 2) * field(3), FieldElement::one());
+        assert_eq!(
+            (value("quotient", 3), value("quotient", 4)),
+            (field(142), field(6))
+        );
+        assert_eq!(
+            (value("truncate", 2), value("truncate", 3)),
+            (field(232), field(3))
+        );
+        assert_eq!(
+            (value("odd_range", 2), value("odd_range", 3)),
+            (field(1), field(72))
+        );
+        let digits: Vec<FieldElement> = (2..6).map(|index| value("to_radix", index)).collect();
+        assert_eq!(digits, vec![field(4), field(3), field(2), field(1)]);
+    }
+}
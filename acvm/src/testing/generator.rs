@@ -0,0 +1,473 @@
+use acir::{
+    circuit::{
+        directives::Directive,
+        opcodes::{BlackBoxFuncCall, FunctionInput},
+        Circuit, Opcode, PublicInputs,
+    },
+    native_types::{Expression, Witness},
+    BlackBoxFunc, FieldElement,
+};
+use rand::{rngs::StdRng, seq::index::sample, Rng, SeedableRng};
+
+use super::equivalence::random_field_element;
+use crate::{
+    pwg::{get_value, NativeSolver},
+    PartialWitnessGenerator, WitnessMap,
+};
+
+/// The shape of a circuit built by `generate_circuit`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitSpec {
+    /// The number of arithmetic gates
+    pub num_arithmetic: usize,
+    /// The number of witnesses in each arithmetic gate, which must be at least 2
+    pub width: usize,
+    /// The number of range checks
+    pub num_range_checks: usize,
+    /// The number of bits allowed by each range check
+    pub range_bits: u32,
+    /// The number of SHA256 gadgets
+    pub num_sha256: usize,
+    /// The seed of the random coefficients, inputs and wiring. The same spec always
+    /// generates the same circuit and inputs.
+    pub seed: u64,
+}
+
+impl Default for CircuitSpec {
+    fn default() -> Self {
+        CircuitSpec {
+            num_arithmetic: 0,
+            width: 3,
+            num_range_checks: 0,
+            range_bits: 32,
+            num_sha256: 0,
+            seed: 0,
+        }
+    }
+}
+
+/// Builds a synthetic circuit of the shape given by `spec`, along with an assignment of
+/// its inputs which satisfies it.
+///
+/// Each arithmetic gate assigns a new witness from `width - 1` witnesses which are
+/// assigned before it, using random coefficients and a multiplication of two of them.
+/// The gates form a chain from the public inputs, so they must be solved in order.
+/// Each range check constrains a separate input which is in range. The first SHA256
+/// gadget hashes 32 input bytes and each of the others hashes the output of the one
+/// before it.
+///
+/// # Panics
+///
+/// Panics if `width` is less than 2 when there are arithmetic gates, or if `range_bits`
+/// is not less than the number of bits in the field.
+pub fn generate_circuit(spec: &CircuitSpec) -> (Circuit, WitnessMap) {
+    assert!(
+        spec.num_arithmetic == 0 || spec.width >= 2,
+        "arithmetic gates must have a width of at least 2"
+    );
+    assert!(
+        spec.range_bits < FieldElement::max_num_bits(),
+        "range checks must be smaller than the field"
+    );
+
+    let mut rng = StdRng::seed_from_u64(spec.seed);
+    let mut generator = Generator::default();
+
+    // Arithmetic gates, chained from the public inputs
+    let mut assigned = Vec::new();
+    if spec.num_arithmetic > 0 {
+        for _ in 1..spec.width {
+            assigned.push(generator.input(random_field_element(&mut rng, 64)));
+        }
+    }
+    let public_inputs = PublicInputs(assigned.clone());
+    for _ in 0..spec.num_arithmetic {
+        // Each gate depends on the most recent witness, so that the gates form a chain
+        let mut operands: Vec<Witness> = sample(&mut rng, assigned.len() - 1, spec.width - 2)
+            .into_iter()
+            .map(|index| assigned[index])
+            .collect();
+        operands.push(
+            *assigned
+                .last()
+                .expect("there are at least width - 1 witnesses"),
+        );
+
+        let mut expr = Expression::default();
+        if let [lhs, rhs, ..] = operands[..] {
+            expr.term_multiplication(random_coefficient(&mut rng), lhs, rhs);
+        }
+        for operand in operands {
+            expr.term_addition(random_coefficient(&mut rng), operand);
+        }
+        expr.q_c = random_field_element(&mut rng, 64);
+
+        let output = generator.witness();
+        expr.term_addition(-FieldElement::one(), output);
+        generator.opcodes.push(Opcode::Arithmetic(expr));
+        assigned.push(output);
+    }
+
+    // Range checks, each on its own input
+    for _ in 0..spec.num_range_checks {
+        let value = random_field_element(&mut rng, spec.range_bits);
+        let input = generator.input(value);
+        generator
+            .opcodes
+            .push(Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                name: BlackBoxFunc::RANGE,
+                inputs: vec![FunctionInput {
+                    witness: input,
+                    num_bits: spec.range_bits,
+                }],
+                outputs: Vec::new(),
+            }));
+    }
+
+    // SHA256 gadgets, each hashing the digest of the one before it
+    let mut message: Vec<Witness> = Vec::new();
+    if spec.num_sha256 > 0 {
+        message = (0..32)
+            .map(|_| generator.input(FieldElement::from(rng.gen_range(0..256_i128))))
+            .collect();
+    }
+    for _ in 0..spec.num_sha256 {
+        let digest: Vec<Witness> = (0..32).map(|_| generator.witness()).collect();
+        generator
+            .opcodes
+            .push(Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                name: BlackBoxFunc::SHA256,
+                inputs: message
+                    .iter()
+                    .map(|witness| FunctionInput {
+                        witness: *witness,
+                        num_bits: 8,
+                    })
+                    .collect(),
+                outputs: digest.clone(),
+            }));
+        message = digest;
+    }
+
+    let circuit = Circuit {
+        current_witness_index: generator.current_witness_index,
+        opcodes: generator.opcodes,
+        public_inputs,
+        return_values: PublicInputs::default(),
+    };
+    (circuit, generator.inputs)
+}
+
+/// A random circuit along with a witness which satisfies it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SatisfiedCircuit {
+    pub circuit: Circuit,
+    /// The assignments of the witnesses which no opcode assigns, which the rest of the
+    /// witness is solved from
+    pub inputs: WitnessMap,
+    /// The assignment of every witness of the circuit
+    pub witness: WitnessMap,
+}
+
+/// Builds a random circuit of at least `num_opcodes` opcodes, and at most one more, along
+/// with a full witness which satisfies it.
+///
+/// The circuit is built forwards from its inputs: each opcode only reads witnesses which
+/// are already assigned, and its outputs are assigned the values it constrains them to.
+/// The opcodes are arithmetic gates which assign a new witness or assert a relation between
+/// assigned ones, RANGE, AND and XOR calls on small inputs, and invert and quotient
+/// directives along with the gates which constrain their outputs. The same seed always
+/// generates the same circuit.
+pub fn generate_satisfied_circuit(num_opcodes: usize, seed: u64) -> SatisfiedCircuit {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut generator = Generator::default();
+    // The witnesses which are assigned, which later opcodes can read
+    let mut assigned = Vec::new();
+
+    // Every public input is used by the first gate
+    let mut public_inputs = Vec::new();
+    if num_opcodes > 0 {
+        let mut expr = Expression::default();
+        for _ in 0..rng.gen_range(1..=3) {
+            let input = generator.input(random_field_element(&mut rng, 64));
+            expr.term_addition(random_coefficient(&mut rng), input);
+            public_inputs.push(input);
+        }
+        assigned.extend(&public_inputs);
+        assigned.push(generator.evaluate(expr));
+    }
+
+    while generator.opcodes.len() < num_opcodes {
+        match rng.gen_range(0..8) {
+            // A gate which assigns a new witness
+            0..=2 => {
+                let expr = random_expression(&mut rng, &assigned);
+                assigned.push(generator.evaluate(expr));
+            }
+            // A gate which asserts a relation between assigned witnesses
+            3 => {
+                let mut expr = random_expression(&mut rng, &assigned);
+                expr.q_c -= generator.value(&expr);
+                generator.opcodes.push(Opcode::Arithmetic(expr));
+            }
+            // A range check of a small input
+            4 => {
+                let num_bits = rng.gen_range(1..=32);
+                let input = generator.input(random_field_element(&mut rng, num_bits));
+                generator.call(BlackBoxFunc::RANGE, &[(input, num_bits)], 0);
+            }
+            // AND or XOR of small inputs
+            5 => {
+                let func = [BlackBoxFunc::AND, BlackBoxFunc::XOR][rng.gen_range(0..2)];
+                let num_bits = rng.gen_range(1..=32);
+                let lhs = generator.input(random_field_element(&mut rng, num_bits));
+                let rhs = generator.input(random_field_element(&mut rng, num_bits));
+                let outputs = generator.call(func, &[(lhs, num_bits), (rhs, num_bits)], 1);
+                assigned.extend([lhs, rhs, outputs[0]]);
+            }
+            // The inverse of an assigned witness, and the gate x * inverse = 1
+            6 => {
+                let x = assigned[rng.gen_range(0..assigned.len())];
+                if generator.values[&x].is_zero() {
+                    continue;
+                }
+                let result = generator.witness();
+                generator.directive(Directive::Invert { x, result });
+                let mut expr = Expression::default();
+                expr.term_multiplication(FieldElement::one(), x, result);
+                expr.q_c = -FieldElement::one();
+                generator.opcodes.push(Opcode::Arithmetic(expr));
+                assigned.push(result);
+            }
+            // The quotient of two small witnesses, and the gate a = b * q + r
+            _ => {
+                let a = generator.input(random_field_element(&mut rng, 32));
+                let b =
+                    generator.input(FieldElement::from(rng.gen_range(1..=i128::from(u16::MAX))));
+                let (q, r) = (generator.witness(), generator.witness());
+                let directive = Directive::Quotient {
+                    a: Expression::from(&a),
+                    b: Expression::from(&b),
+                    q,
+                    r,
+                    predicate: None,
+                };
+                generator.directive(directive);
+                let mut expr = Expression::from(&a);
+                expr.term_multiplication(-FieldElement::one(), b, q);
+                expr.term_addition(-FieldElement::one(), r);
+                generator.opcodes.push(Opcode::Arithmetic(expr));
+                assigned.extend([a, b, q, r]);
+            }
+        }
+    }
+
+    let circuit = Circuit {
+        current_witness_index: generator.current_witness_index,
+        opcodes: generator.opcodes,
+        public_inputs: PublicInputs(public_inputs),
+        return_values: PublicInputs::default(),
+    };
+    SatisfiedCircuit {
+        circuit,
+        inputs: generator.inputs,
+        witness: generator.values,
+    }
+}
+
+// An expression with at most one multiplication of assigned witnesses, and up to three
+// linear terms of them
+fn random_expression<R: Rng>(rng: &mut R, assigned: &[Witness]) -> Expression {
+    let pick = |rng: &mut R| assigned[rng.gen_range(0..assigned.len())];
+    let mut expr = Expression::default();
+    if rng.gen_bool(0.5) {
+        let (lhs, rhs) = (pick(rng), pick(rng));
+        expr.term_multiplication(random_coefficient(rng), lhs, rhs);
+    }
+    for _ in 0..rng.gen_range(1..=3) {
+        let term = pick(rng);
+        expr.term_addition(random_coefficient(rng), term);
+    }
+    expr.q_c = random_field_element(rng, 64);
+    expr
+}
+
+#[derive(Default)]
+struct Generator {
+    current_witness_index: u32,
+    opcodes: Vec<Opcode>,
+    inputs: WitnessMap,
+    // The values of the inputs and of the witnesses assigned by opcodes
+    values: WitnessMap,
+}
+
+impl Generator {
+    fn witness(&mut self) -> Witness {
+        self.current_witness_index += 1;
+        Witness(self.current_witness_index)
+    }
+
+    fn input(&mut self, value: FieldElement) -> Witness {
+        let witness = self.witness();
+        self.inputs.insert(witness, value);
+        self.values.insert(witness, value);
+        witness
+    }
+
+    fn value(&self, expr: &Expression) -> FieldElement {
+        get_value(expr, &self.values).expect("expressions only use assigned witnesses")
+    }
+
+    // Adds a gate which assigns the value of `expr` to a new witness
+    fn evaluate(&mut self, mut expr: Expression) -> Witness {
+        let value = self.value(&expr);
+        let output = self.witness();
+        expr.term_addition(-FieldElement::one(), output);
+        self.opcodes.push(Opcode::Arithmetic(expr));
+        self.values.insert(output, value);
+        output
+    }
+
+    // Adds a black box call with new outputs, which are solved natively
+    fn call(
+        &mut self,
+        name: BlackBoxFunc,
+        inputs: &[(Witness, u32)],
+        num_outputs: usize,
+    ) -> Vec<Witness> {
+        let outputs: Vec<Witness> = (0..num_outputs).map(|_| self.witness()).collect();
+        let func_call = BlackBoxFuncCall {
+            name,
+            inputs: inputs
+                .iter()
+                .map(|&(witness, num_bits)| FunctionInput { witness, num_bits })
+                .collect(),
+            outputs: outputs.clone(),
+        };
+        self.solve(Opcode::BlackBoxFuncCall(func_call));
+        outputs
+    }
+
+    // Adds a directive, whose outputs are solved natively
+    fn directive(&mut self, directive: Directive) {
+        self.solve(Opcode::Directive(directive));
+    }
+
+    fn solve(&mut self, opcode: Opcode) {
+        NativeSolver
+            .solve(&mut self.values, vec![opcode.clone()])
+            .expect("generated opcodes are satisfied");
+        self.opcodes.push(opcode);
+    }
+}
+
+// Coefficients are non-zero, so that every term is kept by the optimiser
+fn random_coefficient<R: Rng>(rng: &mut R) -> FieldElement {
+    FieldElement::from(rng.gen_range(1..=i128::from(u32::MAX)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        compiler::{compile, stats, validate},
+        pwg::NativeSolver,
+        Language, PartialWitnessGenerator,
+    };
+
+    fn spec() -> CircuitSpec {
+        CircuitSpec {
+            num_arithmetic: 50,
+            width: 4,
+            num_range_checks: 5,
+            range_bits: 16,
+            num_sha256: 3,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn generated_circuits_are_satisfied_by_their_inputs() {
+        let (circuit, inputs) = generate_circuit(&spec());
+
+        let circuit_stats = stats(&circuit);
+        assert_eq!(circuit_stats.num_arithmetic, 50);
+        assert_eq!(circuit_stats.max_expression_width, 4);
+        assert_eq!(circuit_stats.blackbox_calls["range"], 5);
+        assert_eq!(circuit_stats.blackbox_calls["sha256"], 3);
+        assert_eq!(circuit.public_inputs.0.len(), 3);
+
+        let mut witness = inputs.clone();
+        NativeSolver
+            .solve(&mut witness, circuit.opcodes.clone())
+            .unwrap();
+        assert_eq!(witness.len() as u32, circuit.current_witness_index);
+
+        // The compiled circuit is satisfied by the same inputs
+        let compiled =
+            compile(circuit.clone(), Language::PLONKCSat { width: 3 }, |_| true).unwrap();
+        let mut compiled_witness = inputs;
+        NativeSolver
+            .solve(&mut compiled_witness, compiled.opcodes)
+            .unwrap();
+        for (witness, value) in witness.iter() {
+            assert_eq!(compiled_witness[witness], *value);
+        }
+    }
+
+    #[test]
+    fn generation_is_reproducible() {
+        assert_eq!(generate_circuit(&spec()), generate_circuit(&spec()));
+        let (other, _) = generate_circuit(&CircuitSpec { seed: 8, ..spec() });
+        assert_ne!(generate_circuit(&spec()).0, other);
+    }
+
+    #[test]
+    fn satisfied_circuits_are_satisfied_by_their_witness() {
+        for seed in 0..20 {
+            let generated = generate_satisfied_circuit(100, seed);
+            let circuit = &generated.circuit;
+            assert!((100..=101).contains(&circuit.opcodes.len()));
+            assert_eq!(validate(circuit), Ok(()));
+            assert_eq!(
+                generated.witness.len() as u32,
+                circuit.current_witness_index
+            );
+
+            let mut witness = generated.inputs.clone();
+            NativeSolver
+                .solve(&mut witness, circuit.opcodes.clone())
+                .unwrap();
+            assert_eq!(witness, generated.witness);
+
+            // Compiling the circuit keeps the values of its witnesses
+            let compiled =
+                compile(circuit.clone(), Language::PLONKCSat { width: 3 }, |_| true).unwrap();
+            let mut compiled_witness = generated.inputs;
+            NativeSolver
+                .solve(&mut compiled_witness, compiled.opcodes)
+                .unwrap();
+            for (witness, value) in generated.witness.iter() {
+                assert_eq!(compiled_witness[witness], *value);
+            }
+        }
+        assert_eq!(
+            generate_satisfied_circuit(50, 3),
+            generate_satisfied_circuit(50, 3)
+        );
+        let empty = generate_satisfied_circuit(0, 0);
+        assert_eq!(empty.circuit, Circuit::default());
+        assert!(empty.witness.is_empty());
+    }
+
+    #[test]
+    fn out_of_range_inputs_are_rejected() {
+        let (circuit, mut inputs) = generate_circuit(&CircuitSpec {
+            num_range_checks: 1,
+            range_bits: 8,
+            ..CircuitSpec::default()
+        });
+        inputs.insert(Witness(1), FieldElement::from(256_i128));
+        assert!(NativeSolver.solve(&mut inputs, circuit.opcodes).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use acir::circuit::{Circuit, Opcode};
+
+/// Shrinks a failing circuit into a small circuit which fails in the same way.
+///
+/// `fails` is called on candidate circuits and should return true if the candidate still
+/// reproduces the failure, for example if solving it with the same inputs returns
+/// `UnsatisfiedConstrain`, or if a backend rejects it. It must return true for `circuit`.
+///
+/// Opcodes are removed using delta debugging, so the returned circuit is 1-minimal:
+/// removing any single opcode from it makes the failure disappear.
+/// The witness count and public inputs of the circuit are left unchanged.
+pub fn minimize<F>(circuit: Circuit, mut fails: F) -> Circuit
+where
+    F: FnMut(&Circuit) -> bool,
+{
+    debug_assert!(fails(&circuit), "the circuit must fail before minimizing");
+
+    let Circuit {
+        current_witness_index,
+        mut opcodes,
+        public_inputs,
+        return_values,
+    } = circuit;
+
+    let with_opcodes = |opcodes: Vec<Opcode>| Circuit {
+        current_witness_index,
+        opcodes,
+        public_inputs: public_inputs.clone(),
+        return_values: return_values.clone(),
+    };
+
+    let mut num_chunks = 2;
+    while opcodes.len() >= 2 {
+        let chunk_size = opcodes.len().div_ceil(num_chunks);
+
+        // Try removing each chunk in turn, keeping the first removal which still fails
+        let reduced = (0..opcodes.len()).step_by(chunk_size).find_map(|start| {
+            let end = (start + chunk_size).min(opcodes.len());
+            let mut complement = opcodes[..start].to_vec();
+            complement.extend_from_slice(&opcodes[end..]);
+
+            let candidate = with_opcodes(complement);
+            fails(&candidate).then_some(candidate.opcodes)
+        });
+
+        match reduced {
+            Some(reduced) => {
+                opcodes = reduced;
+                num_chunks = (num_chunks - 1).max(2);
+            }
+            // Each chunk is a single opcode, so no opcode can be removed
+            None if chunk_size == 1 => break,
+            None => num_chunks = (num_chunks * 2).min(opcodes.len()),
+        }
+    }
+
+    with_opcodes(opcodes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{pwg::NativeSolver, OpcodeResolutionError, PartialWitnessGenerator, WitnessMap};
+    use acir::{
+        circuit::PublicInputs,
+        native_types::{Expression, Witness},
+        FieldElement,
+    };
+
+    // x_{i+1} = x_i + 1
+    fn increment(i: u32) -> Opcode {
+        let mut expr = Expression::from(&Witness(i));
+        expr.term_addition(-FieldElement::one(), Witness(i + 1));
+        expr.q_c = FieldElement::one();
+        Opcode::Arithmetic(expr)
+    }
+
+    #[test]
+    fn minimizes_unsatisfied_circuit() {
+        let mut opcodes: Vec<Opcode> = (1..=20).map(increment).collect();
+
+        // x_3 = 100 is unsatisfied when x_1 = 0, while the other witnesses are unconstrained
+        let mut failing = Expression::from(&Witness(3));
+        failing.q_c = -FieldElement::from(100_i128);
+        opcodes.insert(12, Opcode::Arithmetic(failing.clone()));
+
+        let circuit = Circuit {
+            current_witness_index: 21,
+            opcodes,
+            public_inputs: PublicInputs::default(),
+            return_values: PublicInputs::default(),
+        };
+
+        let fails = |circuit: &Circuit| {
+            let mut witness = WitnessMap::from([(Witness(1), FieldElement::zero())]);
+            NativeSolver.solve(&mut witness, circuit.opcodes.clone())
+                == Err(OpcodeResolutionError::UnsatisfiedConstrain)
+        };
+
+        let minimized = minimize(circuit, fails);
+        assert_eq!(
+            minimized.opcodes,
+            vec![increment(1), increment(2), Opcode::Arithmetic(failing)]
+        );
+    }
+}
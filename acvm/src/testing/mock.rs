@@ -0,0 +1,492 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use acir::{
+    circuit::{opcodes::BlackBoxFuncCall, Circuit},
+    BlackBoxFunc, FieldElement,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    circuit_digest,
+    compiler::{GateWeights, ProvingTimeModel},
+    public_input_values, pwg, Backend, BackendError, Contract, ContractGenError, ContractOptions,
+    ContractPlatform, FieldModulus, Language, OpcodeResolutionError, PartialWitnessGenerator,
+    ProofSystemCompiler, ProvingKey, SmartContract, VerificationKey, WitnessMap,
+};
+
+/// A backend for tests which does not depend on a proving library.
+///
+/// Proving checks that the witness satisfies the circuit, and the "proof" is the
+/// circuit digest followed by a SHA256 digest of the public inputs. Verification
+/// recomputes it. The proofs are not zero knowledge and provide no soundness.
+///
+/// Black box functions are solved using the solvers in `pwg`. The supported functions
+/// can be restricted with `with_supported_opcodes` to simulate a more limited backend.
+#[derive(Debug, Clone)]
+pub struct MockBackend {
+    language: Language,
+    supported_opcodes: HashSet<BlackBoxFunc>,
+    proving_time_model: Option<ProvingTimeModel>,
+    gate_weights: GateWeights,
+    lookup_table_bits: Option<u32>,
+    field: FieldModulus,
+    // Shared with clones, so that wrappers which clone the backend are counted too
+    num_preprocessed: Arc<AtomicUsize>,
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        MockBackend {
+            language: Language::PLONKCSat { width: 3 },
+            supported_opcodes: HashSet::from(pwg::NATIVE_BLACK_BOX_FUNCTIONS),
+            proving_time_model: None,
+            gate_weights: GateWeights::default(),
+            lookup_table_bits: None,
+            field: FieldModulus::acvm(),
+            num_preprocessed: Arc::default(),
+        }
+    }
+}
+
+impl MockBackend {
+    /// Creates a width 3 PLONK backend which supports every function it can solve
+    pub fn new() -> Self {
+        MockBackend::default()
+    }
+
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Restricts the black box functions which the backend reports as supported and solves.
+    ///
+    /// Functions which `MockBackend` cannot solve are reported as supported, but fail to solve.
+    pub fn with_supported_opcodes(
+        mut self,
+        opcodes: impl IntoIterator<Item = BlackBoxFunc>,
+    ) -> Self {
+        self.supported_opcodes = opcodes.into_iter().collect();
+        self
+    }
+
+    /// Sets the proving time model which the backend reports
+    pub fn with_proving_time_model(mut self, model: ProvingTimeModel) -> Self {
+        self.proving_time_model = Some(model);
+        self
+    }
+
+    /// Sets the gate weights which the backend reports
+    pub fn with_gate_weights(mut self, weights: GateWeights) -> Self {
+        self.gate_weights = weights;
+        self
+    }
+
+    /// Sets the size of the lookup tables which the backend reports
+    pub fn with_lookup_table_bits(mut self, table_bits: u32) -> Self {
+        self.lookup_table_bits = Some(table_bits);
+        self
+    }
+
+    /// Sets the field which the backend reports, to simulate a backend which ACVM rejects
+    pub fn with_field(mut self, field: FieldModulus) -> Self {
+        self.field = field;
+        self
+    }
+
+    /// Returns the number of circuits which the backend and its clones have preprocessed
+    pub fn num_preprocessed(&self) -> usize {
+        self.num_preprocessed.load(Ordering::SeqCst)
+    }
+
+    fn check_satisfied(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+    ) -> Result<(), BackendError> {
+        pwg::check_satisfied(self, circuit, witness_values).map_err(|err| {
+            BackendError::Other(format!("witness does not satisfy the circuit: {err}"))
+        })
+    }
+
+    fn proof(circuit: &Circuit, public_inputs: &[FieldElement]) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        for value in public_inputs {
+            hasher.update(value.to_be_bytes());
+        }
+        let mut proof = circuit_digest(circuit).to_vec();
+        proof.extend(hasher.finalize());
+        proof
+    }
+
+    fn check_key(circuit: &Circuit, key: &[u8]) -> Result<(), BackendError> {
+        if key == circuit_digest(circuit) {
+            Ok(())
+        } else {
+            Err(BackendError::Other(
+                "key was derived for a different circuit".to_owned(),
+            ))
+        }
+    }
+}
+
+impl Backend for MockBackend {}
+
+impl PartialWitnessGenerator for MockBackend {
+    fn solve_blackbox_function_call(
+        &self,
+        initial_witness: &mut WitnessMap,
+        func_call: &BlackBoxFuncCall,
+    ) -> Result<(), OpcodeResolutionError> {
+        if !self.supported_opcodes.contains(&func_call.name) {
+            return Err(OpcodeResolutionError::UnsupportedBlackBoxFunc(
+                func_call.name,
+            ));
+        }
+        pwg::solve_native_black_box(initial_witness, func_call)
+    }
+}
+
+impl SmartContract for MockBackend {
+    fn supported_platforms(&self) -> HashSet<ContractPlatform> {
+        HashSet::from([ContractPlatform::EvmSolidity, ContractPlatform::EvmYul])
+    }
+
+    fn contract_from_cs(
+        &self,
+        circuit: &Circuit,
+        platform: ContractPlatform,
+        options: &ContractOptions,
+    ) -> Result<Contract, ContractGenError> {
+        let (_, verification_key) = self.preprocess(circuit)?;
+        self.verifier_contract(&verification_key, platform, options)
+    }
+
+    fn verifier_contract(
+        &self,
+        verification_key: &VerificationKey,
+        platform: ContractPlatform,
+        options: &ContractOptions,
+    ) -> Result<Contract, ContractGenError> {
+        options.validate()?;
+        let name = &options.contract_name;
+        let digest = if options.inline_verification_key {
+            hex::encode(&verification_key.0)
+        } else {
+            "supplied at deployment".to_owned()
+        };
+        let source = match platform {
+            ContractPlatform::EvmSolidity => format!(
+                "{}// Mock verifier for the circuit with digest {digest}\ncontract {name} {{}}\n",
+                options.solidity_header()
+            ),
+            ContractPlatform::EvmYul => format!(
+                "// Mock verifier for the circuit with digest {digest}\nobject \"{name}\" {{ code {{}} }}\n"
+            ),
+            _ => return Err(ContractGenError::UnsupportedPlatform(platform)),
+        };
+        Ok(Contract { platform, source })
+    }
+
+    fn eth_contract_from_cs(
+        &self,
+        circuit: &Circuit,
+        options: &ContractOptions,
+    ) -> Result<String, ContractGenError> {
+        self.contract_from_cs(circuit, ContractPlatform::EvmSolidity, options)
+            .map(|contract| contract.source)
+    }
+}
+
+impl ProofSystemCompiler for MockBackend {
+    fn np_language(&self) -> Language {
+        self.language.clone()
+    }
+
+    fn name(&self) -> &str {
+        "mock_backend"
+    }
+
+    fn np_field(&self) -> FieldModulus {
+        self.field.clone()
+    }
+
+    fn blackbox_function_supported(&self, opcode: &BlackBoxFunc) -> bool {
+        self.supported_opcodes.contains(opcode)
+    }
+
+    fn supported_opcodes(&self) -> HashSet<BlackBoxFunc> {
+        self.supported_opcodes.clone()
+    }
+
+    fn proving_time_model(&self) -> Option<ProvingTimeModel> {
+        self.proving_time_model.clone()
+    }
+
+    fn lookup_table_bits(&self) -> Option<u32> {
+        self.lookup_table_bits
+    }
+
+    fn gate_weights(&self) -> GateWeights {
+        self.gate_weights.clone()
+    }
+
+    fn prove_with_meta(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+    ) -> Result<Vec<u8>, BackendError> {
+        self.check_satisfied(circuit, witness_values)?;
+        let public_inputs = public_input_values(circuit, witness_values)
+            .map_err(|err| BackendError::Other(err.to_string()))?;
+        Ok(MockBackend::proof(circuit, &public_inputs))
+    }
+
+    fn verify_from_cs(
+        &self,
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+    ) -> Result<bool, BackendError> {
+        Ok(proof == MockBackend::proof(circuit, &public_inputs))
+    }
+
+    fn get_exact_circuit_size(&self, circuit: &Circuit) -> Result<u32, BackendError> {
+        Ok(circuit.opcodes.len() as u32)
+    }
+
+    fn preprocess(&self, circuit: &Circuit) -> Result<(ProvingKey, VerificationKey), BackendError> {
+        self.num_preprocessed.fetch_add(1, Ordering::SeqCst);
+        let digest = circuit_digest(circuit).to_vec();
+        Ok((ProvingKey(digest.clone()), VerificationKey(digest)))
+    }
+
+    fn prove_with_pk(
+        &self,
+        circuit: &Circuit,
+        witness_values: &WitnessMap,
+        proving_key: &ProvingKey,
+    ) -> Result<Vec<u8>, BackendError> {
+        MockBackend::check_key(circuit, &proving_key.0)?;
+        self.prove_with_meta(circuit, witness_values)
+    }
+
+    fn verify_with_vk(
+        &self,
+        proof: &[u8],
+        public_inputs: Vec<FieldElement>,
+        circuit: &Circuit,
+        verification_key: &VerificationKey,
+    ) -> Result<bool, BackendError> {
+        MockBackend::check_key(circuit, &verification_key.0)?;
+        self.verify_from_cs(proof, public_inputs, circuit)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{
+        circuit::{
+            opcodes::{FunctionInput, Opcode},
+            PublicInputs,
+        },
+        native_types::{Expression, Witness},
+    };
+
+    // Proves knowledge of x1 such that x1 AND 0xf0 = x2, where x2 is public
+    fn circuit() -> Circuit {
+        Circuit {
+            current_witness_index: 3,
+            opcodes: vec![
+                Opcode::Arithmetic(
+                    &Expression::from(&Witness(2))
+                        - &Expression::from_field(FieldElement::from(0xf0_i128)),
+                ),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::AND,
+                    inputs: vec![
+                        FunctionInput {
+                            witness: Witness(1),
+                            num_bits: 8,
+                        },
+                        FunctionInput {
+                            witness: Witness(2),
+                            num_bits: 8,
+                        },
+                    ],
+                    outputs: vec![Witness(3)],
+                }),
+            ],
+            public_inputs: PublicInputs(vec![Witness(3)]),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    fn solved_witness(backend: &MockBackend, x: i128) -> WitnessMap {
+        let mut witness = WitnessMap::from([(Witness(1), FieldElement::from(x))]);
+        backend.solve(&mut witness, circuit().opcodes).unwrap();
+        witness
+    }
+
+    #[test]
+    fn proofs_verify_against_their_public_inputs() {
+        let backend = MockBackend::new();
+        let circuit = circuit();
+        let witness = solved_witness(&backend, 0x3c);
+        assert_eq!(witness[&Witness(3)], FieldElement::from(0x30_i128));
+
+        let (pk, vk) = backend.preprocess(&circuit).unwrap();
+        let proof = backend.prove_with_pk(&circuit, &witness, &pk).unwrap();
+        assert_eq!(
+            backend.verify_with_vk(&proof, vec![FieldElement::from(0x30_i128)], &circuit, &vk),
+            Ok(true)
+        );
+        assert_eq!(
+            backend.verify_with_vk(&proof, vec![FieldElement::from(0x31_i128)], &circuit, &vk),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn contracts_are_generated_from_verification_keys() {
+        let backend = MockBackend::new();
+        let circuit = circuit();
+        let (_, vk) = backend.preprocess(&circuit).unwrap();
+
+        let options = ContractOptions::default();
+
+        let contract = backend
+            .verifier_contract(&vk, ContractPlatform::EvmSolidity, &options)
+            .unwrap();
+        assert_eq!(
+            Ok(contract.source.clone()),
+            backend.eth_contract_from_cs(&circuit, &options)
+        );
+        assert!(contract.source.starts_with(&options.solidity_header()));
+        assert!(contract
+            .source
+            .contains(&hex::encode(circuit_digest(&circuit))));
+
+        for platform in backend.supported_platforms() {
+            assert_eq!(
+                backend
+                    .contract_from_cs(&circuit, platform, &options)
+                    .unwrap()
+                    .platform,
+                platform
+            );
+        }
+        assert_eq!(
+            backend.contract_from_cs(&circuit, ContractPlatform::FuelSway, &options),
+            Err(ContractGenError::UnsupportedPlatform(
+                ContractPlatform::FuelSway
+            ))
+        );
+
+        let yul = backend.yul_contract_from_cs(&circuit, &options).unwrap();
+        assert!(yul.contains(&format!("object \"{}\"", options.contract_name)));
+    }
+
+    #[test]
+    fn contract_options_are_honoured() {
+        let backend = MockBackend::new();
+        let circuit = circuit();
+
+        let options = ContractOptions {
+            contract_name: "CircuitVerifier".to_owned(),
+            inline_verification_key: false,
+            ..ContractOptions::default()
+        };
+        let source = backend.eth_contract_from_cs(&circuit, &options).unwrap();
+        assert!(source.contains("contract CircuitVerifier"));
+        assert!(!source.contains(&hex::encode(circuit_digest(&circuit))));
+
+        let invalid = ContractOptions {
+            contract_name: "Circuit Verifier".to_owned(),
+            ..ContractOptions::default()
+        };
+        assert!(matches!(
+            backend.eth_contract_from_cs(&circuit, &invalid),
+            Err(ContractGenError::InvalidOption(_))
+        ));
+    }
+
+    #[test]
+    fn unsatisfying_witnesses_are_rejected() {
+        let backend = MockBackend::new();
+        let mut witness = solved_witness(&backend, 0x3c);
+        witness.insert(Witness(3), FieldElement::from(0x31_i128));
+
+        assert!(matches!(
+            backend.prove_with_meta(&circuit(), &witness),
+            Err(BackendError::Other(_))
+        ));
+    }
+
+    #[test]
+    fn supported_opcodes_are_honoured() {
+        let backend = MockBackend::new().with_supported_opcodes([BlackBoxFunc::RANGE]);
+        assert!(!backend.blackbox_function_supported(&BlackBoxFunc::AND));
+
+        let unsupported = || OpcodeResolutionError::UnsupportedBlackBoxFuncCall {
+            opcode_index: 1,
+            call: match &circuit().opcodes[1] {
+                Opcode::BlackBoxFuncCall(call) => call.clone(),
+                _ => unreachable!("the second opcode is an AND call"),
+            },
+        };
+        let mut witness = WitnessMap::from([(Witness(1), FieldElement::from(0x3c_i128))]);
+        assert_eq!(
+            backend.solve_supported(&mut witness, circuit().opcodes),
+            Err(unsupported())
+        );
+
+        // Solving without checking first fails at the same opcode
+        assert_eq!(
+            backend.solve(&mut witness, circuit().opcodes),
+            Err(unsupported())
+        );
+        assert!(unsupported()
+            .to_string()
+            .starts_with("backend does not support the and opcode, which is called by opcode 1"));
+    }
+
+    #[test]
+    fn other_fields_are_rejected() {
+        use crate::{
+            compiler::compile_for_backend,
+            key_cache::{CachingBackend, KeyCache},
+        };
+
+        let field = if FieldModulus::acvm() == FieldModulus::bn254() {
+            FieldModulus::bls12_381()
+        } else {
+            FieldModulus::bn254()
+        };
+        let backend = MockBackend::new().with_field(field.clone());
+        let mismatch = || BackendError::FieldMismatch {
+            backend: field.clone(),
+            acvm: FieldModulus::acvm(),
+        };
+
+        assert_eq!(compile_for_backend(circuit(), &backend), Err(mismatch()));
+        let mut witness = WitnessMap::from([(Witness(1), FieldElement::from(0x3c_i128))]);
+        assert_eq!(
+            backend.solve_supported(&mut witness, circuit().opcodes),
+            Err(OpcodeResolutionError::Backend(mismatch()))
+        );
+        assert_eq!(witness.len(), 1);
+        assert_eq!(backend.prove_batch(&circuit(), &[witness]), Err(mismatch()));
+
+        let caching = CachingBackend::new(backend, KeyCache::new());
+        assert_eq!(caching.preprocess(&circuit()), Err(mismatch()));
+        assert_eq!(caching.into_inner().num_preprocessed(), 0);
+    }
+}
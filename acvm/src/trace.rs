@@ -0,0 +1,132 @@
+// Structured logging of the solver and compiler, behind the `tracing` feature
+//
+// Spans and events are emitted with these macros, which take a `tracing::Level` name
+// followed by the arguments of `tracing::span!` and `tracing::event!`. Without the
+// feature they expand to nothing, so call sites do not need to be configured.
+//
+// The solver opens a `solve` span for each function, with a `black_box` or `directive`
+// span for each attempt to solve one of those opcodes. The compiler opens a `compile`
+// span, with a span for each of its passes.
+
+#[cfg(feature = "tracing")]
+macro_rules! span {
+    ($level:ident, $($arg:tt)+) => {
+        ::tracing::span!(::tracing::Level::$level, $($arg)+).entered()
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! span {
+    ($level:ident, $($arg:tt)+) => {
+        $crate::trace::Disabled
+    };
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {
+        ::tracing::event!(::tracing::Level::$level, $($arg)+)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {{}};
+}
+
+pub(crate) use event;
+pub(crate) use span;
+
+// Stands in for an entered span when tracing is disabled
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct Disabled;
+
+#[cfg(all(test, feature = "tracing"))]
+mod test {
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    };
+
+    use acir::{
+        circuit::{
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            Circuit, Opcode, PublicInputs,
+        },
+        native_types::{Expression, Witness},
+        BlackBoxFunc, FieldElement,
+    };
+    use tracing::{
+        span::{Attributes, Id, Record},
+        Event, Metadata, Subscriber,
+    };
+
+    use crate::{compiler, pwg::NativeSolver, Language, PartialWitnessGenerator, WitnessMap};
+
+    // Records the names of the spans which are created
+    #[derive(Default)]
+    struct Recorder {
+        spans: Arc<Mutex<Vec<String>>>,
+        next_id: AtomicU64,
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            self.spans
+                .lock()
+                .unwrap()
+                .push(span.metadata().name().to_owned());
+            Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn solving_and_compiling_are_traced() {
+        let (x, y) = (Witness(1), Witness(2));
+        let circuit = Circuit {
+            current_witness_index: 2,
+            opcodes: vec![
+                Opcode::Arithmetic(&Expression::from(x) - &Expression::from(y)),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall {
+                    name: BlackBoxFunc::RANGE,
+                    inputs: vec![FunctionInput {
+                        witness: x,
+                        num_bits: 8,
+                    }],
+                    outputs: Vec::new(),
+                }),
+            ],
+            public_inputs: PublicInputs(vec![x]),
+            return_values: PublicInputs::default(),
+        };
+
+        let recorder = Recorder::default();
+        let spans = recorder.spans.clone();
+        tracing::subscriber::with_default(recorder, || {
+            let mut witness = WitnessMap::from([(x, FieldElement::from(7_i128))]);
+            NativeSolver
+                .solve(&mut witness, circuit.opcodes.clone())
+                .unwrap();
+            compiler::compile(circuit, Language::PLONKCSat { width: 3 }, |_| false).unwrap();
+        });
+
+        let spans = spans.lock().unwrap();
+        for name in ["solve", "black_box", "compile", "fallback", "optimise"] {
+            assert!(spans.iter().any(|span| span == name), "no {name} span");
+        }
+    }
+}
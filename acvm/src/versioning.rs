@@ -0,0 +1,180 @@
+// Reading of circuits which were serialised by older versions of ACIR
+//
+// Every circuit written by `Circuit::write` starts with a version number. A circuit is read
+// with the reader for its version, and is then upgraded one version at a time to the current
+// in-memory representation. When the format changes, the reader for the previous version is
+// kept here along with a function which upgrades its circuits.
+//
+// Circuits written by the deprecated `Circuit::to_bytes` have no version number, and are
+// read with `read_legacy_circuit`.
+
+use std::io::Read;
+
+use acir::{
+    circuit::{Circuit, Opcode, PublicInputs, VERSION_NUMBER},
+    native_types::Witness,
+    serialisation::read_u32,
+};
+use flate2::bufread::DeflateDecoder;
+use thiserror::Error;
+
+#[derive(PartialEq, Eq, Debug, Error)]
+pub enum CircuitVersionError {
+    #[error("circuit was serialised with version {found}, but only versions up to {VERSION_NUMBER} can be read")]
+    UnsupportedVersion { found: u32 },
+    #[error("malformed circuit: {0}")]
+    Malformed(String),
+}
+
+fn malformed(err: impl std::fmt::Display) -> CircuitVersionError {
+    CircuitVersionError::Malformed(err.to_string())
+}
+
+/// Reads a circuit written by `Circuit::write` with any supported version,
+/// and returns it in the current representation
+pub fn read_versioned_circuit<R: Read>(mut reader: R) -> Result<Circuit, CircuitVersionError> {
+    let version = read_u32(&mut reader).map_err(malformed)?;
+    match version {
+        0 => read_v0(reader).map(upgrade_v0),
+        1 => read_v1(reader),
+        found => Err(CircuitVersionError::UnsupportedVersion { found }),
+    }
+}
+
+// Version 1 is the current version
+fn read_v1<R: Read>(reader: R) -> Result<Circuit, CircuitVersionError> {
+    let version = 1_u32.to_le_bytes();
+    Circuit::read(version.chain(reader)).map_err(malformed)
+}
+
+// Version 0 circuits do not have return values, which are written after the public inputs
+// in version 1
+struct CircuitV0 {
+    current_witness_index: u32,
+    opcodes: Vec<Opcode>,
+    public_inputs: PublicInputs,
+}
+
+fn read_v0<R: Read>(mut reader: R) -> Result<CircuitV0, CircuitVersionError> {
+    let current_witness_index = read_u32(&mut reader).map_err(malformed)?;
+    let num_public_inputs = read_u32(&mut reader).map_err(malformed)?;
+    let public_inputs = (0..num_public_inputs)
+        .map(|_| read_u32(&mut reader).map(Witness))
+        .collect::<std::io::Result<_>>()
+        .map_err(malformed)?;
+    let num_opcodes = read_u32(&mut reader).map_err(malformed)?;
+    let opcodes = (0..num_opcodes)
+        .map(|_| Opcode::read(&mut reader))
+        .collect::<std::io::Result<_>>()
+        .map_err(malformed)?;
+    Ok(CircuitV0 {
+        current_witness_index,
+        opcodes,
+        public_inputs: PublicInputs(public_inputs),
+    })
+}
+
+fn upgrade_v0(circuit: CircuitV0) -> Circuit {
+    Circuit {
+        current_witness_index: circuit.current_witness_index,
+        opcodes: circuit.opcodes,
+        public_inputs: circuit.public_inputs,
+        return_values: PublicInputs::default(),
+    }
+}
+
+/// Reads a circuit written by the deprecated `Circuit::to_bytes`, which is
+/// DEFLATE compressed MessagePack without a version number
+pub fn read_legacy_circuit(bytes: &[u8]) -> Result<Circuit, CircuitVersionError> {
+    let mut decompressed = Vec::new();
+    DeflateDecoder::new(bytes)
+        .read_to_end(&mut decompressed)
+        .map_err(malformed)?;
+    rmp_serde::from_slice(&decompressed).map_err(malformed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{
+        circuit::{directives::Directive, Opcode, PublicInputs},
+        native_types::{Expression, Witness},
+        serialisation::write_u32,
+    };
+
+    fn circuit() -> Circuit {
+        Circuit {
+            current_witness_index: 3,
+            opcodes: vec![
+                Opcode::Arithmetic(&Expression::from(&Witness(1)) - &Witness(2)),
+                Opcode::Directive(Directive::Invert {
+                    x: Witness(2),
+                    result: Witness(3),
+                }),
+            ],
+            public_inputs: PublicInputs(vec![Witness(1)]),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    #[test]
+    fn current_circuits_are_read() {
+        let circuit = Circuit {
+            return_values: PublicInputs(vec![Witness(3)]),
+            ..circuit()
+        };
+        let mut bytes = Vec::new();
+        circuit.write(&mut bytes).unwrap();
+        assert_eq!(read_versioned_circuit(&*bytes), Ok(circuit));
+
+        assert!(matches!(
+            read_versioned_circuit(&bytes[..bytes.len() - 1]),
+            Err(CircuitVersionError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn version_0_circuits_are_upgraded() {
+        let circuit = circuit();
+        let mut bytes = Vec::new();
+        write_u32(&mut bytes, 0).unwrap();
+        write_u32(&mut bytes, circuit.current_witness_index).unwrap();
+        write_u32(&mut bytes, 1).unwrap();
+        write_u32(&mut bytes, 1).unwrap();
+        write_u32(&mut bytes, circuit.opcodes.len() as u32).unwrap();
+        for opcode in &circuit.opcodes {
+            opcode.write(&mut bytes).unwrap();
+        }
+        assert_eq!(read_versioned_circuit(&*bytes), Ok(circuit));
+
+        assert!(matches!(
+            read_versioned_circuit(&bytes[..bytes.len() - 1]),
+            Err(CircuitVersionError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn future_versions_are_unsupported() {
+        let mut bytes = Vec::new();
+        write_u32(&mut bytes, VERSION_NUMBER + 1).unwrap();
+        assert_eq!(
+            read_versioned_circuit(&*bytes),
+            Err(CircuitVersionError::UnsupportedVersion {
+                found: VERSION_NUMBER + 1
+            })
+        );
+    }
+
+    #[test]
+    fn legacy_circuits_are_read() {
+        let circuit = circuit();
+        #[allow(deprecated)]
+        let bytes = circuit.to_bytes();
+        assert_eq!(read_legacy_circuit(&bytes), Ok(circuit));
+
+        assert!(matches!(
+            read_legacy_circuit(&[0x00, 0x01]),
+            Err(CircuitVersionError::Malformed(_))
+        ));
+    }
+}
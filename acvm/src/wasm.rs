@@ -0,0 +1,271 @@
+// A backend which is loaded from a WebAssembly module
+//
+// Backends distributed as WebAssembly run in a sandbox, so unlike native plugins they
+// cannot access the host's memory, filesystem or network. Modules are instantiated
+// without imports.
+//
+// The module speaks the protocol described in `external`, and must export:
+//
+// - `memory`, its linear memory
+// - `acvm_alloc(len: i32) -> i32`, which allocates `len` bytes for a request
+// - `acvm_request(ptr: i32, len: i32) -> i64`, which handles the request at `ptr`
+//   and returns the location of its response as `(ptr << 32) | len`
+//
+// It may also export `acvm_free(ptr: i32, len: i32)`, which is called with each request
+// and response once ACVM has finished with them.
+//
+// Each request may execute a limited amount of fuel, roughly one unit per instruction,
+// so that a module which never returns fails the request instead of hanging the host.
+// Its linear memory is also capped, so that growing it fails once the module would use
+// more than `DEFAULT_MAX_MEMORY` bytes, unless the transport is given another limit.
+// Responses must lie within the module's memory.
+
+use std::sync::Mutex;
+
+use wasmtime::{
+    Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+use crate::{
+    external::{ExternalBackend, Transport},
+    BackendError,
+};
+
+/// A backend loaded from a WebAssembly module
+pub type WasmBackend = ExternalBackend<WasmTransport>;
+
+/// Compiles and instantiates a WebAssembly backend, and requests its `info`
+pub fn load_backend(wasm: &[u8]) -> Result<WasmBackend, BackendError> {
+    ExternalBackend::with_transport(WasmTransport::new(wasm)?)
+}
+
+/// The fuel which a request may use unless the transport is given another limit
+pub const DEFAULT_FUEL: u64 = 1 << 40;
+
+/// The bytes of linear memory which a module may use unless the transport is given another limit
+pub const DEFAULT_MAX_MEMORY: usize = 256 << 20;
+
+fn memory_limits(max_memory: usize) -> StoreLimits {
+    StoreLimitsBuilder::new().memory_size(max_memory).build()
+}
+
+/// A transport which calls the exports of a WebAssembly module
+pub struct WasmTransport {
+    instance: Mutex<WasmInstance>,
+    fuel: u64,
+}
+
+struct WasmInstance {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    request: TypedFunc<(i32, i32), i64>,
+    free: Option<TypedFunc<(i32, i32), ()>>,
+}
+
+fn wasm_error(err: wasmtime::Error) -> BackendError {
+    BackendError::Other(format!("wasm backend failed: {err}"))
+}
+
+impl WasmTransport {
+    /// Compiles and instantiates a module, given in either the binary or text format
+    pub fn new(wasm: &[u8]) -> Result<Self, BackendError> {
+        let engine = Engine::new(Config::new().consume_fuel(true)).map_err(wasm_error)?;
+        let module = Module::new(&engine, wasm).map_err(wasm_error)?;
+        let mut store = Store::new(&engine, memory_limits(DEFAULT_MAX_MEMORY));
+        store.limiter(|limits| limits);
+        store.set_fuel(DEFAULT_FUEL).map_err(wasm_error)?;
+        let instance = Instance::new(&mut store, &module, &[]).map_err(wasm_error)?;
+
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| BackendError::Other("wasm backend does not export memory".to_owned()))?;
+        let alloc = instance
+            .get_typed_func(&mut store, "acvm_alloc")
+            .map_err(wasm_error)?;
+        let request = instance
+            .get_typed_func(&mut store, "acvm_request")
+            .map_err(wasm_error)?;
+        let free = instance.get_typed_func(&mut store, "acvm_free").ok();
+
+        Ok(WasmTransport {
+            instance: Mutex::new(WasmInstance {
+                store,
+                memory,
+                alloc,
+                request,
+                free,
+            }),
+            fuel: DEFAULT_FUEL,
+        })
+    }
+
+    /// Sets the fuel which each request may use before it fails
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    /// Sets the bytes of linear memory which the module may grow to. Memory which the module
+    /// already uses is kept, but it cannot grow further while it is over the limit.
+    pub fn with_max_memory(self, max_memory: usize) -> Self {
+        *self
+            .instance
+            .lock()
+            .expect("wasm instance lock is poisoned")
+            .store
+            .data_mut() = memory_limits(max_memory);
+        self
+    }
+}
+
+impl Transport for WasmTransport {
+    fn exchange(&self, request: &[u8]) -> Result<Vec<u8>, BackendError> {
+        let mut instance = self
+            .instance
+            .lock()
+            .expect("wasm instance lock is poisoned");
+        let WasmInstance {
+            store,
+            memory,
+            alloc,
+            request: handle_request,
+            free,
+        } = &mut *instance;
+        store.set_fuel(self.fuel).map_err(wasm_error)?;
+
+        let request_len = i32::try_from(request.len())
+            .map_err(|_| BackendError::Other("request is too large for wasm".to_owned()))?;
+        let request_ptr = alloc.call(&mut *store, request_len).map_err(wasm_error)?;
+        memory
+            .write(&mut *store, request_ptr as u32 as usize, request)
+            .map_err(|err| BackendError::Other(err.to_string()))?;
+
+        let location = handle_request
+            .call(&mut *store, (request_ptr, request_len))
+            .map_err(wasm_error)? as u64;
+        let (response_ptr, response_len) = ((location >> 32) as u32, location as u32);
+
+        // The length is chosen by the module, so it is checked before anything is allocated
+        let end = response_ptr as u64 + response_len as u64;
+        if end > memory.data_size(&*store) as u64 {
+            return Err(BackendError::Other(format!(
+                "wasm backend returned a response outside of its memory, ending at {end}"
+            )));
+        }
+        let mut response = vec![0; response_len as usize];
+        memory
+            .read(&*store, response_ptr as usize, &mut response)
+            .map_err(|err| BackendError::Other(err.to_string()))?;
+
+        if let Some(free) = free {
+            free.call(&mut *store, (request_ptr, request_len))
+                .map_err(wasm_error)?;
+            free.call(&mut *store, (response_ptr as i32, response_len as i32))
+                .map_err(wasm_error)?;
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{FieldModulus, Language, ProofSystemCompiler};
+    use acir::{circuit::Circuit, BlackBoxFunc};
+
+    // A backend which answers every request with its info
+    const MODULE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (data (i32.const 0) "{\"result\": {\"name\": \"wasm_prover\", \"width\": null, \"field\": \"bls12_381\", \"blackbox_functions\": [\"range\"]}}")
+          (func (export "acvm_alloc") (param i32) (result i32)
+            i32.const 1024)
+          (func (export "acvm_request") (param i32 i32) (result i64)
+            i64.const 105))
+    "#;
+
+    #[test]
+    fn requests_are_forwarded_to_the_module() {
+        let backend = load_backend(MODULE.as_bytes()).unwrap();
+        assert_eq!(backend.name(), "wasm_prover");
+        assert!(matches!(backend.np_language(), Language::R1CS));
+        assert_eq!(backend.np_field(), FieldModulus::bls12_381());
+        assert!(backend.blackbox_function_supported(&BlackBoxFunc::RANGE));
+
+        // The module's answer is not a proof
+        assert!(matches!(
+            backend.prove_with_meta(&Circuit::default(), &Default::default()),
+            Err(BackendError::SerializationError(_))
+        ));
+    }
+
+    #[test]
+    fn responses_outside_of_memory_are_rejected() {
+        // The response claims to be 4 GB long
+        let module = r#"
+            (module
+              (memory (export "memory") 1)
+              (func (export "acvm_alloc") (param i32) (result i32)
+                i32.const 1024)
+              (func (export "acvm_request") (param i32 i32) (result i64)
+                i64.const 0xffffffff))
+        "#;
+        let transport = WasmTransport::new(module.as_bytes()).unwrap();
+        assert!(matches!(
+            transport.exchange(b"{}"),
+            Err(BackendError::Other(_))
+        ));
+    }
+
+    #[test]
+    fn requests_run_out_of_fuel() {
+        let module = r#"
+            (module
+              (memory (export "memory") 1)
+              (func (export "acvm_alloc") (param i32) (result i32)
+                i32.const 1024)
+              (func (export "acvm_request") (param i32 i32) (result i64)
+                (loop (br 0))
+                i64.const 0))
+        "#;
+        let transport = WasmTransport::new(module.as_bytes())
+            .unwrap()
+            .with_fuel(10_000);
+        assert!(matches!(
+            transport.exchange(b"{}"),
+            Err(BackendError::Other(_))
+        ));
+    }
+
+    #[test]
+    fn memory_growth_is_limited() {
+        // The response is one byte long if the memory grew, and 4 GB long if it did not
+        let module = r#"
+            (module
+              (memory (export "memory") 1)
+              (func (export "acvm_alloc") (param i32) (result i32)
+                i32.const 1024)
+              (func (export "acvm_request") (param i32 i32) (result i64)
+                (i64.extend_i32_u (memory.grow (i32.const 64)))))
+        "#;
+        let transport = WasmTransport::new(module.as_bytes()).unwrap();
+        assert_eq!(transport.exchange(b"{}").unwrap(), vec![0]);
+
+        let transport = transport.with_max_memory(1 << 20);
+        assert!(matches!(
+            transport.exchange(b"{}"),
+            Err(BackendError::Other(_))
+        ));
+
+        // Modules which start with more memory than the limit are not instantiated
+        let result = WasmTransport::new(br#"(module (memory (export "memory") 8192))"#);
+        assert!(matches!(result, Err(BackendError::Other(_))));
+    }
+
+    #[test]
+    fn modules_must_export_the_protocol() {
+        let result = WasmTransport::new(br#"(module (memory (export "memory") 1))"#);
+        assert!(matches!(result, Err(BackendError::Other(_))));
+    }
+}
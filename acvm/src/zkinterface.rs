@@ -0,0 +1,313 @@
+// Export of circuits to zkInterface
+//
+// zkInterface is a FlatBuffers based interchange format for constraint systems. A circuit is
+// described by a stream of size-prefixed messages, each a `Root` table holding one of:
+//
+// - `CircuitHeader`, which lists the instance (public) variables and the first free variable id
+// - `ConstraintSystem`, which lists R1CS constraints as three sparse linear combinations
+// - `Witness`, which assigns values to the variables which are not instance variables
+//
+// Variable ids are the indices of the variables in `compiler::R1cs`, so variable 0 is the
+// constant one and the public inputs are the variables from 1. Values are little-endian.
+//
+// The tables are built with the FlatBuffers builder directly, following the schema at
+// https://github.com/QED-it/zkinterface/blob/master/zkinterface.fbs
+
+use std::io::Write;
+
+use acir::{circuit::Circuit, FieldElement};
+use flatbuffers::{FlatBufferBuilder, UnionWIPOffset, WIPOffset};
+use num_bigint::BigUint;
+use thiserror::Error;
+
+use crate::{
+    compiler::{to_r1cs, CompileError, R1cs, SparseRow},
+    OpcodeResolutionError, WitnessMap,
+};
+
+const FILE_IDENTIFIER: &str = "zkif";
+
+// The tags of the `Message` union
+const MESSAGE_CIRCUIT_HEADER: u8 = 1;
+const MESSAGE_CONSTRAINT_SYSTEM: u8 = 2;
+const MESSAGE_WITNESS: u8 = 3;
+
+// The vtable offset of the field in each slot of a table
+const fn slot(index: u16) -> u16 {
+    4 + 2 * index
+}
+
+#[derive(Debug, Error)]
+pub enum ZkInterfaceError {
+    #[error(transparent)]
+    Compile(#[from] CompileError),
+    #[error(transparent)]
+    Witness(#[from] OpcodeResolutionError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Writes the circuit header and constraint system messages of a circuit, which must
+/// have been lowered as required by `compiler::to_r1cs`
+pub fn write_circuit<W: Write>(circuit: &Circuit, mut writer: W) -> Result<(), ZkInterfaceError> {
+    let r1cs = to_r1cs(circuit)?;
+    writer.write_all(&circuit_header(&r1cs, None))?;
+    writer.write_all(&constraint_system(&r1cs))?;
+    Ok(())
+}
+
+/// Writes the circuit header and witness messages for a solved witness map of a circuit.
+///
+/// The header assigns the values of the public inputs, and the witness assigns the
+/// values of every other variable.
+pub fn write_witness<W: Write>(
+    circuit: &Circuit,
+    witness_values: &WitnessMap,
+    mut writer: W,
+) -> Result<(), ZkInterfaceError> {
+    let r1cs = to_r1cs(circuit)?;
+    let assignment = r1cs.assignment(witness_values)?;
+    writer.write_all(&circuit_header(&r1cs, Some(&assignment)))?;
+    writer.write_all(&witness(&r1cs, &assignment))?;
+    Ok(())
+}
+
+fn to_le_bytes(value: &FieldElement) -> Vec<u8> {
+    let mut bytes = value.to_be_bytes();
+    bytes.reverse();
+    bytes
+}
+
+// Builds a `Variables` table, with values only if they are given
+fn variables<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    ids: &[u64],
+    values: Option<&[FieldElement]>,
+) -> WIPOffset<flatbuffers::TableFinishedWIPOffset> {
+    let ids = builder.create_vector(ids);
+    let values = values.map(|values| {
+        builder.create_vector(&values.iter().flat_map(to_le_bytes).collect::<Vec<_>>())
+    });
+
+    let table = builder.start_table();
+    builder.push_slot_always(slot(0), ids);
+    if let Some(values) = values {
+        builder.push_slot_always(slot(1), values);
+    }
+    builder.end_table(table)
+}
+
+fn row_variables<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    row: &SparseRow,
+) -> WIPOffset<flatbuffers::TableFinishedWIPOffset> {
+    let ids: Vec<u64> = row.iter().map(|(var, _)| *var as u64).collect();
+    let values: Vec<FieldElement> = row.iter().map(|(_, coeff)| *coeff).collect();
+    variables(builder, &ids, Some(&values))
+}
+
+// Wraps a message in a `Root` table and returns the size-prefixed buffer
+fn finish(
+    mut builder: FlatBufferBuilder,
+    message_type: u8,
+    message: WIPOffset<UnionWIPOffset>,
+) -> Vec<u8> {
+    let root = builder.start_table();
+    builder.push_slot_always(slot(1), message);
+    builder.push_slot_always(slot(0), message_type);
+    let root = builder.end_table(root);
+    builder.finish_size_prefixed(root, Some(FILE_IDENTIFIER));
+    builder.finished_data().to_vec()
+}
+
+fn circuit_header(r1cs: &R1cs, assignment: Option<&[FieldElement]>) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::new();
+
+    let instance_ids: Vec<u64> = (1..=r1cs.num_public as u64).collect();
+    let instance_values = assignment.map(|assignment| &assignment[1..=r1cs.num_public]);
+    let instance_variables = variables(&mut builder, &instance_ids, instance_values);
+
+    let mut field_maximum = (FieldElement::modulus() - BigUint::from(1_u32)).to_bytes_le();
+    field_maximum.resize(FieldElement::max_num_bytes() as usize, 0);
+    let field_maximum = builder.create_vector(&field_maximum);
+
+    let header = builder.start_table();
+    builder.push_slot_always(slot(0), instance_variables);
+    builder.push_slot_always(slot(1), r1cs.num_vars as u64);
+    builder.push_slot_always(slot(2), field_maximum);
+    let header = builder.end_table(header);
+
+    finish(builder, MESSAGE_CIRCUIT_HEADER, header.as_union_value())
+}
+
+fn constraint_system(r1cs: &R1cs) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::new();
+
+    let mut constraints = Vec::with_capacity(r1cs.num_constraints());
+    for i in 0..r1cs.num_constraints() {
+        let a = row_variables(&mut builder, &r1cs.a[i]);
+        let b = row_variables(&mut builder, &r1cs.b[i]);
+        let c = row_variables(&mut builder, &r1cs.c[i]);
+
+        let constraint = builder.start_table();
+        builder.push_slot_always(slot(0), a);
+        builder.push_slot_always(slot(1), b);
+        builder.push_slot_always(slot(2), c);
+        constraints.push(builder.end_table(constraint));
+    }
+    let constraints = builder.create_vector(&constraints);
+
+    // The constraint type defaults to R1CS
+    let constraint_system = builder.start_table();
+    builder.push_slot_always(slot(0), constraints);
+    let constraint_system = builder.end_table(constraint_system);
+
+    finish(
+        builder,
+        MESSAGE_CONSTRAINT_SYSTEM,
+        constraint_system.as_union_value(),
+    )
+}
+
+fn witness(r1cs: &R1cs, assignment: &[FieldElement]) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::new();
+
+    let first_private = r1cs.num_public + 1;
+    let ids: Vec<u64> = (first_private as u64..r1cs.num_vars as u64).collect();
+    let assigned_variables = variables(&mut builder, &ids, Some(&assignment[first_private..]));
+
+    let witness = builder.start_table();
+    builder.push_slot_always(slot(0), assigned_variables);
+    let witness = builder.end_table(witness);
+
+    finish(builder, MESSAGE_WITNESS, witness.as_union_value())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acir::{
+        circuit::{Opcode, PublicInputs},
+        native_types::{Expression, Witness},
+    };
+    use flatbuffers::{ForwardsUOffset, Table, Vector};
+
+    // x * y - o = 0, with o public
+    fn circuit() -> Circuit {
+        let mut expr = Expression::default();
+        expr.term_multiplication(FieldElement::one(), Witness(1), Witness(2));
+        expr.term_addition(-FieldElement::one(), Witness(3));
+        Circuit {
+            current_witness_index: 3,
+            opcodes: vec![Opcode::Arithmetic(expr)],
+            public_inputs: PublicInputs(vec![Witness(3)]),
+            return_values: PublicInputs::default(),
+        }
+    }
+
+    // Splits a stream into its messages, returning the type and table of each
+    fn messages(mut bytes: &[u8]) -> Vec<(u8, Table<'_>)> {
+        let mut messages = Vec::new();
+        while !bytes.is_empty() {
+            let size = u32::from_le_bytes(bytes[..4].try_into().unwrap()) as usize;
+            let (message, rest) = bytes.split_at(4 + size);
+            assert!(flatbuffers::buffer_has_identifier(
+                message,
+                FILE_IDENTIFIER,
+                true
+            ));
+            // Safety: the buffers are built by the functions under test
+            unsafe {
+                let root = flatbuffers::size_prefixed_root_unchecked::<Table>(message);
+                let message_type = root.get::<u8>(slot(0), Some(0)).unwrap();
+                let message = root.get::<ForwardsUOffset<Table>>(slot(1), None).unwrap();
+                messages.push((message_type, message));
+            }
+            bytes = rest;
+        }
+        messages
+    }
+
+    fn ids<'a>(table: &Table<'a>, slot_index: u16) -> Vec<u64> {
+        // Safety: the buffers are built by the functions under test
+        unsafe {
+            let variables = table
+                .get::<ForwardsUOffset<Table>>(slot(slot_index), None)
+                .unwrap();
+            variables
+                .get::<ForwardsUOffset<Vector<u64>>>(slot(0), None)
+                .unwrap()
+                .iter()
+                .collect()
+        }
+    }
+
+    #[test]
+    fn circuit_messages() {
+        let mut bytes = Vec::new();
+        write_circuit(&circuit(), &mut bytes).unwrap();
+
+        let messages = messages(&bytes);
+        let types: Vec<u8> = messages
+            .iter()
+            .map(|(message_type, _)| *message_type)
+            .collect();
+        assert_eq!(
+            types,
+            vec![MESSAGE_CIRCUIT_HEADER, MESSAGE_CONSTRAINT_SYSTEM]
+        );
+
+        let header = &messages[0].1;
+        assert_eq!(ids(header, 0), vec![1]);
+        // Safety: the buffers are built by the functions under test
+        let free_variable_id = unsafe { header.get::<u64>(slot(1), None) };
+        assert_eq!(free_variable_id, Some(4));
+
+        // Safety: the buffers are built by the functions under test
+        let constraints = unsafe {
+            messages[1]
+                .1
+                .get::<ForwardsUOffset<Vector<ForwardsUOffset<Table>>>>(slot(0), None)
+                .unwrap()
+        };
+        assert_eq!(constraints.len(), 1);
+        // x * y = o, where o is variable 1 and x and y are variables 2 and 3
+        let constraint = constraints.get(0);
+        assert_eq!(ids(&constraint, 0), vec![2]);
+        assert_eq!(ids(&constraint, 1), vec![3]);
+        assert_eq!(ids(&constraint, 2), vec![1]);
+    }
+
+    #[test]
+    fn witness_messages() {
+        let witness_values = WitnessMap::from([
+            (Witness(1), FieldElement::from(2_i128)),
+            (Witness(2), FieldElement::from(3_i128)),
+            (Witness(3), FieldElement::from(6_i128)),
+        ]);
+        let mut bytes = Vec::new();
+        write_witness(&circuit(), &witness_values, &mut bytes).unwrap();
+
+        let messages = messages(&bytes);
+        assert_eq!(messages[1].0, MESSAGE_WITNESS);
+        assert_eq!(ids(&messages[1].1, 0), vec![2, 3]);
+
+        // Safety: the buffers are built by the functions under test
+        let values = unsafe {
+            messages[0]
+                .1
+                .get::<ForwardsUOffset<Table>>(slot(0), None)
+                .unwrap()
+                .get::<ForwardsUOffset<Vector<u8>>>(slot(1), None)
+                .unwrap()
+                .bytes()
+                .to_vec()
+        };
+        assert_eq!(values, to_le_bytes(&FieldElement::from(6_i128)));
+
+        assert!(matches!(
+            write_witness(&circuit(), &WitnessMap::new(), Vec::new()),
+            Err(ZkInterfaceError::Witness(_))
+        ));
+    }
+}
@@ -3,15 +3,16 @@ use acir::{
     circuit::{directives::Directive, Opcode},
     native_types::{Expression, Witness},
 };
-use acir_field::FieldElement;
+use acir_field::AcirField;
+use alloc::vec::Vec;
 
 // Perform bit decomposition on the provided expression
 #[deprecated(note = "use bit_decomposition function instead")]
-pub fn split(
-    gate: Expression,
+pub fn split<F: AcirField>(
+    gate: Expression<F>,
     bit_size: u32,
     num_witness: u32,
-    new_gates: &mut Vec<Opcode>,
+    new_gates: &mut Vec<Opcode<F>>,
 ) -> Vec<Witness> {
     let (extra_gates, bits, _) = bit_decomposition(gate, bit_size, num_witness);
     new_gates.extend(extra_gates);
@@ -23,11 +24,11 @@ pub fn split(
 // TODO:Ideally, we return the updated witness counter, or we require the input
 // TODO to be a VariableStore. We are not doing this because we want migration to
 // TODO be less painful
-pub(crate) fn bit_decomposition(
-    gate: Expression,
+pub(crate) fn bit_decomposition<F: AcirField>(
+    gate: Expression<F>,
     bit_size: u32,
     mut num_witness: u32,
-) -> (Vec<Opcode>, Vec<Witness>, u32) {
+) -> (Vec<Opcode<F>>, Vec<Witness>, u32) {
     let mut new_gates = Vec::new();
     let mut variables = VariableStore::new(&mut num_witness);
 
@@ -48,13 +49,13 @@ pub(crate) fn bit_decomposition(
     // of the input and each bit is actually a bit
     let mut binary_exprs = Vec::new();
     let mut bit_decomp_constraint = gate;
-    let mut two_pow = FieldElement::one();
-    let two = FieldElement::from(2_i128);
+    let mut two_pow = F::one();
+    let two = F::from(2_i128);
     for &bit in &bit_vector {
         // Bit constraint to ensure each bit is a zero or one; bit^2 - bit = 0
         let mut expr = Expression::default();
-        expr.term_multiplication(FieldElement::one(), bit, bit);
-        expr.term_addition(-FieldElement::one(), bit);
+        expr.term_multiplication(F::one(), bit, bit);
+        expr.term_addition(-F::one(), bit);
         binary_exprs.push(Opcode::Arithmetic(expr));
 
         // Constraint to ensure that the bits are constrained to be a bit decomposition
@@ -72,18 +73,22 @@ pub(crate) fn bit_decomposition(
 }
 
 // Range constraint
-pub fn range(gate: Expression, bit_size: u32, num_witness: u32) -> (u32, Vec<Opcode>) {
+pub fn range<F: AcirField>(
+    gate: Expression<F>,
+    bit_size: u32,
+    num_witness: u32,
+) -> (u32, Vec<Opcode<F>>) {
     let (new_gates, _, updated_witness_counter) = bit_decomposition(gate, bit_size, num_witness);
     (updated_witness_counter, new_gates)
 }
 
-pub fn and(
-    a: Expression,
-    b: Expression,
+pub fn and<F: AcirField>(
+    a: Expression<F>,
+    b: Expression<F>,
     result: Witness,
     bit_size: u32,
     num_witness: u32,
-) -> (u32, Vec<Opcode>) {
+) -> (u32, Vec<Opcode<F>>) {
     // Decompose the operands into bits
     //
     let (extra_gates_a, a_bits, updated_witness_counter) =
@@ -95,8 +100,8 @@ pub fn and(
     assert_eq!(a_bits.len(), b_bits.len());
     assert_eq!(a_bits.len(), bit_size as usize);
 
-    let mut two_pow = FieldElement::one();
-    let two = FieldElement::from(2_i128);
+    let mut two_pow = F::one();
+    let two = F::from(2_i128);
 
     // Build an expression that Multiplies each bit element-wise
     // This gives the same truth table as the AND operation
@@ -107,7 +112,7 @@ pub fn and(
         and_expr.term_multiplication(two_pow, a_bit, b_bit);
         two_pow = two * two_pow;
     }
-    and_expr.term_addition(-FieldElement::one(), result);
+    and_expr.term_addition(-F::one(), result);
 
     and_expr.sort();
 
@@ -119,13 +124,13 @@ pub fn and(
     (updated_witness_counter, new_gates)
 }
 
-pub fn xor(
-    a: Expression,
-    b: Expression,
+pub fn xor<F: AcirField>(
+    a: Expression<F>,
+    b: Expression<F>,
     result: Witness,
     bit_size: u32,
     num_witness: u32,
-) -> (u32, Vec<Opcode>) {
+) -> (u32, Vec<Opcode<F>>) {
     // Decompose the operands into bits
     //
     let (extra_gates_a, a_bits, updated_witness_counter) =
@@ -136,8 +141,8 @@ pub fn xor(
     assert_eq!(a_bits.len(), b_bits.len());
     assert_eq!(a_bits.len(), bit_size as usize);
 
-    let mut two_pow = FieldElement::one();
-    let two = FieldElement::from(2_i128);
+    let mut two_pow = F::one();
+    let two = F::from(2_i128);
 
     // Build an xor expression
     // TODO: check this is the correct arithmetisation
@@ -148,7 +153,7 @@ pub fn xor(
         two_pow = two * two_pow;
         xor_expr.term_multiplication(-two_pow, a_bit, b_bit);
     }
-    xor_expr.term_addition(-FieldElement::one(), result);
+    xor_expr.term_addition(-F::one(), result);
 
     xor_expr.sort();
     let mut new_gates = Vec::new();